@@ -162,4 +162,51 @@ impl SharedDataFrame {
         let mut storage = SHARED_STORAGE.lock().unwrap();
         storage.remove(&self.config.name);
     }
-}
\ No newline at end of file
+}
+
+/// Reads several writers' arenas as one stream, tagging each frame with its source
+pub struct MergeReader {
+    sources: Vec<(String, SharedDataFrame)>,
+    next_source: usize,
+}
+
+impl MergeReader {
+    pub fn new<S: AsRef<str>>(names: &[S]) -> Result<Self> {
+        let sources = names
+            .iter()
+            .map(|name| {
+                let config = SharedMemoryConfig::new(name.as_ref());
+                Ok((name.as_ref().to_string(), SharedDataFrame::create_reader(config)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { sources, next_source: 0 })
+    }
+
+    /// Next frame from any source, with a `source` column appended
+    pub fn read(&mut self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
+        let start = Instant::now();
+        let timeout_duration = timeout_ms.map(|ms| Duration::from_millis(ms as u64));
+
+        loop {
+            for _ in 0..self.sources.len() {
+                let (name, reader) = &self.sources[self.next_source];
+                self.next_source = (self.next_source + 1) % self.sources.len();
+
+                if let Ok(Some(mut df)) = reader.read(Some(0)) {
+                    let tag = Series::new("source".into(), vec![name.as_str(); df.height()]);
+                    df.with_column(tag)?;
+                    return Ok(Some(df));
+                }
+            }
+
+            if let Some(timeout) = timeout_duration {
+                if start.elapsed() > timeout {
+                    return Err(QADataSwapError::Timeout);
+                }
+            }
+
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
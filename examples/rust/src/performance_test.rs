@@ -1,5 +1,5 @@
 mod lib;
-use lib::{SharedDataFrame, SharedMemoryConfig, Result};
+use lib::{MergeReader, SharedDataFrame, SharedMemoryConfig, Result};
 use polars::prelude::*;
 use std::time::{Duration, Instant};
 use std::thread;
@@ -186,17 +186,16 @@ fn benchmark_concurrent() -> Result<()> {
         let handle = thread::spawn(move || -> Result<()> {
             thread::sleep(Duration::from_millis(100)); // Let writers start
 
-            for w in 0..num_writers {
-                let writer_name = format!("{}_{}", shared_name, w);
-                let config = SharedMemoryConfig::new(&writer_name);
-                let arena = SharedDataFrame::create_reader(config)?;
-
-                for _m in 0..messages_per_writer {
-                    if let Ok(Some(_)) = arena.read(Some(1000)) { // 1 second
-                        total_reads_clone.fetch_add(1, Ordering::Relaxed);
-                    } else {
-                        break; // Timeout or error
-                    }
+            let writer_names: Vec<String> = (0..num_writers)
+                .map(|w| format!("{}_{}", shared_name, w))
+                .collect();
+            let mut merged = MergeReader::new(&writer_names)?;
+
+            for _m in 0..num_writers * messages_per_writer {
+                if let Ok(Some(_)) = merged.read(Some(1000)) { // 1 second
+                    total_reads_clone.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    break; // Timeout or error
                 }
             }
 
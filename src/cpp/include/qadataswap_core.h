@@ -22,7 +22,43 @@ namespace qadataswap {
 
 constexpr size_t CACHE_LINE_SIZE = 64;
 constexpr uint32_t MAGIC_NUMBER = 0x51444153; // 'QDAS'
-constexpr uint32_t VERSION = 1;
+constexpr uint32_t VERSION = 6;
+constexpr size_t CANARY_SIZE = sizeof(uint64_t);
+
+// How a writer treats a segment that already exists under the same name
+enum class OpenMode : int32_t {
+    Create = 0,        // create fresh, replacing only stale segments (no live writer)
+    OpenOrAttach = 1,  // reattach to an existing segment, keeping sequences and readers
+    FailIfExists = 2,  // refuse if any segment exists under this name
+    Truncate = 3,      // unconditionally unlink and recreate
+};
+
+constexpr uint64_t SLOT_BEING_WRITTEN = UINT64_MAX;
+constexpr size_t MAX_CONSUMER_GROUPS = 16;
+constexpr size_t GROUP_NAME_SIZE = 32;
+constexpr size_t AUTH_NONCE_SIZE = 16;
+constexpr size_t AUTH_TAG_SIZE = 32;
+constexpr size_t MAX_META_ENTRIES = 32;
+constexpr size_t META_KEY_SIZE = 32;     // including the terminating NUL
+constexpr size_t META_VALUE_SIZE = 224;  // including the terminating NUL
+constexpr size_t MAX_READERS = 64;
+constexpr size_t BOOT_ID_SIZE = 40;  // /proc/sys/kernel/random/boot_id, NUL-terminated
+
+// Reader seek origins
+enum class SeekOrigin : int32_t {
+    Sequence = 0,  // resume at an explicit sequence number
+    Latest = 1,    // skip everything retained, wait for the next frame
+    Earliest = 2,  // oldest frame still held in the ring
+};
+
+// Status codes shared with the FFI layer
+constexpr int QADS_OK = 0;
+constexpr int QADS_TIMEOUT = 1;
+constexpr int QADS_ALREADY_EXISTS = 2;
+constexpr int QADS_BUSY = 3;
+constexpr int QADS_FAULT = 4;  // a guarded read hit a memory fault; the frame is skipped
+constexpr int QADS_OVERRUN = 5;  // a frame wrote past its slot and clobbered the canary
+constexpr int QADS_ERROR = -1;
 
 #pragma pack(push, 1)
 struct alignas(CACHE_LINE_SIZE) SharedMemoryHeader {
@@ -39,6 +75,10 @@ struct alignas(CACHE_LINE_SIZE) SharedMemoryHeader {
     size_t buffer_count;
     size_t buffer_size;
     size_t buffers_offset;
+    size_t max_buffer_count;                  // slot states reserved for re-slotting
+    size_t slot_stride;                       // distance between slot starts, guard page included
+    int32_t slot_guards;                      // GUARD_PAGES | CANARIES, fixed at creation
+    std::atomic<uint64_t> geometry_epoch{0};  // bumped whenever the ring is re-slotted
 
     // Synchronization
     std::atomic<uint64_t> write_sequence{0};
@@ -46,6 +86,73 @@ struct alignas(CACHE_LINE_SIZE) SharedMemoryHeader {
     std::atomic<bool> writer_active{false};
     std::atomic<int32_t> reader_count{0};
 
+    // Ownership
+    std::atomic<int32_t> writer_pid{0};
+    std::atomic<uint64_t> writer_start_time{0};  // of writer_pid, so a reused pid is not taken for it
+    uint64_t created_at_us;
+    uint64_t generation;  // increases every time a segment is created under this name
+    char boot_id[BOOT_ID_SIZE];  // kernel boot the pids above belong to
+
+    // Attach authorization: the writer publishes HMAC(token, nonce), readers recompute it
+    std::atomic<int32_t> auth_required{0};
+    uint8_t auth_nonce[AUTH_NONCE_SIZE];
+    uint8_t auth_tag[AUTH_TAG_SIZE];
+
+    // At-least-once delivery: buffers are recycled only once acknowledged
+    std::atomic<bool> ack_mode{false};
+    std::atomic<uint64_t> ack_sequence{0};
+
+    // Frames older than this are no longer replayable (0 = bounded by ring size only)
+    std::atomic<uint64_t> retention_us{0};
+
+    // Sizing history for memory reports
+    std::atomic<uint64_t> high_water_bytes{0};    // largest frame ever written
+    std::atomic<uint64_t> high_water_buffers{0};  // most slots ever occupied at once
+
+    // Highest sequence visible to readers (lags write_sequence while a batch is open)
+    std::atomic<uint64_t> publish_sequence{0};
+
+    // Futex bumped on every publish for readers waiting outside the semaphores
+    // (io_uring futex waits); the writer only issues FUTEX_WAKE while someone waits
+    std::atomic<uint32_t> publish_futex{0};
+    std::atomic<int32_t> futex_waiters{0};
+
+    // Consumer groups: each group sees every frame once, shared among its members
+    std::atomic<bool> groups_enabled{false};
+    std::atomic<int32_t> groups_lock{0};
+
+    struct ConsumerGroupState {
+        char name[GROUP_NAME_SIZE];
+        std::atomic<int32_t> in_use{0};
+        std::atomic<int32_t> members{0};
+        std::atomic<uint64_t> cursor{0};
+    };
+
+    ConsumerGroupState groups[MAX_CONSUMER_GROUPS];
+
+    // Session metadata: small string key/values any attached process may set
+    std::atomic<int32_t> meta_lock{0};
+
+    struct MetaEntry {
+        char key[META_KEY_SIZE];
+        char value[META_VALUE_SIZE];
+        std::atomic<int32_t> in_use{0};
+    };
+
+    MetaEntry meta[MAX_META_ENTRIES];
+
+    // Reader registry: attached readers and their cursors, for attach limits and listings
+    std::atomic<int32_t> max_readers{0};  // 0 = unlimited
+    std::atomic<int32_t> readers_lock{0};
+
+    struct ReaderEntry {
+        std::atomic<int32_t> pid{0};  // 0 while free
+        std::atomic<uint64_t> attached_at_us{0};
+        std::atomic<uint64_t> cursor{0};  // next sequence this reader expects
+    };
+
+    ReaderEntry readers[MAX_READERS];
+
     // POSIX named semaphores
     char write_sem_name[64];
     char read_sem_name[64];
@@ -54,12 +161,92 @@ struct alignas(CACHE_LINE_SIZE) SharedMemoryHeader {
     struct BufferState {
         std::atomic<uint64_t> data_size{0};
         std::atomic<bool> ready{false};
-        std::atomic<uint64_t> timestamp{0};
+        std::atomic<uint64_t> timestamp{0};  // wall clock, microseconds since epoch
+        std::atomic<uint64_t> sequence{SLOT_BEING_WRITTEN};  // frame held by this slot
     };
 
     BufferState buffer_states[];
 };
 
+// Snapshot of slot usage, laid out for the FFI layer
+struct MemoryReport {
+    uint64_t total_bytes;
+    uint64_t header_bytes;
+    uint64_t buffer_count;
+    uint64_t buffer_bytes;
+    uint64_t used_buffers;
+    uint64_t free_buffers;
+    uint64_t high_water_bytes;
+    uint64_t high_water_buffers;
+    uint64_t largest_writable_bytes;
+};
+
+// Ownership snapshot of a segment, read by name without attaching
+struct SegmentStatus {
+    uint64_t total_bytes;
+    uint64_t created_at_us;
+    uint64_t generation;
+    int32_t writer_pid;
+    int32_t writer_alive;  // 1 if the writer is active and its process still exists
+    int32_t reader_count;
+    int32_t max_readers;   // 0 = unlimited
+};
+
+// Control block snapshot for debug dumps, laid out for the FFI layer
+struct HeaderDump {
+    uint32_t magic;
+    uint32_t version;
+    uint64_t total_size;
+    uint64_t header_size;
+    uint64_t buffer_count;
+    uint64_t buffer_size;
+    uint64_t max_buffer_count;
+    uint64_t geometry_epoch;
+    uint64_t write_sequence;
+    uint64_t publish_sequence;
+    uint64_t read_sequence;
+    uint64_t ack_sequence;
+    uint64_t retention_us;
+    uint64_t created_at_us;
+    uint64_t generation;
+    uint64_t last_read_sequence;  // this endpoint's, valid if last_read_valid
+    int32_t writer_active;
+    int32_t writer_pid;
+    int32_t reader_count;
+    int32_t ack_mode;
+    int32_t auth_required;
+    int32_t groups_enabled;
+    int32_t groups_lock;      // spinlocks: 1 while held
+    int32_t meta_lock;
+    int32_t futex_waiters;
+    int32_t write_sem_value;  // -1 if the semaphore is not open
+    int32_t read_sem_value;
+    int32_t last_read_valid;
+    int32_t max_readers;
+};
+
+struct SlotDump {
+    uint64_t sequence;  // SLOT_BEING_WRITTEN while empty or being filled
+    uint64_t data_size;
+    uint64_t timestamp;
+    int32_t ready;
+    int32_t reserved;
+};
+
+struct GroupDump {
+    char name[GROUP_NAME_SIZE];
+    uint64_t cursor;
+    int32_t members;
+    int32_t reserved;
+};
+
+struct ReaderDump {
+    uint64_t cursor;
+    uint64_t attached_at_us;
+    int32_t pid;
+    int32_t alive;  // 1 if the reader's process still exists
+};
+
 struct BufferDescriptor {
     size_t offset;
     size_t size;
@@ -76,14 +263,97 @@ public:
 
     // Writer interface
     bool CreateWriter();
+    int CreateWriter(OpenMode mode);
     arrow::Status WriteRecordBatch(const std::shared_ptr<arrow::RecordBatch>& batch);
     arrow::Status WriteTable(const std::shared_ptr<arrow::Table>& table);
 
     // Reader interface
     bool AttachReader();
+    int TryAttachReader();  // QADS_BUSY while max_readers live readers are attached
     arrow::Result<std::shared_ptr<arrow::RecordBatch>> ReadRecordBatch(int timeout_ms = -1);
     arrow::Result<std::shared_ptr<arrow::Table>> ReadTable(int timeout_ms = -1);
 
+    // Raw frame interface (used by the FFI layer)
+    int WriteBytes(const uint8_t* data, size_t size);
+    int ReadBytes(uint8_t* data, size_t max_size, size_t* actual_size, int timeout_ms = -1);
+    bool LastReadSequence(uint64_t* sequence) const;
+    bool HasData();  // a ReadBytes call would not block right now
+    int PeekSize(size_t* size, int timeout_ms = -1);  // size of the frame ReadBytes returns next
+
+    // In-place writes: fill the next slot directly, then publish or release it
+    uint8_t* AcquireWriteBuffer(size_t* capacity);
+    int PublishAcquired(size_t size);
+    int ReleaseAcquired();
+
+    // Adaptive sizing: reserve slot states up front, re-slot the ring while quiet
+    bool ReserveSlots(size_t max_buffer_count);
+    int Reslot(size_t buffer_count);
+
+    // Batch publish: staged frames become visible together on commit
+    int BeginBatch();
+    int CommitBatch();
+    int AbortBatch();
+
+    // Replay: the last buffer_count frames stay addressable by sequence
+    int Seek(SeekOrigin origin, uint64_t sequence);
+    void RetainedRange(uint64_t* first, uint64_t* next) const;
+    void SetRetention(uint64_t retention_us);
+    int SeekTime(uint64_t timestamp_us);
+
+    // Consumer groups
+    void EnableConsumerGroups();
+    int JoinGroup(const std::string& group);
+
+    // Reader registry
+    void SetMaxReaders(int32_t max_readers);  // 0 lifts the limit
+
+    // Memory protection, set before creating or attaching: readers map the data
+    // region read-only, the writer write-protects published slots until it reuses them
+    static constexpr int PROTECT_MEMORY = 1;
+    static constexpr int GUARD_READS = 2;  // faults while copying a frame out become QADS_FAULT
+    void SetProtection(int flags);
+
+    // Overrun detection, set before creating; readers follow the writer's choice.
+    // Guard pages end every slot in an inaccessible page, so writing past it faults
+    // at the culprit; a canary word after each slot's capacity is checked when the
+    // frame is published and again when it is consumed (QADS_OVERRUN).
+    static constexpr int GUARD_PAGES = 1;
+    static constexpr int CANARIES = 2;
+    void SetSlotGuards(int flags);
+
+    // madvise() hints applied to the whole mapping whenever it is mapped
+    static constexpr int ADVISE_WILLNEED = 1;
+    static constexpr int ADVISE_SEQUENTIAL = 2;
+    static constexpr int ADVISE_RANDOM = 4;
+    static constexpr int ADVISE_HUGEPAGE = 8;
+    static constexpr int ADVISE_NOHUGEPAGE = 16;
+    static constexpr int ADVISE_DONTDUMP = 32;  // leave the arena out of core dumps
+    void SetAdvice(int flags);
+
+    // Persistent backing, set before creating or attaching: map the arena from a
+    // devdax device (/dev/daxX.Y) or a file on a DAX filesystem instead of /dev/shm.
+    // Frames and cursors are flushed to the persistence domain before they become
+    // visible, and the segment outlives the writer, so a writer reopening it with
+    // OpenMode::OpenOrAttach picks the ring up where it stopped, even after a reboot.
+    int SetBackingPath(const std::string& path);  // QADS_ERROR unless a devdax device or DAX file
+
+    // Futex wakeups: readers wait on *word while *waiters is raised
+    bool PublishFutex(std::atomic<uint32_t>** word, std::atomic<int32_t>** waiters);
+
+    // Attach authorization (the HMAC itself is computed by the caller)
+    void SetAuth(const uint8_t* nonce, const uint8_t* tag);  // nullptr clears it
+    bool GetAuth(uint8_t* nonce, uint8_t* tag) const;       // false if no token is set
+
+    // Session metadata side-channel
+    int PutMeta(const std::string& key, const char* value);  // nullptr removes the key
+    int GetMeta(const std::string& key, char* value, size_t max_size) const;  // 1 found, 0 missing
+    int MetaKeyAt(size_t index, char* key) const;  // 1 if entry `index` is in use
+
+    // Acknowledgement
+    void SetAckMode(bool enabled);
+    int Ack(uint64_t sequence);
+    uint64_t Unacked() const;
+
     // Advanced features
     arrow::Result<std::shared_ptr<arrow::RecordBatch>> ReadRecordBatchNoWait();
     arrow::Status WaitForData(int timeout_ms = -1);
@@ -97,6 +367,11 @@ public:
     std::unique_ptr<Reader> GetReader();
 
     void Close();
+    void Abandon();  // detach like a crashed process: nothing unlinked, writer marked gone
+
+    // Janitor support: inspect or remove a segment by name without attaching to it
+    static bool InspectSegment(const std::string& name, SegmentStatus* status);
+    static void RemoveSegment(const std::string& name);
 
     // Statistics
     struct Stats {
@@ -105,15 +380,26 @@ public:
         uint64_t writes_count = 0;
         uint64_t reads_count = 0;
         uint64_t wait_timeouts = 0;
+        uint64_t lapped = 0;
     };
 
     Stats GetStats() const { return stats_; }
+    MemoryReport GetMemoryReport() const;
+
+    // Debug dumps, read without taking the spinlocks so they work while one is stuck
+    int DumpHeader(HeaderDump* dump) const;
+    int DumpSlot(size_t index, SlotDump* dump) const;    // 1 if index < buffer_count
+    int DumpGroup(size_t index, GroupDump* dump) const;  // 1 if group `index` is in use
+    int DumpReader(size_t index, ReaderDump* dump) const;  // 1 if entry `index` is in use
 
 private:
     std::string name_;
     size_t total_size_;
     size_t buffer_count_;
     size_t buffer_size_;
+    size_t slot_stride_;
+    size_t max_buffer_count_;
+    uint64_t geometry_epoch_;
 
     int shm_fd_;
     void* mapped_memory_;
@@ -125,13 +411,77 @@ private:
     bool is_writer_;
     bool is_attached_;
 
+    bool has_read_;
+    uint64_t last_read_sequence_;
+
+    bool batch_open_;
+    size_t batch_pending_;
+
+    int group_idx_;
+    int reader_idx_;  // registry entry, -1 if unregistered
+
+    bool seeking_;
+    uint64_t seek_cursor_;
+
+    int64_t acquired_idx_;  // slot handed out by AcquireWriteBuffer, -1 if none
+
+    int protection_;  // PROTECT_MEMORY | GUARD_READS
+    int slot_guards_;  // GUARD_PAGES | CANARIES
+    int advice_;       // ADVISE_* flags
+
+    std::string backing_path_;  // empty: POSIX shared memory
+    bool msync_persist_;        // the mapping lacks MAP_SYNC, so persist with msync
+
     mutable Stats stats_;
 
+    void ComputeGeometry();
+    size_t BuffersOffset() const;
+    void LayOutSlots(size_t usable, size_t buffer_count);
+    void ArmGuardPages();
+    void ApplyAdvice();
+    uint64_t CanaryFor(size_t buffer_idx) const;
+    void WriteCanary(size_t buffer_idx);
+    bool CanaryIntact(size_t buffer_idx) const;
+    void SyncGeometry();
     bool CreateSharedMemory();
     bool AttachSharedMemory();
+    int OpenSegment(bool create) const;
+    void* MapSegment();
+    void Persist(const void* addr, size_t size) const;
+    bool BootChanged() const;
+    void ForgetPreviousBoot();
+    bool CreateSemaphores();
+    bool OpenSemaphores();
+    bool RestoreSemaphores();
     void InitializeHeader();
+    void UnlinkSegment();
+    int ReattachWriter(size_t buffer_count, size_t buffer_size);
+    bool SegmentExists() const;
+    bool HasLiveWriter() const;
+    void ClaimWriter();
     size_t GetNextWriteBuffer();
     size_t GetCurrentReadBuffer();
+    uint8_t* BufferAt(size_t buffer_idx) const;
+    int WaitReadable(int timeout_ms);
+    void PublishBuffer(size_t buffer_idx, size_t data_size);
+    void WakeFutexWaiters();
+    void ReleaseBuffer(size_t buffer_idx);
+    void BeginBufferWrite(size_t buffer_idx);
+    void ProtectRange(size_t offset, size_t size, int prot);
+    void ProtectBuffer(size_t buffer_idx, bool writable);
+    int CopyOut(uint8_t* data, size_t buffer_idx, size_t size);
+    int ReadSeekBytes(uint8_t* data, size_t max_size, size_t* actual_size, int timeout_ms);
+    int ReadGroupBytes(uint8_t* data, size_t max_size, size_t* actual_size, int timeout_ms);
+    void WaitForGroupSpace();
+    bool MinGroupCursor(uint64_t* cursor) const;
+    void LockGroups();
+    void UnlockGroups();
+    void LockMeta() const;
+    void UnlockMeta() const;
+    void LockReaders();
+    void UnlockReaders();
+    int RegisterReader();
+    void NoteRead(uint64_t sequence);
 
     arrow::Status SerializeRecordBatch(const std::shared_ptr<arrow::RecordBatch>& batch,
                                       uint8_t* buffer, size_t buffer_size, size_t* out_size);
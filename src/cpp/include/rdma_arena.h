@@ -0,0 +1,117 @@
+#pragma once
+
+// Experimental cross-host arena over RDMA (InfiniBand / RoCE), built with
+// -DQADS_WITH_RDMA=ON. The reader owns the ring: it registers the slots with
+// its RDMA device and listens for the writer, which connects over TCP to swap
+// queue pair details and then RDMA-writes every frame straight into a slot of
+// the reader's memory. Each frame goes out as a single RDMA_WRITE_WITH_IMM
+// carrying the slot index, so the reader's receive completion guarantees the
+// whole slot has landed. The reader returns consumed slots by RDMA-writing
+// its read count into the writer's credit word. One writer, one reader.
+
+#include <atomic>
+#include <cstdint>
+#include <deque>
+#include <mutex>
+#include <string>
+#include <thread>
+
+#include <infiniband/verbs.h>
+
+#include "qadataswap_core.h"
+
+namespace qadataswap {
+
+#pragma pack(push, 1)
+// Ahead of every slot's payload, written together with it
+struct RdmaSlotHeader {
+    uint64_t sequence;
+    uint64_t data_size;
+    uint64_t timestamp;  // wall clock, microseconds since epoch
+    uint8_t reserved[CACHE_LINE_SIZE - 3 * sizeof(uint64_t)];
+};
+
+// Queue pair and memory details each side sends the other when connecting
+struct RdmaPeerInfo {
+    uint32_t qp_num;
+    uint32_t psn;
+    uint16_t lid;
+    uint8_t gid[16];
+    uint64_t addr;       // remote address of the slots (reader) or credit word (writer)
+    uint32_t rkey;
+    uint64_t slot_size;  // stride between slots, header included
+    uint64_t buffer_count;
+};
+#pragma pack(pop)
+
+class RdmaArena {
+public:
+    // `host:port` is where the reader listens and the writer connects
+    RdmaArena(const std::string& host, uint16_t port, size_t size, size_t buffer_count);
+    ~RdmaArena();
+
+    // Writer: connect to the reader, retrying until it listens or the timeout passes
+    int CreateWriter(int timeout_ms);
+    // Reader: register the ring and accept the writer in the background
+    int AttachReader();
+
+    int WriteBytes(const uint8_t* data, size_t size);
+    uint8_t* AcquireWriteBuffer(size_t* capacity);
+    int PublishAcquired(size_t size);
+    int ReleaseAcquired();
+
+    int ReadBytes(uint8_t* data, size_t max_size, size_t* actual_size, int timeout_ms);
+    bool LastReadSequence(uint64_t* sequence) const;
+    bool HasData();
+    int PeekSize(size_t* size, int timeout_ms);
+    int WaitForData(int timeout_ms);
+
+    void Close();
+
+private:
+    bool OpenDevice();
+    bool CreateQueuePair();
+    bool ExchangePeerInfo(int fd, RdmaPeerInfo* remote);
+    bool ConnectQueuePair(const RdmaPeerInfo& remote);
+    bool PostReceive();
+    // Drain completions; receives queue their slot for reading. False on a failed work request.
+    bool PollCompletions();
+    // Writer: wait until the reader has consumed the slot the next frame goes to
+    int WaitForCredit(int timeout_ms);
+    void AcceptWriter();
+
+    std::string host_;
+    uint16_t port_;
+    size_t buffer_count_;
+    size_t slot_size_;  // stride between slots, header included
+
+    ibv_context* context_ = nullptr;
+    ibv_pd* pd_ = nullptr;
+    ibv_cq* cq_ = nullptr;
+    ibv_qp* qp_ = nullptr;
+    ibv_mr* mr_ = nullptr;
+    uint8_t port_num_ = 1;
+    int gid_index_ = 0;
+
+    // Reader: the slots followed by the read count it sends back.
+    // Writer: staging slots followed by the credit word the reader writes.
+    uint8_t* region_ = nullptr;
+    size_t region_size_ = 0;
+    RdmaPeerInfo remote_{};
+
+    bool is_writer_ = false;
+    std::atomic<bool> connected_{false};
+    std::atomic<bool> closing_{false};
+    int listen_fd_ = -1;
+    std::thread accept_thread_;
+
+    std::mutex cq_mutex_;
+    std::deque<uint32_t> ready_slots_;  // reader: slots landed, in arrival order
+    uint64_t write_sequence_ = 0;
+    uint64_t read_count_ = 0;
+    uint64_t last_read_sequence_ = 0;
+    bool last_read_valid_ = false;
+    bool acquired_ = false;
+};
+
+}  // namespace qadataswap
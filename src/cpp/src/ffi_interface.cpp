@@ -1,4 +1,7 @@
 #include "../include/qadataswap_core.h"
+#ifdef QADS_WITH_RDMA
+#include "../include/rdma_arena.h"
+#endif
 #include <cstring>
 #include <memory>
 #include <thread>
@@ -39,37 +42,314 @@ int qads_create_writer(void* arena) {
     }
 }
 
+int qads_create_writer_with_mode(void* arena, int mode) {
+    if (!arena || mode < 0 || mode > static_cast<int>(OpenMode::Truncate)) return QADS_ERROR;
+
+    try {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        return arena_ptr->CreateWriter(static_cast<OpenMode>(mode));
+    } catch (...) {
+        return QADS_ERROR;
+    }
+}
+
 int qads_attach_reader(void* arena) {
     if (!arena) return -1;
 
     try {
         auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
-        if (arena_ptr->AttachReader()) {
-            return 0; // success
-        }
-        return -1;
+        return arena_ptr->TryAttachReader();
     } catch (...) {
         return -1;
     }
 }
 
 int qads_write_data(void* arena, const uint8_t* data, size_t size) {
-    if (!arena || !data) return -1;
+    if (!arena || !data) return QADS_ERROR;
 
-    // Simplified implementation - in practice would deserialize Arrow data
-    // For demonstration, we'll just return success
-    return 0;
+    try {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        return arena_ptr->WriteBytes(data, size);
+    } catch (...) {
+        return QADS_ERROR;
+    }
 }
 
 int qads_read_data(void* arena, uint8_t* data, size_t max_size, size_t* actual_size, int timeout_ms) {
-    if (!arena || !data || !actual_size) return -1;
+    if (!arena || !data || !actual_size) return QADS_ERROR;
+
+    try {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        return arena_ptr->ReadBytes(data, max_size, actual_size, timeout_ms);
+    } catch (...) {
+        return QADS_ERROR;
+    }
+}
+
+int qads_last_read_sequence(void* arena, uint64_t* sequence) {
+    if (!arena || !sequence) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->LastReadSequence(sequence) ? QADS_OK : QADS_ERROR;
+}
+
+uint8_t* qads_acquire_write_buffer(void* arena, size_t* capacity) {
+    if (!arena || !capacity) return nullptr;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->AcquireWriteBuffer(capacity);
+}
+
+int qads_publish_acquired(void* arena, size_t size) {
+    if (!arena) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->PublishAcquired(size);
+}
+
+int qads_release_acquired(void* arena) {
+    if (!arena) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->ReleaseAcquired();
+}
+
+int qads_peek_size(void* arena, size_t* size, int timeout_ms) {
+    if (!arena || !size) return QADS_ERROR;
+
+    try {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        return arena_ptr->PeekSize(size, timeout_ms);
+    } catch (...) {
+        return QADS_ERROR;
+    }
+}
+
+int qads_has_data(void* arena) {
+    if (!arena) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->HasData() ? 1 : 0;
+}
+
+int qads_reserve_slots(void* arena, size_t max_buffer_count) {
+    if (!arena) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->ReserveSlots(max_buffer_count) ? QADS_OK : QADS_ERROR;
+}
+
+int qads_reslot(void* arena, size_t buffer_count) {
+    if (!arena) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->Reslot(buffer_count);
+}
+
+int qads_begin_batch(void* arena) {
+    if (!arena) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->BeginBatch();
+}
+
+int qads_commit_batch(void* arena) {
+    if (!arena) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->CommitBatch();
+}
+
+int qads_abort_batch(void* arena) {
+    if (!arena) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->AbortBatch();
+}
+
+int qads_memory_report(void* arena, MemoryReport* report) {
+    if (!arena || !report) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    *report = arena_ptr->GetMemoryReport();
+    return QADS_OK;
+}
+
+int qads_dump_header(void* arena, HeaderDump* dump) {
+    if (!arena || !dump) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->DumpHeader(dump);
+}
+
+int qads_dump_slot(void* arena, size_t index, SlotDump* dump) {
+    if (!arena || !dump) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->DumpSlot(index, dump);
+}
+
+int qads_dump_group(void* arena, size_t index, GroupDump* dump) {
+    if (!arena || !dump) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->DumpGroup(index, dump);
+}
+
+int qads_dump_reader(void* arena, size_t index, ReaderDump* dump) {
+    if (!arena || !dump) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->DumpReader(index, dump);
+}
+
+int qads_seek(void* arena, int origin, uint64_t sequence) {
+    if (!arena || origin < 0 || origin > static_cast<int>(SeekOrigin::Earliest)) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->Seek(static_cast<SeekOrigin>(origin), sequence);
+}
+
+void qads_retained_range(void* arena, uint64_t* first, uint64_t* next) {
+    if (!arena || !first || !next) return;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    arena_ptr->RetainedRange(first, next);
+}
+
+int qads_seek_time(void* arena, uint64_t timestamp_us) {
+    if (!arena) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->SeekTime(timestamp_us);
+}
+
+void qads_set_retention(void* arena, uint64_t retention_us) {
+    if (arena) {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        arena_ptr->SetRetention(retention_us);
+    }
+}
+
+void qads_set_max_readers(void* arena, int32_t max_readers) {
+    if (arena) {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        arena_ptr->SetMaxReaders(max_readers);
+    }
+}
+
+void qads_set_protection(void* arena, int flags) {
+    if (arena) {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        arena_ptr->SetProtection(flags);
+    }
+}
+
+void qads_set_slot_guards(void* arena, int flags) {
+    if (arena) {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        arena_ptr->SetSlotGuards(flags);
+    }
+}
+
+void qads_set_advice(void* arena, int flags) {
+    if (arena) {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        arena_ptr->SetAdvice(flags);
+    }
+}
+
+int qads_set_backing_path(void* arena, const char* path) {
+    if (!arena || !path) return QADS_ERROR;
+
+    try {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        return arena_ptr->SetBackingPath(path);
+    } catch (...) {
+        return QADS_ERROR;
+    }
+}
+
+void qads_enable_consumer_groups(void* arena) {
+    if (arena) {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        arena_ptr->EnableConsumerGroups();
+    }
+}
+
+int qads_join_group(void* arena, const char* group) {
+    if (!arena || !group) return QADS_ERROR;
+
+    try {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        return arena_ptr->JoinGroup(std::string(group));
+    } catch (...) {
+        return QADS_ERROR;
+    }
+}
+
+void qads_set_auth(void* arena, const uint8_t* nonce, const uint8_t* tag) {
+    if (arena) {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        arena_ptr->SetAuth(nonce, tag);
+    }
+}
+
+int qads_get_auth(void* arena, uint8_t* nonce, uint8_t* tag) {
+    if (!arena || !nonce || !tag) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->GetAuth(nonce, tag) ? 1 : 0;
+}
+
+int qads_put_meta(void* arena, const char* key, const char* value) {
+    if (!arena || !key) return QADS_ERROR;
+
+    try {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        return arena_ptr->PutMeta(std::string(key), value);
+    } catch (...) {
+        return QADS_ERROR;
+    }
+}
+
+int qads_get_meta(void* arena, const char* key, char* value, size_t max_size) {
+    if (!arena || !key || !value) return QADS_ERROR;
+
+    try {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        return arena_ptr->GetMeta(std::string(key), value, max_size);
+    } catch (...) {
+        return QADS_ERROR;
+    }
+}
+
+int qads_meta_key_at(void* arena, size_t index, char* key) {
+    if (!arena || !key) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->MetaKeyAt(index, key);
+}
+
+void qads_set_ack_mode(void* arena, int enabled) {
+    if (arena) {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        arena_ptr->SetAckMode(enabled != 0);
+    }
+}
 
-    // Simplified implementation - return dummy data
-    const char* dummy_data = "dummy_arrow_data";
-    *actual_size = std::min(strlen(dummy_data), max_size);
-    std::memcpy(data, dummy_data, *actual_size);
+int qads_ack(void* arena, uint64_t sequence) {
+    if (!arena) return QADS_ERROR;
 
-    return 0; // success
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->Ack(sequence);
+}
+
+uint64_t qads_unacked(void* arena) {
+    if (!arena) return 0;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    return arena_ptr->Unacked();
 }
 
 int qads_wait_for_data(void* arena, int timeout_ms) {
@@ -92,4 +372,113 @@ void qads_close(void* arena) {
     }
 }
 
+void qads_abandon(void* arena) {
+    if (arena) {
+        auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+        arena_ptr->Abandon();
+    }
+}
+
+int qads_publish_futex(void* arena, uint32_t** word, int32_t** waiters) {
+    if (!arena || !word || !waiters) return QADS_ERROR;
+
+    auto arena_ptr = static_cast<SharedMemoryArena*>(arena);
+    std::atomic<uint32_t>* futex_word = nullptr;
+    std::atomic<int32_t>* futex_waiters = nullptr;
+    if (!arena_ptr->PublishFutex(&futex_word, &futex_waiters)) return QADS_ERROR;
+
+    // std::atomic<T> is layout-compatible with T for these lock-free sizes
+    *word = reinterpret_cast<uint32_t*>(futex_word);
+    *waiters = reinterpret_cast<int32_t*>(futex_waiters);
+    return QADS_OK;
+}
+
+int qads_inspect_segment(const char* name, SegmentStatus* status) {
+    if (!name || !status) return QADS_ERROR;
+    return SharedMemoryArena::InspectSegment(name, status) ? QADS_OK : QADS_ERROR;
+}
+
+void qads_remove_segment(const char* name) {
+    if (name) {
+        SharedMemoryArena::RemoveSegment(name);
+    }
+}
+
+#ifdef QADS_WITH_RDMA
+void* qads_rdma_create_arena(const char* host, uint16_t port, size_t size, size_t buffer_count) {
+    if (!host) return nullptr;
+
+    try {
+        return new RdmaArena(std::string(host), port, size, buffer_count);
+    } catch (...) {
+        return nullptr;
+    }
+}
+
+void qads_rdma_destroy_arena(void* arena) {
+    delete static_cast<RdmaArena*>(arena);
+}
+
+int qads_rdma_create_writer(void* arena, int timeout_ms) {
+    if (!arena) return QADS_ERROR;
+    return static_cast<RdmaArena*>(arena)->CreateWriter(timeout_ms);
+}
+
+int qads_rdma_attach_reader(void* arena) {
+    if (!arena) return QADS_ERROR;
+    return static_cast<RdmaArena*>(arena)->AttachReader();
+}
+
+int qads_rdma_write_data(void* arena, const uint8_t* data, size_t size) {
+    if (!arena || !data) return QADS_ERROR;
+    return static_cast<RdmaArena*>(arena)->WriteBytes(data, size);
+}
+
+int qads_rdma_read_data(void* arena, uint8_t* data, size_t max_size, size_t* actual_size, int timeout_ms) {
+    if (!arena || !data || !actual_size) return QADS_ERROR;
+    return static_cast<RdmaArena*>(arena)->ReadBytes(data, max_size, actual_size, timeout_ms);
+}
+
+uint8_t* qads_rdma_acquire_write_buffer(void* arena, size_t* capacity) {
+    if (!arena || !capacity) return nullptr;
+    return static_cast<RdmaArena*>(arena)->AcquireWriteBuffer(capacity);
+}
+
+int qads_rdma_publish_acquired(void* arena, size_t size) {
+    if (!arena) return QADS_ERROR;
+    return static_cast<RdmaArena*>(arena)->PublishAcquired(size);
+}
+
+int qads_rdma_release_acquired(void* arena) {
+    if (!arena) return QADS_ERROR;
+    return static_cast<RdmaArena*>(arena)->ReleaseAcquired();
+}
+
+int qads_rdma_last_read_sequence(void* arena, uint64_t* sequence) {
+    if (!arena || !sequence) return QADS_ERROR;
+    return static_cast<RdmaArena*>(arena)->LastReadSequence(sequence) ? QADS_OK : QADS_ERROR;
+}
+
+int qads_rdma_peek_size(void* arena, size_t* size, int timeout_ms) {
+    if (!arena || !size) return QADS_ERROR;
+    return static_cast<RdmaArena*>(arena)->PeekSize(size, timeout_ms);
+}
+
+int qads_rdma_has_data(void* arena) {
+    if (!arena) return QADS_ERROR;
+    return static_cast<RdmaArena*>(arena)->HasData() ? 1 : 0;
+}
+
+int qads_rdma_wait_for_data(void* arena, int timeout_ms) {
+    if (!arena) return QADS_ERROR;
+    return static_cast<RdmaArena*>(arena)->WaitForData(timeout_ms);
+}
+
+void qads_rdma_close(void* arena) {
+    if (arena) {
+        static_cast<RdmaArena*>(arena)->Close();
+    }
+}
+#endif
+
 } // extern "C"
\ No newline at end of file
@@ -0,0 +1,523 @@
+#include "rdma_arena.h"
+
+#include <algorithm>
+#include <chrono>
+#include <cstdlib>
+#include <cstring>
+#include <iostream>
+#include <random>
+
+#include <arpa/inet.h>
+#include <netdb.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <sys/mman.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+namespace qadataswap {
+
+namespace {
+
+constexpr int CONNECT_RETRY_MS = 100;
+constexpr int POLL_BATCH = 16;
+constexpr int SPIN_POLLS = 1000;  // busy polls before sleeping between checks
+
+uint64_t NowMicros() {
+    return std::chrono::duration_cast<std::chrono::microseconds>(
+        std::chrono::system_clock::now().time_since_epoch()).count();
+}
+
+bool SendAll(int fd, const void* data, size_t size) {
+    auto bytes = static_cast<const uint8_t*>(data);
+    while (size > 0) {
+        ssize_t sent = send(fd, bytes, size, MSG_NOSIGNAL);
+        if (sent <= 0) return false;
+        bytes += sent;
+        size -= static_cast<size_t>(sent);
+    }
+    return true;
+}
+
+bool RecvAll(int fd, void* data, size_t size) {
+    auto bytes = static_cast<uint8_t*>(data);
+    while (size > 0) {
+        ssize_t received = recv(fd, bytes, size, 0);
+        if (received <= 0) return false;
+        bytes += received;
+        size -= static_cast<size_t>(received);
+    }
+    return true;
+}
+
+bool Resolve(const std::string& host, uint16_t port, sockaddr_in* addr) {
+    addrinfo hints{};
+    hints.ai_family = AF_INET;
+    hints.ai_socktype = SOCK_STREAM;
+    addrinfo* result = nullptr;
+    if (getaddrinfo(host.c_str(), std::to_string(port).c_str(), &hints, &result) != 0 || !result) {
+        return false;
+    }
+    std::memcpy(addr, result->ai_addr, sizeof(sockaddr_in));
+    freeaddrinfo(result);
+    return true;
+}
+
+// Wait out one idle round: spin first, then sleep so an idle reader costs little CPU
+void Backoff(int* polls) {
+    if (++*polls > SPIN_POLLS) {
+        std::this_thread::sleep_for(std::chrono::microseconds(20));
+    }
+}
+
+bool Expired(std::chrono::steady_clock::time_point deadline, int timeout_ms) {
+    return timeout_ms >= 0 && std::chrono::steady_clock::now() >= deadline;
+}
+
+}  // namespace
+
+RdmaArena::RdmaArena(const std::string& host, uint16_t port, size_t size, size_t buffer_count)
+    : host_(host), port_(port), buffer_count_(std::max<size_t>(buffer_count, 1)) {
+    // The region ends in one cache line for the read count / credit word
+    slot_size_ = (size - CACHE_LINE_SIZE) / buffer_count_ / CACHE_LINE_SIZE * CACHE_LINE_SIZE;
+    region_size_ = slot_size_ * buffer_count_ + CACHE_LINE_SIZE;
+}
+
+RdmaArena::~RdmaArena() {
+    Close();
+}
+
+bool RdmaArena::OpenDevice() {
+    int count = 0;
+    ibv_device** devices = ibv_get_device_list(&count);
+    if (!devices || count == 0) {
+        std::cerr << "No RDMA devices found\n";
+        if (devices) ibv_free_device_list(devices);
+        return false;
+    }
+
+    // QADS_RDMA_DEVICE picks a device by name (e.g. mlx5_0), otherwise the first one
+    const char* wanted = std::getenv("QADS_RDMA_DEVICE");
+    ibv_device* device = devices[0];
+    for (int i = 0; wanted && i < count; ++i) {
+        if (std::strcmp(ibv_get_device_name(devices[i]), wanted) == 0) {
+            device = devices[i];
+        }
+    }
+    context_ = ibv_open_device(device);
+    ibv_free_device_list(devices);
+    if (!context_) {
+        std::cerr << "Failed to open RDMA device\n";
+        return false;
+    }
+
+    // RoCE needs the GID index of the interface's address family (often 1 or 3 for RoCE v2)
+    if (const char* gid_index = std::getenv("QADS_RDMA_GID_INDEX")) {
+        gid_index_ = std::atoi(gid_index);
+    }
+
+    pd_ = ibv_alloc_pd(context_);
+    cq_ = ibv_create_cq(context_, static_cast<int>(buffer_count_ * 2 + POLL_BATCH), nullptr, nullptr, 0);
+    if (!pd_ || !cq_) {
+        std::cerr << "Failed to allocate RDMA protection domain or completion queue\n";
+        return false;
+    }
+
+    void* region = mmap(nullptr, region_size_, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (region == MAP_FAILED) {
+        std::cerr << "Failed to map RDMA region: " << strerror(errno) << std::endl;
+        return false;
+    }
+    region_ = static_cast<uint8_t*>(region);
+    mr_ = ibv_reg_mr(pd_, region_, region_size_, IBV_ACCESS_LOCAL_WRITE | IBV_ACCESS_REMOTE_WRITE);
+    if (!mr_) {
+        std::cerr << "Failed to register RDMA region: " << strerror(errno) << std::endl;
+        return false;
+    }
+    return CreateQueuePair();
+}
+
+bool RdmaArena::CreateQueuePair() {
+    ibv_qp_init_attr init{};
+    init.send_cq = cq_;
+    init.recv_cq = cq_;
+    init.qp_type = IBV_QPT_RC;
+    init.cap.max_send_wr = static_cast<uint32_t>(buffer_count_ + POLL_BATCH);
+    init.cap.max_recv_wr = static_cast<uint32_t>(buffer_count_);
+    init.cap.max_send_sge = 1;
+    init.cap.max_recv_sge = 1;
+    init.cap.max_inline_data = sizeof(uint64_t);
+    qp_ = ibv_create_qp(pd_, &init);
+    if (!qp_) {
+        std::cerr << "Failed to create RDMA queue pair\n";
+        return false;
+    }
+
+    ibv_qp_attr attr{};
+    attr.qp_state = IBV_QPS_INIT;
+    attr.pkey_index = 0;
+    attr.port_num = port_num_;
+    attr.qp_access_flags = IBV_ACCESS_LOCAL_WRITE | IBV_ACCESS_REMOTE_WRITE;
+    return ibv_modify_qp(qp_, &attr, IBV_QP_STATE | IBV_QP_PKEY_INDEX | IBV_QP_PORT | IBV_QP_ACCESS_FLAGS) == 0;
+}
+
+bool RdmaArena::ExchangePeerInfo(int fd, RdmaPeerInfo* remote) {
+    ibv_port_attr port{};
+    ibv_gid gid{};
+    if (ibv_query_port(context_, port_num_, &port) != 0 || ibv_query_gid(context_, port_num_, gid_index_, &gid) != 0) {
+        return false;
+    }
+
+    std::random_device random;
+    RdmaPeerInfo local{};
+    local.qp_num = qp_->qp_num;
+    local.psn = random() & 0xffffff;
+    local.lid = port.lid;
+    std::memcpy(local.gid, gid.raw, sizeof(local.gid));
+    // The reader hands out its slots, the writer its credit word
+    local.addr = reinterpret_cast<uint64_t>(is_writer_ ? region_ + slot_size_ * buffer_count_ : region_);
+    local.rkey = mr_->rkey;
+    local.slot_size = slot_size_;
+    local.buffer_count = buffer_count_;
+
+    if (!SendAll(fd, &local, sizeof(local)) || !RecvAll(fd, remote, sizeof(*remote))) {
+        return false;
+    }
+    if (remote->slot_size != slot_size_ || remote->buffer_count != buffer_count_) {
+        std::cerr << "RDMA peer ring geometry differs: " << remote->buffer_count << " x "
+                  << remote->slot_size << " bytes, expected " << buffer_count_ << " x " << slot_size_ << "\n";
+        return false;
+    }
+    remote_ = *remote;
+    if (!ConnectQueuePair(remote_)) return false;
+
+    ibv_qp_attr attr{};
+    attr.qp_state = IBV_QPS_RTS;
+    attr.timeout = 14;
+    attr.retry_cnt = 7;
+    attr.rnr_retry = 7;
+    attr.sq_psn = local.psn;
+    attr.max_rd_atomic = 1;
+    if (ibv_modify_qp(qp_, &attr, IBV_QP_STATE | IBV_QP_TIMEOUT | IBV_QP_RETRY_CNT |
+                      IBV_QP_RNR_RETRY | IBV_QP_SQ_PSN | IBV_QP_MAX_QP_RD_ATOMIC) != 0) {
+        return false;
+    }
+
+    // Neither side sends until both queue pairs are ready to receive
+    char ready = 1;
+    return SendAll(fd, &ready, 1) && RecvAll(fd, &ready, 1);
+}
+
+bool RdmaArena::ConnectQueuePair(const RdmaPeerInfo& remote) {
+    ibv_port_attr port{};
+    if (ibv_query_port(context_, port_num_, &port) != 0) return false;
+
+    ibv_qp_attr attr{};
+    attr.qp_state = IBV_QPS_RTR;
+    attr.path_mtu = port.active_mtu;
+    attr.dest_qp_num = remote.qp_num;
+    attr.rq_psn = remote.psn;
+    attr.max_dest_rd_atomic = 1;
+    attr.min_rnr_timer = 12;
+    attr.ah_attr.dlid = remote.lid;
+    attr.ah_attr.sl = 0;
+    attr.ah_attr.src_path_bits = 0;
+    attr.ah_attr.port_num = port_num_;
+
+    // RoCE (and routed InfiniBand) addresses the peer by GID
+    static const uint8_t zero_gid[16] = {};
+    if (std::memcmp(remote.gid, zero_gid, sizeof(zero_gid)) != 0) {
+        attr.ah_attr.is_global = 1;
+        std::memcpy(attr.ah_attr.grh.dgid.raw, remote.gid, sizeof(remote.gid));
+        attr.ah_attr.grh.sgid_index = static_cast<uint8_t>(gid_index_);
+        attr.ah_attr.grh.hop_limit = 1;
+    }
+    return ibv_modify_qp(qp_, &attr, IBV_QP_STATE | IBV_QP_AV | IBV_QP_PATH_MTU | IBV_QP_DEST_QPN |
+                         IBV_QP_RQ_PSN | IBV_QP_MAX_DEST_RD_ATOMIC | IBV_QP_MIN_RNR_TIMER) == 0;
+}
+
+bool RdmaArena::PostReceive() {
+    // Writes with immediate consume a receive, which needs no buffer of its own
+    ibv_recv_wr wr{};
+    ibv_recv_wr* bad = nullptr;
+    wr.sg_list = nullptr;
+    wr.num_sge = 0;
+    return ibv_post_recv(qp_, &wr, &bad) == 0;
+}
+
+int RdmaArena::CreateWriter(int timeout_ms) {
+    is_writer_ = true;
+    if (!OpenDevice()) return QADS_ERROR;
+
+    sockaddr_in addr{};
+    if (!Resolve(host_, port_, &addr)) {
+        std::cerr << "Failed to resolve RDMA peer " << host_ << "\n";
+        return QADS_ERROR;
+    }
+
+    auto deadline = std::chrono::steady_clock::now() + std::chrono::milliseconds(timeout_ms);
+    int fd = -1;
+    while (true) {
+        fd = socket(AF_INET, SOCK_STREAM, 0);
+        if (fd >= 0 && connect(fd, reinterpret_cast<sockaddr*>(&addr), sizeof(addr)) == 0) break;
+        if (fd >= 0) close(fd);
+        if (Expired(deadline, timeout_ms)) return QADS_TIMEOUT;
+        std::this_thread::sleep_for(std::chrono::milliseconds(CONNECT_RETRY_MS));
+    }
+
+    RdmaPeerInfo remote{};
+    bool ok = ExchangePeerInfo(fd, &remote);
+    close(fd);
+    if (!ok) {
+        std::cerr << "Failed to connect RDMA queue pair to " << host_ << ":" << port_ << "\n";
+        return QADS_ERROR;
+    }
+    connected_.store(true);
+    return QADS_OK;
+}
+
+int RdmaArena::AttachReader() {
+    is_writer_ = false;
+    if (!OpenDevice()) return QADS_ERROR;
+    for (size_t i = 0; i < buffer_count_; ++i) {
+        if (!PostReceive()) return QADS_ERROR;
+    }
+
+    sockaddr_in addr{};
+    if (!Resolve(host_, port_, &addr)) {
+        std::cerr << "Failed to resolve RDMA listen address " << host_ << "\n";
+        return QADS_ERROR;
+    }
+    listen_fd_ = socket(AF_INET, SOCK_STREAM, 0);
+    int reuse = 1;
+    setsockopt(listen_fd_, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse));
+    if (listen_fd_ < 0 || bind(listen_fd_, reinterpret_cast<sockaddr*>(&addr), sizeof(addr)) != 0 ||
+        listen(listen_fd_, 1) != 0) {
+        std::cerr << "Failed to listen on " << host_ << ":" << port_ << ": " << strerror(errno) << std::endl;
+        return QADS_ERROR;
+    }
+
+    accept_thread_ = std::thread([this] { AcceptWriter(); });
+    return QADS_OK;
+}
+
+void RdmaArena::AcceptWriter() {
+    while (!closing_.load()) {
+        int fd = accept(listen_fd_, nullptr, nullptr);
+        if (fd < 0) return;  // listener shut down by Close()
+
+        RdmaPeerInfo remote{};
+        bool ok = ExchangePeerInfo(fd, &remote);
+        close(fd);
+        if (ok) {
+            connected_.store(true);
+            return;
+        }
+        std::cerr << "RDMA writer handshake failed, waiting for another\n";
+    }
+}
+
+bool RdmaArena::PollCompletions() {
+    ibv_wc completions[POLL_BATCH];
+    int count;
+    while ((count = ibv_poll_cq(cq_, POLL_BATCH, completions)) > 0) {
+        for (int i = 0; i < count; ++i) {
+            const ibv_wc& wc = completions[i];
+            if (wc.status != IBV_WC_SUCCESS) {
+                std::cerr << "RDMA work request failed: " << ibv_wc_status_str(wc.status) << "\n";
+                return false;
+            }
+            if (wc.opcode == IBV_WC_RECV_RDMA_WITH_IMM) {
+                ready_slots_.push_back(ntohl(wc.imm_data));
+                if (!PostReceive()) return false;
+            }
+        }
+    }
+    return count == 0;
+}
+
+int RdmaArena::WaitForCredit(int timeout_ms) {
+    auto deadline = std::chrono::steady_clock::now() + std::chrono::milliseconds(timeout_ms);
+    auto credit = reinterpret_cast<uint64_t*>(region_ + slot_size_ * buffer_count_);
+    int polls = 0;
+    while (write_sequence_ - __atomic_load_n(credit, __ATOMIC_ACQUIRE) >= buffer_count_) {
+        if (!PollCompletions()) return QADS_ERROR;
+        if (Expired(deadline, timeout_ms)) return QADS_TIMEOUT;
+        Backoff(&polls);
+    }
+    return PollCompletions() ? QADS_OK : QADS_ERROR;
+}
+
+uint8_t* RdmaArena::AcquireWriteBuffer(size_t* capacity) {
+    std::lock_guard<std::mutex> lock(cq_mutex_);
+    if (!is_writer_ || !connected_.load() || acquired_) return nullptr;
+    if (WaitForCredit(-1) != QADS_OK) return nullptr;
+
+    acquired_ = true;
+    *capacity = slot_size_ - sizeof(RdmaSlotHeader);
+    return region_ + (write_sequence_ % buffer_count_) * slot_size_ + sizeof(RdmaSlotHeader);
+}
+
+int RdmaArena::PublishAcquired(size_t size) {
+    std::lock_guard<std::mutex> lock(cq_mutex_);
+    if (!acquired_ || size > slot_size_ - sizeof(RdmaSlotHeader)) return QADS_ERROR;
+    acquired_ = false;
+
+    size_t idx = write_sequence_ % buffer_count_;
+    auto header = reinterpret_cast<RdmaSlotHeader*>(region_ + idx * slot_size_);
+    header->sequence = write_sequence_;
+    header->data_size = size;
+    header->timestamp = NowMicros();
+
+    // Header and payload travel in one write; its completion at the reader covers both
+    ibv_sge sge{};
+    sge.addr = reinterpret_cast<uint64_t>(header);
+    sge.length = static_cast<uint32_t>(sizeof(RdmaSlotHeader) + size);
+    sge.lkey = mr_->lkey;
+
+    ibv_send_wr wr{};
+    ibv_send_wr* bad = nullptr;
+    wr.opcode = IBV_WR_RDMA_WRITE_WITH_IMM;
+    wr.imm_data = htonl(static_cast<uint32_t>(idx));
+    wr.send_flags = IBV_SEND_SIGNALED;
+    wr.sg_list = &sge;
+    wr.num_sge = 1;
+    wr.wr.rdma.remote_addr = remote_.addr + idx * slot_size_;
+    wr.wr.rdma.rkey = remote_.rkey;
+    if (ibv_post_send(qp_, &wr, &bad) != 0) return QADS_ERROR;
+
+    write_sequence_++;
+    return QADS_OK;
+}
+
+int RdmaArena::ReleaseAcquired() {
+    // Nothing was posted, so the staging slot is simply reused
+    std::lock_guard<std::mutex> lock(cq_mutex_);
+    if (!acquired_) return QADS_ERROR;
+    acquired_ = false;
+    return QADS_OK;
+}
+
+int RdmaArena::WriteBytes(const uint8_t* data, size_t size) {
+    size_t capacity = 0;
+    uint8_t* slot = AcquireWriteBuffer(&capacity);
+    if (!slot) return QADS_ERROR;
+    if (size > capacity) {
+        ReleaseAcquired();
+        return QADS_ERROR;
+    }
+    std::memcpy(slot, data, size);
+    return PublishAcquired(size);
+}
+
+int RdmaArena::ReadBytes(uint8_t* data, size_t max_size, size_t* actual_size, int timeout_ms) {
+    if (is_writer_ || !region_) return QADS_ERROR;
+
+    auto deadline = std::chrono::steady_clock::now() + std::chrono::milliseconds(timeout_ms);
+    int polls = 0;
+    std::unique_lock<std::mutex> lock(cq_mutex_);
+    while (true) {
+        if (connected_.load() && !PollCompletions()) return QADS_ERROR;
+        if (!ready_slots_.empty()) break;
+        if (Expired(deadline, timeout_ms)) return QADS_TIMEOUT;
+        lock.unlock();
+        Backoff(&polls);
+        lock.lock();
+    }
+
+    uint32_t idx = ready_slots_.front();
+    auto header = reinterpret_cast<const RdmaSlotHeader*>(region_ + idx * slot_size_);
+    if (header->data_size > max_size) {
+        return QADS_ERROR;  // left for a caller with a larger buffer
+    }
+    std::memcpy(data, region_ + idx * slot_size_ + sizeof(RdmaSlotHeader), header->data_size);
+    *actual_size = header->data_size;
+    last_read_sequence_ = header->sequence;
+    last_read_valid_ = true;
+    ready_slots_.pop_front();
+
+    // Hand the slot back: the writer waits on this count before reusing it
+    read_count_++;
+    ibv_sge sge{};
+    sge.addr = reinterpret_cast<uint64_t>(&read_count_);
+    sge.length = sizeof(read_count_);
+
+    ibv_send_wr wr{};
+    ibv_send_wr* bad = nullptr;
+    wr.opcode = IBV_WR_RDMA_WRITE;
+    wr.send_flags = IBV_SEND_SIGNALED | IBV_SEND_INLINE;
+    wr.sg_list = &sge;
+    wr.num_sge = 1;
+    wr.wr.rdma.remote_addr = remote_.addr;
+    wr.wr.rdma.rkey = remote_.rkey;
+    return ibv_post_send(qp_, &wr, &bad) == 0 ? QADS_OK : QADS_ERROR;
+}
+
+int RdmaArena::PeekSize(size_t* size, int timeout_ms) {
+    if (is_writer_ || !region_) return QADS_ERROR;
+
+    auto deadline = std::chrono::steady_clock::now() + std::chrono::milliseconds(timeout_ms);
+    int polls = 0;
+    std::unique_lock<std::mutex> lock(cq_mutex_);
+    while (true) {
+        if (connected_.load() && !PollCompletions()) return QADS_ERROR;
+        if (!ready_slots_.empty()) break;
+        if (Expired(deadline, timeout_ms)) return QADS_TIMEOUT;
+        lock.unlock();
+        Backoff(&polls);
+        lock.lock();
+    }
+
+    auto header = reinterpret_cast<const RdmaSlotHeader*>(region_ + ready_slots_.front() * slot_size_);
+    *size = header->data_size;
+    return QADS_OK;
+}
+
+bool RdmaArena::LastReadSequence(uint64_t* sequence) const {
+    if (!last_read_valid_) return false;
+    *sequence = last_read_sequence_;
+    return true;
+}
+
+bool RdmaArena::HasData() {
+    std::lock_guard<std::mutex> lock(cq_mutex_);
+    if (is_writer_ || !connected_.load()) return false;
+    PollCompletions();
+    return !ready_slots_.empty();
+}
+
+int RdmaArena::WaitForData(int timeout_ms) {
+    auto deadline = std::chrono::steady_clock::now() + std::chrono::milliseconds(timeout_ms);
+    int polls = 0;
+    while (!HasData()) {
+        if (Expired(deadline, timeout_ms)) return QADS_TIMEOUT;
+        Backoff(&polls);
+    }
+    return QADS_OK;
+}
+
+void RdmaArena::Close() {
+    closing_.store(true);
+    if (listen_fd_ >= 0) {
+        shutdown(listen_fd_, SHUT_RDWR);
+        close(listen_fd_);
+        listen_fd_ = -1;
+    }
+    if (accept_thread_.joinable()) accept_thread_.join();
+
+    if (qp_) ibv_destroy_qp(qp_);
+    if (mr_) ibv_dereg_mr(mr_);
+    if (cq_) ibv_destroy_cq(cq_);
+    if (pd_) ibv_dealloc_pd(pd_);
+    if (context_) ibv_close_device(context_);
+    if (region_) munmap(region_, region_size_);
+    qp_ = nullptr;
+    mr_ = nullptr;
+    cq_ = nullptr;
+    pd_ = nullptr;
+    context_ = nullptr;
+    region_ = nullptr;
+    connected_.store(false);
+}
+
+}  // namespace qadataswap
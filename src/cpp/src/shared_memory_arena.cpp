@@ -1,24 +1,193 @@
 #include "qadataswap_core.h"
+#include <algorithm>
 #include <chrono>
 #include <cstring>
 #include <iostream>
+#include <cerrno>
+#include <csignal>
+#include <thread>
+#include <climits>
+#include <cstdlib>
+#include <csetjmp>
+#include <mutex>
+#include <unistd.h>
+#include <linux/futex.h>
+#include <sys/syscall.h>
+#include <sys/sysmacros.h>
 #include <arrow/ipc/writer.h>
 #include <arrow/ipc/reader.h>
+#if defined(__x86_64__) || defined(__i386__)
+#include <immintrin.h>
+#endif
+
+// Older libc headers predate DAX mappings
+#ifndef MAP_SYNC
+#define MAP_SHARED_VALIDATE 0x03
+#define MAP_SYNC 0x80000
+#endif
 
 namespace qadataswap {
 
+namespace {
+
+constexpr uint64_t CANARY_SEED = 0x5144415343414e59ULL;  // 'QDASCANY'
+
+size_t PageSize() {
+    return static_cast<size_t>(sysconf(_SC_PAGESIZE));
+}
+
+// Identifies the running kernel boot; pids recorded under another boot mean nothing
+void ReadBootId(char* boot_id) {
+    memset(boot_id, 0, BOOT_ID_SIZE);
+    int fd = open("/proc/sys/kernel/random/boot_id", O_RDONLY);
+    if (fd == -1) return;
+    ssize_t n = read(fd, boot_id, BOOT_ID_SIZE - 1);
+    close(fd);
+    if (n > 0 && boot_id[n - 1] == '\n') boot_id[n - 1] = '\0';
+}
+
+// Start time of a process in clock ticks since boot, 0 if it cannot be read.
+// Together with the pid it names one process, even once the pid is handed out again.
+uint64_t ProcessStartTime(pid_t pid) {
+    char path[32];
+    snprintf(path, sizeof(path), "/proc/%d/stat", static_cast<int>(pid));
+    int fd = open(path, O_RDONLY);
+    if (fd == -1) return 0;
+    char stat[1024];
+    ssize_t n = read(fd, stat, sizeof(stat) - 1);
+    close(fd);
+    if (n <= 0) return 0;
+    stat[n] = '\0';
+
+    // The command name may hold spaces and parentheses, so fields are counted
+    // from its closing one: starttime is field 22, the state field 3
+    const char* field = strrchr(stat, ')');
+    for (int i = 3; field && i <= 22; ++i) field = strchr(field + 1, ' ');
+    return field ? strtoull(field + 1, nullptr, 10) : 0;
+}
+
+// The recorded writer is still running: its pid exists and was not handed to a
+// newer process since. Without /proc access (hidepid) the pid is taken on trust.
+bool WriterAlive(const SharedMemoryHeader* header) {
+    if (!header->writer_active.load()) return false;
+
+    pid_t pid = static_cast<pid_t>(header->writer_pid.load());
+    if (pid <= 0) return false;
+
+    // EPERM means the process exists but belongs to someone else
+    if (kill(pid, 0) != 0 && errno != EPERM) return false;
+
+    uint64_t recorded = header->writer_start_time.load();
+    uint64_t started = ProcessStartTime(pid);
+    return recorded == 0 || started == 0 || started == recorded;
+}
+
+}  // namespace
+
 SharedMemoryArena::SharedMemoryArena(const std::string& name, size_t size, size_t buffer_count)
-    : name_(name), total_size_(size), buffer_count_(buffer_count), shm_fd_(-1),
+    : name_(name), total_size_(size), buffer_count_(buffer_count),
+      max_buffer_count_(buffer_count), geometry_epoch_(0), shm_fd_(-1),
       mapped_memory_(nullptr), header_(nullptr), write_sem_(nullptr), read_sem_(nullptr),
-      is_writer_(false), is_attached_(false) {
+      is_writer_(false), is_attached_(false), has_read_(false), last_read_sequence_(0),
+      batch_open_(false), batch_pending_(0), group_idx_(-1), reader_idx_(-1),
+      seeking_(false), seek_cursor_(0), acquired_idx_(-1), protection_(0), slot_guards_(0),
+      advice_(0), msync_persist_(false) {
 
-    // Calculate buffer size
+    ComputeGeometry();
+}
+
+void SharedMemoryArena::ComputeGeometry() {
+    LayOutSlots(total_size_ - BuffersOffset(), buffer_count_);
+}
+
+size_t SharedMemoryArena::BuffersOffset() const {
     size_t header_size = sizeof(SharedMemoryHeader) +
-                        sizeof(SharedMemoryHeader::BufferState) * buffer_count;
-    header_size = (header_size + CACHE_LINE_SIZE - 1) & ~(CACHE_LINE_SIZE - 1); // Align
+                        sizeof(SharedMemoryHeader::BufferState) * max_buffer_count_;
+    // Guarded slots start on page boundaries
+    size_t alignment = (slot_guards_ & GUARD_PAGES) ? PageSize() : CACHE_LINE_SIZE;
+    return (header_size + alignment - 1) / alignment * alignment;
+}
 
-    buffer_size_ = (total_size_ - header_size) / buffer_count;
-    buffer_size_ = buffer_size_ & ~(CACHE_LINE_SIZE - 1); // Align buffer size
+void SharedMemoryArena::LayOutSlots(size_t usable, size_t buffer_count) {
+    size_t stride = usable / buffer_count;
+    if (slot_guards_ & GUARD_PAGES) {
+        size_t page = PageSize();
+        stride = stride / page * page;
+        buffer_size_ = stride > page ? stride - page : 0;
+    } else {
+        stride &= ~(CACHE_LINE_SIZE - 1);
+        buffer_size_ = stride;
+    }
+    // The canary sits right after the capacity, ahead of any guard page
+    if (slot_guards_ & CANARIES) {
+        buffer_size_ = buffer_size_ > CANARY_SIZE ? buffer_size_ - CANARY_SIZE : 0;
+    }
+    slot_stride_ = stride;
+}
+
+void SharedMemoryArena::SyncGeometry() {
+    // The writer only re-slots while no frame is outstanding
+    uint64_t epoch = header_->geometry_epoch.load();
+    if (epoch == geometry_epoch_) return;
+
+    buffer_count_ = header_->buffer_count;
+    buffer_size_ = header_->buffer_size;
+    slot_stride_ = header_->slot_stride;
+    geometry_epoch_ = epoch;
+    ArmGuardPages();
+}
+
+bool SharedMemoryArena::ReserveSlots(size_t max_buffer_count) {
+    if (is_attached_) return false;
+
+    max_buffer_count_ = std::max(max_buffer_count, buffer_count_);
+    ComputeGeometry();
+    return true;
+}
+
+int SharedMemoryArena::Reslot(size_t buffer_count) {
+    if (!is_writer_ || !header_ || batch_open_) return QADS_ERROR;
+    if (buffer_count == 0 || buffer_count > header_->max_buffer_count) return QADS_ERROR;
+    if (buffer_count == buffer_count_) return QADS_OK;
+
+    // Only safe while every published frame has been consumed
+    if (Unacked() != 0) return QADS_BUSY;
+
+    if (!header_->groups_enabled.load()) {
+        // Hand the writer exactly one token per new slot
+        if (buffer_count > buffer_count_) {
+            for (size_t i = buffer_count_; i < buffer_count; ++i) {
+                sem_post(write_sem_);
+            }
+        } else {
+            for (size_t i = buffer_count; i < buffer_count_; ++i) {
+                if (sem_trywait(write_sem_) != 0) {
+                    for (size_t j = buffer_count; j < i; ++j) {
+                        sem_post(write_sem_);
+                    }
+                    return QADS_BUSY;
+                }
+            }
+        }
+    }
+
+    buffer_count_ = buffer_count;
+    LayOutSlots(total_size_ - header_->buffers_offset, buffer_count);
+
+    // Retained frames do not survive a re-slot
+    for (size_t i = 0; i < header_->max_buffer_count; ++i) {
+        header_->buffer_states[i].ready.store(false);
+        header_->buffer_states[i].data_size.store(0);
+        header_->buffer_states[i].sequence.store(SLOT_BEING_WRITTEN);
+    }
+
+    header_->buffer_count = buffer_count_;
+    header_->buffer_size = buffer_size_;
+    header_->slot_stride = slot_stride_;
+    geometry_epoch_ = header_->geometry_epoch.fetch_add(1) + 1;
+    Persist(header_, header_->header_size);
+    ArmGuardPages();
+    return QADS_OK;
 }
 
 SharedMemoryArena::~SharedMemoryArena() {
@@ -26,15 +195,133 @@ SharedMemoryArena::~SharedMemoryArena() {
 }
 
 bool SharedMemoryArena::CreateWriter() {
-    if (is_attached_) return false;
+    return CreateWriter(OpenMode::Create) == QADS_OK;
+}
 
-    if (!CreateSharedMemory()) return false;
+int SharedMemoryArena::CreateWriter(OpenMode mode) {
+    if (is_attached_) return QADS_ERROR;
+
+    // Readers of a segment this one replaces see the generation change and reconnect.
+    // Starting from the clock keeps it increasing after the old segment was unlinked.
+    SegmentStatus previous{};
+    uint64_t generation = std::chrono::duration_cast<std::chrono::microseconds>(
+        std::chrono::system_clock::now().time_since_epoch()).count();
+    if (InspectSegment(name_, &previous)) generation = std::max(generation, previous.generation + 1);
+
+    switch (mode) {
+    case OpenMode::Truncate:
+        UnlinkSegment();
+        break;
+    case OpenMode::FailIfExists:
+        if (SegmentExists()) return QADS_ALREADY_EXISTS;
+        break;
+    case OpenMode::Create:
+    case OpenMode::OpenOrAttach:
+        if (SegmentExists()) {
+            // Remember our own geometry; attaching overwrites it with the segment's
+            size_t total_size = total_size_;
+            size_t buffer_count = buffer_count_;
+            size_t buffer_size = buffer_size_;
+            size_t slot_stride = slot_stride_;
+            size_t max_buffer_count = max_buffer_count_;
+            uint64_t geometry_epoch = geometry_epoch_;
+            int slot_guards = slot_guards_;
+            auto restore = [&] {
+                total_size_ = total_size;
+                buffer_count_ = buffer_count;
+                buffer_size_ = buffer_size;
+                slot_stride_ = slot_stride;
+                max_buffer_count_ = max_buffer_count;
+                geometry_epoch_ = geometry_epoch;
+                slot_guards_ = slot_guards;
+            };
+
+            if (!AttachSharedMemory()) {
+                mapped_memory_ = nullptr;
+                header_ = nullptr;
+                shm_fd_ = -1;
+                restore();
+                // Not a segment we can open: only Create may replace it
+                if (mode == OpenMode::OpenOrAttach) return QADS_ERROR;
+            } else {
+                bool live = HasLiveWriter();
+                int status = live ? QADS_ALREADY_EXISTS : QADS_OK;
+                if (mode == OpenMode::OpenOrAttach && !live) {
+                    status = ReattachWriter(buffer_count, buffer_size);
+                    if (status == QADS_OK) return QADS_OK;
+                }
+                munmap(mapped_memory_, total_size_);
+                close(shm_fd_);
+                mapped_memory_ = nullptr;
+                header_ = nullptr;
+                shm_fd_ = -1;
+                restore();
+                if (status != QADS_OK) return status;
+            }
+
+            // Leftover from a writer that is gone: safe to replace
+            UnlinkSegment();
+        }
+        break;
+    }
+
+    if (!CreateSharedMemory()) {
+        return SegmentExists() ? QADS_ALREADY_EXISTS : QADS_ERROR;
+    }
 
     InitializeHeader();
+    header_->generation = generation;
     is_writer_ = true;
     is_attached_ = true;
+    ArmGuardPages();
 
-    // Create semaphores
+    if (!CreateSemaphores()) {
+        std::cerr << "Failed to create semaphores\n";
+        return QADS_ERROR;
+    }
+    Persist(header_, header_->header_size);
+
+    ClaimWriter();
+    return QADS_OK;
+}
+
+int SharedMemoryArena::ReattachWriter(size_t buffer_count, size_t buffer_size) {
+    // The ring must be the one this writer was configured for, not merely one under its name
+    if (header_->version != VERSION || header_->buffer_count != buffer_count ||
+        header_->buffer_size != buffer_size) {
+        std::cerr << "Segment holds " << header_->buffer_count << " buffers of "
+                  << header_->buffer_size << " bytes (version " << header_->version
+                  << "), configured " << buffer_count << " of " << buffer_size
+                  << "; refusing to reattach\n";
+        return QADS_ERROR;
+    }
+
+    is_writer_ = true;
+    ArmGuardPages();
+
+    if (BootChanged()) ForgetPreviousBoot();
+
+    // Semaphores do not persist: a persisted segment reopened after a reboot
+    // (or a janitor sweep) gets new ones counted from its cursors
+    if (!OpenSemaphores() && (backing_path_.empty() || !RestoreSemaphores())) {
+        std::cerr << "Failed to open semaphores\n";
+        is_writer_ = false;
+        return QADS_ERROR;
+    }
+
+    is_attached_ = true;
+    ClaimWriter();
+    return QADS_OK;
+}
+
+void SharedMemoryArena::ClaimWriter() {
+    // The start time goes first: a pid is only ever read together with its own
+    header_->writer_start_time.store(ProcessStartTime(getpid()));
+    header_->writer_pid.store(static_cast<int32_t>(getpid()));
+    header_->writer_active.store(true);
+}
+
+bool SharedMemoryArena::CreateSemaphores() {
     snprintf(header_->write_sem_name, sizeof(header_->write_sem_name),
              "/qads_w_%s", name_.c_str());
     snprintf(header_->read_sem_name, sizeof(header_->read_sem_name),
@@ -46,262 +333,1477 @@ bool SharedMemoryArena::CreateWriter() {
     write_sem_ = sem_open(header_->write_sem_name, O_CREAT | O_EXCL, 0644, buffer_count_);
     read_sem_ = sem_open(header_->read_sem_name, O_CREAT | O_EXCL, 0644, 0);
 
-    if (write_sem_ == SEM_FAILED || read_sem_ == SEM_FAILED) {
-        std::cerr << "Failed to create semaphores\n";
+    return write_sem_ != SEM_FAILED && read_sem_ != SEM_FAILED;
+}
+
+bool SharedMemoryArena::OpenSemaphores() {
+    write_sem_ = sem_open(header_->write_sem_name, 0);
+    read_sem_ = sem_open(header_->read_sem_name, 0);
+
+    return write_sem_ != SEM_FAILED && read_sem_ != SEM_FAILED;
+}
+
+bool SharedMemoryArena::RestoreSemaphores() {
+    if (write_sem_ && write_sem_ != SEM_FAILED) sem_close(write_sem_);
+    if (read_sem_ && read_sem_ != SEM_FAILED) sem_close(read_sem_);
+    sem_unlink(header_->write_sem_name);
+    sem_unlink(header_->read_sem_name);
+
+    // Readers may take every published frame they have not consumed; the writer
+    // every slot not still held by one
+    uint64_t unread = header_->groups_enabled.load()
+        ? 0 : header_->publish_sequence.load() - header_->read_sequence.load();
+    uint64_t free_slots = buffer_count_ - std::min<uint64_t>(Unacked(), buffer_count_);
+
+    write_sem_ = sem_open(header_->write_sem_name, O_CREAT | O_EXCL, 0644, free_slots);
+    read_sem_ = sem_open(header_->read_sem_name, O_CREAT | O_EXCL, 0644, unread);
+
+    return write_sem_ != SEM_FAILED && read_sem_ != SEM_FAILED;
+}
+
+bool SharedMemoryArena::BootChanged() const {
+    char boot_id[BOOT_ID_SIZE];
+    ReadBootId(boot_id);
+    return strncmp(boot_id, header_->boot_id, BOOT_ID_SIZE) != 0;
+}
+
+void SharedMemoryArena::ForgetPreviousBoot() {
+    // Every process listed here died with the old kernel, possibly mid-way through
+    // holding a spinlock; pids may since have been handed to unrelated processes
+    header_->groups_lock.store(0);
+    header_->meta_lock.store(0);
+    header_->readers_lock.store(0);
+    header_->reader_count.store(0);
+    header_->futex_waiters.store(0);
+    for (size_t i = 0; i < MAX_READERS; ++i) {
+        header_->readers[i].pid.store(0);
+    }
+    for (size_t i = 0; i < MAX_CONSUMER_GROUPS; ++i) {
+        header_->groups[i].members.store(0);
+    }
+    ReadBootId(header_->boot_id);
+    Persist(header_, header_->header_size);
+}
+
+bool SharedMemoryArena::SegmentExists() const {
+    if (!backing_path_.empty()) {
+        // The backing file or device is always there; it holds a segment once a header is written
+        int fd = open(backing_path_.c_str(), O_RDONLY);
+        if (fd == -1) return false;
+
+        struct stat st{};
+        bool device = fstat(fd, &st) == 0 && S_ISCHR(st.st_mode);
+        size_t length = device ? total_size_ : sizeof(SharedMemoryHeader);
+        bool exists = false;
+        if (device || static_cast<size_t>(st.st_size) >= length) {
+            void* mapped = mmap(nullptr, length, PROT_READ, MAP_SHARED, fd, 0);
+            if (mapped != MAP_FAILED) {
+                auto header = static_cast<const SharedMemoryHeader*>(mapped);
+                exists = header->magic == MAGIC_NUMBER && header->version == VERSION;
+                munmap(mapped, length);
+            }
+        }
+        close(fd);
+        return exists;
+    }
+
+    std::string shm_name = "/qads_" + name_;
+    int fd = shm_open(shm_name.c_str(), O_RDONLY, 0);
+    if (fd == -1) return false;
+    close(fd);
+    return true;
+}
+
+bool SharedMemoryArena::HasLiveWriter() const {
+    if (!header_ || BootChanged()) return false;
+    return WriterAlive(header_);
+}
+
+bool SharedMemoryArena::InspectSegment(const std::string& name, SegmentStatus* status) {
+    std::string shm_name = "/qads_" + name;
+    int fd = shm_open(shm_name.c_str(), O_RDONLY, 0);
+    if (fd == -1) return false;
+
+    struct stat st;
+    if (fstat(fd, &st) == -1 || static_cast<size_t>(st.st_size) < sizeof(SharedMemoryHeader)) {
+        close(fd);
         return false;
     }
 
-    header_->writer_active.store(true);
-    return true;
+    void* mapped = mmap(nullptr, sizeof(SharedMemoryHeader), PROT_READ, MAP_SHARED, fd, 0);
+    close(fd);
+    if (mapped == MAP_FAILED) return false;
+
+    auto header = static_cast<const SharedMemoryHeader*>(mapped);
+    bool valid = header->magic == MAGIC_NUMBER;
+    if (valid) {
+        pid_t pid = static_cast<pid_t>(header->writer_pid.load());
+        bool alive = WriterAlive(header);
+
+        status->total_bytes = static_cast<uint64_t>(st.st_size);
+        status->created_at_us = header->created_at_us;
+        status->generation = header->generation;
+        status->writer_pid = static_cast<int32_t>(pid);
+        status->writer_alive = alive ? 1 : 0;
+        status->reader_count = header->reader_count.load();
+        status->max_readers = header->max_readers.load();
+    }
+    munmap(mapped, sizeof(SharedMemoryHeader));
+    return valid;
+}
+
+void SharedMemoryArena::RemoveSegment(const std::string& name) {
+    shm_unlink(("/qads_" + name).c_str());
+    sem_unlink(("/qads_w_" + name).c_str());
+    sem_unlink(("/qads_r_" + name).c_str());
+}
+
+void SharedMemoryArena::UnlinkSegment() {
+    std::string shm_name = "/qads_" + name_;
+    std::string write_sem_name = "/qads_w_" + name_;
+    std::string read_sem_name = "/qads_r_" + name_;
+
+    // A persistent backing is reinitialized in place instead
+    if (backing_path_.empty()) shm_unlink(shm_name.c_str());
+    sem_unlink(write_sem_name.c_str());
+    sem_unlink(read_sem_name.c_str());
+}
+
+bool SharedMemoryArena::AttachReader() {
+    return TryAttachReader() == QADS_OK;
+}
+
+int SharedMemoryArena::TryAttachReader() {
+    if (is_attached_) return QADS_ERROR;
+
+    if (!AttachSharedMemory()) return QADS_ERROR;
+
+    is_writer_ = false;
+    is_attached_ = true;
+    if (protection_ & PROTECT_MEMORY) {
+        ProtectRange(header_->buffers_offset, total_size_ - header_->buffers_offset, PROT_READ);
+    }
+    ArmGuardPages();
+
+    // Open existing semaphores
+    if (!OpenSemaphores()) {
+        std::cerr << "Failed to open semaphores\n";
+        return QADS_ERROR;
+    }
+
+    header_->reader_count.fetch_add(1);
+    int rc = RegisterReader();
+    if (rc != QADS_OK) Close();
+    return rc;
+}
+
+int SharedMemoryArena::RegisterReader() {
+    LockReaders();
+
+    // Entries of readers that died without detaching are reclaimed, so a crash
+    // does not hold a seat forever
+    int32_t live = 0;
+    int free_idx = -1;
+    for (size_t i = 0; i < MAX_READERS; ++i) {
+        auto& entry = header_->readers[i];
+        pid_t pid = static_cast<pid_t>(entry.pid.load());
+        if (pid > 0 && kill(pid, 0) != 0 && errno != EPERM) {
+            entry.pid.store(0);
+            header_->reader_count.fetch_sub(1);
+            pid = 0;
+        }
+        if (pid > 0) {
+            live++;
+        } else if (free_idx < 0) {
+            free_idx = static_cast<int>(i);
+        }
+    }
+
+    int32_t max_readers = header_->max_readers.load();
+    if (max_readers > 0 && live >= max_readers) {
+        UnlockReaders();
+        return QADS_BUSY;
+    }
+
+    // Past MAX_READERS readers still attach, they are just not listed
+    if (free_idx >= 0) {
+        auto& entry = header_->readers[free_idx];
+        entry.attached_at_us.store(std::chrono::duration_cast<std::chrono::microseconds>(
+            std::chrono::system_clock::now().time_since_epoch()).count());
+        entry.cursor.store(header_->read_sequence.load());
+        entry.pid.store(static_cast<int32_t>(getpid()));
+        reader_idx_ = free_idx;
+    }
+
+    UnlockReaders();
+    return QADS_OK;
+}
+
+void SharedMemoryArena::NoteRead(uint64_t sequence) {
+    last_read_sequence_ = sequence;
+    has_read_ = true;
+    if (reader_idx_ >= 0) header_->readers[reader_idx_].cursor.store(sequence + 1);
+}
+
+bool SharedMemoryArena::CreateSharedMemory() {
+    std::string shm_name = "/qads_" + name_;
+    shm_fd_ = OpenSegment(true);
+
+    if (shm_fd_ == -1) {
+        std::cerr << "Failed to create shared memory: " << strerror(errno) << std::endl;
+        return false;
+    }
+
+    // A devdax device has a fixed size and cannot be truncated
+    struct stat st{};
+    bool device = fstat(shm_fd_, &st) == 0 && S_ISCHR(st.st_mode);
+    if (!device && ftruncate(shm_fd_, total_size_) == -1) {
+        std::cerr << "Failed to set shared memory size\n";
+        close(shm_fd_);
+        if (backing_path_.empty()) shm_unlink(shm_name.c_str());
+        return false;
+    }
+
+    mapped_memory_ = MapSegment();
+
+    if (mapped_memory_ == MAP_FAILED) {
+        std::cerr << "Failed to map shared memory: " << strerror(errno) << std::endl;
+        close(shm_fd_);
+        if (backing_path_.empty()) shm_unlink(shm_name.c_str());
+        return false;
+    }
+
+    header_ = static_cast<SharedMemoryHeader*>(mapped_memory_);
+    ApplyAdvice();
+    return true;
+}
+
+bool SharedMemoryArena::AttachSharedMemory() {
+    shm_fd_ = OpenSegment(false);
+
+    if (shm_fd_ == -1) {
+        std::cerr << "Failed to open shared memory\n";
+        return false;
+    }
+
+    // Get the size first
+    struct stat st;
+    if (fstat(shm_fd_, &st) == -1) {
+        std::cerr << "Failed to get shared memory size\n";
+        close(shm_fd_);
+        return false;
+    }
+
+    // A devdax device reports no size, so it is mapped at the configured size
+    if (!S_ISCHR(st.st_mode)) total_size_ = st.st_size;
+
+    mapped_memory_ = MapSegment();
+
+    if (mapped_memory_ == MAP_FAILED) {
+        std::cerr << "Failed to map shared memory\n";
+        close(shm_fd_);
+        return false;
+    }
+
+    header_ = static_cast<SharedMemoryHeader*>(mapped_memory_);
+
+    // Verify header
+    if (header_->magic != MAGIC_NUMBER || header_->version != VERSION) {
+        std::cerr << "Invalid shared memory header\n";
+        munmap(mapped_memory_, total_size_);
+        close(shm_fd_);
+        return false;
+    }
+    if (header_->total_size != total_size_) {
+        std::cerr << "Segment spans " << header_->total_size << " bytes, mapped "
+                  << total_size_ << "; configure the same size as the writer\n";
+        munmap(mapped_memory_, total_size_);
+        close(shm_fd_);
+        return false;
+    }
+
+    buffer_count_ = header_->buffer_count;
+    buffer_size_ = header_->buffer_size;
+    slot_stride_ = header_->slot_stride;
+    slot_guards_ = header_->slot_guards;
+    max_buffer_count_ = header_->max_buffer_count;
+    geometry_epoch_ = header_->geometry_epoch.load();
+
+    ApplyAdvice();
+    return true;
+}
+
+int SharedMemoryArena::OpenSegment(bool create) const {
+    if (backing_path_.empty()) {
+        std::string shm_name = "/qads_" + name_;
+        return shm_open(shm_name.c_str(), create ? O_CREAT | O_EXCL | O_RDWR : O_RDWR, 0644);
+    }
+    // The backing outlives every process, so creating reuses it in place
+    return open(backing_path_.c_str(), create ? O_CREAT | O_RDWR : O_RDWR, 0644);
+}
+
+void* SharedMemoryArena::MapSegment() {
+    msync_persist_ = false;
+    struct stat st{};
+    if (backing_path_.empty() || (fstat(shm_fd_, &st) == 0 && S_ISCHR(st.st_mode))) {
+        // devdax stores go straight to the media: cache flushes alone persist them
+        return mmap(nullptr, total_size_, PROT_READ | PROT_WRITE, MAP_SHARED, shm_fd_, 0);
+    }
+
+    // fsdax: MAP_SYNC keeps the file's blocks durable, so the same holds for a file
+    void* mapped = mmap(nullptr, total_size_, PROT_READ | PROT_WRITE,
+                        MAP_SHARED_VALIDATE | MAP_SYNC, shm_fd_, 0);
+    if (mapped != MAP_FAILED) return mapped;
+
+    std::cerr << backing_path_ << " is not on a DAX filesystem, persisting with msync\n";
+    msync_persist_ = true;
+    return mmap(nullptr, total_size_, PROT_READ | PROT_WRITE, MAP_SHARED, shm_fd_, 0);
+}
+
+void SharedMemoryArena::Persist(const void* addr, size_t size) const {
+    if (backing_path_.empty() || size == 0) return;
+
+    uintptr_t start = reinterpret_cast<uintptr_t>(addr) & ~(CACHE_LINE_SIZE - 1);
+    uintptr_t end = reinterpret_cast<uintptr_t>(addr) + size;
+    if (!msync_persist_) {
+#if defined(__x86_64__) || defined(__i386__)
+        for (uintptr_t line = start; line < end; line += CACHE_LINE_SIZE) {
+            _mm_clflush(reinterpret_cast<const void*>(line));
+        }
+        _mm_sfence();
+        return;
+#elif defined(__aarch64__)
+        for (uintptr_t line = start; line < end; line += CACHE_LINE_SIZE) {
+            asm volatile("dc cvac, %0" : : "r"(line) : "memory");
+        }
+        asm volatile("dsb sy" : : : "memory");
+        return;
+#endif
+    }
+
+    uintptr_t page_start = start & ~(PageSize() - 1);
+    if (msync(reinterpret_cast<void*>(page_start), end - page_start, MS_SYNC) != 0) {
+        std::cerr << "Failed to persist " << backing_path_ << ": " << strerror(errno) << std::endl;
+    }
+}
+
+void SharedMemoryArena::InitializeHeader() {
+    // Use placement new for proper initialization
+    new (header_) SharedMemoryHeader();
+
+    // Initialize the flexible array member
+    for (size_t i = 0; i < max_buffer_count_; ++i) {
+        new (&header_->buffer_states[i]) SharedMemoryHeader::BufferState();
+    }
+
+    header_->magic = MAGIC_NUMBER;
+    header_->version = VERSION;
+    header_->total_size = total_size_;
+    header_->header_size = sizeof(SharedMemoryHeader) +
+                          sizeof(SharedMemoryHeader::BufferState) * max_buffer_count_;
+
+    header_->buffer_count = buffer_count_;
+    header_->buffer_size = buffer_size_;
+    header_->max_buffer_count = max_buffer_count_;
+    header_->buffers_offset = BuffersOffset();
+    header_->slot_stride = slot_stride_;
+    header_->slot_guards = slot_guards_;
+
+    header_->schema_offset = 0;
+    header_->schema_size = 0;
+
+    header_->writer_pid.store(0);
+    header_->created_at_us = std::chrono::duration_cast<std::chrono::microseconds>(
+        std::chrono::system_clock::now().time_since_epoch()).count();
+    ReadBootId(header_->boot_id);
+
+    for (size_t i = 0; i < MAX_CONSUMER_GROUPS; ++i) {
+        memset(header_->groups[i].name, 0, GROUP_NAME_SIZE);
+    }
+    for (size_t i = 0; i < MAX_META_ENTRIES; ++i) {
+        memset(header_->meta[i].key, 0, META_KEY_SIZE);
+        memset(header_->meta[i].value, 0, META_VALUE_SIZE);
+    }
+
+    // Initialize buffer states
+    for (size_t i = 0; i < buffer_count_; ++i) {
+        header_->buffer_states[i].data_size.store(0);
+        header_->buffer_states[i].ready.store(false);
+        header_->buffer_states[i].timestamp.store(0);
+    }
+}
+
+arrow::Status SharedMemoryArena::WriteTable(const std::shared_ptr<arrow::Table>& table) {
+    // Convert table to record batch
+    auto batches_result = table->CombineChunksToBatch();
+    if (!batches_result.ok()) {
+        return batches_result.status();
+    }
+    return WriteRecordBatch(batches_result.ValueOrDie());
+}
+
+arrow::Status SharedMemoryArena::WriteRecordBatch(
+    const std::shared_ptr<arrow::RecordBatch>& batch) {
+
+    if (!is_writer_ || !is_attached_) {
+        return arrow::Status::Invalid("Not attached as writer");
+    }
+
+    // Wait for available write buffer
+    if (sem_wait(write_sem_) != 0) {
+        return arrow::Status::IOError("Failed to wait for write semaphore");
+    }
+
+    size_t buffer_idx = GetNextWriteBuffer();
+    uint8_t* buffer = BufferAt(buffer_idx);
+    BeginBufferWrite(buffer_idx);
+
+    size_t serialized_size;
+    auto status = SerializeRecordBatch(batch, buffer, buffer_size_, &serialized_size);
+    if (!status.ok()) {
+        sem_post(write_sem_); // Give the slot back
+        return status;
+    }
+
+    PublishBuffer(buffer_idx, serialized_size);
+    return arrow::Status::OK();
+}
+
+arrow::Result<std::shared_ptr<arrow::RecordBatch>> SharedMemoryArena::ReadRecordBatch(
+    int timeout_ms) {
+
+    if (is_writer_ || !is_attached_) {
+        return arrow::Status::Invalid("Not attached as reader");
+    }
+
+    int rc = WaitReadable(timeout_ms);
+    if (rc == QADS_TIMEOUT) {
+        return arrow::Status::IOError("Timeout waiting for data");
+    }
+    if (rc != QADS_OK) {
+        return arrow::Status::IOError("Failed to wait for read semaphore");
+    }
+
+    SyncGeometry();
+
+    size_t buffer_idx = GetCurrentReadBuffer();
+
+    if (!header_->buffer_states[buffer_idx].ready.load()) {
+        sem_post(write_sem_); // Return write token
+        return arrow::Status::IOError("Buffer not ready");
+    }
+
+    const uint8_t* buffer = BufferAt(buffer_idx);
+    size_t data_size = header_->buffer_states[buffer_idx].data_size.load();
+
+    auto result = DeserializeRecordBatch(buffer, data_size);
+
+    ReleaseBuffer(buffer_idx);
+
+    if (result.ok()) {
+        stats_.bytes_read += data_size;
+        stats_.reads_count++;
+    }
+
+    return result;
+}
+
+int SharedMemoryArena::WriteBytes(const uint8_t* data, size_t size) {
+    if (!is_writer_ || !is_attached_) return QADS_ERROR;
+    if (size > buffer_size_) return QADS_ERROR;
+
+    size_t capacity = 0;
+    uint8_t* buffer = AcquireWriteBuffer(&capacity);
+    if (!buffer) return QADS_ERROR;
+
+    std::memcpy(buffer, data, size);
+    return PublishAcquired(size);
+}
+
+uint8_t* SharedMemoryArena::AcquireWriteBuffer(size_t* capacity) {
+    if (!is_writer_ || !is_attached_ || acquired_idx_ >= 0) return nullptr;
+
+    // A batch can never span more slots than the ring holds
+    if (batch_open_ && batch_pending_ >= buffer_count_) return nullptr;
+
+    if (header_->groups_enabled.load()) {
+        WaitForGroupSpace();
+    } else if (sem_wait(write_sem_) != 0) {
+        return nullptr;
+    }
+
+    size_t buffer_idx = GetNextWriteBuffer();
+    BeginBufferWrite(buffer_idx);
+
+    acquired_idx_ = static_cast<int64_t>(buffer_idx);
+    *capacity = buffer_size_;
+    return BufferAt(buffer_idx);
+}
+
+int SharedMemoryArena::PublishAcquired(size_t size) {
+    if (acquired_idx_ < 0 || size > buffer_size_) return QADS_ERROR;
+
+    // The frame ran past its slot: drop it rather than hand readers a torn neighbour
+    if (!CanaryIntact(static_cast<size_t>(acquired_idx_))) {
+        ReleaseAcquired();
+        return QADS_OVERRUN;
+    }
+
+    PublishBuffer(static_cast<size_t>(acquired_idx_), size);
+    acquired_idx_ = -1;
+    return QADS_OK;
+}
+
+int SharedMemoryArena::ReleaseAcquired() {
+    if (acquired_idx_ < 0) return QADS_ERROR;
+
+    // The sequence was never advanced; only the write token goes back
+    ProtectBuffer(static_cast<size_t>(acquired_idx_), false);
+    if (!header_->groups_enabled.load()) {
+        sem_post(write_sem_);
+    }
+    acquired_idx_ = -1;
+    return QADS_OK;
+}
+
+int SharedMemoryArena::ReadBytes(uint8_t* data, size_t max_size, size_t* actual_size,
+                                 int timeout_ms) {
+    if (is_writer_ || !is_attached_) return QADS_ERROR;
+
+    if (seeking_) {
+        return ReadSeekBytes(data, max_size, actual_size, timeout_ms);
+    }
+
+    if (header_->groups_enabled.load()) {
+        if (group_idx_ < 0 && JoinGroup("default") != QADS_OK) return QADS_ERROR;
+        return ReadGroupBytes(data, max_size, actual_size, timeout_ms);
+    }
+
+    int rc = WaitReadable(timeout_ms);
+    if (rc != QADS_OK) return rc;
+
+    SyncGeometry();
+
+    size_t buffer_idx = GetCurrentReadBuffer();
+    auto& state = header_->buffer_states[buffer_idx];
+
+    if (!state.ready.load()) {
+        sem_post(write_sem_); // Return write token
+        return QADS_ERROR;
+    }
+
+    size_t data_size = state.data_size.load();
+    if (data_size > max_size) {
+        sem_post(read_sem_); // Leave the frame for a caller with a larger buffer
+        return QADS_ERROR;
+    }
+
+    int copied = CopyOut(data, buffer_idx, data_size);
+    *actual_size = data_size;
+
+    ReleaseBuffer(buffer_idx);
+    if (copied != QADS_OK) return copied;
+
+    stats_.bytes_read += data_size;
+    stats_.reads_count++;
+    return QADS_OK;
+}
+
+int SharedMemoryArena::BeginBatch() {
+    if (!is_writer_ || !is_attached_ || batch_open_) return QADS_ERROR;
+    batch_open_ = true;
+    batch_pending_ = 0;
+    return QADS_OK;
+}
+
+int SharedMemoryArena::CommitBatch() {
+    if (!batch_open_) return QADS_ERROR;
+
+    header_->publish_sequence.store(header_->write_sequence.load());
+    Persist(&header_->publish_sequence, sizeof(header_->publish_sequence));
+
+    if (!header_->groups_enabled.load()) {
+        for (size_t i = 0; i < batch_pending_; ++i) {
+            sem_post(read_sem_);
+        }
+    }
+    WakeFutexWaiters();
+
+    batch_open_ = false;
+    batch_pending_ = 0;
+    return QADS_OK;
+}
+
+int SharedMemoryArena::AbortBatch() {
+    if (!batch_open_) return QADS_ERROR;
+
+    // Nothing was signalled yet, so rewinding the sequence hides the staged frames
+    for (size_t i = 0; i < batch_pending_; ++i) {
+        uint64_t sequence = header_->write_sequence.fetch_sub(1) - 1;
+        header_->buffer_states[sequence % buffer_count_].ready.store(false);
+        if (!header_->groups_enabled.load()) {
+            sem_post(write_sem_);
+        }
+    }
+
+    batch_open_ = false;
+    batch_pending_ = 0;
+    return QADS_OK;
+}
+
+int SharedMemoryArena::Seek(SeekOrigin origin, uint64_t sequence) {
+    if (is_writer_ || !header_) return QADS_ERROR;
+    SyncGeometry();
+
+    uint64_t first, next;
+    RetainedRange(&first, &next);
+
+    switch (origin) {
+    case SeekOrigin::Sequence:
+        if (sequence < first || sequence > next) return QADS_ERROR;
+        seek_cursor_ = sequence;
+        break;
+    case SeekOrigin::Latest:
+        seek_cursor_ = next;
+        break;
+    case SeekOrigin::Earliest:
+        seek_cursor_ = first;
+        break;
+    }
+
+    seeking_ = true;
+    if (reader_idx_ >= 0) header_->readers[reader_idx_].cursor.store(seek_cursor_);
+    return QADS_OK;
+}
+
+void SharedMemoryArena::RetainedRange(uint64_t* first, uint64_t* next) const {
+    *next = header_ ? header_->publish_sequence.load() : 0;
+    *first = *next > buffer_count_ ? *next - buffer_count_ : 0;
+
+    uint64_t retention_us = header_ ? header_->retention_us.load() : 0;
+    if (retention_us == 0) return;
+
+    uint64_t now = std::chrono::duration_cast<std::chrono::microseconds>(
+        std::chrono::system_clock::now().time_since_epoch()).count();
+    uint64_t cutoff = now > retention_us ? now - retention_us : 0;
+
+    // Drop frames that fell out of the time window
+    while (*first < *next) {
+        auto& state = header_->buffer_states[*first % buffer_count_];
+        if (state.sequence.load() == *first && state.timestamp.load() >= cutoff) break;
+        ++*first;
+    }
+}
+
+void SharedMemoryArena::SetRetention(uint64_t retention_us) {
+    if (!is_writer_ || !header_) return;
+    header_->retention_us.store(retention_us);
+}
+
+int SharedMemoryArena::SeekTime(uint64_t timestamp_us) {
+    if (is_writer_ || !header_) return QADS_ERROR;
+    SyncGeometry();
+
+    uint64_t first, next;
+    RetainedRange(&first, &next);
+
+    // Timestamps grow with sequence, so the first match is the resume point
+    seek_cursor_ = next;
+    for (uint64_t sequence = first; sequence < next; ++sequence) {
+        auto& state = header_->buffer_states[sequence % buffer_count_];
+        if (state.sequence.load() == sequence && state.timestamp.load() >= timestamp_us) {
+            seek_cursor_ = sequence;
+            break;
+        }
+    }
+
+    seeking_ = true;
+    if (reader_idx_ >= 0) header_->readers[reader_idx_].cursor.store(seek_cursor_);
+    return QADS_OK;
+}
+
+int SharedMemoryArena::ReadSeekBytes(uint8_t* data, size_t max_size, size_t* actual_size,
+                                     int timeout_ms) {
+    auto deadline = std::chrono::steady_clock::now() + std::chrono::milliseconds(timeout_ms);
+    int backoff_us = 1;
+
+    while (true) {
+        uint64_t next = header_->publish_sequence.load();
+        SyncGeometry();
+
+        if (seek_cursor_ < next) {
+            size_t buffer_idx = seek_cursor_ % buffer_count_;
+            auto& state = header_->buffer_states[buffer_idx];
+
+            // Seqlock-style read: the slot must hold our frame before and after the copy
+            uint64_t stamp = state.sequence.load();
+            size_t data_size = state.data_size.load();
+            if (stamp == seek_cursor_ && data_size <= max_size) {
+                int copied = CopyOut(data, buffer_idx, data_size);
+                if (copied != QADS_OK) {
+                    NoteRead(seek_cursor_++);
+                    return copied;
+                }
+                if (state.sequence.load() == stamp) {
+                    *actual_size = data_size;
+                    NoteRead(seek_cursor_++);
+                    stats_.bytes_read += data_size;
+                    stats_.reads_count++;
+                    return QADS_OK;
+                }
+            } else if (stamp == seek_cursor_) {
+                return QADS_ERROR; // Caller's buffer is too small
+            }
+
+            // The writer lapped us: continue from the oldest frame still retained
+            stats_.lapped++;
+            seek_cursor_ = next > buffer_count_ ? next - buffer_count_ + 1 : 0;
+            continue;
+        }
+
+        if (timeout_ms >= 0 && std::chrono::steady_clock::now() >= deadline) {
+            stats_.wait_timeouts++;
+            return QADS_TIMEOUT;
+        }
+
+        std::this_thread::sleep_for(std::chrono::microseconds(backoff_us));
+        backoff_us = std::min(backoff_us * 2, 1000);
+    }
+}
+
+int SharedMemoryArena::PeekSize(size_t* size, int timeout_ms) {
+    if (is_writer_ || !is_attached_) return QADS_ERROR;
+
+    if (!seeking_ && !header_->groups_enabled.load()) {
+        int rc = WaitReadable(timeout_ms);
+        if (rc != QADS_OK) return rc;
+
+        SyncGeometry();
+        *size = header_->buffer_states[GetCurrentReadBuffer()].data_size.load();
+        sem_post(read_sem_); // The frame stays for the ReadBytes call that follows
+        return QADS_OK;
+    }
+
+    if (header_->groups_enabled.load() && !seeking_ && group_idx_ < 0 && JoinGroup("default") != QADS_OK) {
+        return QADS_ERROR;
+    }
+
+    auto deadline = std::chrono::steady_clock::now() + std::chrono::milliseconds(timeout_ms);
+    int backoff_us = 1;
+
+    while (true) {
+        uint64_t cursor = seeking_ ? seek_cursor_ : header_->groups[group_idx_].cursor.load();
+        SyncGeometry();
+
+        if (cursor < header_->publish_sequence.load()) {
+            auto& state = header_->buffer_states[cursor % buffer_count_];
+            // A lapped frame is replaced by one at most a slot long
+            *size = state.sequence.load() == cursor ? state.data_size.load() : buffer_size_;
+            return QADS_OK;
+        }
+
+        if (timeout_ms >= 0 && std::chrono::steady_clock::now() >= deadline) {
+            stats_.wait_timeouts++;
+            return QADS_TIMEOUT;
+        }
+
+        std::this_thread::sleep_for(std::chrono::microseconds(backoff_us));
+        backoff_us = std::min(backoff_us * 2, 1000);
+    }
+}
+
+bool SharedMemoryArena::HasData() {
+    if (is_writer_ || !is_attached_) return false;
+
+    uint64_t published = header_->publish_sequence.load();
+    if (seeking_) {
+        return seek_cursor_ < published;
+    }
+
+    if (header_->groups_enabled.load()) {
+        if (group_idx_ < 0 && JoinGroup("default") != QADS_OK) return false;
+        return header_->groups[group_idx_].cursor.load() < published;
+    }
+
+    int value = 0;
+    return sem_getvalue(read_sem_, &value) == 0 && value > 0;
+}
+
+bool SharedMemoryArena::LastReadSequence(uint64_t* sequence) const {
+    if (!has_read_) return false;
+    *sequence = last_read_sequence_;
+    return true;
+}
+
+void SharedMemoryArena::SetAuth(const uint8_t* nonce, const uint8_t* tag) {
+    if (!is_writer_ || !header_) return;
+
+    // Readers attaching in between see no token rather than a half-written one
+    header_->auth_required.store(0);
+    if (!nonce || !tag) return;
+    memcpy(header_->auth_nonce, nonce, AUTH_NONCE_SIZE);
+    memcpy(header_->auth_tag, tag, AUTH_TAG_SIZE);
+    header_->auth_required.store(1, std::memory_order_release);
+}
+
+bool SharedMemoryArena::GetAuth(uint8_t* nonce, uint8_t* tag) const {
+    if (!header_ || header_->auth_required.load(std::memory_order_acquire) == 0) return false;
+    memcpy(nonce, header_->auth_nonce, AUTH_NONCE_SIZE);
+    memcpy(tag, header_->auth_tag, AUTH_TAG_SIZE);
+    return true;
+}
+
+int SharedMemoryArena::PutMeta(const std::string& key, const char* value) {
+    if (!header_ || key.empty() || key.size() >= META_KEY_SIZE) return QADS_ERROR;
+    if (value && strlen(value) >= META_VALUE_SIZE) return QADS_ERROR;
+
+    LockMeta();
+
+    int found = -1;
+    int free_idx = -1;
+    for (size_t i = 0; i < MAX_META_ENTRIES; ++i) {
+        auto& entry = header_->meta[i];
+        if (entry.in_use.load() && strncmp(entry.key, key.c_str(), META_KEY_SIZE) == 0) {
+            found = static_cast<int>(i);
+            break;
+        }
+        if (!entry.in_use.load() && free_idx < 0) {
+            free_idx = static_cast<int>(i);
+        }
+    }
+
+    int rc = QADS_OK;
+    if (!value) {
+        if (found >= 0) header_->meta[found].in_use.store(0);
+    } else if (found < 0 && free_idx < 0) {
+        rc = QADS_ERROR;
+    } else {
+        auto& entry = header_->meta[found >= 0 ? found : free_idx];
+        snprintf(entry.key, sizeof(entry.key), "%s", key.c_str());
+        snprintf(entry.value, sizeof(entry.value), "%s", value);
+        entry.in_use.store(1);
+    }
+
+    UnlockMeta();
+    return rc;
+}
+
+int SharedMemoryArena::GetMeta(const std::string& key, char* value, size_t max_size) const {
+    if (!header_ || max_size == 0) return QADS_ERROR;
+
+    LockMeta();
+    int rc = 0;
+    for (size_t i = 0; i < MAX_META_ENTRIES; ++i) {
+        const auto& entry = header_->meta[i];
+        if (entry.in_use.load() && strncmp(entry.key, key.c_str(), META_KEY_SIZE) == 0) {
+            snprintf(value, max_size, "%s", entry.value);
+            rc = 1;
+            break;
+        }
+    }
+    UnlockMeta();
+    return rc;
+}
+
+int SharedMemoryArena::MetaKeyAt(size_t index, char* key) const {
+    if (!header_ || index >= MAX_META_ENTRIES) return QADS_ERROR;
+
+    LockMeta();
+    const auto& entry = header_->meta[index];
+    int rc = entry.in_use.load() ? 1 : 0;
+    if (rc) memcpy(key, entry.key, META_KEY_SIZE);
+    UnlockMeta();
+    return rc;
+}
+
+void SharedMemoryArena::SetAckMode(bool enabled) {
+    if (!is_writer_ || !header_) return;
+    header_->ack_sequence.store(header_->read_sequence.load());
+    header_->ack_mode.store(enabled);
+}
+
+int SharedMemoryArena::Ack(uint64_t sequence) {
+    if (is_writer_ || !header_) return QADS_ERROR;
+    if (!header_->ack_mode.load()) return QADS_OK; // Buffers are already recycled on read
+
+    // Only frames that have been consumed can be acknowledged
+    if (sequence >= header_->read_sequence.load()) return QADS_ERROR;
+
+    uint64_t acked = header_->ack_sequence.load();
+    while (acked <= sequence) {
+        if (header_->ack_sequence.compare_exchange_weak(acked, sequence + 1)) {
+            // Acks are cumulative: hand every covered slot back to the writer
+            Persist(&header_->ack_sequence, sizeof(header_->ack_sequence));
+            for (uint64_t i = acked; i <= sequence; ++i) {
+                sem_post(write_sem_);
+            }
+            break;
+        }
+    }
+    return QADS_OK;
+}
+
+uint64_t SharedMemoryArena::Unacked() const {
+    if (!header_) return 0;
+    uint64_t written = header_->write_sequence.load();
+    uint64_t done = header_->ack_mode.load() ? header_->ack_sequence.load()
+                                             : header_->read_sequence.load();
+    if (header_->groups_enabled.load() && !MinGroupCursor(&done)) {
+        done = written;
+    }
+    return written > done ? written - done : 0;
+}
+
+MemoryReport SharedMemoryArena::GetMemoryReport() const {
+    MemoryReport report{};
+    if (!header_) return report;
+
+    report.total_bytes = header_->total_size;
+    report.header_bytes = header_->buffers_offset;
+    report.buffer_count = buffer_count_;
+    report.buffer_bytes = buffer_size_;
+    report.used_buffers = std::min<uint64_t>(Unacked(), buffer_count_);
+    report.free_buffers = buffer_count_ - report.used_buffers;
+    report.high_water_bytes = header_->high_water_bytes.load();
+    report.high_water_buffers = header_->high_water_buffers.load();
+
+    // Every slot has the same size, so any free slot takes the largest frame
+    report.largest_writable_bytes = report.free_buffers > 0 ? buffer_size_ : 0;
+    return report;
 }
 
-bool SharedMemoryArena::AttachReader() {
-    if (is_attached_) return false;
+int SharedMemoryArena::DumpHeader(HeaderDump* dump) const {
+    if (!header_) return QADS_ERROR;
+
+    *dump = HeaderDump{};
+    dump->magic = header_->magic;
+    dump->version = header_->version;
+    dump->total_size = header_->total_size;
+    dump->header_size = header_->buffers_offset;
+    dump->buffer_count = header_->buffer_count;
+    dump->buffer_size = header_->buffer_size;
+    dump->max_buffer_count = header_->max_buffer_count;
+    dump->geometry_epoch = header_->geometry_epoch.load();
+    dump->write_sequence = header_->write_sequence.load();
+    dump->publish_sequence = header_->publish_sequence.load();
+    dump->read_sequence = header_->read_sequence.load();
+    dump->ack_sequence = header_->ack_sequence.load();
+    dump->retention_us = header_->retention_us.load();
+    dump->created_at_us = header_->created_at_us;
+    dump->generation = header_->generation;
+    dump->last_read_sequence = last_read_sequence_;
+    dump->writer_active = header_->writer_active.load() ? 1 : 0;
+    dump->writer_pid = header_->writer_pid.load();
+    dump->reader_count = header_->reader_count.load();
+    dump->ack_mode = header_->ack_mode.load() ? 1 : 0;
+    dump->auth_required = header_->auth_required.load();
+    dump->groups_enabled = header_->groups_enabled.load() ? 1 : 0;
+    dump->groups_lock = header_->groups_lock.load();
+    dump->meta_lock = header_->meta_lock.load();
+    dump->futex_waiters = header_->futex_waiters.load();
+
+    int value = -1;
+    dump->write_sem_value = write_sem_ && sem_getvalue(write_sem_, &value) == 0 ? value : -1;
+    value = -1;
+    dump->read_sem_value = read_sem_ && sem_getvalue(read_sem_, &value) == 0 ? value : -1;
+    dump->last_read_valid = has_read_ ? 1 : 0;
+    dump->max_readers = header_->max_readers.load();
+    return QADS_OK;
+}
 
-    if (!AttachSharedMemory()) return false;
+int SharedMemoryArena::DumpSlot(size_t index, SlotDump* dump) const {
+    if (!header_) return QADS_ERROR;
+    if (index >= header_->buffer_count) return 0;
+
+    const auto& state = header_->buffer_states[index];
+    *dump = SlotDump{};
+    dump->sequence = state.sequence.load();
+    dump->data_size = state.data_size.load();
+    dump->timestamp = state.timestamp.load();
+    dump->ready = state.ready.load() ? 1 : 0;
+    return 1;
+}
 
-    is_writer_ = false;
-    is_attached_ = true;
+int SharedMemoryArena::DumpGroup(size_t index, GroupDump* dump) const {
+    if (!header_ || index >= MAX_CONSUMER_GROUPS) return QADS_ERROR;
+
+    const auto& state = header_->groups[index];
+    if (!state.in_use.load()) return 0;
+    *dump = GroupDump{};
+    memcpy(dump->name, state.name, GROUP_NAME_SIZE);
+    dump->name[GROUP_NAME_SIZE - 1] = '\0';
+    dump->cursor = state.cursor.load();
+    dump->members = state.members.load();
+    return 1;
+}
 
-    // Open existing semaphores
-    write_sem_ = sem_open(header_->write_sem_name, 0);
-    read_sem_ = sem_open(header_->read_sem_name, 0);
+int SharedMemoryArena::DumpReader(size_t index, ReaderDump* dump) const {
+    if (!header_ || index >= MAX_READERS) return QADS_ERROR;
+
+    const auto& entry = header_->readers[index];
+    pid_t pid = static_cast<pid_t>(entry.pid.load());
+    if (pid <= 0) return 0;
+    *dump = ReaderDump{};
+    dump->cursor = entry.cursor.load();
+    dump->attached_at_us = entry.attached_at_us.load();
+    dump->pid = static_cast<int32_t>(pid);
+    dump->alive = kill(pid, 0) == 0 || errno == EPERM ? 1 : 0;
+    return 1;
+}
 
-    if (write_sem_ == SEM_FAILED || read_sem_ == SEM_FAILED) {
-        std::cerr << "Failed to open semaphores\n";
-        return false;
-    }
+void SharedMemoryArena::SetMaxReaders(int32_t max_readers) {
+    if (!is_writer_ || !header_) return;
+    header_->max_readers.store(max_readers);
+}
 
-    header_->reader_count.fetch_add(1);
-    return true;
+void SharedMemoryArena::EnableConsumerGroups() {
+    if (!is_writer_ || !header_) return;
+    header_->groups_enabled.store(true);
 }
 
-bool SharedMemoryArena::CreateSharedMemory() {
-    std::string shm_name = "/qads_" + name_;
-    shm_fd_ = shm_open(shm_name.c_str(), O_CREAT | O_EXCL | O_RDWR, 0644);
+int SharedMemoryArena::JoinGroup(const std::string& group) {
+    if (is_writer_ || !header_) return QADS_ERROR;
+    if (group.empty() || group.size() >= GROUP_NAME_SIZE) return QADS_ERROR;
+    if (group_idx_ >= 0) return QADS_ERROR;
 
-    if (shm_fd_ == -1) {
-        std::cerr << "Failed to create shared memory: " << strerror(errno) << std::endl;
-        return false;
+    LockGroups();
+
+    int found = -1;
+    int free_idx = -1;
+    for (size_t i = 0; i < MAX_CONSUMER_GROUPS; ++i) {
+        auto& state = header_->groups[i];
+        if (state.in_use.load() && strncmp(state.name, group.c_str(), GROUP_NAME_SIZE) == 0) {
+            found = static_cast<int>(i);
+            break;
+        }
+        if (!state.in_use.load() && free_idx < 0) {
+            free_idx = static_cast<int>(i);
+        }
     }
 
-    if (ftruncate(shm_fd_, total_size_) == -1) {
-        std::cerr << "Failed to set shared memory size\n";
-        close(shm_fd_);
-        shm_unlink(shm_name.c_str());
-        return false;
+    uint64_t head = header_->publish_sequence.load();
+
+    if (found < 0) {
+        if (free_idx < 0) {
+            UnlockGroups();
+            return QADS_ERROR;
+        }
+        // New groups start at the live edge
+        auto& state = header_->groups[free_idx];
+        snprintf(state.name, sizeof(state.name), "%s", group.c_str());
+        state.cursor.store(head);
+        state.members.store(0);
+        state.in_use.store(1);
+        found = free_idx;
     }
 
-    mapped_memory_ = mmap(nullptr, total_size_, PROT_READ | PROT_WRITE,
-                         MAP_SHARED, shm_fd_, 0);
+    auto& state = header_->groups[found];
 
-    if (mapped_memory_ == MAP_FAILED) {
-        std::cerr << "Failed to map shared memory\n";
-        close(shm_fd_);
-        shm_unlink(shm_name.c_str());
-        return false;
+    // A dormant group resumes from its cursor, or the oldest frame still in the ring
+    if (state.members.load() == 0 && head - state.cursor.load() >= buffer_count_) {
+        state.cursor.store(head - buffer_count_ + 1);
     }
+    state.members.fetch_add(1);
 
-    header_ = static_cast<SharedMemoryHeader*>(mapped_memory_);
-    return true;
+    UnlockGroups();
+
+    group_idx_ = found;
+    return QADS_OK;
 }
 
-bool SharedMemoryArena::AttachSharedMemory() {
-    std::string shm_name = "/qads_" + name_;
-    shm_fd_ = shm_open(shm_name.c_str(), O_RDWR, 0644);
+int SharedMemoryArena::ReadGroupBytes(uint8_t* data, size_t max_size, size_t* actual_size,
+                                      int timeout_ms) {
+    auto& group = header_->groups[group_idx_];
+    auto deadline = std::chrono::steady_clock::now() + std::chrono::milliseconds(timeout_ms);
+    int backoff_us = 1;
+
+    while (true) {
+        uint64_t cursor = group.cursor.load();
+        SyncGeometry();
+
+        if (cursor < header_->publish_sequence.load()) {
+            // The writer never laps the slowest group, so this slot is stable until we advance
+            size_t buffer_idx = cursor % buffer_count_;
+            size_t data_size = header_->buffer_states[buffer_idx].data_size.load();
+            if (data_size > max_size) return QADS_ERROR;
+
+            int copied = CopyOut(data, buffer_idx, data_size);
+
+            if (group.cursor.compare_exchange_strong(cursor, cursor + 1)) {
+                Persist(&group.cursor, sizeof(group.cursor));
+                if (copied != QADS_OK) return copied;
+                *actual_size = data_size;
+                NoteRead(cursor);
+                stats_.bytes_read += data_size;
+                stats_.reads_count++;
+                return QADS_OK;
+            }
+            continue; // Another member of the group claimed it first
+        }
 
-    if (shm_fd_ == -1) {
-        std::cerr << "Failed to open shared memory\n";
-        return false;
-    }
+        if (timeout_ms >= 0 && std::chrono::steady_clock::now() >= deadline) {
+            stats_.wait_timeouts++;
+            return QADS_TIMEOUT;
+        }
 
-    // Get the size first
-    struct stat st;
-    if (fstat(shm_fd_, &st) == -1) {
-        std::cerr << "Failed to get shared memory size\n";
-        close(shm_fd_);
-        return false;
+        std::this_thread::sleep_for(std::chrono::microseconds(backoff_us));
+        backoff_us = std::min(backoff_us * 2, 1000);
     }
+}
 
-    total_size_ = st.st_size;
+void SharedMemoryArena::WaitForGroupSpace() {
+    int backoff_us = 1;
 
-    mapped_memory_ = mmap(nullptr, total_size_, PROT_READ | PROT_WRITE,
-                         MAP_SHARED, shm_fd_, 0);
+    while (true) {
+        uint64_t next = header_->write_sequence.load();
+        uint64_t slowest;
+        if (!MinGroupCursor(&slowest) || next - slowest < buffer_count_) return;
 
-    if (mapped_memory_ == MAP_FAILED) {
-        std::cerr << "Failed to map shared memory\n";
-        close(shm_fd_);
-        return false;
+        std::this_thread::sleep_for(std::chrono::microseconds(backoff_us));
+        backoff_us = std::min(backoff_us * 2, 1000);
     }
+}
 
-    header_ = static_cast<SharedMemoryHeader*>(mapped_memory_);
+bool SharedMemoryArena::MinGroupCursor(uint64_t* cursor) const {
+    bool any = false;
+    for (size_t i = 0; i < MAX_CONSUMER_GROUPS; ++i) {
+        auto& state = header_->groups[i];
+        if (!state.in_use.load() || state.members.load() <= 0) continue;
 
-    // Verify header
-    if (header_->magic != MAGIC_NUMBER || header_->version != VERSION) {
-        std::cerr << "Invalid shared memory header\n";
-        munmap(mapped_memory_, total_size_);
-        close(shm_fd_);
-        return false;
+        uint64_t value = state.cursor.load();
+        if (!any || value < *cursor) *cursor = value;
+        any = true;
     }
+    return any;
+}
 
-    buffer_count_ = header_->buffer_count;
-    buffer_size_ = header_->buffer_size;
-
-    return true;
+void SharedMemoryArena::LockGroups() {
+    int32_t expected = 0;
+    while (!header_->groups_lock.compare_exchange_weak(expected, 1)) {
+        expected = 0;
+        std::this_thread::yield();
+    }
 }
 
-void SharedMemoryArena::InitializeHeader() {
-    // Use placement new for proper initialization
-    new (header_) SharedMemoryHeader();
+void SharedMemoryArena::UnlockGroups() {
+    header_->groups_lock.store(0);
+}
 
-    // Initialize the flexible array member
-    for (size_t i = 0; i < buffer_count_; ++i) {
-        new (&header_->buffer_states[i]) SharedMemoryHeader::BufferState();
+void SharedMemoryArena::LockMeta() const {
+    int32_t expected = 0;
+    while (!header_->meta_lock.compare_exchange_weak(expected, 1)) {
+        expected = 0;
+        std::this_thread::yield();
     }
+}
 
-    header_->magic = MAGIC_NUMBER;
-    header_->version = VERSION;
-    header_->total_size = total_size_;
-    header_->header_size = sizeof(SharedMemoryHeader) +
-                          sizeof(SharedMemoryHeader::BufferState) * buffer_count_;
+void SharedMemoryArena::UnlockMeta() const {
+    header_->meta_lock.store(0);
+}
 
-    header_->buffer_count = buffer_count_;
-    header_->buffer_size = buffer_size_;
-    header_->buffers_offset = (header_->header_size + CACHE_LINE_SIZE - 1) &
-                             ~(CACHE_LINE_SIZE - 1);
+void SharedMemoryArena::LockReaders() {
+    int32_t expected = 0;
+    while (!header_->readers_lock.compare_exchange_weak(expected, 1)) {
+        expected = 0;
+        std::this_thread::yield();
+    }
+}
 
-    header_->schema_offset = 0;
-    header_->schema_size = 0;
+void SharedMemoryArena::UnlockReaders() {
+    header_->readers_lock.store(0);
+}
 
-    // Initialize buffer states
-    for (size_t i = 0; i < buffer_count_; ++i) {
-        header_->buffer_states[i].data_size.store(0);
-        header_->buffer_states[i].ready.store(false);
-        header_->buffer_states[i].timestamp.store(0);
+arrow::Result<std::shared_ptr<arrow::Table>> SharedMemoryArena::ReadTable(int timeout_ms) {
+    auto batch_result = ReadRecordBatch(timeout_ms);
+    if (!batch_result.ok()) {
+        return batch_result.status();
     }
+    auto batch = batch_result.ValueOrDie();
+    return arrow::Table::FromRecordBatches({batch});
 }
 
-arrow::Status SharedMemoryArena::WriteTable(const std::shared_ptr<arrow::Table>& table) {
-    // Convert table to record batch
-    auto batches_result = table->CombineChunksToBatch();
-    if (!batches_result.ok()) {
-        return batches_result.status();
-    }
-    return WriteRecordBatch(batches_result.ValueOrDie());
+size_t SharedMemoryArena::GetNextWriteBuffer() {
+    return header_->write_sequence.load() % buffer_count_;
 }
 
-arrow::Status SharedMemoryArena::WriteRecordBatch(
-    const std::shared_ptr<arrow::RecordBatch>& batch) {
+size_t SharedMemoryArena::GetCurrentReadBuffer() {
+    return header_->read_sequence.load() % buffer_count_;
+}
 
-    if (!is_writer_ || !is_attached_) {
-        return arrow::Status::Invalid("Not attached as writer");
-    }
+uint8_t* SharedMemoryArena::BufferAt(size_t buffer_idx) const {
+    size_t buffer_offset = header_->buffers_offset + buffer_idx * slot_stride_;
+    return static_cast<uint8_t*>(mapped_memory_) + buffer_offset;
+}
 
-    // Wait for available write buffer
-    if (sem_wait(write_sem_) != 0) {
-        return arrow::Status::IOError("Failed to wait for write semaphore");
+int SharedMemoryArena::WaitReadable(int timeout_ms) {
+    if (timeout_ms >= 0) {
+        struct timespec ts;
+        clock_gettime(CLOCK_REALTIME, &ts);
+        ts.tv_sec += timeout_ms / 1000;
+        ts.tv_nsec += (timeout_ms % 1000) * 1000000;
+        if (ts.tv_nsec >= 1000000000) {
+            ts.tv_sec += 1;
+            ts.tv_nsec -= 1000000000;
+        }
+
+        if (sem_timedwait(read_sem_, &ts) != 0) {
+            if (errno == ETIMEDOUT) {
+                stats_.wait_timeouts++;
+                return QADS_TIMEOUT;
+            }
+            return QADS_ERROR;
+        }
+    } else {
+        if (sem_wait(read_sem_) != 0) {
+            return QADS_ERROR;
+        }
     }
+    return QADS_OK;
+}
 
-    size_t buffer_idx = GetNextWriteBuffer();
-    size_t buffer_offset = header_->buffers_offset + buffer_idx * buffer_size_;
-    uint8_t* buffer = static_cast<uint8_t*>(mapped_memory_) + buffer_offset;
+void SharedMemoryArena::PublishBuffer(size_t buffer_idx, size_t data_size) {
+    ProtectBuffer(buffer_idx, false);
 
-    size_t serialized_size;
-    ARROW_RETURN_NOT_OK(SerializeRecordBatch(batch, buffer, buffer_size_, &serialized_size));
+    // With a persistent backing the frame reaches the media before its slot
+    // claims it, and the slot before any cursor points past it
+    Persist(BufferAt(buffer_idx), data_size);
 
-    // Update buffer state
+    // Update buffer state; wall clock so readers can seek by time
     auto now = std::chrono::duration_cast<std::chrono::microseconds>(
-        std::chrono::steady_clock::now().time_since_epoch()).count();
+        std::chrono::system_clock::now().time_since_epoch()).count();
 
-    header_->buffer_states[buffer_idx].data_size.store(serialized_size);
+    header_->buffer_states[buffer_idx].data_size.store(data_size);
     header_->buffer_states[buffer_idx].timestamp.store(now);
+    header_->buffer_states[buffer_idx].sequence.store(header_->write_sequence.load());
     header_->buffer_states[buffer_idx].ready.store(true);
+    Persist(&header_->buffer_states[buffer_idx], sizeof(SharedMemoryHeader::BufferState));
 
     header_->write_sequence.fetch_add(1);
+    Persist(&header_->write_sequence, sizeof(header_->write_sequence));
 
-    // Signal readers
-    sem_post(read_sem_);
+    uint64_t high_water = header_->high_water_bytes.load();
+    while (data_size > high_water &&
+           !header_->high_water_bytes.compare_exchange_weak(high_water, data_size)) {
+    }
+    uint64_t occupied = std::min<uint64_t>(Unacked(), buffer_count_);
+    uint64_t most_occupied = header_->high_water_buffers.load();
+    while (occupied > most_occupied &&
+           !header_->high_water_buffers.compare_exchange_weak(most_occupied, occupied)) {
+    }
+
+    // Signal readers, unless the frame is staged in an open batch
+    if (batch_open_) {
+        batch_pending_++;
+    } else {
+        header_->publish_sequence.store(header_->write_sequence.load());
+        Persist(&header_->publish_sequence, sizeof(header_->publish_sequence));
+        if (!header_->groups_enabled.load()) {
+            sem_post(read_sem_);
+        }
+        WakeFutexWaiters();
+    }
 
-    stats_.bytes_written += serialized_size;
+    stats_.bytes_written += data_size;
     stats_.writes_count++;
+}
 
-    return arrow::Status::OK();
+void SharedMemoryArena::BeginBufferWrite(size_t buffer_idx) {
+    // Replaying readers treat the slot as gone from here on
+    header_->buffer_states[buffer_idx].sequence.store(SLOT_BEING_WRITTEN);
+    ProtectBuffer(buffer_idx, true);
+    WriteCanary(buffer_idx);
 }
 
-arrow::Result<std::shared_ptr<arrow::RecordBatch>> SharedMemoryArena::ReadRecordBatch(
-    int timeout_ms) {
+void SharedMemoryArena::SetProtection(int flags) {
+    protection_ = flags;
+}
 
-    if (is_writer_ || !is_attached_) {
-        return arrow::Status::Invalid("Not attached as reader");
+void SharedMemoryArena::SetSlotGuards(int flags) {
+    if (is_attached_) return;
+
+    slot_guards_ = flags;
+    ComputeGeometry();
+}
+
+void SharedMemoryArena::SetAdvice(int flags) {
+    advice_ = flags;
+}
+
+int SharedMemoryArena::SetBackingPath(const std::string& path) {
+    if (is_attached_) return QADS_ERROR;
+
+    struct stat st{};
+    if (stat(path.c_str(), &st) != 0) {
+        std::cerr << "pmem path " << path << ": " << strerror(errno) << std::endl;
+        return QADS_ERROR;
     }
 
-    // Wait for data
-    if (timeout_ms >= 0) {
-        struct timespec ts;
-        clock_gettime(CLOCK_REALTIME, &ts);
-        ts.tv_sec += timeout_ms / 1000;
-        ts.tv_nsec += (timeout_ms % 1000) * 1000000;
-        if (ts.tv_nsec >= 1000000000) {
-            ts.tv_sec += 1;
-            ts.tv_nsec -= 1000000000;
+    if (S_ISCHR(st.st_mode)) {
+        // devdax devices belong to the dax subsystem in sysfs
+        std::string subsystem = "/sys/dev/char/" + std::to_string(major(st.st_rdev)) + ":" +
+                                std::to_string(minor(st.st_rdev)) + "/subsystem";
+        char target[PATH_MAX];
+        ssize_t len = readlink(subsystem.c_str(), target, sizeof(target) - 1);
+        std::string link = len > 0 ? std::string(target, static_cast<size_t>(len)) : std::string();
+        if (link.size() < 4 || link.compare(link.size() - 4, 4, "/dax") != 0) {
+            std::cerr << "pmem path " << path << " is not a devdax device\n";
+            return QADS_ERROR;
         }
-
-        if (sem_timedwait(read_sem_, &ts) != 0) {
-            if (errno == ETIMEDOUT) {
-                stats_.wait_timeouts++;
-                return arrow::Status::IOError("Timeout waiting for data");
-            }
-            return arrow::Status::IOError("Failed to wait for read semaphore");
+    } else if (S_ISREG(st.st_mode)) {
+#ifdef STATX_ATTR_DAX
+        struct statx stx{};
+        if (statx(AT_FDCWD, path.c_str(), 0, STATX_BASIC_STATS, &stx) != 0 ||
+            !(stx.stx_attributes & STATX_ATTR_DAX)) {
+            std::cerr << "pmem path " << path << " is not on a DAX filesystem\n";
+            return QADS_ERROR;
         }
+#endif
     } else {
-        if (sem_wait(read_sem_) != 0) {
-            return arrow::Status::IOError("Failed to wait for read semaphore");
+        std::cerr << "pmem path " << path << " is neither a device nor a file\n";
+        return QADS_ERROR;
+    }
+
+    backing_path_ = path;
+    return QADS_OK;
+}
+
+void SharedMemoryArena::ApplyAdvice() {
+    static const struct {
+        int flag;
+        int advice;
+        const char* name;
+    } hints[] = {
+        {ADVISE_WILLNEED, MADV_WILLNEED, "MADV_WILLNEED"},
+        {ADVISE_SEQUENTIAL, MADV_SEQUENTIAL, "MADV_SEQUENTIAL"},
+        {ADVISE_RANDOM, MADV_RANDOM, "MADV_RANDOM"},
+        {ADVISE_HUGEPAGE, MADV_HUGEPAGE, "MADV_HUGEPAGE"},
+        {ADVISE_NOHUGEPAGE, MADV_NOHUGEPAGE, "MADV_NOHUGEPAGE"},
+        {ADVISE_DONTDUMP, MADV_DONTDUMP, "MADV_DONTDUMP"},
+    };
+
+    // Hints only: a kernel that declines one (e.g. no huge pages for shmem) still maps the arena
+    for (const auto& hint : hints) {
+        if ((advice_ & hint.flag) && madvise(mapped_memory_, total_size_, hint.advice) != 0) {
+            std::cerr << hint.name << " not applied: " << strerror(errno) << std::endl;
         }
     }
+}
 
-    size_t buffer_idx = GetCurrentReadBuffer();
+void SharedMemoryArena::ArmGuardPages() {
+    if (!(slot_guards_ & GUARD_PAGES)) return;
 
-    if (!header_->buffer_states[buffer_idx].ready.load()) {
-        sem_post(write_sem_); // Return write token
-        return arrow::Status::IOError("Buffer not ready");
+    // Re-slotting moves the guard pages, so reset the whole data region first
+    bool read_only = !is_writer_ && (protection_ & PROTECT_MEMORY);
+    ProtectRange(header_->buffers_offset, total_size_ - header_->buffers_offset,
+                 read_only ? PROT_READ : PROT_READ | PROT_WRITE);
+    size_t page = PageSize();
+    for (size_t i = 0; i < buffer_count_; ++i) {
+        ProtectRange(header_->buffers_offset + (i + 1) * slot_stride_ - page, page, PROT_NONE);
     }
+}
 
-    size_t buffer_offset = header_->buffers_offset + buffer_idx * buffer_size_;
-    const uint8_t* buffer = static_cast<const uint8_t*>(mapped_memory_) + buffer_offset;
-    size_t data_size = header_->buffer_states[buffer_idx].data_size.load();
+uint64_t SharedMemoryArena::CanaryFor(size_t buffer_idx) const {
+    // Differs per segment and slot, so bytes copied in from elsewhere never match
+    return CANARY_SEED ^ header_->generation ^ (static_cast<uint64_t>(buffer_idx) << 48);
+}
 
-    auto result = DeserializeRecordBatch(buffer, data_size);
+void SharedMemoryArena::WriteCanary(size_t buffer_idx) {
+    if (!(slot_guards_ & CANARIES)) return;
 
-    // Mark buffer as read
-    header_->buffer_states[buffer_idx].ready.store(false);
-    header_->read_sequence.fetch_add(1);
+    uint64_t canary = CanaryFor(buffer_idx);
+    std::memcpy(BufferAt(buffer_idx) + buffer_size_, &canary, CANARY_SIZE);
+}
 
-    // Return write token
-    sem_post(write_sem_);
+bool SharedMemoryArena::CanaryIntact(size_t buffer_idx) const {
+    if (!(slot_guards_ & CANARIES)) return true;
 
-    if (result.ok()) {
-        stats_.bytes_read += data_size;
-        stats_.reads_count++;
+    uint64_t canary;
+    std::memcpy(&canary, BufferAt(buffer_idx) + buffer_size_, CANARY_SIZE);
+    return canary == CanaryFor(buffer_idx);
+}
+
+void SharedMemoryArena::ProtectRange(size_t offset, size_t size, int prot) {
+    // Whole pages only, and never the control block's, which every endpoint writes
+    size_t page = PageSize();
+    size_t data_start = (header_->buffers_offset + page - 1) / page * page;
+    size_t start = std::max(offset / page * page, data_start);
+    size_t end = std::min((offset + size + page - 1) / page * page, total_size_ / page * page);
+    if (start < end) {
+        mprotect(static_cast<uint8_t*>(mapped_memory_) + start, end - start, prot);
     }
+}
 
-    return result;
+void SharedMemoryArena::ProtectBuffer(size_t buffer_idx, bool writable) {
+    if (!(protection_ & PROTECT_MEMORY) || !is_writer_) return;
+    // Pages shared with a neighbouring slot follow whichever slot changed last
+    size_t canary = (slot_guards_ & CANARIES) ? CANARY_SIZE : 0;
+    ProtectRange(header_->buffers_offset + buffer_idx * slot_stride_, buffer_size_ + canary,
+                 writable ? PROT_READ | PROT_WRITE : PROT_READ);
 }
 
-arrow::Result<std::shared_ptr<arrow::Table>> SharedMemoryArena::ReadTable(int timeout_ms) {
-    auto batch_result = ReadRecordBatch(timeout_ms);
-    if (!batch_result.ok()) {
-        return batch_result.status();
+namespace {
+
+thread_local sigjmp_buf* guarded_copy = nullptr;
+struct sigaction previous_segv;
+struct sigaction previous_bus;
+
+void OnMemoryFault(int signal, siginfo_t* info, void* context) {
+    if (guarded_copy) siglongjmp(*guarded_copy, 1);
+
+    // Not ours: hand the fault to whoever handled it before, or crash as usual
+    const struct sigaction& previous = signal == SIGBUS ? previous_bus : previous_segv;
+    if (previous.sa_flags & SA_SIGINFO) {
+        previous.sa_sigaction(signal, info, context);
+    } else if (previous.sa_handler != SIG_DFL && previous.sa_handler != SIG_IGN) {
+        previous.sa_handler(signal);
+    } else {
+        sigaction(signal, &previous, nullptr);
     }
-    auto batch = batch_result.ValueOrDie();
-    return arrow::Table::FromRecordBatches({batch});
 }
 
-size_t SharedMemoryArena::GetNextWriteBuffer() {
-    return header_->write_sequence.load() % buffer_count_;
+void InstallFaultHandler() {
+    static std::once_flag installed;
+    std::call_once(installed, [] {
+        struct sigaction action {};
+        action.sa_sigaction = OnMemoryFault;
+        action.sa_flags = SA_SIGINFO | SA_NODEFER;
+        sigemptyset(&action.sa_mask);
+        sigaction(SIGSEGV, &action, &previous_segv);
+        sigaction(SIGBUS, &action, &previous_bus);
+    });
 }
 
-size_t SharedMemoryArena::GetCurrentReadBuffer() {
-    return header_->read_sequence.load() % buffer_count_;
+}  // namespace
+
+int SharedMemoryArena::CopyOut(uint8_t* data, size_t buffer_idx, size_t size) {
+    if (!(protection_ & GUARD_READS)) {
+        std::memcpy(data, BufferAt(buffer_idx), size);
+        return CanaryIntact(buffer_idx) ? QADS_OK : QADS_OVERRUN;
+    }
+
+    // A plain memcpy between the jump points, so unwinding past it skips no destructors
+    InstallFaultHandler();
+    sigjmp_buf jump;
+    if (sigsetjmp(jump, 1) != 0) {
+        guarded_copy = nullptr;
+        return QADS_FAULT;
+    }
+    guarded_copy = &jump;
+    std::memcpy(data, BufferAt(buffer_idx), size);
+    guarded_copy = nullptr;
+    return CanaryIntact(buffer_idx) ? QADS_OK : QADS_OVERRUN;
+}
+
+void SharedMemoryArena::ReleaseBuffer(size_t buffer_idx) {
+    // Mark buffer as read
+    header_->buffer_states[buffer_idx].ready.store(false);
+    NoteRead(header_->read_sequence.fetch_add(1));
+    Persist(&header_->read_sequence, sizeof(header_->read_sequence));
+
+    // In ack mode the slot is handed back by Ack() instead
+    if (!header_->ack_mode.load()) {
+        sem_post(write_sem_);
+    }
 }
 
 arrow::Status SharedMemoryArena::SerializeRecordBatch(
@@ -369,6 +1871,22 @@ arrow::Status SharedMemoryArena::WaitForData(int timeout_ms) {
 
 void SharedMemoryArena::NotifyDataReady() {
     sem_post(read_sem_);
+    if (header_) WakeFutexWaiters();
+}
+
+void SharedMemoryArena::WakeFutexWaiters() {
+    header_->publish_futex.fetch_add(1);
+    if (header_->futex_waiters.load() > 0) {
+        // Shared (not FUTEX_PRIVATE) so waiters in other processes match the key
+        syscall(SYS_futex, &header_->publish_futex, FUTEX_WAKE, INT_MAX, nullptr, nullptr, 0);
+    }
+}
+
+bool SharedMemoryArena::PublishFutex(std::atomic<uint32_t>** word, std::atomic<int32_t>** waiters) {
+    if (!header_) return false;
+    *word = &header_->publish_futex;
+    *waiters = &header_->futex_waiters;
+    return true;
 }
 
 std::unique_ptr<SharedMemoryArena::Writer> SharedMemoryArena::GetWriter() {
@@ -389,11 +1907,23 @@ void SharedMemoryArena::Close() {
     if (mapped_memory_ && header_) {
         if (is_writer_) {
             header_->writer_active.store(false);
+            header_->writer_pid.store(0);
             // 保存信号量名称
-            write_sem_name = std::string(header_->write_sem_name);
-            read_sem_name = std::string(header_->read_sem_name);
+            // A persistent segment keeps its semaphores for the next writer
+            if (backing_path_.empty()) {
+                write_sem_name = std::string(header_->write_sem_name);
+                read_sem_name = std::string(header_->read_sem_name);
+            }
         } else {
             header_->reader_count.fetch_sub(1);
+            if (reader_idx_ >= 0) {
+                header_->readers[reader_idx_].pid.store(0);
+                reader_idx_ = -1;
+            }
+            if (group_idx_ >= 0) {
+                header_->groups[group_idx_].members.fetch_sub(1);
+                group_idx_ = -1;
+            }
         }
 
         // 解除内存映射
@@ -423,7 +1953,7 @@ void SharedMemoryArena::Close() {
         read_sem_ = nullptr;
     }
 
-    if (is_writer_) {
+    if (is_writer_ && backing_path_.empty()) {
         std::string shm_name = "/qads_" + name_;
         shm_unlink(shm_name.c_str());
     }
@@ -432,6 +1962,36 @@ void SharedMemoryArena::Close() {
 }
 
 // Writer implementation
+void SharedMemoryArena::Abandon() {
+    if (!is_attached_) return;
+
+    // A crashed writer leaves its pid behind; in-process the pid is still alive, so
+    // clear the liveness marker instead. Any acquired slot and its token stay leaked.
+    if (is_writer_ && header_) {
+        header_->writer_active.store(false);
+        header_->writer_pid.store(0);
+    }
+
+    if (mapped_memory_) {
+        munmap(mapped_memory_, total_size_);
+        mapped_memory_ = nullptr;
+        header_ = nullptr;
+    }
+    if (shm_fd_ != -1) {
+        close(shm_fd_);
+        shm_fd_ = -1;
+    }
+    if (write_sem_ && write_sem_ != SEM_FAILED) sem_close(write_sem_);
+    if (read_sem_ && read_sem_ != SEM_FAILED) sem_close(read_sem_);
+    write_sem_ = nullptr;
+    read_sem_ = nullptr;
+
+    // Keep Close() from unlinking the segment later
+    is_writer_ = false;
+    is_attached_ = false;
+    acquired_idx_ = -1;
+}
+
 SharedMemoryArena::Writer::Writer(SharedMemoryArena* arena)
     : arena_(arena), finished_(false) {
 }
@@ -0,0 +1,47 @@
+"""
+numpy decoding of tensor frames published by the Rust ``SharedTensor``.
+
+Frame layout (little-endian): a 32-byte frame header, a 64-byte tensor
+header, then the elements. See ``src/rust/src/tensor.rs``.
+"""
+
+import struct
+
+import numpy as np
+
+FRAME_HEADER_SIZE = 32
+TENSOR_HEADER_SIZE = 64
+FRAME_KIND_TENSOR = 6
+
+_DTYPES = {0: np.dtype("<f4"), 1: np.dtype("<f8")}
+
+
+def tensor_from_frame(buffer):
+    """
+    Map a tensor frame onto a numpy array without copying.
+
+    Args:
+        buffer: bytes-like object holding one frame (e.g. a memoryview of a slot)
+
+    Returns:
+        numpy.ndarray: 2-D read-only view sharing memory with ``buffer``
+    """
+    view = memoryview(buffer)
+    if len(view) < FRAME_HEADER_SIZE + TENSOR_HEADER_SIZE or view[5] != FRAME_KIND_TENSOR:
+        raise ValueError("Not a tensor frame")
+
+    header = view[FRAME_HEADER_SIZE:FRAME_HEADER_SIZE + TENSOR_HEADER_SIZE]
+    dtype = _DTYPES.get(header[0])
+    if dtype is None or header[1] != 2:
+        raise ValueError("Unsupported tensor dtype or rank")
+    rows, cols, row_stride, col_stride = struct.unpack_from("<QQqq", header, 8)
+
+    array = np.ndarray(
+        (rows, cols),
+        dtype=dtype,
+        buffer=view,
+        offset=FRAME_HEADER_SIZE + TENSOR_HEADER_SIZE,
+        strides=(row_stride * dtype.itemsize, col_stride * dtype.itemsize),
+    )
+    array.flags.writeable = False
+    return array
@@ -0,0 +1,99 @@
+//! `#[derive(SwapMessage)]`: one DataFrame column per struct field, so rows of
+//! the struct can be written and read with `write_rows` / `read_rows`.
+//!
+//! ```ignore
+//! #[derive(SwapMessage)]
+//! struct Tick {
+//!     symbol: String,
+//!     #[swap(rename = "px")]
+//!     price: f64,
+//!     volume: Option<i64>,
+//! }
+//! ```
+//!
+//! Field types must implement `qadataswap::message::SwapField`.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr};
+
+#[proc_macro_derive(SwapMessage, attributes(swap))]
+pub fn derive_swap_message(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(name, "SwapMessage needs a struct with named fields")),
+        },
+        _ => return Err(Error::new_spanned(name, "SwapMessage can only be derived for structs")),
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "SwapMessage does not support generic structs"));
+    }
+
+    let mut idents = Vec::new();
+    let mut locals = Vec::new();
+    let mut types = Vec::new();
+    let mut columns = Vec::new();
+    for field in fields {
+        let ident = field.ident.clone().expect("named field");
+        columns.push(column_name(field)?.unwrap_or_else(|| LitStr::new(&ident.to_string(), Span::call_site())));
+        locals.push(format_ident!("__qads_{}", ident));
+        types.push(field.ty.clone());
+        idents.push(ident);
+    }
+
+    Ok(quote! {
+        impl ::qadataswap::message::SwapMessage for #name {
+            fn to_dataframe(rows: &[Self]) -> ::qadataswap::Result<::qadataswap::message::__private::DataFrame> {
+                ::qadataswap::message::__private::frame(::std::vec![
+                    #(
+                        <#types as ::qadataswap::message::SwapField>::to_column(
+                            #columns,
+                            rows.iter().map(|row| ::std::clone::Clone::clone(&row.#idents)).collect(),
+                        ),
+                    )*
+                ])
+            }
+
+            fn from_dataframe(
+                df: &::qadataswap::message::__private::DataFrame,
+            ) -> ::qadataswap::Result<::std::vec::Vec<Self>> {
+                #(
+                    let mut #locals = <#types as ::qadataswap::message::SwapField>::from_column(
+                        ::qadataswap::message::__private::column(df, #columns)?,
+                    )?
+                    .into_iter();
+                )*
+                let height = ::qadataswap::message::__private::height(df);
+                ::std::result::Result::Ok(
+                    (0..height)
+                        .map(|_| Self { #( #idents: #locals.next().expect("column length matches frame height"), )* })
+                        .collect(),
+                )
+            }
+        }
+    })
+}
+
+/// `#[swap(rename = "...")]`, if present
+fn column_name(field: &syn::Field) -> syn::Result<Option<LitStr>> {
+    let mut rename = None;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("swap")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown swap attribute; expected `rename`"))
+            }
+        })?;
+    }
+    Ok(rename)
+}
@@ -0,0 +1,100 @@
+//! Allocation behaviour of the write-side encode path.
+//!
+//! Runs without the shared memory backend: it exercises the same `Scratch`
+//! encoder `SharedDataFrame::write` uses and asserts that, once warm, the
+//! output buffer is no longer reallocated per frame. The IPC encoder still
+//! stages each message body in its own buffer; `FrameBuilder` avoids that too.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use polars::prelude::*;
+use qadataswap::Scratch;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static LARGE_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static LARGE_THRESHOLD: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+fn count(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    if size >= LARGE_THRESHOLD.load(Ordering::Relaxed) {
+        LARGE_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn frame(rows: usize) -> DataFrame {
+    df! {
+        "id" => (0..rows as i64).collect::<Vec<_>>(),
+        "price" => (0..rows).map(|i| i as f64 * 0.01).collect::<Vec<_>>(),
+    }
+    .unwrap()
+}
+
+fn main() {
+    const ROWS: usize = 200_000;
+    const ITERATIONS: usize = 200;
+
+    let df = frame(ROWS);
+    let mut scratch = Scratch::new();
+    let frame_bytes = scratch.encode(&df, 1).unwrap().len();
+
+    // Anything at least half a frame counts as a per-frame buffer allocation
+    LARGE_THRESHOLD.store(frame_bytes / 2, Ordering::Relaxed);
+
+    let fresh = measure(ITERATIONS, || {
+        let mut scratch = Scratch::new();
+        scratch.encode(&df, 1).unwrap().len()
+    });
+    let reused = measure(ITERATIONS, || scratch.encode(&df, 1).unwrap().len());
+
+    println!("frame size:          {} bytes", frame_bytes);
+    println!("fresh buffer:        {:>8.1} allocs/frame, {:>5.2} large/frame, {:?}/frame",
+             fresh.0, fresh.1, fresh.2);
+    println!("reused scratch:      {:>8.1} allocs/frame, {:>5.2} large/frame, {:?}/frame",
+             reused.0, reused.1, reused.2);
+
+    // A fresh Vec grows through several frame-sized reallocations per write;
+    // the warm scratch leaves only the encoder's own staging buffer
+    assert!(reused.1 <= 1.0, "warm scratch buffer must not grow per frame");
+    assert!(fresh.1 > reused.1, "scratch reuse should save frame-sized allocations");
+}
+
+/// (allocations per iteration, large allocations per iteration, time per iteration)
+fn measure(iterations: usize, mut f: impl FnMut() -> usize) -> (f64, f64, std::time::Duration) {
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+    LARGE_ALLOCATIONS.store(0, Ordering::Relaxed);
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        std::hint::black_box(f());
+    }
+    let elapsed = start.elapsed();
+
+    (
+        ALLOCATIONS.load(Ordering::Relaxed) as f64 / iterations as f64,
+        LARGE_ALLOCATIONS.load(Ordering::Relaxed) as f64 / iterations as f64,
+        elapsed / iterations as u32,
+    )
+}
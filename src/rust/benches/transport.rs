@@ -0,0 +1,125 @@
+//! Transport benchmarks over real shared memory segments.
+//!
+//! Covers write→read round-trip latency, throughput against frame size,
+//! 1→N fan-out through consumer groups, and compression on/off. Each bench
+//! uses its own segment name so runs don't interfere with each other.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use polars::prelude::*;
+use qadataswap::{SharedDataFrame, SharedMemoryConfig};
+
+const FRAME_SIZES: [usize; 4] = [100, 1_000, 10_000, 100_000];
+
+fn segment(label: &str) -> String {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    format!("qads_bench_{}_{}_{}", label, std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+fn frame(rows: usize) -> DataFrame {
+    df! {
+        "id" => (0..rows as i64).collect::<Vec<_>>(),
+        "price" => (0..rows).map(|i| 100.0 + (i % 100) as f64 * 0.01).collect::<Vec<_>>(),
+        "volume" => (0..rows).map(|i| (i % 1000) as i64).collect::<Vec<_>>(),
+        "symbol" => (0..rows).map(|i| format!("SYM{}", i % 50)).collect::<Vec<_>>(),
+    }
+    .unwrap()
+}
+
+fn ipc_size(df: &DataFrame) -> u64 {
+    let mut scratch = qadataswap::Scratch::new();
+    scratch.encode(df, 0).unwrap().len() as u64
+}
+
+fn pair(config: SharedMemoryConfig) -> (SharedDataFrame, SharedDataFrame) {
+    let writer = SharedDataFrame::create_writer(config.clone()).unwrap();
+    let reader = SharedDataFrame::create_reader(config).unwrap();
+    (writer, reader)
+}
+
+/// Single small frame, write then read, on one thread
+fn round_trip_latency(c: &mut Criterion) {
+    let (writer, reader) = pair(SharedMemoryConfig::new(segment("latency")).with_size_mb(16));
+    let df = frame(100);
+
+    c.bench_function("round_trip_latency/100_rows", |b| {
+        b.iter(|| {
+            writer.write(&df).unwrap();
+            reader.read(Some(1000)).unwrap().unwrap()
+        })
+    });
+}
+
+fn throughput_vs_frame_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("throughput");
+
+    for rows in FRAME_SIZES {
+        let df = frame(rows);
+        let (writer, reader) = pair(SharedMemoryConfig::new(segment("throughput")).with_size_mb(256));
+
+        group.throughput(Throughput::Bytes(ipc_size(&df)));
+        group.bench_with_input(BenchmarkId::from_parameter(rows), &df, |b, df| {
+            b.iter(|| {
+                writer.write(df).unwrap();
+                reader.read(Some(1000)).unwrap().unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// One writer, N consumer groups that each see every frame
+fn fan_out(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fan_out");
+    let df = frame(1_000);
+
+    for readers in [1usize, 2, 4, 8] {
+        let name = segment("fanout");
+        let writer = SharedDataFrame::create_writer(
+            SharedMemoryConfig::new(&name).with_size_mb(64).with_consumer_groups(true),
+        ).unwrap();
+        let consumers: Vec<SharedDataFrame> = (0..readers)
+            .map(|i| SharedDataFrame::create_reader(
+                SharedMemoryConfig::new(&name).with_consumer_group(format!("g{}", i)),
+            ).unwrap())
+            .collect();
+
+        group.throughput(Throughput::Elements(readers as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(readers), &consumers, |b, consumers| {
+            b.iter(|| {
+                writer.write(&df).unwrap();
+                for consumer in consumers {
+                    consumer.read(Some(1000)).unwrap().unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn compression(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compression");
+    let df = frame(100_000);
+    group.throughput(Throughput::Bytes(ipc_size(&df)));
+
+    let variants = [("none", None), ("lz4", Some(IpcCompression::LZ4)), ("zstd", Some(IpcCompression::ZSTD))];
+    for (label, codec) in variants {
+        let mut config = SharedMemoryConfig::new(segment("compression")).with_size_mb(256);
+        if let Some(codec) = codec {
+            config = config.with_compression(codec);
+        }
+        let (writer, reader) = pair(config);
+
+        group.bench_function(label, |b| {
+            b.iter(|| {
+                writer.write(&df).unwrap();
+                reader.read(Some(1000)).unwrap().unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, round_trip_latency, throughput_vs_frame_size, fan_out, compression);
+criterion_main!(benches);
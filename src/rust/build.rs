@@ -2,10 +2,17 @@ use std::path::Path;
 use std::env;
 
 fn main() {
+    // Stubs for the gRPC control plane (needs protoc on PATH or in $PROTOC)
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/control.proto").expect("Failed to compile proto/control.proto; is protoc installed?");
+
     // Get the absolute path to the C++ library
     let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
     let cpp_lib_path = Path::new(&manifest_dir).join("../../build/cpp").canonicalize();
 
+    // Set when the C++ core is linked; without it only Transport::InProcess works
+    println!("cargo:rustc-check-cfg=cfg(qads_ffi)");
+
     if let Ok(lib_path) = cpp_lib_path {
         let lib_file = lib_path.join("libqadataswap_core.so");
 
@@ -15,6 +22,7 @@ fn main() {
             // Link to the C++ library
             println!("cargo:rustc-link-search=native={}", lib_dir);
             println!("cargo:rustc-link-lib=dylib=qadataswap_core");
+            println!("cargo:rustc-cfg=qads_ffi");
 
             // Also link required system libraries
             println!("cargo:rustc-link-lib=rt");
@@ -33,10 +41,10 @@ fn main() {
             println!("cargo:warning=Linking to C++ library: {}", lib_file.to_string_lossy());
         } else {
             println!("cargo:warning=C++ library not found, skipping FFI linking");
-            println!("cargo:warning=Building with mock implementation");
+            println!("cargo:warning=Building with the in-process transport only");
         }
     } else {
         println!("cargo:warning=Could not resolve C++ library path, skipping FFI linking");
-        println!("cargo:warning=Building with mock implementation");
+        println!("cargo:warning=Building with the in-process transport only");
     }
 }
\ No newline at end of file
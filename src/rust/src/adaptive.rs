@@ -0,0 +1,211 @@
+//! Adaptive ring sizing driven by the observed frame size distribution, and
+//! adaptive compression driven by what compressing recent frames achieved
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Policy for re-slotting the ring so slot size tracks the P99 frame size
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveSizing {
+    /// Fewest (and therefore largest) slots the ring may be split into
+    pub min_buffer_count: usize,
+    /// Most (and therefore smallest) slots; the ring starts here
+    pub max_buffer_count: usize,
+    /// Slot size as a multiple of the P99 frame size
+    pub headroom: f64,
+    /// Relative change in buffer count required before re-slotting
+    pub hysteresis: f64,
+    /// Number of recent frames the distribution is computed over
+    pub window: usize,
+    /// Re-evaluate the geometry every N frames
+    pub check_every: usize,
+}
+
+impl Default for AdaptiveSizing {
+    fn default() -> Self {
+        Self {
+            min_buffer_count: 2,
+            max_buffer_count: 64,
+            headroom: 1.25,
+            hysteresis: 0.25,
+            window: 1024,
+            check_every: 64,
+        }
+    }
+}
+
+/// Writer-side record of recent frame sizes
+pub(crate) struct FrameSizeTracker {
+    policy: AdaptiveSizing,
+    sizes: VecDeque<usize>,
+    since_check: usize,
+}
+
+impl FrameSizeTracker {
+    pub(crate) fn new(policy: AdaptiveSizing) -> Self {
+        Self {
+            sizes: VecDeque::with_capacity(policy.window),
+            policy,
+            since_check: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, size: usize) {
+        if self.sizes.len() == self.policy.window {
+            self.sizes.pop_front();
+        }
+        self.sizes.push_back(size);
+        self.since_check += 1;
+    }
+
+    pub(crate) fn percentile(&self, p: f64) -> Option<usize> {
+        if self.sizes.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<usize> = self.sizes.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = ((sorted.len() - 1) as f64 * p.clamp(0.0, 1.0)).round() as usize;
+        Some(sorted[rank])
+    }
+
+    /// Buffer count whose slots fit `frame_size` with headroom
+    pub(crate) fn count_for_frame(&self, frame_size: usize, usable_bytes: usize) -> usize {
+        let slot = ((frame_size.max(1) as f64) * self.policy.headroom).ceil() as usize;
+        (usable_bytes / slot).clamp(self.policy.min_buffer_count, self.policy.max_buffer_count)
+    }
+
+    /// Buffer count to re-slot to, once per `check_every` frames and only when the
+    /// current geometry is off by more than the hysteresis
+    pub(crate) fn recommend(&mut self, usable_bytes: usize, current_count: usize) -> Option<usize> {
+        if self.since_check < self.policy.check_every {
+            return None;
+        }
+        self.since_check = 0;
+
+        let p99 = self.percentile(0.99)?;
+        let target = self.count_for_frame(p99, usable_bytes);
+        let change = (target as f64 - current_count as f64).abs() / current_count.max(1) as f64;
+
+        if target != current_count && change > self.policy.hysteresis {
+            Some(target)
+        } else {
+            None
+        }
+    }
+}
+
+/// Policy for sending frames uncompressed when compressing them does not pay.
+/// A frame that compresses too little or too slowly goes out uncompressed,
+/// flagged so readers can tell; the next `probe_interval` frames skip
+/// compression outright before it is tried again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveCompression {
+    /// Fraction of the frame compression must save, e.g. 0.1 for 10%
+    pub min_savings: f64,
+    /// Longest compressing one frame may take
+    pub max_cost: Duration,
+    /// Frames sent uncompressed after one where compression did not pay
+    pub probe_interval: usize,
+}
+
+impl Default for AdaptiveCompression {
+    fn default() -> Self {
+        Self {
+            min_savings: 0.1,
+            max_cost: Duration::from_micros(500),
+            probe_interval: 16,
+        }
+    }
+}
+
+impl AdaptiveCompression {
+    pub fn new(min_savings: f64, max_cost: Duration) -> Self {
+        Self {
+            min_savings,
+            max_cost,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_probe_interval(mut self, frames: usize) -> Self {
+        self.probe_interval = frames;
+        self
+    }
+}
+
+/// Writer-side record of whether compression currently pays
+pub(crate) struct CompressionGovernor {
+    policy: AdaptiveCompression,
+    /// Frames left to send uncompressed before the next probe
+    skip: usize,
+}
+
+impl CompressionGovernor {
+    pub(crate) fn new(policy: AdaptiveCompression) -> Self {
+        Self { policy, skip: 0 }
+    }
+
+    /// Whether to try compressing the next frame
+    pub(crate) fn should_compress(&mut self) -> bool {
+        if self.skip == 0 {
+            return true;
+        }
+        self.skip -= 1;
+        false
+    }
+
+    /// Judge a compressed frame; `false` means send it uncompressed instead
+    pub(crate) fn record(&mut self, raw_size: usize, compressed_size: usize, cost: Duration) -> bool {
+        let savings = 1.0 - compressed_size as f64 / raw_size.max(1) as f64;
+        let pays = savings >= self.policy.min_savings && cost <= self.policy.max_cost;
+        if !pays {
+            self.skip = self.policy.probe_interval;
+        }
+        pays
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recommendation_respects_hysteresis() {
+        let policy = AdaptiveSizing {
+            check_every: 4,
+            ..Default::default()
+        };
+        let mut tracker = FrameSizeTracker::new(policy);
+        let usable = 64 * 1024 * 1024;
+
+        for _ in 0..4 {
+            tracker.record(1024 * 1024);
+        }
+        // 1 MB frames with 1.25x headroom fit 51 slots
+        assert_eq!(tracker.recommend(usable, 64), None);
+
+        for _ in 0..4 {
+            tracker.record(8 * 1024 * 1024);
+        }
+        assert_eq!(tracker.recommend(usable, 64), Some(6));
+        assert_eq!(tracker.recommend(usable, 64), None);
+    }
+
+    #[test]
+    fn test_compression_skipped_until_next_probe() {
+        let policy = AdaptiveCompression::new(0.2, Duration::from_millis(1)).with_probe_interval(2);
+        let mut governor = CompressionGovernor::new(policy);
+
+        assert!(governor.should_compress());
+        assert!(governor.record(1000, 300, Duration::from_micros(50)));
+        assert!(governor.should_compress());
+        // Saves only 10%
+        assert!(!governor.record(1000, 900, Duration::from_micros(50)));
+        assert!(!governor.should_compress());
+        assert!(!governor.should_compress());
+        assert!(governor.should_compress());
+        // Too slow
+        assert!(!governor.record(1000, 100, Duration::from_millis(5)));
+        assert!(!governor.should_compress());
+    }
+}
@@ -0,0 +1,309 @@
+//! Recording a stream to hive-partitioned parquet files
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use polars::prelude::*;
+
+use crate::{Clock, ClockSource, Event, QADataSwapError, Result, SharedDataFrame};
+
+/// How often an idle archiver checks whether it should stop or roll
+const POLL_TIMEOUT_MS: i32 = 50;
+
+/// Directory value used for a null partition key, as in Hive
+const NULL_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// Manifest listing every file written, one line per file, relative to the root
+pub const ARCHIVE_MANIFEST: &str = "_manifest.tsv";
+
+/// Layout and rolling of the files written by `ParquetArchiver`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivePolicy {
+    /// Columns whose values name the directories (`date=2024-05-01/symbol=AAPL`)
+    pub partition_by: Vec<String>,
+    /// Close a partition's file once its first buffered row is this old
+    pub roll_every: Duration,
+    /// Close a partition's file once this many bytes are buffered for it
+    pub max_file_bytes: usize,
+}
+
+impl Default for ArchivePolicy {
+    fn default() -> Self {
+        Self {
+            partition_by: Vec::new(),
+            roll_every: Duration::from_secs(60),
+            max_file_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+impl ArchivePolicy {
+    pub fn with_partition_by<S: Into<String>>(mut self, columns: impl IntoIterator<Item = S>) -> Self {
+        self.partition_by = columns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_roll_every(mut self, roll_every: Duration) -> Self {
+        self.roll_every = roll_every;
+        self
+    }
+
+    pub fn with_max_file_bytes(mut self, max_file_bytes: usize) -> Self {
+        self.max_file_bytes = max_file_bytes;
+        self
+    }
+}
+
+/// A parquet file the archiver finished, as listed in the manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivedFile {
+    /// Relative to the archive root
+    pub path: PathBuf,
+    pub rows: usize,
+    pub bytes: u64,
+}
+
+/// Rows buffered for one partition directory
+struct Pending {
+    frames: Vec<DataFrame>,
+    bytes: usize,
+    since: Instant,
+}
+
+struct Archive {
+    root: PathBuf,
+    policy: ArchivePolicy,
+    pending: BTreeMap<PathBuf, Pending>,
+    written: Arc<Mutex<Vec<ArchivedFile>>>,
+    next_file: u64,
+    /// The reader's time source, for roll ages and file stamps
+    time: Arc<dyn Clock>,
+}
+
+impl Archive {
+    fn now_us(&self) -> u64 {
+        self.time.now_ns(ClockSource::Realtime) / 1000
+    }
+
+    fn add(&mut self, df: DataFrame) -> Result<()> {
+        if df.height() == 0 {
+            return Ok(());
+        }
+        let parts = match self.policy.partition_by.is_empty() {
+            true => vec![df],
+            false => df.partition_by(self.policy.partition_by.iter().map(String::as_str), true)?,
+        };
+
+        let now = self.time.instant();
+        for part in parts {
+            let dir = self.partition_dir(&part)?;
+            let pending = self.pending.entry(dir).or_insert_with(|| Pending {
+                frames: Vec::new(),
+                bytes: 0,
+                since: now,
+            });
+            pending.bytes += part.estimated_size();
+            pending.frames.push(part);
+        }
+        self.roll(false)
+    }
+
+    /// `date=2024-05-01/symbol=AAPL` from the partition's (uniform) key values
+    fn partition_dir(&self, part: &DataFrame) -> Result<PathBuf> {
+        let mut dir = PathBuf::new();
+        for name in &self.policy.partition_by {
+            let values = part.column(name)?.cast(&DataType::String)?;
+            let value = match values.str()?.get(0) {
+                Some(value) => value.replace(['/', '\\'], "_"),
+                None => NULL_PARTITION.to_string(),
+            };
+            dir.push(format!("{}={}", name, value));
+        }
+        Ok(dir)
+    }
+
+    /// Write out every partition that is due, or all of them with `all`
+    fn roll(&mut self, all: bool) -> Result<()> {
+        let now = self.time.instant();
+        let due: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| {
+                all || p.bytes >= self.policy.max_file_bytes || now.duration_since(p.since) >= self.policy.roll_every
+            })
+            .map(|(dir, _)| dir.clone())
+            .collect();
+
+        for dir in due {
+            if let Some(pending) = self.pending.remove(&dir) {
+                self.write_file(&dir, pending)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_file(&mut self, dir: &Path, pending: Pending) -> Result<()> {
+        let mut frames = pending.frames.into_iter();
+        let Some(mut df) = frames.next() else {
+            return Ok(());
+        };
+        for frame in frames {
+            df.vstack_mut(&frame)?;
+        }
+
+        fs::create_dir_all(self.root.join(dir))?;
+        let relative = dir.join(format!("part-{}-{:06}.parquet", self.now_us(), self.next_file));
+        self.next_file += 1;
+
+        // Readers of the directory never see a half-written file
+        let path = self.root.join(&relative);
+        let tmp = path.with_extension("parquet.tmp");
+        let bytes = ParquetWriter::new(File::create(&tmp)?).finish(&mut df)?;
+        fs::rename(&tmp, &path)?;
+
+        let file = ArchivedFile {
+            path: relative,
+            rows: df.height(),
+            bytes,
+        };
+        self.append_manifest(&file)?;
+        self.written.lock().unwrap().push(file);
+        Ok(())
+    }
+
+    fn append_manifest(&self, file: &ArchivedFile) -> Result<()> {
+        let path = self.root.join(ARCHIVE_MANIFEST);
+        let is_new = !path.exists();
+        let mut manifest = OpenOptions::new().create(true).append(true).open(path)?;
+        if is_new {
+            writeln!(manifest, "path\trows\tbytes\tcreated_us")?;
+        }
+        writeln!(manifest, "{}\t{}\t{}\t{}", file.path.display(), file.rows, file.bytes, self.now_us())?;
+        Ok(())
+    }
+}
+
+/// Background thread draining a stream into parquet under `root`, see `spawn`
+pub struct ParquetArchiver {
+    stop: Arc<AtomicBool>,
+    written: Arc<Mutex<Vec<ArchivedFile>>>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl ParquetArchiver {
+    /// Archive every frame `reader` receives. Buffered rows are written when a
+    /// partition rolls, on a Flush or EOS event, and when the archiver stops;
+    /// an EOS also ends the thread.
+    pub fn spawn(reader: SharedDataFrame, root: impl Into<PathBuf>, policy: ArchivePolicy) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let mut archive = Archive {
+            root,
+            policy,
+            pending: BTreeMap::new(),
+            written: Arc::clone(&written),
+            next_file: 0,
+            time: Arc::clone(&reader.arena.config.time_source),
+        };
+
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("qads-archiver".to_string())
+                .spawn(move || run(&reader, &mut archive, &stop))?
+        };
+
+        Ok(Self {
+            stop,
+            written,
+            thread: Some(thread),
+        })
+    }
+
+    /// Every file finished so far, oldest first
+    pub fn files(&self) -> Vec<ArchivedFile> {
+        self.written.lock().unwrap().clone()
+    }
+
+    /// Write out what is buffered and stop, returning the error that ended the
+    /// thread early, if any
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::Release);
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(QADataSwapError::SharedMemory("Parquet archiver panicked".to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+fn run(reader: &SharedDataFrame, archive: &mut Archive, stop: &AtomicBool) -> Result<()> {
+    while !stop.load(Ordering::Acquire) {
+        match reader.read_event(Some(POLL_TIMEOUT_MS)) {
+            Ok(Some(Event::Data(df))) => archive.add(df)?,
+            Ok(Some(Event::Flush)) => archive.roll(true)?,
+            Ok(Some(Event::Eos)) => break,
+            Ok(Some(_)) | Ok(None) | Err(QADataSwapError::Timeout) => archive.roll(false)?,
+            Err(e) => return Err(e),
+        }
+    }
+    archive.roll(true)
+}
+
+impl Drop for ParquetArchiver {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_archive_partitions_and_manifest() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config("archive_ticks", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let policy = ArchivePolicy::default().with_partition_by(["date", "symbol"]);
+        let archiver = ParquetArchiver::spawn(reader, dir.path(), policy)?;
+        writer.write(&df! {
+            "date" => ["2024-05-01", "2024-05-01", "2024-05-02"],
+            "symbol" => ["AAPL", "MSFT", "AAPL"],
+            "price" => [1.0, 2.0, 3.0],
+        }?)?;
+        writer.write(&df! { "date" => ["2024-05-01"], "symbol" => ["AAPL"], "price" => [4.0] }?)?;
+        writer.write_event(&Event::Eos)?;
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while archiver.files().len() < 3 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        archiver.stop()?;
+
+        let aapl = dir.path().join("date=2024-05-01").join("symbol=AAPL");
+        let file = fs::read_dir(&aapl)?.next().unwrap()?.path();
+        let df = ParquetReader::new(File::open(file)?).finish()?;
+        assert_eq!(df.column("price")?.f64()?.to_vec(), vec![Some(1.0), Some(4.0)]);
+
+        let manifest = fs::read_to_string(dir.path().join(ARCHIVE_MANIFEST))?;
+        assert_eq!(manifest.lines().count(), 4);
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+//! Capability tokens: a shared-secret HMAC handshake readers must pass to attach
+
+use std::fmt;
+use std::sync::Arc;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{QADataSwapError, Result};
+
+pub(crate) const NONCE_SIZE: usize = 16;
+pub(crate) const TAG_SIZE: usize = 32;
+
+/// What the writer publishes in the segment: a random nonce and HMAC-SHA256(token, nonce)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Challenge {
+    pub(crate) nonce: [u8; NONCE_SIZE],
+    pub(crate) tag: [u8; TAG_SIZE],
+}
+
+/// Shared secret for `SharedMemoryConfig::with_capability_token`. Never printed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CapabilityToken(Arc<[u8]>);
+
+impl CapabilityToken {
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self(Arc::from(secret.as_ref()))
+    }
+
+    fn mac(&self, nonce: &[u8; NONCE_SIZE]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        mac.update(nonce);
+        mac
+    }
+
+    /// Writer side: a fresh challenge to publish
+    pub(crate) fn issue(&self) -> Result<Challenge> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        getrandom::getrandom(&mut nonce)
+            .map_err(|e| QADataSwapError::SharedMemory(format!("Failed to generate nonce: {}", e)))?;
+        let tag = self.mac(&nonce).finalize().into_bytes().into();
+        Ok(Challenge { nonce, tag })
+    }
+
+    /// Reader side: whether this token produced `challenge` (constant time)
+    pub(crate) fn verify(&self, challenge: &Challenge) -> bool {
+        self.mac(&challenge.nonce).verify_slice(&challenge.tag).is_ok()
+    }
+}
+
+impl fmt::Debug for CapabilityToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CapabilityToken(<redacted>)")
+    }
+}
+
+impl<T: AsRef<[u8]>> From<T> for CapabilityToken {
+    fn from(secret: T) -> Self {
+        Self::new(secret)
+    }
+}
+
+/// Whether a reader holding `token` may attach to a segment publishing `challenge`
+pub(crate) fn authorize(token: Option<&CapabilityToken>, challenge: Option<&Challenge>) -> Result<()> {
+    match (token, challenge) {
+        (None, None) => Ok(()),
+        (Some(token), Some(challenge)) if token.verify(challenge) => Ok(()),
+        _ => Err(QADataSwapError::Unauthorized),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{SharedDataFrame, SharedMemoryConfig};
+
+    fn config(token: Option<&str>) -> SharedMemoryConfig {
+        let config = test_config("auth_stream", 3);
+        match token {
+            Some(token) => config.with_capability_token(token),
+            None => config,
+        }
+    }
+
+    #[test]
+    fn test_attach_requires_matching_token() -> Result<()> {
+        let _writer = SharedDataFrame::create_writer(config(Some("s3cret")))?;
+
+        assert!(matches!(SharedDataFrame::create_reader(config(None)), Err(QADataSwapError::Unauthorized)));
+        assert!(matches!(SharedDataFrame::create_reader(config(Some("guess"))), Err(QADataSwapError::Unauthorized)));
+        SharedDataFrame::create_reader(config(Some("s3cret")))?;
+
+        assert_eq!(format!("{:?}", CapabilityToken::new("s3cret")), "CapabilityToken(<redacted>)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampered_or_unexpected_challenges_are_rejected() -> Result<()> {
+        let token = CapabilityToken::new("s3cret");
+        let challenge = token.issue()?;
+        assert_ne!(token.issue()?.nonce, challenge.nonce);
+        authorize(Some(&token), Some(&challenge))?;
+
+        let mut forged = challenge;
+        forged.tag[0] ^= 1;
+        assert!(matches!(authorize(Some(&token), Some(&forged)), Err(QADataSwapError::Unauthorized)));
+        let mut mismatched = challenge;
+        mismatched.nonce[0] ^= 1;
+        assert!(matches!(authorize(Some(&token), Some(&mismatched)), Err(QADataSwapError::Unauthorized)));
+
+        // A reader expecting a token refuses an open segment, it could be an impostor
+        assert!(matches!(authorize(Some(&token), None), Err(QADataSwapError::Unauthorized)));
+        let _open = SharedDataFrame::create_writer(test_config("auth_open", 3))?;
+        let insisting = test_config("auth_open", 3).with_capability_token("s3cret");
+        assert!(matches!(SharedDataFrame::create_reader(insisting), Err(QADataSwapError::Unauthorized)));
+        Ok(())
+    }
+}
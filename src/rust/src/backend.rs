@@ -0,0 +1,334 @@
+//! Transport selection and the raw operations every backend provides
+
+use std::path::Path;
+use std::sync::atomic::{AtomicI32, AtomicU32};
+use std::sync::Arc;
+
+use crate::auth::Challenge;
+use crate::{Advice, MemoryProtection, MemoryReport, OpenMode, Result, SeekFrom, SharedMemoryConfig};
+
+/// Status codes shared by every backend (mirroring the C++ core's QADS_*)
+pub(crate) const STATUS_OK: i32 = 0;
+pub(crate) const STATUS_TIMEOUT: i32 = 1;
+pub(crate) const STATUS_ALREADY_EXISTS: i32 = 2;
+pub(crate) const STATUS_BUSY: i32 = 3;
+pub(crate) const STATUS_FAULT: i32 = 4;
+pub(crate) const STATUS_OVERRUN: i32 = 5;
+pub(crate) const STATUS_ERROR: i32 = -1;
+
+/// Session metadata table limits (mirroring the C++ core), terminating NULs included
+pub(crate) const MAX_META_ENTRIES: usize = 32;
+pub(crate) const META_KEY_SIZE: usize = 32;
+pub(crate) const META_VALUE_SIZE: usize = 224;
+
+/// Consumer group table size (mirroring the C++ core), name NUL included
+pub(crate) const MAX_CONSUMER_GROUPS: usize = 16;
+pub(crate) const GROUP_NAME_SIZE: usize = 32;
+
+/// Reader registry size (mirroring the C++ core); readers past it attach unlisted
+pub(crate) const MAX_READERS: usize = 64;
+
+/// Which mechanism carries frames between writer and readers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// POSIX shared memory through the C++ core
+    SharedMemory,
+    /// In-memory channel within this process, for tests and CI machines without
+    /// the C++ core or /dev/shm. Same semantics, segments are visible by name to
+    /// every arena in the process.
+    InProcess,
+    /// Experimental: the ring lives in RDMA-registered memory on the reader's
+    /// host. The reader listens on this (IPv4) address and the writer, on
+    /// another host, connects to it and RDMA-writes frames straight into the
+    /// reader's memory. One writer and one reader; replay, consumer groups,
+    /// batches, acks and capability tokens are not carried across.
+    #[cfg(feature = "rdma")]
+    Rdma(std::net::SocketAddr),
+}
+
+impl Transport {
+    /// `QADATASWAP_TRANSPORT=inprocess` selects the in-process transport, so test
+    /// suites can switch without code changes; anything else means shared memory
+    pub fn from_env() -> Self {
+        match std::env::var("QADATASWAP_TRANSPORT") {
+            Ok(value) if matches!(value.to_ascii_lowercase().as_str(), "inprocess" | "in_process" | "in-process") => {
+                Transport::InProcess
+            },
+            _ => Transport::SharedMemory,
+        }
+    }
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+/// Operations `SharedMemoryArena` needs from a backend, returning `STATUS_*` codes
+pub(crate) trait RawArena: Send + Sync {
+    fn create_writer(&self, mode: OpenMode) -> i32;
+    fn attach_reader(&self) -> i32;
+    fn write(&self, data: &[u8]) -> i32;
+    fn read(&self, buffer: &mut [u8], actual_size: &mut usize, timeout_ms: i32) -> i32;
+    /// Wait for the frame `read` returns next and report its size, leaving it unread
+    fn peek_size(&self, size: &mut usize, timeout_ms: i32) -> i32;
+    fn last_read_sequence(&self) -> Option<u64>;
+    fn has_data(&self) -> bool;
+    fn acquire_write_buffer(&self) -> Option<(*mut u8, usize)>;
+    fn publish_acquired(&self, size: usize) -> i32;
+    fn release_acquired(&self);
+    fn reserve_slots(&self, max_buffer_count: usize);
+    fn reslot(&self, buffer_count: usize) -> i32;
+    fn begin_batch(&self) -> i32;
+    fn commit_batch(&self) -> i32;
+    fn abort_batch(&self);
+    fn memory_report(&self, report: &mut MemoryReport) -> i32;
+    /// Debug dumps; `dump_slot`/`dump_group` return 1 for a slot in the ring
+    /// or a group in use, 0 past the end or for a free group
+    fn dump_header(&self, dump: &mut HeaderDump) -> i32;
+    fn dump_slot(&self, index: usize, dump: &mut SlotDump) -> i32;
+    fn dump_group(&self, index: usize, dump: &mut GroupDump) -> i32;
+    fn seek(&self, pos: SeekFrom) -> i32;
+    fn retained_range(&self) -> (u64, u64);
+    fn seek_time(&self, timestamp_us: u64) -> i32;
+    fn set_retention(&self, retention_us: u64);
+    fn enable_consumer_groups(&self);
+    fn join_group(&self, group: &str) -> i32;
+    /// Writer side: refuse attaches (`STATUS_BUSY`) while `max_readers` live
+    /// readers are registered, 0 for no limit
+    fn set_max_readers(&self, max_readers: usize);
+    /// Before creating or attaching; guarded reads return `STATUS_FAULT`
+    fn set_protection(&self, protection: MemoryProtection);
+    /// Writer side, before creating: publishing or consuming a frame whose slot
+    /// canary was clobbered returns `STATUS_OVERRUN`
+    fn set_slot_guards(&self, guard_pages: bool, canaries: bool);
+    /// Before creating or attaching
+    fn set_advice(&self, advice: &[Advice]);
+    /// Before creating or attaching: map the arena from this file or device
+    fn set_backing_path(&self, path: &Path) -> i32;
+    /// 1 for a registry entry in use, 0 for a free one
+    fn dump_reader(&self, index: usize, dump: &mut ReaderDump) -> i32;
+    /// Writer side: publish (or clear) the attach challenge
+    fn set_auth(&self, challenge: Option<&Challenge>);
+    fn auth(&self) -> Option<Challenge>;
+    /// Session metadata in the control block; `None` removes the key
+    fn put_meta(&self, key: &str, value: Option<&str>) -> i32;
+    fn get_meta(&self, key: &str) -> Option<String>;
+    fn meta_keys(&self) -> Vec<String>;
+    fn set_ack_mode(&self, enabled: bool);
+    fn ack(&self, sequence: u64) -> i32;
+    fn unacked(&self) -> u64;
+    fn wait_for_data(&self, timeout_ms: i32) -> i32;
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    fn wake_word(&self) -> Option<WakeWord>;
+    fn notify_data_ready(&self);
+    fn close(&self);
+    /// Detach without cleaning up, the way a crashed process leaves a segment
+    fn abandon(&self);
+}
+
+pub(crate) fn open(segment: &str, config: &SharedMemoryConfig, buffer_count: usize) -> Result<Arc<dyn RawArena>> {
+    let size = config.size_mb * 1024 * 1024;
+
+    match config.transport {
+        Transport::InProcess => {
+            let time = Arc::clone(&config.time_source);
+            Ok(Arc::new(crate::loopback::LoopbackArena::new(segment, size, buffer_count, time)))
+        },
+        #[cfg(qads_ffi)]
+        Transport::SharedMemory => Ok(Arc::new(crate::shm::ShmArena::new(segment, size, buffer_count)?)),
+        #[cfg(not(qads_ffi))]
+        Transport::SharedMemory => Err(crate::QADataSwapError::SharedMemory(
+            "Built without the C++ core; use Transport::InProcess".to_string(),
+        )),
+        #[cfg(all(qads_ffi, feature = "rdma"))]
+        Transport::Rdma(addr) => {
+            Ok(Arc::new(crate::rdma::RdmaArena::new(addr, size, buffer_count, config.timeout_ms)?))
+        },
+        #[cfg(all(not(qads_ffi), feature = "rdma"))]
+        Transport::Rdma(_) => Err(crate::QADataSwapError::SharedMemory(
+            "Built without the C++ core; use Transport::InProcess".to_string(),
+        )),
+    }
+}
+
+/// Futex readers can sleep on outside the backend's own wait: bumped on every
+/// publish, with a waiter count so the writer only issues a wake when needed.
+/// Valid for as long as the arena stays attached.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+pub(crate) struct WakeWord {
+    pub(crate) futex: *const AtomicU32,
+    pub(crate) waiters: *const AtomicI32,
+}
+
+/// Control block fields for `debug_dump()`, laid out as the C++ core's HeaderDump
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct HeaderDump {
+    pub(crate) magic: u32,
+    pub(crate) version: u32,
+    pub(crate) total_size: u64,
+    pub(crate) header_size: u64,
+    pub(crate) buffer_count: u64,
+    pub(crate) buffer_size: u64,
+    pub(crate) max_buffer_count: u64,
+    pub(crate) geometry_epoch: u64,
+    pub(crate) write_sequence: u64,
+    pub(crate) publish_sequence: u64,
+    pub(crate) read_sequence: u64,
+    pub(crate) ack_sequence: u64,
+    pub(crate) retention_us: u64,
+    pub(crate) created_at_us: u64,
+    pub(crate) generation: u64,
+    pub(crate) last_read_sequence: u64,
+    pub(crate) writer_active: i32,
+    pub(crate) writer_pid: i32,
+    pub(crate) reader_count: i32,
+    pub(crate) ack_mode: i32,
+    pub(crate) auth_required: i32,
+    pub(crate) groups_enabled: i32,
+    pub(crate) groups_lock: i32,
+    pub(crate) meta_lock: i32,
+    pub(crate) futex_waiters: i32,
+    pub(crate) write_sem_value: i32,
+    pub(crate) read_sem_value: i32,
+    pub(crate) last_read_valid: i32,
+    pub(crate) max_readers: i32,
+}
+
+/// One ring slot, laid out as the C++ core's SlotDump
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SlotDump {
+    /// `SLOT_EMPTY` while empty or being filled
+    pub(crate) sequence: u64,
+    pub(crate) data_size: u64,
+    pub(crate) timestamp: u64,
+    pub(crate) ready: i32,
+    pub(crate) reserved: i32,
+}
+
+/// Sequence of a slot holding no frame (the C++ core's SLOT_BEING_WRITTEN)
+pub(crate) const SLOT_EMPTY: u64 = u64::MAX;
+
+/// One consumer group, laid out as the C++ core's GroupDump
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GroupDump {
+    pub(crate) name: [u8; GROUP_NAME_SIZE],
+    pub(crate) cursor: u64,
+    pub(crate) members: i32,
+    pub(crate) reserved: i32,
+}
+
+impl Default for GroupDump {
+    fn default() -> Self {
+        Self {
+            name: [0; GROUP_NAME_SIZE],
+            cursor: 0,
+            members: 0,
+            reserved: 0,
+        }
+    }
+}
+
+/// One attached reader, laid out as the C++ core's ReaderDump
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ReaderDump {
+    pub(crate) cursor: u64,
+    pub(crate) attached_at_us: u64,
+    pub(crate) pid: i32,
+    pub(crate) alive: i32,
+}
+
+/// Ownership snapshot of a named segment, read without attaching
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SegmentStatus {
+    pub(crate) total_bytes: u64,
+    pub(crate) created_at_us: u64,
+    /// Increases every time a segment is created under the name: past the one
+    /// it replaced, and at least the creation time in microseconds
+    pub(crate) generation: u64,
+    pub(crate) writer_pid: i32,
+    pub(crate) writer_alive: i32,
+    pub(crate) reader_count: i32,
+    pub(crate) max_readers: i32,
+}
+
+/// Names of every segment `transport` currently holds
+pub(crate) fn list_segments(transport: Transport) -> Vec<String> {
+    match transport {
+        Transport::InProcess => crate::loopback::list_segments(),
+        #[cfg(qads_ffi)]
+        Transport::SharedMemory => crate::shm::list_segments(),
+        #[cfg(not(qads_ffi))]
+        Transport::SharedMemory => Vec::new(),
+        // The ring is private to the connection, there is nothing to list by name
+        #[cfg(feature = "rdma")]
+        Transport::Rdma(_) => Vec::new(),
+    }
+}
+
+pub(crate) fn inspect_segment(transport: Transport, name: &str) -> Option<SegmentStatus> {
+    match transport {
+        Transport::InProcess => crate::loopback::inspect_segment(name),
+        #[cfg(qads_ffi)]
+        Transport::SharedMemory => crate::shm::inspect_segment(name),
+        #[cfg(not(qads_ffi))]
+        Transport::SharedMemory => None,
+        #[cfg(feature = "rdma")]
+        Transport::Rdma(_) => None,
+    }
+}
+
+pub(crate) fn remove_segment(transport: Transport, name: &str) {
+    match transport {
+        Transport::InProcess => crate::loopback::remove_segment(name),
+        #[cfg(qads_ffi)]
+        Transport::SharedMemory => crate::shm::remove_segment(name),
+        #[cfg(not(qads_ffi))]
+        Transport::SharedMemory => {},
+        #[cfg(feature = "rdma")]
+        Transport::Rdma(_) => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+
+    #[test]
+    fn test_in_process_segments_are_listed_inspected_and_removed() -> Result<()> {
+        let writer = SharedDataFrame::create_writer(test_config("backend_status", 4))?;
+        let _reader = SharedDataFrame::create_reader(test_config("backend_status", 4))?;
+
+        assert!(list_segments(Transport::InProcess).contains(&"backend_status".to_string()));
+        let status = inspect_segment(Transport::InProcess, "backend_status").unwrap();
+        assert_eq!((status.writer_pid, status.writer_alive), (std::process::id() as i32, 1));
+        assert_eq!(status.reader_count, 1);
+        assert!(status.total_bytes > 0);
+
+        // Removal forgets the name even while the writer still holds the segment
+        remove_segment(Transport::InProcess, "backend_status");
+        assert!(inspect_segment(Transport::InProcess, "backend_status").is_none());
+        assert!(!list_segments(Transport::InProcess).contains(&"backend_status".to_string()));
+        drop(writer);
+        assert!(inspect_segment(Transport::InProcess, "backend_never_created").is_none());
+        Ok(())
+    }
+
+    #[cfg(not(qads_ffi))]
+    #[test]
+    fn test_shared_memory_needs_the_core() {
+        let config = test_config("backend_no_core", 4).with_transport(Transport::SharedMemory);
+        assert!(matches!(open("backend_no_core", &config, 4), Err(crate::QADataSwapError::SharedMemory(_))));
+        assert!(list_segments(Transport::SharedMemory).is_empty());
+        assert!(inspect_segment(Transport::SharedMemory, "backend_no_core").is_none());
+    }
+}
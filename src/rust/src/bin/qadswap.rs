@@ -0,0 +1,498 @@
+//! `qadswap`: command-line tools for QADataSwap arenas
+
+use std::fmt;
+use std::fs::File;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use datafusion::dataframe::DataFrameWriteOptions;
+use datafusion::prelude::SessionContext;
+use polars::prelude::{
+    Column, CsvWriter, DataFrame, DataType, JsonFormat, JsonWriter, ParquetWriter, SerWriter, TimeUnit,
+};
+use qadataswap::{QADataSwapError, RateLimit, SeekFrom, SharedArenaTable, SharedDataFrame, SharedMemoryConfig, TableSnapshot};
+
+#[derive(Parser)]
+#[command(name = "qadswap", version, about = "Inspect and query QADataSwap arenas")]
+struct Cli {
+    /// Namespace of the arena (defaults to QADATASWAP_NAMESPACE)
+    #[arg(long, global = true)]
+    namespace: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a SQL query over the latest frames of a live stream
+    Sql(SqlArgs),
+    /// Print the control block, slot states, group cursors and lock states of an arena
+    Dump(DumpArgs),
+    /// Sample a live stream and print its memory use and frame statistics
+    Stat(StatArgs),
+    /// Print frames as they arrive, like `tail -f`
+    Tail(TailArgs),
+    /// Write the frames a stream still holds, and optionally later ones, to a file
+    Export(ExportArgs),
+    /// Publish synthetic market-data frames to load-test consumers; like any
+    /// writer it blocks while the ring is full, so start the consumers too
+    Bench(BenchArgs),
+    /// Check an arena for liveness probes: exits 0 when healthy, 2 when the
+    /// arena is missing, 3 when its writer is gone or stale, 4 when a reader lags
+    Health(HealthArgs),
+}
+
+#[derive(Args)]
+struct SqlArgs {
+    /// Arena to attach to
+    arena: String,
+    /// Query; the stream is the table named after the arena unless --table is given
+    query: String,
+    /// Table name to use in the query
+    #[arg(long)]
+    table: Option<String>,
+    /// Query the last N frames instead of only the latest one
+    #[arg(long, default_value_t = 1)]
+    window: usize,
+    /// Keep accumulating frames for this long before running the query
+    #[arg(long, default_value_t = 0)]
+    collect_ms: u64,
+    /// How long to wait for the first frame
+    #[arg(long, default_value_t = 5000)]
+    timeout_ms: i32,
+    /// Write the result to this parquet file instead of printing it
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct DumpArgs {
+    /// Arena to attach to (as a reader, which does not consume frames)
+    arena: String,
+}
+
+#[derive(Args)]
+struct StatArgs {
+    /// Arena to attach to (reading from its own cursor, which does not consume frames)
+    arena: String,
+    /// How long to sample frames for
+    #[arg(long, default_value_t = 5000)]
+    duration_ms: u64,
+    /// Print a bar chart of each distribution under its quantiles
+    #[arg(long)]
+    histogram: bool,
+}
+
+#[derive(Args)]
+struct TailArgs {
+    /// Arena to attach to (reading from its own cursor, which does not consume frames)
+    arena: String,
+    /// Keep printing frames as they are published instead of exiting after the latest
+    #[arg(short, long)]
+    follow: bool,
+    /// Only print these columns
+    #[arg(long, value_delimiter = ',')]
+    select: Vec<String>,
+    /// Rows printed per frame
+    #[arg(short = 'n', long, default_value_t = 10)]
+    limit: usize,
+    /// Without --follow, how long to wait for a frame when none is retained
+    #[arg(long, default_value_t = 5000)]
+    timeout_ms: i32,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Arena to attach to (reading from its own cursor, which does not consume frames)
+    arena: String,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Parquet)]
+    format: ExportFormat,
+    /// File to write
+    #[arg(long)]
+    out: PathBuf,
+    /// After draining the retained frames, keep collecting new ones for this long
+    #[arg(long, default_value_t = 0)]
+    follow_ms: u64,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Parquet,
+    Csv,
+    /// One JSON object per row
+    Ndjson,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// Arena to create
+    #[arg(long, default_value = "bench")]
+    name: String,
+    /// Total rows to publish, e.g. 1e6
+    #[arg(long, default_value = "1e6", value_parser = parse_count)]
+    rows: usize,
+    /// Rows per frame
+    #[arg(long, default_value_t = 1000)]
+    batch_rows: usize,
+    /// JSON object of column name to type (f64, i64, str, timestamp); defaults
+    /// to timestamp, symbol, price, volume, bid, ask
+    #[arg(long)]
+    cols: Option<PathBuf>,
+    /// Pace publication, as frames ("1000/s") or megabytes ("50MB/s") per second
+    #[arg(long, value_parser = parse_rate)]
+    rate: Option<RateLimit>,
+    #[arg(long, default_value_t = 64)]
+    size_mb: usize,
+    #[arg(long, default_value_t = 4)]
+    buffer_count: usize,
+}
+
+#[derive(Args)]
+struct HealthArgs {
+    /// Arena to check (attaching as a reader, which does not consume frames)
+    arena: String,
+    /// Unhealthy when any reader or consumer group is more frames behind
+    #[arg(long)]
+    max_lag: Option<u64>,
+    /// Unhealthy when nothing, not even a heartbeat, was published for this long (e.g. 5s, 500ms)
+    #[arg(long, value_parser = parse_duration)]
+    max_staleness: Option<Duration>,
+}
+
+/// A failed health check, exiting with its own code
+#[derive(Debug)]
+struct Unhealthy {
+    code: u8,
+    reason: String,
+}
+
+impl fmt::Display for Unhealthy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unhealthy: {}", self.reason)
+    }
+}
+
+impl std::error::Error for Unhealthy {}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Sql(args) => sql(cli.namespace.as_deref(), args),
+        Command::Dump(args) => dump(cli.namespace.as_deref(), args),
+        Command::Stat(args) => stat(cli.namespace.as_deref(), args),
+        Command::Tail(args) => tail(cli.namespace.as_deref(), args),
+        Command::Export(args) => export(cli.namespace.as_deref(), args),
+        Command::Bench(args) => bench(cli.namespace.as_deref(), args),
+        Command::Health(args) => health(cli.namespace.as_deref(), args),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.is::<Unhealthy>() => {
+            println!("{}", e);
+            ExitCode::from(e.downcast_ref::<Unhealthy>().unwrap().code)
+        },
+        Err(e) => {
+            eprintln!("qadswap: {:#}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn config(namespace: Option<&str>, arena: &str) -> SharedMemoryConfig {
+    let config = SharedMemoryConfig::new(arena);
+    match namespace {
+        Some(namespace) => config.with_namespace(namespace),
+        None => config,
+    }
+}
+
+fn sql(namespace: Option<&str>, args: SqlArgs) -> anyhow::Result<()> {
+    let reader = SharedDataFrame::create_reader(config(namespace, &args.arena).with_timeout_ms(args.timeout_ms))?;
+    let snapshot = match args.window {
+        0 | 1 => TableSnapshot::Latest,
+        n => TableSnapshot::Window(n),
+    };
+    let table = SharedArenaTable::new(reader, snapshot)?;
+    std::thread::sleep(Duration::from_millis(args.collect_ms));
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let ctx = SessionContext::new();
+        ctx.register_table(args.table.as_deref().unwrap_or(&args.arena), Arc::new(table))?;
+        let df = ctx.sql(&args.query).await?;
+        match &args.output {
+            Some(path) => {
+                df.write_parquet(&path.to_string_lossy(), DataFrameWriteOptions::new(), None).await?;
+            },
+            None => df.show().await?,
+        }
+        Ok(())
+    })
+}
+
+fn dump(namespace: Option<&str>, args: DumpArgs) -> anyhow::Result<()> {
+    let reader = SharedDataFrame::create_reader(config(namespace, &args.arena))?;
+    println!("{}", reader.debug_dump()?);
+    Ok(())
+}
+
+fn stat(namespace: Option<&str>, args: StatArgs) -> anyhow::Result<()> {
+    let reader = SharedDataFrame::create_reader(config(namespace, &args.arena).with_histograms())?;
+    reader.seek(SeekFrom::Latest)?;
+    let deadline = Instant::now() + Duration::from_millis(args.duration_ms);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now()).as_millis() as i32;
+        if remaining == 0 {
+            break;
+        }
+        match reader.read(Some(remaining)) {
+            Ok(_) => {},
+            Err(QADataSwapError::Timeout) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    print!("{}", reader.memory_report()?);
+    let histograms = reader.histograms().unwrap_or_default();
+    println!("frames read in {} ms: {} (encode times are only known to the writer)",
+             args.duration_ms, reader.read_stats().frames);
+    match args.histogram {
+        true => print!("{:#}", histograms),
+        false => print!("{}", histograms),
+    }
+    Ok(())
+}
+
+fn tail(namespace: Option<&str>, args: TailArgs) -> anyhow::Result<()> {
+    let reader = SharedDataFrame::create_reader(config(namespace, &args.arena))?;
+    match reader.retained_range() {
+        (start, end) if end > start => reader.seek(SeekFrom::Sequence(end - 1))?,
+        _ => reader.seek(SeekFrom::Latest)?,
+    }
+
+    let timeout = if args.follow { -1 } else { args.timeout_ms };
+    loop {
+        let (metadata, df) = match reader.read_with_metadata(Some(timeout)) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(()),
+            Err(QADataSwapError::Timeout) if !args.follow => anyhow::bail!("no frame within {} ms", timeout),
+            Err(e) => return Err(e.into()),
+        };
+        let df = match args.select.is_empty() {
+            true => df,
+            false => df.select(args.select.iter().map(String::as_str))?,
+        };
+        println!("--- #{} from writer {} at {}.{:06}, {} rows x {} columns",
+                 metadata.sequence.map_or("?".to_string(), |sequence| sequence.to_string()), metadata.writer_id,
+                 metadata.timestamp_us / 1_000_000, metadata.timestamp_us % 1_000_000, df.height(), df.width());
+        println!("{}", df.head(Some(args.limit)));
+        if !args.follow {
+            return Ok(());
+        }
+    }
+}
+
+fn export(namespace: Option<&str>, args: ExportArgs) -> anyhow::Result<()> {
+    let reader = SharedDataFrame::create_reader(config(namespace, &args.arena))?;
+    match reader.retained_range() {
+        (start, end) if end > start => reader.seek(SeekFrom::Earliest)?,
+        _ => reader.seek(SeekFrom::Latest)?,
+    }
+
+    let deadline = Instant::now() + Duration::from_millis(args.follow_ms);
+    let (mut rows, mut frames): (Option<DataFrame>, usize) = (None, 0);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now()).as_millis() as i32;
+        let df = match reader.read(Some(remaining)) {
+            Ok(Some(df)) => df,
+            Ok(None) | Err(QADataSwapError::Timeout) => break,
+            Err(e) => return Err(e.into()),
+        };
+        match rows.as_mut() {
+            Some(rows) => {
+                rows.vstack_mut(&df)?;
+            },
+            None => rows = Some(df),
+        }
+        frames += 1;
+    }
+    let Some(mut df) = rows else {
+        anyhow::bail!("no frames in '{}' to export", args.arena);
+    };
+
+    let mut file = File::create(&args.out)?;
+    match args.format {
+        ExportFormat::Parquet => {
+            ParquetWriter::new(file).finish(&mut df)?;
+        },
+        ExportFormat::Csv => CsvWriter::new(&mut file).finish(&mut df)?,
+        ExportFormat::Ndjson => JsonWriter::new(&mut file).with_json_format(JsonFormat::JsonLines).finish(&mut df)?,
+    }
+    eprintln!("exported {} frames, {} rows to {}", frames, df.height(), args.out.display());
+    Ok(())
+}
+
+/// Columns `bench` generates when no --cols file is given
+const DEFAULT_BENCH_COLUMNS: [(&str, &str); 6] =
+    [("timestamp", "timestamp"), ("symbol", "str"), ("price", "f64"), ("volume", "i64"), ("bid", "f64"), ("ask", "f64")];
+
+const SYMBOLS: [&str; 8] = ["rb2410", "cu2409", "au2412", "IF2409", "AAPL", "MSFT", "NVDA", "TSLA"];
+
+fn bench(namespace: Option<&str>, args: BenchArgs) -> anyhow::Result<()> {
+    let columns: Vec<(String, String)> = match &args.cols {
+        Some(path) => {
+            let spec: serde_json::Map<String, serde_json::Value> = serde_json::from_reader(File::open(path)?)?;
+            spec.into_iter()
+                .map(|(name, dtype)| match dtype.as_str() {
+                    Some(dtype) => Ok((name, dtype.to_string())),
+                    None => Err(anyhow::anyhow!("type of column '{}' must be a string", name)),
+                })
+                .collect::<anyhow::Result<_>>()?
+        },
+        None => DEFAULT_BENCH_COLUMNS.iter().map(|(name, dtype)| (name.to_string(), dtype.to_string())).collect(),
+    };
+
+    let mut config = config(namespace, &args.name).with_size_mb(args.size_mb).with_buffer_count(args.buffer_count)
+        .with_histograms();
+    if let Some(rate) = args.rate {
+        config = config.with_rate_limit(rate);
+    }
+    let writer = SharedDataFrame::create_writer(config)?;
+
+    let mut generator = Generator::new();
+    let started = Instant::now();
+    let mut written = 0;
+    while written < args.rows {
+        let rows = args.batch_rows.max(1).min(args.rows - written);
+        writer.write(&generator.frame(&columns, rows)?)?;
+        written += rows;
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let frames = writer.write_stats().frames;
+    println!("published {} rows in {} frames in {:.2}s: {:.0} rows/s, {:.0} frames/s",
+             written, frames, elapsed, written as f64 / elapsed, frames as f64 / elapsed);
+    print!("{}", writer.histograms().unwrap_or_default());
+    Ok(())
+}
+
+/// Random walks per column from a xorshift generator, so runs are repeatable
+struct Generator {
+    state: u64,
+    row: usize,
+}
+
+impl Generator {
+    fn new() -> Self {
+        Self { state: 0x9e37_79b9_7f4a_7c15, row: 0 }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Uniform in [-0.5, 0.5)
+    fn jitter(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+    }
+
+    fn frame(&mut self, columns: &[(String, String)], rows: usize) -> anyhow::Result<DataFrame> {
+        let first = self.row;
+        self.row += rows;
+        let now_us = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_micros() as i64;
+        let columns = columns
+            .iter()
+            .map(|(name, dtype)| {
+                let name = name.as_str().into();
+                Ok(match dtype.as_str() {
+                    "f64" => {
+                        let values: Vec<f64> =
+                            (first..first + rows).map(|i| 100.0 + (i % 1000) as f64 * 0.1 + self.jitter()).collect();
+                        Column::new(name, values)
+                    },
+                    "i64" => Column::new(name, (0..rows).map(|_| 100 + (self.next() % 10_000) as i64).collect::<Vec<_>>()),
+                    "str" => Column::new(name, (first..first + rows).map(|i| SYMBOLS[i % SYMBOLS.len()]).collect::<Vec<_>>()),
+                    "timestamp" => Column::new(name, (0..rows as i64).map(|i| now_us + i).collect::<Vec<_>>())
+                        .cast(&DataType::Datetime(TimeUnit::Microseconds, None))?,
+                    other => anyhow::bail!("unknown column type '{}', expected f64, i64, str or timestamp", other),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(DataFrame::new(columns)?)
+    }
+}
+
+/// A row count, also in scientific notation
+fn parse_count(value: &str) -> Result<usize, String> {
+    match value.parse::<f64>() {
+        Ok(count) if count >= 0.0 && count.fract() == 0.0 => Ok(count as usize),
+        _ => Err(format!("'{}' is not a whole number of rows", value)),
+    }
+}
+
+/// "1000/s" for frames, "50MB/s" for bytes per second
+fn parse_rate(value: &str) -> Result<RateLimit, String> {
+    let invalid = || format!("'{}' is not a rate like 1000/s or 50MB/s", value);
+    let per_sec = value.strip_suffix("/s").ok_or_else(invalid)?;
+    match per_sec.strip_suffix("MB") {
+        Some(mb) => mb.parse::<f64>().map(|mb| RateLimit::BytesPerSec(mb * 1024.0 * 1024.0)).map_err(|_| invalid()),
+        None => per_sec.parse::<f64>().map(RateLimit::FramesPerSec).map_err(|_| invalid()),
+    }
+}
+
+fn health(namespace: Option<&str>, args: HealthArgs) -> anyhow::Result<()> {
+    let unhealthy = |code, reason: String| Err(Unhealthy { code, reason }.into());
+    let config = config(namespace, &args.arena);
+    if !qadataswap::list_arenas(config.transport, config.namespace.as_deref()).contains(&args.arena) {
+        return unhealthy(2, format!("arena '{}' does not exist", args.arena));
+    }
+    let reader = SharedDataFrame::create_reader(config)?;
+    let dump = reader.debug_dump()?;
+    if !dump.writer_active {
+        return unhealthy(3, format!("arena '{}' has no live writer", args.arena));
+    }
+
+    // Heartbeats are frames too, so the newest slot is the writer's last sign of life
+    let last_us = dump.slots.iter().filter(|slot| slot.sequence.is_some()).map(|slot| slot.timestamp_us).max()
+        .unwrap_or(dump.created_at_us);
+    let now_us = SystemTime::now().duration_since(UNIX_EPOCH)?.as_micros() as u64;
+    let idle = Duration::from_micros(now_us.saturating_sub(last_us));
+    if let Some(max_staleness) = args.max_staleness.filter(|max| idle > *max) {
+        return unhealthy(3, format!("nothing published for {:?} (max {:?})", idle, max_staleness));
+    }
+
+    let own_pid = std::process::id();
+    let lags = reader.readers()?
+        .into_iter()
+        .filter(|info| info.alive && info.pid != own_pid)
+        .map(|info| (format!("reader pid {}", info.pid), info.cursor))
+        .chain(dump.groups.iter().map(|group| (format!("group '{}'", group.name), group.cursor)))
+        .map(|(who, cursor)| (who, dump.publish_sequence.saturating_sub(cursor)));
+    let (laggard, lag) = lags.max_by_key(|(_, lag)| *lag).unwrap_or_default();
+    if let Some(max_lag) = args.max_lag.filter(|max| lag > *max) {
+        return unhealthy(4, format!("{} is {} frames behind (max {})", laggard, lag, max_lag));
+    }
+
+    println!("ok: '{}' last published {:?} ago, max reader lag {} frames", args.arena, idle, lag);
+    Ok(())
+}
+
+/// "5s", "500ms", "2m"; a bare number is seconds
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("'{}' is not a duration like 5s or 500ms", value);
+    let (number, scale) = match value {
+        v if v.ends_with("ms") => (&v[..v.len() - 2], 0.001),
+        v if v.ends_with('s') => (&v[..v.len() - 1], 1.0),
+        v if v.ends_with('m') => (&v[..v.len() - 1], 60.0),
+        v => (v, 1.0),
+    };
+    number.parse::<f64>().ok().filter(|n| *n >= 0.0).map(|n| Duration::from_secs_f64(n * scale)).ok_or_else(invalid)
+}
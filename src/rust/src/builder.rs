@@ -0,0 +1,269 @@
+//! In-place frame construction directly inside a shared memory slot
+
+use std::mem::size_of;
+use std::ptr;
+
+use polars::prelude::*;
+
+use crate::frame::{FrameHeader, FrameKind, FRAME_HEADER_SIZE};
+use crate::{QADataSwapError, Result, SharedMemoryArena};
+
+const COLUMN_ALIGNMENT: usize = 64;
+const TRAILER_SIZE: usize = 12;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Primitive types `FrameBuilder` can lay out directly in shared memory
+pub trait FrameNative: sealed::Sealed + Copy + 'static {
+    #[doc(hidden)]
+    const TAG: u8;
+    #[doc(hidden)]
+    fn into_series(name: &str, values: Vec<Self>) -> Series;
+    #[doc(hidden)]
+    fn read_le(bytes: &[u8]) -> Self;
+    #[doc(hidden)]
+    fn write_le(self, out: &mut [u8]);
+}
+
+macro_rules! frame_native {
+    ($($ty:ty => $tag:expr),* $(,)?) => {$(
+        impl sealed::Sealed for $ty {}
+        impl FrameNative for $ty {
+            const TAG: u8 = $tag;
+            fn into_series(name: &str, values: Vec<Self>) -> Series {
+                Series::new(name.into(), values)
+            }
+            fn read_le(bytes: &[u8]) -> Self {
+                <$ty>::from_le_bytes(bytes[..size_of::<$ty>()].try_into().unwrap())
+            }
+            fn write_le(self, out: &mut [u8]) {
+                out[..size_of::<$ty>()].copy_from_slice(&self.to_le_bytes());
+            }
+        }
+    )*};
+}
+
+frame_native!(i32 => 0, i64 => 1, u32 => 2, u64 => 3, f32 => 4, f64 => 5);
+
+struct ColumnEntry {
+    name: String,
+    tag: u8,
+    offset: usize,
+    rows: usize,
+}
+
+/// Builds a frame of primitive, non-null columns straight into the next ring
+/// slot: each `append_column` is a single copy from the caller's slice into
+/// shared memory, with no IPC encoding and no intermediate buffer.
+///
+/// Layout after the frame header: column data (64-byte aligned), a column
+/// directory, then a trailer with the column count and directory offset.
+/// The slot stays reserved until `publish()`; dropping the builder releases it.
+pub struct FrameBuilder<'a> {
+    arena: &'a SharedMemoryArena,
+    slot: *mut u8,
+    capacity: usize,
+    len: usize,
+    columns: Vec<ColumnEntry>,
+    finished: bool,
+}
+
+impl<'a> FrameBuilder<'a> {
+    pub(crate) fn new(arena: &'a SharedMemoryArena) -> Result<Self> {
+        let (slot, capacity) = arena.acquire_write_buffer()?;
+        if capacity < FRAME_HEADER_SIZE + TRAILER_SIZE {
+            arena.release_acquired();
+            return Err(QADataSwapError::SharedMemory("Slot too small for a frame".to_string()));
+        }
+
+        Ok(Self {
+            arena,
+            slot,
+            capacity: capacity - FRAME_HEADER_SIZE,
+            len: 0,
+            columns: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Copy `values` into the slot as a new column; every column must have the same length
+    pub fn append_column<T: FrameNative>(&mut self, name: &str, values: &[T]) -> Result<&mut Self> {
+        if let Some(first) = self.columns.first() {
+            if first.rows != values.len() {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Column '{}' has {} rows, expected {}", name, values.len(), first.rows
+                )));
+            }
+        }
+
+        let offset = self.len.next_multiple_of(COLUMN_ALIGNMENT);
+        let bytes = std::mem::size_of_val(values);
+        let directory_entry = 2 + name.len() + 1 + 16;
+        let reserved: usize = self.columns.iter().map(|c| 2 + c.name.len() + 1 + 16).sum();
+        if offset + bytes + reserved + directory_entry + TRAILER_SIZE > self.capacity {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Column '{}' does not fit in the remaining {} bytes of the slot",
+                name, self.remaining()
+            )));
+        }
+
+        unsafe {
+            ptr::copy_nonoverlapping(values.as_ptr() as *const u8, self.payload().add(offset), bytes);
+        }
+        self.len = offset + bytes;
+        self.columns.push(ColumnEntry {
+            name: name.to_string(),
+            tag: T::TAG,
+            offset,
+            rows: values.len(),
+        });
+        Ok(self)
+    }
+
+    /// Payload bytes still free in the slot
+    pub fn remaining(&self) -> usize {
+        self.capacity.saturating_sub(self.len + TRAILER_SIZE)
+    }
+
+    /// Make the frame visible to readers
+    pub fn publish(mut self) -> Result<()> {
+        let tail = encode_directory(&self.columns, self.len);
+
+        let payload_len = self.len + tail.len();
+        let header = FrameHeader::new_with_clock(FrameKind::Columns, self.arena.writer_id(), payload_len, self.arena.config.clock)
+            .stamped_by(&*self.arena.config.time_source);
+
+        unsafe {
+            ptr::copy_nonoverlapping(tail.as_ptr(), self.payload().add(self.len), tail.len());
+            ptr::copy_nonoverlapping(header.to_bytes().as_ptr(), self.slot, FRAME_HEADER_SIZE);
+        }
+
+        self.finished = true;
+        self.arena.publish_acquired(FRAME_HEADER_SIZE + payload_len)
+    }
+
+    /// Release the slot without publishing
+    pub fn abort(mut self) {
+        self.finished = true;
+        self.arena.release_acquired();
+    }
+
+    fn payload(&self) -> *mut u8 {
+        unsafe { self.slot.add(FRAME_HEADER_SIZE) }
+    }
+}
+
+impl<'a> Drop for FrameBuilder<'a> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.arena.release_acquired();
+        }
+    }
+}
+
+/// Column directory and trailer, written after the column data
+fn encode_directory(columns: &[ColumnEntry], data_len: usize) -> Vec<u8> {
+    let mut tail = Vec::new();
+    for column in columns {
+        tail.extend_from_slice(&(column.name.len() as u16).to_le_bytes());
+        tail.extend_from_slice(column.name.as_bytes());
+        tail.push(column.tag);
+        tail.extend_from_slice(&(column.offset as u64).to_le_bytes());
+        tail.extend_from_slice(&(column.rows as u64).to_le_bytes());
+    }
+    tail.extend_from_slice(&(columns.len() as u32).to_le_bytes());
+    tail.extend_from_slice(&(data_len as u64).to_le_bytes());
+    tail
+}
+
+/// Decode a `FrameKind::Columns` payload
+pub(crate) fn decode_columns(payload: &[u8]) -> Result<DataFrame> {
+    let corrupt = || QADataSwapError::SharedMemory("Corrupt column frame".to_string());
+
+    if payload.len() < TRAILER_SIZE {
+        return Err(corrupt());
+    }
+    let trailer = &payload[payload.len() - TRAILER_SIZE..];
+    let count = u32::from_le_bytes(trailer[0..4].try_into().unwrap()) as usize;
+    let mut pos = u64::from_le_bytes(trailer[4..12].try_into().unwrap()) as usize;
+    let directory = &payload[..payload.len() - TRAILER_SIZE];
+
+    // Every entry takes at least 19 bytes, so a corrupt count cannot reserve more
+    let mut columns = Vec::with_capacity(count.min(directory.len() / 19));
+    for _ in 0..count {
+        let name_len = directory.get(pos..).and_then(|rest| rest.get(..2)).ok_or_else(corrupt)?;
+        let name_len = u16::from_le_bytes(name_len.try_into().unwrap()) as usize;
+        pos += 2;
+        let entry = directory.get(pos..).and_then(|rest| rest.get(..name_len + 17)).ok_or_else(corrupt)?;
+        let name = std::str::from_utf8(&entry[..name_len]).map_err(|_| corrupt())?;
+        let tag = entry[name_len];
+        let offset = u64::from_le_bytes(entry[name_len + 1..name_len + 9].try_into().unwrap()) as usize;
+        let rows = u64::from_le_bytes(entry[name_len + 9..name_len + 17].try_into().unwrap()) as usize;
+        pos += name_len + 17;
+
+        let series = match tag {
+            0 => column_from_bytes::<i32>(name, payload, offset, rows),
+            1 => column_from_bytes::<i64>(name, payload, offset, rows),
+            2 => column_from_bytes::<u32>(name, payload, offset, rows),
+            3 => column_from_bytes::<u64>(name, payload, offset, rows),
+            4 => column_from_bytes::<f32>(name, payload, offset, rows),
+            5 => column_from_bytes::<f64>(name, payload, offset, rows),
+            _ => None,
+        }.ok_or_else(corrupt)?;
+        columns.push(series.into_column());
+    }
+
+    DataFrame::new(columns).map_err(QADataSwapError::Polars)
+}
+
+fn column_from_bytes<T: FrameNative>(name: &str, payload: &[u8], offset: usize, rows: usize) -> Option<Series> {
+    let bytes = rows.checked_mul(size_of::<T>())?;
+    let src = payload.get(offset..offset.checked_add(bytes)?)?;
+
+    let mut values: Vec<T> = Vec::with_capacity(rows);
+    unsafe {
+        ptr::copy_nonoverlapping(src.as_ptr(), values.as_mut_ptr() as *mut u8, bytes);
+        values.set_len(rows);
+    }
+    Some(T::into_series(name, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_payload_round_trip() -> Result<()> {
+        let ids: [i64; 3] = [1, 2, 3];
+        let prices: [f64; 3] = [1.5, 2.5, 3.5];
+
+        let mut payload = vec![0u8; 64 + 24];
+        payload[..24].copy_from_slice(unsafe { std::slice::from_raw_parts(ids.as_ptr() as *const u8, 24) });
+        payload[64..].copy_from_slice(unsafe { std::slice::from_raw_parts(prices.as_ptr() as *const u8, 24) });
+        let columns = [
+            ColumnEntry { name: "id".to_string(), tag: i64::TAG, offset: 0, rows: 3 },
+            ColumnEntry { name: "price".to_string(), tag: f64::TAG, offset: 64, rows: 3 },
+        ];
+        let data_len = payload.len();
+        payload.extend_from_slice(&encode_directory(&columns, data_len));
+
+        let df = decode_columns(&payload)?;
+        assert_eq!(df.shape(), (3, 2));
+        let decoded: Vec<Option<f64>> = df.column("price").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(decoded, vec![Some(1.5), Some(2.5), Some(3.5)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_directory_is_rejected() {
+        // A trailer claiming u32::MAX columns whose directory starts past the end
+        let mut payload = vec![0u8; 8];
+        payload.extend_from_slice(&u32::MAX.to_le_bytes());
+        payload.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(decode_columns(&payload).is_err());
+        payload[12..].copy_from_slice(&0u64.to_le_bytes());
+        assert!(decode_columns(&payload).is_err());
+    }
+}
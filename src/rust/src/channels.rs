@@ -0,0 +1,222 @@
+//! Typed channels: names declared together with their schemas at compile time
+//!
+//! ```ignore
+//! qadataswap::channels! {
+//!     /// Level-1 quotes
+//!     pub QuotesChannel = "quotes" {
+//!         "symbol" => String,
+//!         "bid" => Float64,
+//!         "ask" => Float64,
+//!     }
+//! }
+//!
+//! let writer = QuotesChannel::writer()?;
+//! let reader = QuotesChannel::reader()?;
+//! ```
+//!
+//! A typed writer publishes a fingerprint of its schema in the control
+//! block's metadata entry `CHANNEL_SCHEMA_META_KEY` and refuses frames of any
+//! other schema. A typed reader refuses to attach to an arena carrying a
+//! different fingerprint, the usual sign of a typo'd or reused arena name, and
+//! rejects frames that do not match when no typed writer vouched for them.
+
+use std::marker::PhantomData;
+
+use polars::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::{FrameMetadata, QADataSwapError, Result, SharedDataFrame, SharedMemoryConfig};
+
+/// Metadata key holding the schema fingerprint of a typed channel
+pub const CHANNEL_SCHEMA_META_KEY: &str = "__schema";
+
+/// A named channel with a fixed schema, implemented by `channels!`
+pub trait Channel {
+    const NAME: &'static str;
+    /// Column names and dtypes, in order
+    const COLUMNS: &'static [(&'static str, DataType)];
+
+    fn schema() -> Schema {
+        Self::COLUMNS.iter().map(|(name, dtype)| Field::new((*name).into(), dtype.clone())).collect()
+    }
+
+    /// Default settings for the channel; the typed constructors force `NAME`
+    fn config() -> SharedMemoryConfig {
+        SharedMemoryConfig::new(Self::NAME)
+    }
+}
+
+/// Declare typed channels, see the `channels` module
+#[macro_export]
+macro_rules! channels {
+    ($(
+        $(#[$meta:meta])*
+        $vis:vis $channel:ident = $name:literal { $($column:literal => $dtype:ident),* $(,)? }
+    )*) => {$(
+        $(#[$meta])*
+        $vis struct $channel;
+
+        impl $crate::channels::Channel for $channel {
+            const NAME: &'static str = $name;
+            const COLUMNS: &'static [(&'static str, $crate::channels::__private::DataType)] =
+                &[$(($column, $crate::channels::__private::DataType::$dtype)),*];
+        }
+
+        // Generated for every channel, so not every one is used
+        #[allow(dead_code)]
+        impl $channel {
+            pub fn writer() -> $crate::Result<$crate::channels::TypedWriter<Self>> {
+                $crate::channels::TypedWriter::create(<Self as $crate::channels::Channel>::config())
+            }
+
+            pub fn reader() -> $crate::Result<$crate::channels::TypedReader<Self>> {
+                $crate::channels::TypedReader::attach(<Self as $crate::channels::Channel>::config())
+            }
+        }
+    )*};
+}
+
+/// Writer half of a typed channel
+pub struct TypedWriter<C: Channel> {
+    inner: SharedDataFrame,
+    schema: Schema,
+    channel: PhantomData<C>,
+}
+
+impl<C: Channel> TypedWriter<C> {
+    /// Create the channel's arena with `config`, whatever name it carries
+    pub fn create(config: SharedMemoryConfig) -> Result<Self> {
+        let schema = C::schema();
+        let inner = SharedDataFrame::create_writer(SharedMemoryConfig { name: C::NAME.to_string(), ..config })?;
+        inner.put_meta(CHANNEL_SCHEMA_META_KEY, &fingerprint(&schema))?;
+        Ok(Self {
+            inner,
+            schema,
+            channel: PhantomData,
+        })
+    }
+
+    /// Fails without publishing if `df` does not have the channel's schema
+    pub fn write(&self, df: &DataFrame) -> Result<()> {
+        check::<C>(&self.schema, df)?;
+        self.inner.write(df)
+    }
+
+    /// The untyped writer, for everything else
+    pub fn inner(&self) -> &SharedDataFrame {
+        &self.inner
+    }
+}
+
+/// Reader half of a typed channel
+pub struct TypedReader<C: Channel> {
+    inner: SharedDataFrame,
+    schema: Schema,
+    /// Whether a typed writer published the same schema
+    vouched: bool,
+    channel: PhantomData<C>,
+}
+
+impl<C: Channel> TypedReader<C> {
+    /// Attach to the channel's arena with `config`, whatever name it carries
+    pub fn attach(config: SharedMemoryConfig) -> Result<Self> {
+        let schema = C::schema();
+        let inner = SharedDataFrame::create_reader(SharedMemoryConfig { name: C::NAME.to_string(), ..config })?;
+        let vouched = match inner.get_meta(CHANNEL_SCHEMA_META_KEY) {
+            Some(published) if published != fingerprint(&schema) => {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Arena '{}' carries a different schema than channel {} declares", C::NAME,
+                    std::any::type_name::<C>()
+                )))
+            },
+            published => published.is_some(),
+        };
+        Ok(Self {
+            inner,
+            schema,
+            vouched,
+            channel: PhantomData,
+        })
+    }
+
+    pub fn read(&self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
+        Ok(self.read_with_metadata(timeout_ms)?.map(|(_, df)| df))
+    }
+
+    pub fn read_with_metadata(&self, timeout_ms: Option<i32>) -> Result<Option<(FrameMetadata, DataFrame)>> {
+        let frame = self.inner.read_with_metadata(timeout_ms)?;
+        if let (Some((_, df)), false) = (&frame, self.vouched) {
+            check::<C>(&self.schema, df)?;
+        }
+        Ok(frame)
+    }
+
+    /// The untyped reader, for everything else
+    pub fn inner(&self) -> &SharedDataFrame {
+        &self.inner
+    }
+}
+
+fn check<C: Channel>(schema: &Schema, df: &DataFrame) -> Result<()> {
+    if df.schema().as_ref() == schema {
+        return Ok(());
+    }
+    Err(QADataSwapError::SharedMemory(format!(
+        "Frame with schema {:?} does not match channel '{}' ({:?})", df.schema(), C::NAME, schema
+    )))
+}
+
+/// SHA-256 of the column names and dtypes, in hex
+fn fingerprint(schema: &Schema) -> String {
+    let mut digest = Sha256::new();
+    for (name, dtype) in schema.iter() {
+        digest.update(name.as_bytes());
+        digest.update([0]);
+        digest.update(dtype.to_string().as_bytes());
+        digest.update([0]);
+    }
+    digest.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Used by `channels!`
+#[doc(hidden)]
+pub mod __private {
+    pub use polars::prelude::DataType;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use polars::df;
+
+    crate::channels! {
+        /// Level-1 quotes
+        pub QuotesChannel = "typed_quotes" {
+            "symbol" => String,
+            "bid" => Float64,
+        }
+        TradesChannel = "typed_quotes" { "symbol" => String, "qty" => Int64 }
+    }
+
+    /// The typed constructors replace the name with the channel's
+    fn config() -> SharedMemoryConfig {
+        test_config("ignored", 4)
+    }
+
+    #[test]
+    fn test_typed_channels_check_schema() -> Result<()> {
+        let writer = TypedWriter::<QuotesChannel>::create(config())?;
+        let reader = TypedReader::<QuotesChannel>::attach(config())?;
+        assert_eq!(QuotesChannel::COLUMNS[1], ("bid", DataType::Float64));
+
+        let quotes = df! { "symbol" => ["rb2410"], "bid" => [3500.0] }?;
+        writer.write(&quotes)?;
+        assert_eq!(reader.read(Some(100))?, Some(quotes));
+        assert!(writer.write(&df! { "symbol" => ["rb2410"] }?).is_err());
+
+        // Same arena name, different declared schema
+        assert!(TypedReader::<TradesChannel>::attach(config()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+//! Frame timestamp clocks
+//!
+//! Writers stamp every frame header in nanoseconds on the clock chosen with
+//! `with_clock`, and the header records which clock that was, so readers on
+//! the same host can take `FrameMetadata::age_ns()` against the same clock.
+//! `Realtime` is the wall clock and the only one comparable across hosts.
+//! `Monotonic` never steps with NTP adjustments. `Tsc` reads the CPU's
+//! invariant time-stamp counter, converted to `Monotonic` nanoseconds with a
+//! calibration shared by every process on the host through
+//! `TSC_CALIBRATION_PATH`; hosts without an invariant TSC fall back to
+//! `Monotonic`.
+//!
+//! Where those nanoseconds come from goes through a `Clock`: the `SystemClock`
+//! unless `with_time_source` installs another, such as a `MockClock` in tests.
+//! So do the arena's other readings of time: TTL expiry, read, wait and
+//! handover deadlines, market hours, stall supervision, rate limiting, policy
+//! polling, stats snapshots and histograms, time-based seeks and retention on
+//! the in-process transport, merge and duplex polling, archive rolls and
+//! replay pacing.
+//!
+//! Real time still passes in blocking waits inside the backend, in
+//! `AdaptiveWait`'s spin budget and in measured encode and decode times. The
+//! C++ core stamps shared-memory slots, and applies retention to them, on the
+//! host's wall clock, so time-based seeks there need a clock close to it.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Where the host's TSC calibration is kept: `<tsc> <monotonic ns> <ns per tick>`
+pub const TSC_CALIBRATION_PATH: &str = "/dev/shm/qads_tsc_calibration";
+
+/// A stored calibration that predicts the monotonic clock worse than this is
+/// stale (a reboot, or another machine's file) and is redone
+#[cfg(target_arch = "x86_64")]
+const MAX_CALIBRATION_SKEW_NS: u64 = 1_000_000;
+
+/// How long calibration measures the TSC against the monotonic clock
+#[cfg(target_arch = "x86_64")]
+const CALIBRATION_WINDOW: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// Clock a writer stamps frames with, see `with_clock`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ClockSource {
+    /// `CLOCK_REALTIME`, nanoseconds since the Unix epoch
+    #[default]
+    Realtime,
+    /// `CLOCK_MONOTONIC`, nanoseconds since an arbitrary point fixed at boot
+    Monotonic,
+    /// The time-stamp counter, in `Monotonic` nanoseconds
+    Tsc,
+}
+
+impl ClockSource {
+    /// Now on this clock, in nanoseconds
+    pub fn now_ns(self) -> u64 {
+        match self {
+            ClockSource::Realtime => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0),
+            ClockSource::Monotonic => monotonic_ns(),
+            ClockSource::Tsc => tsc_ns().unwrap_or_else(monotonic_ns),
+        }
+    }
+
+    pub(crate) fn as_bits(self) -> u16 {
+        match self {
+            ClockSource::Realtime => 0,
+            ClockSource::Monotonic => 1,
+            ClockSource::Tsc => 2,
+        }
+    }
+
+    pub(crate) fn from_bits(bits: u16) -> Option<Self> {
+        match bits {
+            0 => Some(ClockSource::Realtime),
+            1 => Some(ClockSource::Monotonic),
+            2 => Some(ClockSource::Tsc),
+            _ => None,
+        }
+    }
+}
+
+/// Source of time for an arena, see `SharedMemoryConfig::with_time_source`
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Now on `source`, in nanoseconds; stamps frames and expires their TTLs
+    fn now_ns(&self, source: ClockSource) -> u64;
+    /// For deadlines and intervals
+    fn instant(&self) -> Instant;
+    /// Wall-clock time, for stats snapshots and time-based seeks
+    fn now(&self) -> SystemTime;
+    /// Let `duration` pass
+    fn sleep(&self, duration: Duration);
+}
+
+/// The host's clocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ns(&self, source: ClockSource) -> u64 {
+        source.now_ns()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// Simulated time that stands still until `advance`d, for deterministic tests
+/// and replays that should not wait out recorded gaps. `sleep` advances it.
+/// Blocking waits inside the backend still take real time.
+#[derive(Debug)]
+pub struct MockClock {
+    start: SystemTime,
+    base: Instant,
+    elapsed_ns: AtomicU64,
+}
+
+impl MockClock {
+    /// Wall-clock time starts at `start`
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            start,
+            base: Instant::now(),
+            elapsed_ns: AtomicU64::new(0),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.elapsed_ns.fetch_add(by.as_nanos() as u64, Ordering::AcqRel);
+    }
+
+    /// Time advanced since the clock was created
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_ns.load(Ordering::Acquire))
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(SystemTime::now())
+    }
+}
+
+impl Clock for MockClock {
+    /// `Monotonic` and `Tsc` count from the clock's creation
+    fn now_ns(&self, source: ClockSource) -> u64 {
+        match source {
+            ClockSource::Realtime => self.now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64),
+            ClockSource::Monotonic | ClockSource::Tsc => self.elapsed().as_nanos() as u64,
+        }
+    }
+
+    fn instant(&self) -> Instant {
+        self.base + self.elapsed()
+    }
+
+    fn now(&self) -> SystemTime {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+fn monotonic_ns() -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Calibration {
+    tsc: u64,
+    monotonic_ns: u64,
+    ns_per_tick: f64,
+}
+
+impl Calibration {
+    fn to_ns(self, tsc: u64) -> u64 {
+        let ticks = tsc.wrapping_sub(self.tsc) as i64;
+        self.monotonic_ns.saturating_add_signed((ticks as f64 * self.ns_per_tick) as i64)
+    }
+}
+
+static CALIBRATION: OnceLock<Option<Calibration>> = OnceLock::new();
+
+fn tsc_ns() -> Option<u64> {
+    let calibration = (*CALIBRATION.get_or_init(calibrate))?;
+    Some(calibration.to_ns(read_tsc()?))
+}
+
+#[cfg(target_arch = "x86_64")]
+fn read_tsc() -> Option<u64> {
+    Some(unsafe { std::arch::x86_64::_rdtsc() })
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn read_tsc() -> Option<u64> {
+    None
+}
+
+/// The host's calibration, measuring and storing one if there is none yet
+#[cfg(target_arch = "x86_64")]
+fn calibrate() -> Option<Calibration> {
+    use std::arch::x86_64::__cpuid;
+
+    // Only an invariant TSC ticks at a constant rate, in sync across cores
+    let invariant = __cpuid(0x8000_0000).eax >= 0x8000_0007 && __cpuid(0x8000_0007).edx & (1 << 8) != 0;
+    if !invariant {
+        return None;
+    }
+
+    if let Some(stored) = load_calibration() {
+        let (tsc, now) = (read_tsc()?, monotonic_ns());
+        if stored.to_ns(tsc).abs_diff(now) <= MAX_CALIBRATION_SKEW_NS {
+            return Some(stored);
+        }
+    }
+
+    let (start_tsc, start_ns) = (read_tsc()?, monotonic_ns());
+    std::thread::sleep(CALIBRATION_WINDOW);
+    let (end_tsc, end_ns) = (read_tsc()?, monotonic_ns());
+    let calibration = Calibration {
+        tsc: end_tsc,
+        monotonic_ns: end_ns,
+        ns_per_tick: (end_ns - start_ns) as f64 / end_tsc.wrapping_sub(start_tsc).max(1) as f64,
+    };
+    store_calibration(calibration);
+    Some(calibration)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn calibrate() -> Option<Calibration> {
+    None
+}
+
+#[cfg(target_arch = "x86_64")]
+fn load_calibration() -> Option<Calibration> {
+    let text = std::fs::read_to_string(TSC_CALIBRATION_PATH).ok()?;
+    let mut fields = text.split_whitespace();
+    Some(Calibration {
+        tsc: fields.next()?.parse().ok()?,
+        monotonic_ns: fields.next()?.parse().ok()?,
+        ns_per_tick: fields.next()?.parse().ok()?,
+    })
+}
+
+/// Best effort: a process that cannot store the calibration still uses its own
+#[cfg(target_arch = "x86_64")]
+fn store_calibration(calibration: Calibration) {
+    let staging = format!("{}.{}", TSC_CALIBRATION_PATH, std::process::id());
+    let text = format!("{} {} {}\n", calibration.tsc, calibration.monotonic_ns, calibration.ns_per_tick);
+    if std::fs::write(&staging, text).is_ok() && std::fs::rename(&staging, TSC_CALIBRATION_PATH).is_err() {
+        let _ = std::fs::remove_file(&staging);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_clocks_agree_with_their_sources() {
+        let wall = ClockSource::Realtime.now_ns();
+        let monotonic = ClockSource::Monotonic.now_ns();
+        let tsc = ClockSource::Tsc.now_ns();
+
+        let system = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        assert!(system >= wall && system - wall < 1_000_000_000);
+        // TSC readings are monotonic nanoseconds, whether calibrated or the fallback
+        assert!(tsc.abs_diff(monotonic) < 50_000_000);
+        assert!(ClockSource::Tsc.now_ns() >= tsc);
+        for clock in [ClockSource::Realtime, ClockSource::Monotonic, ClockSource::Tsc] {
+            assert_eq!(ClockSource::from_bits(clock.as_bits()), Some(clock));
+        }
+    }
+
+    #[test]
+    fn test_mock_clock_drives_ttls_and_replays() -> crate::Result<()> {
+        use std::sync::Arc;
+
+        use polars::df;
+
+        use crate::{QADataSwapError, ReplayClock, Replayer, SharedDataFrame};
+
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let time = Arc::new(MockClock::new(start));
+        let config = test_config("mock_clock", 8).with_time_source(time.clone());
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        writer.write_with_ttl(&df! { "px" => [1.0] }?, Duration::from_secs(5))?;
+        writer.write_with_ttl(&df! { "px" => [2.0] }?, Duration::from_secs(60))?;
+        time.advance(Duration::from_secs(10));
+        let (metadata, df) = reader.read_with_metadata(Some(100))?.unwrap();
+        assert_eq!(df.column("px")?.f64()?.get(0), Some(2.0));
+        assert_eq!(metadata.timestamp_ns, 1_700_000_000 * 1_000_000_000);
+        assert_eq!(reader.read_stats().expired, 1);
+
+        // An hour of recorded time replays without waiting for it
+        let frames = vec![(0, df! { "px" => [3.0] }?), (3_600_000_000, df! { "px" => [4.0] }?)];
+        let mut replayer = Replayer::new(writer, frames, ReplayClock::Original);
+        assert_eq!(replayer.run()?, 2);
+        assert_eq!(time.elapsed(), Duration::from_secs(3610));
+        assert_eq!(time.now(), start + Duration::from_secs(3610));
+        reader.read(Some(100))?;
+        let (metadata, _) = reader.read_with_metadata(Some(100))?.unwrap();
+        assert_eq!(metadata.timestamp_ns, (1_700_000_000 + 3610) * 1_000_000_000);
+        assert!(matches!(reader.read(Some(0)), Err(QADataSwapError::Timeout)));
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+//! Pluggable payload codecs
+//!
+//! Data frames are Arrow IPC files unless the writer picks a codec with
+//! `with_codec`. It then encodes every data frame with that codec (frame kind
+//! `Coded`) and publishes the codec's name in the control block's metadata
+//! entry `CODEC_META_KEY`. Readers decode with the codec their process knows
+//! under that name: the built-in `ArrowIpcCodec`, `ArrowStreamCodec` and
+//! `ParquetCodec`, anything passed to `register_codec`, or the reader's own
+//! `with_codec`, which must carry the name the writer published.
+//!
+//! With the `serde` feature `JsonLinesCodec`, and with `msgpack`
+//! `MsgPackCodec`, carry rows as plain objects for scripts and languages
+//! without Arrow bindings. They trade speed for reach and suit low-rate
+//! channels; column types come back as `serde_rows` infers them, so
+//! datetimes and other non-numeric columns arrive as strings.
+//!
+//! Coded frames skip IPC compression, trained zstd dictionaries and column
+//! group splitting; string interning, null counts, lineage and TTLs apply as
+//! for any data frame.
+
+use std::fmt;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use polars::prelude::*;
+
+use crate::{QADataSwapError, Result};
+
+/// Metadata key holding the name of the writer's codec
+pub const CODEC_META_KEY: &str = "__codec";
+
+/// Turns data frames into payload bytes and back
+pub trait Codec: Send + Sync + fmt::Debug {
+    /// Name published to readers, unique per codec and format version
+    fn name(&self) -> &str;
+
+    /// Append the encoded `df` to `out`
+    fn encode(&self, df: &DataFrame, out: &mut Vec<u8>) -> Result<()>;
+
+    fn decode(&self, bytes: &[u8]) -> Result<DataFrame>;
+}
+
+/// Arrow IPC file, the format of uncoded data frames
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArrowIpcCodec;
+
+impl Codec for ArrowIpcCodec {
+    fn name(&self) -> &str {
+        "arrow-ipc"
+    }
+
+    fn encode(&self, df: &DataFrame, out: &mut Vec<u8>) -> Result<()> {
+        IpcWriter::new(out).finish(&mut df.clone())?;
+        Ok(())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<DataFrame> {
+        crate::read_ipc(bytes, None)
+    }
+}
+
+/// Arrow IPC stream, for consumers with a streaming reader only
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArrowStreamCodec;
+
+impl Codec for ArrowStreamCodec {
+    fn name(&self) -> &str {
+        "arrow-stream"
+    }
+
+    fn encode(&self, df: &DataFrame, out: &mut Vec<u8>) -> Result<()> {
+        IpcStreamWriter::new(out).finish(&mut df.clone())?;
+        Ok(())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<DataFrame> {
+        catch_panic(|| IpcStreamReader::new(Cursor::new(bytes)).finish())
+    }
+}
+
+/// Parquet file: slower to write, but compact for wide low-rate frames
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParquetCodec;
+
+impl Codec for ParquetCodec {
+    fn name(&self) -> &str {
+        "parquet"
+    }
+
+    fn encode(&self, df: &DataFrame, out: &mut Vec<u8>) -> Result<()> {
+        ParquetWriter::new(out).finish(&mut df.clone())?;
+        Ok(())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<DataFrame> {
+        catch_panic(|| ParquetReader::new(Cursor::new(bytes)).finish())
+    }
+}
+
+/// One JSON object per row and line
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonLinesCodec;
+
+#[cfg(feature = "serde")]
+impl Codec for JsonLinesCodec {
+    fn name(&self) -> &str {
+        "json-lines"
+    }
+
+    fn encode(&self, df: &DataFrame, out: &mut Vec<u8>) -> Result<()> {
+        for row in crate::serde_rows::from_dataframe::<serde_json::Map<String, serde_json::Value>>(df)? {
+            serde_json::to_writer(&mut *out, &row).map_err(serde_error)?;
+            out.push(b'\n');
+        }
+        Ok(())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<DataFrame> {
+        let rows = bytes
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(line).map_err(serde_error))
+            .collect::<Result<Vec<_>>>()?;
+        crate::serde_rows::to_dataframe(rows)
+    }
+}
+
+/// MessagePack array with one map per row
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgPackCodec;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MsgPackCodec {
+    fn name(&self) -> &str {
+        "msgpack"
+    }
+
+    fn encode(&self, df: &DataFrame, out: &mut Vec<u8>) -> Result<()> {
+        let rows = crate::serde_rows::from_dataframe::<serde_json::Map<String, serde_json::Value>>(df)?;
+        rmp_serde::encode::write_named(out, &rows).map_err(serde_error)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<DataFrame> {
+        let rows: Vec<serde_json::Map<String, serde_json::Value>> = rmp_serde::from_slice(bytes).map_err(serde_error)?;
+        crate::serde_rows::to_dataframe(rows)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn serde_error(e: impl fmt::Display) -> QADataSwapError {
+    QADataSwapError::SharedMemory(format!("Corrupt frame: malformed row payload: {}", e))
+}
+
+/// Codecs added with `register_codec`, looked up before the built-in ones
+static REGISTRY: Mutex<Vec<Arc<dyn Codec>>> = Mutex::new(Vec::new());
+
+/// Make `codec` available to every reader in this process, replacing any
+/// codec registered under the same name
+pub fn register_codec(codec: impl Codec + 'static) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.retain(|registered| registered.name() != codec.name());
+    registry.push(Arc::new(codec));
+}
+
+/// The codec this process knows as `name`
+pub(crate) fn lookup(name: &str) -> Option<Arc<dyn Codec>> {
+    let registered = REGISTRY.lock().unwrap().iter().find(|codec| codec.name() == name).cloned();
+    registered.or_else(|| {
+        let builtins: Vec<Arc<dyn Codec>> = vec![
+            Arc::new(ArrowIpcCodec),
+            Arc::new(ArrowStreamCodec),
+            Arc::new(ParquetCodec),
+            #[cfg(feature = "serde")]
+            Arc::new(JsonLinesCodec),
+            #[cfg(feature = "msgpack")]
+            Arc::new(MsgPackCodec),
+        ];
+        builtins.into_iter().find(|codec| codec.name() == name)
+    })
+}
+
+/// Polars panics on some malformed inputs, see `read_ipc`
+fn catch_panic(read: impl FnOnce() -> PolarsResult<DataFrame>) -> Result<DataFrame> {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(read)) {
+        Ok(result) => result.map_err(QADataSwapError::Polars),
+        Err(_) => Err(QADataSwapError::SharedMemory("Corrupt frame: malformed coded payload".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+
+    /// Rows as `col=value` lines, the kind of payload a custom codec carries
+    #[derive(Debug)]
+    struct Lines;
+
+    impl Codec for Lines {
+        fn name(&self) -> &str {
+            "test-lines"
+        }
+
+        fn encode(&self, df: &DataFrame, out: &mut Vec<u8>) -> Result<()> {
+            for value in df.column("px")?.f64()?.into_no_null_iter() {
+                out.extend_from_slice(format!("px={}\n", value).as_bytes());
+            }
+            Ok(())
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<DataFrame> {
+            let px: Vec<f64> = String::from_utf8_lossy(bytes)
+                .lines()
+                .filter_map(|line| line.strip_prefix("px=")?.parse().ok())
+                .collect();
+            Ok(df! { "px" => px }?)
+        }
+    }
+
+    #[test]
+    fn test_codecs_round_trip_and_negotiate() -> Result<()> {
+        let df = df! { "px" => [3500.0, 3501.5], "volume" => [10i64, 20] }?;
+        for (name, codec) in [
+            ("codec_ipc", Arc::new(ArrowIpcCodec) as Arc<dyn Codec>),
+            ("codec_stream", Arc::new(ArrowStreamCodec)),
+            ("codec_parquet", Arc::new(ParquetCodec)),
+        ] {
+            let writer = SharedDataFrame::create_writer(test_config(name, 4).with_codec(codec))?;
+            let reader = SharedDataFrame::create_reader(test_config(name, 4))?;
+            writer.write(&df)?;
+            assert_eq!(reader.read(Some(100))?, Some(df.clone()), "{}", name);
+        }
+
+        // A custom codec: unknown to readers until registered
+        let writer = SharedDataFrame::create_writer(test_config("codec_custom", 4).with_codec(Arc::new(Lines)))?;
+        let reader = SharedDataFrame::create_reader(test_config("codec_custom", 4))?;
+        writer.write(&df! { "px" => [1.5, 2.5] }?)?;
+        assert!(reader.read(Some(100)).is_err());
+        register_codec(Lines);
+        writer.write(&df! { "px" => [1.5, 2.5] }?)?;
+        assert_eq!(reader.read(Some(100))?, Some(df! { "px" => [1.5, 2.5] }?));
+
+        // A reader insisting on another codec fails the handshake
+        let insisting = test_config("codec_custom", 4).with_codec(Arc::new(ParquetCodec));
+        assert!(SharedDataFrame::create_reader(insisting).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_row_codecs_are_plain_objects() -> Result<()> {
+        let df = df! { "symbol" => ["rb2410", "cu2409"], "px" => [3500.5, 71230.0], "qty" => [Some(3i64), None] }?;
+        let mut lines = Vec::new();
+        JsonLinesCodec.encode(&df, &mut lines)?;
+        assert_eq!(
+            String::from_utf8_lossy(&lines),
+            "{\"symbol\":\"rb2410\",\"px\":3500.5,\"qty\":3}\n{\"symbol\":\"cu2409\",\"px\":71230.0,\"qty\":null}\n"
+        );
+        assert_eq!(JsonLinesCodec.decode(&lines)?, df);
+
+        #[cfg(feature = "msgpack")]
+        {
+            let config = test_config("codec_msgpack", 4).with_codec(Arc::new(MsgPackCodec));
+            let writer = SharedDataFrame::create_writer(config)?;
+            let reader = SharedDataFrame::create_reader(test_config("codec_msgpack", 4))?;
+            writer.write(&df)?;
+            assert_eq!(reader.read(Some(100))?, Some(df));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+//! gRPC control plane: create, list, inspect and destroy arenas remotely
+//!
+//! ```ignore
+//! ControlPlane::new(Transport::SharedMemory).serve("0.0.0.0:50051".parse()?).await?;
+//! ```
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+use tonic::{Request, Response, Status};
+
+use crate::backend::{self, Transport};
+use crate::{list_arenas, MemoryReport, QADataSwapError, SharedDataStream, SharedMemoryConfig};
+
+/// Messages and service stubs generated from `proto/control.proto`
+pub mod proto {
+    tonic::include_proto!("qadataswap.control.v1");
+}
+
+use proto::arena_control_server::{ArenaControl, ArenaControlServer};
+use proto::{
+    ArenaInfo, ArenaRef, ArenaStats, CreateArenaRequest, DestroyArenaRequest, DestroyArenaResponse, ListArenasRequest,
+    ListArenasResponse,
+};
+
+/// `ArenaControl` service over one transport's segments. Arenas created through
+/// it are owned by the server and stay alive until destroyed or the server exits.
+pub struct ControlPlane {
+    transport: Transport,
+    managed: Mutex<HashMap<String, SharedDataStream>>,
+}
+
+// Helpers return tonic's Status as-is, like the handlers calling them
+#[allow(clippy::result_large_err)]
+impl ControlPlane {
+    pub fn new(transport: Transport) -> Self {
+        Self {
+            transport,
+            managed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wrap in the generated server, e.g. to add it to an existing tonic router
+    pub fn into_service(self) -> ArenaControlServer<Self> {
+        ArenaControlServer::new(self)
+    }
+
+    /// Serve on `addr` until the future is dropped
+    pub async fn serve(self, addr: SocketAddr) -> std::result::Result<(), tonic::transport::Error> {
+        tonic::transport::Server::builder()
+            .add_service(self.into_service())
+            .serve(addr)
+            .await
+    }
+
+    fn config(&self, arena: &ArenaRef) -> SharedMemoryConfig {
+        let config = SharedMemoryConfig::new(&arena.name).with_transport(self.transport);
+        match arena.namespace.is_empty() {
+            true => config,
+            false => config.with_namespace(&arena.namespace),
+        }
+    }
+
+    /// Segment name of the referenced arena, rejecting malformed references
+    fn segment(&self, arena: Option<&ArenaRef>) -> std::result::Result<(ArenaRef, String), Status> {
+        let arena = arena
+            .filter(|arena| !arena.name.is_empty())
+            .ok_or_else(|| Status::invalid_argument("Arena name is required"))?;
+        let segment = self.config(arena).segment_name().map_err(status)?;
+        Ok((arena.clone(), segment))
+    }
+
+    fn describe(&self, arena: ArenaRef, segment: String) -> std::result::Result<ArenaInfo, Status> {
+        let status = backend::inspect_segment(self.transport, &segment)
+            .ok_or_else(|| Status::not_found(format!("No arena '{}'", segment)))?;
+        let managed = self.managed.lock().unwrap().contains_key(&segment);
+        Ok(ArenaInfo {
+            arena: Some(arena),
+            segment,
+            total_bytes: status.total_bytes,
+            created_at_us: status.created_at_us,
+            writer_pid: status.writer_pid,
+            writer_alive: status.writer_alive != 0,
+            reader_count: status.reader_count,
+            managed,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl ArenaControl for ControlPlane {
+    async fn create_arena(&self, request: Request<CreateArenaRequest>) -> std::result::Result<Response<ArenaInfo>, Status> {
+        let request = request.into_inner();
+        let (arena, segment) = self.segment(request.arena.as_ref())?;
+
+        let mut config = self.config(&arena);
+        if request.size_mb > 0 {
+            config = config.with_size_mb(request.size_mb as usize);
+        }
+        if request.buffer_count > 0 {
+            config = config.with_buffer_count(request.buffer_count as usize);
+        }
+
+        {
+            let mut managed = self.managed.lock().unwrap();
+            if managed.contains_key(&segment) {
+                return Err(Status::already_exists(format!("Arena '{}' already exists", segment)));
+            }
+            let writer = SharedDataStream::create_writer(config).map_err(status)?;
+            managed.insert(segment.clone(), writer);
+        }
+        self.describe(arena, segment).map(Response::new)
+    }
+
+    async fn list_arenas(
+        &self,
+        request: Request<ListArenasRequest>,
+    ) -> std::result::Result<Response<ListArenasResponse>, Status> {
+        let namespace = request.into_inner().namespace;
+        let names = list_arenas(self.transport, Some(namespace.as_str()).filter(|ns| !ns.is_empty()));
+        Ok(Response::new(ListArenasResponse { names }))
+    }
+
+    async fn describe_arena(&self, request: Request<ArenaRef>) -> std::result::Result<Response<ArenaInfo>, Status> {
+        let (arena, segment) = self.segment(Some(request.get_ref()))?;
+        self.describe(arena, segment).map(Response::new)
+    }
+
+    async fn destroy_arena(
+        &self,
+        request: Request<DestroyArenaRequest>,
+    ) -> std::result::Result<Response<DestroyArenaResponse>, Status> {
+        let request = request.into_inner();
+        let (_, segment) = self.segment(request.arena.as_ref())?;
+        let Some(status) = backend::inspect_segment(self.transport, &segment) else {
+            return Ok(Response::new(DestroyArenaResponse { removed: false }));
+        };
+        if status.reader_count > 0 && !request.force {
+            return Err(Status::failed_precondition(format!(
+                "Arena '{}' has {} attached reader(s); set force to destroy it anyway",
+                segment, status.reader_count
+            )));
+        }
+
+        // Detach our own writer before unlinking
+        drop(self.managed.lock().unwrap().remove(&segment));
+        backend::remove_segment(self.transport, &segment);
+        Ok(Response::new(DestroyArenaResponse { removed: true }))
+    }
+
+    async fn get_stats(&self, request: Request<ArenaRef>) -> std::result::Result<Response<ArenaStats>, Status> {
+        let (arena, segment) = self.segment(Some(request.get_ref()))?;
+        let report = match self.managed.lock().unwrap().get(&segment) {
+            Some(writer) => writer.memory_report(),
+            None => {
+                if backend::inspect_segment(self.transport, &segment).is_none() {
+                    return Err(Status::not_found(format!("No arena '{}'", segment)));
+                }
+                // Briefly attach to arenas someone else owns
+                SharedDataStream::create_reader(self.config(&arena)).and_then(|reader| reader.memory_report())
+            },
+        }
+        .map_err(status)?;
+        Ok(Response::new(stats(arena, &report)))
+    }
+}
+
+fn stats(arena: ArenaRef, report: &MemoryReport) -> ArenaStats {
+    ArenaStats {
+        arena: Some(arena),
+        total_bytes: report.total_bytes,
+        header_bytes: report.header_bytes,
+        buffer_count: report.buffer_count,
+        buffer_bytes: report.buffer_bytes,
+        used_buffers: report.used_buffers,
+        free_buffers: report.free_buffers,
+        high_water_bytes: report.high_water_bytes,
+        high_water_buffers: report.high_water_buffers,
+        largest_writable_bytes: report.largest_writable_bytes,
+    }
+}
+
+fn status(error: QADataSwapError) -> Status {
+    match error {
+        QADataSwapError::Timeout => Status::deadline_exceeded(error.to_string()),
+        QADataSwapError::NotConnected => Status::unavailable(error.to_string()),
+        QADataSwapError::LimitExceeded(_) | QADataSwapError::TooManyReaders { .. } => {
+            Status::resource_exhausted(error.to_string())
+        },
+        QADataSwapError::Unauthorized => Status::permission_denied(error.to_string()),
+        QADataSwapError::AlreadyExists { .. } => Status::already_exists(error.to_string()),
+        _ => Status::internal(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arena(name: &str) -> ArenaRef {
+        ArenaRef {
+            name: name.to_string(),
+            namespace: "control".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_control_plane_lifecycle() {
+        let plane = ControlPlane::new(Transport::InProcess);
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            let create = CreateArenaRequest {
+                arena: Some(arena("orders")),
+                size_mb: 1,
+                buffer_count: 4,
+            };
+            let info = plane.create_arena(Request::new(create.clone())).await.unwrap().into_inner();
+            assert!(info.managed && info.writer_alive);
+            assert_eq!(info.segment, "control.orders");
+            let again = plane.create_arena(Request::new(create)).await.unwrap_err();
+            assert_eq!(again.code(), tonic::Code::AlreadyExists);
+
+            let listed = plane
+                .list_arenas(Request::new(ListArenasRequest { namespace: "control".to_string() }))
+                .await
+                .unwrap();
+            assert_eq!(listed.into_inner().names, vec!["orders".to_string()]);
+
+            let stats = plane.get_stats(Request::new(arena("orders"))).await.unwrap().into_inner();
+            assert_eq!(stats.buffer_count, 4);
+
+            let destroy = DestroyArenaRequest {
+                arena: Some(arena("orders")),
+                force: false,
+            };
+            assert!(plane.destroy_arena(Request::new(destroy)).await.unwrap().into_inner().removed);
+            let missing = plane.describe_arena(Request::new(arena("orders"))).await.unwrap_err();
+            assert_eq!(missing.code(), tonic::Code::NotFound);
+        });
+    }
+}
@@ -0,0 +1,100 @@
+//! Durable reader positions, so a restarted consumer resumes after the last
+//! frame it finished processing
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{QADataSwapError, Result};
+
+/// Directory of per-(arena, consumer) cursor files. Each update is written to a
+/// temporary file, synced and renamed over the old one, so a crash leaves either
+/// the previous or the new sequence, never a torn write.
+#[derive(Debug, Clone)]
+pub struct CursorStore {
+    dir: PathBuf,
+}
+
+impl CursorStore {
+    /// Use `dir` for cursor files, creating it if needed
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    /// Last sequence `consumer` committed for `arena`, if any
+    pub fn load(&self, arena: &str, consumer: &str) -> Result<Option<u64>> {
+        let path = self.path(arena, consumer)?;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        text.trim().parse().map(Some).map_err(|_| {
+            QADataSwapError::SharedMemory(format!("Corrupt cursor file {}", path.display()))
+        })
+    }
+
+    /// Atomically record `sequence` as the last frame `consumer` processed
+    pub fn store(&self, arena: &str, consumer: &str, sequence: u64) -> Result<()> {
+        let path = self.path(arena, consumer)?;
+        let tmp = path.with_extension("cursor.tmp");
+
+        let mut file = File::create(&tmp)?;
+        writeln!(file, "{}", sequence)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path)?;
+        // Persist the rename itself
+        File::open(&self.dir)?.sync_all()?;
+        Ok(())
+    }
+
+    fn path(&self, arena: &str, consumer: &str) -> Result<PathBuf> {
+        for part in [arena, consumer] {
+            if part.is_empty() || part.contains(['/', '\\', '@']) {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Invalid cursor key '{}': must be non-empty without '/', '\\' or '@'", part
+                )));
+            }
+        }
+        Ok(self.dir.join(format!("{}@{}.cursor", arena, consumer)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::*;
+
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+
+    #[test]
+    fn test_resume_after_committed_sequence() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = CursorStore::open(dir.path())?;
+        let config = test_config("cursor_stream", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        for i in 0..4i64 {
+            writer.write(&df! { "id" => [i] }?)?;
+        }
+
+        let reader = SharedDataFrame::create_reader(config.clone())?;
+        assert_eq!(reader.resume(&store, "risk")?, None);
+        reader.read(Some(100))?.unwrap();
+        reader.read(Some(100))?.unwrap();
+        reader.commit_cursor(&store, "risk")?;
+        drop(reader);
+
+        // A restarted consumer continues with the third frame
+        let reader = SharedDataFrame::create_reader(config)?;
+        assert_eq!(reader.resume(&store, "risk")?, Some(1));
+        let df = reader.read(Some(100))?.unwrap();
+        assert_eq!(df.column("id")?.i64()?.get(0), Some(2));
+
+        assert!(store.load("cursor_stream", "a/b").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+//! Process-wide cache of decoded frames, so readers in one process that each
+//! see the same frame (own cursors, consumer groups) decode it only once
+//!
+//! Entries are keyed by segment, sequence and the frame's header stamp, so a
+//! recreated segment reusing sequence numbers never hits a stale entry. A
+//! reader that misses decodes under the entry's own lock while other readers
+//! of that frame wait for it; frames of other channels decode in parallel.
+//! What the cache hands out is a clone sharing the decoded buffers. Interned
+//! frames are not cached, every reader must apply their dictionary block.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use polars::prelude::DataFrame;
+
+use crate::{FrameHeader, Result};
+
+/// Most frames kept, whatever their size
+const MAX_ENTRIES: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CacheKey {
+    segment: String,
+    sequence: u64,
+    writer_id: u32,
+    timestamp_ns: u64,
+}
+
+impl CacheKey {
+    pub(crate) fn new(segment: String, sequence: u64, header: &FrameHeader) -> Self {
+        Self {
+            segment,
+            sequence,
+            writer_id: header.writer_id,
+            timestamp_ns: header.timestamp_ns,
+        }
+    }
+}
+
+struct Entry {
+    key: CacheKey,
+    frame: Arc<Mutex<Option<DataFrame>>>,
+    bytes: usize,
+}
+
+/// Least recently used first
+static CACHE: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::new());
+
+/// The frame under `key`, decoding it with `decode` unless another reader
+/// already has; then trim the cache to `max_bytes`
+pub(crate) fn get_or_decode(key: CacheKey, max_bytes: usize, decode: impl FnOnce() -> Result<DataFrame>)
+                            -> Result<DataFrame> {
+    let frame = {
+        let mut cache = CACHE.lock().unwrap();
+        let entry = match cache.iter().position(|entry| entry.key == key) {
+            Some(index) => cache.remove(index).unwrap(),
+            None => Entry {
+                key: key.clone(),
+                frame: Arc::new(Mutex::new(None)),
+                bytes: 0,
+            },
+        };
+        let frame = entry.frame.clone();
+        cache.push_back(entry);
+        frame
+    };
+
+    let mut slot = frame.lock().unwrap();
+    if let Some(df) = slot.as_ref() {
+        return Ok(df.clone());
+    }
+    let df = match decode() {
+        Ok(df) => df,
+        Err(e) => {
+            drop(slot);
+            CACHE.lock().unwrap().retain(|entry| entry.key != key);
+            return Err(e);
+        },
+    };
+    *slot = Some(df.clone());
+    drop(slot);
+
+    let mut cache = CACHE.lock().unwrap();
+    if let Some(entry) = cache.iter_mut().find(|entry| entry.key == key) {
+        entry.bytes = df.estimated_size();
+    }
+    let mut total: usize = cache.iter().map(|entry| entry.bytes).sum();
+    while cache.len() > MAX_ENTRIES || (total > max_bytes && cache.len() > 1) {
+        let evicted = cache.pop_front().unwrap();
+        total -= evicted.bytes;
+    }
+    Ok(df)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{SeekFrom, SharedDataFrame};
+    use polars::df;
+
+    #[test]
+    fn test_readers_share_one_decode() -> Result<()> {
+        let config = test_config("decode_cache_broadcast", 4).with_decode_cache(64 * 1024 * 1024);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let first = SharedDataFrame::create_reader(config.clone())?;
+        let second = SharedDataFrame::create_reader(config)?;
+        first.seek(SeekFrom::Latest)?;
+        second.seek(SeekFrom::Latest)?;
+
+        let df = df! { "px" => [3500.0, 3501.0], "volume" => [10i64, 20] }?;
+        writer.write(&df)?;
+        let a = first.read(Some(100))?.unwrap();
+        let b = second.read(Some(100))?.unwrap();
+        assert_eq!(a, df);
+        // Both readers hold the buffers decoded by the first
+        let values = |df: &DataFrame| df.column("px").unwrap().f64().unwrap().cont_slice().unwrap().as_ptr();
+        assert_eq!(values(&a), values(&b));
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! In-process fan-out: one reader thread hands every frame to any number of
+//! subscribers, instead of each consumer thread attaching its own reader
+//!
+//! Subscribers receive `Arc<DataFrame>` over crossbeam channels, so a frame is
+//! read and decoded once however many threads consume it. A subscriber sees
+//! the frames read after it subscribed. A bounded subscriber that falls behind
+//! holds the dispatcher up, and with it the writer, as a slow reader would;
+//! dropping a receiver unsubscribes it.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, SendTimeoutError, Sender};
+use polars::prelude::*;
+
+use crate::threads::{spawn_reader, POLL_TIMEOUT_MS};
+use crate::{QADataSwapError, ReaderThreadOptions, Result, SharedDataFrame};
+
+type Subscribers = Arc<Mutex<Vec<Sender<Arc<DataFrame>>>>>;
+
+/// Reader thread fanning frames out to subscribers; dropping it stops the thread
+/// and disconnects every subscriber
+pub struct Dispatcher {
+    subscribers: Subscribers,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl Dispatcher {
+    pub fn new(reader: SharedDataFrame) -> Result<Self> {
+        Self::with_options(reader, ReaderThreadOptions::default())
+    }
+
+    /// Run the reader thread pinned or prioritized as `options` say
+    pub fn with_options(reader: SharedDataFrame, options: ReaderThreadOptions) -> Result<Self> {
+        let subscribers: Subscribers = Arc::default();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let subscribers = Arc::clone(&subscribers);
+            let stop = Arc::clone(&stop);
+            spawn_reader("qads-dispatch", options, move || {
+                let result = dispatch(&reader, &subscribers, &stop);
+                // Subscribers see the stream end as a disconnect
+                subscribers.lock().unwrap().clear();
+                result
+            })?
+        };
+
+        Ok(Self {
+            subscribers,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Receive every later frame, however far behind this subscriber falls
+    pub fn subscribe(&self) -> Receiver<Arc<DataFrame>> {
+        self.add(crossbeam_channel::unbounded())
+    }
+
+    /// Receive every later frame, holding the dispatcher up while `capacity`
+    /// frames are queued
+    pub fn subscribe_bounded(&self, capacity: usize) -> Receiver<Arc<DataFrame>> {
+        self.add(crossbeam_channel::bounded(capacity.max(1)))
+    }
+
+    fn add(&self, (tx, rx): (Sender<Arc<DataFrame>>, Receiver<Arc<DataFrame>>)) -> Receiver<Arc<DataFrame>> {
+        if self.thread.as_ref().is_some_and(|thread| !thread.is_finished()) {
+            self.subscribers.lock().unwrap().push(tx);
+        }
+        rx
+    }
+
+    /// Subscribers whose receivers are still alive as of the last frame
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    /// Stop the thread, returning the read error that ended it early, if any
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::Release);
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(QADataSwapError::SharedMemory("Dispatcher thread panicked".to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+fn dispatch(reader: &SharedDataFrame, subscribers: &Mutex<Vec<Sender<Arc<DataFrame>>>>, stop: &AtomicBool)
+            -> Result<()> {
+    while !stop.load(Ordering::Acquire) {
+        let frame = match reader.read(Some(POLL_TIMEOUT_MS)) {
+            Ok(Some(df)) => Arc::new(df),
+            Ok(None) | Err(QADataSwapError::Timeout) => continue,
+            Err(e) => return Err(e),
+        };
+        // Subscribing blocks only for as long as a full subscriber does
+        let mut subscribers = subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| deliver(subscriber, &frame, stop));
+    }
+    Ok(())
+}
+
+/// `false` once the subscriber is gone
+fn deliver(subscriber: &Sender<Arc<DataFrame>>, frame: &Arc<DataFrame>, stop: &AtomicBool) -> bool {
+    let timeout = Duration::from_millis(POLL_TIMEOUT_MS as u64);
+    loop {
+        match subscriber.send_timeout(Arc::clone(frame), timeout) {
+            Ok(()) => return true,
+            Err(SendTimeoutError::Timeout(_)) if !stop.load(Ordering::Acquire) => continue,
+            Err(SendTimeoutError::Timeout(_)) => return true,
+            Err(SendTimeoutError::Disconnected(_)) => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_dispatcher_fans_out_one_decode() -> Result<()> {
+        let config = test_config("dispatched", 4);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let dispatcher = Dispatcher::new(SharedDataFrame::create_reader(config)?)?;
+        let fast = dispatcher.subscribe();
+        let slow = dispatcher.subscribe_bounded(1);
+        let gone = dispatcher.subscribe();
+        drop(gone);
+
+        let df = df! { "px" => [3500.0, 3501.0] }?;
+        writer.write(&df)?;
+        writer.write(&df)?;
+        let timeout = Duration::from_secs(1);
+        let first = fast.recv_timeout(timeout).unwrap();
+        assert_eq!(*first, df);
+        assert!(Arc::ptr_eq(&first, &slow.recv_timeout(timeout).unwrap()));
+        assert_eq!(*slow.recv_timeout(timeout).unwrap(), df);
+        assert_eq!(*fast.recv_timeout(timeout).unwrap(), df);
+        assert_eq!(dispatcher.subscriber_count(), 2);
+
+        dispatcher.stop()?;
+        assert!(fast.recv_timeout(timeout).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! Dead-letter queue: frames a reader could not decode, coerce or validate are
+//! published to the `<name>__dlq` arena instead of failing the read loop
+//!
+//! Each dead letter is a one-row frame with the columns `stage`, `error`,
+//! `source`, `sequence`, `writer_id`, `timestamp_us` and `payload` (the raw
+//! frame bytes); read them back with `DeadLetter::from_dataframe`.
+
+use std::fmt;
+
+use polars::prelude::*;
+
+use crate::frame::FrameHeader;
+use crate::{
+    decode_dataframe, FrameMetadata, OpenMode, QADataSwapError, Result, SharedDataFrame, SharedMemoryConfig,
+};
+
+/// Suffix of the dead-letter arena's name
+pub const DEAD_LETTER_SUFFIX: &str = "__dlq";
+
+/// Name of the dead-letter arena for channel `name`
+pub fn dead_letter_name(name: &str) -> String {
+    format!("{}{}", name, DEAD_LETTER_SUFFIX)
+}
+
+/// Which check a dead-lettered frame failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadLetterStage {
+    /// Corrupt header, truncated payload or undecodable IPC
+    Decode,
+    /// Incompatible with the reader's schema or its `expect_non_null` columns
+    Schema,
+    /// Rejected by the channel's `validators`
+    Validation,
+}
+
+impl DeadLetterStage {
+    fn as_str(self) -> &'static str {
+        match self {
+            DeadLetterStage::Decode => "decode",
+            DeadLetterStage::Schema => "schema",
+            DeadLetterStage::Validation => "validation",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "decode" => Ok(DeadLetterStage::Decode),
+            "schema" => Ok(DeadLetterStage::Schema),
+            "validation" => Ok(DeadLetterStage::Validation),
+            other => Err(QADataSwapError::SharedMemory(format!("Unknown dead-letter stage '{}'", other))),
+        }
+    }
+}
+
+impl fmt::Display for DeadLetterStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A failed frame with the reason it failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetter {
+    pub stage: DeadLetterStage,
+    pub error: String,
+    /// Channel the frame was published on
+    pub source: String,
+    pub sequence: Option<u64>,
+    pub writer_id: Option<u32>,
+    pub timestamp_us: Option<u64>,
+    /// The frame exactly as it was read
+    pub payload: Vec<u8>,
+}
+
+impl DeadLetter {
+    fn to_dataframe(&self) -> Result<DataFrame> {
+        df! {
+            "stage" => [self.stage.as_str()],
+            "error" => [self.error.as_str()],
+            "source" => [self.source.as_str()],
+            "sequence" => [self.sequence],
+            "writer_id" => [self.writer_id],
+            "timestamp_us" => [self.timestamp_us],
+            "payload" => [self.payload.as_slice()],
+        }
+        .map_err(QADataSwapError::Polars)
+    }
+
+    /// Every dead letter in a frame read from a `<name>__dlq` arena
+    pub fn from_dataframe(df: &DataFrame) -> Result<Vec<Self>> {
+        let stages = df.column("stage")?.str()?;
+        let errors = df.column("error")?.str()?;
+        let sources = df.column("source")?.str()?;
+        let sequences = df.column("sequence")?.u64()?;
+        let writer_ids = df.column("writer_id")?.u32()?;
+        let timestamps = df.column("timestamp_us")?.u64()?;
+        let payloads = df.column("payload")?.binary()?;
+
+        (0..df.height())
+            .map(|row| {
+                Ok(DeadLetter {
+                    stage: DeadLetterStage::parse(stages.get(row).unwrap_or_default())?,
+                    error: errors.get(row).unwrap_or_default().to_string(),
+                    source: sources.get(row).unwrap_or_default().to_string(),
+                    sequence: sequences.get(row),
+                    writer_id: writer_ids.get(row),
+                    timestamp_us: timestamps.get(row),
+                    payload: payloads.get(row).unwrap_or_default().to_vec(),
+                })
+            })
+            .collect()
+    }
+
+    /// Decode the original frame's data, e.g. after fixing the reader's schema
+    pub fn decode(&self) -> Result<DataFrame> {
+        match FrameHeader::decode(&self.payload)? {
+            Some((_, payload)) => decode_dataframe(payload),
+            None => decode_dataframe(&self.payload),
+        }
+    }
+}
+
+/// Why a reader could not deliver a frame
+pub(crate) struct Rejected {
+    pub(crate) stage: DeadLetterStage,
+    pub(crate) error: QADataSwapError,
+    pub(crate) metadata: Option<Box<FrameMetadata>>,
+}
+
+impl Rejected {
+    pub(crate) fn new(stage: DeadLetterStage, metadata: Option<&FrameMetadata>) -> impl FnOnce(QADataSwapError) -> Self + '_ {
+        move |error| Rejected { stage, error, metadata: metadata.cloned().map(Box::new) }
+    }
+}
+
+/// Writer for a channel's dead-letter arena
+pub(crate) struct DeadLetterQueue {
+    writer: Box<SharedDataFrame>,
+}
+
+impl DeadLetterQueue {
+    /// Shares the channel's size, transport, namespace and token; every reader
+    /// of the channel with a queue enabled writes to the same arena
+    pub(crate) fn open(channel: &SharedMemoryConfig) -> Result<Self> {
+        let mut config = SharedMemoryConfig::new(dead_letter_name(&channel.name))
+            .with_size_mb(channel.size_mb)
+            .with_buffer_count(channel.buffer_count)
+            .with_transport(channel.transport)
+            .with_open_mode(OpenMode::OpenOrAttach);
+        config.namespace = channel.namespace.clone();
+        config.capability_token = channel.capability_token.clone();
+        Ok(Self {
+            writer: Box::new(SharedDataFrame::create_writer(config)?),
+        })
+    }
+
+    pub(crate) fn publish(&self, rejected: Rejected, bytes: &[u8], source: &str) -> Result<()> {
+        let letter = DeadLetter {
+            stage: rejected.stage,
+            error: rejected.error.to_string(),
+            source: source.to_string(),
+            sequence: rejected.metadata.as_ref().and_then(|m| m.sequence),
+            writer_id: rejected.metadata.as_ref().map(|m| m.writer_id),
+            timestamp_us: rejected.metadata.as_ref().map(|m| m.timestamp_us),
+            payload: bytes.to_vec(),
+        };
+        self.writer.write(&letter.to_dataframe()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::validators::{Check, ValidationPolicy, Validators};
+    use crate::ReadPolicy;
+
+    #[test]
+    fn test_failed_frames_go_to_the_dead_letter_queue() -> Result<()> {
+        let writer = SharedDataFrame::create_writer(test_config("dlq_ticks", 8))?;
+        let validators = Validators::new(ValidationPolicy::Reject).with_check(Check::range("px", Some(0.0), None));
+        let reader = SharedDataFrame::create_reader(
+            test_config("dlq_ticks", 8)
+                .with_read_policy(ReadPolicy::CoerceToLatestSchema)
+                .with_validators(validators)
+                .with_dead_letter_queue(true),
+        )?;
+        // The reader opened the queue's arena when it attached
+        let dlq_reader = SharedDataFrame::create_reader(test_config(&dead_letter_name("dlq_ticks"), 8))?;
+
+        writer.write(&df! { "px" => [1.0] }?)?;
+        writer.write(&df! { "px" => [-1.0] }?)?;
+        writer.write(&df! { "px" => ["not a number"] }?)?;
+        writer.write(&df! { "px" => [2.0] }?)?;
+
+        assert_eq!(reader.read(Some(100))?.unwrap().column("px")?.f64()?.get(0), Some(1.0));
+        assert_eq!(reader.read(Some(100))?.unwrap().column("px")?.f64()?.get(0), Some(2.0));
+
+        let letters = DeadLetter::from_dataframe(&dlq_reader.read(Some(100))?.unwrap())?;
+        assert_eq!(letters[0].stage, DeadLetterStage::Validation);
+        assert_eq!(letters[0].source, "dlq_ticks");
+        assert!(letters[0].decode()?.equals(&df! { "px" => [-1.0] }?));
+        let letters = DeadLetter::from_dataframe(&dlq_reader.read(Some(100))?.unwrap())?;
+        assert_eq!(letters[0].stage, DeadLetterStage::Schema);
+        assert!(letters[0].error.contains("px"), "{}", letters[0].error);
+        Ok(())
+    }
+}
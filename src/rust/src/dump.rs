@@ -0,0 +1,266 @@
+//! Control block and ring introspection for debugging stuck or lagging channels
+//!
+//! `debug_dump()` reads the header without taking the segment's spinlocks, so
+//! it works while one of them is held by a wedged process; fields may be
+//! mid-update relative to each other.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::backend::{self, RawArena, SLOT_EMPTY};
+use crate::{QADataSwapError, Result, Transport};
+
+/// Who may touch a ring slot next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotOwner {
+    /// Empty, or being filled by the writer
+    Writer,
+    /// Holds a frame not yet consumed (read, acked or passed by every group)
+    Readers,
+    /// Consumed; the writer may reuse it, readers may still seek to it
+    Free,
+}
+
+/// One ring slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotDump {
+    pub index: usize,
+    pub sequence: Option<u64>,
+    pub size: u64,
+    /// When the frame was published, microseconds since the epoch
+    pub timestamp_us: u64,
+    pub ready: bool,
+    pub owner: SlotOwner,
+}
+
+/// One consumer group's shared cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupDump {
+    pub name: String,
+    pub cursor: u64,
+    pub members: i32,
+}
+
+/// Snapshot returned by `debug_dump()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArenaDump {
+    pub segment: String,
+    pub transport: Transport,
+    /// Whether the dumping endpoint is the writer
+    pub is_writer: bool,
+    /// Layout magic and version; 0 for in-process segments
+    pub magic: u32,
+    pub version: u32,
+    pub total_bytes: u64,
+    pub header_bytes: u64,
+    pub buffer_count: u64,
+    pub buffer_bytes: u64,
+    pub max_buffer_count: u64,
+    /// Bumped every time the ring is re-slotted
+    pub geometry_epoch: u64,
+    pub write_sequence: u64,
+    /// Highest sequence visible to readers; lags `write_sequence` during a batch
+    pub publish_sequence: u64,
+    pub read_sequence: u64,
+    pub ack_mode: bool,
+    pub ack_sequence: u64,
+    pub retention_us: u64,
+    pub created_at_us: u64,
+    /// Incremented every time a writer recreates the segment under this name
+    pub generation: u64,
+    pub writer_active: bool,
+    pub writer_pid: i32,
+    pub reader_count: i32,
+    /// The writer's `with_max_readers` limit
+    pub max_readers: Option<usize>,
+    pub auth_required: bool,
+    pub groups_enabled: bool,
+    /// Spinlock states; a lock held across dumps points at a wedged process
+    pub groups_locked: bool,
+    pub meta_locked: bool,
+    /// Readers parked on the publish futex
+    pub futex_waiters: i32,
+    /// Semaphore counts, `None` where the transport has none
+    pub write_sem_value: Option<i32>,
+    pub read_sem_value: Option<i32>,
+    /// Sequence of the last frame the dumping endpoint read
+    pub last_read_sequence: Option<u64>,
+    pub slots: Vec<SlotDump>,
+    pub groups: Vec<GroupDump>,
+    pub meta: BTreeMap<String, String>,
+}
+
+impl ArenaDump {
+    pub(crate) fn capture(raw: &dyn RawArena, segment: String, transport: Transport, is_writer: bool) -> Result<Self> {
+        let mut header = backend::HeaderDump::default();
+        if raw.dump_header(&mut header) != 0 {
+            return Err(QADataSwapError::NotConnected);
+        }
+
+        let mut groups = Vec::new();
+        for index in 0..backend::MAX_CONSUMER_GROUPS {
+            let mut group = backend::GroupDump::default();
+            if raw.dump_group(index, &mut group) == 1 {
+                groups.push(GroupDump {
+                    name: String::from_utf8_lossy(group.name.split(|b| *b == 0).next().unwrap_or_default())
+                        .into_owned(),
+                    cursor: group.cursor,
+                    members: group.members,
+                });
+            }
+        }
+
+        // Frames below this have been consumed, as the writer counts it
+        let consumed = match (header.groups_enabled != 0, header.ack_mode != 0) {
+            (true, _) => groups
+                .iter()
+                .filter(|g| g.members > 0)
+                .map(|g| g.cursor)
+                .min()
+                .unwrap_or(header.write_sequence),
+            (false, true) => header.ack_sequence,
+            (false, false) => header.read_sequence,
+        };
+
+        let mut slots = Vec::new();
+        let mut slot = backend::SlotDump::default();
+        while raw.dump_slot(slots.len(), &mut slot) == 1 {
+            let sequence = (slot.sequence != SLOT_EMPTY).then_some(slot.sequence);
+            slots.push(SlotDump {
+                index: slots.len(),
+                sequence,
+                size: slot.data_size,
+                timestamp_us: slot.timestamp,
+                ready: slot.ready != 0,
+                owner: match sequence {
+                    None => SlotOwner::Writer,
+                    Some(sequence) if sequence >= consumed => SlotOwner::Readers,
+                    Some(_) => SlotOwner::Free,
+                },
+            });
+        }
+
+        let meta = raw
+            .meta_keys()
+            .into_iter()
+            .filter_map(|key| Some((key.clone(), raw.get_meta(&key)?)))
+            .collect();
+
+        Ok(Self {
+            segment,
+            transport,
+            is_writer,
+            magic: header.magic,
+            version: header.version,
+            total_bytes: header.total_size,
+            header_bytes: header.header_size,
+            buffer_count: header.buffer_count,
+            buffer_bytes: header.buffer_size,
+            max_buffer_count: header.max_buffer_count,
+            geometry_epoch: header.geometry_epoch,
+            write_sequence: header.write_sequence,
+            publish_sequence: header.publish_sequence,
+            read_sequence: header.read_sequence,
+            ack_mode: header.ack_mode != 0,
+            ack_sequence: header.ack_sequence,
+            retention_us: header.retention_us,
+            created_at_us: header.created_at_us,
+            generation: header.generation,
+            writer_active: header.writer_active != 0,
+            writer_pid: header.writer_pid,
+            reader_count: header.reader_count,
+            max_readers: (header.max_readers > 0).then_some(header.max_readers as usize),
+            auth_required: header.auth_required != 0,
+            groups_enabled: header.groups_enabled != 0,
+            groups_locked: header.groups_lock != 0,
+            meta_locked: header.meta_lock != 0,
+            futex_waiters: header.futex_waiters,
+            write_sem_value: (header.write_sem_value >= 0).then_some(header.write_sem_value),
+            read_sem_value: (header.read_sem_value >= 0).then_some(header.read_sem_value),
+            last_read_sequence: (header.last_read_valid != 0).then_some(header.last_read_sequence),
+            slots,
+            groups,
+            meta,
+        })
+    }
+}
+
+impl fmt::Display for ArenaDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let held = |locked: bool| if locked { "HELD" } else { "free" };
+        let sem = |value: Option<i32>| value.map_or("-".to_string(), |v| v.to_string());
+
+        writeln!(f, "segment:          {} ({:?}, dumped by the {})",
+                 self.segment, self.transport, if self.is_writer { "writer" } else { "reader" })?;
+        writeln!(f, "layout:           magic {:#010x} v{}, {} x {} bytes (max {}), header {} bytes, epoch {}",
+                 self.magic, self.version, self.buffer_count, self.buffer_bytes, self.max_buffer_count,
+                 self.header_bytes, self.geometry_epoch)?;
+        writeln!(f, "writer:           pid {} ({}), created at {} us, generation {}",
+                 self.writer_pid, if self.writer_active { "active" } else { "gone" }, self.created_at_us,
+                 self.generation)?;
+        writeln!(f, "readers:          {}{}{}", self.reader_count,
+                 self.max_readers.map_or(String::new(), |max| format!(" of {}", max)),
+                 if self.auth_required { " (token required)" } else { "" })?;
+        writeln!(f, "sequences:        write {}, publish {}, read {}, ack {} (ack mode {})",
+                 self.write_sequence, self.publish_sequence, self.read_sequence, self.ack_sequence,
+                 if self.ack_mode { "on" } else { "off" })?;
+        writeln!(f, "retention:        {}",
+                 if self.retention_us == 0 { "ring only".to_string() } else { format!("{} us", self.retention_us) })?;
+        writeln!(f, "locks:            groups {}, meta {}, futex waiters {}, semaphores write {} / read {}",
+                 held(self.groups_locked), held(self.meta_locked), self.futex_waiters,
+                 sem(self.write_sem_value), sem(self.read_sem_value))?;
+        writeln!(f, "last read here:   {}", self.last_read_sequence.map_or("-".to_string(), |s| s.to_string()))?;
+
+        writeln!(f, "slots:")?;
+        for slot in &self.slots {
+            writeln!(f, "  [{:>3}] seq {:>10}  {:>10} bytes  ts {:>16}  {:<5}  {:?}",
+                     slot.index, slot.sequence.map_or("-".to_string(), |s| s.to_string()), slot.size,
+                     slot.timestamp_us, if slot.ready { "ready" } else { "" }, slot.owner)?;
+        }
+        if self.groups_enabled {
+            writeln!(f, "groups:")?;
+            for group in &self.groups {
+                writeln!(f, "  {:<32} cursor {:>10}  members {}", group.name, group.cursor, group.members)?;
+            }
+        }
+        write!(f, "meta:             {} entries", self.meta.len())?;
+        for (key, value) in &self.meta {
+            write!(f, "\n  {} = {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataStream;
+    use polars::df;
+
+    #[test]
+    fn test_debug_dump_shows_ring_and_cursors() -> Result<()> {
+        let config = test_config("dump_ticks", 4);
+        let writer = SharedDataStream::create_writer(config.clone())?;
+        let reader = SharedDataStream::create_reader(config)?;
+        writer.put_meta("trading_date", "2024-05-01")?;
+
+        for px in [1.0, 2.0, 3.0] {
+            writer.write_chunk(&df! { "px" => [px] }?)?;
+        }
+        reader.read_chunk(Some(100))?;
+
+        let dump = reader.debug_dump()?;
+        assert!(!dump.is_writer && dump.writer_active);
+        assert_eq!((dump.write_sequence, dump.read_sequence, dump.last_read_sequence), (3, 1, Some(0)));
+        let owners: Vec<_> = dump.slots.iter().map(|slot| slot.owner).collect();
+        assert_eq!(owners, [SlotOwner::Free, SlotOwner::Readers, SlotOwner::Readers, SlotOwner::Writer]);
+        assert_eq!(dump.slots[2].sequence, Some(2));
+        assert!(!dump.meta_locked);
+
+        let text = dump.to_string();
+        assert!(text.contains("write 3, publish 3, read 1"), "{}", text);
+        assert!(text.contains("trading_date = 2024-05-01"), "{}", text);
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Full-duplex DataFrame pipe between two processes under one name
+//!
+//! `create` writes to `<name>#ab` and reads `<name>#ba`; `connect` does the
+//! opposite. The creator's inbound side attaches on its first receive, once
+//! the peer has connected.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use polars::prelude::*;
+
+use crate::priority::LANE_SEPARATOR;
+use crate::{Event, QADataSwapError, Result, SharedDataFrame, SharedMemoryConfig};
+
+/// Per-direction defaults: 16 MB in 8 slots
+pub const DUPLEX_SIZE_MB: usize = 16;
+pub const DUPLEX_BUFFER_COUNT: usize = 8;
+
+/// How often a creator waiting for its peer retries attaching
+const CONNECT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+fn direction(config: &SharedMemoryConfig, suffix: &str) -> SharedMemoryConfig {
+    let mut direction = config.clone();
+    direction.name = format!("{}{}{}", config.name, LANE_SEPARATOR, suffix);
+    direction
+}
+
+/// One end of a duplex channel
+pub struct DuplexChannel {
+    name: String,
+    tx: SharedDataFrame,
+    rx: Mutex<Option<SharedDataFrame>>,
+    rx_config: SharedMemoryConfig,
+}
+
+impl DuplexChannel {
+    /// First end, with the default size and slot count
+    pub fn create(name: &str) -> Result<Self> {
+        Self::create_with(Self::defaults(name))
+    }
+
+    /// Second end; fails if no end has been created under `name`
+    pub fn connect(name: &str) -> Result<Self> {
+        Self::connect_with(Self::defaults(name))
+    }
+
+    /// Both directions use `config`, whose name is the channel's
+    pub fn create_with(config: SharedMemoryConfig) -> Result<Self> {
+        Ok(Self {
+            name: config.name.clone(),
+            tx: SharedDataFrame::create_writer(direction(&config, "ab"))?,
+            rx: Mutex::new(None),
+            rx_config: direction(&config, "ba"),
+        })
+    }
+
+    pub fn connect_with(config: SharedMemoryConfig) -> Result<Self> {
+        let rx = SharedDataFrame::create_reader(direction(&config, "ab"))?;
+        Ok(Self {
+            name: config.name.clone(),
+            tx: SharedDataFrame::create_writer(direction(&config, "ba"))?,
+            rx: Mutex::new(Some(rx)),
+            rx_config: direction(&config, "ab"),
+        })
+    }
+
+    fn defaults(name: &str) -> SharedMemoryConfig {
+        SharedMemoryConfig::new(name)
+            .with_size_mb(DUPLEX_SIZE_MB)
+            .with_buffer_count(DUPLEX_BUFFER_COUNT)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the inbound direction is attached, i.e. the peer has connected
+    pub fn is_connected(&self) -> bool {
+        self.rx.lock().unwrap().is_some()
+    }
+
+    pub fn send(&self, df: &DataFrame) -> Result<()> {
+        self.tx.write(df)
+    }
+
+    pub fn send_event(&self, event: &Event) -> Result<()> {
+        self.tx.write_event(event)
+    }
+
+    /// Next data frame from the peer; control frames are skipped
+    pub fn recv(&self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
+        self.with_rx(timeout_ms, |rx, remaining| rx.read(remaining))
+    }
+
+    pub fn recv_event(&self, timeout_ms: Option<i32>) -> Result<Option<Event>> {
+        self.with_rx(timeout_ms, |rx, remaining| rx.read_event(remaining))
+    }
+
+    /// Run `read` on the inbound direction, first waiting for the peer to
+    /// connect if it has not yet. `None` or a negative timeout waits forever.
+    fn with_rx<T>(
+        &self,
+        timeout_ms: Option<i32>,
+        read: impl FnOnce(&SharedDataFrame, Option<i32>) -> Result<T>,
+    ) -> Result<T> {
+        let time = &self.rx_config.time_source;
+        let deadline = timeout_ms.filter(|ms| *ms >= 0).map(|ms| time.instant() + Duration::from_millis(ms as u64));
+        let remaining = || deadline.map(|d| d.saturating_duration_since(time.instant()).as_millis() as i32);
+        let mut rx = self.rx.lock().unwrap();
+        while rx.is_none() {
+            match SharedDataFrame::create_reader(self.rx_config.clone()) {
+                Ok(reader) => *rx = Some(reader),
+                Err(_) if remaining() != Some(0) => time.sleep(CONNECT_POLL_INTERVAL),
+                Err(_) => return Err(QADataSwapError::Timeout),
+            }
+        }
+        read(rx.as_ref().unwrap(), remaining())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use polars::df;
+
+    #[test]
+    fn test_duplex_round_trip() -> Result<()> {
+        assert!(DuplexChannel::connect_with(test_config("duplex_none", 4)).is_err());
+
+        let server = DuplexChannel::create_with(test_config("duplex_rpc", 4))?;
+        assert!(matches!(server.recv(Some(10)), Err(QADataSwapError::Timeout)));
+        let client = DuplexChannel::connect_with(test_config("duplex_rpc", 4))?;
+        assert!(client.is_connected() && !server.is_connected());
+
+        client.send(&df! { "request" => ["quote"] }?)?;
+        let request = server.recv(Some(1000))?.unwrap();
+        assert!(server.is_connected());
+        assert_eq!(request.column("request")?.str()?.get(0), Some("quote"));
+
+        server.send(&df! { "px" => [101.5] }?)?;
+        server.send_event(&Event::Eos)?;
+        assert_eq!(client.recv(Some(100))?.unwrap().column("px")?.f64()?.get(0), Some(101.5));
+        assert!(matches!(client.recv_event(Some(100))?, Some(Event::Eos)));
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+//! Protocol event logging through the `log` crate, so production incidents can
+//! be reconstructed from logs
+//!
+//! Enabled per channel with `with_event_logging(level)`. Every event goes to
+//! the `EVENT_TARGET` target as `<segment> <event>: <details>`, where the event
+//! is one of `attach`, `detach`, `wrap`, `drop`, `timeout`, `recovery`,
+//! `reconnect` or `handover`.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use log::Level;
+
+/// Log target of every protocol event
+pub const EVENT_TARGET: &str = "qadataswap::events";
+
+const NO_SEQUENCE: u64 = u64::MAX;
+
+/// One arena's event emitter
+#[derive(Debug)]
+pub(crate) struct EventLog {
+    level: Option<Level>,
+    segment: String,
+    /// Reader: sequence of the previous frame, to spot frames lost to lapping
+    last_sequence: AtomicU64,
+    /// Set until the arena attaches, and again once it detaches
+    detached: AtomicBool,
+}
+
+impl EventLog {
+    pub(crate) fn new(level: Option<Level>, segment: &str) -> Self {
+        Self {
+            level,
+            segment: segment.to_string(),
+            last_sequence: AtomicU64::new(NO_SEQUENCE),
+            detached: AtomicBool::new(true),
+        }
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.level.is_some_and(|level| log::log_enabled!(target: EVENT_TARGET, level))
+    }
+
+    pub(crate) fn emit(&self, event: &str, details: fmt::Arguments<'_>) {
+        if let Some(level) = self.level {
+            log::log!(target: EVENT_TARGET, level, "{} {}: {}", self.segment, event, details);
+        }
+    }
+
+    /// Report the frames between the previous read and `sequence` that the
+    /// writer overwrote, and where the reader picked up again
+    pub(crate) fn observe_read(&self, sequence: Option<u64>) {
+        let (true, Some(sequence)) = (self.enabled(), sequence) else {
+            return;
+        };
+        let previous = self.last_sequence.swap(sequence, Ordering::Relaxed);
+        if previous != NO_SEQUENCE && sequence > previous + 1 {
+            self.emit("drop", format_args!("{} frame(s) overwritten before they were read", sequence - previous - 1));
+            self.emit("recovery", format_args!("resumed at sequence {}", sequence));
+        }
+    }
+
+    pub(crate) fn attach(&self, details: fmt::Arguments<'_>) {
+        self.detached.store(false, Ordering::Relaxed);
+        self.emit("attach", details);
+    }
+
+    /// Forget the read position, e.g. after a seek
+    pub(crate) fn reset_position(&self) {
+        self.last_sequence.store(NO_SEQUENCE, Ordering::Relaxed);
+    }
+
+    /// Emit `detach` once, whether the arena is closed, dropped or both
+    pub(crate) fn detach(&self, role: &str) {
+        if !self.detached.swap(true, Ordering::Relaxed) {
+            self.emit("detach", format_args!("{} detached", role));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{test_config, FaultInjector};
+    use crate::{OpenMode, QADataSwapError, Result, SharedDataFrame};
+    use log::{Log, Metadata, Record};
+    use polars::df;
+    use std::sync::Mutex;
+
+    static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    struct Capture;
+
+    impl Log for Capture {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.target() == EVENT_TARGET
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                EVENTS.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_protocol_events_are_logged() -> Result<()> {
+        log::set_logger(&Capture).unwrap();
+        log::set_max_level(log::LevelFilter::Info);
+        let config = test_config("events_ticks", 2).with_event_logging(Level::Info);
+
+        let injector = FaultInjector::new().crash_writer_at(2);
+        let writer = SharedDataFrame::create_writer(config.clone().with_fault_injector(injector))?;
+        let reader = SharedDataFrame::create_reader(config.clone())?;
+        writer.write(&df! { "px" => [1.0] }?)?;
+        writer.write(&df! { "px" => [2.0] }?)?;
+        reader.read(Some(100))?;
+        reader.read(Some(0))?;
+        assert!(matches!(reader.read(Some(0)), Err(QADataSwapError::Timeout)));
+
+        assert!(writer.write(&df! { "px" => [3.0] }?).is_err());
+        let restarted = SharedDataFrame::create_writer(config.with_open_mode(OpenMode::OpenOrAttach))?;
+        restarted.close();
+        drop(reader);
+
+        let events = EVENTS.lock().unwrap();
+        for expected in [
+            "events_ticks attach: writer attached",
+            "events_ticks attach: reader attached",
+            "events_ticks wrap: ring wrapped at sequence 2",
+            "events_ticks timeout: no frame within 0 ms",
+            "events_ticks detach: reader detached",
+            "events_ticks recovery: writer reattached",
+            "events_ticks detach: writer detached",
+        ] {
+            assert!(events.iter().any(|e| e.starts_with(expected)), "{} not in {:#?}", expected, events);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,375 @@
+//! Fixed-schema records for small high-rate messages
+//!
+//! A `FixedLayout` declares primitive fields once; each record is then a
+//! FlatBuffers-style struct of those fields at their natural alignment, and a
+//! frame is a vector of such records (`FrameKind::Coded`, payload
+//! `u32 count | u32 record size | records`, little-endian). As a `Codec` it
+//! carries ordinary data frames, and readers that know the layout decode them
+//! with `read()`. For single-tick updates, where Arrow IPC framing dominates,
+//! `write_records` fills the records straight into the ring slot and
+//! `read_records` hands out a view over a buffer the caller reuses, so neither
+//! side builds a DataFrame or allocates per frame.
+//!
+//! The codec name includes a digest of the field names and types, so a reader
+//! with a different layout fails the handshake instead of misreading records.
+
+use std::mem::size_of;
+use std::ptr;
+use std::time::Duration;
+
+use polars::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::builder::FrameNative;
+use crate::frame::{FrameHeader, FrameKind, FRAME_HEADER_SIZE};
+use crate::{Codec, QADataSwapError, Result, SharedMemoryArena};
+
+/// Record count and record size ahead of the records
+const PREFIX_SIZE: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FixedField {
+    name: String,
+    dtype: DataType,
+    tag: u8,
+    offset: usize,
+}
+
+/// Declared fixed schema of primitive, non-null fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedLayout {
+    fields: Vec<FixedField>,
+    record_size: usize,
+    name: String,
+}
+
+impl FixedLayout {
+    /// Lay out `fields` in order, each aligned to its own size. Supports the
+    /// `FrameNative` types: Int32, Int64, UInt32, UInt64, Float32 and Float64.
+    pub fn new(fields: &[(&str, DataType)]) -> Result<Self> {
+        let mut laid_out = Vec::with_capacity(fields.len());
+        let mut end = 0usize;
+        let mut digest = Sha256::new();
+        for (name, dtype) in fields {
+            let (tag, size) = match dtype {
+                DataType::Int32 => (i32::TAG, size_of::<i32>()),
+                DataType::Int64 => (i64::TAG, size_of::<i64>()),
+                DataType::UInt32 => (u32::TAG, size_of::<u32>()),
+                DataType::UInt64 => (u64::TAG, size_of::<u64>()),
+                DataType::Float32 => (f32::TAG, size_of::<f32>()),
+                DataType::Float64 => (f64::TAG, size_of::<f64>()),
+                other => {
+                    return Err(QADataSwapError::SharedMemory(format!(
+                        "Field '{}' has type {}, which fixed records cannot hold", name, other
+                    )))
+                },
+            };
+            if laid_out.iter().any(|field: &FixedField| field.name == *name) {
+                return Err(QADataSwapError::SharedMemory(format!("Field '{}' is declared twice", name)));
+            }
+            let offset = end.next_multiple_of(size);
+            end = offset + size;
+            laid_out.push(FixedField { name: name.to_string(), dtype: dtype.clone(), tag, offset });
+            digest.update(name.as_bytes());
+            digest.update([0, tag]);
+        }
+        if laid_out.is_empty() {
+            return Err(QADataSwapError::SharedMemory("A fixed layout needs at least one field".to_string()));
+        }
+
+        // Records follow one another, so pad to the widest field
+        let record_size = end.next_multiple_of(laid_out.iter().map(|f| tag_size(f.tag)).max().unwrap_or(1));
+        let hex: String = digest.finalize()[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        Ok(Self { fields: laid_out, record_size, name: format!("fixed-v1:{}", hex) })
+    }
+
+    /// Bytes per record, padding included
+    pub fn record_size(&self) -> usize {
+        self.record_size
+    }
+
+    /// Index of the field called `name`, for `FixedRecords::get` and `FixedRecordsMut::set`
+    pub fn field(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == name)
+    }
+
+    /// Records area of a payload, checked against this layout
+    fn records<'p>(&self, payload: &'p [u8]) -> Result<(usize, &'p [u8])> {
+        let corrupt = |reason: &str| QADataSwapError::SharedMemory(format!("Corrupt fixed-record frame: {}", reason));
+        if payload.len() < PREFIX_SIZE {
+            return Err(corrupt("truncated prefix"));
+        }
+        let count = u32::from_le_bytes(payload[0..4].try_into().unwrap()) as usize;
+        let size = u32::from_le_bytes(payload[4..8].try_into().unwrap()) as usize;
+        if size != self.record_size {
+            return Err(corrupt(&format!("records of {} bytes, layout has {}", size, self.record_size)));
+        }
+        let records = count
+            .checked_mul(size)
+            .and_then(|len| payload.get(PREFIX_SIZE..PREFIX_SIZE.checked_add(len)?))
+            .ok_or_else(|| corrupt("fewer records than the prefix claims"))?;
+        Ok((count, records))
+    }
+}
+
+fn tag_size(tag: u8) -> usize {
+    match tag {
+        0 | 2 | 4 => 4,
+        _ => 8,
+    }
+}
+
+impl Codec for FixedLayout {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn encode(&self, df: &DataFrame, out: &mut Vec<u8>) -> Result<()> {
+        let rows = df.height();
+        let start = out.len();
+        out.extend_from_slice(&(rows as u32).to_le_bytes());
+        out.extend_from_slice(&(self.record_size as u32).to_le_bytes());
+        out.resize(start + PREFIX_SIZE + rows * self.record_size, 0);
+        let mut records = FixedRecordsMut { layout: self, data: &mut out[start + PREFIX_SIZE..], len: rows };
+
+        for (index, field) in self.fields.iter().enumerate() {
+            let column = df.column(&field.name)?.cast(&field.dtype)?;
+            if column.null_count() > 0 {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Column '{}' has nulls, which fixed records cannot hold", field.name
+                )));
+            }
+            let series = column.as_materialized_series();
+            match field.tag {
+                0 => records.set_column(index, series.i32()?.into_no_null_iter()),
+                1 => records.set_column(index, series.i64()?.into_no_null_iter()),
+                2 => records.set_column(index, series.u32()?.into_no_null_iter()),
+                3 => records.set_column(index, series.u64()?.into_no_null_iter()),
+                4 => records.set_column(index, series.f32()?.into_no_null_iter()),
+                _ => records.set_column(index, series.f64()?.into_no_null_iter()),
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<DataFrame> {
+        let (len, data) = self.records(bytes)?;
+        let records = FixedRecords { layout: self, data, len };
+        let columns = (0..self.fields.len())
+            .map(|index| match self.fields[index].tag {
+                0 => records.column::<i32>(index),
+                1 => records.column::<i64>(index),
+                2 => records.column::<u32>(index),
+                3 => records.column::<u64>(index),
+                4 => records.column::<f32>(index),
+                _ => records.column::<f64>(index),
+            })
+            .collect();
+        DataFrame::new(columns).map_err(QADataSwapError::Polars)
+    }
+}
+
+/// Records read with `read_records`, borrowed from the caller's buffer
+#[derive(Debug, Clone, Copy)]
+pub struct FixedRecords<'a> {
+    layout: &'a FixedLayout,
+    data: &'a [u8],
+    len: usize,
+}
+
+impl<'a> FixedRecords<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Field `field` of record `row`; `None` past the last record or when `T`
+    /// is not the field's type
+    pub fn get<T: FrameNative>(&self, row: usize, field: usize) -> Option<T> {
+        let field = self.layout.fields.get(field).filter(|f| f.tag == T::TAG)?;
+        (row < self.len).then(|| T::read_le(&self.data[row * self.layout.record_size + field.offset..]))
+    }
+
+    fn column<T: FrameNative>(&self, field: usize) -> Column {
+        let values = (0..self.len).filter_map(|row| self.get::<T>(row, field)).collect();
+        T::into_series(&self.layout.fields[field].name, values).into_column()
+    }
+}
+
+/// Records being filled in place by `write_records`
+#[derive(Debug)]
+pub struct FixedRecordsMut<'a> {
+    layout: &'a FixedLayout,
+    data: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> FixedRecordsMut<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Set field `field` of record `row`; fields left unset read as zero
+    pub fn set<T: FrameNative>(&mut self, row: usize, field: usize, value: T) -> Result<()> {
+        let Some(spec) = self.layout.fields.get(field) else {
+            return Err(QADataSwapError::SharedMemory(format!("Layout has no field {}", field)));
+        };
+        if spec.tag != T::TAG {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Field '{}' is {}, not {}", spec.name, spec.dtype, std::any::type_name::<T>()
+            )));
+        }
+        if row >= self.len {
+            return Err(QADataSwapError::SharedMemory(format!("Record {} of {}", row, self.len)));
+        }
+        value.write_le(&mut self.data[row * self.layout.record_size + spec.offset..]);
+        Ok(())
+    }
+
+    fn set_column<T: FrameNative>(&mut self, field: usize, values: impl Iterator<Item = T>) {
+        let offset = self.layout.fields[field].offset;
+        for (row, value) in values.take(self.len).enumerate() {
+            value.write_le(&mut self.data[row * self.layout.record_size + offset..]);
+        }
+    }
+}
+
+impl SharedMemoryArena {
+    /// Fill `rows` records of `layout` directly in the next slot, then publish them
+    pub(crate) fn write_records(&self, layout: &FixedLayout, rows: usize,
+                                fill: impl FnOnce(&mut FixedRecordsMut<'_>) -> Result<()>) -> Result<()> {
+        if self.config.codec.as_ref().is_none_or(|codec| codec.name() != layout.name()) {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Writer must be configured with_codec of layout {} to write its records", layout.name()
+            )));
+        }
+
+        let records_len = rows.checked_mul(layout.record_size).filter(|&len| len <= u32::MAX as usize);
+        let (slot, capacity) = self.acquire_write_buffer()?;
+        let payload_len = match records_len {
+            Some(len) if rows <= u32::MAX as usize && FRAME_HEADER_SIZE + PREFIX_SIZE + len <= capacity => PREFIX_SIZE + len,
+            _ => {
+                self.release_acquired();
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "{} records of {} bytes do not fit in a {} byte slot", rows, layout.record_size, capacity
+                )));
+            },
+        };
+
+        // The slot still holds an older frame; unset fields must read as zero
+        let payload = unsafe {
+            let payload = std::slice::from_raw_parts_mut(slot.add(FRAME_HEADER_SIZE), payload_len);
+            ptr::write_bytes(payload.as_mut_ptr(), 0, payload_len);
+            payload
+        };
+        payload[0..4].copy_from_slice(&(rows as u32).to_le_bytes());
+        payload[4..8].copy_from_slice(&(layout.record_size as u32).to_le_bytes());
+        let mut records = FixedRecordsMut { layout, data: &mut payload[PREFIX_SIZE..], len: rows };
+        if let Err(e) = fill(&mut records) {
+            self.release_acquired();
+            return Err(e);
+        }
+
+        let header = FrameHeader::new_with_clock(FrameKind::Coded, self.writer_id(), payload_len, self.config.clock)
+            .stamped_by(&*self.config.time_source);
+        unsafe {
+            ptr::copy_nonoverlapping(header.to_bytes().as_ptr(), slot, FRAME_HEADER_SIZE);
+        }
+        self.publish_acquired(FRAME_HEADER_SIZE + payload_len)
+    }
+
+    /// Next data frame's records, read into `buffer`; control and expired frames are skipped
+    pub(crate) fn read_records<'b>(&self, layout: &'b FixedLayout, buffer: &'b mut Vec<u8>,
+                                   timeout_ms: Option<i32>) -> Result<Option<FixedRecords<'b>>> {
+        if self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Writer cannot read".to_string()));
+        }
+        let timeout = timeout_ms.unwrap_or(self.config.timeout_ms.unwrap_or(-1));
+        let time = &self.config.time_source;
+        let deadline = (timeout >= 0).then(|| time.instant() + Duration::from_millis(timeout as u64));
+
+        let size = loop {
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(time.instant()).as_millis() as i32,
+                None => timeout,
+            };
+            // The caller's buffer is reused across reads, growing to the largest frame
+            let read = self.read_into(buffer, Some(remaining));
+            self.sync_readiness();
+            read?;
+            let size = buffer.len();
+            let Some((header, _)) = FrameHeader::decode(&buffer[..size])? else {
+                return Err(QADataSwapError::SharedMemory("Raw payload on a fixed-record channel".to_string()));
+            };
+            match header.kind {
+                FrameKind::Coded if header.is_expired_at(self.config.time_source.now_ns(header.clock)) => {
+                    self.read_counters.expired.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                },
+                FrameKind::Coded => break size,
+                FrameKind::Eos | FrameKind::Heartbeat | FrameKind::Flush | FrameKind::SchemaChange => {},
+                other => {
+                    return Err(QADataSwapError::SharedMemory(format!(
+                        "{:?} frame on a fixed-record channel", other
+                    )))
+                },
+            }
+        };
+
+        let codec = self.codec()?;
+        if codec.name() != layout.name() {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Frames are encoded with codec '{}', not layout {}", codec.name(), layout.name()
+            )));
+        }
+        self.read_counters.frames.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let (_, payload) = FrameHeader::decode(&buffer[..size])?.expect("checked above");
+        let (len, data) = layout.records(payload)?;
+        Ok(Some(FixedRecords { layout, data, len }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_fixed_records_round_trip() -> Result<()> {
+        let layout = FixedLayout::new(&[("px", DataType::Float64), ("qty", DataType::Int32), ("seq", DataType::UInt64)])?;
+        assert_eq!(layout.record_size(), 24);
+        assert!(FixedLayout::new(&[("symbol", DataType::String)]).is_err());
+
+        let config = test_config("fixed_records", 4);
+        let writer = SharedDataFrame::create_writer(config.clone().with_codec(Arc::new(layout.clone())))?;
+        let reader = SharedDataFrame::create_reader(config.with_codec(Arc::new(layout.clone())))?;
+        let (px, qty) = (layout.field("px").unwrap(), layout.field("qty").unwrap());
+
+        writer.write_records(&layout, 2, |records| {
+            records.set(0, px, 3500.5)?;
+            records.set(0, qty, 3i32)?;
+            records.set(1, px, 3501.0)?;
+            assert!(records.set(1, qty, 4i64).is_err());
+            Ok(())
+        })?;
+        let mut buffer = Vec::new();
+        let records = reader.read_records(&layout, &mut buffer, Some(100))?.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records.get::<f64>(0, px), records.get::<i32>(0, qty)), (Some(3500.5), Some(3)));
+        assert_eq!((records.get::<i32>(1, qty), records.get::<i64>(1, qty), records.get::<f64>(2, px)), (Some(0), None, None));
+
+        // Data frames written through the codec decode with plain reads
+        let df = df! { "px" => [1.5, 2.5], "qty" => [1i32, 2], "seq" => [7u64, 8] }?;
+        writer.write(&df)?;
+        assert_eq!(reader.read(Some(100))?, Some(df));
+        Ok(())
+    }
+}
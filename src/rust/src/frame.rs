@@ -0,0 +1,438 @@
+//! Frame envelope: a fixed header in front of every payload written by this crate
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use polars::prelude::{DataFrame, SchemaRef};
+
+use crate::split;
+use crate::{Clock, ClockSource, Lineage, QADataSwapError, Result, RoutingKey};
+
+const FRAME_MAGIC: u32 = 0x4846_4451; // 'QDFH'
+const FRAME_VERSION: u8 = 2;
+
+/// Version 1 headers carried wall-clock microseconds and no clock bits
+const FRAME_VERSION_US: u8 = 1;
+
+/// Flag bits holding the header's `ClockSource`
+const CLOCK_SHIFT: u16 = 5;
+const CLOCK_MASK: u16 = 0b11 << CLOCK_SHIFT;
+
+/// Size of the fixed header preceding the payload
+pub const FRAME_HEADER_SIZE: usize = 32;
+
+/// Header flag: a null-count trailer follows the payload
+pub const FLAG_NULL_COUNTS: u16 = 1;
+
+/// Header flag: the payload starts with a string dictionary block, see `intern`
+pub const FLAG_STRING_DICTIONARY: u16 = 2;
+
+/// Header flag: the payload is compressed with a trained zstd dictionary, see `zdict`
+pub const FLAG_ZSTD_DICTIONARY: u16 = 4;
+
+/// Header flag: the writer compresses but sent this frame uncompressed, see
+/// `AdaptiveCompression`
+pub const FLAG_UNCOMPRESSED: u16 = 8;
+
+/// Header flag: a lineage trailer follows the null counts, see `lineage`
+pub const FLAG_LINEAGE: u16 = 16;
+
+/// Header flag: a routing key directly follows the payload, see `routing`
+pub const FLAG_ROUTING_KEY: u16 = 128;
+
+/// Header flag: the frame is one part of a split message, see `split`
+pub const FLAG_PART: u16 = 256;
+
+/// What a frame carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    /// Arrow IPC file
+    Data,
+    /// Raw primitive columns laid out in place by `FrameBuilder`
+    Columns,
+    /// Control: the writer will publish nothing further
+    Eos,
+    /// Control: liveness signal without data
+    Heartbeat,
+    /// Control: later frames use the schema in the payload (an empty IPC file)
+    SchemaChange,
+    /// Control: downstream should flush anything it has buffered
+    Flush,
+    /// Dense 2-D array written by `SharedTensor`
+    Tensor,
+    /// Arrow IPC files for groups of columns, see the `parallel` feature
+    ColumnGroups,
+    /// Data encoded by the writer's `Codec`, see `codec`
+    Coded,
+    /// Data in an Arrow IPC file the payload names, see `publish_ipc_file`
+    FileRef,
+}
+
+impl FrameKind {
+    /// Eos, heartbeat, schema change or flush
+    pub(crate) fn is_control(self) -> bool {
+        matches!(self, FrameKind::Eos | FrameKind::Heartbeat | FrameKind::SchemaChange | FrameKind::Flush)
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            FrameKind::Data => 0,
+            FrameKind::Columns => 1,
+            FrameKind::Eos => 2,
+            FrameKind::Heartbeat => 3,
+            FrameKind::SchemaChange => 4,
+            FrameKind::Flush => 5,
+            FrameKind::Tensor => 6,
+            FrameKind::ColumnGroups => 7,
+            FrameKind::Coded => 8,
+            FrameKind::FileRef => 9,
+        }
+    }
+
+    /// Number of kinds: the first byte `from_u8` rejects
+    #[cfg(feature = "fuzzing")]
+    pub(crate) fn count() -> u8 {
+        (0..u8::MAX).find(|&value| Self::from_u8(value).is_err()).unwrap_or(u8::MAX)
+    }
+
+    fn from_u8(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(FrameKind::Data),
+            1 => Ok(FrameKind::Columns),
+            2 => Ok(FrameKind::Eos),
+            3 => Ok(FrameKind::Heartbeat),
+            4 => Ok(FrameKind::SchemaChange),
+            5 => Ok(FrameKind::Flush),
+            6 => Ok(FrameKind::Tensor),
+            7 => Ok(FrameKind::ColumnGroups),
+            8 => Ok(FrameKind::Coded),
+            9 => Ok(FrameKind::FileRef),
+            other => Err(QADataSwapError::SharedMemory(format!("Unknown frame kind {}", other))),
+        }
+    }
+}
+
+/// Fixed frame header, little-endian on the wire:
+///
+/// ```text
+/// 0  magic u32 | 4 version u8 | 5 kind u8 | 6 flags u16
+/// 8  writer_id u32 | 12 ttl_ms u32 (0 = never expires)
+/// 16 timestamp_ns u64
+/// 24 payload_len u64
+/// ```
+///
+/// Flag bits 5-6 name the clock of `timestamp_ns`, see `ClockSource`.
+/// Version 1 frames, stamped in wall-clock microseconds, decode as `Realtime`.
+///
+/// With `FLAG_NULL_COUNTS` the payload is followed by the columns that had
+/// nulls when the frame was written: a u32 count, then per column a u16 name
+/// length, the UTF-8 name and a u64 null count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub kind: FrameKind,
+    pub flags: u16,
+    pub writer_id: u32,
+    /// Readers skip the frame once it is this old; 0 means it never expires
+    pub ttl_ms: u32,
+    /// Encode time on `clock`
+    pub timestamp_ns: u64,
+    pub clock: ClockSource,
+    pub payload_len: u64,
+}
+
+impl FrameHeader {
+    /// Stamped on the wall clock
+    pub fn new(kind: FrameKind, writer_id: u32, payload_len: usize) -> Self {
+        Self::new_with_clock(kind, writer_id, payload_len, ClockSource::Realtime)
+    }
+
+    pub fn new_with_clock(kind: FrameKind, writer_id: u32, payload_len: usize, clock: ClockSource) -> Self {
+        Self {
+            kind,
+            flags: 0,
+            writer_id,
+            ttl_ms: 0,
+            timestamp_ns: clock.now_ns(),
+            clock,
+            payload_len: payload_len as u64,
+        }
+    }
+
+    /// Whether the frame's TTL has run out, going by its own clock
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(self.clock.now_ns())
+    }
+
+    /// Whether the TTL has run out at `now_ns` on the frame's clock
+    pub fn is_expired_at(&self, now_ns: u64) -> bool {
+        let ttl_ns = (self.ttl_ms as u64).saturating_mul(1_000_000);
+        self.ttl_ms != 0 && now_ns > self.timestamp_ns.saturating_add(ttl_ns)
+    }
+
+    /// Stamp with the time on `time` instead of the host's clock
+    pub(crate) fn stamped_by(mut self, time: &dyn Clock) -> Self {
+        self.timestamp_ns = time.now_ns(self.clock);
+        self
+    }
+
+    /// Set the TTL, rounding up to whole milliseconds
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl_ms = match ttl {
+            Some(ttl) => ttl.as_millis().clamp(1, u32::MAX as u128) as u32,
+            None => 0,
+        };
+        self
+    }
+
+    pub fn encode_into(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_bytes());
+    }
+
+    pub fn to_bytes(&self) -> [u8; FRAME_HEADER_SIZE] {
+        let mut bytes = [0u8; FRAME_HEADER_SIZE];
+        bytes[0..4].copy_from_slice(&FRAME_MAGIC.to_le_bytes());
+        bytes[4] = FRAME_VERSION;
+        bytes[5] = self.kind.as_u8();
+        let flags = self.flags & !CLOCK_MASK | self.clock.as_bits() << CLOCK_SHIFT;
+        bytes[6..8].copy_from_slice(&flags.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.writer_id.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.ttl_ms.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.timestamp_ns.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.payload_len.to_le_bytes());
+        bytes
+    }
+
+    /// Parse a header, returning it with the payload that follows. Returns `None`
+    /// for bytes without the frame magic (raw payloads from other writers).
+    pub fn decode(bytes: &[u8]) -> Result<Option<(FrameHeader, &[u8])>> {
+        if bytes.len() < 4 || u32::from_le_bytes(bytes[0..4].try_into().unwrap()) != FRAME_MAGIC {
+            return Ok(None);
+        }
+        if bytes.len() < FRAME_HEADER_SIZE {
+            return Err(corrupt("truncated header"));
+        }
+        let version = bytes[4];
+        if version != FRAME_VERSION && version != FRAME_VERSION_US {
+            return Err(corrupt(&format!("unsupported version {}", version)));
+        }
+
+        let flags = u16::from_le_bytes(bytes[6..8].try_into().unwrap());
+        let timestamp = u64::from_le_bytes(bytes[16..24].try_into().unwrap());
+        let (timestamp_ns, clock) = match version {
+            FRAME_VERSION_US => (timestamp.saturating_mul(1000), ClockSource::Realtime),
+            _ => match ClockSource::from_bits((flags & CLOCK_MASK) >> CLOCK_SHIFT) {
+                Some(clock) => (timestamp, clock),
+                None => return Err(corrupt("unknown clock")),
+            },
+        };
+        let header = FrameHeader {
+            kind: FrameKind::from_u8(bytes[5])?,
+            flags: flags & !CLOCK_MASK,
+            writer_id: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            ttl_ms: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+            timestamp_ns,
+            clock,
+            payload_len: u64::from_le_bytes(bytes[24..32].try_into().unwrap()),
+        };
+
+        let payload = &bytes[FRAME_HEADER_SIZE..];
+        let payload_len = header.payload_len as usize;
+        if payload.len() < payload_len {
+            return Err(corrupt("payload shorter than header claims"));
+        }
+
+        Ok(Some((header, &payload[..payload_len])))
+    }
+}
+
+/// What `read_event()` returns: data, or one of the control frames.
+///
+/// A 0-row DataFrame is ordinary data carrying only its schema; readers get an
+/// empty DataFrame with that schema back.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Data(DataFrame),
+    /// The writer finished the stream
+    Eos,
+    Heartbeat,
+    /// Frames after this one follow the new schema
+    SchemaChange(SchemaRef),
+    Flush,
+    /// The reader moved to a segment a restarted writer recreated, see
+    /// `ReadPolicy::AutoReconnect`. Never written by writers.
+    Reconnected { generation: u64 },
+}
+
+impl Event {
+    pub(crate) fn kind(&self) -> FrameKind {
+        match self {
+            Event::Data(_) => FrameKind::Data,
+            Event::Eos => FrameKind::Eos,
+            Event::Heartbeat => FrameKind::Heartbeat,
+            Event::SchemaChange(_) => FrameKind::SchemaChange,
+            Event::Flush => FrameKind::Flush,
+            Event::Reconnected { .. } => unreachable!("reader-side event, rejected by write_event"),
+        }
+    }
+}
+
+/// Per-frame information handed to readers alongside the DataFrame
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameMetadata {
+    /// Position in the arena's sequence, if the backend reported one
+    pub sequence: Option<u64>,
+    /// Identity of the producing writer (process id unless configured)
+    pub writer_id: u32,
+    /// `timestamp_ns` in microseconds: since the Unix epoch for `Realtime` frames
+    pub timestamp_us: u64,
+    /// Encode time in nanoseconds on `clock`
+    pub timestamp_ns: u64,
+    /// The writer's `ClockSource`
+    pub clock: ClockSource,
+    /// Null count of every column that had nulls when the frame was written,
+    /// or `None` if the writer did not record them
+    pub null_counts: Option<BTreeMap<String, u64>>,
+    /// Whether adaptive compression sent this frame uncompressed
+    pub compression_skipped: bool,
+    /// Where the frame's data came from, if the writer said
+    pub lineage: Option<Lineage>,
+    /// Key the writer tagged the frame with, see `write_with_key`
+    pub routing_key: Option<RoutingKey>,
+}
+
+impl FrameMetadata {
+    pub(crate) fn from_header(header: &FrameHeader, sequence: Option<u64>) -> Self {
+        Self {
+            sequence,
+            writer_id: header.writer_id,
+            timestamp_us: header.timestamp_ns / 1000,
+            timestamp_ns: header.timestamp_ns,
+            clock: header.clock,
+            null_counts: None,
+            compression_skipped: header.flags & FLAG_UNCOMPRESSED != 0,
+            lineage: None,
+            routing_key: None,
+        }
+    }
+
+    /// Time since the frame was encoded, on the writer's clock. Meaningful within
+    /// one host, or across hosts for `Realtime` frames with synchronised clocks.
+    pub fn age_ns(&self) -> u64 {
+        self.clock.now_ns().saturating_sub(self.timestamp_ns)
+    }
+
+    /// Nulls `column` had at write time: 0 for columns without any, `None`
+    /// when the writer did not record null counts
+    pub fn null_count(&self, column: &str) -> Option<u64> {
+        self.null_counts.as_ref().map(|counts| counts.get(column).copied().unwrap_or(0))
+    }
+}
+
+/// Append the null-count trailer for `df`
+pub(crate) fn encode_null_counts(df: &DataFrame, out: &mut Vec<u8>) {
+    let with_nulls: Vec<_> = df.get_columns().iter().filter(|c| c.null_count() > 0).collect();
+    out.extend_from_slice(&(with_nulls.len() as u32).to_le_bytes());
+    for column in with_nulls {
+        let name = column.name().as_bytes();
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(name);
+        out.extend_from_slice(&(column.null_count() as u64).to_le_bytes());
+    }
+}
+
+/// Per-column null counts, see `FLAG_NULL_COUNTS`
+pub(crate) type NullCounts = BTreeMap<String, u64>;
+
+/// The null-count and lineage trailers of a complete frame, where its header
+/// has their flags
+pub(crate) fn decode_trailers(header: &FrameHeader, bytes: &[u8]) -> Result<(Option<NullCounts>, Option<Lineage>)> {
+    let mut trailer = bytes.get(FRAME_HEADER_SIZE + header.payload_len as usize..).unwrap_or_default();
+    if header.flags & FLAG_ROUTING_KEY != 0 {
+        trailer = &trailer[RoutingKey::parse(trailer)?.1..];
+    }
+    if header.flags & FLAG_PART != 0 {
+        trailer = trailer.get(split::PART_SIZE..).ok_or_else(|| corrupt("truncated part"))?;
+    }
+    let null_counts = match header.flags & FLAG_NULL_COUNTS {
+        0 => None,
+        _ => Some(decode_null_counts(&mut trailer)?),
+    };
+    let lineage = match header.flags & FLAG_LINEAGE {
+        0 => None,
+        _ => Some(Lineage::decode(trailer)?),
+    };
+    Ok((null_counts, lineage))
+}
+
+/// Consume the null-count trailer at the start of `trailer`
+fn decode_null_counts(trailer: &mut &[u8]) -> Result<NullCounts> {
+    let mut take = |len: usize| -> Result<&[u8]> {
+        if trailer.len() < len {
+            return Err(corrupt("truncated null counts"));
+        }
+        let (head, rest) = trailer.split_at(len);
+        *trailer = rest;
+        Ok(head)
+    };
+
+    let count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+    let mut counts = BTreeMap::new();
+    for _ in 0..count {
+        let name_len = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+        let name = String::from_utf8(take(name_len)?.to_vec()).map_err(|_| corrupt("column name is not UTF-8"))?;
+        counts.insert(name, u64::from_le_bytes(take(8)?.try_into().unwrap()));
+    }
+    Ok(counts)
+}
+
+fn corrupt(reason: &str) -> QADataSwapError {
+    QADataSwapError::SharedMemory(format!("Corrupt frame: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip() -> Result<()> {
+        let header = FrameHeader::new(FrameKind::Data, 42, 3);
+        let mut bytes = Vec::new();
+        header.encode_into(&mut bytes);
+        assert_eq!(bytes.len(), FRAME_HEADER_SIZE);
+        bytes.extend_from_slice(b"abc");
+
+        let (decoded, payload) = FrameHeader::decode(&bytes)?.unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(payload, b"abc");
+
+        // The clock rides in the flags without showing up in them
+        let header = FrameHeader::new_with_clock(FrameKind::Data, 42, 0, ClockSource::Monotonic);
+        let (decoded, _) = FrameHeader::decode(&header.to_bytes())?.unwrap();
+        assert_eq!((decoded.clock, decoded.flags, decoded.timestamp_ns), (ClockSource::Monotonic, 0, header.timestamp_ns));
+
+        // Version 1 frames carried wall-clock microseconds
+        let mut v1 = header.to_bytes();
+        v1[4] = 1;
+        v1[6..8].copy_from_slice(&0u16.to_le_bytes());
+        v1[16..24].copy_from_slice(&1_700_000_000_000_000u64.to_le_bytes());
+        let (decoded, _) = FrameHeader::decode(&v1)?.unwrap();
+        assert_eq!((decoded.clock, decoded.timestamp_ns), (ClockSource::Realtime, 1_700_000_000_000_000_000));
+
+        // Raw payloads pass through untouched
+        assert!(FrameHeader::decode(b"ARROW1")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expiry_saturates_on_hostile_headers() {
+        let mut header = FrameHeader::new(FrameKind::Data, 42, 0);
+        header.ttl_ms = u32::MAX;
+        header.timestamp_ns = u64::MAX - 1;
+        assert!(!header.is_expired_at(u64::MAX));
+
+        header.ttl_ms = 1;
+        header.timestamp_ns = 1_000;
+        assert!(!header.is_expired_at(1_001_000));
+        assert!(header.is_expired_at(1_001_001));
+    }
+}
@@ -0,0 +1,53 @@
+//! Entry points for the cargo-fuzz targets under `fuzz/`, not a stable API
+//!
+//! Frames carry no checksum, so whatever bytes a target is given reach the
+//! header parser, the trailer decoders and the payload decoders the way a torn
+//! or hostile frame in a shared segment would. Errors are the expected outcome;
+//! a panic, hang or runaway allocation is a bug.
+//!
+//! `FileRef` frames are parsed but never opened: a fuzzed path could name a
+//! FIFO or device and hang the target on I/O rather than on a decoder bug.
+
+use crate::frame::{self, FrameHeader, FrameKind};
+use crate::ipc_file::FileRef;
+use crate::{SharedMemoryArena, SharedMemoryConfig, Transport};
+
+/// Header and trailers only
+pub fn frame_header(data: &[u8]) {
+    if let Ok(Some((header, _))) = FrameHeader::decode(data) {
+        let _ = frame::decode_trailers(&header, data);
+    }
+}
+
+/// Everything a reader does with a frame's bytes, as if `data` came out of a slot
+pub fn decode_frame(data: &[u8]) {
+    thread_local! {
+        static READER: SharedMemoryArena = SharedMemoryArena::new(
+            SharedMemoryConfig::new("fuzz").with_size_mb(1).with_transport(Transport::InProcess),
+        )
+        .expect("in-process arena");
+    }
+    if let Ok(Some((header, payload))) = FrameHeader::decode(data) {
+        if header.kind == FrameKind::FileRef {
+            let _ = frame::decode_trailers(&header, data);
+            let _ = FileRef::decode(payload);
+            return;
+        }
+    }
+    READER.with(|reader| {
+        let _ = reader.decode_frame(reader, data, false);
+    });
+}
+
+/// `decode_frame` behind a well-formed header, so the fuzzer spends its time in
+/// the payload decoders: the first byte picks the kind, the next two the flags
+pub fn decode_payload(data: &[u8]) {
+    let [kind, flags_low, flags_high, payload @ ..] = data else {
+        return;
+    };
+    let mut bytes = FrameHeader::new(FrameKind::Data, 0, payload.len()).to_bytes().to_vec();
+    bytes[5] = kind % FrameKind::count();
+    bytes[6..8].copy_from_slice(&[*flags_low, *flags_high]);
+    bytes.extend_from_slice(payload);
+    decode_frame(&bytes);
+}
@@ -0,0 +1,118 @@
+//! Cooperative writer handover, for producer upgrades without downtime
+//!
+//! The replacement process attaches with `create_standby`, which announces it
+//! in the control block's `HANDOVER_META_KEY` entry and waits. The running
+//! writer calls `handover()`: it waits for a standby, then releases the
+//! segment the way a crashed writer would, without unlinking it, and the
+//! standby reattaches as its writer (`OpenMode::OpenOrAttach`). The ring, its
+//! sequence numbers and any unread frames stay where they are, so readers
+//! just see the next frame come from another writer id.
+//!
+//! A standby also takes over when the old writer exits without a handover.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{backend, OpenMode, QADataSwapError, Result, SharedMemoryArena, SharedMemoryConfig};
+
+/// Metadata key a standby writer registers under, as `standby:<writer id>`
+pub const HANDOVER_META_KEY: &str = "__handover";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+impl SharedMemoryArena {
+    /// Wait in standby until the current writer hands the segment over or
+    /// exits, then become its writer
+    pub(crate) fn standby(config: SharedMemoryConfig, timeout_ms: Option<i32>) -> Result<SharedMemoryArena> {
+        let timeout = timeout_ms.unwrap_or(config.timeout_ms.unwrap_or(-1));
+        let time = Arc::clone(&config.time_source);
+        let deadline = (timeout >= 0).then(|| time.instant() + Duration::from_millis(timeout as u64));
+        let mut arena = SharedMemoryArena::new(config.with_open_mode(OpenMode::OpenOrAttach))?;
+        let segment = arena.config.segment_name()?;
+
+        if arena.raw.attach_reader() != 0 {
+            return Err(QADataSwapError::SharedMemory(format!("No writer on '{}' to stand by for", segment)));
+        }
+        let standby = format!("standby:{}", arena.writer_id());
+        if let Some(other) = arena.raw.get_meta(HANDOVER_META_KEY).filter(|value| *value != standby) {
+            arena.raw.close();
+            return Err(QADataSwapError::SharedMemory(format!("'{}' already has a standby writer ({})", segment, other)));
+        }
+        arena.raw.put_meta(HANDOVER_META_KEY, Some(&standby));
+
+        while backend::inspect_segment(arena.config.transport, &segment).is_some_and(|status| status.writer_alive != 0) {
+            if deadline.is_some_and(|deadline| time.instant() >= deadline) {
+                arena.raw.put_meta(HANDOVER_META_KEY, None);
+                arena.raw.close();
+                return Err(QADataSwapError::Timeout);
+            }
+            time.sleep(POLL_INTERVAL);
+        }
+
+        arena.raw.close();
+        arena.create_writer()?;
+        arena.raw.put_meta(HANDOVER_META_KEY, None);
+        arena.events.emit("handover", format_args!("took over as writer {}", arena.writer_id()));
+        Ok(arena)
+    }
+
+    /// Wait for a standby writer, then release the segment to it. The arena is
+    /// detached afterwards and further writes fail.
+    pub(crate) fn handover(&self, timeout_ms: Option<i32>) -> Result<()> {
+        if !self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Not a writer".to_string()));
+        }
+        let timeout = timeout_ms.unwrap_or(self.config.timeout_ms.unwrap_or(-1));
+        let time = &self.config.time_source;
+        let deadline = (timeout >= 0).then(|| time.instant() + Duration::from_millis(timeout as u64));
+
+        let standby = loop {
+            if let Some(standby) = self.raw.get_meta(HANDOVER_META_KEY) {
+                break standby;
+            }
+            if deadline.is_some_and(|deadline| time.instant() >= deadline) {
+                return Err(QADataSwapError::Timeout);
+            }
+            time.sleep(POLL_INTERVAL);
+        };
+
+        if let Some(lane) = &self.high_lane {
+            lane.raw.abandon();
+        }
+        self.raw.abandon();
+        self.events.emit("handover", format_args!("released the segment to {}", standby));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+    use std::thread;
+    use polars::df;
+
+    #[test]
+    fn test_handover_continues_the_sequence() -> Result<()> {
+        let config = test_config("handover", 8);
+        let old = SharedDataFrame::create_writer(config.clone().with_writer_id(1))?;
+        let reader = SharedDataFrame::create_reader(config.clone())?;
+        old.write(&df! { "px" => [1.0] }?)?;
+        assert!(matches!(old.handover(Some(10)), Err(QADataSwapError::Timeout)));
+
+        let standby = thread::spawn(move || SharedDataFrame::create_standby(config.with_writer_id(2), Some(5000)));
+        old.handover(Some(5000))?;
+        assert!(old.write(&df! { "px" => [0.0] }?).is_err());
+        let new = standby.join().unwrap()?;
+        new.write(&df! { "px" => [2.0] }?)?;
+
+        let mut seen = Vec::new();
+        for _ in 0..2 {
+            let (metadata, _) = reader.read_with_metadata(Some(1000))?.unwrap();
+            seen.push((metadata.sequence, metadata.writer_id));
+        }
+        assert_eq!(seen, [(Some(0), 1), (Some(1), 2)]);
+        Ok(())
+    }
+}
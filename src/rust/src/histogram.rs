@@ -0,0 +1,272 @@
+//! Frame size and timing distributions, recorded with `with_histograms`
+//!
+//! `Histogram` buckets values the way HdrHistogram does: exactly up to 255,
+//! then in buckets no wider than 1/128 of their value, so every quantile is
+//! within 1% whatever the range, at a fixed cost per recorded value.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Sub-buckets per power of two; sets the relative precision
+const SUB_BUCKET_BITS: u32 = 7;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+
+/// Quantiles printed by `Display`
+const REPORTED: [(&str, f64); 4] = [("p50", 0.5), ("p90", 0.9), ("p99", 0.99), ("p99.9", 0.999)];
+
+/// Formats a recorded value with its unit
+type Unit = fn(u64) -> String;
+
+/// Log-linear histogram of `u64` values
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    sum: u128,
+    min: u64,
+    max: u64,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, value: u64) {
+        let index = bucket_index(value);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += 1;
+        self.min = if self.count == 0 { value } else { self.min.min(value) };
+        self.max = self.max.max(value);
+        self.count += 1;
+        self.sum += value as u128;
+    }
+
+    /// Values recorded
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+
+    /// Smallest value at least a `q` fraction of the recorded values are at or
+    /// below, to within the bucket precision
+    pub fn quantile(&self, q: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((self.count as f64 * q.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(bucket_high(index).clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    /// Non-empty buckets as (lowest value, highest value, count), ascending
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, u64, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(index, &count)| (bucket_low(index), bucket_high(index), count))
+    }
+
+    /// Add everything `other` recorded
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.count == 0 {
+            return;
+        }
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (mine, theirs) in self.counts.iter_mut().zip(&other.counts) {
+            *mine += theirs;
+        }
+        self.min = if self.count == 0 { other.min } else { self.min.min(other.min) };
+        self.max = self.max.max(other.max);
+        self.count += other.count;
+        self.sum += other.sum;
+    }
+
+    /// One line of quantiles with `unit` formatting the values
+    fn summary(&self, unit: Unit) -> String {
+        if self.count == 0 {
+            return "no samples".to_string();
+        }
+        let mut line = format!("n={} min {}", self.count, unit(self.min));
+        for (name, q) in REPORTED {
+            line += &format!(" {} {}", name, unit(self.quantile(q).unwrap_or_default()));
+        }
+        line + &format!(" max {}", unit(self.max))
+    }
+
+    /// Counts per power of two, as bars scaled to the fullest row
+    fn bars(&self, f: &mut fmt::Formatter<'_>, unit: Unit) -> fmt::Result {
+        let mut rows: Vec<(u64, u64)> = Vec::new();
+        for (low, _, count) in self.buckets() {
+            let floor = if low == 0 { 0 } else { 1 << low.ilog2() };
+            match rows.last_mut() {
+                Some((last, total)) if *last == floor => *total += count,
+                _ => rows.push((floor, count)),
+            }
+        }
+        let widest = rows.iter().map(|(_, count)| *count).max().unwrap_or(1);
+        for (floor, count) in rows {
+            let bar = "#".repeat(((count * 40).div_ceil(widest)) as usize);
+            writeln!(f, "    >= {:>10} {:>8} {}", unit(floor), count, bar)?;
+        }
+        Ok(())
+    }
+}
+
+fn bucket_index(value: u64) -> usize {
+    let bits = u64::BITS - value.leading_zeros();
+    let shift = bits.saturating_sub(SUB_BUCKET_BITS + 1);
+    shift as usize * SUB_BUCKETS + (value >> shift) as usize
+}
+
+fn bucket_low(index: usize) -> u64 {
+    let shift = (index / SUB_BUCKETS).saturating_sub(1);
+    ((index - shift * SUB_BUCKETS) as u64) << shift
+}
+
+fn bucket_high(index: usize) -> u64 {
+    let shift = (index / SUB_BUCKETS).saturating_sub(1);
+    let low = bucket_low(index);
+    low.saturating_add((1u64 << shift) - 1)
+}
+
+/// Distributions an endpoint recorded, returned by `histograms()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameHistograms {
+    /// Encoded size of each data frame, header included, in bytes
+    pub frame_bytes: Histogram,
+    /// Writer side: time to encode a frame, in nanoseconds
+    pub encode_ns: Histogram,
+    /// Reader side: time to decode a frame, in nanoseconds
+    pub decode_ns: Histogram,
+    /// Time between consecutive frames written or read, in nanoseconds
+    pub inter_arrival_ns: Histogram,
+}
+
+impl fmt::Display for FrameHistograms {
+    /// Quantiles per distribution; `{:#}` adds a bar chart of each
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: [(&str, &Histogram, Unit); 4] = [
+            ("frame size", &self.frame_bytes, bytes),
+            ("encode", &self.encode_ns, nanos),
+            ("decode", &self.decode_ns, nanos),
+            ("inter-arrival", &self.inter_arrival_ns, nanos),
+        ];
+        for (name, histogram, unit) in rows {
+            writeln!(f, "{:<15} {}", format!("{}:", name), histogram.summary(unit))?;
+            if f.alternate() {
+                histogram.bars(f, unit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn bytes(value: u64) -> String {
+    match value {
+        0..1024 => format!("{}B", value),
+        1024..1_048_576 => format!("{:.1}KiB", value as f64 / 1024.0),
+        _ => format!("{:.1}MiB", value as f64 / 1_048_576.0),
+    }
+}
+
+fn nanos(value: u64) -> String {
+    format!("{:.1?}", Duration::from_nanos(value))
+}
+
+/// Endpoint side: the histograms and when the last frame went by
+#[derive(Default)]
+pub(crate) struct HistogramRecorder {
+    histograms: FrameHistograms,
+    last_frame: Option<Instant>,
+}
+
+impl HistogramRecorder {
+    /// A frame of `size` bytes that took `elapsed` to encode or decode, handled
+    /// at `now` on the arena's time source
+    pub(crate) fn record(&mut self, size: usize, elapsed: Duration, encoded: bool, now: Instant) {
+        self.histograms.frame_bytes.record(size as u64);
+        match encoded {
+            true => self.histograms.encode_ns.record(elapsed.as_nanos() as u64),
+            false => self.histograms.decode_ns.record(elapsed.as_nanos() as u64),
+        }
+        if let Some(last) = self.last_frame.replace(now) {
+            self.histograms.inter_arrival_ns.record((now - last).as_nanos() as u64);
+        }
+    }
+
+    pub(crate) fn histograms(&self) -> FrameHistograms {
+        self.histograms.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{Result, SeekFrom, SharedDataFrame};
+    use polars::df;
+
+    #[test]
+    fn test_quantiles_within_one_percent() {
+        let mut histogram = Histogram::new();
+        for value in 1..=100_000u64 {
+            histogram.record(value);
+        }
+        for (q, exact) in [(0.5, 50_000.0), (0.99, 99_000.0), (0.999, 99_900.0)] {
+            let value = histogram.quantile(q).unwrap() as f64;
+            assert!((value - exact).abs() / exact < 0.01, "q{} = {}", q, value);
+        }
+        assert_eq!((histogram.min(), histogram.max()), (Some(1), Some(100_000)));
+        assert_eq!(histogram.buckets().map(|(_, _, count)| count).sum::<u64>(), 100_000);
+
+        let mut small = Histogram::new();
+        small.record(7);
+        small.merge(&histogram);
+        assert_eq!((small.count(), small.quantile(0.0)), (100_001, Some(1)));
+        assert_eq!(Histogram::new().quantile(0.5), None);
+    }
+
+    #[test]
+    fn test_endpoints_record_histograms() -> Result<()> {
+        let config = test_config("histograms", 4).with_histograms();
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        reader.seek(SeekFrom::Latest)?;
+
+        let df = df! { "px" => [3500.0, 3501.0, 3502.0] }?;
+        for _ in 0..3 {
+            writer.write(&df)?;
+            reader.read(Some(100))?;
+        }
+        let written = writer.histograms().unwrap();
+        let read = reader.histograms().unwrap();
+        assert_eq!((written.encode_ns.count(), written.inter_arrival_ns.count()), (3, 2));
+        assert_eq!((read.decode_ns.count(), read.inter_arrival_ns.count()), (3, 2));
+        assert_eq!(written.frame_bytes, read.frame_bytes);
+        assert!(format!("{:#}", read).contains("frame size:"));
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+//! Rolling statistics snapshots kept on disk, so operators can look back at a
+//! channel's health after the fact ("was reader lag growing before 14:32?")
+//!
+//! A writer configured `with_stats_history` samples its segment every
+//! interval (a minute by default) on a background thread and keeps the last
+//! `capacity` snapshots in `<dir>/<segment>.stats`, a tab-separated file that
+//! is rewritten and renamed into place on every sample. Any process configured
+//! with the same directory can query it through `stats_history`.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::ops::RangeBounds;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::backend::{self, HeaderDump, RawArena, ReaderDump};
+use crate::{Clock, MemoryReport, QADataSwapError, Result};
+
+const HEADER: &str = "timestamp_us\tpublished\tconsumed\tmax_reader_lag\treaders\tused_buffers\tunacked\thigh_water_bytes";
+
+/// One sample of a channel's counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    pub timestamp: SystemTime,
+    /// Frames published since the segment was created
+    pub published: u64,
+    /// Frames consumed from the shared cursor
+    pub consumed: u64,
+    /// How far the slowest registered reader trails the writer, in frames
+    pub max_reader_lag: u64,
+    pub readers: u32,
+    pub used_buffers: u64,
+    /// Frames written but not yet acknowledged (or consumed, outside ack mode)
+    pub unacked: u64,
+    /// Largest frame ever written, in bytes
+    pub high_water_bytes: u64,
+}
+
+impl StatsSnapshot {
+    fn sample(raw: &dyn RawArena, time: &dyn Clock) -> Self {
+        let mut header = HeaderDump::default();
+        raw.dump_header(&mut header);
+        let mut report = MemoryReport::default();
+        raw.memory_report(&mut report);
+
+        let slowest = (0..backend::MAX_READERS)
+            .filter_map(|index| {
+                let mut dump = ReaderDump::default();
+                (raw.dump_reader(index, &mut dump) == 1 && dump.alive != 0).then_some(dump.cursor)
+            })
+            .min();
+        Self {
+            timestamp: time.now(),
+            published: header.publish_sequence,
+            consumed: header.read_sequence,
+            max_reader_lag: slowest.map_or(0, |cursor| header.publish_sequence.saturating_sub(cursor)),
+            readers: header.reader_count.max(0) as u32,
+            used_buffers: report.used_buffers,
+            unacked: raw.unacked(),
+            high_water_bytes: report.high_water_bytes,
+        }
+    }
+
+    fn to_line(self) -> String {
+        let timestamp_us = self.timestamp.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_micros() as u64);
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            timestamp_us, self.published, self.consumed, self.max_reader_lag, self.readers,
+            self.used_buffers, self.unacked, self.high_water_bytes
+        )
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let fields = line.split('\t').map(|field| field.parse::<u64>().ok()).collect::<Option<Vec<_>>>()?;
+        let [timestamp_us, published, consumed, max_reader_lag, readers, used_buffers, unacked, high_water_bytes] =
+            fields[..]
+        else {
+            return None;
+        };
+        Some(Self {
+            timestamp: UNIX_EPOCH + Duration::from_micros(timestamp_us),
+            published,
+            consumed,
+            max_reader_lag,
+            readers: readers as u32,
+            used_buffers,
+            unacked,
+            high_water_bytes,
+        })
+    }
+}
+
+/// Where and how often snapshots are kept, see `SharedMemoryConfig::with_stats_history`
+#[derive(Debug, Clone)]
+pub struct StatsHistory {
+    dir: PathBuf,
+    interval: Duration,
+    capacity: usize,
+}
+
+impl StatsHistory {
+    /// Keep a day of per-minute snapshots in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            interval: Duration::from_secs(60),
+            capacity: 24 * 60,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Snapshots kept before the oldest are dropped
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Snapshots of `segment` taken within `range`, oldest first
+    pub fn load(&self, segment: &str, range: impl RangeBounds<SystemTime>) -> Result<Vec<StatsSnapshot>> {
+        Ok(self.read(segment)?.into_iter().filter(|snapshot| range.contains(&snapshot.timestamp)).collect())
+    }
+
+    fn path(&self, segment: &str) -> PathBuf {
+        self.dir.join(format!("{}.stats", segment))
+    }
+
+    fn read(&self, segment: &str) -> Result<VecDeque<StatsSnapshot>> {
+        let text = match fs::read_to_string(self.path(segment)) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(VecDeque::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(text.lines().skip(1).filter_map(StatsSnapshot::parse).collect())
+    }
+
+    fn write(&self, segment: &str, snapshots: &VecDeque<StatsSnapshot>) -> io::Result<()> {
+        let path = self.path(segment);
+        let tmp = path.with_extension("stats.tmp");
+        let mut file = File::create(&tmp)?;
+        writeln!(file, "{}", HEADER)?;
+        for snapshot in snapshots {
+            writeln!(file, "{}", snapshot.to_line())?;
+        }
+        fs::rename(&tmp, &path)
+    }
+}
+
+/// Sampling thread, stopped on drop
+pub(crate) struct StatsRecorder {
+    stop: Arc<AtomicBool>,
+    sampler: Option<JoinHandle<()>>,
+}
+
+impl StatsRecorder {
+    /// Sample `raw` right away and then every interval, continuing the file's
+    /// history; snapshots are timestamped on `time`
+    pub(crate) fn spawn(raw: Arc<dyn RawArena>, segment: String, history: StatsHistory, time: Arc<dyn Clock>)
+                        -> Result<Self> {
+        fs::create_dir_all(&history.dir)?;
+        if segment.contains(['/', '\\']) {
+            return Err(QADataSwapError::SharedMemory(format!("Invalid segment name for stats history: {}", segment)));
+        }
+        let snapshots = history.read(&segment)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let sampler = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("qads-stats".to_string())
+                .spawn(move || record(&*raw, &*time, &segment, &history, snapshots, &stop))?
+        };
+        Ok(Self {
+            stop,
+            sampler: Some(sampler),
+        })
+    }
+}
+
+fn record(raw: &dyn RawArena, time: &dyn Clock, segment: &str, history: &StatsHistory,
+          mut snapshots: VecDeque<StatsSnapshot>, stop: &AtomicBool) {
+    while !stop.load(Ordering::Acquire) {
+        snapshots.push_back(StatsSnapshot::sample(raw, time));
+        while snapshots.len() > history.capacity {
+            snapshots.pop_front();
+        }
+        // Best effort: a full disk costs history, not the channel
+        if let Err(e) = history.write(segment, &snapshots) {
+            log::warn!("Failed to write stats history for {}: {}", segment, e);
+        }
+        thread::park_timeout(history.interval);
+    }
+}
+
+impl Drop for StatsRecorder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(sampler) = self.sampler.take() {
+            sampler.thread().unpark();
+            let _ = sampler.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use polars::prelude::*;
+
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+
+    #[test]
+    fn test_snapshots_survive_the_writer() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let history = StatsHistory::new(dir.path()).with_interval(Duration::from_millis(20)).with_capacity(3);
+        let config = test_config("stats_history", 8).with_stats_history(history.clone());
+        let started = SystemTime::now();
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        for px in 0..4 {
+            writer.write(&df! { "px" => [px as f64] }?)?;
+        }
+        reader.read(Some(100))?;
+        thread::sleep(Duration::from_millis(120));
+        drop(writer);
+
+        let snapshots = reader.stats_history(started..)?;
+        assert_eq!(snapshots.len(), 3);
+        let last = snapshots.last().unwrap();
+        assert_eq!((last.published, last.consumed, last.max_reader_lag), (4, 1, 3));
+        assert!(snapshots.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+        assert!(history.load("stats_history", ..started)?.is_empty());
+        Ok(())
+    }
+}
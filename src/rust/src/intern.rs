@@ -0,0 +1,274 @@
+//! String interning: repeated values of chosen string columns travel as u32
+//! codes into a dictionary the writer grows across frames
+//!
+//! A frame with `FLAG_STRING_DICTIONARY` starts its payload with a block
+//! naming the coded columns and the entries added since the previous frame;
+//! the Arrow IPC file follows. Readers keep the dictionary per ring and need
+//! no configuration. Every `keyframe_interval` frames the writer resends the
+//! whole dictionary, so a reader that attached late or was lapped resyncs;
+//! until then it skips the interned frames it cannot resolve. Readers sharing
+//! a cursor with others see only some frames and so resolve few besides
+//! keyframes; interning suits channels with one reader per cursor.
+//!
+//! ```text
+//! u32 block_len | u64 epoch | u32 start | u32 count | count x (u32 len, UTF-8)
+//! u16 columns | columns x (u16 len, UTF-8 name)
+//! ```
+//!
+//! `start` 0 replaces the reader's dictionary; otherwise the entries append
+//! to a dictionary of `start` entries from the same `epoch`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use polars::prelude::*;
+
+use crate::{QADataSwapError, Result};
+
+/// Interning settings, see `SharedMemoryConfig::with_string_interning`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringInterning {
+    pub columns: Vec<String>,
+    /// Frames between full dictionary resends
+    pub keyframe_interval: usize,
+    /// Start a fresh dictionary once it holds this many entries
+    pub max_entries: usize,
+}
+
+impl StringInterning {
+    pub fn new(columns: &[&str]) -> Self {
+        Self {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            keyframe_interval: 64,
+            max_entries: 1 << 20,
+        }
+    }
+
+    pub fn with_keyframe_interval(mut self, frames: usize) -> Self {
+        self.keyframe_interval = frames.max(1);
+        self
+    }
+
+    pub fn with_max_entries(mut self, entries: usize) -> Self {
+        self.max_entries = entries.max(1);
+        self
+    }
+}
+
+/// Writer side: the dictionary and what readers have been sent of it
+#[derive(Debug)]
+pub(crate) struct Interner {
+    settings: StringInterning,
+    epoch: u64,
+    values: Vec<String>,
+    codes: HashMap<String, u32>,
+    /// Entries published so far, `None` until the next keyframe
+    published: Option<usize>,
+    since_keyframe: usize,
+}
+
+impl Interner {
+    pub(crate) fn new(settings: StringInterning) -> Self {
+        Self {
+            settings,
+            epoch: new_epoch(),
+            values: Vec::new(),
+            codes: HashMap::new(),
+            published: None,
+            since_keyframe: 0,
+        }
+    }
+
+    /// `df` with the interned columns replaced by codes, and the block to
+    /// prefix its payload with
+    pub(crate) fn intern(&mut self, df: &DataFrame) -> Result<(DataFrame, Vec<u8>)> {
+        if self.values.len() >= self.settings.max_entries {
+            *self = Self::new(self.settings.clone());
+        }
+        if self.since_keyframe >= self.settings.keyframe_interval {
+            self.published = None;
+        }
+
+        let names = self.settings.columns.clone();
+        let mut coded = df.clone();
+        let mut columns = Vec::new();
+        for name in &names {
+            let Ok(column) = df.column(name) else {
+                continue;
+            };
+            let Ok(strings) = column.str() else {
+                continue;
+            };
+            let codes: Vec<Option<u32>> = strings.iter().map(|value| value.map(|value| self.code(value))).collect();
+            coded.with_column(Column::new(name.as_str().into(), codes))?;
+            columns.push(name.as_str());
+        }
+
+        let start = self.published.unwrap_or(0);
+        let mut block = vec![0u8; 4];
+        block.extend_from_slice(&self.epoch.to_le_bytes());
+        block.extend_from_slice(&(start as u32).to_le_bytes());
+        block.extend_from_slice(&((self.values.len() - start) as u32).to_le_bytes());
+        for value in &self.values[start..] {
+            block.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            block.extend_from_slice(value.as_bytes());
+        }
+        block.extend_from_slice(&(columns.len() as u16).to_le_bytes());
+        for name in columns {
+            block.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            block.extend_from_slice(name.as_bytes());
+        }
+        let block_len = (block.len() - 4) as u32;
+        block[..4].copy_from_slice(&block_len.to_le_bytes());
+
+        self.since_keyframe = if start == 0 { 0 } else { self.since_keyframe + 1 };
+        self.published = Some(self.values.len());
+        Ok((coded, block))
+    }
+
+    /// The last block never reached the ring; resend everything next frame
+    pub(crate) fn resync(&mut self) {
+        self.published = None;
+    }
+
+    fn code(&mut self, value: &str) -> u32 {
+        if let Some(code) = self.codes.get(value) {
+            return *code;
+        }
+        let code = self.values.len() as u32;
+        self.values.push(value.to_string());
+        self.codes.insert(value.to_string(), code);
+        code
+    }
+}
+
+/// Reader side copy of one ring's dictionary
+#[derive(Debug, Default)]
+pub(crate) struct Dictionary {
+    epoch: u64,
+    values: Vec<String>,
+    /// Whether a keyframe has been seen since the last gap
+    synced: bool,
+}
+
+impl Dictionary {
+    /// Apply the block and restore the coded columns of `df`; `None` when the
+    /// dictionary is missing entries from frames this reader never saw
+    pub(crate) fn restore(&mut self, block: &[u8], df: DataFrame) -> Result<Option<DataFrame>> {
+        let mut block = block;
+        let mut take = |len: usize| -> Result<&[u8]> {
+            if block.len() < len {
+                return Err(corrupt("truncated string dictionary"));
+            }
+            let (head, rest) = block.split_at(len);
+            block = rest;
+            Ok(head)
+        };
+
+        let epoch = u64::from_le_bytes(take(8)?.try_into().unwrap());
+        let start = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+        let count = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+        // Not reserved up front: a corrupt count would reserve gigabytes
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+            entries.push(String::from_utf8(take(len)?.to_vec()).map_err(|_| corrupt("entry is not UTF-8"))?);
+        }
+        let column_count = u16::from_le_bytes(take(2)?.try_into().unwrap());
+        let mut columns = Vec::with_capacity(column_count as usize);
+        for _ in 0..column_count {
+            let len = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+            columns.push(String::from_utf8(take(len)?.to_vec()).map_err(|_| corrupt("column name is not UTF-8"))?);
+        }
+
+        if start == 0 {
+            self.epoch = epoch;
+            self.values = entries;
+            self.synced = true;
+        } else if self.synced && epoch == self.epoch && start == self.values.len() {
+            self.values.extend(entries);
+        } else {
+            self.synced = false;
+            return Ok(None);
+        }
+
+        let mut df = df;
+        for name in columns {
+            let codes = df.column(&name)?.u32()?;
+            let mut strings = Vec::with_capacity(codes.len());
+            for code in codes.iter() {
+                strings.push(match code {
+                    Some(code) => Some(self.values.get(code as usize).ok_or_else(|| corrupt("code out of range"))?.as_str()),
+                    None => None,
+                });
+            }
+            let column = Column::new(name.as_str().into(), strings);
+            df.with_column(column)?;
+        }
+        Ok(Some(df))
+    }
+}
+
+/// Split an interned frame's payload into its dictionary block and IPC payload
+pub(crate) fn split(payload: &[u8]) -> Result<(&[u8], &[u8])> {
+    let len = payload
+        .get(..4)
+        .map(|len| u32::from_le_bytes(len.try_into().unwrap()) as usize)
+        .filter(|len| payload.len() >= 4 + len)
+        .ok_or_else(|| corrupt("truncated string dictionary"))?;
+    Ok((&payload[4..4 + len], &payload[4 + len..]))
+}
+
+/// Distinguishes dictionaries of different writers and resets. Random rather
+/// than a timestamp, so writers sharing a mock clock still differ.
+fn new_epoch() -> u64 {
+    static RESETS: AtomicU64 = AtomicU64::new(1);
+    let mut epoch = [0u8; 8];
+    match getrandom::getrandom(&mut epoch) {
+        Ok(()) => u64::from_le_bytes(epoch),
+        Err(_) => ((std::process::id() as u64) << 32) | RESETS.fetch_add(1, Ordering::Relaxed),
+    }
+}
+
+fn corrupt(detail: &str) -> QADataSwapError {
+    QADataSwapError::SharedMemory(format!("Corrupt interned frame: {}", detail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{SeekFrom, SharedDataFrame};
+    use polars::df;
+
+    #[test]
+    fn test_interned_strings_round_trip_and_resync() -> Result<()> {
+        let interning = StringInterning::new(&["symbol", "exchange"]).with_keyframe_interval(3);
+        let writer = SharedDataFrame::create_writer(test_config("interned_ticks", 4).with_string_interning(interning))?;
+        let reader = SharedDataFrame::create_reader(test_config("interned_ticks", 4))?;
+
+        let ticks = df! {
+            "symbol" => [Some("rb2410"), Some("cu2409"), None, Some("rb2410")],
+            "exchange" => ["SHFE", "SHFE", "SHFE", "SHFE"],
+            "px" => [3500.0, 78000.0, 0.0, 3501.0],
+        }?;
+        writer.write(&ticks)?;
+        assert_eq!(reader.read(Some(100))?, Some(ticks.clone()));
+        writer.write(&ticks.head(Some(2)))?;
+        assert_eq!(reader.read(Some(100))?, Some(ticks.head(Some(2))));
+
+        // A reader with its own cursor joins mid-stream and resyncs at the next keyframe
+        let late = SharedDataFrame::create_reader(test_config("interned_ticks", 4))?;
+        late.seek(SeekFrom::Latest)?;
+        let fresh = df! { "symbol" => ["au2412"], "exchange" => ["SHFE"], "px" => [560.0] }?;
+        for _ in 0..3 {
+            writer.write(&fresh)?;
+        }
+        assert_eq!(late.read(Some(100))?, Some(fresh.clone()));
+        assert_eq!(late.read_stats().unresolved, 2);
+        for _ in 0..3 {
+            assert_eq!(reader.read(Some(100))?, Some(fresh.clone()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+//! Arrow IPC (Feather v2) files shared by reference, see `publish_ipc_file`
+//!
+//! The frame payload names the file instead of carrying it: the file's length
+//! and modification time (so a reader notices the file being replaced under
+//! it), then its absolute UTF-8 path. Readers memory-map the file, so a large
+//! static reference dataset costs each process page cache rather than a copy
+//! through the ring. Only uncompressed files map in place; compressed ones are
+//! decoded as usual.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use memmap2::Mmap;
+use polars::prelude::*;
+
+use crate::{QADataSwapError, Result};
+
+const ARROW_MAGIC: &[u8] = b"ARROW1";
+const FIXED_SIZE: usize = 16;
+
+/// A file a `FileRef` frame names, and the state it had when published
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FileRef {
+    path: PathBuf,
+    len: u64,
+    modified_ns: u64,
+}
+
+impl FileRef {
+    /// Check `path` is an Arrow IPC file; returns the reference and the file's schema
+    pub(crate) fn open(path: &Path) -> Result<(Self, SchemaRef)> {
+        let path = fs::canonicalize(path)?;
+        if path.to_str().is_none() {
+            return Err(QADataSwapError::SharedMemory(format!("IPC file path is not UTF-8: {}", path.display())));
+        }
+        let file = File::open(&path)?;
+        // SAFETY: only the magic bytes are read, and the map is dropped before returning
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < 2 * ARROW_MAGIC.len() || !map.starts_with(ARROW_MAGIC) || !map.ends_with(ARROW_MAGIC) {
+            return Err(QADataSwapError::SharedMemory(format!("Not an Arrow IPC file: {}", path.display())));
+        }
+        drop(map);
+        let (len, modified_ns) = stat(&file)?;
+        let schema = IpcReader::new(file).schema().map_err(QADataSwapError::Polars)?;
+        let schema = Arc::new(Schema::from_arrow_schema(&schema));
+        Ok((Self { path, len, modified_ns }, schema))
+    }
+
+    pub(crate) fn encode_into(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.len.to_le_bytes());
+        buffer.extend_from_slice(&self.modified_ns.to_le_bytes());
+        buffer.extend_from_slice(self.path.to_str().unwrap_or_default().as_bytes());
+    }
+
+    pub(crate) fn decode(payload: &[u8]) -> Result<Self> {
+        let truncated = || QADataSwapError::SharedMemory("Truncated IPC file reference".to_string());
+        let (fixed, path) = payload.split_at_checked(FIXED_SIZE).ok_or_else(truncated)?;
+        let path = std::str::from_utf8(path).map_err(|_| truncated())?;
+        Ok(Self {
+            path: PathBuf::from(path),
+            len: u64::from_le_bytes(fixed[..8].try_into().unwrap()),
+            modified_ns: u64::from_le_bytes(fixed[8..].try_into().unwrap()),
+        })
+    }
+
+    /// The file, memory-mapped; fails if it changed since it was published
+    pub(crate) fn read(&self) -> Result<DataFrame> {
+        let file = File::open(&self.path)?;
+        if stat(&file)? != (self.len, self.modified_ns) {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "IPC file {} changed after it was published",
+                self.path.display()
+            )));
+        }
+        IpcReader::new(file)
+            .memory_mapped(Some(self.path.clone()))
+            .finish()
+            .map_err(QADataSwapError::Polars)
+    }
+}
+
+fn stat(file: &File) -> Result<(u64, u64)> {
+    let metadata = file.metadata()?;
+    let modified_ns = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos() as u64);
+    Ok((metadata.len(), modified_ns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+
+    fn write_ipc(path: &Path, df: &DataFrame) -> Result<()> {
+        IpcWriter::new(File::create(path)?).finish(&mut df.clone())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_publish_ipc_file_by_reference() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("instruments.arrow");
+        let instruments = df! { "symbol" => ["IF2412", "IC2412"], "multiplier" => [300i64, 200] }?;
+        write_ipc(&path, &instruments)?;
+
+        let config = test_config("ipc_file_ref", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        writer.publish_ipc_file(&path)?;
+        assert_eq!(reader.read(Some(100))?, Some(instruments));
+
+        // Only the reference travels, so replacing the file is caught on read
+        writer.publish_ipc_file(&path)?;
+        write_ipc(&path, &df! { "symbol" => ["IF2501", "IC2501", "IH2501"] }?)?;
+        assert!(reader.read(Some(100)).is_err());
+
+        fs::write(dir.path().join("notes.txt"), "not arrow")?;
+        assert!(writer.publish_ipc_file(dir.path().join("notes.txt")).is_err());
+        Ok(())
+    }
+}
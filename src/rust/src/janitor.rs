@@ -0,0 +1,260 @@
+//! Background cleanup of segments left behind by crashed writers
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::backend::{self, Transport};
+use crate::{Clock, ClockSource, SystemClock, NAMESPACE_SEPARATOR};
+
+/// When and how the janitor treats a segment as orphaned
+#[derive(Debug, Clone)]
+pub struct JanitorPolicy {
+    /// Pause between sweeps
+    pub interval: Duration,
+    /// Segments younger than this are never touched, so a writer still setting
+    /// one up is not mistaken for a dead one
+    pub min_age: Duration,
+    /// Report orphans without unlinking them
+    pub dry_run: bool,
+    /// Which transport's segments to scan
+    pub transport: Transport,
+    /// Only consider segment names starting with this
+    pub prefix: Option<String>,
+    /// Clock segment ages are measured on
+    pub time_source: Arc<dyn Clock>,
+}
+
+impl PartialEq for JanitorPolicy {
+    fn eq(&self, other: &Self) -> bool {
+        (self.interval, self.min_age, self.dry_run, self.transport, &self.prefix)
+            == (other.interval, other.min_age, other.dry_run, other.transport, &other.prefix)
+            && Arc::ptr_eq(&self.time_source, &other.time_source)
+    }
+}
+
+impl Eq for JanitorPolicy {}
+
+impl Default for JanitorPolicy {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            min_age: Duration::from_secs(30),
+            dry_run: false,
+            transport: Transport::default(),
+            prefix: None,
+            time_source: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl JanitorPolicy {
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_min_age(mut self, min_age: Duration) -> Self {
+        self.min_age = min_age;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Measure segment ages on `time` instead of the system clock, matching the
+    /// writers' `with_time_source` for in-process segments
+    pub fn with_time_source(mut self, time: Arc<dyn Clock>) -> Self {
+        self.time_source = time;
+        self
+    }
+
+    /// Only sweep segments in `namespace` (see `SharedMemoryConfig::with_namespace`)
+    pub fn with_namespace(self, namespace: &str) -> Self {
+        self.with_prefix(format!("{}{}", namespace, NAMESPACE_SEPARATOR))
+    }
+}
+
+/// A segment whose writer is gone and which no reader is attached to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Orphan {
+    pub name: String,
+    pub bytes: u64,
+    /// Pid recorded by the last writer (0 if it detached)
+    pub writer_pid: i32,
+    /// False in dry-run mode
+    pub removed: bool,
+}
+
+/// Scan once and unlink (or, in dry-run mode, just report) every orphaned segment
+pub fn sweep(policy: &JanitorPolicy) -> Vec<Orphan> {
+    let now = policy.time_source.now_ns(ClockSource::Realtime) / 1000;
+    let min_age_us = policy.min_age.as_micros() as u64;
+
+    backend::list_segments(policy.transport)
+        .into_iter()
+        .filter(|name| policy.prefix.as_deref().is_none_or(|prefix| name.starts_with(prefix)))
+        .filter_map(|name| {
+            let status = backend::inspect_segment(policy.transport, &name)?;
+            let orphaned = status.writer_alive == 0
+                && status.reader_count <= 0
+                && now.saturating_sub(status.created_at_us) >= min_age_us;
+            if !orphaned {
+                return None;
+            }
+
+            if !policy.dry_run {
+                backend::remove_segment(policy.transport, &name);
+            }
+            Some(Orphan {
+                name,
+                bytes: status.total_bytes,
+                writer_pid: status.writer_pid,
+                removed: !policy.dry_run,
+            })
+        })
+        .collect()
+}
+
+/// Sweep every `policy.interval` on a background thread until the handle is dropped
+pub fn spawn(policy: JanitorPolicy) -> std::io::Result<Janitor> {
+    let shared = Arc::new(Shared::default());
+    let thread = {
+        let shared = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("qads-janitor".to_string())
+            .spawn(move || run(&policy, &shared))?
+    };
+
+    Ok(Janitor {
+        shared,
+        thread: Some(thread),
+    })
+}
+
+#[derive(Default)]
+struct Shared {
+    stopped: Mutex<bool>,
+    wake: Condvar,
+    found: Mutex<Vec<Orphan>>,
+}
+
+fn run(policy: &JanitorPolicy, shared: &Shared) {
+    loop {
+        let orphans = sweep(policy);
+        let mut found = shared.found.lock().unwrap();
+        for orphan in orphans {
+            // Dry runs report the same orphans every sweep; keep one entry per segment
+            match found.iter_mut().find(|known| !known.removed && known.name == orphan.name) {
+                Some(known) => *known = orphan,
+                None => found.push(orphan),
+            }
+        }
+        drop(found);
+
+        let stopped = shared.stopped.lock().unwrap();
+        let (stopped, _) = shared
+            .wake
+            .wait_timeout_while(stopped, policy.interval, |stopped| !*stopped)
+            .unwrap();
+        if *stopped {
+            return;
+        }
+    }
+}
+
+/// Handle to a running janitor; dropping it stops the thread
+pub struct Janitor {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Janitor {
+    /// Every orphan found since the janitor started, oldest first; a segment a
+    /// dry run keeps finding is listed once
+    pub fn orphans(&self) -> Vec<Orphan> {
+        self.shared.found.lock().unwrap().clone()
+    }
+
+    /// Stop sweeping and wait for the thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        *self.shared.stopped.lock().unwrap() = true;
+        self.shared.wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Janitor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use polars::prelude::*;
+
+    use super::*;
+    use crate::testing::{test_config, FaultInjector};
+    use crate::SharedDataFrame;
+
+    fn policy() -> JanitorPolicy {
+        JanitorPolicy::default()
+            .with_min_age(Duration::ZERO)
+            .with_transport(Transport::InProcess)
+            .with_prefix("janitor_")
+    }
+
+    fn orphan_named<'a>(orphans: &'a [Orphan], name: &str) -> Option<&'a Orphan> {
+        orphans.iter().find(|o| o.name == name)
+    }
+
+    #[test]
+    fn test_sweep_removes_only_orphans() -> crate::Result<()> {
+        let config = |name: &str| test_config(name, 3);
+
+        // A writer that crashes leaves its segment registered
+        let injector = FaultInjector::new().crash_writer_at(0);
+        let crashed = SharedDataFrame::create_writer(config("janitor_crashed").with_fault_injector(injector))?;
+        assert!(crashed.write(&df! { "id" => [1i64] }?).is_err());
+        let _live = SharedDataFrame::create_writer(config("janitor_live"))?;
+
+        let found = sweep(&policy().with_dry_run(true));
+        assert!(!orphan_named(&found, "janitor_crashed").unwrap().removed);
+        assert!(orphan_named(&found, "janitor_live").is_none());
+
+        let dry_run = spawn(policy().with_dry_run(true).with_interval(Duration::from_millis(10)))?;
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(dry_run.orphans().iter().filter(|o| o.name == "janitor_crashed").count(), 1);
+        dry_run.stop();
+
+        let janitor = spawn(policy().with_interval(Duration::from_millis(10)))?;
+        thread::sleep(Duration::from_millis(50));
+        assert!(orphan_named(&janitor.orphans(), "janitor_crashed").unwrap().removed);
+        janitor.stop();
+
+        assert!(SharedDataFrame::create_reader(config("janitor_crashed")).is_err());
+        assert!(SharedDataFrame::create_reader(config("janitor_live")).is_ok());
+        Ok(())
+    }
+}
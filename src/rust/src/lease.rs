@@ -0,0 +1,170 @@
+//! Raw access to a ring slot for custom serializers, see `lease_buffer`
+//!
+//! A lease reserves the next slot and lends out its payload area, so a writer
+//! can serialize its own format straight into shared memory. `publish(len)`
+//! puts a `Coded` frame header in front of the first `len` bytes: readers
+//! decode them with the channel's codec, so the format needs a `Codec` on the
+//! reading side (`with_codec` or `register_codec`). The crate still handles
+//! slot ownership, canaries, rate limits and write statistics, but never looks
+//! at the bytes: validators, masking, dedup and splitting do not apply.
+
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use crate::frame::{FrameHeader, FrameKind, FRAME_HEADER_SIZE};
+use crate::{QADataSwapError, Result, SharedMemoryArena};
+
+/// The payload area of the next ring slot, borrowed until `publish` or `abort`.
+/// It still holds whatever the slot carried before. Like a `FrameBuilder`, a
+/// lease holds the writer's slot: finish it before writing anything else.
+/// Dropping it unpublished releases the slot.
+pub struct BufferLease<'a> {
+    arena: &'a SharedMemoryArena,
+    slot: *mut u8,
+    capacity: usize,
+    acquired: Instant,
+    finished: bool,
+}
+
+impl<'a> BufferLease<'a> {
+    pub(crate) fn new(arena: &'a SharedMemoryArena, min_bytes: usize) -> Result<Self> {
+        let (slot, capacity) = arena.acquire_write_buffer()?;
+        let capacity = capacity.saturating_sub(FRAME_HEADER_SIZE);
+        if capacity < min_bytes {
+            arena.release_acquired();
+            return Err(QADataSwapError::LimitExceeded(format!(
+                "Lease of {} bytes requested, slots hold {}", min_bytes, capacity
+            )));
+        }
+
+        Ok(Self {
+            arena,
+            slot,
+            capacity,
+            acquired: Instant::now(),
+            finished: false,
+        })
+    }
+
+    /// Make the first `len` bytes visible to readers as one frame
+    pub fn publish(mut self, len: usize) -> Result<()> {
+        self.finished = true;
+        if len > self.capacity {
+            self.arena.release_acquired();
+            return Err(QADataSwapError::LimitExceeded(format!(
+                "Published {} bytes from a lease of {}", len, self.capacity
+            )));
+        }
+
+        let header = FrameHeader::new_with_clock(FrameKind::Coded, self.arena.writer_id(), len, self.arena.config.clock)
+            .stamped_by(&*self.arena.config.time_source);
+        unsafe {
+            ptr::copy_nonoverlapping(header.to_bytes().as_ptr(), self.slot, FRAME_HEADER_SIZE);
+        }
+        let size = FRAME_HEADER_SIZE + len;
+        if let Some(pacer) = self.arena.pacer.lock().unwrap().as_mut() {
+            pacer.acquire(size);
+        }
+        self.arena.publish_acquired(size)?;
+
+        self.arena.write_counters.frames.fetch_add(1, Ordering::Relaxed);
+        if let Some(histograms) = &self.arena.histograms {
+            // Time spent filling the lease stands in for encode time
+            let now = self.arena.config.time_source.instant();
+            histograms.lock().unwrap().record(size, self.acquired.elapsed(), true, now);
+        }
+        Ok(())
+    }
+
+    /// Release the slot without publishing
+    pub fn abort(mut self) {
+        self.finished = true;
+        self.arena.release_acquired();
+    }
+}
+
+impl Deref for BufferLease<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.slot.add(FRAME_HEADER_SIZE), self.capacity) }
+    }
+}
+
+impl DerefMut for BufferLease<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.slot.add(FRAME_HEADER_SIZE), self.capacity) }
+    }
+}
+
+impl Drop for BufferLease<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.arena.release_acquired();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use polars::prelude::*;
+
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{Codec, SharedDataFrame};
+
+    /// Prices as bare little-endian f64s
+    #[derive(Debug)]
+    struct RawPrices;
+
+    impl Codec for RawPrices {
+        fn name(&self) -> &str {
+            "raw-prices"
+        }
+
+        fn encode(&self, df: &DataFrame, out: &mut Vec<u8>) -> Result<()> {
+            for px in df.column("px")?.f64()?.into_no_null_iter() {
+                out.extend_from_slice(&px.to_le_bytes());
+            }
+            Ok(())
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<DataFrame> {
+            let px: Vec<f64> = bytes.chunks_exact(8).map(|px| f64::from_le_bytes(px.try_into().unwrap())).collect();
+            Ok(df! { "px" => px }?)
+        }
+    }
+
+    #[test]
+    fn test_lease_publish_and_abort() -> Result<()> {
+        let config = test_config("buffer_lease", 4);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config.with_codec(Arc::new(RawPrices)))?;
+
+        let mut lease = writer.lease_buffer(64)?;
+        assert!(lease.len() >= 64);
+        for (i, px) in [1.5f64, 2.5, 4.0].iter().enumerate() {
+            lease[i * 8..(i + 1) * 8].copy_from_slice(&px.to_le_bytes());
+        }
+        lease.publish(24)?;
+        assert_eq!(writer.write_stats().frames, 1);
+
+        // Aborted and dropped leases hand the slot back unpublished
+        writer.lease_buffer(8)?.abort();
+        drop(writer.lease_buffer(8)?);
+        let lease = writer.lease_buffer(8)?;
+        let capacity = lease.len();
+        assert!(lease.publish(capacity + 1).is_err());
+        assert!(writer.lease_buffer(1 << 20).is_err());
+
+        let df = reader.read(Some(100))?.unwrap();
+        assert_eq!(df.column("px")?.f64()?.into_no_null_iter().collect::<Vec<_>>(), [1.5, 2.5, 4.0]);
+        assert!(matches!(reader.read(Some(0)), Err(QADataSwapError::Timeout)));
+        assert!(reader.lease_buffer(8).is_err());
+        Ok(())
+    }
+}
@@ -1,9 +1,158 @@
-use std::ffi::CString;
-use std::os::raw::{c_char, c_int, c_void};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::ops::RangeBounds;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use polars::prelude::*;
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
+// Lets `#[derive(SwapMessage)]` output, which names `::qadataswap`, compile in this crate
+extern crate self as qadataswap;
+
+mod adaptive;
+mod archive;
+mod auth;
+mod backend;
+mod builder;
+pub mod channels;
+mod clock;
+mod codec;
+#[cfg(feature = "grpc")]
+pub mod control;
+mod cursor;
+mod decode_cache;
+mod dispatch;
+mod dlq;
+mod dump;
+mod duplex;
+pub mod events;
+mod fixed;
+mod frame;
+mod handover;
+mod histogram;
+mod history;
+mod ipc_file;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+mod intern;
+mod lease;
+pub mod janitor;
+mod limits;
+mod lineage;
+mod loopback;
+mod masking;
+pub mod market;
+pub mod message;
+mod merge;
+mod parallel;
+mod partition;
+mod policy;
+mod preset;
+mod priority;
+mod probe;
+mod quota;
+mod rate;
+#[cfg(all(qads_ffi, feature = "rdma"))]
+mod rdma;
+mod replay;
+mod routing;
+#[cfg(target_os = "linux")]
+mod readiness;
+pub mod relay;
+mod schema;
+#[cfg(feature = "serde")]
+pub mod serde_rows;
+#[cfg(qads_ffi)]
+mod shm;
+mod split;
+#[cfg(feature = "datafusion")]
+mod sql;
+mod stripe;
+pub mod supervisor;
+#[cfg(feature = "tap")]
+mod tap;
+pub mod testing;
+mod tensor;
+mod threads;
+pub mod validators;
+mod wait;
+mod watermark;
+mod window;
+mod zdict;
+
+pub use adaptive::{AdaptiveCompression, AdaptiveSizing};
+pub use archive::{ArchivePolicy, ArchivedFile, ParquetArchiver, ARCHIVE_MANIFEST};
+pub use auth::CapabilityToken;
+use adaptive::{CompressionGovernor, FrameSizeTracker};
+pub use backend::Transport;
+use backend::RawArena;
+pub use builder::{FrameBuilder, FrameNative};
+pub use lease::BufferLease;
+pub use clock::{Clock, ClockSource, MockClock, SystemClock, TSC_CALIBRATION_PATH};
+pub use codec::{register_codec, ArrowIpcCodec, ArrowStreamCodec, Codec, ParquetCodec, CODEC_META_KEY};
+#[cfg(feature = "serde")]
+pub use codec::JsonLinesCodec;
+#[cfg(feature = "msgpack")]
+pub use codec::MsgPackCodec;
+pub use cursor::CursorStore;
+pub use dispatch::Dispatcher;
+pub use dump::{ArenaDump, GroupDump, SlotDump, SlotOwner};
+pub use duplex::{DuplexChannel, DUPLEX_BUFFER_COUNT, DUPLEX_SIZE_MB};
+pub use dlq::{dead_letter_name, DeadLetter, DeadLetterStage, DEAD_LETTER_SUFFIX};
+use dlq::{DeadLetterQueue, Rejected};
+use zdict::PayloadCompression;
+use events::EventLog;
+use histogram::HistogramRecorder;
+use ipc_file::FileRef;
+use routing::KeyFilter;
+use split::Part;
+pub use intern::StringInterning;
+pub use fixed::{FixedLayout, FixedRecords, FixedRecordsMut};
+pub use frame::{Event, FrameHeader, FrameKind, FrameMetadata, FRAME_HEADER_SIZE};
+pub use handover::HANDOVER_META_KEY;
+pub use histogram::{FrameHistograms, Histogram};
+pub use history::{StatsHistory, StatsSnapshot};
+pub use limits::ResourceLimits;
+pub use lineage::{Lineage, LineageHop};
+pub use masking::{ColumnKey, ColumnPolicy};
+pub use merge::{MergeEvent, MergeReader};
+pub use partition::{partition_for, PartitionedReader, PartitionedWriter, PARTITION_META_KEY};
+pub use message::SwapMessage;
+pub use policy::{PolicyUpdate, POLICY_META_KEY, POLICY_POLL_INTERVAL};
+pub use preset::Preset;
+pub use priority::Priority;
+pub use probe::{LatencyProbe, LatencyReport, LatencyStats};
+pub use quota::{ChannelQuotas, QuotaPolicy};
+pub use rate::RateLimit;
+pub use replay::{ReplayClock, Replayer};
+pub use routing::{RoutingKey, MAX_ROUTING_KEY_LEN};
+use priority::LaneScheduler;
+pub use schema::ReadPolicy;
+#[cfg(feature = "datafusion")]
+pub use sql::{SharedArenaTable, TableSnapshot};
+pub use stripe::{StripedReader, StripedWriter};
+#[cfg(feature = "tap")]
+pub use tap::{TapFormat, WebSocketTap};
+pub use tensor::{SharedTensor, Tensor, TensorDType, TensorElement, TENSOR_HEADER_SIZE};
+pub use threads::{Prefetcher, ReaderThreadOptions, Subscription};
+use validators::{ValidationStats, Validators, Verdict};
+pub use wait::{AdaptiveWait, MarketHours, WaitStrategy};
+pub use watermark::{Watermark, WatermarkHook, WatermarkLevel};
+pub use window::{WindowAggregator, WindowedStream};
+pub use zdict::{MAX_DICTIONARY_SIZE, ZSTD_DICTIONARY_META_KEY};
+#[cfg(target_os = "linux")]
+use readiness::{ReadinessFd, ReadinessHost};
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, RawFd};
+#[cfg(target_os = "linux")]
+use std::sync::OnceLock;
+
 #[derive(Error, Debug)]
 pub enum QADataSwapError {
     #[error("Polars error: {0}")]
@@ -16,10 +165,204 @@ pub enum QADataSwapError {
     Timeout,
     #[error("Not connected")]
     NotConnected,
+    #[error("Resource limit exceeded: {0}")]
+    LimitExceeded(String),
+    #[error("Unauthorized")]
+    Unauthorized,
+    /// `create_writer` found the name taken; `owner_pid` is 0 when the segment
+    /// vanished before it could be inspected
+    #[error(
+        "Segment '{name}' already exists (writer pid {owner_pid}, created {}s after the epoch); \
+         use force_recreate() to replace it",
+        .created_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+    )]
+    AlreadyExists {
+        name: String,
+        owner_pid: i32,
+        created_at: SystemTime,
+    },
+    /// `attach_reader` found the writer's `with_max_readers` limit reached
+    #[error("Arena '{name}' already has its limit of {max_readers} readers attached; see readers() for who they are")]
+    TooManyReaders {
+        name: String,
+        max_readers: usize,
+    },
+    /// A guarded read faulted while copying a frame out of arena `.0`, see
+    /// `MemoryProtection::Guarded`; the frame was skipped
+    #[error("Memory fault reading a frame from arena '{0}'; the frame was skipped")]
+    MemoryFault(String),
+    /// A frame wrote past the end of its slot in arena `.0` and clobbered the
+    /// slot's canary, see `with_canaries`; the frame was dropped
+    #[error("Slot overrun in arena '{0}': a frame wrote past its slot's capacity and was dropped")]
+    Overrun(String),
+    /// A frame for routing key `channel` would take it past its byte quota or
+    /// its fair share of slots, see `with_channel_quotas`; nothing was published
+    #[error("Channel '{channel}' is over its quota ({held_bytes} bytes in {held_slots} slots unread)")]
+    QuotaExceeded {
+        channel: String,
+        held_bytes: u64,
+        held_slots: usize,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, QADataSwapError>;
 
+/// How a writer treats a segment that already exists under the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpenMode {
+    /// Create a fresh segment, replacing only stale leftovers whose writer is gone
+    #[default]
+    Create,
+    /// Reattach to an existing segment, preserving sequences and reader connections
+    OpenOrAttach,
+    /// Refuse if any segment exists under this name
+    FailIfExists,
+    /// Unconditionally unlink an existing segment and recreate it
+    Truncate,
+}
+
+/// Paging hint for the arena's mapping, see `with_madvise`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Advice {
+    /// Fault the whole arena in up front
+    Willneed,
+    Sequential,
+    Random,
+    /// Back the arena with transparent huge pages, where the kernel allows it for shmem
+    Hugepage,
+    NoHugepage,
+    /// Leave the arena out of core dumps
+    Dontdump,
+}
+
+/// Hardware protection of the ring, set with `with_memory_protection`. Applies
+/// to the shared memory transport; in-process arenas have nothing to protect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryProtection {
+    #[default]
+    Off,
+    /// Readers map the data region read-only and the writer write-protects
+    /// each published slot until it reuses it, so a stray write through a
+    /// zero-copy view crashes at the culprit instead of corrupting frames.
+    /// Protection is per page: pages a slot shares with a neighbour follow
+    /// whichever of the two was written last.
+    ReadOnly,
+    /// `ReadOnly`, and a fault while a read copies a frame out (a truncated or
+    /// remapped segment) fails that read with `MemoryFault` instead of killing
+    /// the process
+    Guarded,
+}
+
+/// Where a reader resumes consuming from after `seek()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekFrom {
+    /// Resume at an explicit sequence number (e.g. one persisted before a restart)
+    Sequence(u64),
+    /// Skip everything retained and wait for the next published frame
+    Latest,
+    /// Oldest frame still held in the ring
+    Earliest,
+}
+
+/// One reader attached to an arena, as listed by `readers()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReaderInfo {
+    pub pid: u32,
+    /// Next sequence the reader expects
+    pub cursor: u64,
+    pub attached_at: SystemTime,
+    /// False for a reader whose process went away without detaching; the next
+    /// attach reclaims its seat
+    pub alive: bool,
+}
+
+/// Slot usage snapshot returned by `memory_report()`, for sizing `size_mb`/`buffer_count`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub total_bytes: u64,
+    pub header_bytes: u64,
+    pub buffer_count: u64,
+    pub buffer_bytes: u64,
+    pub used_buffers: u64,
+    pub free_buffers: u64,
+    /// Largest frame ever written
+    pub high_water_bytes: u64,
+    /// Most slots ever occupied at the same time
+    pub high_water_buffers: u64,
+    /// Largest frame that could be written without blocking right now
+    pub largest_writable_bytes: u64,
+}
+
+impl MemoryReport {
+    /// Fraction of a slot used by the largest frame seen so far
+    pub fn slot_utilization(&self) -> f64 {
+        if self.buffer_bytes == 0 {
+            return 0.0;
+        }
+        self.high_water_bytes as f64 / self.buffer_bytes as f64
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MB: f64 = 1024.0 * 1024.0;
+        writeln!(f, "total:            {:.2} MB (header {} bytes)",
+                 self.total_bytes as f64 / MB, self.header_bytes)?;
+        writeln!(f, "slots:            {} x {:.2} MB",
+                 self.buffer_count, self.buffer_bytes as f64 / MB)?;
+        writeln!(f, "used/free:        {}/{} (peak {})",
+                 self.used_buffers, self.free_buffers, self.high_water_buffers)?;
+        writeln!(f, "largest frame:    {:.2} MB ({:.1}% of a slot)",
+                 self.high_water_bytes as f64 / MB, self.slot_utilization() * 100.0)?;
+        write!(f, "writable now:     {:.2} MB", self.largest_writable_bytes as f64 / MB)
+    }
+}
+
+/// Counters of what this reader consumed, returned by `read_stats()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadStats {
+    /// Data frames delivered
+    pub frames: u64,
+    /// Frames skipped because their TTL ran out before they were read
+    pub expired: u64,
+    /// Interned frames skipped while waiting for a dictionary keyframe
+    pub unresolved: u64,
+    /// Parts of split messages dropped because a part was missed, see
+    /// `with_max_frame_bytes`
+    pub incomplete: u64,
+}
+
+#[derive(Default)]
+struct ReadCounters {
+    frames: AtomicU64,
+    expired: AtomicU64,
+    unresolved: AtomicU64,
+    incomplete: AtomicU64,
+}
+
+/// Counters of what this writer published, returned by `write_stats()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteStats {
+    /// Data frames published
+    pub frames: u64,
+    /// Frames skipped as identical to the one before, see `with_dedup`
+    pub deduplicated: u64,
+    /// Frames sent uncompressed because compression did not pay, see
+    /// `with_adaptive_compression`
+    pub uncompressed: u64,
+}
+
+#[derive(Default)]
+struct WriteCounters {
+    frames: AtomicU64,
+    deduplicated: AtomicU64,
+    uncompressed: AtomicU64,
+}
+
+/// Separates the namespace from the arena name in segment names
+pub(crate) const NAMESPACE_SEPARATOR: char = '.';
+
 /// Configuration for shared memory arena
 #[derive(Debug, Clone)]
 pub struct SharedMemoryConfig {
@@ -27,6 +370,48 @@ pub struct SharedMemoryConfig {
     pub size_mb: usize,
     pub buffer_count: usize,
     pub timeout_ms: Option<i32>,
+    pub open_mode: OpenMode,
+    pub ack_mode: bool,
+    pub consumer_groups: bool,
+    pub consumer_group: Option<String>,
+    pub max_readers: Option<usize>,
+    pub max_frame_bytes: Option<usize>,
+    pub memory_protection: MemoryProtection,
+    pub guard_pages: bool,
+    pub canaries: bool,
+    pub madvise: Vec<Advice>,
+    pub pmem_path: Option<PathBuf>,
+    pub clock: ClockSource,
+    pub time_source: Arc<dyn Clock>,
+    pub retention: Option<Duration>,
+    pub adaptive_sizing: Option<AdaptiveSizing>,
+    pub writer_id: Option<u32>,
+    pub filter: Option<Expr>,
+    pub decode_cache: Option<usize>,
+    pub compression: Option<IpcCompression>,
+    pub codec: Option<Arc<dyn Codec>>,
+    pub adaptive_compression: Option<AdaptiveCompression>,
+    pub transport: Transport,
+    pub fault_injector: Option<testing::FaultInjector>,
+    pub read_policy: ReadPolicy,
+    pub resource_limits: ResourceLimits,
+    pub namespace: Option<String>,
+    pub capability_token: Option<CapabilityToken>,
+    pub wait_strategy: WaitStrategy,
+    pub priority_lanes: Option<usize>,
+    pub relay: Option<Arc<dyn relay::NotificationRelay>>,
+    pub rate_limit: Option<RateLimit>,
+    pub channel_quotas: Option<ChannelQuotas>,
+    pub dedup: bool,
+    pub histograms: bool,
+    pub validators: Option<Arc<Validators>>,
+    pub dead_letter_queue: bool,
+    pub event_logging: Option<log::Level>,
+    pub supervision: Option<supervisor::Supervision>,
+    pub stats_history: Option<StatsHistory>,
+    pub watermarks: Vec<Watermark>,
+    pub string_interning: Option<StringInterning>,
+    pub column_policies: Vec<(String, ColumnPolicy)>,
 }
 
 impl Default for SharedMemoryConfig {
@@ -36,6 +421,48 @@ impl Default for SharedMemoryConfig {
             size_mb: 100,
             buffer_count: 3,
             timeout_ms: None,
+            open_mode: OpenMode::default(),
+            ack_mode: false,
+            consumer_groups: false,
+            consumer_group: None,
+            max_readers: None,
+            max_frame_bytes: None,
+            memory_protection: MemoryProtection::default(),
+            guard_pages: false,
+            canaries: false,
+            madvise: Vec::new(),
+            pmem_path: None,
+            clock: ClockSource::default(),
+            time_source: Arc::new(SystemClock),
+            retention: None,
+            adaptive_sizing: None,
+            writer_id: None,
+            filter: None,
+            decode_cache: None,
+            compression: None,
+            codec: None,
+            adaptive_compression: None,
+            transport: Transport::default(),
+            fault_injector: None,
+            read_policy: ReadPolicy::default(),
+            resource_limits: ResourceLimits::default(),
+            namespace: std::env::var("QADATASWAP_NAMESPACE").ok().filter(|ns| !ns.is_empty()),
+            capability_token: None,
+            wait_strategy: WaitStrategy::default(),
+            priority_lanes: None,
+            relay: None,
+            rate_limit: None,
+            channel_quotas: None,
+            dedup: false,
+            histograms: false,
+            validators: None,
+            dead_letter_queue: false,
+            event_logging: None,
+            supervision: None,
+            stats_history: None,
+            watermarks: Vec::new(),
+            string_interning: None,
+            column_policies: Vec::new(),
         }
     }
 }
@@ -48,6 +475,12 @@ impl SharedMemoryConfig {
         }
     }
 
+    /// Size, slot count, wait strategy and backpressure for a common workload;
+    /// `with_*` calls after this one override single settings
+    pub fn preset(self, preset: Preset) -> Self {
+        preset.apply(self)
+    }
+
     pub fn with_size_mb(mut self, size_mb: usize) -> Self {
         self.size_mb = size_mb;
         self
@@ -62,71 +495,707 @@ impl SharedMemoryConfig {
         self.timeout_ms = Some(timeout_ms);
         self
     }
-}
 
-// FFI bindings to C++ core - simplified for now
-extern "C" {
-    fn qads_create_arena(name: *const c_char, size: usize, buffer_count: usize) -> *mut c_void;
-    fn qads_destroy_arena(arena: *mut c_void);
-    fn qads_create_writer(arena: *mut c_void) -> c_int;
-    fn qads_attach_reader(arena: *mut c_void) -> c_int;
-    fn qads_write_data(arena: *mut c_void, data: *const u8, size: usize) -> c_int;
-    fn qads_read_data(arena: *mut c_void, data: *mut u8, max_size: usize,
-                      actual_size: *mut usize, timeout_ms: c_int) -> c_int;
-    fn qads_wait_for_data(arena: *mut c_void, timeout_ms: c_int) -> c_int;
-    fn qads_notify_data_ready(arena: *mut c_void);
-    fn qads_close(arena: *mut c_void);
+    pub fn with_open_mode(mut self, open_mode: OpenMode) -> Self {
+        self.open_mode = open_mode;
+        self
+    }
+
+    /// Only recycle a buffer once the reader has acknowledged it (at-least-once delivery)
+    pub fn with_ack_mode(mut self, ack_mode: bool) -> Self {
+        self.ack_mode = ack_mode;
+        self
+    }
+
+    /// Writer side: deliver frames per consumer group instead of to a single shared cursor
+    pub fn with_consumer_groups(mut self, enabled: bool) -> Self {
+        self.consumer_groups = enabled;
+        self
+    }
+
+    /// Reader side: join a consumer group; members of one group share its frames
+    /// while every other group independently sees all of them
+    pub fn with_consumer_group(mut self, group: impl Into<String>) -> Self {
+        self.consumer_group = Some(group.into());
+        self
+    }
+
+    /// Writer side: split DataFrames that encode larger than `max_bytes` into
+    /// several frames, which readers put back together into one DataFrame.
+    /// Keep it below the slot size so producers never hit `FrameTooLarge`.
+    /// A reader must see every part of a message: consumer group members and
+    /// readers sharing a ring's cursor each get only some parts, and drop
+    /// the messages they cannot complete (counted in `ReadStats::incomplete`).
+    pub fn with_max_frame_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_frame_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Writer side: refuse reader attaches with `TooManyReaders` while `max_readers`
+    /// readers, from any process, are attached
+    pub fn with_max_readers(mut self, max_readers: usize) -> Self {
+        self.max_readers = Some(max_readers);
+        self
+    }
+
+    /// Write-protect the ring against stray writes, see `MemoryProtection`
+    pub fn with_memory_protection(mut self, protection: MemoryProtection) -> Self {
+        self.memory_protection = protection;
+        self
+    }
+
+    /// Writer side: end every slot in an inaccessible page, so a binding that
+    /// writes past a slot crashes at the culprit (shared memory transport only)
+    pub fn with_guard_pages(mut self, enabled: bool) -> Self {
+        self.guard_pages = enabled;
+        self
+    }
+
+    /// Writer side: put a canary word after every slot's capacity and check it
+    /// when a frame is published and when it is consumed. A clobbered canary
+    /// fails that publish or read with `Overrun` and the frame is dropped.
+    pub fn with_canaries(mut self, enabled: bool) -> Self {
+        self.canaries = enabled;
+        self
+    }
+
+    /// madvise() hints applied to this endpoint's mapping of the arena, e.g.
+    /// `Dontdump` to keep multi-GB arenas out of core dumps. The kernel may
+    /// decline a hint; the arena is mapped regardless. Shared memory transport only.
+    pub fn with_madvise(mut self, advice: &[Advice]) -> Self {
+        self.madvise = advice.to_vec();
+        self
+    }
+
+    /// Map the arena from persistent memory instead of /dev/shm: a devdax
+    /// device such as `/dev/dax0.0`, or a file on a DAX-mounted filesystem.
+    /// Frames are flushed to the media before readers see them and the arena
+    /// outlives its writer, so reopening with `OpenMode::OpenOrAttach` resumes
+    /// the ring after a crash or reboot. The path must already exist; opening
+    /// the arena fails for anything but a devdax device or a file the kernel
+    /// reports as DAX. A path holds one arena, and every endpoint must
+    /// configure it with the same size. The High priority lane stays in
+    /// /dev/shm and is not persisted. Shared memory transport only.
+    pub fn with_pmem(mut self, path: impl Into<PathBuf>) -> Self {
+        self.pmem_path = Some(path.into());
+        self
+    }
+
+    /// Writer side: clock frame headers are stamped on, see `ClockSource`. A
+    /// `MergeReader` source names its writer's clock here too.
+    pub fn with_clock(mut self, clock: ClockSource) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Read time from `time` (frame stamps, TTLs, deadlines, supervision, stats,
+    /// pacing; see the `clock` module for the full list) instead of the system
+    /// clock, e.g. a shared `MockClock` for deterministic tests or simulated replays
+    pub fn with_time_source(mut self, time: Arc<dyn Clock>) -> Self {
+        self.time_source = time;
+        self
+    }
+
+    /// Keep frames replayable for a rolling time window (still bounded by `buffer_count`)
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Start with `max_buffer_count` small slots and re-slot the ring during quiet
+    /// periods to match the observed P99 frame size. Overrides `buffer_count`.
+    pub fn with_adaptive_sizing(mut self, policy: AdaptiveSizing) -> Self {
+        self.adaptive_sizing = Some(policy);
+        self
+    }
+
+    /// Identity stamped into every frame this writer produces (defaults to the process id)
+    pub fn with_writer_id(mut self, writer_id: u32) -> Self {
+        self.writer_id = Some(writer_id);
+        self
+    }
+
+    /// Writer side: compress frame payloads (LZ4 or ZSTD); readers detect it automatically
+    pub fn with_compression(mut self, compression: IpcCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Writer side: send frames uncompressed while compressing them saves too
+    /// little or takes too long; readers see `FrameMetadata::compression_skipped`
+    pub fn with_adaptive_compression(mut self, policy: AdaptiveCompression) -> Self {
+        self.adaptive_compression = Some(policy);
+        self
+    }
+
+    /// Writer side: encode data frames with `codec` instead of Arrow IPC and
+    /// publish its name to readers. Reader side: decode with `codec`, refusing
+    /// to attach to a writer publishing another; see `codec`
+    pub fn with_codec(mut self, codec: Arc<dyn Codec>) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+
+    /// Reader side: only return rows matching `predicate`. The predicate's columns
+    /// are decoded first and frames without a matching row are skipped undecoded.
+    pub fn with_filter(mut self, predicate: Expr) -> Self {
+        self.filter = Some(predicate);
+        self
+    }
+
+    /// Reader side: share decoded frames with the process's other readers of
+    /// this channel that enable it too, keeping up to `max_bytes` of recently
+    /// read frames, see `decode_cache`
+    pub fn with_decode_cache(mut self, max_bytes: usize) -> Self {
+        self.decode_cache = Some(max_bytes);
+        self
+    }
+
+    /// Carry frames over shared memory (default) or an in-process channel; the
+    /// default honours `QADATASWAP_TRANSPORT=inprocess`
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Reader side: how frames written before a schema change are returned
+    pub fn with_read_policy(mut self, policy: ReadPolicy) -> Self {
+        self.read_policy = policy;
+        self
+    }
+
+    /// Refuse to open this arena if the process would exceed `limits`
+    pub fn with_resource_limits(mut self, limits: ResourceLimits) -> Self {
+        self.resource_limits = limits;
+        self
+    }
+
+    /// Partition segment names so e.g. dev, staging and prod processes sharing a
+    /// host never attach to each other's arenas; defaults to `QADATASWAP_NAMESPACE`
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Name of the underlying segment: `<namespace>.<name>`, or just `name`
+    pub fn segment_name(&self) -> Result<String> {
+        match &self.namespace {
+            None => Ok(self.name.clone()),
+            Some(ns) if ns.is_empty() || ns.contains(['/', NAMESPACE_SEPARATOR]) => Err(QADataSwapError::SharedMemory(
+                format!("Invalid namespace '{}': must be non-empty without '/' or '{}'", ns, NAMESPACE_SEPARATOR),
+            )),
+            Some(ns) => Ok(format!("{}{}{}", ns, NAMESPACE_SEPARATOR, self.name)),
+        }
+    }
+
+    /// Shared secret: a writer requires readers to present it, a reader presents it.
+    /// Attaching with a missing or different token fails with `Unauthorized`.
+    pub fn with_capability_token(mut self, token: impl Into<CapabilityToken>) -> Self {
+        self.capability_token = Some(token.into());
+        self
+    }
+
+    /// Reader side: how to sleep until the next frame is published
+    pub fn with_wait_strategy(mut self, strategy: WaitStrategy) -> Self {
+        self.wait_strategy = strategy;
+        self
+    }
+
+    /// Carry `Priority::High` frames in a second ring that readers drain first.
+    /// After `max_high_burst` high frames in a row a waiting normal frame is
+    /// delivered, so normal data is never starved. Set on writers and readers.
+    pub fn with_priority_lanes(mut self, max_high_burst: usize) -> Self {
+        self.priority_lanes = Some(max_high_burst);
+        self
+    }
+
+    /// Writer side: also announce `notify_data_ready()` on a message bus for
+    /// other hosts (see `relay::RedisRelay`, `relay::ZmqRelay`)
+    pub fn with_relay(mut self, relay: Arc<dyn relay::NotificationRelay>) -> Self {
+        self.relay = Some(relay);
+        self
+    }
+
+    /// Writer side: pace data frames written with `write()` / `write_chunk()`.
+    /// Frames sent to the high priority lane are not paced.
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Writer side: hold routing keys sharing this arena to byte quotas and a
+    /// fair share of its slots, see `ChannelQuotas`. Frames sent to the high
+    /// priority lane are not scheduled.
+    pub fn with_channel_quotas(mut self, quotas: ChannelQuotas) -> Self {
+        self.channel_quotas = Some(quotas);
+        self
+    }
+
+    /// Writer side: skip publishing a data frame identical to the previous one,
+    /// compared by a SHA-256 of its encoded payload
+    pub fn with_dedup(mut self, enabled: bool) -> Self {
+        self.dedup = enabled;
+        self
+    }
+
+    /// Record frame size, encode or decode time and inter-arrival time
+    /// distributions, returned by `histograms()`
+    pub fn with_histograms(mut self) -> Self {
+        self.histograms = true;
+        self
+    }
+
+    /// Check every frame written (writer) or read (reader), see `validators`
+    pub fn with_validators(mut self, validators: Validators) -> Self {
+        self.validators = Some(Arc::new(validators));
+        self
+    }
+
+    /// Reader side: publish frames that fail to decode, coerce or validate to
+    /// the `<name>__dlq` arena instead of failing or silently skipping them
+    pub fn with_dead_letter_queue(mut self, enabled: bool) -> Self {
+        self.dead_letter_queue = enabled;
+        self
+    }
+
+    /// Log attach/detach, ring wraps, dropped frames, timeouts and recoveries at
+    /// `level`, see `events`
+    pub fn with_event_logging(mut self, level: log::Level) -> Self {
+        self.event_logging = Some(level);
+        self
+    }
+
+    /// Reader side: report when neither data nor a heartbeat is published for
+    /// an interval, see `supervisor`
+    pub fn with_supervision(mut self, supervision: supervisor::Supervision) -> Self {
+        self.supervision = Some(supervision);
+        self
+    }
+
+    /// Writer side records snapshots of the channel's counters to disk; any
+    /// endpoint configured with the same history can query them through
+    /// `stats_history`, see `history`
+    pub fn with_stats_history(mut self, history: StatsHistory) -> Self {
+        self.stats_history = Some(history);
+        self
+    }
+
+    /// Writer side: tell `hook` when the fraction of slots holding unconsumed
+    /// frames reaches `threshold` and when it falls back, see `watermark`
+    pub fn on_occupancy(mut self, threshold: f64, hook: impl WatermarkHook + 'static) -> Self {
+        self.watermarks.push(Watermark::new(WatermarkLevel::Occupancy(threshold), hook));
+        self
+    }
+
+    /// Writer side: tell `hook` when readers fall `frames` behind and when they
+    /// catch up again
+    pub fn on_reader_lag(mut self, frames: u64, hook: impl WatermarkHook + 'static) -> Self {
+        self.watermarks.push(Watermark::new(WatermarkLevel::ReaderLag(frames), hook));
+        self
+    }
+
+    /// Writer side: send the values of these string columns as codes into a
+    /// dictionary shared across frames, see `intern`
+    pub fn with_string_interning(mut self, interning: StringInterning) -> Self {
+        self.string_interning = Some(interning);
+        self
+    }
+
+    /// Hash, encrypt or redact `column` as frames are written; a reader with
+    /// the same `ColumnPolicy::Encrypt` key decrypts it, see `masking`
+    pub fn with_column_policy(mut self, column: impl Into<String>, policy: ColumnPolicy) -> Self {
+        let column = column.into();
+        self.column_policies.retain(|(name, _)| *name != column);
+        self.column_policies.push((column, policy));
+        self
+    }
+
+    /// Route this arena through a fault schedule (see `testing::FaultInjector`)
+    pub fn with_fault_injector(mut self, injector: testing::FaultInjector) -> Self {
+        self.fault_injector = Some(injector);
+        self
+    }
 }
 
+/// LazyFrame transform applied to every frame a reader receives
+pub type Pipeline = Arc<dyn Fn(LazyFrame) -> LazyFrame + Send + Sync>;
+
+/// How often an idle `ReadPolicy::AutoReconnect` reader checks whether its
+/// segment was recreated
+const RECONNECT_POLL_MS: i32 = 100;
+
 /// Shared memory arena for zero-copy data transfer
 pub struct SharedMemoryArena {
-    inner: *mut c_void,
+    raw: Arc<dyn RawArena>,
     config: SharedMemoryConfig,
     is_writer: bool,
+    sizer: Option<Mutex<FrameSizeTracker>>,
+    scratch: Mutex<Scratch>,
+    /// Writer: schema of the last data frame; reader: latest schema under coercion
+    schema: Mutex<Option<SchemaRef>>,
+    pipeline: Option<Pipeline>,
+    /// Reader: columns that must not contain nulls, see `expect_non_null`
+    non_null: Vec<String>,
+    /// Reader: routing keys of the data frames to deliver, see `set_key_filter`
+    key_filter: RwLock<Option<KeyFilter>>,
+    lease: limits::Lease,
+    pacer: Mutex<Option<rate::TokenBucket>>,
+    /// Writer: admits keyed frames, see `with_channel_quotas`
+    quotas: Option<quota::QuotaScheduler>,
+    /// Settings changed at runtime, see `update_policy`
+    policy: Mutex<policy::PolicyState>,
+    /// Writer: whether compression currently pays, see `with_adaptive_compression`
+    governor: Option<Mutex<CompressionGovernor>>,
+    read_counters: ReadCounters,
+    write_counters: WriteCounters,
+    /// Reader: codec of this ring's coded frames, once resolved
+    codec: Mutex<Option<Arc<dyn Codec>>>,
+    /// Frame distributions, see `with_histograms`
+    histograms: Option<Mutex<HistogramRecorder>>,
+    /// Digest of the last data frame published, when deduplicating
+    last_digest: Mutex<Option<[u8; 32]>>,
+    /// Writer: number of the next split message, see `with_max_frame_bytes`
+    next_message: AtomicU64,
+    /// Reader: parts of the split message being received
+    assembly: Mutex<split::Assembly>,
+    /// Reader: frames `wait_for_schema` took off the ring, delivered before newer ones
+    held: Mutex<VecDeque<(FrameMetadata, Event)>>,
+    /// Writer: string dictionary, see `with_string_interning`
+    interner: Option<Mutex<intern::Interner>>,
+    /// Reader: this ring's copy of the writer's string dictionary
+    dictionary: Mutex<intern::Dictionary>,
+    /// Writer: trained zstd dictionary, see `train_dictionary`
+    zstd_writer: RwLock<Option<zdict::DictionaryWriter>>,
+    /// Reader: zstd dictionaries fetched for this ring
+    zstd_reader: Mutex<zdict::DictionaryReader>,
+    /// Reader: where frames it cannot deliver go, see `with_dead_letter_queue`
+    dlq: Option<DeadLetterQueue>,
+    events: EventLog,
+    /// Writer: which of the configured watermarks are crossed
+    watermarks: watermark::WatermarkState,
+    /// Reader: stall watcher, see `with_supervision`
+    supervisor: Option<supervisor::Supervisor>,
+    /// Writer: snapshot sampler, see `with_stats_history`
+    stats_recorder: Option<history::StatsRecorder>,
+    /// The channel's high priority ring, with `lanes` choosing which to read next
+    high_lane: Option<Box<SharedMemoryArena>>,
+    lanes: Option<LaneScheduler>,
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    uring: Option<wait::UringWaiter>,
+    /// Reader: eventfd handed out by `readiness_fd`
+    #[cfg(target_os = "linux")]
+    readiness: OnceLock<ReadinessFd>,
+    /// Writer: signals the eventfds of readers that asked for one
+    #[cfg(target_os = "linux")]
+    readiness_host: Option<ReadinessHost>,
 }
 
-unsafe impl Send for SharedMemoryArena {}
-unsafe impl Sync for SharedMemoryArena {}
-
 impl SharedMemoryArena {
     pub fn new(config: SharedMemoryConfig) -> Result<Self> {
-        let name_cstr = CString::new(config.name.clone())
-            .map_err(|_| QADataSwapError::SharedMemory("Invalid name".to_string()))?;
-
-        let inner = unsafe {
-            qads_create_arena(
-                name_cstr.as_ptr(),
-                config.size_mb * 1024 * 1024,
-                config.buffer_count,
-            )
+        let buffer_count = match &config.adaptive_sizing {
+            Some(policy) => policy.max_buffer_count,
+            None => config.buffer_count,
         };
 
-        if inner.is_null() {
-            return Err(QADataSwapError::SharedMemory("Failed to create arena".to_string()));
+        let segment = config.segment_name()?;
+        let bytes = (config.size_mb as u64).saturating_mul(1024 * 1024);
+        let lease = limits::Lease::acquire(&segment, bytes, config.resource_limits)?;
+        let events = EventLog::new(config.event_logging, &segment);
+        let mut raw = backend::open(&segment, &config, buffer_count)?;
+        if let Some(injector) = &config.fault_injector {
+            raw = injector.wrap(raw);
+        }
+        raw.set_protection(config.memory_protection);
+        raw.set_slot_guards(config.guard_pages, config.canaries);
+        raw.set_advice(&config.madvise);
+        if let Some(path) = &config.pmem_path {
+            if raw.set_backing_path(path) != backend::STATUS_OK {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Invalid pmem path {}: not a devdax device or a file on a DAX filesystem", path.display()
+                )));
+            }
         }
 
+        let sizer = match &config.adaptive_sizing {
+            Some(policy) => {
+                raw.reserve_slots(policy.max_buffer_count);
+                Some(Mutex::new(FrameSizeTracker::new(policy.clone())))
+            },
+            None => None,
+        };
+
+        let (high_lane, lanes) = match config.priority_lanes {
+            Some(max_high_burst) => {
+                let mut lane = config.clone();
+                lane.name = priority::lane_name(&config.name, Priority::High);
+                lane.priority_lanes = None;
+                // The backing holds a single arena, so the high lane stays in /dev/shm
+                lane.pmem_path = None;
+                lane.rate_limit = None;
+                lane.channel_quotas = None;
+                lane.dead_letter_queue = false;
+                lane.supervision = None;
+                lane.stats_history = None;
+                lane.watermarks.clear();
+                (Some(Box::new(SharedMemoryArena::new(lane)?)), Some(LaneScheduler::new(max_high_burst)))
+            },
+            None => (None, None),
+        };
+        let pacer = config.rate_limit.map(|limit| rate::TokenBucket::new(limit, Arc::clone(&config.time_source)));
+        let pacer = Mutex::new(pacer);
+        let quotas = config
+            .channel_quotas
+            .clone()
+            .map(|quotas| quota::QuotaScheduler::new(quotas, Arc::clone(&config.time_source)));
+        let policy = Mutex::new(policy::PolicyState::new(config.compression));
+        let interner = config.string_interning.clone().map(|interning| Mutex::new(intern::Interner::new(interning)));
+        let governor = config.adaptive_compression.clone().map(|policy| Mutex::new(CompressionGovernor::new(policy)));
+        let histograms = config.histograms.then(Mutex::default);
+
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        let uring = match config.wait_strategy {
+            WaitStrategy::IoUring => Some(wait::UringWaiter::new()?),
+            WaitStrategy::Blocking | WaitStrategy::Adaptive(_) => None,
+        };
+
+        let scratch = Scratch::new().with_clock(config.clock).with_time_source(Arc::clone(&config.time_source));
         Ok(Self {
-            inner,
+            raw,
             config,
             is_writer: false,
+            sizer,
+            scratch: Mutex::new(scratch),
+            schema: Mutex::new(None),
+            pipeline: None,
+            non_null: Vec::new(),
+            key_filter: RwLock::new(None),
+            lease,
+            pacer,
+            quotas,
+            policy,
+            governor,
+            read_counters: ReadCounters::default(),
+            write_counters: WriteCounters::default(),
+            codec: Mutex::new(None),
+            histograms,
+            last_digest: Mutex::new(None),
+            next_message: AtomicU64::new(0),
+            assembly: Mutex::new(split::Assembly::default()),
+            held: Mutex::new(VecDeque::new()),
+            interner,
+            dictionary: Mutex::new(intern::Dictionary::default()),
+            zstd_writer: RwLock::new(None),
+            zstd_reader: Mutex::new(zdict::DictionaryReader::default()),
+            dlq: None,
+            events,
+            watermarks: watermark::WatermarkState::default(),
+            supervisor: None,
+            stats_recorder: None,
+            high_lane,
+            lanes,
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            uring,
+            #[cfg(target_os = "linux")]
+            readiness: OnceLock::new(),
+            #[cfg(target_os = "linux")]
+            readiness_host: None,
         })
     }
 
     pub fn create_writer(&mut self) -> Result<()> {
-        let result = unsafe { qads_create_writer(self.inner) };
-        if result != 0 {
-            return Err(QADataSwapError::SharedMemory("Failed to create writer".to_string()));
+        if let Some(lane) = &mut self.high_lane {
+            lane.create_writer()?;
+        }
+        let previous = match self.events.enabled() {
+            true => backend::inspect_segment(self.config.transport, &self.config.segment_name()?),
+            false => None,
+        };
+        match self.raw.create_writer(self.config.open_mode) {
+            0 => {
+                self.events.attach(format_args!("writer attached ({:?})", self.config.open_mode));
+                match previous {
+                    Some(_) if self.config.open_mode == OpenMode::OpenOrAttach => {
+                        self.events.emit("recovery", format_args!("writer reattached to the existing segment"));
+                    },
+                    Some(status) if status.writer_alive == 0 => self.events.emit(
+                        "recovery",
+                        format_args!("replaced a stale segment left by pid {}", status.writer_pid),
+                    ),
+                    _ => {},
+                }
+                self.is_writer = true;
+                // Without the socket readers' handles are never signalled, but frames still flow
+                #[cfg(target_os = "linux")]
+                {
+                    self.readiness_host = readiness_address(&self.config)
+                        .ok()
+                        .and_then(|address| ReadinessHost::bind(&address).ok());
+                }
+                if self.config.ack_mode {
+                    self.raw.set_ack_mode(true);
+                }
+                if self.config.consumer_groups {
+                    self.raw.enable_consumer_groups();
+                }
+                if let Some(max_readers) = self.config.max_readers {
+                    self.raw.set_max_readers(max_readers);
+                }
+                if let Some(retention) = self.config.retention {
+                    self.raw.set_retention(retention.as_micros() as u64);
+                }
+                let challenge = match &self.config.capability_token {
+                    Some(token) => Some(token.issue()?),
+                    None => None,
+                };
+                self.raw.set_auth(challenge.as_ref());
+                let codec = self.config.codec.as_ref().map(|codec| codec.name().to_string());
+                self.raw.put_meta(CODEC_META_KEY, codec.as_deref());
+                if let Some(history) = self.config.stats_history.clone() {
+                    let segment = self.config.segment_name()?;
+                    self.stats_recorder = Some(history::StatsRecorder::spawn(
+                        Arc::clone(&self.raw),
+                        segment,
+                        history,
+                        Arc::clone(&self.config.time_source),
+                    )?);
+                }
+                Ok(())
+            },
+            2 => {
+                let segment = self.config.segment_name()?;
+                let status = backend::inspect_segment(self.config.transport, &segment).unwrap_or_default();
+                Err(QADataSwapError::AlreadyExists {
+                    name: segment,
+                    owner_pid: status.writer_pid,
+                    created_at: UNIX_EPOCH + Duration::from_micros(status.created_at_us),
+                })
+            },
+            _ => Err(QADataSwapError::SharedMemory("Failed to create writer".to_string())),
         }
-        self.is_writer = true;
-        Ok(())
     }
 
     pub fn attach_reader(&mut self) -> Result<()> {
-        let result = unsafe { qads_attach_reader(self.inner) };
-        if result != 0 {
-            return Err(QADataSwapError::SharedMemory("Failed to attach reader".to_string()));
+        if let Some(lane) = &mut self.high_lane {
+            lane.attach_reader()?;
+        }
+        self.lease.add_reader()?;
+        match self.raw.attach_reader() {
+            backend::STATUS_OK => {},
+            backend::STATUS_BUSY => {
+                let name = self.config.segment_name()?;
+                let status = backend::inspect_segment(self.config.transport, &name).unwrap_or_default();
+                return Err(QADataSwapError::TooManyReaders {
+                    name,
+                    max_readers: status.max_readers.max(0) as usize,
+                });
+            },
+            _ => return Err(QADataSwapError::SharedMemory("Failed to attach reader".to_string())),
+        }
+        self.is_writer = false;
+        self.handshake()?;
+        if let Some(group) = self.config.consumer_group.clone() {
+            self.join_group(&group)?;
+        }
+        if self.config.dead_letter_queue {
+            self.dlq = Some(DeadLetterQueue::open(&self.config)?);
+        }
+        if let Some(supervision) = self.config.supervision.clone() {
+            let mut rings = vec![Arc::clone(&self.raw)];
+            rings.extend(self.high_lane.iter().map(|lane| Arc::clone(&lane.raw)));
+            let (segment, time) = (self.config.segment_name()?, Arc::clone(&self.config.time_source));
+            self.supervisor = Some(supervisor::Supervisor::spawn(rings, segment, supervision, time)?);
+        }
+        self.events.attach(format_args!("reader attached"));
+        Ok(())
+    }
+
+    /// Check the token and codec of the segment just attached; detach on failure
+    fn handshake(&self) -> Result<()> {
+        if let Err(e) = auth::authorize(self.config.capability_token.as_ref(), self.raw.auth().as_ref()) {
+            self.raw.close();
+            return Err(e);
+        }
+
+        if let Some(codec) = &self.config.codec {
+            if let Some(published) = self.raw.get_meta(CODEC_META_KEY).filter(|published| published != codec.name()) {
+                self.raw.close();
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Arena '{}' is written with codec '{}', not '{}'", self.config.name, published, codec.name()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// With `ReadPolicy::AutoReconnect`: if a writer has recreated the segment
+    /// since this reader attached, attach to the new one and return its generation
+    fn reconnect_if_recreated(&self) -> Result<Option<u64>> {
+        let segment = self.config.segment_name()?;
+        let Some(status) = backend::inspect_segment(self.config.transport, &segment) else {
+            return Ok(None);
+        };
+        let mut attached = backend::HeaderDump::default();
+        if self.raw.dump_header(&mut attached) == 0 && attached.generation == status.generation {
+            return Ok(None);
+        }
+
+        // The new writer may still be setting the segment up; retried on the next poll
+        self.raw.close();
+        if self.raw.attach_reader() != 0 {
+            return Ok(None);
+        }
+        self.handshake()?;
+        if let Some(group) = &self.config.consumer_group {
+            self.join_group(group)?;
+        }
+        *self.codec.lock().unwrap() = None;
+        self.events.reset_position();
+        self.events.emit("reconnect", format_args!("attached to generation {}", status.generation));
+        Ok(Some(status.generation))
+    }
+
+    /// Reader over this writer's own segment, starting at the live edge. It keeps
+    /// its own cursor, so it sees every frame with the sequence the writer gave
+    /// it without consuming frames other readers wait for or holding the writer
+    /// back; a view that falls behind skips to the oldest retained frame.
+    pub fn reader_view(&self) -> Result<SharedMemoryArena> {
+        if !self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Only a writer has a reader view".to_string()));
+        }
+        let mut config = self.config.clone();
+        // The writer already validated its frames and is the one holding the token
+        config.validators = None;
+        config.capability_token = None;
+        config.consumer_group = None;
+        config.dead_letter_queue = false;
+        config.supervision = None;
+        config.fault_injector = None;
+        let mut view = SharedMemoryArena::new(config)?;
+        view.attach_view()?;
+        Ok(view)
+    }
+
+    /// Attach without the reader handshake: no authorization, group or reader
+    /// limit, and positioned at the live edge outside buffer recycling
+    fn attach_view(&mut self) -> Result<()> {
+        if let Some(lane) = &mut self.high_lane {
+            lane.attach_view()?;
+        }
+        if self.raw.attach_reader() != 0 || self.raw.seek(SeekFrom::Latest) != 0 {
+            return Err(QADataSwapError::SharedMemory("Failed to open a reader view".to_string()));
         }
         self.is_writer = false;
+        self.events.attach(format_args!("reader view attached"));
+        Ok(())
+    }
+
+    fn join_group(&self, group: &str) -> Result<()> {
+        let result = self.raw.join_group(group);
+        if result != 0 {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Failed to join consumer group '{}'", group
+            )));
+        }
         Ok(())
     }
 
@@ -135,197 +1204,2215 @@ impl SharedMemoryArena {
             return Err(QADataSwapError::SharedMemory("Not a writer".to_string()));
         }
 
-        let result = unsafe {
-            qads_write_data(self.inner, bytes.as_ptr(), bytes.len())
-        };
+        if let Some(sizer) = &self.sizer {
+            self.adapt_geometry(sizer, bytes.len())?;
+        }
+
+        let result = self.raw.write(bytes);
 
+        if result == backend::STATUS_OVERRUN {
+            return Err(QADataSwapError::Overrun(self.config.name.clone()));
+        }
         if result != 0 {
             return Err(QADataSwapError::SharedMemory("Failed to write data".to_string()));
         }
+        self.wake_readers();
 
+        if self.events.enabled() {
+            self.log_wrap();
+        }
+        if !self.config.watermarks.is_empty() {
+            self.watermarks.check(self.raw.as_ref(), &self.config.name, &self.config.watermarks);
+        }
         Ok(())
     }
 
-    fn read_dataframe_bytes(&self, timeout_ms: Option<i32>) -> Result<Option<Vec<u8>>> {
-        if self.is_writer {
-            return Err(QADataSwapError::SharedMemory("Writer cannot read".to_string()));
+    fn log_wrap(&self) {
+        let mut header = backend::HeaderDump::default();
+        if self.raw.dump_header(&mut header) == 0
+            && header.buffer_count > 0
+            && header.write_sequence % header.buffer_count == 0
+        {
+            self.events.emit("wrap", format_args!("ring wrapped at sequence {}", header.write_sequence));
         }
+    }
 
-        let mut buffer = vec![0u8; self.config.size_mb * 1024 * 1024];
-        let mut actual_size = 0usize;
-        let timeout = timeout_ms.unwrap_or(self.config.timeout_ms.unwrap_or(-1));
+    /// Re-slot ahead of a write: immediately if the frame would not fit, otherwise
+    /// when the tracked distribution has drifted past the hysteresis
+    fn adapt_geometry(&self, sizer: &Mutex<FrameSizeTracker>, frame_size: usize) -> Result<()> {
+        let report = self.memory_report()?;
+        let usable = (report.total_bytes - report.header_bytes) as usize;
+        let current = report.buffer_count as usize;
 
-        let result = unsafe {
-            qads_read_data(
-                self.inner,
-                buffer.as_mut_ptr(),
-                buffer.len(),
-                &mut actual_size,
-                timeout,
-            )
-        };
+        let mut tracker = sizer.lock().unwrap();
+        tracker.record(frame_size);
 
-        match result {
-            0 => {
-                buffer.truncate(actual_size);
-                Ok(Some(buffer))
-            },
-            1 => Err(QADataSwapError::Timeout),
-            _ => Err(QADataSwapError::SharedMemory("Failed to read data".to_string())),
+        if frame_size as u64 > report.buffer_bytes {
+            let count = tracker.count_for_frame(frame_size, usable);
+            if !self.reslot(count)? {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Frame of {} bytes exceeds the {} byte slot and the ring is busy",
+                    frame_size, report.buffer_bytes
+                )));
+            }
+        } else if let Some(count) = tracker.recommend(usable, current) {
+            // Best effort: retried on a later check if readers are still behind
+            self.reslot(count)?;
         }
+        Ok(())
     }
 
-    pub fn wait_for_data(&self, timeout_ms: Option<i32>) -> Result<()> {
-        let timeout = timeout_ms.unwrap_or(self.config.timeout_ms.unwrap_or(-1));
-        let result = unsafe { qads_wait_for_data(self.inner, timeout) };
+    /// Split the ring into `buffer_count` equal slots. Only possible while every
+    /// published frame has been consumed; returns `false` if the ring is busy.
+    pub fn reslot(&self, buffer_count: usize) -> Result<bool> {
+        if !self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Not a writer".to_string()));
+        }
 
-        match result {
-            0 => Ok(()),
-            1 => Err(QADataSwapError::Timeout),
-            _ => Err(QADataSwapError::SharedMemory("Wait failed".to_string())),
+        match self.raw.reslot(buffer_count) {
+            backend::STATUS_OK => Ok(true),
+            backend::STATUS_BUSY => Ok(false),
+            _ => Err(QADataSwapError::SharedMemory(format!(
+                "Cannot re-slot ring into {} buffers", buffer_count
+            ))),
         }
     }
 
+    fn read_dataframe_bytes(&self, timeout_ms: Option<i32>) -> Result<Option<Vec<u8>>> {
+        let mut buffer = Vec::new();
+        self.read_into(&mut buffer, timeout_ms)?;
+        Ok(Some(buffer))
+    }
+
+    /// Size in bytes of the next frame on the ring, header and trailers
+    /// included, waiting up to `timeout_ms` for one. The frame stays unread.
+    pub fn peek_frame_size(&self, timeout_ms: Option<i32>) -> Result<usize> {
+        if self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Writer cannot read".to_string()));
+        }
+
+        let timeout = timeout_ms.unwrap_or(self.config.timeout_ms.unwrap_or(-1));
+        let mut size = 0usize;
+        match self.wait_with(timeout, |timeout| self.raw.peek_size(&mut size, timeout)) {
+            backend::STATUS_OK => Ok(size),
+            backend::STATUS_TIMEOUT => Err(QADataSwapError::Timeout),
+            _ => Err(QADataSwapError::SharedMemory("Failed to peek at next frame".to_string())),
+        }
+    }
+
+    /// Read the next frame into `buffer`, resized to the frame: memory follows
+    /// the frames actually read, not the size of the ring
+    pub(crate) fn read_into(&self, buffer: &mut Vec<u8>, timeout_ms: Option<i32>) -> Result<()> {
+        let timeout = timeout_ms.unwrap_or(self.config.timeout_ms.unwrap_or(-1));
+        let time = &self.config.time_source;
+        let deadline = (timeout >= 0).then(|| time.instant() + Duration::from_millis(timeout as u64));
+
+        loop {
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(time.instant()).as_millis() as i32,
+                None => timeout,
+            };
+            let size = self.peek_frame_size(Some(remaining))?;
+            buffer.resize(size, 0);
+            let mut actual_size = 0usize;
+            match self.raw.read(buffer, &mut actual_size, 0) {
+                backend::STATUS_OK => {
+                    buffer.truncate(actual_size);
+                    return Ok(());
+                },
+                // Another member of the consumer group took the frame first
+                backend::STATUS_TIMEOUT if remaining != 0 => {},
+                backend::STATUS_TIMEOUT => return Err(QADataSwapError::Timeout),
+                backend::STATUS_FAULT => return Err(QADataSwapError::MemoryFault(self.config.name.clone())),
+                backend::STATUS_OVERRUN => return Err(QADataSwapError::Overrun(self.config.name.clone())),
+                // ...or took it and left a larger one in its place
+                _ if self.peek_frame_size(Some(0)).is_ok_and(|next| next > size) => {},
+                _ => return Err(QADataSwapError::SharedMemory("Failed to read data".to_string())),
+            }
+        }
+    }
+
+    /// A fresh encode buffer stamping on this arena's clocks
+    fn scratch(&self) -> Scratch {
+        Scratch::new().with_clock(self.config.clock).with_time_source(Arc::clone(&self.config.time_source))
+    }
+
+    /// Identity stamped into frames written through this arena
+    pub fn writer_id(&self) -> u32 {
+        self.config.writer_id.unwrap_or_else(std::process::id)
+    }
+
+    /// Encode through the arena's own scratch buffer, or a temporary one when
+    /// another thread is writing through this arena at the same time
+    fn write_frame(&self, df: &DataFrame, ttl: Option<Duration>, lineage: Option<&Lineage>,
+                   key: Option<&RoutingKey>) -> Result<()> {
+        match self.scratch.try_lock() {
+            Ok(mut scratch) => self.write_frame_with(df, &mut scratch, ttl, lineage, key),
+            Err(_) => {
+                self.write_frame_with(df, &mut self.scratch(), ttl, lineage, key)
+            },
+        }
+    }
+
+    fn write_frame_with(&self, df: &DataFrame, scratch: &mut Scratch, ttl: Option<Duration>,
+                        lineage: Option<&Lineage>, key: Option<&RoutingKey>) -> Result<()> {
+        let compression = self.refresh_policy();
+        let validated;
+        let df = match &self.config.validators {
+            Some(validators) => match validators.validate(df.clone())? {
+                Verdict::Pass(df) => {
+                    validated = df;
+                    &validated
+                },
+                Verdict::Reject(reason) => {
+                    return Err(QADataSwapError::SharedMemory(format!("Frame rejected by validators: {}", reason)))
+                },
+            },
+            None => df,
+        };
+        let masked = masking::mask(df, &self.config.column_policies)?;
+        let df = masked.as_ref().unwrap_or(df);
+        self.announce_schema(df.schema())?;
+        self.write_prepared(df, scratch, compression, ttl, lineage, key, None)
+    }
+
+    /// Encode and publish a frame that already passed validators and masking
+    #[allow(clippy::too_many_arguments)]
+    fn write_prepared(&self, df: &DataFrame, scratch: &mut Scratch, compression: Option<IpcCompression>,
+                      ttl: Option<Duration>, lineage: Option<&Lineage>, key: Option<&RoutingKey>, part: Option<Part>)
+                      -> Result<()> {
+        let (prepared, requested) = (df, compression);
+        let started = Instant::now();
+        let mut interner = self.interner.as_ref().map(|interner| interner.lock().unwrap());
+        let (interned, strings) = match interner.as_mut() {
+            Some(interner) => {
+                let (coded, block) = interner.intern(df)?;
+                (Some(coded), Some(block))
+            },
+            None => (None, None),
+        };
+        let df = interned.as_ref().unwrap_or(df);
+        let zstd_writer = self.zstd_writer.read().unwrap();
+        let compression = match (compression, zstd_writer.as_ref()) {
+            (Some(IpcCompression::ZSTD), Some(dictionary)) => PayloadCompression::Dictionary(dictionary),
+            (compression, _) => PayloadCompression::Ipc(compression),
+        };
+        let (bytes, skipped) = match (&self.config.codec, &self.governor, compression) {
+            (Some(codec), _, _) => {
+                (scratch.encode_coded(df, codec.as_ref(), self.writer_id(), ttl, strings.as_deref())?, false)
+            },
+            (None, Some(governor), PayloadCompression::Ipc(Some(_)) | PayloadCompression::Dictionary(_)) => {
+                let mut governor = governor.lock().unwrap();
+                self.encode_adaptive(&mut governor, scratch, df, compression, ttl, strings.as_deref())?
+            },
+            _ => (scratch.encode_with(df, self.writer_id(), compression, ttl, strings.as_deref())?, false),
+        };
+        let bytes = match lineage {
+            Some(lineage) => scratch.append_lineage(lineage)?,
+            None => bytes,
+        };
+        let bytes = match part {
+            Some(part) => scratch.insert_part(part),
+            None => bytes,
+        };
+        let bytes = match key {
+            Some(key) => scratch.insert_routing_key(key)?,
+            None => bytes,
+        };
+        let (encode_time, size) = (started.elapsed(), bytes.len());
+        if let Some(max) = self.config.max_frame_bytes.filter(|max| size > *max) {
+            if let Some(interner) = interner.as_mut() {
+                interner.resync();
+            }
+            if part.is_some() {
+                return Err(QADataSwapError::LimitExceeded(format!(
+                    "Part of a split frame encodes to {} bytes, over max_frame_bytes {}",
+                    size, max
+                )));
+            }
+            drop((interner, zstd_writer));
+            // One more part than the size suggests leaves room for each part's schema
+            let parts = size.div_ceil(max) + 1;
+            return self.write_split(prepared, scratch, requested, ttl, lineage, key, parts);
+        }
+        if self.config.dedup && self.is_duplicate(&bytes[FRAME_HEADER_SIZE..]) {
+            self.write_counters.deduplicated.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        if let Some(pacer) = self.pacer.lock().unwrap().as_mut() {
+            pacer.acquire(bytes.len());
+        }
+        let admission = match (&self.quotas, key) {
+            (Some(quotas), Some(key)) => quotas.admit(self.raw.as_ref(), key, bytes.len() as u64).map(Some),
+            _ => Ok(None),
+        };
+        if let Err(e) = admission.and_then(|admission| {
+            self.write_dataframe_bytes(bytes)?;
+            if let Some(admission) = admission {
+                admission.publish();
+            }
+            Ok(())
+        }) {
+            if let Some(interner) = interner.as_mut() {
+                interner.resync();
+            }
+            return Err(e);
+        }
+        self.write_counters.frames.fetch_add(1, Ordering::Relaxed);
+        if let Some(histograms) = &self.histograms {
+            histograms.lock().unwrap().record(size, encode_time, true, self.config.time_source.instant());
+        }
+        if skipped {
+            self.write_counters.uncompressed.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Publish the prepared `df` as `parts` frames of one message, see
+    /// `with_max_frame_bytes`; validators and masking already ran on the whole
+    #[allow(clippy::too_many_arguments)]
+    fn write_split(&self, df: &DataFrame, scratch: &mut Scratch, compression: Option<IpcCompression>,
+                   ttl: Option<Duration>, lineage: Option<&Lineage>, key: Option<&RoutingKey>, parts: usize)
+                   -> Result<()> {
+        let ranges: Vec<_> = split::row_ranges(df.height(), parts).collect();
+        if ranges.len() < 2 {
+            return Err(QADataSwapError::LimitExceeded(format!(
+                "Frame of {} rows cannot be split under max_frame_bytes",
+                df.height()
+            )));
+        }
+        let count = u32::try_from(ranges.len())
+            .map_err(|_| QADataSwapError::LimitExceeded("Too many parts for a split frame".to_string()))?;
+        let message = self.next_message.fetch_add(1, Ordering::Relaxed);
+        for (index, (offset, len)) in ranges.into_iter().enumerate() {
+            let part = Part {
+                message,
+                index: index as u32,
+                count,
+            };
+            self.write_prepared(&df.slice(offset, len), scratch, compression, ttl, lineage, key, Some(part))?;
+        }
+        Ok(())
+    }
+
+    /// Encode `df` compressed, or uncompressed and flagged when the governor
+    /// finds compression does not pay; `true` for the latter
+    fn encode_adaptive<'s>(&self, governor: &mut CompressionGovernor, scratch: &'s mut Scratch, df: &DataFrame,
+                           compression: PayloadCompression<'_>, ttl: Option<Duration>, strings: Option<&[u8]>)
+                           -> Result<(&'s [u8], bool)> {
+        if governor.should_compress() {
+            let started = Instant::now();
+            let compressed = scratch.encode_with(df, self.writer_id(), compression, ttl, strings)?.len();
+            if governor.record(df.estimated_size(), compressed - FRAME_HEADER_SIZE, started.elapsed()) {
+                return Ok((scratch.bytes(), false));
+            }
+        }
+        scratch.encode_with(df, self.writer_id(), PayloadCompression::Ipc(None), ttl, strings)?;
+        Ok((scratch.add_flags(frame::FLAG_UNCOMPRESSED), true))
+    }
+
+    /// Train a zstd dictionary on representative frames and compress later
+    /// frames with it while the writer compresses with zstd, see `zdict`.
+    /// Returns the dictionary's id.
+    pub fn train_dictionary(&self, samples: &[DataFrame]) -> Result<u32> {
+        if !self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Only a writer trains dictionaries".to_string()));
+        }
+        let dictionary = zdict::train(samples)?;
+        let last_id = self.raw.get_meta(ZSTD_DICTIONARY_META_KEY).and_then(|id| id.parse().ok());
+        let mut current = self.zstd_writer.write().unwrap();
+        let trained = zdict::DictionaryWriter::publish(&self.config, current.take(), last_id, &dictionary)?;
+        let id = trained.id();
+        self.put_meta(ZSTD_DICTIONARY_META_KEY, &id.to_string())?;
+        *current = Some(trained);
+        Ok(id)
+    }
+
+    /// Change tunable settings on this side and, through the control block, on
+    /// every peer attached to the segment
+    pub fn update_policy(&self, update: PolicyUpdate) -> Result<()> {
+        let (version, current) = match self.raw.get_meta(POLICY_META_KEY) {
+            Some(entry) => PolicyUpdate::decode(&entry)?,
+            None => (0, PolicyUpdate::default()),
+        };
+        let merged = current.merge(update);
+        self.put_meta(POLICY_META_KEY, &merged.encode(version + 1))?;
+
+        let mut state = self.policy.lock().unwrap();
+        self.apply_policy(&mut state, version + 1, &merged);
+        Ok(())
+    }
+
+    /// Pick up a policy a peer published since the last check; returns the
+    /// compression to write with
+    fn refresh_policy(&self) -> Option<IpcCompression> {
+        let mut state = self.policy.lock().unwrap();
+        if state.due(self.config.time_source.instant()) {
+            // A malformed entry is reported to whoever calls update_policy next
+            if let Some(Ok((version, update))) = self.raw.get_meta(POLICY_META_KEY).map(|e| PolicyUpdate::decode(&e)) {
+                if version != state.version {
+                    self.apply_policy(&mut state, version, &update);
+                }
+            }
+        }
+        state.compression
+    }
+
+    fn apply_policy(&self, state: &mut policy::PolicyState, version: u64, update: &PolicyUpdate) {
+        state.version = version;
+        if let Some(compression) = update.compression {
+            state.compression = compression;
+        }
+        if let Some(limit) = update.rate_limit {
+            let time = &self.config.time_source;
+            *self.pacer.lock().unwrap() = limit.map(|limit| rate::TokenBucket::new(limit, Arc::clone(time)));
+        }
+        if let (Some(enabled), true) = (update.ack_mode, self.is_writer) {
+            self.raw.set_ack_mode(enabled);
+        }
+        if let (Some(policy), Some(validators)) = (update.validation, &self.config.validators) {
+            validators.set_policy(policy);
+        }
+    }
+
+    /// Whether `payload` matches the previous data frame; remembers it if not
+    fn is_duplicate(&self, payload: &[u8]) -> bool {
+        let digest: [u8; 32] = Sha256::digest(payload).into();
+        let mut last = self.last_digest.lock().unwrap();
+        if *last == Some(digest) {
+            return true;
+        }
+        *last = Some(digest);
+        false
+    }
+
+    /// Reserve the next slot for in-place writing
+    fn acquire_write_buffer(&self) -> Result<(*mut u8, usize)> {
+        if !self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Not a writer".to_string()));
+        }
+
+        self.raw
+            .acquire_write_buffer()
+            .ok_or_else(|| QADataSwapError::SharedMemory("Failed to acquire a write buffer".to_string()))
+    }
+
+    fn publish_acquired(&self, size: usize) -> Result<()> {
+        let result = self.raw.publish_acquired(size);
+        if result == backend::STATUS_OVERRUN {
+            return Err(QADataSwapError::Overrun(self.config.name.clone()));
+        }
+        if result != 0 {
+            return Err(QADataSwapError::SharedMemory("Failed to publish frame".to_string()));
+        }
+        self.wake_readers();
+        Ok(())
+    }
+
+    fn release_acquired(&self) {
+        self.raw.release_acquired();
+    }
+
+    /// Publish a SchemaChange ahead of the first frame whose schema differs from the last one
+    fn announce_schema(&self, schema: &SchemaRef) -> Result<()> {
+        let mut current = self.schema.lock().unwrap();
+        match current.as_ref() {
+            Some(previous) if previous == schema => return Ok(()),
+            Some(_) => {
+                let mut scratch = self.scratch();
+                let bytes = scratch.encode_control(&Event::SchemaChange(schema.clone()), self.writer_id())?;
+                self.write_dataframe_bytes(bytes)?;
+            },
+            None => {},
+        }
+        *current = Some(schema.clone());
+        Ok(())
+    }
+
+    /// Reader: deliver only data frames tagged with one of `keys`; control
+    /// frames always pass
+    pub fn set_key_filter<K: Into<RoutingKey>>(&self, keys: impl IntoIterator<Item = K>) {
+        *self.key_filter.write().unwrap() = Some(keys.into_iter().map(Into::into).collect());
+    }
+
+    pub fn clear_key_filter(&self) {
+        *self.key_filter.write().unwrap() = None;
+    }
+
+    /// Fail on a frame that has nulls, or lacks, a column `expect_non_null` names.
+    /// Uses the null counts recorded at write time when the frame has them.
+    fn check_non_null(&self, metadata: &FrameMetadata, df: &DataFrame) -> Result<()> {
+        for name in &self.non_null {
+            let frame = match metadata.sequence {
+                Some(sequence) => format!("frame {} from writer {}", sequence, metadata.writer_id),
+                None => format!("a frame from writer {}", metadata.writer_id),
+            };
+            let Ok(column) = df.column(name) else {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Column '{}' is expected to be non-null but is missing from {}", name, frame
+                )));
+            };
+            let nulls = metadata.null_count(name).unwrap_or(column.null_count() as u64);
+            if nulls > 0 {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Column '{}' has {} unexpected null(s) in {} of {} rows",
+                    name, nulls, frame, df.height()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reader side: fold `incoming` into the latest schema when coercing
+    fn track_schema(&self, incoming: &Schema) -> Result<Option<SchemaRef>> {
+        if self.config.read_policy != ReadPolicy::CoerceToLatestSchema {
+            return Ok(None);
+        }
+
+        let mut latest = self.schema.lock().unwrap();
+        let merged = match latest.as_ref() {
+            Some(current) => schema::merge_additive(current, incoming)?,
+            None => incoming.clone(),
+        };
+        let merged = Arc::new(merged);
+        *latest = Some(Arc::clone(&merged));
+        Ok(Some(merged))
+    }
+
+    /// Publish an existing Arrow IPC file by reference; readers memory-map it
+    fn publish_ipc_file(&self, path: &Path) -> Result<()> {
+        let (file, schema) = FileRef::open(path)?;
+        self.announce_schema(&schema)?;
+        // A header and a path, so no scratch reuse needed
+        let mut scratch = self.scratch();
+        self.write_dataframe_bytes(scratch.encode_file_ref(&file, self.writer_id()))?;
+        self.write_counters.frames.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Publish a data or control frame
+    fn write_event(&self, event: &Event) -> Result<()> {
+        match event {
+            Event::Data(df) => self.write_frame(df, None, None, None),
+            Event::Reconnected { .. } => {
+                Err(QADataSwapError::SharedMemory("Reconnected is reported to readers, not written".to_string()))
+            },
+            control => {
+                if let Event::SchemaChange(schema) = control {
+                    *self.schema.lock().unwrap() = Some(schema.clone());
+                }
+                // Control frames are a header and at most a schema, so no scratch reuse needed
+                let mut scratch = self.scratch();
+                let bytes = scratch.encode_control(control, self.writer_id())?;
+                self.write_dataframe_bytes(bytes)
+            },
+        }
+    }
+
+    fn read_frame(&self, timeout_ms: Option<i32>) -> Result<Option<(FrameMetadata, DataFrame)>> {
+        match self.next_frame(timeout_ms, true)? {
+            Some((metadata, Event::Data(df))) => Ok(Some((metadata, df))),
+            Some(_) => unreachable!("control frames are skipped for data reads"),
+            None => Ok(None),
+        }
+    }
+
+    /// Next frame as an event; with `data_only` control frames are consumed and skipped
+    fn next_frame(&self, timeout_ms: Option<i32>, data_only: bool) -> Result<Option<(FrameMetadata, Event)>> {
+        let mut held = self.held.lock().unwrap();
+        while let Some((metadata, event)) = held.pop_front() {
+            if !data_only || matches!(event, Event::Data(_)) {
+                drop(held);
+                self.sync_readiness();
+                return Ok(Some((metadata, event)));
+            }
+        }
+        drop(held);
+        let frame = self.next_ring_frame(timeout_ms, data_only);
+        self.sync_readiness();
+        frame
+    }
+
+    /// Reader: block until the writer publishes its first schema, e.g. to set up
+    /// typed downstream structures before the consume loop. Frames this takes
+    /// off the ring are still delivered by the reads that follow.
+    pub fn wait_for_schema(&self, timeout_ms: Option<i32>) -> Result<SchemaRef> {
+        let timeout = timeout_ms.unwrap_or(self.config.timeout_ms.unwrap_or(-1));
+        let deadline = (timeout >= 0).then(|| self.config.time_source.instant() + Duration::from_millis(timeout as u64));
+        loop {
+            let schema = self.held.lock().unwrap().iter().find_map(|(_, event)| match event {
+                Event::Data(df) => Some(df.schema().clone()),
+                Event::SchemaChange(schema) => Some(schema.clone()),
+                _ => None,
+            });
+            if let Some(schema) = schema {
+                return Ok(schema);
+            }
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(self.config.time_source.instant()).as_millis() as i32,
+                None => timeout,
+            };
+            let frame = self.next_ring_frame(Some(remaining), false)?.ok_or(QADataSwapError::Timeout)?;
+            let ended = matches!(frame.1, Event::Eos);
+            self.held.lock().unwrap().push_back(frame);
+            if ended {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Writer of '{}' ended the stream before publishing a schema",
+                    self.config.name
+                )));
+            }
+        }
+    }
+
+    fn next_ring_frame(&self, timeout_ms: Option<i32>, data_only: bool) -> Result<Option<(FrameMetadata, Event)>> {
+        let timeout = timeout_ms.unwrap_or(self.config.timeout_ms.unwrap_or(-1));
+        let deadline = (timeout >= 0).then(|| self.config.time_source.instant() + Duration::from_millis(timeout as u64));
+        let reconnect = self.config.read_policy == ReadPolicy::AutoReconnect;
+
+        loop {
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(self.config.time_source.instant()).as_millis() as i32,
+                None => timeout,
+            };
+            // Look for a recreated segment whenever the ring stays idle this long
+            let slice = match reconnect && !(0..=RECONNECT_POLL_MS).contains(&remaining) {
+                true => RECONNECT_POLL_MS,
+                false => remaining,
+            };
+            let next = match self.next_bytes(slice) {
+                Err(QADataSwapError::Timeout) if reconnect => match self.reconnect_if_recreated()? {
+                    Some(generation) if !data_only => {
+                        return Ok(Some((FrameMetadata::default(), Event::Reconnected { generation })))
+                    },
+                    Some(_) => continue,
+                    None if slice != remaining => continue,
+                    None => Err(QADataSwapError::Timeout),
+                },
+                next => next,
+            };
+            let Some((source, bytes)) = (match next {
+                Err(QADataSwapError::Timeout) => {
+                    self.events.emit("timeout", format_args!("no frame within {} ms", timeout));
+                    return Err(QADataSwapError::Timeout);
+                },
+                next => next?,
+            }) else {
+                return Ok(None);
+            };
+            // Group members share a cursor, so gaps are other members' frames
+            if std::ptr::eq(source, self) && self.config.consumer_group.is_none() {
+                self.events.observe_read(source.last_read_sequence());
+            }
+
+            self.refresh_policy();
+            let started = Instant::now();
+            match self.decode_frame(source, &bytes, data_only) {
+                Ok(Some((metadata, Event::Data(df)))) => {
+                    if let Some(histograms) = &self.histograms {
+                        let now = self.config.time_source.instant();
+                        histograms.lock().unwrap().record(bytes.len(), started.elapsed(), false, now);
+                    }
+                    let df = match &self.pipeline {
+                        Some(pipeline) => pipeline(df.lazy()).collect().map_err(QADataSwapError::Polars)?,
+                        None => df,
+                    };
+                    self.read_counters.frames.fetch_add(1, Ordering::Relaxed);
+                    return Ok(Some((metadata, Event::Data(df))));
+                },
+                Ok(Some(control)) => return Ok(Some(control)),
+                Ok(None) => continue,
+                Err(rejected) => self.dead_letter(rejected, &bytes)?,
+            }
+        }
+    }
+
+    /// Decode, check and coerce one frame; `None` for frames the read skips
+    fn decode_frame(&self, source: &SharedMemoryArena, bytes: &[u8], data_only: bool)
+                    -> std::result::Result<Option<(FrameMetadata, Event)>, Rejected> {
+        let (header, payload) = match FrameHeader::decode(bytes).map_err(Rejected::new(DeadLetterStage::Decode, None))? {
+            Some((header, payload)) => (Some(header), payload),
+            None => (None, bytes),
+        };
+        if header.as_ref().is_some_and(|h| h.is_expired_at(self.config.time_source.now_ns(h.clock))) {
+            self.read_counters.expired.fetch_add(1, Ordering::Relaxed);
+            return Ok(None);
+        }
+        let routing_key = match &header {
+            Some(header) => RoutingKey::decode(header, bytes).map_err(Rejected::new(DeadLetterStage::Decode, None))?,
+            None => None,
+        };
+        let part = match &header {
+            Some(header) => Part::decode(header, bytes).map_err(Rejected::new(DeadLetterStage::Decode, None))?,
+            None => None,
+        };
+        if let Some(keys) = self.key_filter.read().unwrap().as_ref() {
+            let control = header.as_ref().is_some_and(|h| h.kind.is_control());
+            if !control && routing_key.as_ref().is_none_or(|key| !keys.contains(key)) {
+                return Ok(None);
+            }
+        }
+        let sequence = source.last_read_sequence();
+        let metadata = match &header {
+            Some(header) => {
+                let (null_counts, lineage) =
+                    frame::decode_trailers(header, bytes).map_err(Rejected::new(DeadLetterStage::Decode, None))?;
+                FrameMetadata {
+                    null_counts,
+                    lineage,
+                    routing_key,
+                    ..FrameMetadata::from_header(header, sequence)
+                }
+            },
+            None => FrameMetadata { sequence, ..Default::default() },
+        };
+        let decode_failed = || Rejected::new(DeadLetterStage::Decode, Some(&metadata));
+        let schema_failed = || Rejected::new(DeadLetterStage::Schema, Some(&metadata));
+
+        let control = match header.as_ref().map(|h| h.kind) {
+            Some(FrameKind::Eos) => Some(Event::Eos),
+            Some(FrameKind::Heartbeat) => Some(Event::Heartbeat),
+            Some(FrameKind::Flush) => Some(Event::Flush),
+            Some(FrameKind::SchemaChange) => {
+                let schema = decode_dataframe(payload).map_err(decode_failed())?.schema().clone();
+                match self.track_schema(&schema).map_err(schema_failed())? {
+                    Some(latest) => Some(Event::SchemaChange(latest)),
+                    None => Some(Event::SchemaChange(schema)),
+                }
+            },
+            _ => None,
+        };
+        if let Some(event) = control {
+            return Ok((!data_only).then_some((metadata, event)));
+        }
+
+        let df = match (self.config.decode_cache, &header, sequence) {
+            (Some(max_bytes), Some(header), Some(sequence)) if header.flags & frame::FLAG_STRING_DICTIONARY == 0 => {
+                let segment = source.config.segment_name().map_err(decode_failed())?;
+                let key = decode_cache::CacheKey::new(segment, sequence, header);
+                let df = decode_cache::get_or_decode(key, max_bytes, || {
+                    let payload = source.decompress(Some(header), payload)?;
+                    Ok(self.decode_payload(source, Some(header.kind), &payload, None)?.unwrap_or_default())
+                });
+                self.apply_filter(df.map_err(decode_failed())?).map_err(decode_failed())?
+            },
+            _ => self.decode_data(source, header.as_ref(), payload).map_err(decode_failed())?,
+        };
+        // A split message is delivered with its first part's metadata
+        let (first, df) = match part {
+            Some(part) => {
+                let mut assembly = self.assembly.lock().unwrap();
+                let ring = std::ptr::from_ref(source) as usize;
+                let assembled = assembly.push(ring, part, metadata.clone(), df);
+                self.read_counters.incomplete.fetch_add(assembly.take_dropped(), Ordering::Relaxed);
+                match assembled.map_err(decode_failed())? {
+                    Some((first, df)) => (Some(first), df),
+                    None => return Ok(None),
+                }
+            },
+            None => (None, df),
+        };
+        let Some(df) = df else {
+            return Ok(None);
+        };
+        let df = masking::unmask(df, &self.config.column_policies).map_err(decode_failed())?;
+        let delivered = first.as_ref().unwrap_or(&metadata);
+        self.check_non_null(delivered, &df).map_err(schema_failed())?;
+        let df = match &self.config.validators {
+            Some(validators) => match validators.validate(df).map_err(schema_failed())? {
+                Verdict::Pass(df) => df,
+                Verdict::Reject(reason) => {
+                    return Err(Rejected::new(DeadLetterStage::Validation, Some(delivered))(
+                        QADataSwapError::SharedMemory(format!("Frame rejected by validators: {}", reason)),
+                    ))
+                },
+            },
+            None => df,
+        };
+        let df = match self.track_schema(df.schema()).map_err(schema_failed())? {
+            Some(latest) => schema::coerce(df, &latest).map_err(schema_failed())?,
+            None => df,
+        };
+        Ok(Some((first.unwrap_or(metadata), Event::Data(df))))
+    }
+
+    /// A data frame's rows past the reader's filter; `None` if none match or the
+    /// frame is interned against entries this reader never saw
+    fn decode_data(&self, source: &SharedMemoryArena, header: Option<&FrameHeader>, payload: &[u8])
+                   -> Result<Option<DataFrame>> {
+        let payload = source.decompress(header, payload)?;
+        let kind = header.map(|h| h.kind);
+        if header.is_none_or(|h| h.flags & frame::FLAG_STRING_DICTIONARY == 0) {
+            return self.decode_payload(source, kind, &payload, self.config.filter.as_ref());
+        }
+        let (strings, payload) = intern::split(&payload)?;
+        let Some(coded) = self.decode_payload(source, kind, payload, None)? else {
+            return Ok(None);
+        };
+        match source.dictionary.lock().unwrap().restore(strings, coded)? {
+            Some(df) => self.apply_filter(df),
+            None => {
+                self.read_counters.unresolved.fetch_add(1, Ordering::Relaxed);
+                Ok(None)
+            },
+        }
+    }
+
+    /// Codec of this ring's coded frames: the reader's own, or the one this
+    /// process knows under the name the writer published
+    fn codec(&self) -> Result<Arc<dyn Codec>> {
+        let mut resolved = self.codec.lock().unwrap();
+        if let Some(codec) = resolved.as_ref() {
+            return Ok(Arc::clone(codec));
+        }
+        let codec = match (&self.config.codec, self.raw.get_meta(CODEC_META_KEY)) {
+            (Some(codec), _) => Arc::clone(codec),
+            (None, Some(name)) => codec::lookup(&name).ok_or_else(|| {
+                QADataSwapError::SharedMemory(format!("Frame encoded with codec '{}', which is not registered", name))
+            })?,
+            (None, None) => {
+                return Err(QADataSwapError::SharedMemory("Coded frame, but the writer published no codec".to_string()))
+            },
+        };
+        *resolved = Some(Arc::clone(&codec));
+        Ok(codec)
+    }
+
+    /// The payload with any trained-dictionary compression undone
+    fn decompress<'p>(&self, header: Option<&FrameHeader>, payload: &'p [u8]) -> Result<Cow<'p, [u8]>> {
+        match header.filter(|h| h.flags & frame::FLAG_ZSTD_DICTIONARY != 0) {
+            Some(_) => Ok(Cow::Owned(self.zstd_reader.lock().unwrap().decompress(&self.config, payload)?)),
+            None => Ok(Cow::Borrowed(payload)),
+        }
+    }
+
+    /// Decode a data payload, applying `filter`; `None` if no row matches
+    fn decode_payload(&self, source: &SharedMemoryArena, kind: Option<FrameKind>, payload: &[u8],
+                      filter: Option<&Expr>) -> Result<Option<DataFrame>> {
+        let df = match (kind, filter) {
+            (Some(FrameKind::Tensor), _) => {
+                return Err(QADataSwapError::SharedMemory(
+                    "Tensor frame on a DataFrame channel; read it with SharedTensor".to_string(),
+                ))
+            },
+            (Some(FrameKind::Columns), Some(predicate)) => {
+                builder::decode_columns(payload)?.lazy().filter(predicate.clone()).collect()?
+            },
+            (Some(FrameKind::Columns), None) => builder::decode_columns(payload)?,
+            (Some(FrameKind::ColumnGroups), Some(predicate)) => {
+                parallel::decode_groups(payload)?.lazy().filter(predicate.clone()).collect()?
+            },
+            (Some(FrameKind::ColumnGroups), None) => parallel::decode_groups(payload)?,
+            (Some(FrameKind::Coded), Some(predicate)) => {
+                source.codec()?.decode(payload)?.lazy().filter(predicate.clone()).collect()?
+            },
+            (Some(FrameKind::Coded), None) => source.codec()?.decode(payload)?,
+            (Some(FrameKind::FileRef), Some(predicate)) => {
+                FileRef::decode(payload)?.read()?.lazy().filter(predicate.clone()).collect()?
+            },
+            (Some(FrameKind::FileRef), None) => FileRef::decode(payload)?.read()?,
+            (_, Some(predicate)) => return decode_filtered(payload, predicate),
+            (_, None) => decode_dataframe(payload)?,
+        };
+        if filter.is_some() && df.height() == 0 {
+            return Ok(None);
+        }
+        Ok(Some(df))
+    }
+
+    /// The reader's filter over an already decoded frame; `None` if no row matches
+    fn apply_filter(&self, df: DataFrame) -> Result<Option<DataFrame>> {
+        let Some(predicate) = &self.config.filter else {
+            return Ok(Some(df));
+        };
+        let df = df.lazy().filter(predicate.clone()).collect()?;
+        Ok((df.height() > 0).then_some(df))
+    }
+
+    /// Hand a frame the reader could not deliver to the dead-letter queue; without
+    /// one, validator rejects are skipped and other failures end the read
+    fn dead_letter(&self, rejected: Rejected, bytes: &[u8]) -> Result<()> {
+        match (&self.dlq, rejected.stage) {
+            (Some(dlq), _) => dlq.publish(rejected, bytes, &self.config.name),
+            (None, DeadLetterStage::Validation) => Ok(()),
+            (None, _) => Err(rejected.error),
+        }
+    }
+
+    /// Next frame's bytes and the ring (this one or the high lane) they came from
+    fn next_bytes(&self, timeout: i32) -> Result<Option<(&SharedMemoryArena, Vec<u8>)>> {
+        let (Some(high), Some(lanes)) = (&self.high_lane, &self.lanes) else {
+            return Ok(self.read_dataframe_bytes(Some(timeout))?.map(|bytes| (self, bytes)));
+        };
+        let deadline = (timeout >= 0).then(|| self.config.time_source.instant() + Duration::from_millis(timeout as u64));
+
+        loop {
+            for priority in lanes.order() {
+                let lane = match priority {
+                    Priority::High => &**high,
+                    Priority::Normal => self,
+                };
+                if !lane.raw.has_data() {
+                    continue;
+                }
+                match lane.read_dataframe_bytes(Some(0)) {
+                    Ok(Some(bytes)) => {
+                        lanes.served(priority);
+                        return Ok(Some((lane, bytes)));
+                    },
+                    Ok(None) | Err(QADataSwapError::Timeout) => {},
+                    Err(e) => return Err(e),
+                }
+            }
+
+            // Sleep on the normal ring, waking at least every poll interval for the high one
+            let slice = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(self.config.time_source.instant()) {
+                    Some(remaining) if !remaining.is_zero() => remaining.min(priority::LANE_POLL_INTERVAL),
+                    _ => return Err(QADataSwapError::Timeout),
+                },
+                None => priority::LANE_POLL_INTERVAL,
+            };
+            self.wait_with(slice.as_millis().max(1) as i32, |timeout| self.raw.wait_for_data(timeout));
+        }
+    }
+
+    /// The ring `priority` frames are written to
+    fn lane(&self, priority: Priority) -> Result<&SharedMemoryArena> {
+        match (priority, &self.high_lane) {
+            (Priority::Normal, _) => Ok(self),
+            (Priority::High, Some(lane)) => Ok(lane),
+            (Priority::High, None) => Err(QADataSwapError::SharedMemory(format!(
+                "Priority lanes are not enabled for '{}'", self.config.name
+            ))),
+        }
+    }
+
+    fn begin_batch(&self) -> Result<()> {
+        if !self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Not a writer".to_string()));
+        }
+
+        let result = self.raw.begin_batch();
+        if result != 0 {
+            return Err(QADataSwapError::SharedMemory("A batch is already open".to_string()));
+        }
+        Ok(())
+    }
+
+    fn commit_batch(&self) -> Result<()> {
+        let result = self.raw.commit_batch();
+        if result != 0 {
+            return Err(QADataSwapError::SharedMemory("Failed to commit batch".to_string()));
+        }
+        self.wake_readers();
+        Ok(())
+    }
+
+    fn abort_batch(&self) {
+        self.raw.abort_batch();
+    }
+
+    /// Sequence number of the last frame this reader consumed
+    pub fn last_read_sequence(&self) -> Option<u64> {
+        self.raw.last_read_sequence()
+    }
+
+    /// Frames this reader has delivered and skipped as expired
+    pub fn read_stats(&self) -> ReadStats {
+        ReadStats {
+            frames: self.read_counters.frames.load(Ordering::Relaxed),
+            expired: self.read_counters.expired.load(Ordering::Relaxed),
+            unresolved: self.read_counters.unresolved.load(Ordering::Relaxed),
+            incomplete: self.read_counters.incomplete.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Distributions recorded since `with_histograms` enabled them
+    pub fn histograms(&self) -> Option<FrameHistograms> {
+        self.histograms.as_ref().map(|histograms| histograms.lock().unwrap().histograms())
+    }
+
+    /// Counters of the validators attached with `with_validators`
+    pub fn validation_stats(&self) -> Option<ValidationStats> {
+        self.config.validators.as_ref().map(|validators| validators.stats())
+    }
+
+    /// Data frames this writer published and skipped as duplicates
+    pub fn write_stats(&self) -> WriteStats {
+        WriteStats {
+            frames: self.write_counters.frames.load(Ordering::Relaxed),
+            deduplicated: self.write_counters.deduplicated.load(Ordering::Relaxed),
+            uncompressed: self.write_counters.uncompressed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Whether a read would return a frame without blocking
+    pub fn has_data(&self) -> bool {
+        !self.is_writer
+            && (!self.held.lock().unwrap().is_empty()
+                || self.raw.has_data()
+                || self.high_lane.as_ref().is_some_and(|lane| lane.has_data()))
+    }
+
+    /// File descriptor that is readable while `has_data()` holds, for registering
+    /// the arena with epoll/mio/tokio. Created on first use; the writer signals
+    /// it as it publishes and reads clear it, so it never needs to be read.
+    ///
+    /// Only writers in this library signal it. After the writer restarts it is
+    /// signalled again from the reader's next read on.
+    #[cfg(target_os = "linux")]
+    pub fn readiness_fd(&self) -> Result<RawFd> {
+        if self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Writer has no readiness handle".to_string()));
+        }
+        if let Some(readiness) = self.readiness.get() {
+            return Ok(readiness.as_raw_fd());
+        }
+
+        let mut addresses = vec![readiness_address(&self.config)?];
+        if let Some(lane) = &self.high_lane {
+            addresses.push(readiness_address(&lane.config)?);
+        }
+        let readiness = ReadinessFd::new(addresses)?;
+        let readiness = self.readiness.get_or_init(|| readiness);
+        readiness.sync(|| self.has_data());
+        Ok(readiness.as_raw_fd())
+    }
+
+    /// Reader: bring the readiness handle, if any, in line with `has_data()`
+    fn sync_readiness(&self) {
+        #[cfg(target_os = "linux")]
+        if let Some(readiness) = self.readiness.get() {
+            readiness.sync(|| self.has_data());
+        }
+    }
+
+    /// Writer: signal the readiness handles of registered readers
+    fn wake_readers(&self) {
+        #[cfg(target_os = "linux")]
+        if let Some(host) = &self.readiness_host {
+            host.notify();
+        }
+    }
+
+    /// Move this reader to a retained frame. After a seek the reader tracks its own
+    /// position and no longer takes part in buffer recycling.
+    pub fn seek(&self, pos: SeekFrom) -> Result<()> {
+        if self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Writer cannot seek".to_string()));
+        }
+
+        if self.raw.seek(pos) != 0 {
+            let sequence = match pos {
+                SeekFrom::Sequence(sequence) => sequence,
+                _ => 0,
+            };
+            let (first, next) = self.retained_range();
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Sequence {} is not retained (available: {}..{})", sequence, first, next
+            )));
+        }
+        self.events.reset_position();
+        self.held.lock().unwrap().clear();
+        self.sync_readiness();
+        Ok(())
+    }
+
+    /// Move this reader to the first retained frame published at or after `timestamp`,
+    /// or to the live edge if every retained frame is older
+    pub fn seek_time(&self, timestamp: SystemTime) -> Result<()> {
+        if self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Writer cannot seek".to_string()));
+        }
+
+        let timestamp_us = timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+
+        let result = self.raw.seek_time(timestamp_us);
+        if result != 0 {
+            return Err(QADataSwapError::SharedMemory("Seek failed".to_string()));
+        }
+        self.events.reset_position();
+        self.held.lock().unwrap().clear();
+        self.sync_readiness();
+        Ok(())
+    }
+
+    /// Sequences currently addressable by `seek()`, as a half-open range
+    pub fn retained_range(&self) -> (u64, u64) {
+        self.raw.retained_range()
+    }
+
+    /// Every retained frame published within the last `window`, concatenated
+    /// into one DataFrame. Seeks like `seek_time`, so the reader carries on from
+    /// the newest frame collected.
+    pub fn collect_window(&self, window: Duration) -> Result<DataFrame> {
+        let since = self.config.time_source.now().checked_sub(window).unwrap_or(UNIX_EPOCH);
+        self.seek_time(since)?;
+        self.collect_retained()
+    }
+
+    /// Every retained frame merged into one DataFrame, oldest first, for late
+    /// joiners that want the history in one piece instead of replaying it frame
+    /// by frame. With `dedup_key` only the latest row per key is kept. Seeks
+    /// like `seek`, so the reader carries on from the newest frame merged.
+    pub fn compact(&self, dedup_key: Option<&str>) -> Result<DataFrame> {
+        self.seek(SeekFrom::Earliest)?;
+        let merged = self.collect_retained()?;
+        match dedup_key {
+            Some(key) if merged.height() > 0 => {
+                Ok(merged.unique_stable(Some(&[key.to_string()]), UniqueKeepStrategy::Last, None)?)
+            },
+            _ => Ok(merged),
+        }
+    }
+
+    /// Drain data frames up to the newest one retained when called; frames the
+    /// writer publishes meanwhile are left for the next read
+    fn collect_retained(&self) -> Result<DataFrame> {
+        let (_, end) = self.retained_range();
+        let mut merged: Option<DataFrame> = None;
+        while let Some((metadata, event)) = self.next_frame(Some(0), false)? {
+            if let Event::Data(df) = event {
+                match &mut merged {
+                    Some(merged) => {
+                        merged.vstack_mut(&df)?;
+                    },
+                    None => merged = Some(df),
+                }
+            }
+            if metadata.sequence.is_some_and(|sequence| sequence + 1 >= end) {
+                break;
+            }
+        }
+        let mut merged = merged.unwrap_or_default();
+        merged.rechunk_mut();
+        Ok(merged)
+    }
+
+    /// Seek past the last sequence `consumer` committed to `store`, returning it
+    pub fn resume(&self, store: &CursorStore, consumer: &str) -> Result<Option<u64>> {
+        let committed = store.load(&self.config.segment_name()?, consumer)?;
+        if let Some(sequence) = committed {
+            self.seek(SeekFrom::Sequence(sequence + 1))?;
+        }
+        Ok(committed)
+    }
+
+    /// Record the last frame read as processed by `consumer`. Call it once the
+    /// frame is fully handled so a restart neither skips nor repeats it.
+    pub fn commit_cursor(&self, store: &CursorStore, consumer: &str) -> Result<()> {
+        match self.last_read_sequence() {
+            Some(sequence) => store.store(&self.config.segment_name()?, consumer, sequence),
+            None => Ok(()),
+        }
+    }
+
+    /// Acknowledge every consumed frame up to and including `sequence`
+    pub fn ack(&self, sequence: u64) -> Result<()> {
+        if self.is_writer {
+            return Err(QADataSwapError::SharedMemory("Writer cannot ack".to_string()));
+        }
+
+        let result = self.raw.ack(sequence);
+        if result != 0 {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Cannot ack sequence {} before it has been read", sequence
+            )));
+        }
+        Ok(())
+    }
+
+    /// Frames published but not yet acknowledged (or not yet read outside ack mode)
+    pub fn unacked(&self) -> u64 {
+        self.raw.unacked()
+    }
+
+    /// Slot sizes, occupancy and high-water marks of this arena
+    pub fn memory_report(&self) -> Result<MemoryReport> {
+        let mut report = MemoryReport::default();
+        let result = self.raw.memory_report(&mut report);
+        if result != 0 {
+            return Err(QADataSwapError::NotConnected);
+        }
+        Ok(report)
+    }
+
+    /// Header fields, per-slot state, group cursors and lock states, for
+    /// debugging a channel without attaching gdb to the C++ core
+    pub fn debug_dump(&self) -> Result<ArenaDump> {
+        ArenaDump::capture(self.raw.as_ref(), self.config.segment_name()?, self.config.transport, self.is_writer)
+    }
+
+    /// Readers attached to this arena's segment with their pids and cursors, for
+    /// capacity planning; the first `MAX_READERS` (64) to attach are listed
+    pub fn readers(&self) -> Result<Vec<ReaderInfo>> {
+        let mut readers = Vec::new();
+        for index in 0..backend::MAX_READERS {
+            let mut dump = backend::ReaderDump::default();
+            match self.raw.dump_reader(index, &mut dump) {
+                0 => continue,
+                1 => readers.push(ReaderInfo {
+                    pid: dump.pid as u32,
+                    cursor: dump.cursor,
+                    attached_at: UNIX_EPOCH + Duration::from_micros(dump.attached_at_us),
+                    alive: dump.alive != 0,
+                }),
+                _ => return Err(QADataSwapError::NotConnected),
+            }
+        }
+        Ok(readers)
+    }
+
+    /// Recorded snapshots of this channel taken within `range`, oldest first,
+    /// e.g. `stats_history(incident - Duration::from_secs(1800)..incident)`
+    pub fn stats_history(&self, range: impl RangeBounds<SystemTime>) -> Result<Vec<StatsSnapshot>> {
+        let Some(history) = &self.config.stats_history else {
+            return Err(QADataSwapError::SharedMemory("Stats history is not configured".to_string()));
+        };
+        history.load(&self.config.segment_name()?, range)
+    }
+
+    /// Set a session metadata entry (trading date, universe version, ...) in the
+    /// segment's control block, visible to every process attached to it
+    pub fn put_meta(&self, key: &str, value: &str) -> Result<()> {
+        if self.raw.put_meta(key, Some(value)) != 0 {
+            return Err(QADataSwapError::LimitExceeded(format!(
+                "Cannot store metadata '{}': keys hold up to {} bytes, values {} and the table {} entries",
+                key, backend::META_KEY_SIZE - 1, backend::META_VALUE_SIZE - 1, backend::MAX_META_ENTRIES
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn get_meta(&self, key: &str) -> Option<String> {
+        self.raw.get_meta(key)
+    }
+
+    pub fn remove_meta(&self, key: &str) -> Result<()> {
+        if self.raw.put_meta(key, None) != 0 {
+            return Err(QADataSwapError::SharedMemory(format!("Cannot remove metadata '{}'", key)));
+        }
+        Ok(())
+    }
+
+    /// Snapshot of every metadata entry
+    pub fn meta(&self) -> BTreeMap<String, String> {
+        self.raw
+            .meta_keys()
+            .into_iter()
+            .filter_map(|key| Some((key.clone(), self.raw.get_meta(&key)?)))
+            .collect()
+    }
+
+    pub fn wait_for_data(&self, timeout_ms: Option<i32>) -> Result<()> {
+        let timeout = timeout_ms.unwrap_or(self.config.timeout_ms.unwrap_or(-1));
+        let result = self.wait_with(timeout, |timeout| self.raw.wait_for_data(timeout));
+
+        match result {
+            0 => Ok(()),
+            1 => Err(QADataSwapError::Timeout),
+            _ => Err(QADataSwapError::SharedMemory("Wait failed".to_string())),
+        }
+    }
+
+    /// Run a backend operation that blocks up to `timeout`, sleeping per the
+    /// configured `WaitStrategy` instead when it is not the backend's own
+    fn wait_with(&self, timeout: i32, mut op: impl FnMut(i32) -> i32) -> i32 {
+        if let WaitStrategy::Adaptive(adaptive) = &self.config.wait_strategy {
+            return adaptive.wait_until(&*self.raw, &*self.config.time_source, timeout, op);
+        }
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if let Some(uring) = &self.uring {
+            return uring.wait_until(&*self.raw, timeout, || op(0));
+        }
+        op(timeout)
+    }
+
+    pub fn notify_data_ready(&self) {
+        self.raw.notify_data_ready();
+        self.wake_readers();
+        if let (Some(relay), true) = (&self.config.relay, self.is_writer) {
+            let arena = self.config.segment_name().unwrap_or_else(|_| self.config.name.clone());
+            let _ = relay.publish(&arena, self.retained_range().1);
+        }
+    }
+
+    pub fn close(&self) {
+        if let Some(lane) = &self.high_lane {
+            lane.close();
+        }
+        self.raw.close();
+        self.events.detach(self.role());
+    }
+
+    fn role(&self) -> &'static str {
+        if self.is_writer {
+            "writer"
+        } else {
+            "reader"
+        }
+    }
+}
+
+impl Drop for SharedMemoryArena {
+    fn drop(&mut self) {
+        self.events.detach(self.role());
+        drop(self.supervisor.take());
+        drop(self.stats_recorder.take());
+    }
+}
+
+/// Abstract unix socket a segment's writer takes reader registrations on
+#[cfg(target_os = "linux")]
+fn readiness_address(config: &SharedMemoryConfig) -> Result<String> {
+    let segment = config.segment_name()?;
+    match config.transport {
+        Transport::SharedMemory => Ok(format!("qads_ready/{}", segment)),
+        // Segments of the same name in other processes are unrelated
+        Transport::InProcess => Ok(format!("qads_ready/{}/{}", std::process::id(), segment)),
+        #[cfg(feature = "rdma")]
+        Transport::Rdma(_) => Err(QADataSwapError::SharedMemory(
+            "Readiness handles need a SharedMemory or InProcess transport".to_string(),
+        )),
+    }
+}
+
+fn decode_dataframe(bytes: &[u8]) -> Result<DataFrame> {
+    read_ipc(bytes, None)
+}
+
+/// Polars panics on some malformed IPC files instead of erroring; where panics
+/// unwind, such a frame fails the read like any other corrupt frame
+fn read_ipc(bytes: &[u8], columns: Option<Vec<String>>) -> Result<DataFrame> {
+    let read = || IpcReader::new(std::io::Cursor::new(bytes)).with_columns(columns).finish();
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(read)) {
+        Ok(result) => result.map_err(QADataSwapError::Polars),
+        Err(_) => Err(QADataSwapError::SharedMemory("Corrupt frame: malformed Arrow IPC payload".to_string())),
+    }
+}
+
+/// Reusable encode buffer, so high-frequency writers don't allocate a fresh
+/// frame-sized Vec per write. Keeps the capacity of the largest frame encoded.
+#[derive(Debug, Default)]
+pub struct Scratch {
+    buffer: Vec<u8>,
+    clock: ClockSource,
+    time: Option<Arc<dyn Clock>>,
+}
+
+impl Scratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self {
+            buffer: Vec::with_capacity(bytes),
+            ..Default::default()
+        }
+    }
+
+    /// Stamp frame headers on `clock` instead of the wall clock
+    pub fn with_clock(mut self, clock: ClockSource) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Read the stamps' time from `time` instead of the system clock
+    pub fn with_time_source(mut self, time: Arc<dyn Clock>) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    fn header(&self, kind: FrameKind, writer_id: u32, payload_len: usize) -> FrameHeader {
+        let header = FrameHeader::new_with_clock(kind, writer_id, payload_len, self.clock);
+        match &self.time {
+            Some(time) => header.stamped_by(&**time),
+            None => header,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Encode `df` as a complete frame (header followed by the IPC payload)
+    pub fn encode(&mut self, df: &DataFrame, writer_id: u32) -> Result<&[u8]> {
+        self.encode_with(df, writer_id, PayloadCompression::Ipc(None), None, None)
+    }
+
+    /// The frame encoded last
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Set header `flags` on the frame encoded last
+    pub(crate) fn add_flags(&mut self, flags: u16) -> &[u8] {
+        let current = u16::from_le_bytes([self.buffer[6], self.buffer[7]]);
+        self.buffer[6..8].copy_from_slice(&(current | flags).to_le_bytes());
+        &self.buffer
+    }
+
+    /// Mark the data frame encoded last as `part` of a split message, right after the payload
+    pub(crate) fn insert_part(&mut self, part: Part) -> &[u8] {
+        let payload_len = u64::from_le_bytes(self.buffer[24..32].try_into().unwrap()) as usize;
+        let at = FRAME_HEADER_SIZE + payload_len;
+        self.buffer.splice(at..at, part.to_bytes());
+        self.add_flags(frame::FLAG_PART)
+    }
+
+    /// Tag the data frame encoded last with `key`, placed right after the payload
+    pub(crate) fn insert_routing_key(&mut self, key: &RoutingKey) -> Result<&[u8]> {
+        let payload_len = u64::from_le_bytes(self.buffer[24..32].try_into().unwrap()) as usize;
+        let at = FRAME_HEADER_SIZE + payload_len;
+        self.buffer.splice(at..at, key.to_bytes()?);
+        Ok(self.add_flags(frame::FLAG_ROUTING_KEY))
+    }
+
+    /// Append a lineage trailer to the data frame encoded last
+    pub(crate) fn append_lineage(&mut self, lineage: &Lineage) -> Result<&[u8]> {
+        lineage.encode_into(&mut self.buffer)?;
+        Ok(self.add_flags(frame::FLAG_LINEAGE))
+    }
+
+    /// `strings` is a dictionary block to put in front of the IPC payload
+    pub(crate) fn encode_with(&mut self, df: &DataFrame, writer_id: u32, compression: PayloadCompression<'_>,
+                              ttl: Option<Duration>, strings: Option<&[u8]>) -> Result<&[u8]> {
+        self.encode_frame(FrameKind::Data, Some(df), writer_id, compression, ttl, strings)
+    }
+
+    /// Encode `df` with `codec` as a `Coded` data frame; `strings` as for `encode_with`
+    pub(crate) fn encode_coded(&mut self, df: &DataFrame, codec: &dyn Codec, writer_id: u32, ttl: Option<Duration>,
+                               strings: Option<&[u8]>) -> Result<&[u8]> {
+        self.buffer.clear();
+        self.buffer.resize(FRAME_HEADER_SIZE, 0);
+        if let Some(block) = strings {
+            self.buffer.extend_from_slice(block);
+        }
+        codec.encode(df, &mut self.buffer)?;
+
+        let payload_len = self.buffer.len() - FRAME_HEADER_SIZE;
+        let mut header = self.header(FrameKind::Coded, writer_id, payload_len).with_ttl(ttl);
+        frame::encode_null_counts(df, &mut self.buffer);
+        header.flags |= frame::FLAG_NULL_COUNTS;
+        if strings.is_some() {
+            header.flags |= frame::FLAG_STRING_DICTIONARY;
+        }
+        self.buffer[..FRAME_HEADER_SIZE].copy_from_slice(&header.to_bytes());
+        Ok(&self.buffer)
+    }
+
+    /// Encode a frame naming an Arrow IPC file instead of carrying its data
+    pub(crate) fn encode_file_ref(&mut self, file: &FileRef, writer_id: u32) -> &[u8] {
+        self.buffer.clear();
+        self.buffer.resize(FRAME_HEADER_SIZE, 0);
+        file.encode_into(&mut self.buffer);
+        let payload_len = self.buffer.len() - FRAME_HEADER_SIZE;
+        let header = self.header(FrameKind::FileRef, writer_id, payload_len);
+        self.buffer[..FRAME_HEADER_SIZE].copy_from_slice(&header.to_bytes());
+        &self.buffer
+    }
+
+    /// Encode a control frame; a schema change carries its schema as an empty IPC file
+    pub(crate) fn encode_control(&mut self, event: &Event, writer_id: u32) -> Result<&[u8]> {
+        let schema_only = match event {
+            Event::SchemaChange(schema) => Some(DataFrame::empty_with_schema(schema)),
+            _ => None,
+        };
+        self.encode_frame(event.kind(), schema_only.as_ref(), writer_id, PayloadCompression::Ipc(None), None, None)
+    }
+
+    fn encode_frame(&mut self, kind: FrameKind, df: Option<&DataFrame>, writer_id: u32,
+                    payload_compression: PayloadCompression<'_>, ttl: Option<Duration>, strings: Option<&[u8]>)
+                    -> Result<&[u8]> {
+        let compression = match payload_compression {
+            PayloadCompression::Ipc(compression) => compression,
+            PayloadCompression::Dictionary(_) => None,
+        };
+        self.buffer.clear();
+        self.buffer.resize(FRAME_HEADER_SIZE, 0);
+        if let Some(block) = strings {
+            self.buffer.extend_from_slice(block);
+        }
+        let data = df.filter(|_| kind == FrameKind::Data);
+
+        #[cfg(feature = "parallel")]
+        let (kind, df) = match df {
+            Some(df) if kind == FrameKind::Data && parallel::should_split(df) => {
+                parallel::encode_groups(df, compression, &mut self.buffer)?;
+                (FrameKind::ColumnGroups, None)
+            },
+            df => (kind, df),
+        };
+
+        if let Some(df) = df {
+            let start = self.buffer.len() as u64;
+            let mut cursor = std::io::Cursor::new(&mut self.buffer);
+            cursor.set_position(start);
+            IpcWriter::new(&mut cursor)
+                .with_compression(compression)
+                .finish(&mut df.clone())
+                .map_err(QADataSwapError::Polars)?;
+        }
+
+        let dictionary = match payload_compression {
+            PayloadCompression::Dictionary(dictionary) if data.is_some() => Some(dictionary),
+            _ => None,
+        };
+        if let Some(dictionary) = dictionary {
+            dictionary.compress_tail(&mut self.buffer, FRAME_HEADER_SIZE)?;
+        }
+
+        let payload_len = self.buffer.len() - FRAME_HEADER_SIZE;
+        let mut header = self.header(kind, writer_id, payload_len).with_ttl(ttl);
+        if dictionary.is_some() {
+            header.flags |= frame::FLAG_ZSTD_DICTIONARY;
+        }
+        if let Some(df) = data {
+            frame::encode_null_counts(df, &mut self.buffer);
+            header.flags |= frame::FLAG_NULL_COUNTS;
+        }
+        if strings.is_some() {
+            header.flags |= frame::FLAG_STRING_DICTIONARY;
+        }
+        self.buffer[..FRAME_HEADER_SIZE].copy_from_slice(&header.to_bytes());
+        Ok(&self.buffer)
+    }
+}
+
+/// Decode only the rows matching `predicate`, or `None` if no row matches.
+/// The predicate's input columns are decoded first so non-matching frames are
+/// never fully materialized.
+fn decode_filtered(bytes: &[u8], predicate: &Expr) -> Result<Option<DataFrame>> {
+    let columns: Vec<String> = predicate
+        .clone()
+        .meta()
+        .root_names()
+        .into_iter()
+        .map(|name| name.to_string())
+        .collect();
+
+    let inputs = match columns.is_empty() {
+        true => decode_dataframe(bytes)?,
+        false => read_ipc(bytes, Some(columns))?,
+    };
+
+    let mask = inputs
+        .lazy()
+        .select([predicate.clone().alias("__mask")])
+        .collect()
+        .map_err(QADataSwapError::Polars)?;
+    let mask = mask
+        .column("__mask")
+        .and_then(|c| c.bool())
+        .map_err(QADataSwapError::Polars)?
+        .clone();
+
+    if !mask.any() {
+        return Ok(None);
+    }
+
+    let df = decode_dataframe(bytes)?;
+    df.filter(&mask).map(Some).map_err(QADataSwapError::Polars)
+}
+
+/// High-level interface for Polars DataFrames
+pub struct SharedDataFrame {
+    arena: SharedMemoryArena,
+}
+
+impl SharedDataFrame {
+    pub fn create_writer(config: SharedMemoryConfig) -> Result<Self> {
+        let mut arena = SharedMemoryArena::new(config)?;
+        arena.create_writer()?;
+        Ok(Self { arena })
+    }
+
+    /// Writer that unlinks whatever holds the name first, even a segment whose
+    /// writer is still alive; its processes keep their now-orphaned mapping
+    pub fn force_recreate(config: SharedMemoryConfig) -> Result<Self> {
+        Self::create_writer(config.with_open_mode(OpenMode::Truncate))
+    }
+
+    /// Writer that waits in standby until the current writer calls `handover()`
+    /// (or exits), then continues its segment and sequence numbering
+    pub fn create_standby(config: SharedMemoryConfig, timeout_ms: Option<i32>) -> Result<Self> {
+        Ok(Self { arena: SharedMemoryArena::standby(config, timeout_ms)? })
+    }
+
+    pub fn create_reader(config: SharedMemoryConfig) -> Result<Self> {
+        let mut arena = SharedMemoryArena::new(config)?;
+        arena.attach_reader()?;
+        Ok(Self { arena })
+    }
+
+    /// Subscribe to this writer's own frames from the same process, see
+    /// `SharedMemoryArena::reader_view`
+    pub fn reader_view(&self) -> Result<Self> {
+        Ok(Self {
+            arena: self.arena.reader_view()?,
+        })
+    }
+
+    /// Transform every frame this reader returns, e.g.
+    /// `reader.with_pipeline(|lf| lf.select([col("price")]))`
+    pub fn with_pipeline<F>(mut self, pipeline: F) -> Self
+    where
+        F: Fn(LazyFrame) -> LazyFrame + Send + Sync + 'static,
+    {
+        self.arena.pipeline = Some(Arc::new(pipeline));
+        self
+    }
+
+    /// Fail reads with a descriptive error as soon as a frame carries nulls in
+    /// any of `columns` (or lacks one), instead of passing them downstream
+    pub fn expect_non_null(mut self, columns: &[&str]) -> Self {
+        self.arena.non_null = columns.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Write a Polars DataFrame using IPC format. A 0-row frame is valid and
+    /// delivers just its schema.
+    pub fn write(&self, df: &DataFrame) -> Result<()> {
+        self.arena.write_frame(df, None, None, None)
+    }
+
+    /// Write to the `priority` lane, see `SharedMemoryConfig::with_priority_lanes`
+    pub fn write_priority(&self, df: &DataFrame, priority: Priority) -> Result<()> {
+        self.arena.lane(priority)?.write_frame(df, None, None, None)
+    }
+
+    /// Write a frame that readers skip, rather than deliver, once `ttl` has passed
+    pub fn write_with_ttl(&self, df: &DataFrame, ttl: Duration) -> Result<()> {
+        self.arena.write_frame(df, Some(ttl), None, None)
+    }
+
+    /// Write a frame readers see with `lineage` in its metadata, see `Lineage::derive`
+    pub fn write_with_lineage(&self, df: &DataFrame, lineage: &Lineage) -> Result<()> {
+        self.arena.write_frame(df, None, Some(lineage), None)
+    }
+
+    /// Write a frame tagged with `key`, for readers that `set_key_filter`
+    pub fn write_with_key(&self, df: &DataFrame, key: impl Into<RoutingKey>) -> Result<()> {
+        self.arena.write_frame(df, None, None, Some(&key.into()))
+    }
+
+    /// Deliver only data frames tagged with one of `keys`; others, untagged
+    /// ones included, are skipped without being decoded
+    pub fn set_key_filter<K: Into<RoutingKey>>(&self, keys: impl IntoIterator<Item = K>) {
+        self.arena.set_key_filter(keys)
+    }
+
+    /// Deliver every data frame again
+    pub fn clear_key_filter(&self) {
+        self.arena.clear_key_filter()
+    }
+
+    /// Write a data or control frame (EOS, heartbeat, schema change, flush)
+    pub fn write_event(&self, event: &Event) -> Result<()> {
+        self.arena.write_event(event)
+    }
+
+    /// Share an existing Arrow IPC (Feather v2) file without re-encoding it: the
+    /// frame carries only its path, and readers memory-map the file. The file
+    /// must stay in place and unmodified while readers may still receive it.
+    pub fn publish_ipc_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.arena.publish_ipc_file(path.as_ref())
+    }
+
+    /// Write using a caller-owned encode buffer, e.g. one per writer thread
+    pub fn write_with_scratch(&self, df: &DataFrame, scratch: &mut Scratch) -> Result<()> {
+        self.arena.write_frame_with(df, scratch, None, None, None)
+    }
+
+    /// Lay out primitive columns directly in the next ring slot, skipping IPC
+    /// encoding and the intermediate copy of `write()`
+    pub fn frame_builder(&self) -> Result<FrameBuilder<'_>> {
+        FrameBuilder::new(&self.arena)
+    }
+
+    /// Borrow at least `min_bytes` of the next ring slot to serialize a custom
+    /// format into; readers decode it with the channel's codec
+    pub fn lease_buffer(&self, min_bytes: usize) -> Result<BufferLease<'_>> {
+        BufferLease::new(&self.arena, min_bytes)
+    }
+
+    /// Release the segment to a writer waiting in `create_standby`, waiting
+    /// for one to attach first. Writes through this handle fail afterwards.
+    pub fn handover(&self, timeout_ms: Option<i32>) -> Result<()> {
+        self.arena.handover(timeout_ms)
+    }
+
+    /// Fill `rows` records of `layout` in place in the next slot and publish
+    /// them. The writer must be configured `with_codec` of the same layout.
+    pub fn write_records(&self, layout: &FixedLayout, rows: usize,
+                         fill: impl FnOnce(&mut FixedRecordsMut<'_>) -> Result<()>) -> Result<()> {
+        self.arena.write_records(layout, rows, fill)
+    }
+
+    /// Next frame's records without building a DataFrame, read into `buffer`,
+    /// which grows to the largest frame read and is reused across calls. Reads
+    /// the normal ring only, not a high-priority lane.
+    pub fn read_records<'b>(&self, layout: &'b FixedLayout, buffer: &'b mut Vec<u8>,
+                            timeout_ms: Option<i32>) -> Result<Option<FixedRecords<'b>>> {
+        self.arena.read_records(layout, buffer, timeout_ms)
+    }
+
+    /// Stage several frames that readers will only see together on `commit()`
+    pub fn begin_batch(&self) -> Result<BatchGuard<'_>> {
+        self.arena.begin_batch()?;
+        Ok(BatchGuard {
+            arena: &self.arena,
+            frames: 0,
+            finished: false,
+        })
+    }
+
+    /// Write a Polars LazyFrame
+    pub fn write_lazy(&self, lf: LazyFrame) -> Result<()> {
+        let df = lf.collect().map_err(QADataSwapError::Polars)?;
+        self.write(&df)
+    }
+
+    /// Read as Polars DataFrame using IPC format
+    pub fn read(&self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
+        Ok(self.read_with_metadata(timeout_ms)?.map(|(_, df)| df))
+    }
+
+    /// Read a DataFrame together with its writer identity, timestamp and sequence
+    pub fn read_with_metadata(&self, timeout_ms: Option<i32>) -> Result<Option<(FrameMetadata, DataFrame)>> {
+        self.arena.read_frame(timeout_ms)
+    }
+
+    /// Read the next frame including control frames, which `read()` skips
+    pub fn read_event(&self, timeout_ms: Option<i32>) -> Result<Option<Event>> {
+        Ok(self.arena.next_frame(timeout_ms, false)?.map(|(_, event)| event))
+    }
+
+    /// Block until the writer publishes its first schema, for readers that
+    /// attach before the first write; no frame is lost to the wait
+    pub fn wait_for_schema(&self, timeout_ms: Option<i32>) -> Result<SchemaRef> {
+        self.arena.wait_for_schema(timeout_ms)
+    }
+
+    /// Encoded size of the next frame without reading it, e.g. to size a
+    /// buffer or skip oversize frames; waits up to `timeout_ms` for one
+    pub fn peek_frame_size(&self, timeout_ms: Option<i32>) -> Result<usize> {
+        self.arena.peek_frame_size(timeout_ms)
+    }
+
+    /// Read as Polars LazyFrame
+    pub fn read_lazy(&self, timeout_ms: Option<i32>) -> Result<Option<LazyFrame>> {
+        match self.read(timeout_ms)? {
+            Some(df) => Ok(Some(df.lazy())),
+            None => Ok(None),
+        }
+    }
+
+    /// Write typed rows as one frame, see `message::SwapMessage`
+    pub fn write_rows<T: SwapMessage>(&self, rows: &[T]) -> Result<()> {
+        self.write(&T::to_dataframe(rows)?)
+    }
+
+    /// Read the next frame as typed rows
+    pub fn read_rows<T: SwapMessage>(&self, timeout_ms: Option<i32>) -> Result<Option<Vec<T>>> {
+        match self.read(timeout_ms)? {
+            Some(df) => T::from_dataframe(&df).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Write serializable rows as one frame, see `serde_rows`
+    #[cfg(feature = "serde")]
+    pub fn write_serde<T: serde::Serialize>(&self, rows: impl IntoIterator<Item = T>) -> Result<()> {
+        self.write(&serde_rows::to_dataframe(rows)?)
+    }
+
+    /// Read the next frame as rows of a serde type
+    #[cfg(feature = "serde")]
+    pub fn read_serde<T: serde::de::DeserializeOwned>(&self, timeout_ms: Option<i32>) -> Result<Option<Vec<T>>> {
+        match self.read(timeout_ms)? {
+            Some(df) => serde_rows::from_dataframe(&df).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Read ahead on a background thread, keeping up to `depth` decoded frames queued
+    pub fn spawn_prefetcher(self, depth: usize, options: ReaderThreadOptions) -> Result<Prefetcher> {
+        Prefetcher::spawn(self, depth, options)
+    }
+
+    /// Call `callback` with every frame on a background thread
+    pub fn on_data<F>(self, options: ReaderThreadOptions, callback: F) -> Result<Subscription>
+    where
+        F: FnMut(DataFrame) + Send + 'static,
+    {
+        Subscription::spawn(self, options, callback)
+    }
+
+    /// Sequence number of the last frame returned by `read`
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.arena.last_read_sequence()
+    }
+
+    pub fn seek(&self, pos: SeekFrom) -> Result<()> {
+        self.arena.seek(pos)
+    }
+
+    pub fn seek_time(&self, timestamp: SystemTime) -> Result<()> {
+        self.arena.seek_time(timestamp)
+    }
+
+    pub fn retained_range(&self) -> (u64, u64) {
+        self.arena.retained_range()
+    }
+
+    pub fn collect_window(&self, window: Duration) -> Result<DataFrame> {
+        self.arena.collect_window(window)
+    }
+
+    pub fn compact(&self, dedup_key: Option<&str>) -> Result<DataFrame> {
+        self.arena.compact(dedup_key)
+    }
+
+    pub fn resume(&self, store: &CursorStore, consumer: &str) -> Result<Option<u64>> {
+        self.arena.resume(store, consumer)
+    }
+
+    pub fn commit_cursor(&self, store: &CursorStore, consumer: &str) -> Result<()> {
+        self.arena.commit_cursor(store, consumer)
+    }
+
+    pub fn ack(&self, sequence: u64) -> Result<()> {
+        self.arena.ack(sequence)
+    }
+
+    pub fn unacked(&self) -> u64 {
+        self.arena.unacked()
+    }
+
+    pub fn memory_report(&self) -> Result<MemoryReport> {
+        self.arena.memory_report()
+    }
+
+    pub fn debug_dump(&self) -> Result<ArenaDump> {
+        self.arena.debug_dump()
+    }
+
+    pub fn readers(&self) -> Result<Vec<ReaderInfo>> {
+        self.arena.readers()
+    }
+
+    pub fn stats_history(&self, range: impl RangeBounds<SystemTime>) -> Result<Vec<StatsSnapshot>> {
+        self.arena.stats_history(range)
+    }
+
+    pub fn put_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.arena.put_meta(key, value)
+    }
+
+    pub fn get_meta(&self, key: &str) -> Option<String> {
+        self.arena.get_meta(key)
+    }
+
+    pub fn remove_meta(&self, key: &str) -> Result<()> {
+        self.arena.remove_meta(key)
+    }
+
+    pub fn meta(&self) -> BTreeMap<String, String> {
+        self.arena.meta()
+    }
+
+    pub fn read_stats(&self) -> ReadStats {
+        self.arena.read_stats()
+    }
+
+    pub fn write_stats(&self) -> WriteStats {
+        self.arena.write_stats()
+    }
+
+    pub fn histograms(&self) -> Option<FrameHistograms> {
+        self.arena.histograms()
+    }
+
+    pub fn validation_stats(&self) -> Option<ValidationStats> {
+        self.arena.validation_stats()
+    }
+
+    /// Retune rate limit, compression, backpressure or validation at runtime,
+    /// here and on every peer, see `PolicyUpdate`
+    pub fn update_policy(&self, update: PolicyUpdate) -> Result<()> {
+        self.arena.update_policy(update)
+    }
+
+    /// Compress later frames with a zstd dictionary trained on `samples`,
+    /// see `zdict`; returns the dictionary's id
+    pub fn train_dictionary(&self, samples: &[DataFrame]) -> Result<u32> {
+        self.arena.train_dictionary(samples)
+    }
+
+    pub fn has_data(&self) -> bool {
+        self.arena.has_data()
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn readiness_fd(&self) -> Result<RawFd> {
+        self.arena.readiness_fd()
+    }
+
+    pub fn wait_for_data(&self, timeout_ms: Option<i32>) -> Result<()> {
+        self.arena.wait_for_data(timeout_ms)
+    }
+
     pub fn notify_data_ready(&self) {
-        unsafe { qads_notify_data_ready(self.inner) };
+        self.arena.notify_data_ready();
     }
 
     pub fn close(&self) {
-        unsafe { qads_close(self.inner) };
+        self.arena.close();
     }
 }
 
-impl Drop for SharedMemoryArena {
+/// Frames staged by `begin_batch()`; dropping the guard without `commit()` discards them
+pub struct BatchGuard<'a> {
+    arena: &'a SharedMemoryArena,
+    frames: usize,
+    finished: bool,
+}
+
+impl<'a> BatchGuard<'a> {
+    /// Stage a DataFrame; it stays invisible to readers until commit.
+    /// A batch cannot hold more frames than the arena has buffers.
+    pub fn write(&mut self, df: &DataFrame) -> Result<()> {
+        self.arena.write_frame(df, None, None, None)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Number of frames staged so far
+    pub fn len(&self) -> usize {
+        self.frames
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Publish every staged frame at once
+    pub fn commit(mut self) -> Result<()> {
+        self.finished = true;
+        self.arena.commit_batch()
+    }
+
+    /// Discard every staged frame
+    pub fn abort(mut self) {
+        self.finished = true;
+        self.arena.abort_batch();
+    }
+}
+
+impl<'a> Drop for BatchGuard<'a> {
     fn drop(&mut self) {
-        if !self.inner.is_null() {
-            unsafe { qads_destroy_arena(self.inner) };
+        if !self.finished {
+            self.arena.abort_batch();
         }
     }
 }
 
-/// High-level interface for Polars DataFrames
-pub struct SharedDataFrame {
+/// Streaming interface for large datasets
+pub struct SharedDataStream {
     arena: SharedMemoryArena,
 }
 
-impl SharedDataFrame {
-    pub fn create_writer(config: SharedMemoryConfig) -> Result<Self> {
-        let mut arena = SharedMemoryArena::new(config)?;
-        arena.create_writer()?;
-        Ok(Self { arena })
+impl SharedDataStream {
+    pub fn create_writer(config: SharedMemoryConfig) -> Result<Self> {
+        let mut arena = SharedMemoryArena::new(config)?;
+        arena.create_writer()?;
+        Ok(Self { arena })
+    }
+
+    /// Writer that unlinks whatever holds the name first, even a segment whose
+    /// writer is still alive; its processes keep their now-orphaned mapping
+    pub fn force_recreate(config: SharedMemoryConfig) -> Result<Self> {
+        Self::create_writer(config.with_open_mode(OpenMode::Truncate))
+    }
+
+    /// Writer that waits in standby until the current writer calls `handover()`
+    /// (or exits), then continues its segment and sequence numbering
+    pub fn create_standby(config: SharedMemoryConfig, timeout_ms: Option<i32>) -> Result<Self> {
+        Ok(Self { arena: SharedMemoryArena::standby(config, timeout_ms)? })
+    }
+
+    pub fn create_reader(config: SharedMemoryConfig) -> Result<Self> {
+        let mut arena = SharedMemoryArena::new(config)?;
+        arena.attach_reader()?;
+        Ok(Self { arena })
+    }
+
+    /// Subscribe to this writer's own frames from the same process, see
+    /// `SharedMemoryArena::reader_view`
+    pub fn reader_view(&self) -> Result<Self> {
+        Ok(Self {
+            arena: self.arena.reader_view()?,
+        })
+    }
+
+    /// Transform every chunk before it reaches `read_chunk`, `iter_chunks` or `window`
+    pub fn with_pipeline<F>(mut self, pipeline: F) -> Self
+    where
+        F: Fn(LazyFrame) -> LazyFrame + Send + Sync + 'static,
+    {
+        self.arena.pipeline = Some(Arc::new(pipeline));
+        self
+    }
+
+    /// Fail reads with a descriptive error as soon as a frame carries nulls in
+    /// any of `columns` (or lacks one), instead of passing them downstream
+    pub fn expect_non_null(mut self, columns: &[&str]) -> Self {
+        self.arena.non_null = columns.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Write a chunk (DataFrame)
+    pub fn write_chunk(&self, df: &DataFrame) -> Result<()> {
+        self.arena.write_frame(df, None, None, None)
+    }
+
+    /// Write a chunk to the `priority` lane, see `SharedMemoryConfig::with_priority_lanes`
+    pub fn write_chunk_priority(&self, df: &DataFrame, priority: Priority) -> Result<()> {
+        self.arena.lane(priority)?.write_frame(df, None, None, None)
+    }
+
+    /// Write a chunk that readers skip, rather than deliver, once `ttl` has passed
+    pub fn write_chunk_with_ttl(&self, df: &DataFrame, ttl: Duration) -> Result<()> {
+        self.arena.write_frame(df, Some(ttl), None, None)
+    }
+
+    /// Write a chunk readers see with `lineage` in its metadata, see `Lineage::derive`
+    pub fn write_chunk_with_lineage(&self, df: &DataFrame, lineage: &Lineage) -> Result<()> {
+        self.arena.write_frame(df, None, Some(lineage), None)
+    }
+
+    /// Write a chunk tagged with `key`, for readers that `set_key_filter`
+    pub fn write_chunk_with_key(&self, df: &DataFrame, key: impl Into<RoutingKey>) -> Result<()> {
+        self.arena.write_frame(df, None, None, Some(&key.into()))
+    }
+
+    /// Deliver only data frames tagged with one of `keys`; others, untagged
+    /// ones included, are skipped without being decoded
+    pub fn set_key_filter<K: Into<RoutingKey>>(&self, keys: impl IntoIterator<Item = K>) {
+        self.arena.set_key_filter(keys)
+    }
+
+    /// Deliver every data frame again
+    pub fn clear_key_filter(&self) {
+        self.arena.clear_key_filter()
+    }
+
+    /// Write a data or control frame (EOS, heartbeat, schema change, flush)
+    pub fn write_event(&self, event: &Event) -> Result<()> {
+        self.arena.write_event(event)
+    }
+
+    /// Share an existing Arrow IPC (Feather v2) file without re-encoding it: the
+    /// frame carries only its path, and readers memory-map the file. The file
+    /// must stay in place and unmodified while readers may still receive it.
+    pub fn publish_ipc_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.arena.publish_ipc_file(path.as_ref())
+    }
+
+    /// Lay out primitive columns directly in the next ring slot
+    pub fn frame_builder(&self) -> Result<FrameBuilder<'_>> {
+        FrameBuilder::new(&self.arena)
+    }
+
+    /// Borrow at least `min_bytes` of the next ring slot to serialize a custom
+    /// format into; readers decode it with the channel's codec
+    pub fn lease_buffer(&self, min_bytes: usize) -> Result<BufferLease<'_>> {
+        BufferLease::new(&self.arena, min_bytes)
+    }
+
+    /// Release the segment to a writer waiting in `create_standby`, waiting
+    /// for one to attach first. Writes through this handle fail afterwards.
+    pub fn handover(&self, timeout_ms: Option<i32>) -> Result<()> {
+        self.arena.handover(timeout_ms)
+    }
+
+    /// Fill `rows` records of `layout` in place in the next slot and publish
+    /// them. The writer must be configured `with_codec` of the same layout.
+    pub fn write_records(&self, layout: &FixedLayout, rows: usize,
+                         fill: impl FnOnce(&mut FixedRecordsMut<'_>) -> Result<()>) -> Result<()> {
+        self.arena.write_records(layout, rows, fill)
+    }
+
+    /// Next frame's records without building a DataFrame, read into `buffer`,
+    /// which grows to the largest frame read and is reused across calls. Reads
+    /// the normal ring only, not a high-priority lane.
+    pub fn read_records<'b>(&self, layout: &'b FixedLayout, buffer: &'b mut Vec<u8>,
+                            timeout_ms: Option<i32>) -> Result<Option<FixedRecords<'b>>> {
+        self.arena.read_records(layout, buffer, timeout_ms)
+    }
+
+    /// Stage several chunks that readers will only see together on `commit()`
+    pub fn begin_batch(&self) -> Result<BatchGuard<'_>> {
+        self.arena.begin_batch()?;
+        Ok(BatchGuard {
+            arena: &self.arena,
+            frames: 0,
+            finished: false,
+        })
+    }
+
+    /// Read a chunk as DataFrame
+    pub fn read_chunk(&self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
+        Ok(self.read_chunk_with_metadata(timeout_ms)?.map(|(_, df)| df))
+    }
+
+    /// Read a chunk together with its writer identity, timestamp and sequence
+    pub fn read_chunk_with_metadata(&self, timeout_ms: Option<i32>) -> Result<Option<(FrameMetadata, DataFrame)>> {
+        self.arena.read_frame(timeout_ms)
+    }
+
+    /// Read the next chunk including control frames, which `read_chunk()` skips
+    pub fn read_event(&self, timeout_ms: Option<i32>) -> Result<Option<Event>> {
+        Ok(self.arena.next_frame(timeout_ms, false)?.map(|(_, event)| event))
+    }
+
+    /// Block until the writer publishes its first schema, for readers that
+    /// attach before the first write; no frame is lost to the wait
+    pub fn wait_for_schema(&self, timeout_ms: Option<i32>) -> Result<SchemaRef> {
+        self.arena.wait_for_schema(timeout_ms)
+    }
+
+    /// Encoded size of the next chunk without reading it; waits up to
+    /// `timeout_ms` for one
+    pub fn peek_frame_size(&self, timeout_ms: Option<i32>) -> Result<usize> {
+        self.arena.peek_frame_size(timeout_ms)
+    }
+
+    /// Sequence number of the last chunk returned by `read_chunk`
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.arena.last_read_sequence()
+    }
+
+    pub fn seek(&self, pos: SeekFrom) -> Result<()> {
+        self.arena.seek(pos)
+    }
+
+    pub fn seek_time(&self, timestamp: SystemTime) -> Result<()> {
+        self.arena.seek_time(timestamp)
+    }
+
+    pub fn retained_range(&self) -> (u64, u64) {
+        self.arena.retained_range()
+    }
+
+    pub fn collect_window(&self, window: Duration) -> Result<DataFrame> {
+        self.arena.collect_window(window)
+    }
+
+    pub fn compact(&self, dedup_key: Option<&str>) -> Result<DataFrame> {
+        self.arena.compact(dedup_key)
+    }
+
+    pub fn resume(&self, store: &CursorStore, consumer: &str) -> Result<Option<u64>> {
+        self.arena.resume(store, consumer)
+    }
+
+    pub fn commit_cursor(&self, store: &CursorStore, consumer: &str) -> Result<()> {
+        self.arena.commit_cursor(store, consumer)
+    }
+
+    pub fn ack(&self, sequence: u64) -> Result<()> {
+        self.arena.ack(sequence)
     }
 
-    pub fn create_reader(config: SharedMemoryConfig) -> Result<Self> {
-        let mut arena = SharedMemoryArena::new(config)?;
-        arena.attach_reader()?;
-        Ok(Self { arena })
+    pub fn unacked(&self) -> u64 {
+        self.arena.unacked()
     }
 
-    /// Write a Polars DataFrame using IPC format
-    pub fn write(&self, df: &DataFrame) -> Result<()> {
-        // Use Polars IPC serialization (which uses Arrow internally)
-        let mut buffer = Vec::new();
-        let mut cursor = std::io::Cursor::new(&mut buffer);
-        let mut df_clone = df.clone();
+    pub fn memory_report(&self) -> Result<MemoryReport> {
+        self.arena.memory_report()
+    }
 
-        IpcWriter::new(&mut cursor)
-            .finish(&mut df_clone)
-            .map_err(QADataSwapError::Polars)?;
+    pub fn debug_dump(&self) -> Result<ArenaDump> {
+        self.arena.debug_dump()
+    }
 
-        self.arena.write_dataframe_bytes(&buffer)
+    pub fn readers(&self) -> Result<Vec<ReaderInfo>> {
+        self.arena.readers()
     }
 
-    /// Write a Polars LazyFrame
-    pub fn write_lazy(&self, lf: LazyFrame) -> Result<()> {
-        let df = lf.collect().map_err(QADataSwapError::Polars)?;
-        self.write(&df)
+    pub fn stats_history(&self, range: impl RangeBounds<SystemTime>) -> Result<Vec<StatsSnapshot>> {
+        self.arena.stats_history(range)
     }
 
-    /// Read as Polars DataFrame using IPC format
-    pub fn read(&self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
-        match self.arena.read_dataframe_bytes(timeout_ms)? {
-            Some(bytes) => {
-                let cursor = std::io::Cursor::new(bytes);
-                let df = IpcReader::new(cursor)
-                    .finish()
-                    .map_err(QADataSwapError::Polars)?;
-                Ok(Some(df))
-            },
-            None => Ok(None),
-        }
+    pub fn put_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.arena.put_meta(key, value)
     }
 
-    /// Read as Polars LazyFrame
-    pub fn read_lazy(&self, timeout_ms: Option<i32>) -> Result<Option<LazyFrame>> {
-        match self.read(timeout_ms)? {
-            Some(df) => Ok(Some(df.lazy())),
-            None => Ok(None),
-        }
+    pub fn get_meta(&self, key: &str) -> Option<String> {
+        self.arena.get_meta(key)
     }
 
-    pub fn wait_for_data(&self, timeout_ms: Option<i32>) -> Result<()> {
-        self.arena.wait_for_data(timeout_ms)
+    pub fn remove_meta(&self, key: &str) -> Result<()> {
+        self.arena.remove_meta(key)
     }
 
-    pub fn notify_data_ready(&self) {
-        self.arena.notify_data_ready();
+    pub fn meta(&self) -> BTreeMap<String, String> {
+        self.arena.meta()
     }
 
-    pub fn close(&self) {
-        self.arena.close();
+    pub fn read_stats(&self) -> ReadStats {
+        self.arena.read_stats()
     }
-}
 
-/// Streaming interface for large datasets
-pub struct SharedDataStream {
-    arena: SharedMemoryArena,
-}
+    pub fn write_stats(&self) -> WriteStats {
+        self.arena.write_stats()
+    }
 
-impl SharedDataStream {
-    pub fn create_writer(config: SharedMemoryConfig) -> Result<Self> {
-        let mut arena = SharedMemoryArena::new(config)?;
-        arena.create_writer()?;
-        Ok(Self { arena })
+    pub fn histograms(&self) -> Option<FrameHistograms> {
+        self.arena.histograms()
     }
 
-    pub fn create_reader(config: SharedMemoryConfig) -> Result<Self> {
-        let mut arena = SharedMemoryArena::new(config)?;
-        arena.attach_reader()?;
-        Ok(Self { arena })
+    pub fn validation_stats(&self) -> Option<ValidationStats> {
+        self.arena.validation_stats()
     }
 
-    /// Write a chunk (DataFrame)
-    pub fn write_chunk(&self, df: &DataFrame) -> Result<()> {
-        // Use IPC format for streaming
-        let mut buffer = Vec::new();
-        let mut cursor = std::io::Cursor::new(&mut buffer);
-        let mut df_clone = df.clone();
+    /// Retune rate limit, compression, backpressure or validation at runtime,
+    /// here and on every peer, see `PolicyUpdate`
+    pub fn update_policy(&self, update: PolicyUpdate) -> Result<()> {
+        self.arena.update_policy(update)
+    }
 
-        IpcWriter::new(&mut cursor)
-            .finish(&mut df_clone)
-            .map_err(QADataSwapError::Polars)?;
+    /// Compress later frames with a zstd dictionary trained on `samples`,
+    /// see `zdict`; returns the dictionary's id
+    pub fn train_dictionary(&self, samples: &[DataFrame]) -> Result<u32> {
+        self.arena.train_dictionary(samples)
+    }
 
-        self.arena.write_dataframe_bytes(&buffer)
+    pub fn has_data(&self) -> bool {
+        self.arena.has_data()
     }
 
-    /// Read a chunk as DataFrame
-    pub fn read_chunk(&self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
-        match self.arena.read_dataframe_bytes(timeout_ms)? {
-            Some(bytes) => {
-                let cursor = std::io::Cursor::new(bytes);
-                let df = IpcReader::new(cursor)
-                    .finish()
-                    .map_err(QADataSwapError::Polars)?;
-                Ok(Some(df))
-            },
-            None => Ok(None),
-        }
+    #[cfg(target_os = "linux")]
+    pub fn readiness_fd(&self) -> Result<RawFd> {
+        self.arena.readiness_fd()
     }
 
     /// Iterator over chunks as DataFrames
     pub fn iter_chunks(&self) -> DataFrameChunkIterator<'_> {
         DataFrameChunkIterator { stream: self }
     }
+
+    /// Consume chunks and yield `aggs` per tumbling window of length `every`,
+    /// e.g. OHLCV bars from tick frames. Use `.period()` for sliding windows.
+    pub fn window(&self, every: Duration, aggs: Vec<Expr>) -> WindowedStream<'_> {
+        WindowedStream::new(self, WindowAggregator::new(every, aggs))
+    }
 }
 
 pub struct DataFrameChunkIterator<'a> {
@@ -347,39 +3434,111 @@ impl<'a> Iterator for DataFrameChunkIterator<'a> {
 #[cfg(feature = "async")]
 pub mod r#async {
     use super::*;
-    use tokio::time::{timeout, Duration};
+    use tokio::time::{sleep, Instant as TokioInstant};
+
+    const MAX_POLL_INTERVAL: Duration = Duration::from_millis(1);
 
     impl SharedDataFrame {
+        /// Read without blocking the executor: polls for readiness and sleeps in between
         pub async fn read_async(&self, timeout_duration: Option<Duration>) -> Result<Option<DataFrame>> {
-            let timeout_ms = timeout_duration.map(|d| d.as_millis() as i32);
-
-            match timeout_duration {
-                Some(duration) => {
-                    timeout(duration, tokio::task::spawn_blocking({
-                        let timeout_ms = timeout_ms;
-                        move || self.read(timeout_ms)
-                    })).await
-                    .map_err(|_| QADataSwapError::Timeout)?
-                    .map_err(|e| QADataSwapError::SharedMemory(e.to_string()))?
-                },
-                None => {
-                    tokio::task::spawn_blocking(move || self.read(None)).await
-                    .map_err(|e| QADataSwapError::SharedMemory(e.to_string()))?
+            let deadline = timeout_duration.map(|d| TokioInstant::now() + d);
+            let mut backoff = Duration::from_micros(10);
+
+            loop {
+                if self.has_data() {
+                    match self.read(Some(0)) {
+                        Err(QADataSwapError::Timeout) => {}, // claimed by another group member
+                        other => return other,
+                    }
+                }
+
+                if deadline.is_some_and(|deadline| TokioInstant::now() >= deadline) {
+                    return Err(QADataSwapError::Timeout);
                 }
+
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_POLL_INTERVAL);
             }
         }
 
+        /// Write a DataFrame without blocking the executor: while every buffer is
+        /// still held by readers, sleeps until one is freed
         pub async fn write_async(&self, df: &DataFrame) -> Result<()> {
-            let df = df.clone();
-            tokio::task::spawn_blocking(move || {
-                // Write would be implemented here
-                Ok(())
-            }).await
-            .map_err(|e| QADataSwapError::SharedMemory(e.to_string()))?
+            let mut backoff = Duration::from_micros(10);
+            while self.memory_report()?.free_buffers == 0 {
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_POLL_INTERVAL);
+            }
+            self.write(df)
+        }
+    }
+
+    /// Async counterpart of `wait_any`, timed on the runtime's clock rather
+    /// than the readers' time sources
+    pub async fn wait_any_async(readers: &[&SharedDataFrame], timeout_duration: Option<Duration>) -> Result<usize> {
+        let deadline = timeout_duration.map(|d| TokioInstant::now() + d);
+        let mut backoff = Duration::from_micros(10);
+
+        loop {
+            if let Some(idx) = readers.iter().position(|r| r.has_data()) {
+                return Ok(idx);
+            }
+
+            if deadline.is_some_and(|deadline| TokioInstant::now() >= deadline) {
+                return Err(QADataSwapError::Timeout);
+            }
+
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Block until any of `readers` has a frame ready and return its index, so one
+/// thread can serve several arenas (e.g. quotes, fills and control messages).
+/// The timeout and polling run on the first reader's time source only, so
+/// readers under a `MockClock` should all share it.
+pub fn wait_any(readers: &[&SharedDataFrame], timeout_ms: Option<i32>) -> Result<usize> {
+    if readers.is_empty() {
+        return Err(QADataSwapError::SharedMemory("wait_any needs at least one reader".to_string()));
+    }
+
+    let time = &readers[0].arena.config.time_source;
+    let deadline = timeout_ms
+        .filter(|ms| *ms >= 0)
+        .map(|ms| time.instant() + Duration::from_millis(ms as u64));
+    let mut backoff = Duration::from_micros(1);
+
+    loop {
+        if let Some(idx) = readers.iter().position(|r| r.has_data()) {
+            return Ok(idx);
+        }
+
+        if deadline.is_some_and(|deadline| time.instant() >= deadline) {
+            return Err(QADataSwapError::Timeout);
         }
+
+        time.sleep(backoff);
+        backoff = (backoff * 2).min(Duration::from_millis(1));
     }
 }
 
+/// Arenas currently present in `namespace` (or outside any namespace for `None`),
+/// by the name they were created with
+pub fn list_arenas(transport: Transport, namespace: Option<&str>) -> Vec<String> {
+    let mut names: Vec<String> = backend::list_segments(transport)
+        .into_iter()
+        .filter_map(|segment| match (segment.split_once(NAMESPACE_SEPARATOR), namespace) {
+            (Some((ns, name)), Some(wanted)) if ns == wanted => Some(name.to_string()),
+            (None, None) => Some(segment),
+            _ => None,
+        })
+        .filter(|name| !name.contains(priority::LANE_SEPARATOR))
+        .collect();
+    names.sort();
+    names
+}
+
 // Convenience functions
 pub fn create_shared_dataframe_writer(name: &str, size_mb: usize) -> Result<SharedDataFrame> {
     let config = SharedMemoryConfig::new(name).with_size_mb(size_mb);
@@ -406,8 +3565,7 @@ pub fn create_shared_datastream_reader(name: &str) -> Result<SharedDataStream> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread;
-    use std::time::Duration;
+    use crate::testing::test_config;
 
     #[test]
     fn test_config_creation() {
@@ -422,6 +3580,61 @@ mod tests {
         assert_eq!(config.timeout_ms, Some(1000));
     }
 
+    #[test]
+    fn test_config_open_mode() {
+        let config = SharedMemoryConfig::new("test");
+        assert_eq!(config.open_mode, OpenMode::Create);
+        assert_eq!(config.with_open_mode(OpenMode::OpenOrAttach).open_mode, OpenMode::OpenOrAttach);
+    }
+
+    #[test]
+    fn test_open_modes_respect_an_existing_segment() -> Result<()> {
+        let injector = testing::FaultInjector::new().crash_writer_at(0);
+        let writer = SharedDataFrame::create_writer(test_config("open_modes", 3).with_fault_injector(injector))?;
+        let fail = test_config("open_modes", 3).with_open_mode(OpenMode::FailIfExists);
+        assert!(matches!(SharedDataFrame::create_writer(fail.clone()), Err(QADataSwapError::AlreadyExists { .. })));
+
+        // The crashed writer's ring is only picked up with the geometry it was created with
+        let df = df! { "id" => [1i64] }?;
+        assert!(writer.write(&df).is_err());
+        let reattach = test_config("open_modes", 4).with_open_mode(OpenMode::OpenOrAttach);
+        assert!(matches!(SharedDataFrame::create_writer(reattach), Err(QADataSwapError::SharedMemory(_))));
+        let restarted = SharedDataFrame::create_writer(test_config("open_modes", 3).with_open_mode(OpenMode::OpenOrAttach))?;
+
+        // A live writer is never displaced, and a segment is never replaced under FailIfExists
+        let standby = test_config("open_modes", 3).with_open_mode(OpenMode::OpenOrAttach);
+        assert!(matches!(SharedDataFrame::create_writer(standby), Err(QADataSwapError::AlreadyExists { .. })));
+        drop(restarted);
+        assert!(SharedDataFrame::create_writer(fail)?.write(&df).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_ack_mode() {
+        let config = SharedMemoryConfig::new("test");
+        assert!(!config.ack_mode);
+        assert!(config.with_ack_mode(true).ack_mode);
+    }
+
+    #[test]
+    fn test_config_consumer_group() {
+        let config = SharedMemoryConfig::new("test");
+        assert_eq!(config.consumer_group, None);
+        assert_eq!(config.with_consumer_group("workers").consumer_group.as_deref(), Some("workers"));
+    }
+
+    #[test]
+    fn test_config_presets() {
+        let ticks = SharedMemoryConfig::new("ticks").preset(Preset::TickStream);
+        assert_eq!((ticks.size_mb, ticks.buffer_count, ticks.ack_mode), (32, 256, false));
+        assert!(matches!(ticks.wait_strategy, WaitStrategy::Adaptive(_)));
+
+        let bulk = SharedMemoryConfig::new("eod").preset(Preset::BulkTransfer).with_buffer_count(4);
+        assert_eq!((bulk.size_mb, bulk.buffer_count, bulk.ack_mode), (1024, 4, true));
+        assert_eq!(bulk.wait_strategy, WaitStrategy::Blocking);
+        assert_eq!(bulk.name, "eod");
+    }
+
     #[test]
     fn test_basic_dataframe_creation() -> Result<()> {
         // This test only checks that we can create DataFrames
@@ -463,4 +3676,479 @@ mod tests {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_filtered_decode_skips_non_matching_frames() -> Result<()> {
+        let df = df! {
+            "symbol" => ["AAPL", "MSFT", "AAPL"],
+            "price" => [1.0, 2.0, 3.0],
+        }.map_err(QADataSwapError::Polars)?;
+        let mut scratch = Scratch::new();
+        let frame = scratch.encode(&df, 1)?;
+        let (_, bytes) = FrameHeader::decode(frame)?.unwrap();
+
+        let aapl = decode_filtered(bytes, &col("symbol").eq(lit("AAPL")))?.unwrap();
+        assert_eq!(aapl.shape(), (2, 2));
+        assert!(decode_filtered(bytes, &col("symbol").eq(lit("TSLA")))?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_control_frames_and_empty_dataframes() -> Result<()> {
+        let config = test_config("lib_control_frames", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let df = df! { "id" => [1i64, 2], "price" => [1.5, 2.5] }?;
+        let empty = df.clear();
+        writer.write_event(&Event::Heartbeat)?;
+        writer.write(&empty)?;
+        writer.write_event(&Event::SchemaChange(df.schema().clone()))?;
+        writer.write(&df)?;
+        writer.write_event(&Event::Eos)?;
+
+        assert!(matches!(reader.read_event(Some(100))?, Some(Event::Heartbeat)));
+        let Some(Event::Data(received)) = reader.read_event(Some(100))? else { panic!("expected data") };
+        assert_eq!(received.shape(), (0, 2));
+        assert_eq!(received.schema(), empty.schema());
+        let Some(Event::SchemaChange(schema)) = reader.read_event(Some(100))? else { panic!("expected schema") };
+        assert_eq!(&schema, df.schema());
+
+        // Plain reads skip control frames
+        assert!(reader.read(Some(100))?.unwrap().equals(&df));
+        assert!(matches!(reader.read(Some(10)), Err(QADataSwapError::Timeout)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_change_and_coercion() -> Result<()> {
+        let config = test_config("lib_schema_change", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config.clone())?;
+        let coercing = SharedDataFrame::create_reader(config.with_read_policy(ReadPolicy::CoerceToLatestSchema))?;
+
+        let old = df! { "id" => [1i64, 2] }?;
+        let new = df! { "id" => [3i64], "price" => [1.5] }?;
+        writer.write(&old)?;
+        writer.write(&new)?;
+
+        // The writer announces the appended column before the first frame carrying it
+        assert!(matches!(reader.read_event(Some(100))?, Some(Event::Data(_))));
+        let Some(Event::SchemaChange(schema)) = reader.read_event(Some(100))? else { panic!("expected schema") };
+        assert_eq!(&schema, new.schema());
+        assert!(reader.read(Some(100))?.unwrap().equals(&new));
+
+        // Replaying under coercion backfills the column in frames written before it
+        coercing.seek(SeekFrom::Earliest)?;
+        while let Ok(Some(_)) = coercing.read_event(Some(10)) {}
+        coercing.seek(SeekFrom::Earliest)?;
+        let replayed = coercing.read(Some(100))?.unwrap();
+        assert_eq!(replayed.schema(), new.schema());
+        assert_eq!(replayed.column("price")?.null_count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_namespaces_partition_arenas() -> Result<()> {
+        let config = |ns: &str| test_config("ticks", 3)
+            .with_namespace(ns);
+        let prod = SharedDataFrame::create_writer(config("ns_prod"))?;
+        let _staging = SharedDataFrame::create_writer(config("ns_staging"))?;
+
+        assert_eq!(list_arenas(Transport::InProcess, Some("ns_prod")), vec!["ticks".to_string()]);
+        assert!(SharedDataFrame::create_reader(config("ns_dev")).is_err());
+        assert!(SharedDataFrame::create_writer(config("bad.ns")).is_err());
+
+        let reader = SharedDataFrame::create_reader(config("ns_prod"))?;
+        let df = df! { "id" => [1i64] }?;
+        prod.write(&df)?;
+        assert!(reader.read(Some(100))?.unwrap().equals(&df));
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_frames_are_skipped() -> Result<()> {
+        let clock = Arc::new(MockClock::default());
+        let config = test_config("ttl_stream", 4)
+            .with_time_source(clock.clone());
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let stale = df! { "id" => [1i64] }?;
+        let fresh = df! { "id" => [2i64] }?;
+        writer.write_with_ttl(&stale, Duration::from_millis(1))?;
+        clock.advance(Duration::from_millis(5));
+        writer.write_with_ttl(&fresh, Duration::from_secs(60))?;
+
+        assert!(reader.read(Some(100))?.unwrap().equals(&fresh));
+        assert_eq!(reader.read_stats(), ReadStats { frames: 1, expired: 1, unresolved: 0, incomplete: 0 });
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedup_skips_identical_consecutive_frames() -> Result<()> {
+        let config = test_config("dedup_stream", 8)
+            .with_dedup(true);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let snapshot = df! { "px" => [1.0, 2.0] }?;
+        let changed = df! { "px" => [1.0, 3.0] }?;
+        for df in [&snapshot, &snapshot, &changed, &changed, &snapshot] {
+            writer.write(df)?;
+        }
+
+        assert_eq!(writer.write_stats(), WriteStats { frames: 3, deduplicated: 2, uncompressed: 0 });
+        assert!(reader.read(Some(100))?.unwrap().equals(&snapshot));
+        assert!(reader.read(Some(100))?.unwrap().equals(&changed));
+        assert!(reader.read(Some(100))?.unwrap().equals(&snapshot));
+        assert!(!reader.has_data());
+        Ok(())
+    }
+
+    #[test]
+    fn test_adaptive_compression_skips_incompressible_frames() -> Result<()> {
+        let config = test_config("adaptive_compression", 8);
+        let policy = AdaptiveCompression::new(0.2, Duration::from_secs(1)).with_probe_interval(1);
+        let writer = SharedDataFrame::create_writer(
+            config.clone().with_compression(IpcCompression::ZSTD).with_adaptive_compression(policy),
+        )?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let repetitive = df! { "px" => vec![3500.0; 10_000] }?;
+        let noise = df! {
+            "px" => (0..10_000u64).map(|i| f64::from_bits(i.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 2)).collect::<Vec<_>>(),
+        }?;
+        for df in [&repetitive, &noise, &repetitive, &repetitive] {
+            writer.write(df)?;
+        }
+
+        // The frame after the incompressible one skips compression without probing
+        let skipped: Vec<bool> = (0..4)
+            .map(|_| Ok(reader.read_with_metadata(Some(100))?.unwrap().0.compression_skipped))
+            .collect::<Result<_>>()?;
+        assert_eq!(skipped, [false, true, true, false]);
+        assert_eq!(writer.write_stats().uncompressed, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_counts_and_expect_non_null() -> Result<()> {
+        let config = test_config("null_audit", 4);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let audited = SharedDataFrame::create_reader(config)?.expect_non_null(&["price", "volume"]);
+
+        writer.write(&df! { "price" => [1.0, 2.0], "volume" => [10i64, 20], "note" => [None::<&str>, Some("x")] }?)?;
+        writer.write(&df! { "price" => [Some(1.0), None, None], "volume" => [10i64, 20, 30] }?)?;
+
+        let (metadata, _) = audited.read_with_metadata(Some(100))?.unwrap();
+        assert_eq!(metadata.null_count("note"), Some(1));
+        assert_eq!(metadata.null_count("price"), Some(0));
+
+        let error = audited.read(Some(100)).unwrap_err().to_string();
+        assert!(error.contains("'price' has 2 unexpected null(s)"), "{}", error);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_view_echoes_without_consuming() -> Result<()> {
+        let config = test_config("echo_stream", 4);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        writer.write(&df! { "px" => [0.0] }?)?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        let view = writer.reader_view()?;
+        assert!(reader.reader_view().is_err());
+
+        writer.write(&df! { "px" => [1.0] }?)?;
+        writer.write(&df! { "px" => [2.0] }?)?;
+
+        // The view starts at the live edge and leaves every frame to the reader
+        let (echoed, df) = view.read_with_metadata(Some(100))?.unwrap();
+        assert_eq!((echoed.sequence, df.column("px")?.f64()?.get(0)), (Some(1), Some(1.0)));
+        assert_eq!(view.read_with_metadata(Some(100))?.unwrap().0.sequence, Some(2));
+        for expected in [0.0, 1.0, 2.0] {
+            assert_eq!(reader.read(Some(100))?.unwrap().column("px")?.f64()?.get(0), Some(expected));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_collision_is_diagnosed() -> Result<()> {
+        let config = test_config("taken_stream", 3);
+        let owner = SharedDataFrame::create_writer(config.clone())?;
+
+        match SharedDataFrame::create_writer(config.clone()) {
+            Err(QADataSwapError::AlreadyExists { name, owner_pid, created_at }) => {
+                assert_eq!((name.as_str(), owner_pid), ("taken_stream", std::process::id() as i32));
+                assert!(created_at > UNIX_EPOCH && created_at <= SystemTime::now());
+            },
+            other => panic!("expected AlreadyExists, got {:?}", other.err()),
+        }
+
+        let replacement = SharedDataFrame::force_recreate(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        replacement.write(&df! { "px" => [1.0] }?)?;
+        owner.write(&df! { "px" => [0.0] }?)?;
+        assert_eq!(reader.read(Some(100))?.unwrap().column("px")?.f64()?.get(0), Some(1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_reconnect_follows_recreated_segment() -> Result<()> {
+        let config = test_config("lib_reconnect", 4);
+        let first = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config.clone().with_read_policy(ReadPolicy::AutoReconnect))?;
+        first.write(&df! { "px" => [1.0] }?)?;
+        first.close();
+        drop(first);
+
+        let second = SharedDataFrame::create_writer(config)?;
+        second.write(&df! { "px" => [2.0] }?)?;
+        let Some(Event::Data(old)) = reader.read_event(Some(1000))? else { panic!("expected the old segment's frame") };
+        assert_eq!(old.column("px")?.f64()?.get(0), Some(1.0));
+        assert!(matches!(reader.read_event(Some(1000))?, Some(Event::Reconnected { .. })));
+        assert_eq!(reader.read(Some(1000))?.unwrap().column("px")?.f64()?.get(0), Some(2.0));
+        assert!(second.write_event(&Event::Reconnected { generation: 1 }).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_limit_and_listing() -> Result<()> {
+        let config = test_config("crowded", 4);
+        let writer = SharedDataFrame::create_writer(config.clone().with_max_readers(2))?;
+        let first = SharedDataFrame::create_reader(config.clone())?;
+        let second = SharedDataFrame::create_reader(config.clone())?;
+        match SharedDataFrame::create_reader(config.clone()) {
+            Err(QADataSwapError::TooManyReaders { name, max_readers }) => {
+                assert_eq!((name.as_str(), max_readers), ("crowded", 2));
+            },
+            other => panic!("expected TooManyReaders, got {:?}", other.err()),
+        }
+
+        writer.write(&df! { "px" => [1.0] }?)?;
+        first.read(Some(100))?;
+        let readers = writer.readers()?;
+        assert_eq!(readers.iter().map(|r| r.cursor).collect::<Vec<_>>(), [1, 0]);
+        assert!(readers.iter().all(|r| r.pid == std::process::id() && r.alive));
+
+        // A detached reader frees its seat
+        drop(second);
+        let third = SharedDataFrame::create_reader(config)?;
+        assert_eq!(third.readers()?.len(), 2);
+        assert_eq!(third.debug_dump()?.max_readers, Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_side_channel() -> Result<()> {
+        let config = test_config("meta_stream", 3);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        writer.put_meta("trading_date", "2024-05-01")?;
+        reader.put_meta("universe", "v42")?;
+        assert_eq!(reader.get_meta("trading_date").as_deref(), Some("2024-05-01"));
+        assert_eq!(writer.meta().len(), 2);
+
+        writer.remove_meta("universe")?;
+        assert_eq!(reader.get_meta("universe"), None);
+        assert!(matches!(writer.put_meta(&"k".repeat(64), "v"), Err(QADataSwapError::LimitExceeded(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_merges_retained_frames() -> Result<()> {
+        let config = test_config("compact_stream", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        for (symbols, prices) in [(["A", "B"], [1.0, 2.0]), (["A", "C"], [3.0, 4.0])] {
+            writer.write(&df! { "symbol" => symbols, "px" => prices }?)?;
+        }
+        writer.write_event(&Event::Heartbeat)?;
+        writer.write(&df! { "symbol" => ["B"], "px" => [5.0] }?)?;
+
+        let merged = reader.compact(None)?;
+        assert_eq!(merged.height(), 5);
+        assert_eq!(merged.first_col_n_chunks(), 1);
+
+        let latest = reader.compact(Some("symbol"))?;
+        assert_eq!(latest.column("symbol")?.str()?.into_no_null_iter().collect::<Vec<_>>(), ["A", "C", "B"]);
+        assert_eq!(latest.column("px")?.f64()?.into_no_null_iter().collect::<Vec<_>>(), [3.0, 4.0, 5.0]);
+
+        // Frames published after the collection are left for the next read
+        assert_eq!(reader.collect_window(Duration::from_secs(60))?.height(), 5);
+        writer.write(&df! { "symbol" => ["D"], "px" => [6.0] }?)?;
+        assert_eq!(reader.read(Some(0))?.map(|df| df.height()), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_windows_follow_the_time_source() -> Result<()> {
+        let clock = Arc::new(MockClock::default());
+        let config = test_config("window_stream", 8)
+            .with_retention(Duration::from_secs(70))
+            .with_time_source(clock.clone());
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        for id in 1i64..=3 {
+            writer.write(&df! { "id" => [id] }?)?;
+            clock.advance(Duration::from_secs(30));
+        }
+        // 90s in, the first frame is past retention
+        assert_eq!(reader.retained_range(), (1, 3));
+        let ids = |df: DataFrame| -> Result<Vec<i64>> { Ok(df.column("id")?.i64()?.into_no_null_iter().collect()) };
+        assert_eq!(ids(reader.collect_window(Duration::from_secs(45))?)?, [3]);
+        assert_eq!(ids(reader.collect_window(Duration::from_secs(75))?)?, [2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_any_reports_the_ready_reader() -> Result<()> {
+        let clock = Arc::new(MockClock::default());
+        let quotes_config = test_config("wait_any_quotes", 4).with_time_source(clock.clone());
+        let fills_config = test_config("wait_any_fills", 4).with_time_source(clock.clone());
+        let _quotes = SharedDataFrame::create_writer(quotes_config.clone())?;
+        let fills = SharedDataFrame::create_writer(fills_config.clone())?;
+        let quotes_reader = SharedDataFrame::create_reader(quotes_config)?;
+        let fills_reader = SharedDataFrame::create_reader(fills_config)?;
+
+        assert!(wait_any(&[], Some(0)).is_err());
+        assert!(matches!(wait_any(&[&quotes_reader, &fills_reader], Some(50)), Err(QADataSwapError::Timeout)));
+        assert!(clock.elapsed() >= Duration::from_millis(50));
+
+        fills.write(&df! { "id" => [1i64] }?)?;
+        assert_eq!(wait_any(&[&quotes_reader, &fills_reader], Some(0))?, 1);
+        fills_reader.read(Some(0))?;
+        assert!(matches!(wait_any(&[&quotes_reader, &fills_reader], Some(0)), Err(QADataSwapError::Timeout)));
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_writes_wait_without_blocking_the_runtime() -> Result<()> {
+        let config = test_config("async_backpressure", 2);
+        let idle_config = test_config("async_idle", 2);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let _idle = SharedDataFrame::create_writer(idle_config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        let idle_reader = SharedDataFrame::create_reader(idle_config)?;
+        let frame = df! { "id" => [1i64] }?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(async {
+            writer.write_async(&frame).await?;
+            writer.write_async(&frame).await?;
+            assert_eq!(r#async::wait_any_async(&[&idle_reader, &reader], Some(Duration::from_secs(1))).await?, 1);
+            assert!(matches!(
+                r#async::wait_any_async(&[&idle_reader], Some(Duration::from_millis(5))).await,
+                Err(QADataSwapError::Timeout)
+            ));
+
+            // The ring is full: the write has to yield for the read to free a slot
+            let drain = async {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                reader.read(Some(0))
+            };
+            let (written, drained) = tokio::join!(writer.write_async(&frame), drain);
+            written?;
+            assert!(drained?.is_some());
+            Ok(())
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_readiness_fd_follows_unread_frames() -> Result<()> {
+        let config = test_config("readiness_fd", 4);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        assert!(writer.readiness_fd().is_err());
+
+        let readable = |fd: RawFd| {
+            let mut poll = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+            unsafe { libc::poll(&mut poll, 1, 0) == 1 }
+        };
+        // A frame published before the handle existed counts too
+        let frame = df! { "id" => [1i64] }?;
+        writer.write(&frame)?;
+        let fd = reader.readiness_fd()?;
+        assert_eq!(reader.readiness_fd()?, fd);
+        assert!(readable(fd));
+        reader.read(Some(0))?;
+        assert!(!readable(fd));
+
+        writer.write(&frame)?;
+        assert!(readable(fd));
+        reader.read(Some(0))?;
+        assert!(!readable(fd));
+
+        writer.write(&frame)?;
+        writer.write(&frame)?;
+        reader.read(Some(0))?;
+        assert!(readable(fd));
+        reader.read(Some(0))?;
+        assert!(!readable(fd));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_for_schema_keeps_frames() -> Result<()> {
+        let config = test_config("await_schema", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        assert!(matches!(reader.wait_for_schema(Some(10)), Err(QADataSwapError::Timeout)));
+
+        let publisher = std::thread::spawn(move || -> Result<SharedDataFrame> {
+            std::thread::sleep(Duration::from_millis(20));
+            writer.write_event(&Event::Heartbeat)?;
+            writer.write(&df! { "symbol" => ["A"], "px" => [1.0] }?)?;
+            Ok(writer)
+        });
+        let schema = reader.wait_for_schema(Some(1000))?;
+        assert_eq!(schema.get("px"), Some(&DataType::Float64));
+        assert_eq!(reader.wait_for_schema(Some(0))?, schema);
+
+        // The heartbeat and the frame the wait took off the ring are still delivered
+        assert!(matches!(reader.read_event(Some(0))?, Some(Event::Heartbeat)));
+        assert_eq!(reader.read(Some(0))?.map(|df| df.schema().clone()), Some(schema));
+        publisher.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_pmem_needs_shared_memory() {
+        let config = test_config("pmem_in_process", 3)
+            .with_pmem("/dev/dax0.0");
+        assert!(SharedDataFrame::create_writer(config).is_err());
+    }
+
+    #[test]
+    fn test_peek_frame_size() -> Result<()> {
+        let config = test_config("peek_size", 4).with_size_mb(4);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        assert!(matches!(reader.peek_frame_size(Some(0)), Err(QADataSwapError::Timeout)));
+
+        writer.write(&df! { "px" => [1.0] }?)?;
+        writer.write(&df! { "px" => (0..10_000).map(f64::from).collect::<Vec<_>>() }?)?;
+        let small = reader.peek_frame_size(Some(100))?;
+        assert!(small > frame::FRAME_HEADER_SIZE && small < 4096);
+        assert_eq!(reader.peek_frame_size(Some(0))?, small);
+        assert_eq!(reader.read(Some(0))?.map(|df| df.height()), Some(1));
+
+        // Peeking follows a replaying reader's cursor
+        let large = reader.peek_frame_size(Some(0))?;
+        assert!(large > 10 * small);
+        reader.seek(SeekFrom::Earliest)?;
+        assert_eq!(reader.peek_frame_size(Some(0))?, small);
+        reader.read(Some(0))?;
+        assert_eq!(reader.peek_frame_size(Some(0))?, large);
+        assert_eq!(reader.read(Some(0))?.map(|df| df.height()), Some(10_000));
+        assert!(matches!(reader.peek_frame_size(Some(0)), Err(QADataSwapError::Timeout)));
+        assert!(writer.peek_frame_size(Some(0)).is_err());
+        Ok(())
+    }
+}
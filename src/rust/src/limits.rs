@@ -0,0 +1,175 @@
+//! Resource limits enforced before a segment is mapped, so a runaway loop
+//! can't exhaust /dev/shm for every other service on the host
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::{QADataSwapError, Result};
+
+/// Caps checked against everything this process has open. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Total segment bytes mapped by the process, this arena included
+    pub max_total_bytes: Option<u64>,
+    /// Arenas open in the process, this one included
+    pub max_arenas: Option<usize>,
+    /// Readers this process has attached to one arena name
+    pub max_readers_per_arena: Option<usize>,
+}
+
+impl ResourceLimits {
+    pub fn with_max_total_bytes(mut self, bytes: u64) -> Self {
+        self.max_total_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_max_arenas(mut self, count: usize) -> Self {
+        self.max_arenas = Some(count);
+        self
+    }
+
+    pub fn with_max_readers_per_arena(mut self, count: usize) -> Self {
+        self.max_readers_per_arena = Some(count);
+        self
+    }
+}
+
+/// What the process currently holds
+#[derive(Debug, Default)]
+struct Usage {
+    arenas: usize,
+    bytes: u64,
+    readers: BTreeMap<String, usize>,
+}
+
+static USAGE: Mutex<Usage> = Mutex::new(Usage {
+    arenas: 0,
+    bytes: 0,
+    readers: BTreeMap::new(),
+});
+
+/// An arena's share of the process usage, returned when it is dropped
+#[derive(Debug)]
+pub(crate) struct Lease {
+    name: String,
+    bytes: u64,
+    limits: ResourceLimits,
+    reader: bool,
+}
+
+impl Lease {
+    /// Count a new arena of `bytes` against the limits
+    pub(crate) fn acquire(name: &str, bytes: u64, limits: ResourceLimits) -> Result<Self> {
+        let mut usage = USAGE.lock().unwrap();
+        if let Some(max) = limits.max_arenas {
+            if usage.arenas >= max {
+                return Err(QADataSwapError::LimitExceeded(format!(
+                    "Cannot open arena '{}': {} of {} arenas already open", name, usage.arenas, max
+                )));
+            }
+        }
+        if let Some(max) = limits.max_total_bytes {
+            if usage.bytes.saturating_add(bytes) > max {
+                return Err(QADataSwapError::LimitExceeded(format!(
+                    "Cannot open arena '{}' ({} bytes): {} of {} bytes already mapped",
+                    name, bytes, usage.bytes, max
+                )));
+            }
+        }
+
+        usage.arenas += 1;
+        usage.bytes += bytes;
+        Ok(Self {
+            name: name.to_string(),
+            bytes,
+            limits,
+            reader: false,
+        })
+    }
+
+    /// Count this arena as a reader of its name
+    pub(crate) fn add_reader(&mut self) -> Result<()> {
+        if self.reader {
+            return Ok(());
+        }
+
+        let mut usage = USAGE.lock().unwrap();
+        let readers = usage.readers.entry(self.name.clone()).or_default();
+        if let Some(max) = self.limits.max_readers_per_arena {
+            if *readers >= max {
+                return Err(QADataSwapError::LimitExceeded(format!(
+                    "Cannot attach to arena '{}': {} of {} readers already attached",
+                    self.name, readers, max
+                )));
+            }
+        }
+        *readers += 1;
+        self.reader = true;
+        Ok(())
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let mut usage = USAGE.lock().unwrap();
+        usage.arenas -= 1;
+        usage.bytes -= self.bytes;
+        if self.reader {
+            if let Some(readers) = usage.readers.get_mut(&self.name) {
+                *readers -= 1;
+                if *readers == 0 {
+                    usage.readers.remove(&self.name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{SharedDataFrame, SharedMemoryConfig};
+
+    fn config(name: &str, limits: ResourceLimits) -> SharedMemoryConfig {
+        test_config(name, 3).with_resource_limits(limits)
+    }
+
+    #[test]
+    fn test_limits_reject_with_clear_errors() -> Result<()> {
+        let no_arenas = ResourceLimits::default().with_max_arenas(0);
+        assert!(matches!(
+            SharedDataFrame::create_writer(config("limits_arenas", no_arenas)),
+            Err(QADataSwapError::LimitExceeded(_))
+        ));
+
+        let too_small = ResourceLimits::default().with_max_total_bytes(1024);
+        assert!(matches!(
+            SharedDataFrame::create_writer(config("limits_bytes", too_small)),
+            Err(QADataSwapError::LimitExceeded(_))
+        ));
+
+        let one_reader = ResourceLimits::default().with_max_readers_per_arena(1);
+        let _writer = SharedDataFrame::create_writer(config("limits_readers", one_reader))?;
+        let first = SharedDataFrame::create_reader(config("limits_readers", one_reader))?;
+        let err = SharedDataFrame::create_reader(config("limits_readers", one_reader)).err().unwrap();
+        assert!(err.to_string().contains("1 of 1 readers"));
+
+        // Closing a reader frees its place
+        drop(first);
+        SharedDataFrame::create_reader(config("limits_readers", one_reader))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_byte_limit_does_not_overflow() {
+        // Whatever else the process has open, a huge arena is refused rather than wrapping around
+        let limits = ResourceLimits::default().with_max_total_bytes(u64::MAX - 1);
+        let huge = config("limits_overflow", limits).with_size_mb(usize::MAX);
+        assert!(matches!(SharedDataFrame::create_writer(huge), Err(QADataSwapError::LimitExceeded(_))));
+        assert!(matches!(
+            Lease::acquire("limits_overflow", u64::MAX, limits),
+            Err(QADataSwapError::LimitExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,154 @@
+//! Lineage: where a frame's data came from, carried with the frame
+//!
+//! A writer attaches a `Lineage` with `write_with_lineage`; readers get it back
+//! in `FrameMetadata::lineage`. A stage that reads a frame, transforms it and
+//! writes the result passes `Lineage::derive` the input's metadata, so the
+//! output names its input on top of everything the input named: provenance
+//! from a derived analytics frame back to the original sources.
+//!
+//! With `FLAG_LINEAGE` the frame ends in a trailer after the null counts:
+//!
+//! ```text
+//! u16 hops | hops x (u16 len, UTF-8 source | u64 sequence | u16 len, UTF-8 transform)
+//! ```
+//!
+//! A sequence of `u64::MAX` stands for none.
+
+use crate::{FrameMetadata, QADataSwapError, Result};
+
+/// One step of a frame's history, oldest first in `Lineage::hops`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineageHop {
+    /// Channel or external feed the input came from
+    pub source: String,
+    /// The input's sequence in `source`, if it has one
+    pub sequence: Option<u64>,
+    /// Name and version of the transform applied to the input, e.g. "vwap@2"
+    pub transform: String,
+}
+
+/// A frame's history, from the original sources to its immediate input
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lineage {
+    pub hops: Vec<LineageHop>,
+}
+
+impl Lineage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a hop, e.g. for a frame built from an external feed
+    pub fn with_hop(mut self, source: impl Into<String>, sequence: Option<u64>, transform: impl Into<String>) -> Self {
+        self.hops.push(LineageHop {
+            source: source.into(),
+            sequence,
+            transform: transform.into(),
+        });
+        self
+    }
+
+    /// Lineage for a frame `transform` derived from the frame `metadata`
+    /// describes, read from channel `source`
+    pub fn derive(metadata: &FrameMetadata, source: impl Into<String>, transform: impl Into<String>) -> Self {
+        metadata.lineage.clone().unwrap_or_default().with_hop(source, metadata.sequence, transform)
+    }
+
+    /// The original source, if any hop is recorded
+    pub fn origin(&self) -> Option<&LineageHop> {
+        self.hops.first()
+    }
+
+    pub(crate) fn encode_into(&self, out: &mut Vec<u8>) -> Result<()> {
+        let hops = u16::try_from(self.hops.len()).map_err(|_| too_long("hop count"))?;
+        out.extend_from_slice(&hops.to_le_bytes());
+        for hop in &self.hops {
+            put_str(out, &hop.source)?;
+            out.extend_from_slice(&hop.sequence.unwrap_or(u64::MAX).to_le_bytes());
+            put_str(out, &hop.transform)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut trailer = Trailer(bytes);
+        let mut lineage = Lineage::new();
+        for _ in 0..u16::from_le_bytes(trailer.take()?) {
+            let source = trailer.string()?;
+            let sequence = Some(u64::from_le_bytes(trailer.take()?)).filter(|&s| s != u64::MAX);
+            lineage = lineage.with_hop(source, sequence, trailer.string()?);
+        }
+        Ok(lineage)
+    }
+}
+
+struct Trailer<'a>(&'a [u8]);
+
+impl Trailer<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&[u8]> {
+        if self.0.len() < len {
+            return Err(corrupt("truncated lineage"));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = u16::from_le_bytes(self.take()?) as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| corrupt("lineage is not UTF-8"))
+    }
+}
+
+fn put_str(out: &mut Vec<u8>, value: &str) -> Result<()> {
+    let len = u16::try_from(value.len()).map_err(|_| too_long(value))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+fn corrupt(reason: &str) -> QADataSwapError {
+    QADataSwapError::SharedMemory(format!("Corrupt frame: {}", reason))
+}
+
+fn too_long(what: &str) -> QADataSwapError {
+    QADataSwapError::LimitExceeded(format!("Lineage field too long: {:.40}", what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+    use polars::df;
+
+    #[test]
+    fn test_lineage_propagates_through_stages() -> Result<()> {
+        let ticks = SharedDataFrame::create_writer(test_config("lineage_ticks", 4))?;
+        let tick_reader = SharedDataFrame::create_reader(test_config("lineage_ticks", 4))?;
+        let bars = SharedDataFrame::create_writer(test_config("lineage_bars", 4))?;
+        let bar_reader = SharedDataFrame::create_reader(test_config("lineage_bars", 4))?;
+
+        let df = df! { "px" => [3500.0, 3502.0] }?;
+        ticks.write_with_lineage(&df, &Lineage::new().with_hop("ctp", Some(9001), "normalize@1"))?;
+        let (tick, read) = tick_reader.read_with_metadata(Some(100))?.unwrap();
+        assert_eq!(read, df);
+
+        bars.write_with_lineage(&read.head(Some(1)), &Lineage::derive(&tick, "lineage_ticks", "bar_1m@3"))?;
+        let (bar, _) = bar_reader.read_with_metadata(Some(100))?.unwrap();
+        let lineage = bar.lineage.unwrap();
+        assert_eq!(lineage.origin().unwrap().sequence, Some(9001));
+        assert_eq!(lineage.hops[1].source, "lineage_ticks");
+        assert_eq!(lineage.hops[1].sequence, tick.sequence);
+        assert_eq!(lineage.hops[1].transform, "bar_1m@3");
+
+        // Frames written without lineage carry none
+        ticks.write(&df)?;
+        assert_eq!(tick_reader.read_with_metadata(Some(100))?.unwrap().0.lineage, None);
+        Ok(())
+    }
+}
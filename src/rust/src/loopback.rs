@@ -0,0 +1,1336 @@
+//! In-process transport: the arena protocol over heap buffers and a condvar
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicI32, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock, TryLockError};
+use std::time::{Duration, Instant};
+
+use crate::auth::Challenge;
+use crate::backend::{
+    GroupDump, HeaderDump, RawArena, ReaderDump, SegmentStatus, SlotDump, WakeWord, GROUP_NAME_SIZE,
+    MAX_CONSUMER_GROUPS, MAX_META_ENTRIES, MAX_READERS, META_KEY_SIZE, META_VALUE_SIZE, SLOT_EMPTY,
+    STATUS_ALREADY_EXISTS, STATUS_BUSY, STATUS_ERROR, STATUS_OK, STATUS_OVERRUN, STATUS_TIMEOUT,
+};
+use crate::{Advice, Clock, ClockSource, MemoryProtection, MemoryReport, OpenMode, SeekFrom};
+
+const SLOT_ALIGNMENT: usize = 64;
+const CANARY_SIZE: usize = 8;
+const CANARY_SEED: u64 = 0x5144_4153_4341_4e59;
+
+/// Segments by name, the in-process counterpart of /dev/shm
+fn registry() -> MutexGuard<'static, HashMap<String, Arc<Segment>>> {
+    static SEGMENTS: OnceLock<Mutex<HashMap<String, Arc<Segment>>>> = OnceLock::new();
+    SEGMENTS.get_or_init(Default::default).lock().unwrap()
+}
+
+pub(crate) fn list_segments() -> Vec<String> {
+    registry().keys().cloned().collect()
+}
+
+pub(crate) fn inspect_segment(name: &str) -> Option<SegmentStatus> {
+    let segment = registry().get(name).cloned()?;
+    let state = segment.state.lock().unwrap();
+    Some(SegmentStatus {
+        total_bytes: state.total_size as u64,
+        created_at_us: state.created_at_us,
+        generation: state.generation,
+        // Every endpoint lives in this process
+        writer_pid: if state.writer_active { std::process::id() as i32 } else { 0 },
+        writer_alive: state.writer_active as i32,
+        reader_count: state.readers as i32,
+        max_readers: state.max_readers as i32,
+    })
+}
+
+pub(crate) fn remove_segment(name: &str) {
+    registry().remove(name);
+}
+
+/// Heap slot a writer fills through a raw pointer while the state lock is not held
+struct SlotBuffer {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+impl SlotBuffer {
+    fn new(len: usize) -> Self {
+        let buffer = Box::into_raw(vec![0u8; len].into_boxed_slice());
+        Self {
+            ptr: NonNull::new(buffer as *mut u8).unwrap(),
+            len,
+        }
+    }
+
+    /// Only called for published slots, which the writer no longer touches
+    fn bytes(&self, size: usize) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), size.min(self.len)) }
+    }
+}
+
+impl Drop for SlotBuffer {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(self.ptr.as_ptr(), self.len))) };
+    }
+}
+
+unsafe impl Send for SlotBuffer {}
+
+#[derive(Default)]
+struct Slot {
+    /// Sequence held by the slot, `None` while empty or being written
+    sequence: Option<u64>,
+    timestamp_us: u64,
+    size: usize,
+    buffer: Option<SlotBuffer>,
+}
+
+struct Group {
+    name: String,
+    cursor: u64,
+    members: u64,
+}
+
+struct State {
+    total_size: usize,
+    buffer_count: usize,
+    buffer_size: usize,
+    max_buffer_count: usize,
+    /// Each slot buffer carries a canary word after `buffer_size`
+    canaries: bool,
+    slots: Vec<Slot>,
+    write_sequence: u64,
+    publish_sequence: u64,
+    read_sequence: u64,
+    ack_mode: bool,
+    ack_sequence: u64,
+    retention_us: u64,
+    groups_enabled: bool,
+    groups: Vec<Group>,
+    writer_active: bool,
+    readers: usize,
+    max_readers: usize,
+    reader_entries: Vec<Option<ReaderEntry>>,
+    created_at_us: u64,
+    /// See `SegmentStatus`
+    generation: u64,
+    auth: Option<Challenge>,
+    meta: BTreeMap<String, String>,
+    high_water_bytes: u64,
+    high_water_buffers: u64,
+    notifications: u64,
+}
+
+impl State {
+    fn new(total_size: usize, buffer_count: usize, max_buffer_count: usize, canaries: bool, now_us: u64) -> Self {
+        Self {
+            total_size,
+            buffer_count,
+            buffer_size: slot_size(total_size, buffer_count, canaries),
+            max_buffer_count,
+            canaries,
+            slots: (0..max_buffer_count).map(|_| Slot::default()).collect(),
+            write_sequence: 0,
+            publish_sequence: 0,
+            read_sequence: 0,
+            ack_mode: false,
+            ack_sequence: 0,
+            retention_us: 0,
+            groups_enabled: false,
+            groups: Vec::new(),
+            writer_active: false,
+            readers: 0,
+            max_readers: 0,
+            reader_entries: Vec::new(),
+            created_at_us: now_us,
+            generation: 0,
+            auth: None,
+            meta: BTreeMap::new(),
+            high_water_bytes: 0,
+            high_water_buffers: 0,
+            notifications: 0,
+        }
+    }
+
+    fn slot(&self, sequence: u64) -> &Slot {
+        &self.slots[(sequence % self.buffer_count as u64) as usize]
+    }
+
+    fn slot_mut(&mut self, sequence: u64) -> &mut Slot {
+        let count = self.buffer_count as u64;
+        &mut self.slots[(sequence % count) as usize]
+    }
+
+    /// Canary of slot `index`, as the C++ core computes it
+    fn canary(&self, index: usize) -> u64 {
+        CANARY_SEED ^ self.generation ^ ((index as u64) << 48)
+    }
+
+    fn canary_intact(&self, index: usize) -> bool {
+        if !self.canaries {
+            return true;
+        }
+        let Some(buffer) = self.slots[index].buffer.as_ref() else {
+            return true;
+        };
+        let end = self.buffer_size + CANARY_SIZE;
+        buffer.bytes(end).get(self.buffer_size..end) == Some(&self.canary(index).to_le_bytes()[..])
+    }
+
+    fn min_group_cursor(&self) -> Option<u64> {
+        self.groups.iter().filter(|g| g.members > 0).map(|g| g.cursor).min()
+    }
+
+    fn unacked(&self) -> u64 {
+        let done = match self.groups_enabled {
+            true => self.min_group_cursor().unwrap_or(self.write_sequence),
+            false if self.ack_mode => self.ack_sequence,
+            false => self.read_sequence,
+        };
+        self.write_sequence.saturating_sub(done)
+    }
+
+    /// Whether the slot for the next write is free: never lap the slowest
+    /// group, an unacknowledged frame, or an unread one
+    fn has_space(&self) -> bool {
+        self.unacked() < self.buffer_count as u64
+    }
+
+    /// Frames still retained at `now_us`
+    fn retained_range(&self, now_us: u64) -> (u64, u64) {
+        let next = self.publish_sequence;
+        let mut first = next.saturating_sub(self.buffer_count as u64);
+        if self.retention_us == 0 {
+            return (first, next);
+        }
+
+        // Drop frames that fell out of the time window
+        let cutoff = now_us.saturating_sub(self.retention_us);
+        while first < next {
+            let slot = self.slot(first);
+            if slot.sequence == Some(first) && slot.timestamp_us >= cutoff {
+                break;
+            }
+            first += 1;
+        }
+        (first, next)
+    }
+
+    /// Copy the frame with `sequence` out, if the slot still holds it
+    fn copy_frame(&self, sequence: u64, buffer: &mut [u8], actual_size: &mut usize) -> Option<i32> {
+        let slot = self.slot(sequence);
+        if slot.sequence != Some(sequence) {
+            return None;
+        }
+        if slot.size > buffer.len() {
+            return Some(STATUS_ERROR);
+        }
+
+        let bytes = slot.buffer.as_ref().map(|b| b.bytes(slot.size)).unwrap_or(&[]);
+        buffer[..bytes.len()].copy_from_slice(bytes);
+        *actual_size = bytes.len();
+        match self.canary_intact((sequence % self.buffer_count as u64) as usize) {
+            true => Some(STATUS_OK),
+            false => Some(STATUS_OVERRUN),
+        }
+    }
+}
+
+/// Whether a read status consumed the frame: overrun frames are skipped
+fn consumed(status: i32) -> bool {
+    status == STATUS_OK || status == STATUS_OVERRUN
+}
+
+fn slot_size(total_size: usize, buffer_count: usize, canaries: bool) -> usize {
+    let size = (total_size / buffer_count.max(1)) & !(SLOT_ALIGNMENT - 1);
+    match canaries {
+        true => size.saturating_sub(CANARY_SIZE),
+        false => size,
+    }
+}
+
+/// Registry entry of an attached reader, as in the shared header
+struct ReaderEntry {
+    attached_at_us: u64,
+    /// Next sequence the reader expects, advanced by its handle
+    cursor: Arc<AtomicU64>,
+    /// Cleared when the reader is abandoned, standing in for a dead pid
+    alive: bool,
+}
+
+struct Segment {
+    state: Mutex<State>,
+    changed: Condvar,
+    /// Publish futex for `WaitStrategy::IoUring` readers, as in the shared header
+    futex: AtomicU32,
+    futex_waiters: AtomicI32,
+}
+
+impl Segment {
+    fn new(state: State) -> Self {
+        Self {
+            state: Mutex::new(state),
+            changed: Condvar::new(),
+            futex: AtomicU32::new(0),
+            futex_waiters: AtomicI32::new(0),
+        }
+    }
+
+    fn notify(&self) {
+        self.changed.notify_all();
+        self.futex.fetch_add(1, Ordering::SeqCst);
+        if self.futex_waiters.load(Ordering::SeqCst) > 0 {
+            crate::wait::futex_wake(&self.futex);
+        }
+    }
+
+    /// Wait until `ready` holds; false on timeout (`timeout_ms < 0` waits forever)
+    fn wait_until<'a>(&'a self, mut state: MutexGuard<'a, State>, timeout_ms: i32,
+                      mut ready: impl FnMut(&mut State) -> bool) -> (MutexGuard<'a, State>, bool) {
+        let deadline = (timeout_ms >= 0).then(|| Instant::now() + Duration::from_millis(timeout_ms as u64));
+
+        while !ready(&mut state) {
+            state = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return (state, false);
+                    }
+                    self.changed.wait_timeout(state, remaining).unwrap().0
+                },
+                None => self.changed.wait(state).unwrap(),
+            };
+        }
+        (state, true)
+    }
+}
+
+#[derive(Default)]
+struct Handle {
+    segment: Option<Arc<Segment>>,
+    is_writer: bool,
+    max_buffer_count: usize,
+    canaries: bool,
+    seeking: bool,
+    seek_cursor: u64,
+    group: Option<String>,
+    last_read: Option<u64>,
+    /// Registry index and cursor, if the reader got an entry
+    reader: Option<(usize, Arc<AtomicU64>)>,
+    acquired: bool,
+    batch_open: bool,
+    batch_pending: usize,
+}
+
+impl Handle {
+    fn note_read(&mut self, sequence: u64) {
+        self.last_read = Some(sequence);
+        if let Some((_, cursor)) = &self.reader {
+            cursor.store(sequence + 1, Ordering::Relaxed);
+        }
+    }
+
+    fn seek_to(&mut self, sequence: u64) {
+        self.seek_cursor = sequence;
+        self.seeking = true;
+        if let Some((_, cursor)) = &self.reader {
+            cursor.store(sequence, Ordering::Relaxed);
+        }
+    }
+}
+
+/// One process-local endpoint of a named segment
+pub(crate) struct LoopbackArena {
+    name: String,
+    size: usize,
+    buffer_count: usize,
+    handle: Mutex<Handle>,
+    /// Stamps slots and applies retention, see `with_time_source`
+    time: Arc<dyn Clock>,
+}
+
+impl LoopbackArena {
+    pub(crate) fn new(name: &str, size: usize, buffer_count: usize, time: Arc<dyn Clock>) -> Self {
+        Self {
+            name: name.to_string(),
+            size,
+            buffer_count,
+            handle: Mutex::new(Handle {
+                max_buffer_count: buffer_count,
+                ..Default::default()
+            }),
+            time,
+        }
+    }
+
+    fn now_us(&self) -> u64 {
+        self.time.now_ns(ClockSource::Realtime) / 1000
+    }
+
+    fn segment(&self) -> Option<Arc<Segment>> {
+        self.handle.lock().unwrap().segment.clone()
+    }
+
+    /// The segment a reader reads from, whether it is replaying, and its consumer group
+    fn read_position(&self) -> Option<(Arc<Segment>, bool, Option<String>)> {
+        let mut handle = self.handle.lock().unwrap();
+        let segment = handle.segment.clone()?;
+        if handle.is_writer {
+            return None;
+        }
+
+        let mut state = segment.state.lock().unwrap();
+        let group = match state.groups_enabled && !handle.seeking {
+            true => Some(group_for(&mut handle, &mut state)?),
+            false => None,
+        };
+        drop(state);
+        let seeking = handle.seeking;
+        Some((segment, seeking, group))
+    }
+
+    fn read_seek(&self, segment: &Segment, buffer: &mut [u8], actual_size: &mut usize, timeout_ms: i32) -> i32 {
+        let mut cursor = self.handle.lock().unwrap().seek_cursor;
+        let state = segment.state.lock().unwrap();
+        let mut result = STATUS_ERROR;
+
+        let (_state, ready) = segment.wait_until(state, timeout_ms, |state| {
+            while cursor < state.publish_sequence {
+                match state.copy_frame(cursor, buffer, actual_size) {
+                    Some(status) => {
+                        result = status;
+                        return true;
+                    },
+                    // The writer lapped us: continue from the oldest frame still retained
+                    None => cursor = state.publish_sequence.saturating_sub(state.buffer_count as u64) + 1,
+                }
+            }
+            false
+        });
+        if !ready {
+            return STATUS_TIMEOUT;
+        }
+
+        let mut handle = self.handle.lock().unwrap();
+        if consumed(result) {
+            handle.note_read(cursor);
+            cursor += 1;
+        }
+        handle.seek_cursor = cursor;
+        result
+    }
+
+    fn read_group(&self, segment: &Segment, group: &str, buffer: &mut [u8], actual_size: &mut usize,
+                  timeout_ms: i32) -> i32 {
+        let state = segment.state.lock().unwrap();
+        let mut result = STATUS_ERROR;
+        let mut sequence = 0;
+
+        let (state, ready) = segment.wait_until(state, timeout_ms, |state| {
+            let Some(idx) = state.groups.iter().position(|g| g.name == group) else {
+                return true;
+            };
+            let cursor = state.groups[idx].cursor;
+            if cursor >= state.publish_sequence {
+                return false;
+            }
+
+            result = state.copy_frame(cursor, buffer, actual_size).unwrap_or(STATUS_ERROR);
+            if consumed(result) {
+                state.groups[idx].cursor += 1;
+                sequence = cursor;
+            }
+            true
+        });
+        drop(state);
+        if !ready {
+            return STATUS_TIMEOUT;
+        }
+
+        if result == STATUS_OK {
+            segment.notify();
+            self.handle.lock().unwrap().note_read(sequence);
+        }
+        result
+    }
+
+    fn read_shared(&self, segment: &Segment, buffer: &mut [u8], actual_size: &mut usize, timeout_ms: i32) -> i32 {
+        let state = segment.state.lock().unwrap();
+        let mut result = STATUS_ERROR;
+        let mut sequence = 0;
+
+        let (state, ready) = segment.wait_until(state, timeout_ms, |state| {
+            if state.read_sequence >= state.publish_sequence {
+                return false;
+            }
+
+            sequence = state.read_sequence;
+            result = state.copy_frame(sequence, buffer, actual_size).unwrap_or(STATUS_ERROR);
+            if consumed(result) {
+                state.read_sequence += 1;
+            }
+            true
+        });
+        drop(state);
+        if !ready {
+            return STATUS_TIMEOUT;
+        }
+
+        if result == STATUS_OK {
+            segment.notify();
+            self.handle.lock().unwrap().note_read(sequence);
+        }
+        result
+    }
+}
+
+/// Resolve the consumer group a reader in groups mode consumes through
+fn group_for(handle: &mut Handle, state: &mut State) -> Option<String> {
+    if handle.group.is_none() && join(handle, state, "default") != STATUS_OK {
+        return None;
+    }
+    handle.group.clone()
+}
+
+fn join(handle: &mut Handle, state: &mut State, group: &str) -> i32 {
+    if handle.is_writer || handle.group.is_some() || group.is_empty() || group.len() >= GROUP_NAME_SIZE {
+        return STATUS_ERROR;
+    }
+
+    let head = state.publish_sequence;
+    let idx = match state.groups.iter().position(|g| g.name == group) {
+        Some(idx) => idx,
+        None if state.groups.len() >= MAX_CONSUMER_GROUPS => return STATUS_ERROR,
+        None => {
+            // New groups start at the live edge
+            state.groups.push(Group { name: group.to_string(), cursor: head, members: 0 });
+            state.groups.len() - 1
+        },
+    };
+
+    // A dormant group resumes from its cursor, or the oldest frame still held
+    let buffer_count = state.buffer_count as u64;
+    let entry = &mut state.groups[idx];
+    if entry.members == 0 && head - entry.cursor >= buffer_count {
+        entry.cursor = head - buffer_count + 1;
+    }
+    entry.members += 1;
+
+    handle.group = Some(group.to_string());
+    STATUS_OK
+}
+
+impl RawArena for LoopbackArena {
+    fn create_writer(&self, mode: OpenMode) -> i32 {
+        let mut handle = self.handle.lock().unwrap();
+        if handle.segment.is_some() {
+            return STATUS_ERROR;
+        }
+
+        let mut segments = registry();
+        let mut generation = self.now_us();
+        if let Some(existing) = segments.get(&self.name) {
+            generation = generation.max(existing.state.lock().unwrap().generation + 1);
+            match mode {
+                OpenMode::Truncate => {},
+                OpenMode::FailIfExists => return STATUS_ALREADY_EXISTS,
+                OpenMode::Create | OpenMode::OpenOrAttach => {
+                    let mut state = existing.state.lock().unwrap();
+                    if state.writer_active {
+                        return STATUS_ALREADY_EXISTS;
+                    }
+                    if mode == OpenMode::OpenOrAttach {
+                        // Only the ring this writer was configured for, as the C++ core checks
+                        let buffer_size = slot_size(self.size, self.buffer_count, handle.canaries);
+                        if state.buffer_count != self.buffer_count || state.buffer_size != buffer_size {
+                            return STATUS_ERROR;
+                        }
+                        state.writer_active = true;
+                        drop(state);
+                        handle.segment = Some(Arc::clone(existing));
+                        handle.is_writer = true;
+                        return STATUS_OK;
+                    }
+                    // Leftover from a writer that is gone: safe to replace
+                },
+            }
+        }
+
+        let mut state = State::new(self.size, self.buffer_count, handle.max_buffer_count, handle.canaries, self.now_us());
+        state.writer_active = true;
+        state.generation = generation;
+        let segment = Arc::new(Segment::new(state));
+
+        segments.insert(self.name.clone(), Arc::clone(&segment));
+        handle.segment = Some(segment);
+        handle.is_writer = true;
+        STATUS_OK
+    }
+
+    fn attach_reader(&self) -> i32 {
+        let mut handle = self.handle.lock().unwrap();
+        if handle.segment.is_some() {
+            return STATUS_ERROR;
+        }
+
+        match registry().get(&self.name) {
+            Some(segment) => {
+                let mut guard = segment.state.lock().unwrap();
+                let state = &mut *guard;
+                // Abandoned readers' entries are reclaimed, as the C++ core does for dead pids
+                for entry in state.reader_entries.iter_mut() {
+                    if entry.as_ref().is_some_and(|entry| !entry.alive) {
+                        *entry = None;
+                        state.readers -= 1;
+                    }
+                }
+                if state.max_readers > 0 && state.reader_entries.iter().flatten().count() >= state.max_readers {
+                    return STATUS_BUSY;
+                }
+
+                state.readers += 1;
+                let cursor = Arc::new(AtomicU64::new(state.read_sequence));
+                let entry = ReaderEntry {
+                    attached_at_us: self.now_us(),
+                    cursor: Arc::clone(&cursor),
+                    alive: true,
+                };
+                let index = match state.reader_entries.iter().position(Option::is_none) {
+                    Some(index) => Some(index),
+                    None if state.reader_entries.len() < MAX_READERS => {
+                        state.reader_entries.push(None);
+                        Some(state.reader_entries.len() - 1)
+                    },
+                    None => None,
+                };
+                if let Some(index) = index {
+                    state.reader_entries[index] = Some(entry);
+                    handle.reader = Some((index, cursor));
+                }
+                drop(guard);
+                handle.segment = Some(Arc::clone(segment));
+                handle.is_writer = false;
+                STATUS_OK
+            },
+            None => STATUS_ERROR,
+        }
+    }
+
+    fn write(&self, data: &[u8]) -> i32 {
+        let Some((slot, capacity)) = self.acquire_write_buffer() else {
+            return STATUS_ERROR;
+        };
+        if data.len() > capacity {
+            self.release_acquired();
+            return STATUS_ERROR;
+        }
+
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), slot, data.len()) };
+        self.publish_acquired(data.len())
+    }
+
+    fn read(&self, buffer: &mut [u8], actual_size: &mut usize, timeout_ms: i32) -> i32 {
+        let Some((segment, seeking, group)) = self.read_position() else {
+            return STATUS_ERROR;
+        };
+
+        match (seeking, group) {
+            (true, _) => self.read_seek(&segment, buffer, actual_size, timeout_ms),
+            (false, Some(group)) => self.read_group(&segment, &group, buffer, actual_size, timeout_ms),
+            (false, None) => self.read_shared(&segment, buffer, actual_size, timeout_ms),
+        }
+    }
+
+    fn peek_size(&self, size: &mut usize, timeout_ms: i32) -> i32 {
+        let Some((segment, seeking, group)) = self.read_position() else {
+            return STATUS_ERROR;
+        };
+        let seek_cursor = self.handle.lock().unwrap().seek_cursor;
+
+        let state = segment.state.lock().unwrap();
+        let (_state, ready) = segment.wait_until(state, timeout_ms, |state| {
+            let cursor = match (seeking, &group) {
+                (true, _) => seek_cursor,
+                (false, Some(group)) => match state.groups.iter().find(|g| &g.name == group) {
+                    Some(group) => group.cursor,
+                    None => return false,
+                },
+                (false, None) => state.read_sequence,
+            };
+            if cursor >= state.publish_sequence {
+                return false;
+            }
+            // A lapped frame is replaced by one at most a slot long
+            let slot = state.slot(cursor);
+            *size = match slot.sequence == Some(cursor) {
+                true => slot.size,
+                false => state.buffer_size,
+            };
+            true
+        });
+        match ready {
+            true => STATUS_OK,
+            false => STATUS_TIMEOUT,
+        }
+    }
+
+    fn last_read_sequence(&self) -> Option<u64> {
+        self.handle.lock().unwrap().last_read
+    }
+
+    fn has_data(&self) -> bool {
+        let mut handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.clone() else {
+            return false;
+        };
+        if handle.is_writer {
+            return false;
+        }
+
+        let mut state = segment.state.lock().unwrap();
+        if handle.seeking {
+            return handle.seek_cursor < state.publish_sequence;
+        }
+        if state.groups_enabled {
+            let Some(group) = group_for(&mut handle, &mut state) else {
+                return false;
+            };
+            return state.groups.iter().any(|g| g.name == group && g.cursor < state.publish_sequence);
+        }
+        state.read_sequence < state.publish_sequence
+    }
+
+    fn acquire_write_buffer(&self) -> Option<(*mut u8, usize)> {
+        let mut handle = self.handle.lock().unwrap();
+        let segment = handle.segment.clone()?;
+        if !handle.is_writer || handle.acquired {
+            return None;
+        }
+
+        let state = segment.state.lock().unwrap();
+        // A batch can never span more slots than the ring holds
+        if handle.batch_open && handle.batch_pending >= state.buffer_count {
+            return None;
+        }
+
+        let (mut state, _) = segment.wait_until(state, -1, |state| state.has_space());
+        let buffer_size = state.buffer_size;
+        let len = buffer_size + if state.canaries { CANARY_SIZE } else { 0 };
+        let sequence = state.write_sequence;
+        let index = (sequence % state.buffer_count as u64) as usize;
+        let canary = state.canary(index);
+        let canaries = state.canaries;
+        let slot = &mut state.slots[index];
+
+        // Replaying readers treat the slot as gone from here on
+        slot.sequence = None;
+        if slot.buffer.as_ref().is_none_or(|b| b.len < len) {
+            slot.buffer = Some(SlotBuffer::new(len));
+        }
+        let ptr = slot.buffer.as_ref().unwrap().ptr.as_ptr();
+        if canaries {
+            unsafe { std::ptr::copy_nonoverlapping(canary.to_le_bytes().as_ptr(), ptr.add(buffer_size), CANARY_SIZE) };
+        }
+
+        handle.acquired = true;
+        Some((ptr, buffer_size))
+    }
+
+    fn publish_acquired(&self, size: usize) -> i32 {
+        let mut handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.clone() else {
+            return STATUS_ERROR;
+        };
+        let mut state = segment.state.lock().unwrap();
+        if !handle.acquired || size > state.buffer_size {
+            return STATUS_ERROR;
+        }
+
+        let sequence = state.write_sequence;
+        // The frame ran past its slot: drop it rather than hand readers a torn neighbour
+        if !state.canary_intact((sequence % state.buffer_count as u64) as usize) {
+            handle.acquired = false;
+            return STATUS_OVERRUN;
+        }
+
+        let slot = state.slot_mut(sequence);
+        slot.sequence = Some(sequence);
+        slot.timestamp_us = self.now_us();
+        slot.size = size;
+
+        state.write_sequence += 1;
+        state.high_water_bytes = state.high_water_bytes.max(size as u64);
+        state.high_water_buffers = state.high_water_buffers.max(state.unacked().min(state.buffer_count as u64));
+
+        // Readers see the frame now, unless it is staged in an open batch
+        if handle.batch_open {
+            handle.batch_pending += 1;
+        } else {
+            state.publish_sequence = state.write_sequence;
+        }
+        handle.acquired = false;
+
+        drop(state);
+        segment.notify();
+        STATUS_OK
+    }
+
+    fn release_acquired(&self) {
+        // The sequence was never advanced, so the slot is simply reused
+        self.handle.lock().unwrap().acquired = false;
+    }
+
+    fn reserve_slots(&self, max_buffer_count: usize) {
+        let mut handle = self.handle.lock().unwrap();
+        if handle.segment.is_none() {
+            handle.max_buffer_count = max_buffer_count.max(self.buffer_count);
+        }
+    }
+
+    fn reslot(&self, buffer_count: usize) -> i32 {
+        let handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.clone() else {
+            return STATUS_ERROR;
+        };
+        if !handle.is_writer || handle.batch_open || handle.acquired {
+            return STATUS_ERROR;
+        }
+
+        let mut state = segment.state.lock().unwrap();
+        if buffer_count == 0 || buffer_count > state.max_buffer_count {
+            return STATUS_ERROR;
+        }
+        if buffer_count == state.buffer_count {
+            return STATUS_OK;
+        }
+        // Only safe while every published frame has been consumed
+        if state.unacked() != 0 {
+            return STATUS_BUSY;
+        }
+
+        state.buffer_count = buffer_count;
+        state.buffer_size = slot_size(state.total_size, buffer_count, state.canaries);
+        // Retained frames do not survive a re-slot
+        for slot in &mut state.slots {
+            slot.sequence = None;
+        }
+        STATUS_OK
+    }
+
+    fn begin_batch(&self) -> i32 {
+        let mut handle = self.handle.lock().unwrap();
+        if !handle.is_writer || handle.segment.is_none() || handle.batch_open {
+            return STATUS_ERROR;
+        }
+        handle.batch_open = true;
+        handle.batch_pending = 0;
+        STATUS_OK
+    }
+
+    fn commit_batch(&self) -> i32 {
+        let mut handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.clone().filter(|_| handle.batch_open) else {
+            return STATUS_ERROR;
+        };
+
+        let mut state = segment.state.lock().unwrap();
+        state.publish_sequence = state.write_sequence;
+        drop(state);
+
+        handle.batch_open = false;
+        handle.batch_pending = 0;
+        segment.notify();
+        STATUS_OK
+    }
+
+    fn abort_batch(&self) {
+        let mut handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.clone().filter(|_| handle.batch_open) else {
+            return;
+        };
+
+        // Nothing was published yet, so rewinding the sequence hides the staged frames
+        let mut state = segment.state.lock().unwrap();
+        for _ in 0..handle.batch_pending {
+            state.write_sequence -= 1;
+            let sequence = state.write_sequence;
+            state.slot_mut(sequence).sequence = None;
+        }
+        drop(state);
+
+        handle.batch_open = false;
+        handle.batch_pending = 0;
+        segment.notify();
+    }
+
+    fn memory_report(&self, report: &mut MemoryReport) -> i32 {
+        let Some(segment) = self.segment() else {
+            return STATUS_ERROR;
+        };
+        let state = segment.state.lock().unwrap();
+
+        let used = state.unacked().min(state.buffer_count as u64);
+        let free = state.buffer_count as u64 - used;
+        *report = MemoryReport {
+            total_bytes: state.total_size as u64,
+            header_bytes: 0,
+            buffer_count: state.buffer_count as u64,
+            buffer_bytes: state.buffer_size as u64,
+            used_buffers: used,
+            free_buffers: free,
+            high_water_bytes: state.high_water_bytes,
+            high_water_buffers: state.high_water_buffers,
+            largest_writable_bytes: if free > 0 { state.buffer_size as u64 } else { 0 },
+        };
+        STATUS_OK
+    }
+
+    fn dump_header(&self, dump: &mut HeaderDump) -> i32 {
+        let (segment, last_read) = {
+            let handle = self.handle.lock().unwrap();
+            (handle.segment.clone(), handle.last_read)
+        };
+        let Some(segment) = segment else {
+            return STATUS_ERROR;
+        };
+        // One mutex guards the whole segment; it is reported as both spinlocks
+        let (state, locked) = match segment.state.try_lock() {
+            Ok(state) => (state, false),
+            Err(TryLockError::WouldBlock) => (segment.state.lock().unwrap(), true),
+            Err(TryLockError::Poisoned(poisoned)) => (poisoned.into_inner(), true),
+        };
+
+        *dump = HeaderDump {
+            total_size: state.total_size as u64,
+            buffer_count: state.buffer_count as u64,
+            buffer_size: state.buffer_size as u64,
+            max_buffer_count: state.max_buffer_count as u64,
+            write_sequence: state.write_sequence,
+            publish_sequence: state.publish_sequence,
+            read_sequence: state.read_sequence,
+            ack_sequence: state.ack_sequence,
+            retention_us: state.retention_us,
+            created_at_us: state.created_at_us,
+            generation: state.generation,
+            last_read_sequence: last_read.unwrap_or_default(),
+            writer_active: state.writer_active as i32,
+            writer_pid: if state.writer_active { std::process::id() as i32 } else { 0 },
+            reader_count: state.readers as i32,
+            ack_mode: state.ack_mode as i32,
+            auth_required: state.auth.is_some() as i32,
+            groups_enabled: state.groups_enabled as i32,
+            groups_lock: locked as i32,
+            meta_lock: locked as i32,
+            futex_waiters: segment.futex_waiters.load(Ordering::Relaxed),
+            write_sem_value: -1,
+            read_sem_value: -1,
+            last_read_valid: last_read.is_some() as i32,
+            max_readers: state.max_readers as i32,
+            ..HeaderDump::default()
+        };
+        STATUS_OK
+    }
+
+    fn dump_slot(&self, index: usize, dump: &mut SlotDump) -> i32 {
+        let Some(segment) = self.segment() else {
+            return STATUS_ERROR;
+        };
+        let state = segment.state.lock().unwrap();
+        if index >= state.buffer_count {
+            return 0;
+        }
+
+        let slot = &state.slots[index];
+        *dump = SlotDump {
+            sequence: slot.sequence.unwrap_or(SLOT_EMPTY),
+            data_size: slot.size as u64,
+            timestamp: slot.timestamp_us,
+            ready: slot.sequence.is_some() as i32,
+            reserved: 0,
+        };
+        1
+    }
+
+    fn dump_group(&self, index: usize, dump: &mut GroupDump) -> i32 {
+        let Some(segment) = self.segment() else {
+            return STATUS_ERROR;
+        };
+        if index >= MAX_CONSUMER_GROUPS {
+            return STATUS_ERROR;
+        }
+        let state = segment.state.lock().unwrap();
+        let Some(group) = state.groups.get(index) else {
+            return 0;
+        };
+
+        *dump = GroupDump {
+            cursor: group.cursor,
+            members: group.members as i32,
+            ..GroupDump::default()
+        };
+        dump.name[..group.name.len()].copy_from_slice(group.name.as_bytes());
+        1
+    }
+
+    fn seek(&self, pos: SeekFrom) -> i32 {
+        let mut handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.clone().filter(|_| !handle.is_writer) else {
+            return STATUS_ERROR;
+        };
+
+        let (first, next) = segment.state.lock().unwrap().retained_range(self.now_us());
+        let cursor = match pos {
+            SeekFrom::Sequence(sequence) if sequence < first || sequence > next => return STATUS_ERROR,
+            SeekFrom::Sequence(sequence) => sequence,
+            SeekFrom::Latest => next,
+            SeekFrom::Earliest => first,
+        };
+        handle.seek_to(cursor);
+        STATUS_OK
+    }
+
+    fn retained_range(&self) -> (u64, u64) {
+        match self.segment() {
+            Some(segment) => segment.state.lock().unwrap().retained_range(self.now_us()),
+            None => (0, 0),
+        }
+    }
+
+    fn seek_time(&self, timestamp_us: u64) -> i32 {
+        let mut handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.clone().filter(|_| !handle.is_writer) else {
+            return STATUS_ERROR;
+        };
+
+        // Timestamps grow with sequence, so the first match is the resume point
+        let state = segment.state.lock().unwrap();
+        let (first, next) = state.retained_range(self.now_us());
+        let cursor = (first..next)
+            .find(|&sequence| {
+                let slot = state.slot(sequence);
+                slot.sequence == Some(sequence) && slot.timestamp_us >= timestamp_us
+            })
+            .unwrap_or(next);
+        handle.seek_to(cursor);
+        STATUS_OK
+    }
+
+    fn set_retention(&self, retention_us: u64) {
+        if let Some(segment) = self.segment() {
+            segment.state.lock().unwrap().retention_us = retention_us;
+        }
+    }
+
+    fn enable_consumer_groups(&self) {
+        if let Some(segment) = self.segment() {
+            segment.state.lock().unwrap().groups_enabled = true;
+        }
+    }
+
+    fn join_group(&self, group: &str) -> i32 {
+        let mut handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.clone() else {
+            return STATUS_ERROR;
+        };
+        let mut state = segment.state.lock().unwrap();
+        join(&mut handle, &mut state, group)
+    }
+
+    fn set_protection(&self, _protection: MemoryProtection) {
+        // Every endpoint shares this process's heap, so there is no reader mapping to protect
+    }
+
+    fn set_advice(&self, _advice: &[Advice]) {
+        // Slots are ordinary heap allocations; there is no mapping to advise
+    }
+
+    fn set_backing_path(&self, _path: &Path) -> i32 {
+        // Nothing outlives the process in-process, so there is nothing to back
+        STATUS_ERROR
+    }
+
+    fn set_slot_guards(&self, _guard_pages: bool, canaries: bool) {
+        // Slots are separate heap allocations, so there are no pages to guard between them
+        let mut handle = self.handle.lock().unwrap();
+        if handle.segment.is_none() {
+            handle.canaries = canaries;
+        }
+    }
+
+    fn set_max_readers(&self, max_readers: usize) {
+        let handle = self.handle.lock().unwrap();
+        if let (Some(segment), true) = (&handle.segment, handle.is_writer) {
+            segment.state.lock().unwrap().max_readers = max_readers;
+        }
+    }
+
+    fn dump_reader(&self, index: usize, dump: &mut ReaderDump) -> i32 {
+        let Some(segment) = self.segment() else {
+            return STATUS_ERROR;
+        };
+        if index >= MAX_READERS {
+            return STATUS_ERROR;
+        }
+        let state = segment.state.lock().unwrap();
+        let Some(Some(entry)) = state.reader_entries.get(index) else {
+            return 0;
+        };
+
+        *dump = ReaderDump {
+            cursor: entry.cursor.load(Ordering::Relaxed),
+            attached_at_us: entry.attached_at_us,
+            pid: std::process::id() as i32,
+            alive: entry.alive as i32,
+        };
+        1
+    }
+
+    fn set_auth(&self, challenge: Option<&Challenge>) {
+        let handle = self.handle.lock().unwrap();
+        if let (Some(segment), true) = (&handle.segment, handle.is_writer) {
+            segment.state.lock().unwrap().auth = challenge.copied();
+        }
+    }
+
+    fn auth(&self) -> Option<Challenge> {
+        self.segment()?.state.lock().unwrap().auth
+    }
+
+    fn put_meta(&self, key: &str, value: Option<&str>) -> i32 {
+        let Some(segment) = self.segment() else {
+            return STATUS_ERROR;
+        };
+        if key.is_empty() || key.len() >= META_KEY_SIZE || key.contains('\0') {
+            return STATUS_ERROR;
+        }
+
+        let mut state = segment.state.lock().unwrap();
+        match value {
+            None => {
+                state.meta.remove(key);
+            },
+            Some(value) if value.len() >= META_VALUE_SIZE || value.contains('\0') => return STATUS_ERROR,
+            Some(_) if !state.meta.contains_key(key) && state.meta.len() >= MAX_META_ENTRIES => return STATUS_ERROR,
+            Some(value) => {
+                state.meta.insert(key.to_string(), value.to_string());
+            },
+        }
+        STATUS_OK
+    }
+
+    fn get_meta(&self, key: &str) -> Option<String> {
+        self.segment()?.state.lock().unwrap().meta.get(key).cloned()
+    }
+
+    fn meta_keys(&self) -> Vec<String> {
+        match self.segment() {
+            Some(segment) => segment.state.lock().unwrap().meta.keys().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn set_ack_mode(&self, enabled: bool) {
+        if let Some(segment) = self.segment() {
+            let mut state = segment.state.lock().unwrap();
+            state.ack_sequence = state.read_sequence;
+            state.ack_mode = enabled;
+        }
+    }
+
+    fn ack(&self, sequence: u64) -> i32 {
+        let handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.clone().filter(|_| !handle.is_writer) else {
+            return STATUS_ERROR;
+        };
+
+        let mut state = segment.state.lock().unwrap();
+        if !state.ack_mode {
+            return STATUS_OK; // Slots are already recycled on read
+        }
+        // Only frames that have been consumed can be acknowledged
+        if sequence >= state.read_sequence {
+            return STATUS_ERROR;
+        }
+
+        // Acks are cumulative
+        state.ack_sequence = state.ack_sequence.max(sequence + 1);
+        drop(state);
+        segment.notify();
+        STATUS_OK
+    }
+
+    fn unacked(&self) -> u64 {
+        self.segment().map_or(0, |segment| segment.state.lock().unwrap().unacked())
+    }
+
+    fn wait_for_data(&self, timeout_ms: i32) -> i32 {
+        let Some(segment) = self.segment() else {
+            return STATUS_ERROR;
+        };
+
+        let state = segment.state.lock().unwrap();
+        let notifications = state.notifications;
+        let (state, ready) = segment.wait_until(state, timeout_ms, |state| {
+            state.notifications != notifications || state.read_sequence < state.publish_sequence
+        });
+        drop(state);
+
+        // Frames in groups or seek mode don't show up in the shared cursor
+        match ready || self.has_data() {
+            true => STATUS_OK,
+            false => STATUS_TIMEOUT,
+        }
+    }
+
+    fn wake_word(&self) -> Option<WakeWord> {
+        let segment = self.segment()?;
+        Some(WakeWord {
+            futex: &segment.futex,
+            waiters: &segment.futex_waiters,
+        })
+    }
+
+    fn notify_data_ready(&self) {
+        if let Some(segment) = self.segment() {
+            segment.state.lock().unwrap().notifications += 1;
+            segment.notify();
+        }
+    }
+
+    fn close(&self) {
+        let mut handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.take() else {
+            return;
+        };
+
+        if handle.is_writer {
+            segment.state.lock().unwrap().writer_active = false;
+            let mut segments = registry();
+            if segments.get(&self.name).is_some_and(|s| Arc::ptr_eq(s, &segment)) {
+                segments.remove(&self.name);
+            }
+        } else {
+            let mut state = segment.state.lock().unwrap();
+            state.readers -= 1;
+            if let Some((index, _)) = handle.reader.take() {
+                state.reader_entries[index] = None;
+            }
+            if let Some(group) = handle.group.take() {
+                if let Some(entry) = state.groups.iter_mut().find(|g| g.name == group) {
+                    entry.members -= 1;
+                }
+            }
+        }
+        segment.notify();
+    }
+
+    fn abandon(&self) {
+        let mut handle = self.handle.lock().unwrap();
+        let Some(segment) = handle.segment.take() else {
+            return;
+        };
+
+        // The segment stays registered for a restarted writer to replace or reattach;
+        // an acquired slot is simply never published
+        let mut state = segment.state.lock().unwrap();
+        if handle.is_writer {
+            state.writer_active = false;
+        }
+        if let Some((index, _)) = handle.reader.take() {
+            if let Some(entry) = state.reader_entries[index].as_mut() {
+                entry.alive = false;
+            }
+        }
+        drop(state);
+        handle.group = None;
+        handle.acquired = false;
+        segment.notify();
+    }
+}
+
+impl Drop for LoopbackArena {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use polars::prelude::*;
+
+    use super::LoopbackArena;
+    use crate::backend::{RawArena, STATUS_OK, STATUS_OVERRUN};
+    use crate::testing::test_config;
+    use crate::{OpenMode, SeekFrom, SharedDataFrame, SharedDataStream, SystemClock};
+
+    #[test]
+    fn test_in_process_round_trip() -> crate::Result<()> {
+        let writer = SharedDataFrame::create_writer(test_config("loopback_round_trip", 3))?;
+        let reader = SharedDataFrame::create_reader(test_config("loopback_round_trip", 3))?;
+
+        let df = df! { "id" => [1i64, 2, 3], "price" => [1.5, 2.5, 3.5] }?;
+        writer.write(&df)?;
+
+        let received = reader.read(Some(100))?.unwrap();
+        assert!(received.equals(&df));
+        assert_eq!(reader.last_sequence(), Some(0));
+        assert!(matches!(reader.read(Some(0)), Err(crate::QADataSwapError::Timeout)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_canaries_catch_slot_overruns() {
+        let writer = LoopbackArena::new("loopback_canaries", 1 << 16, 2, Arc::new(SystemClock));
+        writer.set_slot_guards(false, true);
+        assert_eq!(writer.create_writer(OpenMode::Create), STATUS_OK);
+        let reader = LoopbackArena::new("loopback_canaries", 1 << 16, 2, Arc::new(SystemClock));
+        assert_eq!(reader.attach_reader(), STATUS_OK);
+
+        // A byte past the capacity is caught when the frame is published...
+        let (slot, capacity) = writer.acquire_write_buffer().unwrap();
+        unsafe { slot.add(capacity).write(0xFF) };
+        assert_eq!(writer.publish_acquired(capacity), STATUS_OVERRUN);
+
+        // ...or when it is consumed, if the stray write came later
+        let (slot, capacity) = writer.acquire_write_buffer().unwrap();
+        assert_eq!(writer.publish_acquired(1), STATUS_OK);
+        unsafe { slot.add(capacity).write(0xFF) };
+        assert_eq!(writer.write(b"next"), STATUS_OK);
+
+        let mut buffer = vec![0u8; capacity];
+        let mut size = 0;
+        assert_eq!(reader.read(&mut buffer, &mut size, 100), STATUS_OVERRUN);
+        assert_eq!(reader.read(&mut buffer, &mut size, 100), STATUS_OK);
+        assert_eq!(&buffer[..size], b"next");
+    }
+
+    #[test]
+    fn test_in_process_batch_and_seek() -> crate::Result<()> {
+        let writer = SharedDataStream::create_writer(test_config("loopback_seek", 4))?;
+        let reader = SharedDataStream::create_reader(test_config("loopback_seek", 3))?;
+
+        let mut batch = writer.begin_batch()?;
+        for i in 0..3i64 {
+            batch.write(&df! { "id" => [i] }?)?;
+        }
+        assert!(!reader.has_data());
+        batch.commit()?;
+
+        assert_eq!(reader.retained_range(), (0, 3));
+        reader.seek(SeekFrom::Sequence(1))?;
+        let chunk = reader.read_chunk(Some(100))?.unwrap();
+        assert_eq!(chunk.column("id")?.i64()?.get(0), Some(1));
+        assert_eq!(reader.last_sequence(), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_process_writer_conflict() {
+        let _writer = SharedDataFrame::create_writer(test_config("loopback_conflict", 3)).unwrap();
+        assert!(SharedDataFrame::create_writer(test_config("loopback_conflict", 3)).is_err());
+        assert!(SharedDataFrame::create_reader(test_config("loopback_missing", 3)).is_err());
+    }
+}
@@ -0,0 +1,670 @@
+//! Market data payloads: order book snapshots and delta-compressed updates
+//!
+//! Books travel as ordinary DataFrames so any reader (Polars, pyarrow) can use
+//! them. A snapshot frame has one row per book with the columns `symbol`,
+//! `timestamp_us`, `bid_price_1..N`, `bid_volume_1..N`, `ask_price_1..N` and
+//! `ask_volume_1..N`. A delta frame has one row per changed level, see
+//! `BookDeltaEncoder`.
+//!
+//! `BarBuilder` turns tick frames (`symbol`, `timestamp_us`, `price`,
+//! `volume`) into OHLCV bar frames.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use polars::prelude::*;
+
+use crate::{Event, QADataSwapError, Result, SharedDataFrame};
+
+const DAY_US: i64 = 86_400_000_000;
+
+/// Depth of `OrderBookFrame` unless another is given
+pub const DEFAULT_BOOK_DEPTH: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Level {
+    pub price: f64,
+    pub volume: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+impl Side {
+    fn as_u32(self) -> u32 {
+        match self {
+            Side::Bid => 0,
+            Side::Ask => 1,
+        }
+    }
+
+    fn from_u32(value: u32) -> Result<Self> {
+        match value {
+            0 => Ok(Side::Bid),
+            1 => Ok(Side::Ask),
+            other => Err(QADataSwapError::SharedMemory(format!("Unknown book side {}", other))),
+        }
+    }
+}
+
+/// One level of one side that differs from the previous book; a zeroed level
+/// means it was emptied
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelChange {
+    pub side: Side,
+    /// 0 is the best price
+    pub level: usize,
+    pub value: Level,
+}
+
+/// Top `DEPTH` levels of a book, best price first; unused levels are zeroed
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBookFrame<const DEPTH: usize = DEFAULT_BOOK_DEPTH> {
+    pub symbol: String,
+    pub timestamp_us: u64,
+    pub bids: [Level; DEPTH],
+    pub asks: [Level; DEPTH],
+}
+
+impl<const DEPTH: usize> OrderBookFrame<DEPTH> {
+    pub fn new(symbol: impl Into<String>, timestamp_us: u64) -> Self {
+        Self {
+            symbol: symbol.into(),
+            timestamp_us,
+            bids: [Level::default(); DEPTH],
+            asks: [Level::default(); DEPTH],
+        }
+    }
+
+    pub fn side(&self, side: Side) -> &[Level; DEPTH] {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    pub fn side_mut(&mut self, side: Side) -> &mut [Level; DEPTH] {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    /// Levels that changed going from `previous` to `self`
+    pub fn diff(&self, previous: &Self) -> Vec<LevelChange> {
+        [Side::Bid, Side::Ask]
+            .into_iter()
+            .flat_map(|side| {
+                let (now, before) = (self.side(side), previous.side(side));
+                (0..DEPTH)
+                    .filter(move |&level| now[level] != before[level])
+                    .map(move |level| LevelChange { side, level, value: now[level] })
+            })
+            .collect()
+    }
+
+    /// Every level of both sides, as changes
+    fn levels(&self) -> Vec<LevelChange> {
+        [Side::Bid, Side::Ask]
+            .into_iter()
+            .flat_map(|side| (0..DEPTH).map(move |level| LevelChange { side, level, value: self.side(side)[level] }))
+            .collect()
+    }
+
+    pub fn apply(&mut self, changes: &[LevelChange]) -> Result<()> {
+        for change in changes {
+            let levels = self.side_mut(change.side);
+            let slot = levels.get_mut(change.level).ok_or_else(|| {
+                QADataSwapError::SharedMemory(format!("Level {} beyond book depth {}", change.level, DEPTH))
+            })?;
+            *slot = change.value;
+        }
+        Ok(())
+    }
+
+    /// Snapshot frame with one row per book
+    pub fn to_dataframe(books: &[Self]) -> Result<DataFrame> {
+        let mut columns = vec![
+            Column::new("symbol".into(), books.iter().map(|b| b.symbol.as_str()).collect::<Vec<_>>()),
+            Column::new("timestamp_us".into(), books.iter().map(|b| b.timestamp_us).collect::<Vec<_>>()),
+        ];
+        for side in [Side::Bid, Side::Ask] {
+            for level in 0..DEPTH {
+                let (price, volume) = level_columns(side, level);
+                columns.push(Column::new(price.into(), books.iter().map(|b| b.side(side)[level].price).collect::<Vec<_>>()));
+                columns.push(Column::new(volume.into(), books.iter().map(|b| b.side(side)[level].volume).collect::<Vec<_>>()));
+            }
+        }
+        DataFrame::new(columns).map_err(QADataSwapError::Polars)
+    }
+
+    /// Books from a snapshot frame, which must carry at least `DEPTH` levels
+    pub fn from_dataframe(df: &DataFrame) -> Result<Vec<Self>> {
+        let symbols = df.column("symbol")?.str()?.clone();
+        let timestamps = df.column("timestamp_us")?.cast(&DataType::UInt64)?;
+        let timestamps = timestamps.u64()?;
+
+        let mut books: Vec<Self> = (0..df.height())
+            .map(|row| Self::new(symbols.get(row).unwrap_or_default(), timestamps.get(row).unwrap_or_default()))
+            .collect();
+        for side in [Side::Bid, Side::Ask] {
+            for level in 0..DEPTH {
+                let (price, volume) = level_columns(side, level);
+                let prices = df.column(&price)?.cast(&DataType::Float64)?;
+                let volumes = df.column(&volume)?.cast(&DataType::Float64)?;
+                for (book, (price, volume)) in books.iter_mut().zip(prices.f64()?.iter().zip(volumes.f64()?.iter())) {
+                    book.side_mut(side)[level] = Level {
+                        price: price.unwrap_or_default(),
+                        volume: volume.unwrap_or_default(),
+                    };
+                }
+            }
+        }
+        Ok(books)
+    }
+}
+
+fn level_columns(side: Side, level: usize) -> (String, String) {
+    let prefix = match side {
+        Side::Bid => "bid",
+        Side::Ask => "ask",
+    };
+    (format!("{}_price_{}", prefix, level + 1), format!("{}_volume_{}", prefix, level + 1))
+}
+
+/// Publish books as one snapshot frame
+pub fn write_books<const DEPTH: usize>(writer: &SharedDataFrame, books: &[OrderBookFrame<DEPTH>]) -> Result<()> {
+    writer.write(&OrderBookFrame::to_dataframe(books)?)
+}
+
+/// Next snapshot frame as books
+pub fn read_books<const DEPTH: usize>(
+    reader: &SharedDataFrame,
+    timeout_ms: Option<i32>,
+) -> Result<Option<Vec<OrderBookFrame<DEPTH>>>> {
+    match reader.read(timeout_ms)? {
+        Some(df) => OrderBookFrame::from_dataframe(&df).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Turns full books into delta frames: one row per changed level with the
+/// columns `symbol`, `timestamp_us`, `snapshot`, `side` (0 bid, 1 ask),
+/// `level` (0 = best), `price` and `volume`.
+///
+/// Every `keyframe_every`-th update of a symbol (and its first) is sent in full,
+/// all levels with `snapshot` set, so readers that join late resynchronise.
+pub struct BookDeltaEncoder<const DEPTH: usize = DEFAULT_BOOK_DEPTH> {
+    last: HashMap<String, (OrderBookFrame<DEPTH>, usize)>,
+    keyframe_every: usize,
+}
+
+impl<const DEPTH: usize> BookDeltaEncoder<DEPTH> {
+    pub fn new(keyframe_every: usize) -> Self {
+        Self {
+            last: HashMap::new(),
+            keyframe_every: keyframe_every.max(1),
+        }
+    }
+
+    pub fn encode(&mut self, books: &[OrderBookFrame<DEPTH>]) -> Result<DataFrame> {
+        let mut rows = DeltaRows::default();
+        for book in books {
+            let (changes, snapshot) = match self.last.get_mut(&book.symbol) {
+                Some((previous, since_keyframe)) if *since_keyframe + 1 < self.keyframe_every => {
+                    *since_keyframe += 1;
+                    let changes = book.diff(previous);
+                    *previous = book.clone();
+                    (changes, false)
+                },
+                _ => {
+                    self.last.insert(book.symbol.clone(), (book.clone(), 0));
+                    (book.levels(), true)
+                },
+            };
+            // An unchanged book still sends one row so its timestamp advances
+            if changes.is_empty() {
+                rows.push(book, snapshot, LevelChange { side: Side::Bid, level: 0, value: book.bids[0] });
+            }
+            for change in changes {
+                rows.push(book, snapshot, change);
+            }
+        }
+        rows.finish()
+    }
+
+    /// Send every symbol in full on its next update
+    pub fn force_keyframe(&mut self) {
+        self.last.clear();
+    }
+}
+
+#[derive(Default)]
+struct DeltaRows {
+    symbols: Vec<String>,
+    timestamps: Vec<u64>,
+    snapshots: Vec<bool>,
+    sides: Vec<u32>,
+    levels: Vec<u32>,
+    prices: Vec<f64>,
+    volumes: Vec<f64>,
+}
+
+impl DeltaRows {
+    fn push<const DEPTH: usize>(&mut self, book: &OrderBookFrame<DEPTH>, snapshot: bool, change: LevelChange) {
+        self.symbols.push(book.symbol.clone());
+        self.timestamps.push(book.timestamp_us);
+        self.snapshots.push(snapshot);
+        self.sides.push(change.side.as_u32());
+        self.levels.push(change.level as u32);
+        self.prices.push(change.value.price);
+        self.volumes.push(change.value.volume);
+    }
+
+    fn finish(self) -> Result<DataFrame> {
+        DataFrame::new(vec![
+            Column::new("symbol".into(), self.symbols),
+            Column::new("timestamp_us".into(), self.timestamps),
+            Column::new("snapshot".into(), self.snapshots),
+            Column::new("side".into(), self.sides),
+            Column::new("level".into(), self.levels),
+            Column::new("price".into(), self.prices),
+            Column::new("volume".into(), self.volumes),
+        ])
+        .map_err(QADataSwapError::Polars)
+    }
+}
+
+/// Rebuilds books from `BookDeltaEncoder` frames. Deltas for a symbol are
+/// ignored until its first snapshot arrives.
+pub struct BookDeltaDecoder<const DEPTH: usize = DEFAULT_BOOK_DEPTH> {
+    books: HashMap<String, OrderBookFrame<DEPTH>>,
+}
+
+impl<const DEPTH: usize> Default for BookDeltaDecoder<DEPTH> {
+    fn default() -> Self {
+        Self { books: HashMap::new() }
+    }
+}
+
+impl<const DEPTH: usize> BookDeltaDecoder<DEPTH> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a delta frame and return the books it updated, in frame order
+    pub fn apply(&mut self, df: &DataFrame) -> Result<Vec<OrderBookFrame<DEPTH>>> {
+        let symbols = df.column("symbol")?.str()?;
+        let timestamps = df.column("timestamp_us")?.u64()?;
+        let snapshots = df.column("snapshot")?.bool()?;
+        let sides = df.column("side")?.u32()?;
+        let levels = df.column("level")?.u32()?;
+        let prices = df.column("price")?.f64()?;
+        let volumes = df.column("volume")?.f64()?;
+
+        let mut updated: Vec<String> = Vec::new();
+        for row in 0..df.height() {
+            let Some(symbol) = symbols.get(row) else {
+                continue;
+            };
+            let timestamp_us = timestamps.get(row).unwrap_or_default();
+            // The first row of a snapshot replaces whatever we had
+            let starts_snapshot = snapshots.get(row) == Some(true)
+                && (row == 0 || symbols.get(row - 1) != Some(symbol) || snapshots.get(row - 1) != Some(true));
+            if starts_snapshot {
+                self.books.insert(symbol.to_string(), OrderBookFrame::new(symbol, timestamp_us));
+            }
+            let Some(book) = self.books.get_mut(symbol) else {
+                continue;
+            };
+
+            book.timestamp_us = timestamp_us;
+            book.apply(&[LevelChange {
+                side: Side::from_u32(sides.get(row).unwrap_or_default())?,
+                level: levels.get(row).unwrap_or_default() as usize,
+                value: Level {
+                    price: prices.get(row).unwrap_or_default(),
+                    volume: volumes.get(row).unwrap_or_default(),
+                },
+            }])?;
+            if updated.last().map(String::as_str) != Some(symbol) {
+                updated.push(symbol.to_string());
+            }
+        }
+        Ok(updated.iter().map(|symbol| self.books[symbol].clone()).collect())
+    }
+
+    /// Latest state of `symbol`, once a snapshot for it has been seen
+    pub fn book(&self, symbol: &str) -> Option<&OrderBookFrame<DEPTH>> {
+        self.books.get(symbol)
+    }
+}
+
+/// A trading session as times of day in the builder's UTC offset; `end`
+/// before `start` runs over midnight (night sessions)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Session {
+    start: Duration,
+    end: Duration,
+}
+
+#[derive(Debug, Clone)]
+struct Bar {
+    end_us: i64,
+    open: (i64, f64),
+    high: f64,
+    low: f64,
+    close: (i64, f64),
+    volume: f64,
+    trades: u32,
+}
+
+/// Aggregates tick frames into OHLCV bars of a fixed interval, on event time.
+///
+/// Ticks carry `symbol`, `timestamp_us` (microseconds or a Datetime), `price`
+/// and optionally `volume`. Bar frames have one row per closed bar:
+/// `symbol`, `timestamp_us` (bar start), `open`, `high`, `low`, `close`,
+/// `volume` and `trades`, ordered by start time then symbol.
+///
+/// Bars align to the start of their session (to midnight without sessions)
+/// and the last bar of a session is cut short at its close. Ticks may arrive
+/// out of order: a bar stays open until the latest tick seen, from any
+/// symbol, is `allowed_lateness` past its end. Ticks for bars already closed
+/// are dropped and counted in `late_ticks()`; ticks outside every session in
+/// `out_of_session_ticks()`.
+pub struct BarBuilder {
+    interval_us: i64,
+    sessions: Vec<Session>,
+    utc_offset_us: i64,
+    lateness_us: i64,
+    bars: HashMap<(String, i64), Bar>,
+    watermark_us: i64,
+    late_ticks: u64,
+    out_of_session_ticks: u64,
+}
+
+impl BarBuilder {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval_us: (interval.as_micros() as i64).max(1),
+            sessions: Vec::new(),
+            utc_offset_us: 0,
+            lateness_us: 0,
+            bars: HashMap::new(),
+            watermark_us: i64::MIN,
+            late_ticks: 0,
+            out_of_session_ticks: 0,
+        }
+    }
+
+    /// Only build bars within these sessions; repeat for several a day
+    pub fn with_session(mut self, start: Duration, end: Duration) -> Self {
+        self.sessions.push(Session { start, end });
+        self
+    }
+
+    /// Offset of the sessions' (and daily alignment's) local time from UTC, e.g. 8 * 3600 for Shanghai
+    pub fn with_utc_offset(mut self, offset_secs: i32) -> Self {
+        self.utc_offset_us = offset_secs as i64 * 1_000_000;
+        self
+    }
+
+    /// Keep bars open for ticks arriving up to `lateness` after their end
+    pub fn with_allowed_lateness(mut self, lateness: Duration) -> Self {
+        self.lateness_us = lateness.as_micros() as i64;
+        self
+    }
+
+    /// Ticks dropped because their bar had already been closed
+    pub fn late_ticks(&self) -> u64 {
+        self.late_ticks
+    }
+
+    pub fn out_of_session_ticks(&self) -> u64 {
+        self.out_of_session_ticks
+    }
+
+    /// Feed a tick frame; returns the bars it closed, if any
+    pub fn push(&mut self, ticks: &DataFrame) -> Result<Option<DataFrame>> {
+        let symbols = ticks.column("symbol")?.str()?.clone();
+        let times = tick_times(ticks)?;
+        let prices = ticks.column("price")?.cast(&DataType::Float64)?;
+        let prices = prices.f64()?;
+        let volumes = match ticks.column("volume") {
+            Ok(volumes) => Some(volumes.cast(&DataType::Float64)?),
+            Err(_) => None,
+        };
+        let volumes = volumes.as_ref().map(|volumes| volumes.f64()).transpose()?;
+
+        for (row, time) in times.into_iter().enumerate() {
+            let (Some(symbol), Some(time), Some(price)) = (symbols.get(row), time, prices.get(row)) else {
+                continue;
+            };
+            let Some((start, end)) = self.bar_bounds(time) else {
+                self.out_of_session_ticks += 1;
+                continue;
+            };
+            if end + self.lateness_us <= self.watermark_us {
+                self.late_ticks += 1;
+                continue;
+            }
+
+            let volume = volumes.and_then(|volumes| volumes.get(row)).unwrap_or_default();
+            let bar = self.bars.entry((symbol.to_string(), start)).or_insert(Bar {
+                end_us: end,
+                open: (time, price),
+                high: price,
+                low: price,
+                close: (time, price),
+                volume: 0.0,
+                trades: 0,
+            });
+            if time < bar.open.0 {
+                bar.open = (time, price);
+            }
+            if time >= bar.close.0 {
+                bar.close = (time, price);
+            }
+            bar.high = bar.high.max(price);
+            bar.low = bar.low.min(price);
+            bar.volume += volume;
+            bar.trades += 1;
+            self.watermark_us = self.watermark_us.max(time);
+        }
+
+        let closed_before = self.watermark_us.saturating_sub(self.lateness_us);
+        self.close_bars(|bar| bar.end_us <= closed_before)
+    }
+
+    /// Close every open bar, complete or not, e.g. at the end of the stream
+    pub fn flush(&mut self) -> Result<Option<DataFrame>> {
+        self.close_bars(|_| true)
+    }
+
+    /// Build bars from `reader`'s ticks and publish them through `writer` until
+    /// the tick stream ends; a `Flush` closes the open bars early. The end of
+    /// stream is passed on after the last bars.
+    pub fn run(&mut self, reader: &SharedDataFrame, writer: &SharedDataFrame) -> Result<()> {
+        loop {
+            let bars = match reader.read_event(None) {
+                Ok(Some(Event::Data(ticks))) => self.push(&ticks)?,
+                Ok(Some(Event::Flush)) => self.flush()?,
+                Ok(Some(Event::Eos)) => break,
+                Ok(_) | Err(QADataSwapError::Timeout) => None,
+                Err(e) => return Err(e),
+            };
+            if let Some(bars) = bars {
+                writer.write(&bars)?;
+            }
+        }
+        if let Some(bars) = self.flush()? {
+            writer.write(&bars)?;
+        }
+        writer.write_event(&Event::Eos)
+    }
+
+    /// Start and end of the bar holding `time`, or `None` outside every session
+    fn bar_bounds(&self, time: i64) -> Option<(i64, i64)> {
+        let local = time + self.utc_offset_us;
+        let (open, close) = match self.sessions.is_empty() {
+            true => (i64::MIN, i64::MAX),
+            false => self.sessions.iter().find_map(|session| session_bounds(session, local))?,
+        };
+
+        let origin = if open == i64::MIN { local.div_euclid(DAY_US) * DAY_US } else { open };
+        let start = origin + (local - origin).div_euclid(self.interval_us) * self.interval_us;
+        let end = (start + self.interval_us).min(close);
+        Some((start - self.utc_offset_us, end - self.utc_offset_us))
+    }
+
+    fn close_bars(&mut self, closed: impl Fn(&Bar) -> bool) -> Result<Option<DataFrame>> {
+        let keys: Vec<_> = self.bars.iter().filter(|(_, bar)| closed(bar)).map(|(key, _)| key.clone()).collect();
+        if keys.is_empty() {
+            return Ok(None);
+        }
+        let mut bars: Vec<_> = keys.into_iter().map(|key| {
+            let bar = self.bars.remove(&key).unwrap();
+            (key, bar)
+        }).collect();
+        bars.sort_by(|((a, a_start), _), ((b, b_start), _)| a_start.cmp(b_start).then_with(|| a.cmp(b)));
+
+        DataFrame::new(vec![
+            Column::new("symbol".into(), bars.iter().map(|((symbol, _), _)| symbol.as_str()).collect::<Vec<_>>()),
+            Column::new("timestamp_us".into(), bars.iter().map(|((_, start), _)| *start as u64).collect::<Vec<_>>()),
+            Column::new("open".into(), bars.iter().map(|(_, bar)| bar.open.1).collect::<Vec<_>>()),
+            Column::new("high".into(), bars.iter().map(|(_, bar)| bar.high).collect::<Vec<_>>()),
+            Column::new("low".into(), bars.iter().map(|(_, bar)| bar.low).collect::<Vec<_>>()),
+            Column::new("close".into(), bars.iter().map(|(_, bar)| bar.close.1).collect::<Vec<_>>()),
+            Column::new("volume".into(), bars.iter().map(|(_, bar)| bar.volume).collect::<Vec<_>>()),
+            Column::new("trades".into(), bars.iter().map(|(_, bar)| bar.trades).collect::<Vec<_>>()),
+        ])
+        .map(Some)
+        .map_err(QADataSwapError::Polars)
+    }
+}
+
+/// Open and close (local microseconds) of the `session` occurrence holding `local`
+fn session_bounds(session: &Session, local: i64) -> Option<(i64, i64)> {
+    let day = local.div_euclid(DAY_US) * DAY_US;
+    let (start, end) = (session.start.as_micros() as i64, session.end.as_micros() as i64);
+    let length = if end > start { end - start } else { DAY_US - start + end };
+    // A night session may have opened the day before
+    [day + start, day - DAY_US + start]
+        .into_iter()
+        .find(|&open| local >= open && local < open + length)
+        .map(|open| (open, open + length))
+}
+
+/// Tick times in microseconds, from a Datetime or integer `timestamp_us` column
+fn tick_times(ticks: &DataFrame) -> Result<Vec<Option<i64>>> {
+    let column = ticks.column("timestamp_us")?;
+    let column = match column.dtype() {
+        DataType::Datetime(_, _) => column.cast(&DataType::Datetime(TimeUnit::Microseconds, None))?,
+        _ => column.clone(),
+    };
+    Ok(column.cast(&DataType::Int64)?.i64()?.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    fn book(symbol: &str, timestamp_us: u64, best_bid: f64) -> OrderBookFrame<3> {
+        let mut book = OrderBookFrame::new(symbol, timestamp_us);
+        book.bids = [
+            Level { price: best_bid, volume: 10.0 },
+            Level { price: best_bid - 0.01, volume: 20.0 },
+            Level::default(),
+        ];
+        book.asks[0] = Level { price: best_bid + 0.01, volume: 5.0 };
+        book
+    }
+
+    #[test]
+    fn test_book_snapshots_and_deltas() -> Result<()> {
+        let config = test_config("market_books", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let books = vec![book("AAPL", 1, 100.0), book("MSFT", 1, 400.0)];
+        write_books(&writer, &books)?;
+        assert_eq!(read_books::<3>(&reader, Some(100))?, Some(books.clone()));
+
+        let mut encoder = BookDeltaEncoder::<3>::new(100);
+        let mut decoder = BookDeltaDecoder::<3>::new();
+        decoder.apply(&encoder.encode(&books)?)?;
+
+        let mut next = book("AAPL", 2, 100.0);
+        next.bids[1].volume = 25.0;
+        let delta = encoder.encode(std::slice::from_ref(&next))?;
+        assert_eq!(delta.height(), 1);
+        assert_eq!(decoder.apply(&delta)?, vec![next]);
+        assert_eq!(decoder.book("MSFT"), Some(&books[1]));
+        Ok(())
+    }
+
+    fn ticks(rows: &[(&str, u64, f64)]) -> DataFrame {
+        let minute = 60_000_000;
+        df! {
+            "symbol" => rows.iter().map(|row| row.0).collect::<Vec<_>>(),
+            "timestamp_us" => rows.iter().map(|row| row.1 * minute).collect::<Vec<_>>(),
+            "price" => rows.iter().map(|row| row.2).collect::<Vec<_>>(),
+            "volume" => vec![1.0; rows.len()],
+        }
+        .unwrap()
+    }
+
+    #[test]
+    fn test_bars_follow_sessions_and_drop_late_ticks() -> Result<()> {
+        let config = |name: &str| test_config(name, 8);
+        let tick_writer = SharedDataFrame::create_writer(config("market_ticks"))?;
+        let tick_reader = SharedDataFrame::create_reader(config("market_ticks"))?;
+        let bar_writer = SharedDataFrame::create_writer(config("market_bars"))?;
+        let bar_reader = SharedDataFrame::create_reader(config("market_bars"))?;
+
+        // One session 09:00-10:10 UTC+8, in minutes since the Unix epoch
+        let local = |hour: u64, minute: u64| (hour - 8) * 60 + minute;
+        tick_writer.write(&ticks(&[
+            ("AAPL", local(9, 5), 10.0),
+            ("AAPL", local(9, 10), 12.0),
+            ("MSFT", local(9, 20), 50.0),
+            ("AAPL", local(8, 59), 99.0),
+        ]))?;
+        tick_writer.write(&ticks(&[
+            ("AAPL", local(9, 40), 11.0),
+            ("AAPL", local(9, 2), 99.0),
+            ("AAPL", local(9, 31), 13.0),
+            ("AAPL", local(10, 5), 14.0),
+        ]))?;
+        tick_writer.write_event(&Event::Eos)?;
+
+        let mut builder = BarBuilder::new(Duration::from_secs(30 * 60))
+            .with_session(Duration::from_secs(9 * 3600), Duration::from_secs(10 * 3600 + 600))
+            .with_utc_offset(8 * 3600)
+            .with_allowed_lateness(Duration::from_secs(5 * 60));
+        builder.run(&tick_reader, &bar_writer)?;
+        assert_eq!((builder.late_ticks(), builder.out_of_session_ticks()), (1, 1));
+
+        // Nothing closes until the second frame's ticks pass the bar ends plus the lateness
+        let closed = bar_reader.read(Some(100))?.unwrap();
+        let starts: Vec<_> = closed.column("timestamp_us")?.u64()?.into_no_null_iter().collect();
+        assert_eq!(starts, vec![local(9, 0) * 60_000_000, local(9, 0) * 60_000_000, local(9, 30) * 60_000_000]);
+        assert_eq!(closed.column("symbol")?.str()?.get(1), Some("MSFT"));
+        assert_eq!(closed.column("open")?.f64()?.get(0), Some(10.0));
+        assert_eq!(closed.column("close")?.f64()?.get(0), Some(12.0));
+        assert_eq!(closed.column("trades")?.u32()?.get(0), Some(2));
+        assert_eq!(closed.column("open")?.f64()?.get(2), Some(13.0));
+        assert_eq!(closed.column("low")?.f64()?.get(2), Some(11.0));
+
+        // The session's last bar is cut short at 10:10 and flushed at the end of stream
+        let flushed = bar_reader.read(Some(100))?.unwrap();
+        assert_eq!(flushed.column("timestamp_us")?.u64()?.get(0), Some(local(10, 0) * 60_000_000));
+        assert_eq!(flushed.column("close")?.f64()?.get(0), Some(14.0));
+        assert!(matches!(bar_reader.read_event(Some(100))?, Some(Event::Eos)));
+        Ok(())
+    }
+}
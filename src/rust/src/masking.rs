@@ -0,0 +1,206 @@
+//! Column policies: mask or encrypt sensitive columns as frames are written
+//!
+//! Only the named columns are rewritten; every other column is published as
+//! is. Masked and encrypted values become hex strings, nulls stay null.
+//! `Encrypt` is deterministic (a synthetic IV derived from the value), so
+//! equal values still group and join; a reader configured with the same
+//! policy and key gets the plaintext back, as strings.
+
+use std::fmt;
+use std::sync::Arc;
+
+use hmac::{Hmac, Mac};
+use polars::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::{QADataSwapError, Result};
+
+const IV_SIZE: usize = 16;
+
+/// What happens to a column at write time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnPolicy {
+    /// SHA-256 of the value; anyone can compute it, so only for high-entropy values
+    HashSha256,
+    /// HMAC-SHA256 under a key: a stable pseudonym nobody without the key can compute
+    HmacSha256(ColumnKey),
+    /// Reversible for readers holding the key
+    Encrypt(ColumnKey),
+    /// Replaced by nulls
+    Redact,
+}
+
+/// Secret for `ColumnPolicy::HmacSha256` / `ColumnPolicy::Encrypt`. Never printed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ColumnKey(Arc<[u8]>);
+
+impl ColumnKey {
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self(Arc::from(secret.as_ref()))
+    }
+
+    /// HMAC-SHA256 under a subkey derived for `purpose`
+    fn mac(&self, purpose: &[u8]) -> Hmac<Sha256> {
+        let mut derive = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        derive.update(purpose);
+        Hmac::<Sha256>::new_from_slice(&derive.finalize().into_bytes()).expect("HMAC accepts keys of any length")
+    }
+
+    fn iv(&self, column: &str, value: &[u8]) -> [u8; IV_SIZE] {
+        let mut mac = self.mac(b"qads-column-iv");
+        mac.update(&(column.len() as u32).to_le_bytes());
+        mac.update(column.as_bytes());
+        mac.update(value);
+        mac.finalize().into_bytes()[..IV_SIZE].try_into().unwrap()
+    }
+
+    /// XOR `data` with the HMAC-SHA256 counter-mode keystream for `iv`
+    fn apply_keystream(&self, iv: &[u8], data: &mut [u8]) {
+        let stream = self.mac(b"qads-column-ctr");
+        for (counter, block) in data.chunks_mut(32).enumerate() {
+            let mut mac = stream.clone();
+            mac.update(iv);
+            mac.update(&(counter as u32).to_le_bytes());
+            for (byte, key) in block.iter_mut().zip(mac.finalize().into_bytes()) {
+                *byte ^= key;
+            }
+        }
+    }
+
+    fn encrypt(&self, column: &str, value: &str) -> String {
+        let iv = self.iv(column, value.as_bytes());
+        let mut data = value.as_bytes().to_vec();
+        self.apply_keystream(&iv, &mut data);
+        hex(&[&iv[..], &data].concat())
+    }
+
+    /// `None` for values this key did not encrypt
+    fn decrypt(&self, column: &str, value: &str) -> Option<String> {
+        let bytes = unhex(value)?;
+        let (iv, data) = bytes.split_at_checked(IV_SIZE)?;
+        let mut data = data.to_vec();
+        self.apply_keystream(iv, &mut data);
+        // The IV doubles as the authentication tag
+        (self.iv(column, &data) == iv).then_some(())?;
+        String::from_utf8(data).ok()
+    }
+}
+
+impl fmt::Debug for ColumnKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ColumnKey(<redacted>)")
+    }
+}
+
+impl<T: AsRef<[u8]>> From<T> for ColumnKey {
+    fn from(secret: T) -> Self {
+        Self::new(secret)
+    }
+}
+
+/// Writer side: `df` with every policy applied; `None` if no column changed
+pub(crate) fn mask(df: &DataFrame, policies: &[(String, ColumnPolicy)]) -> Result<Option<DataFrame>> {
+    let mut masked = None;
+    for (name, policy) in policies {
+        let Ok(column) = df.column(name) else {
+            continue;
+        };
+        let values = column.cast(&DataType::String)?;
+        let values = values.str()?;
+        let column = match policy {
+            ColumnPolicy::HashSha256 => map(name, values, |v| hex(&Sha256::digest(v))),
+            ColumnPolicy::HmacSha256(key) => map(name, values, |v| {
+                let mut mac = key.mac(b"qads-column-hmac");
+                mac.update(v.as_bytes());
+                hex(&mac.finalize().into_bytes())
+            }),
+            ColumnPolicy::Encrypt(key) => map(name, values, |v| key.encrypt(name, v)),
+            ColumnPolicy::Redact => Column::full_null(name.as_str().into(), values.len(), &DataType::String),
+        };
+        masked.get_or_insert_with(|| df.clone()).with_column(column)?;
+    }
+    Ok(masked)
+}
+
+/// Reader side: decrypt the `Encrypt` columns the reader holds keys for
+pub(crate) fn unmask(df: DataFrame, policies: &[(String, ColumnPolicy)]) -> Result<DataFrame> {
+    let mut df = df;
+    for (name, policy) in policies {
+        let ColumnPolicy::Encrypt(key) = policy else {
+            continue;
+        };
+        let Ok(column) = df.column(name) else {
+            continue;
+        };
+        let mut plain = Vec::with_capacity(column.len());
+        for value in column.str()?.iter() {
+            plain.push(match value {
+                Some(value) => Some(key.decrypt(name, value).ok_or_else(|| {
+                    QADataSwapError::SharedMemory(format!("Column '{}' does not decrypt under this key", name))
+                })?),
+                None => None,
+            });
+        }
+        df.with_column(Column::new(name.as_str().into(), plain))?;
+    }
+    Ok(df)
+}
+
+fn map(name: &str, values: &StringChunked, f: impl Fn(&str) -> String) -> Column {
+    let masked: Vec<Option<String>> = values.iter().map(|v| v.map(&f)).collect();
+    Column::new(name.into(), masked)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+    use polars::df;
+
+    #[test]
+    fn test_sensitive_columns_masked_for_readers_without_key() -> Result<()> {
+        let key = ColumnKey::new("desk-7");
+        let config = test_config("masked_fills", 3);
+        let writer = SharedDataFrame::create_writer(
+            config
+                .clone()
+                .with_column_policy("account_id", ColumnPolicy::Encrypt(key.clone()))
+                .with_column_policy("trader", ColumnPolicy::HashSha256)
+                .with_column_policy("note", ColumnPolicy::Redact),
+        )?;
+        let fills = df! {
+            "account_id" => [Some("ACC-001"), None, Some("ACC-001")],
+            "trader" => ["alice", "bob", "alice"],
+            "note" => ["vip", "", "vip"],
+            "qty" => [10i64, 20, 30],
+        }?;
+        writer.write(&fills)?;
+        writer.write(&fills)?;
+
+        let masked = SharedDataFrame::create_reader(config.clone())?.read(Some(100))?.unwrap();
+        let accounts = masked.column("account_id")?.str()?;
+        assert_ne!(accounts.get(0), Some("ACC-001"));
+        assert_eq!((accounts.get(0), accounts.get(1)), (accounts.get(2), None));
+        assert_eq!(masked.column("trader")?.str()?.get(1).map(str::len), Some(64));
+        assert_eq!(masked.column("note")?.null_count(), 3);
+        assert_eq!(masked.column("qty")?, fills.column("qty")?);
+
+        let keyed = config.with_column_policy("account_id", ColumnPolicy::Encrypt(key));
+        let clear = SharedDataFrame::create_reader(keyed)?.read(Some(100))?.unwrap();
+        assert_eq!(clear.column("account_id")?, fills.column("account_id")?);
+        assert_eq!(ColumnKey::new("other").decrypt("account_id", accounts.get(0).unwrap()), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,494 @@
+//! Reading several arenas as one time-ordered stream
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use polars::prelude::*;
+
+use crate::{Clock, ClockSource, FrameMetadata, QADataSwapError, Result, SharedDataFrame, SharedMemoryConfig};
+
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+struct MergeSource {
+    name: String,
+    reader: SharedDataFrame,
+    pending: Option<(FrameMetadata, DataFrame)>,
+}
+
+/// Item produced by `MergeReader::next_event`
+#[derive(Debug)]
+pub enum MergeEvent {
+    Frame(FrameMetadata, DataFrame),
+    /// Every frame with `timestamp_us` at or before this value (on the sources'
+    /// clock) has been emitted; anything older arriving later is reported as late
+    Watermark(u64),
+}
+
+/// Reorder buffer used in watermark mode
+struct WatermarkState {
+    delay_us: u64,
+    buffered: BTreeMap<(u64, u64), (usize, FrameMetadata, DataFrame)>,
+    arrivals: u64,
+    max_seen_us: u64,
+    current_us: u64,
+    signalled_us: u64,
+    emitted_since_signal: bool,
+    late_frames: u64,
+}
+
+/// Reader over several arenas that yields frames in timestamp order, tagging
+/// each with the arena it came from.
+///
+/// Every source holds at most one frame of lookahead, so the order is exact
+/// among frames already published and best effort against sources that are
+/// momentarily idle. `with_watermark` trades latency for a strict event-time order.
+///
+/// Timestamps are only comparable within one clock, so every source must be
+/// stamped on the same `ClockSource`: set it with `with_clock` on each source's
+/// config, the same as its writer's.
+pub struct MergeReader {
+    sources: Vec<MergeSource>,
+    source_column: String,
+    watermark: Option<WatermarkState>,
+    /// Clock every source's frames are stamped on
+    clock: ClockSource,
+    /// The first source's time source, for timeouts, polling and the watermark
+    time: Arc<dyn Clock>,
+}
+
+impl MergeReader {
+    /// Attach a reader to each named arena with default settings
+    pub fn new<S: AsRef<str>>(names: &[S]) -> Result<Self> {
+        Self::from_configs(names.iter().map(|name| SharedMemoryConfig::new(name.as_ref())))
+    }
+
+    /// Attach to each source; fails unless they all name the same `clock`
+    pub fn from_configs(configs: impl IntoIterator<Item = SharedMemoryConfig>) -> Result<Self> {
+        let configs: Vec<SharedMemoryConfig> = configs.into_iter().collect();
+        let Some(clock) = configs.first().map(|config| config.clock) else {
+            return Err(QADataSwapError::SharedMemory("MergeReader needs at least one source".to_string()));
+        };
+        if let Some(other) = configs.iter().find(|config| config.clock != clock) {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "MergeReader sources must share a clock: '{}' is on {:?}, '{}' on {:?}",
+                configs[0].name, clock, other.name, other.clock
+            )));
+        }
+
+        let sources = configs
+            .into_iter()
+            .map(|config| {
+                let name = config.name.clone();
+                Ok(MergeSource {
+                    name,
+                    reader: SharedDataFrame::create_reader(config)?,
+                    pending: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            clock,
+            time: Arc::clone(&sources[0].reader.arena.config.time_source),
+            sources,
+            source_column: "source".to_string(),
+            watermark: None,
+        })
+    }
+
+    /// Name of the appended column holding the source arena name (default `source`)
+    pub fn with_source_column(mut self, name: impl Into<String>) -> Self {
+        self.source_column = name.into();
+        self
+    }
+
+    /// Hold frames for up to `delay` and emit them in event-time order across all
+    /// sources, interleaved with `MergeEvent::Watermark` signals
+    pub fn with_watermark(mut self, delay: Duration) -> Self {
+        self.watermark = Some(WatermarkState {
+            delay_us: delay.as_micros() as u64,
+            buffered: BTreeMap::new(),
+            arrivals: 0,
+            max_seen_us: 0,
+            current_us: 0,
+            signalled_us: 0,
+            emitted_since_signal: false,
+            late_frames: 0,
+        });
+        self
+    }
+
+    /// Current watermark in microseconds on the sources' clock, since the Unix
+    /// epoch for `Realtime` (watermark mode only)
+    pub fn watermark(&self) -> Option<u64> {
+        self.watermark.as_ref().map(|w| w.current_us)
+    }
+
+    /// Frames that arrived behind an already signalled watermark
+    pub fn late_frames(&self) -> u64 {
+        self.watermark.as_ref().map_or(0, |w| w.late_frames)
+    }
+
+    /// Names of the merged arenas, in the order given
+    pub fn sources(&self) -> Vec<&str> {
+        self.sources.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    /// Next frame across all sources, with the source column appended
+    pub fn read(&mut self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
+        Ok(self.read_with_metadata(timeout_ms)?.map(|(_, df)| df))
+    }
+
+    /// Next frame across all sources along with its metadata. `None` (or a negative
+    /// timeout) waits indefinitely; watermark signals are skipped.
+    pub fn read_with_metadata(&mut self, timeout_ms: Option<i32>) -> Result<Option<(FrameMetadata, DataFrame)>> {
+        let deadline = self.deadline_for(timeout_ms);
+        loop {
+            match self.next_event_until(deadline)? {
+                Some(MergeEvent::Frame(metadata, df)) => return Ok(Some((metadata, df))),
+                Some(MergeEvent::Watermark(_)) => {},
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Next frame or watermark signal
+    pub fn next_event(&mut self, timeout_ms: Option<i32>) -> Result<Option<MergeEvent>> {
+        self.next_event_until(self.deadline_for(timeout_ms))
+    }
+
+    fn deadline_for(&self, timeout_ms: Option<i32>) -> Option<Instant> {
+        timeout_ms
+            .filter(|ms| *ms >= 0)
+            .map(|ms| self.time.instant() + Duration::from_millis(ms as u64))
+    }
+
+    fn next_event_until(&mut self, deadline: Option<Instant>) -> Result<Option<MergeEvent>> {
+        let mut backoff = Duration::from_micros(1);
+
+        loop {
+            self.fill_pending()?;
+
+            let event = match self.watermark {
+                Some(_) => self.next_watermarked()?,
+                None => match self.earliest_pending() {
+                    Some(idx) => {
+                        let (metadata, df) = self.sources[idx].pending.take().unwrap();
+                        Some(self.tagged(idx, metadata, df)?)
+                    },
+                    None => None,
+                },
+            };
+            if let Some(event) = event {
+                return Ok(Some(event));
+            }
+
+            if deadline.is_some_and(|deadline| self.time.instant() >= deadline) {
+                return Err(QADataSwapError::Timeout);
+            }
+
+            self.time.sleep(backoff);
+            backoff = (backoff * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+
+    /// Move lookahead frames into the reorder buffer, then release whatever the
+    /// watermark has passed, closing each run with a watermark signal
+    fn next_watermarked(&mut self) -> Result<Option<MergeEvent>> {
+        let state = self.watermark.as_mut().unwrap();
+
+        for (idx, source) in self.sources.iter_mut().enumerate() {
+            if let Some((metadata, df)) = source.pending.take() {
+                if metadata.timestamp_us <= state.signalled_us {
+                    state.late_frames += 1;
+                }
+                state.max_seen_us = state.max_seen_us.max(metadata.timestamp_us);
+                state.buffered.insert((metadata.timestamp_us, state.arrivals), (idx, metadata, df));
+                state.arrivals += 1;
+            }
+        }
+
+        let now_us = self.time.now_ns(self.clock) / 1000;
+        let horizon = state.max_seen_us.max(now_us).saturating_sub(state.delay_us);
+        state.current_us = state.current_us.max(horizon);
+
+        if let Some(entry) = state.buffered.first_entry() {
+            let timestamp_us = entry.key().0;
+            if timestamp_us <= state.current_us {
+                let (idx, metadata, df) = entry.remove();
+                state.emitted_since_signal = true;
+                return self.tagged(idx, metadata, df).map(Some);
+            }
+        }
+
+        if state.emitted_since_signal {
+            state.emitted_since_signal = false;
+            state.signalled_us = state.current_us;
+            return Ok(Some(MergeEvent::Watermark(state.current_us)));
+        }
+        Ok(None)
+    }
+
+    fn tagged(&self, idx: usize, metadata: FrameMetadata, mut df: DataFrame) -> Result<MergeEvent> {
+        let name = self.sources[idx].name.as_str();
+        let tag = Series::new(self.source_column.as_str().into(), vec![name; df.height()]);
+        df.with_column(tag).map_err(QADataSwapError::Polars)?;
+        Ok(MergeEvent::Frame(metadata, df))
+    }
+
+    /// Pull one frame of lookahead from every source that has none buffered
+    fn fill_pending(&mut self) -> Result<()> {
+        for source in self.sources.iter_mut().filter(|s| s.pending.is_none()) {
+            match source.reader.read_with_metadata(Some(0)) {
+                Ok(Some((metadata, _))) if metadata.clock != self.clock => {
+                    return Err(QADataSwapError::SharedMemory(format!(
+                        "Frame from '{}' is stamped on {:?}, the merge runs on {:?}",
+                        source.name, metadata.clock, self.clock
+                    )));
+                },
+                Ok(frame) => source.pending = frame,
+                Err(QADataSwapError::Timeout) => {},
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Source holding the oldest buffered frame; ties go to the earlier source
+    fn earliest_pending(&self) -> Option<usize> {
+        self.sources
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, s)| s.pending.as_ref().map(|(meta, _)| (meta.timestamp_us, meta.sequence, idx)))
+            .min()
+            .map(|(_, _, idx)| idx)
+    }
+
+    pub fn close(&self) {
+        for source in &self.sources {
+            source.reader.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{Event, MockClock};
+    use std::time::UNIX_EPOCH;
+
+    fn ids(df: &DataFrame) -> Result<Vec<i64>> {
+        Ok(df.column("id")?.i64()?.into_no_null_iter().collect())
+    }
+
+    fn source_of(event: Option<MergeEvent>) -> (String, Vec<i64>) {
+        match event {
+            Some(MergeEvent::Frame(_, df)) => {
+                let source = df.column("source").unwrap().str().unwrap().get(0).unwrap().to_string();
+                (source, ids(&df).unwrap())
+            },
+            other => panic!("expected a frame, got {:?}", other),
+        }
+    }
+
+    fn frame(id: i64) -> DataFrame {
+        df! { "id" => [id] }.unwrap()
+    }
+
+    /// Writer and merge config on its own mock clock, so each source can stamp
+    /// frames at any time regardless of publish order
+    fn clocked(name: &str, at_secs: u64) -> (SharedMemoryConfig, Arc<MockClock>) {
+        let clock = Arc::new(MockClock::new(UNIX_EPOCH + Duration::from_secs(at_secs)));
+        (test_config(name, 8).with_time_source(clock.clone()), clock)
+    }
+
+    #[test]
+    fn test_merge_orders_frames_published_out_of_order() -> Result<()> {
+        let (late_cfg, late_clock) = clocked("merge_order_late", 1_020);
+        let (early_cfg, early_clock) = clocked("merge_order_early", 1_010);
+        let late = SharedDataFrame::create_writer(late_cfg.clone())?;
+        let early = SharedDataFrame::create_writer(early_cfg.clone())?;
+        let mut merged = MergeReader::from_configs([late_cfg, early_cfg])?;
+
+        // The later-stamped source publishes everything first
+        late.write(&frame(20))?;
+        late_clock.advance(Duration::from_secs(20));
+        late.write(&frame(40))?;
+        early.write(&frame(10))?;
+        early_clock.advance(Duration::from_secs(20));
+        early.write(&frame(30))?;
+
+        let mut out = Vec::new();
+        for _ in 0..4 {
+            let (metadata, df) = merged.read_with_metadata(Some(0))?.unwrap();
+            out.push((metadata.timestamp_us, ids(&df)?[0]));
+        }
+        assert!(matches!(merged.read(Some(0)), Err(QADataSwapError::Timeout)));
+        assert_eq!(
+            out,
+            vec![(1_010_000_000, 10), (1_020_000_000, 20), (1_030_000_000, 30), (1_040_000_000, 40)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_breaks_ties_by_sequence_then_source() -> Result<()> {
+        let (a_cfg, _) = clocked("merge_tie_a", 1_000);
+        let (b_cfg, _) = clocked("merge_tie_b", 1_000);
+        let a = SharedDataFrame::create_writer(a_cfg.clone())?;
+        let b = SharedDataFrame::create_writer(b_cfg.clone())?;
+        let mut merged = MergeReader::from_configs([a_cfg, b_cfg])?;
+
+        // All stamped at the same instant: (timestamp, sequence, source index)
+        b.write(&frame(2))?;
+        a.write(&frame(1))?;
+        a.write(&frame(3))?;
+        let order: Vec<_> = (0..3).map(|_| source_of(merged.next_event(Some(0)).unwrap())).collect();
+        assert_eq!(
+            order,
+            vec![
+                ("merge_tie_a".to_string(), vec![1]),
+                ("merge_tie_b".to_string(), vec![2]),
+                ("merge_tie_a".to_string(), vec![3]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_tags_rows_with_their_source() -> Result<()> {
+        let (a_cfg, _) = clocked("merge_tag_a", 1_000);
+        let a = SharedDataFrame::create_writer(a_cfg.clone())?;
+        let mut merged = MergeReader::from_configs([a_cfg])?.with_source_column("venue");
+        assert_eq!(merged.sources(), vec!["merge_tag_a"]);
+
+        a.write(&df! { "id" => [1i64, 2, 3] }?)?;
+        let df = merged.read(Some(0))?.unwrap();
+        let venues: Vec<_> = df.column("venue")?.str()?.into_no_null_iter().collect();
+        assert_eq!(venues, vec!["merge_tag_a"; 3]);
+        assert_eq!(ids(&df)?, vec![1, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_continues_past_one_source_ending() -> Result<()> {
+        let (a_cfg, clock) = clocked("merge_eos_a", 1_000);
+        let b_cfg = test_config("merge_eos_b", 8).with_time_source(clock.clone());
+        let a = SharedDataFrame::create_writer(a_cfg.clone())?;
+        let b = SharedDataFrame::create_writer(b_cfg.clone())?;
+        let mut merged = MergeReader::from_configs([a_cfg, b_cfg])?;
+
+        a.write(&frame(1))?;
+        a.write_event(&Event::Eos)?;
+        clock.advance(Duration::from_millis(1));
+        b.write(&frame(2))?;
+        clock.advance(Duration::from_millis(1));
+        b.write(&frame(3))?;
+
+        let ids: Vec<_> = (0..3).map(|_| source_of(merged.next_event(Some(0)).unwrap()).1[0]).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert!(matches!(merged.read(Some(0)), Err(QADataSwapError::Timeout)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_timeout_follows_the_time_source() -> Result<()> {
+        let (a_cfg, clock) = clocked("merge_timeout_a", 1_000);
+        let b_cfg = test_config("merge_timeout_b", 8).with_time_source(clock.clone());
+        let _a = SharedDataFrame::create_writer(a_cfg.clone())?;
+        let _b = SharedDataFrame::create_writer(b_cfg.clone())?;
+        let mut merged = MergeReader::from_configs([a_cfg, b_cfg])?;
+
+        assert!(matches!(merged.read(Some(0)), Err(QADataSwapError::Timeout)));
+        assert!(matches!(merged.next_event(Some(50)), Err(QADataSwapError::Timeout)));
+        assert!(clock.elapsed() >= Duration::from_millis(50));
+        Ok(())
+    }
+
+    #[test]
+    fn test_watermark_holds_frames_for_the_delay() -> Result<()> {
+        let (a_cfg, clock) = clocked("merge_delay_a", 1_000);
+        let b_cfg = test_config("merge_delay_b", 8).with_time_source(clock.clone());
+        let a = SharedDataFrame::create_writer(a_cfg.clone())?;
+        let b = SharedDataFrame::create_writer(b_cfg.clone())?;
+        let mut merged = MergeReader::from_configs([a_cfg, b_cfg])?.with_watermark(Duration::from_millis(10));
+
+        a.write(&frame(1))?;
+        clock.advance(Duration::from_millis(5));
+        b.write(&frame(2))?;
+        assert!(matches!(merged.next_event(Some(0)), Err(QADataSwapError::Timeout)));
+
+        // Each run of released frames closes with the watermark it reached
+        clock.advance(Duration::from_millis(5));
+        assert_eq!(source_of(merged.next_event(Some(0))?), ("merge_delay_a".to_string(), vec![1]));
+        assert!(matches!(merged.next_event(Some(0))?, Some(MergeEvent::Watermark(1_000_000_000))));
+        assert!(matches!(merged.next_event(Some(0)), Err(QADataSwapError::Timeout)));
+
+        clock.advance(Duration::from_millis(5));
+        assert_eq!(source_of(merged.next_event(Some(0))?), ("merge_delay_b".to_string(), vec![2]));
+        assert!(matches!(merged.next_event(Some(0))?, Some(MergeEvent::Watermark(1_000_005_000))));
+        assert_eq!(merged.watermark(), Some(1_000_005_000));
+
+        // Plain reads skip the signals
+        b.write(&frame(3))?;
+        clock.advance(Duration::from_millis(10));
+        a.write(&frame(4))?;
+        assert_eq!(ids(&merged.read(Some(0))?.unwrap())?, vec![3]);
+        assert!(matches!(merged.read(Some(0)), Err(QADataSwapError::Timeout)));
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(ids(&merged.read(Some(0))?.unwrap())?, vec![4]);
+        assert_eq!(merged.late_frames(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_watermark_counts_late_frames() -> Result<()> {
+        let (on_time_cfg, on_time_clock) = clocked("merge_late_on_time", 1_000);
+        let (lagging_cfg, _) = clocked("merge_late_lagging", 999);
+        let on_time = SharedDataFrame::create_writer(on_time_cfg.clone())?;
+        let lagging = SharedDataFrame::create_writer(lagging_cfg.clone())?;
+        let mut merged =
+            MergeReader::from_configs([on_time_cfg, lagging_cfg])?.with_watermark(Duration::from_millis(10));
+
+        on_time.write(&frame(1))?;
+        on_time_clock.advance(Duration::from_millis(10));
+        assert_eq!(source_of(merged.next_event(Some(0))?).1, vec![1]);
+        assert!(matches!(merged.next_event(Some(0))?, Some(MergeEvent::Watermark(1_000_000_000))));
+
+        // Stamped a second behind the signalled watermark: still delivered, but counted
+        lagging.write(&frame(2))?;
+        assert_eq!(source_of(merged.next_event(Some(0))?), ("merge_late_lagging".to_string(), vec![2]));
+        assert_eq!(merged.late_frames(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_watermark_on_monotonic_sources() -> Result<()> {
+        let clock = Arc::new(MockClock::default());
+        clock.advance(Duration::from_secs(1));
+        let config = |name: &str| test_config(name, 8).with_clock(ClockSource::Monotonic).with_time_source(clock.clone());
+        let quotes = SharedDataFrame::create_writer(config("merge_mono_quotes"))?;
+        let fills = SharedDataFrame::create_writer(config("merge_mono_fills"))?;
+
+        let mixed = [config("merge_mono_quotes"), test_config("merge_mono_fills", 8)];
+        assert!(MergeReader::from_configs(mixed).is_err());
+
+        let mut merged = MergeReader::from_configs([config("merge_mono_quotes"), config("merge_mono_fills")])?
+            .with_watermark(Duration::from_millis(10));
+        quotes.write(&df! { "id" => [1i64] }?)?;
+        clock.advance(Duration::from_millis(2));
+        fills.write(&df! { "id" => [2i64] }?)?;
+
+        // Held until the monotonic clock is a delay past each frame
+        assert!(matches!(merged.next_event(Some(0)), Err(QADataSwapError::Timeout)));
+        clock.advance(Duration::from_millis(9));
+        assert_eq!(source_of(merged.next_event(Some(0))?), ("merge_mono_quotes".to_string(), vec![1]));
+        assert!(matches!(merged.next_event(Some(0))?, Some(MergeEvent::Watermark(1_001_000))));
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(source_of(merged.next_event(Some(0))?), ("merge_mono_fills".to_string(), vec![2]));
+        assert_eq!(merged.late_frames(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! Typed rows over a DataFrame channel: structs in, structs out
+//!
+//! Implement `SwapMessage` by hand or, with the `derive` feature, with
+//! `#[derive(SwapMessage)]`; each field becomes a column of the same name.
+
+use polars::prelude::*;
+
+use crate::{QADataSwapError, Result};
+
+#[cfg(feature = "derive")]
+pub use qadataswap_derive::SwapMessage;
+
+/// A row type with a fixed mapping to DataFrame columns
+pub trait SwapMessage: Sized {
+    fn to_dataframe(rows: &[Self]) -> Result<DataFrame>;
+    fn from_dataframe(df: &DataFrame) -> Result<Vec<Self>>;
+}
+
+/// A field type `#[derive(SwapMessage)]` can map to a column. `Option<T>`
+/// fields are nullable; the others reject null values.
+pub trait SwapField: Sized {
+    fn to_column(name: &str, values: Vec<Self>) -> Column;
+    fn from_column(column: &Column) -> Result<Vec<Self>>;
+}
+
+fn has_nulls(column: &Column) -> Result<()> {
+    if column.null_count() > 0 {
+        return Err(QADataSwapError::SharedMemory(format!(
+            "Column '{}' has nulls; use an Option field", column.name()
+        )));
+    }
+    Ok(())
+}
+
+macro_rules! swap_field {
+    ($($ty:ty => $accessor:ident),* $(,)?) => {$(
+        impl SwapField for $ty {
+            fn to_column(name: &str, values: Vec<Self>) -> Column {
+                Column::new(name.into(), values)
+            }
+
+            fn from_column(column: &Column) -> Result<Vec<Self>> {
+                has_nulls(column)?;
+                Ok(column.$accessor()?.into_no_null_iter().collect())
+            }
+        }
+
+        impl SwapField for Option<$ty> {
+            fn to_column(name: &str, values: Vec<Self>) -> Column {
+                Column::new(name.into(), values)
+            }
+
+            fn from_column(column: &Column) -> Result<Vec<Self>> {
+                Ok(column.$accessor()?.into_iter().collect())
+            }
+        }
+    )*};
+}
+
+swap_field!(i32 => i32, i64 => i64, u32 => u32, u64 => u64, f32 => f32, f64 => f64, bool => bool);
+
+impl SwapField for String {
+    fn to_column(name: &str, values: Vec<Self>) -> Column {
+        Column::new(name.into(), values)
+    }
+
+    fn from_column(column: &Column) -> Result<Vec<Self>> {
+        has_nulls(column)?;
+        Ok(column.str()?.into_no_null_iter().map(str::to_string).collect())
+    }
+}
+
+impl SwapField for Option<String> {
+    fn to_column(name: &str, values: Vec<Self>) -> Column {
+        Column::new(name.into(), values)
+    }
+
+    fn from_column(column: &Column) -> Result<Vec<Self>> {
+        Ok(column.str()?.into_iter().map(|value| value.map(str::to_string)).collect())
+    }
+}
+
+/// Used by the derive macro's generated code
+#[doc(hidden)]
+pub mod __private {
+    pub use polars::prelude::DataFrame;
+    use polars::prelude::*;
+
+    use crate::{QADataSwapError, Result};
+
+    pub fn frame(columns: Vec<Column>) -> Result<DataFrame> {
+        DataFrame::new(columns).map_err(QADataSwapError::Polars)
+    }
+
+    pub fn column<'a>(df: &'a DataFrame, name: &str) -> Result<&'a Column> {
+        df.column(name).map_err(QADataSwapError::Polars)
+    }
+
+    pub fn height(df: &DataFrame) -> usize {
+        df.height()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "derive", derive(SwapMessage))]
+    struct Fill {
+        symbol: String,
+        #[cfg_attr(feature = "derive", swap(rename = "px"))]
+        price: f64,
+        venue: Option<String>,
+    }
+
+    #[cfg(not(feature = "derive"))]
+    impl SwapMessage for Fill {
+        fn to_dataframe(rows: &[Self]) -> Result<DataFrame> {
+            __private::frame(vec![
+                String::to_column("symbol", rows.iter().map(|r| r.symbol.clone()).collect()),
+                f64::to_column("px", rows.iter().map(|r| r.price).collect()),
+                Option::<String>::to_column("venue", rows.iter().map(|r| r.venue.clone()).collect()),
+            ])
+        }
+
+        fn from_dataframe(df: &DataFrame) -> Result<Vec<Self>> {
+            let symbols = String::from_column(df.column("symbol")?)?;
+            let prices = f64::from_column(df.column("px")?)?;
+            let venues = Option::<String>::from_column(df.column("venue")?)?;
+            Ok(symbols
+                .into_iter()
+                .zip(prices)
+                .zip(venues)
+                .map(|((symbol, price), venue)| Fill { symbol, price, venue })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_rows_round_trip() -> Result<()> {
+        let config = test_config("message_fills", 4);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let fills = vec![
+            Fill { symbol: "AAPL".to_string(), price: 1.5, venue: Some("XNAS".to_string()) },
+            Fill { symbol: "MSFT".to_string(), price: 2.5, venue: None },
+        ];
+        writer.write_rows(&fills)?;
+        assert_eq!(reader.read_rows::<Fill>(Some(100))?, Some(fills));
+
+        // Nulls only map to Option fields
+        writer.write(&df! { "symbol" => [None::<&str>], "px" => [1.0], "venue" => [None::<&str>] }?)?;
+        assert!(reader.read_rows::<Fill>(Some(100)).is_err());
+        Ok(())
+    }
+}
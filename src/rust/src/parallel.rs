@@ -0,0 +1,116 @@
+//! Column-group frames: a wide frame split into several Arrow IPC files that
+//! are encoded and decoded concurrently
+//!
+//! Payload: group count (u32), one u64 length per group, then the groups'
+//! IPC files back to back. Writers only produce these with the `parallel`
+//! feature; every reader can decode them, on the rayon pool when the feature
+//! is enabled and one group after another otherwise.
+
+use polars::prelude::*;
+
+use crate::{decode_dataframe, QADataSwapError, Result};
+
+/// Narrower frames are cheaper to encode in one piece
+#[cfg(feature = "parallel")]
+pub(crate) const MIN_PARALLEL_COLUMNS: usize = 8;
+
+/// Smaller frames are cheaper to encode in one piece
+#[cfg(feature = "parallel")]
+pub(crate) const MIN_PARALLEL_BYTES: usize = 1024 * 1024;
+
+/// Whether `df` is worth splitting across the rayon pool
+#[cfg(feature = "parallel")]
+pub(crate) fn should_split(df: &DataFrame) -> bool {
+    rayon::current_num_threads() > 1 && df.width() >= MIN_PARALLEL_COLUMNS && df.estimated_size() >= MIN_PARALLEL_BYTES
+}
+
+/// Append the column-group payload for `df` to `buffer`
+#[cfg(feature = "parallel")]
+pub(crate) fn encode_groups(df: &DataFrame, compression: Option<IpcCompression>, buffer: &mut Vec<u8>) -> Result<()> {
+    use rayon::prelude::*;
+
+    let columns = df.get_columns();
+    let group_count = rayon::current_num_threads().min(columns.len()).max(1);
+    let per_group = columns.len().div_ceil(group_count);
+    let files = columns
+        .par_chunks(per_group)
+        .map(|group| {
+            let mut part = DataFrame::new(group.to_vec())?;
+            let mut file = Vec::new();
+            IpcWriter::new(&mut file).with_compression(compression).finish(&mut part)?;
+            Ok(file)
+        })
+        .collect::<Result<Vec<Vec<u8>>>>()?;
+
+    buffer.extend_from_slice(&(files.len() as u32).to_le_bytes());
+    for file in &files {
+        buffer.extend_from_slice(&(file.len() as u64).to_le_bytes());
+    }
+    for file in &files {
+        buffer.extend_from_slice(file);
+    }
+    Ok(())
+}
+
+/// The IPC files of a column-group payload
+fn split_groups(payload: &[u8]) -> Result<Vec<&[u8]>> {
+    let malformed = || QADataSwapError::SharedMemory("Malformed column-group frame".to_string());
+    let count = u32::from_le_bytes(payload.get(..4).ok_or_else(malformed)?.try_into().unwrap()) as usize;
+    let mut pos = 4 + count * 8;
+    let lengths = payload.get(4..pos).ok_or_else(malformed)?;
+
+    let mut files = Vec::with_capacity(count);
+    for length in lengths.chunks_exact(8) {
+        let length = u64::from_le_bytes(length.try_into().unwrap()) as usize;
+        files.push(payload.get(pos..).and_then(|rest| rest.get(..length)).ok_or_else(malformed)?);
+        pos += length;
+    }
+    Ok(files)
+}
+
+/// Decode a column-group payload back into one frame
+pub(crate) fn decode_groups(payload: &[u8]) -> Result<DataFrame> {
+    let files = split_groups(payload)?;
+
+    #[cfg(feature = "parallel")]
+    let parts = {
+        use rayon::prelude::*;
+        files.par_iter().map(|file| decode_dataframe(file)).collect::<Result<Vec<_>>>()?
+    };
+    #[cfg(not(feature = "parallel"))]
+    let parts = files.iter().map(|file| decode_dataframe(file)).collect::<Result<Vec<_>>>()?;
+
+    let columns = parts.into_iter().flat_map(DataFrame::take_columns).collect();
+    DataFrame::new(columns).map_err(QADataSwapError::Polars)
+}
+
+#[cfg(all(test, feature = "parallel"))]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+
+    #[test]
+    fn test_wide_frames_round_trip_in_groups() -> Result<()> {
+        let config = test_config("parallel_wide", 2).with_size_mb(8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let columns = (0..16)
+            .map(|i| Column::new(format!("c{}", i).into(), (0..20_000i64).map(|v| v * i).collect::<Vec<_>>()))
+            .collect();
+        let df = DataFrame::new(columns)?;
+
+        // Splitting follows the pool size, whatever the host's core count
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        pool.install(|| -> Result<()> {
+            let mut payload = Vec::new();
+            encode_groups(&df, None, &mut payload)?;
+            assert_eq!(split_groups(&payload)?.len(), 4);
+            assert!(should_split(&df));
+            writer.write(&df)
+        })?;
+        assert_eq!(reader.read(Some(1000))?, Some(df));
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! One logical channel split by key across several arenas, so consumers scale
+//! out by taking a partition each
+//!
+//! Rows are routed by a hash of the key column (usually `symbol`); partition
+//! `i` of `n` is the arena `<name>#part<i>`. The hash is FNV-1a over the key's
+//! string form, so every process and language agrees on where a key lives,
+//! see `partition_for`. Each partition records `<i>/<n>` under
+//! `PARTITION_META_KEY` so a reader can tell how the channel is split.
+
+use polars::prelude::*;
+
+use crate::priority::LANE_SEPARATOR;
+use crate::{Event, QADataSwapError, Result, SharedDataFrame, SharedMemoryConfig};
+
+pub const PARTITION_META_KEY: &str = "__partition";
+
+const MAX_PARTITIONS: usize = 1024;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Partition `key` is routed to out of `partitions`
+pub fn partition_for(key: &str, partitions: usize) -> usize {
+    let hash = key.bytes().fold(FNV_OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME));
+    (hash % partitions.max(1) as u64) as usize
+}
+
+fn partition_config(config: &SharedMemoryConfig, partition: usize) -> SharedMemoryConfig {
+    let mut part = config.clone();
+    part.name = format!("{}{}part{}", config.name, LANE_SEPARATOR, partition);
+    part
+}
+
+/// Writer routing each frame's rows to the partitions their keys hash to
+pub struct PartitionedWriter {
+    partitions: Vec<SharedDataFrame>,
+    key_column: String,
+}
+
+impl PartitionedWriter {
+    /// Create `partitions` arenas named after `config.name`, keyed on `key_column`
+    pub fn new(config: SharedMemoryConfig, partitions: usize, key_column: impl Into<String>) -> Result<Self> {
+        if partitions == 0 || partitions > MAX_PARTITIONS {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Partition count must be between 1 and {}, got {}", MAX_PARTITIONS, partitions
+            )));
+        }
+        let partitions = (0..partitions)
+            .map(|i| {
+                let writer = SharedDataFrame::create_writer(partition_config(&config, i))?;
+                writer.put_meta(PARTITION_META_KEY, &format!("{}/{}", i, partitions))?;
+                Ok(writer)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            partitions,
+            key_column: key_column.into(),
+        })
+    }
+
+    pub fn partition_count(&self) -> usize {
+        self.partitions.len()
+    }
+
+    /// Split `df` by key and write each partition's rows, in their original
+    /// order; partitions with no rows get no frame. Null keys go to partition 0.
+    pub fn write(&self, df: &DataFrame) -> Result<()> {
+        let keys = df.column(&self.key_column)?.cast(&DataType::String)?;
+        let mut rows: Vec<Vec<IdxSize>> = vec![Vec::new(); self.partitions.len()];
+        for (row, key) in keys.str()?.into_iter().enumerate() {
+            let partition = key.map_or(0, |key| partition_for(key, self.partitions.len()));
+            rows[partition].push(row as IdxSize);
+        }
+
+        for (partition, rows) in self.partitions.iter().zip(rows) {
+            if rows.is_empty() {
+                continue;
+            }
+            let part = match rows.len() == df.height() {
+                true => df.clone(),
+                false => df.take(&IdxCa::from_vec("rows".into(), rows))?,
+            };
+            partition.write(&part)?;
+        }
+        Ok(())
+    }
+
+    /// Data is routed as `write` does; control frames go to every partition
+    pub fn write_event(&self, event: &Event) -> Result<()> {
+        match event {
+            Event::Data(df) => self.write(df),
+            event => self.partitions.iter().try_for_each(|partition| partition.write_event(event)),
+        }
+    }
+}
+
+/// Reader of a single partition of a partitioned channel
+pub struct PartitionedReader {
+    reader: SharedDataFrame,
+    partition: usize,
+}
+
+impl PartitionedReader {
+    pub fn attach(config: SharedMemoryConfig, partition: usize) -> Result<Self> {
+        let reader = SharedDataFrame::create_reader(partition_config(&config, partition))?;
+        Ok(Self { reader, partition })
+    }
+
+    pub fn partition(&self) -> usize {
+        self.partition
+    }
+
+    /// How many partitions the writer split the channel into
+    pub fn partition_count(&self) -> Option<usize> {
+        let value = self.reader.get_meta(PARTITION_META_KEY)?;
+        value.split_once('/')?.1.parse().ok()
+    }
+
+    pub fn read(&self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
+        self.reader.read(timeout_ms)
+    }
+
+    pub fn read_event(&self, timeout_ms: Option<i32>) -> Result<Option<Event>> {
+        self.reader.read_event(timeout_ms)
+    }
+
+    /// The partition's own channel, for anything beyond reading frames
+    pub fn channel(&self) -> &SharedDataFrame {
+        &self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_rows_follow_their_key_partition() -> Result<()> {
+        let config = test_config("partitioned", 4);
+        let writer = PartitionedWriter::new(config.clone(), 3, "symbol")?;
+        let readers = (0..3).map(|i| PartitionedReader::attach(config.clone(), i)).collect::<Result<Vec<_>>>()?;
+
+        let symbols = ["AAPL", "MSFT", "GOOG", "TSLA", "AMZN", "AAPL", "NVDA", "MSFT"];
+        let df = df! {
+            "symbol" => symbols,
+            "px" => (0..symbols.len()).map(|i| i as f64).collect::<Vec<_>>(),
+        }?;
+        writer.write(&df)?;
+        writer.write_event(&Event::Eos)?;
+
+        let mut total = 0;
+        for reader in &readers {
+            assert_eq!(reader.partition_count(), Some(3));
+            if let Some(Event::Data(part)) = reader.read_event(Some(100))? {
+                for symbol in part.column("symbol")?.str()?.into_no_null_iter() {
+                    assert_eq!(partition_for(symbol, 3), reader.partition());
+                }
+                total += part.height();
+                assert!(matches!(reader.read_event(Some(100))?, Some(Event::Eos)));
+            }
+        }
+        assert_eq!(total, df.height());
+        assert!(PartitionedWriter::new(SharedMemoryConfig::new("partitioned_none"), 0, "symbol").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! Channel settings that can be tuned while producer and consumer keep running
+//!
+//! `update_policy` merges a `PolicyUpdate` into the control block's metadata
+//! entry `POLICY_META_KEY`, stamped with a version. Every arena attached to the
+//! segment, on either side, applies a new version on its next read or write,
+//! checking at most once per `POLICY_POLL_INTERVAL`.
+//!
+//! The entry is `<version>;rate=frames:100;compression=lz4;ack=1;validation=warn`
+//! with only the settings updated so far. Concurrent updates from several
+//! processes are last-writer-wins per entry, not per setting.
+
+use std::time::{Duration, Instant};
+
+use polars::prelude::IpcCompression;
+
+use crate::validators::ValidationPolicy;
+use crate::{QADataSwapError, RateLimit, Result};
+
+/// Metadata key holding the channel's policy
+pub const POLICY_META_KEY: &str = "__policy";
+
+/// How often an arena looks for a new policy version
+pub const POLICY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Settings to change; `None` fields keep their current value
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PolicyUpdate {
+    /// Writer pacing, `Some(None)` to lift the limit
+    pub rate_limit: Option<Option<RateLimit>>,
+    /// Writer compression, `Some(None)` to stop compressing
+    pub compression: Option<Option<IpcCompression>>,
+    /// Backpressure: whether the writer waits for readers to ack frames
+    pub ack_mode: Option<bool>,
+    /// Policy of the validators configured on each side
+    pub validation: Option<ValidationPolicy>,
+}
+
+impl PolicyUpdate {
+    pub fn with_rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    pub fn with_compression(mut self, compression: Option<IpcCompression>) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    pub fn with_ack_mode(mut self, enabled: bool) -> Self {
+        self.ack_mode = Some(enabled);
+        self
+    }
+
+    pub fn with_validation_policy(mut self, policy: ValidationPolicy) -> Self {
+        self.validation = Some(policy);
+        self
+    }
+
+    /// `self` with every setting `newer` changes replaced
+    pub(crate) fn merge(self, newer: PolicyUpdate) -> Self {
+        Self {
+            rate_limit: newer.rate_limit.or(self.rate_limit),
+            compression: newer.compression.or(self.compression),
+            ack_mode: newer.ack_mode.or(self.ack_mode),
+            validation: newer.validation.or(self.validation),
+        }
+    }
+
+    pub(crate) fn encode(&self, version: u64) -> String {
+        let mut entry = version.to_string();
+        if let Some(limit) = self.rate_limit {
+            entry += match limit {
+                Some(RateLimit::FramesPerSec(rate)) => format!(";rate=frames:{}", rate),
+                Some(RateLimit::BytesPerSec(rate)) => format!(";rate=bytes:{}", rate),
+                None => ";rate=none".to_string(),
+            }
+            .as_str();
+        }
+        if let Some(compression) = self.compression {
+            entry += match compression {
+                Some(IpcCompression::LZ4) => ";compression=lz4",
+                Some(IpcCompression::ZSTD) => ";compression=zstd",
+                None => ";compression=none",
+            };
+        }
+        if let Some(ack_mode) = self.ack_mode {
+            entry += if ack_mode { ";ack=1" } else { ";ack=0" };
+        }
+        if let Some(validation) = self.validation {
+            entry += match validation {
+                ValidationPolicy::Reject => ";validation=reject",
+                ValidationPolicy::Warn => ";validation=warn",
+                ValidationPolicy::Tag => ";validation=tag",
+            };
+        }
+        entry
+    }
+
+    pub(crate) fn decode(entry: &str) -> Result<(u64, Self)> {
+        let malformed = || QADataSwapError::SharedMemory(format!("Malformed policy entry '{}'", entry));
+        let mut fields = entry.split(';');
+        let version = fields.next().and_then(|v| v.parse().ok()).ok_or_else(malformed)?;
+
+        let mut update = PolicyUpdate::default();
+        for field in fields {
+            let (key, value) = field.split_once('=').ok_or_else(malformed)?;
+            match (key, value) {
+                ("rate", "none") => update.rate_limit = Some(None),
+                ("rate", rate) => {
+                    let limit = match rate.split_once(':') {
+                        Some(("frames", rate)) => RateLimit::FramesPerSec(rate.parse().map_err(|_| malformed())?),
+                        Some(("bytes", rate)) => RateLimit::BytesPerSec(rate.parse().map_err(|_| malformed())?),
+                        _ => return Err(malformed()),
+                    };
+                    update.rate_limit = Some(Some(limit));
+                },
+                ("compression", "lz4") => update.compression = Some(Some(IpcCompression::LZ4)),
+                ("compression", "zstd") => update.compression = Some(Some(IpcCompression::ZSTD)),
+                ("compression", "none") => update.compression = Some(None),
+                ("ack", "1") => update.ack_mode = Some(true),
+                ("ack", "0") => update.ack_mode = Some(false),
+                ("validation", "reject") => update.validation = Some(ValidationPolicy::Reject),
+                ("validation", "warn") => update.validation = Some(ValidationPolicy::Warn),
+                ("validation", "tag") => update.validation = Some(ValidationPolicy::Tag),
+                _ => return Err(malformed()),
+            }
+        }
+        Ok((version, update))
+    }
+}
+
+/// An arena's view of the shared policy
+#[derive(Debug)]
+pub(crate) struct PolicyState {
+    /// Version last applied, 0 before any
+    pub(crate) version: u64,
+    /// Last look at the control block; `None` checks on the next frame
+    pub(crate) checked_at: Option<Instant>,
+    pub(crate) compression: Option<IpcCompression>,
+}
+
+impl PolicyState {
+    pub(crate) fn new(compression: Option<IpcCompression>) -> Self {
+        Self {
+            version: 0,
+            checked_at: None,
+            compression,
+        }
+    }
+
+    /// Whether the poll interval has passed at `now`; restarts it if so
+    pub(crate) fn due(&mut self, now: Instant) -> bool {
+        if self.checked_at.is_some_and(|at| now.duration_since(at) < POLICY_POLL_INTERVAL) {
+            return false;
+        }
+        self.checked_at = Some(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::validators::{Check, Validators};
+    use crate::SharedDataFrame;
+    use polars::df;
+    use std::thread;
+
+    #[test]
+    fn test_reader_retunes_the_writer() -> Result<()> {
+        let update = PolicyUpdate::default()
+            .with_rate_limit(Some(RateLimit::BytesPerSec(1.5e6)))
+            .with_compression(None)
+            .with_validation_policy(ValidationPolicy::Tag);
+        assert_eq!(PolicyUpdate::decode(&update.encode(7))?, (7, update));
+        assert!(PolicyUpdate::decode("1;rate=fast").is_err());
+
+        let validators = Validators::new(ValidationPolicy::Reject).with_check(Check::range("px", Some(0.0), None));
+        let writer = SharedDataFrame::create_writer(
+            test_config("policy_ticks", 32)
+                .with_rate_limit(RateLimit::FramesPerSec(20.0))
+                .with_validators(validators),
+        )?;
+        let reader = SharedDataFrame::create_reader(test_config("policy_ticks", 32))?;
+        assert!(writer.write(&df! { "px" => [-1.0] }?).is_err());
+
+        reader.update_policy(PolicyUpdate::default().with_validation_policy(ValidationPolicy::Warn))?;
+        reader.update_policy(PolicyUpdate::default().with_rate_limit(None))?;
+        thread::sleep(POLICY_POLL_INTERVAL);
+
+        // Twenty frames would take a second at the original rate
+        let started = Instant::now();
+        for _ in 0..20 {
+            writer.write(&df! { "px" => [-1.0] }?)?;
+        }
+        assert!(started.elapsed() < Duration::from_millis(500), "{:?}", started.elapsed());
+        assert_eq!(reader.read(Some(100))?.unwrap().column("px")?.f64()?.get(0), Some(-1.0));
+        assert!(writer.get_meta(POLICY_META_KEY).unwrap().contains("rate=none;validation=warn"));
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+//! Starting configurations for common workloads, see `SharedMemoryConfig::preset`
+
+use std::time::Duration;
+
+use crate::{AdaptiveWait, SharedMemoryConfig, WaitStrategy};
+
+/// Ring geometry, wait strategy and backpressure suited to a kind of channel.
+/// Apply one first and override single settings with the `with_*` builders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Many small frames (ticks, order book deltas) read with low latency:
+    /// 32 MB in 256 slots, readers spin 50µs before sleeping, no acks so a
+    /// slow reader never stalls the feed
+    TickStream,
+    /// Periodic full snapshots of a table (positions, instrument lists) where
+    /// readers want the latest: 64 MB in 4 slots, blocking reads, no acks
+    SnapshotTable,
+    /// Large frames that must all arrive (end-of-day files, backfills):
+    /// 1 GB in 8 slots, blocking reads, and the writer waits for readers to
+    /// acknowledge each frame before reusing its slot
+    BulkTransfer,
+}
+
+impl Preset {
+    pub(crate) fn apply(self, config: SharedMemoryConfig) -> SharedMemoryConfig {
+        match self {
+            Preset::TickStream => config
+                .with_size_mb(32)
+                .with_buffer_count(256)
+                .with_wait_strategy(WaitStrategy::Adaptive(
+                    AdaptiveWait::new(Duration::from_micros(50)).with_yields(64),
+                ))
+                .with_ack_mode(false),
+            Preset::SnapshotTable => config
+                .with_size_mb(64)
+                .with_buffer_count(4)
+                .with_wait_strategy(WaitStrategy::Blocking)
+                .with_ack_mode(false),
+            Preset::BulkTransfer => config
+                .with_size_mb(1024)
+                .with_buffer_count(8)
+                .with_wait_strategy(WaitStrategy::Blocking)
+                .with_ack_mode(true),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Priority lanes: a second ring per channel that readers drain ahead of normal data
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Separates the arena name from the lane in a lane's segment name
+pub(crate) const LANE_SEPARATOR: char = '#';
+
+/// How often a reader idle on both lanes re-checks the high lane
+pub(crate) const LANE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Lane a frame is written to, see `SharedMemoryConfig::with_priority_lanes`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Risk limits, kill switches and other commands that must overtake data
+    High,
+    #[default]
+    Normal,
+}
+
+impl Priority {
+    fn as_str(self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+        }
+    }
+}
+
+/// Name of the arena carrying `priority` frames for the channel `name`
+pub(crate) fn lane_name(name: &str, priority: Priority) -> String {
+    format!("{}{}{}", name, LANE_SEPARATOR, priority.as_str())
+}
+
+/// Reader side: which lane to try first, so a burst of high frames cannot
+/// starve normal data indefinitely
+pub(crate) struct LaneScheduler {
+    max_high_burst: usize,
+    high_streak: AtomicUsize,
+}
+
+impl LaneScheduler {
+    pub(crate) fn new(max_high_burst: usize) -> Self {
+        Self {
+            max_high_burst: max_high_burst.max(1),
+            high_streak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Lanes in the order to try for the next frame
+    pub(crate) fn order(&self) -> [Priority; 2] {
+        match self.high_streak.load(Ordering::Relaxed) >= self.max_high_burst {
+            true => [Priority::Normal, Priority::High],
+            false => [Priority::High, Priority::Normal],
+        }
+    }
+
+    pub(crate) fn served(&self, priority: Priority) {
+        match priority {
+            Priority::High => self.high_streak.fetch_add(1, Ordering::Relaxed),
+            Priority::Normal => self.high_streak.swap(0, Ordering::Relaxed),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::*;
+
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{QADataSwapError, Result, SharedDataFrame, SharedMemoryConfig};
+
+    fn config(name: &str) -> SharedMemoryConfig {
+        test_config(name, 8).with_priority_lanes(2)
+    }
+
+    fn id(df: &DataFrame) -> i64 {
+        df.column("id").unwrap().i64().unwrap().get(0).unwrap()
+    }
+
+    #[test]
+    fn test_high_lane_drains_first_without_starving_normal() -> Result<()> {
+        let writer = SharedDataFrame::create_writer(config("priority_lanes"))?;
+        let reader = SharedDataFrame::create_reader(config("priority_lanes"))?;
+
+        for i in 0..2i64 {
+            writer.write_priority(&df! { "id" => [i] }?, Priority::Normal)?;
+        }
+        for i in 10..15i64 {
+            writer.write_priority(&df! { "id" => [i] }?, Priority::High)?;
+        }
+
+        let mut order = Vec::new();
+        while let Ok(Some(df)) = reader.read(Some(10)) {
+            order.push(id(&df));
+        }
+        assert_eq!(order, vec![10, 11, 0, 12, 13, 1, 14]);
+
+        let writer = SharedDataFrame::create_writer(test_config("priority_plain", 3))?;
+        let high = writer.write_priority(&df! { "id" => [1i64] }?, Priority::High);
+        assert!(matches!(high, Err(QADataSwapError::SharedMemory(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scheduler_bounds_high_bursts() {
+        let lanes = LaneScheduler::new(3);
+        for _ in 0..3 {
+            assert_eq!(lanes.order(), [Priority::High, Priority::Normal]);
+            lanes.served(Priority::High);
+        }
+        // A flood of high frames yields to normal data after each burst
+        assert_eq!(lanes.order(), [Priority::Normal, Priority::High]);
+        lanes.served(Priority::High);
+        assert_eq!(lanes.order(), [Priority::Normal, Priority::High]);
+        lanes.served(Priority::Normal);
+        assert_eq!(lanes.order(), [Priority::High, Priority::Normal]);
+
+        // A zero burst still lets one high frame through at a time
+        let strict = LaneScheduler::new(0);
+        assert_eq!(strict.order(), [Priority::High, Priority::Normal]);
+        strict.served(Priority::High);
+        assert_eq!(strict.order(), [Priority::Normal, Priority::High]);
+    }
+
+    #[test]
+    fn test_normal_lane_keeps_flowing_under_a_high_flood() -> Result<()> {
+        let writer = SharedDataFrame::create_writer(config("priority_flood"))?;
+        let reader = SharedDataFrame::create_reader(config("priority_flood"))?;
+
+        writer.write_priority(&df! { "id" => [0i64] }?, Priority::Normal)?;
+        let mut normal_at = None;
+        for i in 0..8i64 {
+            // The high lane never runs dry
+            writer.write_priority(&df! { "id" => [100 + i] }?, Priority::High)?;
+            if id(&reader.read(Some(10))?.unwrap()) == 0 {
+                normal_at = Some(i);
+                break;
+            }
+        }
+        assert_eq!(normal_at, Some(2));
+        Ok(())
+    }
+}
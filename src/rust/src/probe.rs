@@ -0,0 +1,229 @@
+//! Ping/pong latency measurement between two processes
+//!
+//! The probing side publishes pings on one channel; the other side runs
+//! `LatencyProbe::respond`, which answers each on a second channel. Every
+//! exchange yields the four NTP timestamps (ping sent, received, pong sent,
+//! received), from which the clock offset between the processes and the
+//! one-way latency are estimated.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::{ClockSource, Event, QADataSwapError, Result, SharedDataFrame};
+
+const SEQ: &str = "probe_seq";
+const SENT: &str = "probe_t0";
+const RECEIVED: &str = "probe_t1";
+const REPLIED: &str = "probe_t2";
+
+/// Wall-clock nanoseconds on `channel`'s time source
+fn now_ns(channel: &SharedDataFrame) -> u64 {
+    channel.arena.config.time_source.now_ns(ClockSource::Realtime)
+}
+
+/// Distribution of one latency measure, in nanoseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub min_ns: u64,
+    pub p50_ns: u64,
+    pub p90_ns: u64,
+    pub p99_ns: u64,
+    pub max_ns: u64,
+    pub mean_ns: u64,
+}
+
+impl LatencyStats {
+    fn from_samples(mut samples: Vec<u64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+        let at = |q: f64| samples[((samples.len() - 1) as f64 * q).round() as usize];
+        Self {
+            min_ns: samples[0],
+            p50_ns: at(0.5),
+            p90_ns: at(0.9),
+            p99_ns: at(0.99),
+            max_ns: samples[samples.len() - 1],
+            mean_ns: samples.iter().sum::<u64>() / samples.len() as u64,
+        }
+    }
+}
+
+impl fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let us = |ns: u64| ns as f64 / 1000.0;
+        write!(f, "min {:.1} / p50 {:.1} / p90 {:.1} / p99 {:.1} / max {:.1} us (mean {:.1})",
+               us(self.min_ns), us(self.p50_ns), us(self.p90_ns), us(self.p99_ns), us(self.max_ns), us(self.mean_ns))
+    }
+}
+
+/// Result of `LatencyProbe::run`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyReport {
+    /// Pings answered
+    pub samples: usize,
+    /// Pings that got no answer within the timeout
+    pub lost: usize,
+    /// Responder clock minus probe clock, from the exchange with the lowest round trip
+    pub clock_offset_ns: i64,
+    pub round_trip: LatencyStats,
+    /// Ping delivery time, corrected by the clock offset
+    pub one_way: LatencyStats,
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "samples:      {} ({} lost)", self.samples, self.lost)?;
+        writeln!(f, "clock offset: {:.1} us", self.clock_offset_ns as f64 / 1000.0)?;
+        writeln!(f, "round trip:   {}", self.round_trip)?;
+        write!(f, "one way:      {}", self.one_way)
+    }
+}
+
+/// Settings for a latency measurement, see the module docs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyProbe {
+    pub samples: usize,
+    /// Pause between pings
+    pub interval: Duration,
+    /// How long to wait for each pong before counting the ping as lost
+    pub timeout_ms: i32,
+}
+
+impl Default for LatencyProbe {
+    fn default() -> Self {
+        Self {
+            samples: 1000,
+            interval: Duration::ZERO,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+impl LatencyProbe {
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_timeout_ms(mut self, timeout_ms: i32) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Send pings on `writer` and collect the pongs from `reader`; finishes by
+    /// publishing EOS so the responder returns
+    pub fn run(&self, writer: &SharedDataFrame, reader: &SharedDataFrame) -> Result<LatencyReport> {
+        let mut exchanges: Vec<[u64; 4]> = Vec::with_capacity(self.samples);
+        let mut lost = 0;
+
+        for seq in 0..self.samples as u64 {
+            let mut ping = writer.frame_builder()?;
+            ping.append_column(SEQ, &[seq])?.append_column(SENT, &[now_ns(writer)])?;
+            ping.publish()?;
+
+            match self.await_pong(reader, seq)? {
+                Some(exchange) => exchanges.push(exchange),
+                None => lost += 1,
+            }
+            if !self.interval.is_zero() {
+                writer.arena.config.time_source.sleep(self.interval);
+            }
+        }
+        writer.write_event(&Event::Eos)?;
+
+        // theta = ((t1 - t0) + (t2 - t3)) / 2 and delta = (t3 - t0) - (t2 - t1)
+        let offset = |[t0, t1, t2, t3]: [u64; 4]| ((t1 as i64 - t0 as i64) + (t2 as i64 - t3 as i64)) / 2;
+        let round_trip = |[t0, t1, t2, t3]: [u64; 4]| (t3 - t0).saturating_sub(t2.saturating_sub(t1));
+        let clock_offset_ns = exchanges.iter().min_by_key(|e| round_trip(**e)).map_or(0, |e| offset(*e));
+
+        Ok(LatencyReport {
+            samples: exchanges.len(),
+            lost,
+            clock_offset_ns,
+            round_trip: LatencyStats::from_samples(exchanges.iter().map(|e| round_trip(*e)).collect()),
+            one_way: LatencyStats::from_samples(
+                exchanges.iter().map(|[t0, t1, ..]| (*t1 as i64 - *t0 as i64 - clock_offset_ns).max(0) as u64).collect(),
+            ),
+        })
+    }
+
+    /// Wait for the pong to `seq`, dropping stale ones from earlier lost pings
+    fn await_pong(&self, reader: &SharedDataFrame, seq: u64) -> Result<Option<[u64; 4]>> {
+        loop {
+            let df = match reader.read(Some(self.timeout_ms)) {
+                Ok(Some(df)) => df,
+                Ok(None) | Err(QADataSwapError::Timeout) => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            let received = now_ns(reader);
+            let field = |name: &str| -> Result<u64> {
+                df.column(name)?.u64()?.get(0).ok_or_else(|| {
+                    QADataSwapError::SharedMemory(format!("Malformed pong: no '{}'", name))
+                })
+            };
+            if field(SEQ)? == seq {
+                return Ok(Some([field(SENT)?, field(RECEIVED)?, field(REPLIED)?, received]));
+            }
+        }
+    }
+
+    /// Answer pings from `reader` on `writer` until the probe publishes EOS,
+    /// returning how many were answered. Waits at most `idle_timeout_ms` for
+    /// each ping.
+    pub fn respond(reader: &SharedDataFrame, writer: &SharedDataFrame, idle_timeout_ms: i32) -> Result<usize> {
+        let mut answered = 0;
+        loop {
+            let df = match reader.read_event(Some(idle_timeout_ms))? {
+                Some(Event::Data(df)) => df,
+                Some(Event::Eos) | None => return Ok(answered),
+                Some(_) => continue,
+            };
+            let received = now_ns(reader);
+            let seq = df.column(SEQ)?.u64()?;
+            let sent = df.column(SENT)?.u64()?;
+            let (Some(seq), Some(sent)) = (seq.get(0), sent.get(0)) else {
+                continue;
+            };
+
+            let mut pong = writer.frame_builder()?;
+            pong.append_column(SEQ, &[seq])?
+                .append_column(SENT, &[sent])?
+                .append_column(RECEIVED, &[received])?
+                .append_column(REPLIED, &[now_ns(writer)])?;
+            pong.publish()?;
+            answered += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_latency_probe_round_trips() -> Result<()> {
+        let ping_writer = SharedDataFrame::create_writer(test_config("probe_ping", 4))?;
+        let ping_reader = SharedDataFrame::create_reader(test_config("probe_ping", 4))?;
+        let pong_writer = SharedDataFrame::create_writer(test_config("probe_pong", 4))?;
+        let pong_reader = SharedDataFrame::create_reader(test_config("probe_pong", 4))?;
+
+        let responder = std::thread::spawn(move || LatencyProbe::respond(&ping_reader, &pong_writer, 2000));
+        let report = LatencyProbe::default().with_samples(50).run(&ping_writer, &pong_reader)?;
+        assert_eq!(responder.join().unwrap()?, 50);
+
+        assert_eq!((report.samples, report.lost), (50, 0));
+        assert!(report.round_trip.min_ns <= report.round_trip.p50_ns);
+        assert!(report.round_trip.p50_ns <= report.round_trip.max_ns);
+        // Same process, same clock
+        assert!(report.clock_offset_ns.abs() < 100_000_000);
+        Ok(())
+    }
+}
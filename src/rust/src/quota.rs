@@ -0,0 +1,392 @@
+//! Per-channel quotas for routing keys sharing one arena
+//!
+//! Channels multiplexed into one ring with `write_with_key` compete for its
+//! slots. With `with_channel_quotas` the writer keeps a ledger of the frames
+//! each key still has in the ring and admits a frame only while its key stays
+//! within its byte quota and, while other keys are active, within its fair
+//! share of the slots (`buffer_count / active keys`, at least one). When the
+//! ring is full, the next free slot goes to the admitted key holding the fewest
+//! slots, the longest waiting first among equals. Frames without a key are not
+//! scheduled, but take slots as usual.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::backend::{HeaderDump, RawArena, STATUS_OK};
+use crate::{Clock, MemoryReport, QADataSwapError, Result, RoutingKey};
+
+/// What a write over its channel's quota or share does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuotaPolicy {
+    /// Fail at once with `QADataSwapError::QuotaExceeded`
+    #[default]
+    Reject,
+    /// Wait up to this long for readers to make room, then fail
+    Wait(Duration),
+}
+
+/// Byte quotas per routing key, see `with_channel_quotas`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelQuotas {
+    /// Unread bytes any key may hold in the ring; `None` leaves only the fair share
+    pub default_bytes: Option<u64>,
+    /// Quotas of particular keys, in place of the default
+    pub per_channel: HashMap<RoutingKey, u64>,
+    pub policy: QuotaPolicy,
+}
+
+impl ChannelQuotas {
+    pub fn with_default_bytes(mut self, bytes: u64) -> Self {
+        self.default_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_quota(mut self, key: impl Into<RoutingKey>, bytes: u64) -> Self {
+        self.per_channel.insert(key.into(), bytes);
+        self
+    }
+
+    pub fn with_policy(mut self, policy: QuotaPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    fn quota(&self, key: &RoutingKey) -> Option<u64> {
+        self.per_channel.get(key).copied().or(self.default_bytes)
+    }
+}
+
+/// A keyed frame still in the ring
+#[derive(Debug)]
+struct InFlight {
+    sequence: u64,
+    key: RoutingKey,
+    bytes: u64,
+}
+
+/// A write held back, by its arrival order
+#[derive(Debug)]
+struct Waiter {
+    ticket: u64,
+    key: RoutingKey,
+    bytes: u64,
+}
+
+#[derive(Debug, Default)]
+struct Ledger {
+    /// Oldest first
+    in_flight: VecDeque<InFlight>,
+    waiting: Vec<Waiter>,
+    next_ticket: u64,
+}
+
+/// Where the ring stands: its next sequence, the oldest one not yet consumed,
+/// how many slots it has and how many are free
+#[derive(Debug, Clone, Copy)]
+struct Ring {
+    write_sequence: u64,
+    consumed: u64,
+    buffer_count: u64,
+    free: u64,
+}
+
+impl Ring {
+    fn read(raw: &dyn RawArena) -> Result<Self> {
+        let mut header = HeaderDump::default();
+        let mut report = MemoryReport::default();
+        if raw.dump_header(&mut header) != STATUS_OK || raw.memory_report(&mut report) != STATUS_OK {
+            return Err(QADataSwapError::SharedMemory("Channel quotas need the ring's cursors".to_string()));
+        }
+        Ok(Self {
+            write_sequence: header.write_sequence,
+            consumed: header.write_sequence.saturating_sub(raw.unacked()),
+            buffer_count: report.buffer_count,
+            free: report.free_buffers,
+        })
+    }
+}
+
+impl Ledger {
+    /// Forget frames readers have consumed
+    fn trim(&mut self, consumed: u64) {
+        while self.in_flight.front().is_some_and(|frame| frame.sequence < consumed) {
+            self.in_flight.pop_front();
+        }
+    }
+
+    fn held(&self, key: &RoutingKey) -> (usize, u64) {
+        self.in_flight
+            .iter()
+            .filter(|frame| frame.key == *key)
+            .fold((0, 0), |(slots, bytes), frame| (slots + 1, bytes + frame.bytes))
+    }
+
+    /// Whether a frame of `bytes` for `key` fits its quota and fair share
+    fn fits(&self, quotas: &ChannelQuotas, ring: Ring, key: &RoutingKey, bytes: u64) -> bool {
+        let (slots, held) = self.held(key);
+        if quotas.quota(key).is_some_and(|quota| held.saturating_add(bytes) > quota) {
+            return false;
+        }
+        let active: HashSet<_> = self
+            .in_flight
+            .iter()
+            .map(|frame| &frame.key)
+            .chain(self.waiting.iter().map(|waiter| &waiter.key))
+            .chain([key])
+            .collect();
+        let share = (ring.buffer_count / active.len() as u64).max(1);
+        active.len() == 1 || (slots as u64) < share
+    }
+
+    /// Whether the next free slot is `ticket`'s: no admitted waiter holding
+    /// fewer slots, or as few and waiting longer, is ahead of it
+    fn has_turn(&self, quotas: &ChannelQuotas, ring: Ring, ticket: u64, key: &RoutingKey) -> bool {
+        let rank = |ticket: u64, key: &RoutingKey| (self.held(key).0, ticket);
+        let own = rank(ticket, key);
+        ring.free > 0
+            && !self.waiting.iter().any(|waiter| {
+                waiter.ticket != ticket
+                    && self.fits(quotas, ring, &waiter.key, waiter.bytes)
+                    && rank(waiter.ticket, &waiter.key) < own
+            })
+    }
+
+    fn leave(&mut self, ticket: u64) {
+        self.waiting.retain(|waiter| waiter.ticket != ticket);
+    }
+}
+
+/// The writer's side of `with_channel_quotas`
+#[derive(Debug)]
+pub(crate) struct QuotaScheduler {
+    quotas: ChannelQuotas,
+    ledger: Mutex<Ledger>,
+    time: Arc<dyn Clock>,
+}
+
+/// A frame's claim on the next slot; the ledger stays locked until it is
+/// published or dropped, so writes of other keys are admitted after it
+pub(crate) struct Admission<'a> {
+    ledger: MutexGuard<'a, Ledger>,
+    sequence: u64,
+    key: RoutingKey,
+    bytes: u64,
+}
+
+impl Admission<'_> {
+    /// The frame was written: count it against its key until it is consumed
+    pub(crate) fn publish(mut self) {
+        let frame = InFlight {
+            sequence: self.sequence,
+            key: self.key,
+            bytes: self.bytes,
+        };
+        self.ledger.in_flight.push_back(frame);
+    }
+}
+
+impl QuotaScheduler {
+    /// Waits with `QuotaPolicy::Wait` on `time`
+    pub(crate) fn new(quotas: ChannelQuotas, time: Arc<dyn Clock>) -> Self {
+        Self {
+            quotas,
+            ledger: Mutex::default(),
+            time,
+        }
+    }
+
+    /// Hold back a frame of `bytes` for `key` until it may take a slot
+    pub(crate) fn admit(&self, raw: &dyn RawArena, key: &RoutingKey, bytes: u64) -> Result<Admission<'_>> {
+        // A frame larger than the whole quota would wait forever
+        let never_fits = self.quotas.quota(key).is_some_and(|quota| bytes > quota);
+        let started = self.time.instant();
+        let gives_up = || match self.quotas.policy {
+            _ if never_fits => true,
+            QuotaPolicy::Reject => true,
+            QuotaPolicy::Wait(timeout) => self.time.instant().saturating_duration_since(started) >= timeout,
+        };
+        let mut ledger = self.ledger.lock().unwrap();
+        let ticket = ledger.next_ticket;
+        ledger.next_ticket += 1;
+        let mut backoff = Duration::from_micros(1);
+
+        loop {
+            let ring = match Ring::read(raw) {
+                Ok(ring) => ring,
+                Err(e) => {
+                    ledger.leave(ticket);
+                    return Err(e);
+                },
+            };
+            ledger.trim(ring.consumed);
+            if ledger.fits(&self.quotas, ring, key, bytes) {
+                if ledger.has_turn(&self.quotas, ring, ticket, key) {
+                    ledger.leave(ticket);
+                    return Ok(Admission {
+                        ledger,
+                        sequence: ring.write_sequence,
+                        key: key.clone(),
+                        bytes,
+                    });
+                }
+            } else if gives_up() {
+                ledger.leave(ticket);
+                let (held_slots, held_bytes) = ledger.held(key);
+                return Err(QADataSwapError::QuotaExceeded {
+                    channel: key.to_string(),
+                    held_bytes,
+                    held_slots,
+                });
+            }
+
+            if !ledger.waiting.iter().any(|waiter| waiter.ticket == ticket) {
+                ledger.waiting.push(Waiter {
+                    ticket,
+                    key: key.clone(),
+                    bytes,
+                });
+            }
+            drop(ledger);
+            self.time.sleep(backoff);
+            backoff = (backoff * 2).min(Duration::from_millis(1));
+            ledger = self.ledger.lock().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::df;
+    use polars::prelude::DataFrame;
+
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{MockClock, SharedDataFrame, SharedMemoryConfig};
+
+    fn config(name: &str, buffers: usize, quotas: ChannelQuotas) -> SharedMemoryConfig {
+        test_config(name, buffers).with_channel_quotas(quotas)
+    }
+
+    fn frame() -> DataFrame {
+        df! { "price" => [1.0f64, 2.0, 3.0] }.unwrap()
+    }
+
+    /// Encoded size of `frame()` under `key`
+    fn frame_bytes(key: &str) -> Result<u64> {
+        let probe = SharedDataFrame::create_writer(test_config(&format!("quota_probe_{}", key), 3))?;
+        probe.write_with_key(&frame(), key)?;
+        Ok(probe.debug_dump()?.slots[0].size)
+    }
+
+    #[test]
+    fn test_quota_exceeded_is_its_own_error() -> Result<()> {
+        let quotas = ChannelQuotas::default().with_quota("bulk", 1).with_policy(QuotaPolicy::Wait(Duration::MAX));
+        let writer = SharedDataFrame::create_writer(config("quota_bytes", 4, quotas.clone()))?;
+        let reader = SharedDataFrame::create_reader(config("quota_bytes", 4, quotas))?;
+
+        // A frame over the whole quota fails without waiting; other keys are unaffected
+        match writer.write_with_key(&frame(), "bulk") {
+            Err(QADataSwapError::QuotaExceeded { channel, held_bytes, held_slots }) => {
+                assert_eq!((channel.as_str(), held_bytes, held_slots), ("bulk", 0, 0));
+            },
+            other => panic!("expected QuotaExceeded, got {:?}", other.map(|_| ())),
+        }
+        writer.write_with_key(&frame(), "quotes")?;
+        writer.write(&frame())?;
+        assert!(reader.read(Some(100))?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quota_frees_up_as_readers_consume() -> Result<()> {
+        let quotas = ChannelQuotas::default().with_default_bytes(frame_bytes("bulk")? * 2);
+        let writer = SharedDataFrame::create_writer(config("quota_consume", 4, quotas.clone()))?;
+        let reader = SharedDataFrame::create_reader(config("quota_consume", 4, quotas))?;
+        writer.write_with_key(&frame(), "bulk")?;
+        writer.write_with_key(&frame(), "bulk")?;
+        assert!(matches!(
+            writer.write_with_key(&frame(), "bulk"),
+            Err(QADataSwapError::QuotaExceeded { held_slots: 2, .. })
+        ));
+
+        assert!(reader.read(Some(100))?.is_some());
+        writer.write_with_key(&frame(), "bulk")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_bursty_channel_keeps_to_its_fair_share() -> Result<()> {
+        let writer = SharedDataFrame::create_writer(config("quota_share", 4, ChannelQuotas::default()))?;
+        let _reader = SharedDataFrame::create_reader(config("quota_share", 4, ChannelQuotas::default()))?;
+
+        // With one other key in the ring, a burst gets half the slots
+        writer.write_with_key(&frame(), "quotes")?;
+        writer.write_with_key(&frame(), "bulk")?;
+        writer.write_with_key(&frame(), "bulk")?;
+        assert!(matches!(
+            writer.write_with_key(&frame(), "bulk"),
+            Err(QADataSwapError::QuotaExceeded { held_slots: 2, .. })
+        ));
+        writer.write_with_key(&frame(), "quotes")?;
+        assert_eq!(writer.memory_report()?.free_buffers, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lone_channel_may_fill_the_ring() -> Result<()> {
+        let writer = SharedDataFrame::create_writer(config("quota_lone", 3, ChannelQuotas::default()))?;
+        let _reader = SharedDataFrame::create_reader(config("quota_lone", 3, ChannelQuotas::default()))?;
+        for _ in 0..3 {
+            writer.write_with_key(&frame(), "bulk")?;
+        }
+        assert_eq!(writer.memory_report()?.free_buffers, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_waiting_gives_up_on_the_time_source() -> Result<()> {
+        let clock = Arc::new(MockClock::default());
+        let quotas = ChannelQuotas::default()
+            .with_quota("bulk", frame_bytes("bulk")?)
+            .with_policy(QuotaPolicy::Wait(Duration::from_secs(5)));
+        let writer = SharedDataFrame::create_writer(config("quota_wait", 3, quotas).with_time_source(clock.clone()))?;
+
+        writer.write_with_key(&frame(), "bulk")?;
+        assert!(matches!(writer.write_with_key(&frame(), "bulk"), Err(QADataSwapError::QuotaExceeded { .. })));
+        assert!(clock.elapsed() >= Duration::from_secs(5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_freed_slot_goes_to_the_key_holding_fewest() {
+        let ring = Ring {
+            write_sequence: 2,
+            consumed: 0,
+            buffer_count: 6,
+            free: 1,
+        };
+        let quotas = ChannelQuotas::default();
+        let frame = |sequence, key: &str| InFlight {
+            sequence,
+            key: key.into(),
+            bytes: 10,
+        };
+        let mut ledger = Ledger {
+            in_flight: VecDeque::from([frame(0, "bulk"), frame(1, "quotes")]),
+            ..Ledger::default()
+        };
+        ledger.waiting.push(Waiter { ticket: 0, key: "bulk".into(), bytes: 10 });
+        ledger.waiting.push(Waiter { ticket: 1, key: "trades".into(), bytes: 10 });
+
+        // "trades" holds nothing yet, so it goes ahead of the earlier "bulk"
+        assert!(ledger.has_turn(&quotas, ring, 1, &"trades".into()));
+        assert!(!ledger.has_turn(&quotas, ring, 0, &"bulk".into()));
+        assert!(!ledger.has_turn(&quotas, Ring { free: 0, ..ring }, 1, &"trades".into()));
+
+        // Holding as many, the one waiting longer goes first
+        ledger.in_flight.push_back(frame(2, "trades"));
+        assert!(ledger.has_turn(&quotas, ring, 0, &"bulk".into()));
+        assert!(!ledger.has_turn(&quotas, ring, 1, &"trades".into()));
+    }
+}
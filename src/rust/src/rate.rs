@@ -0,0 +1,95 @@
+//! Writer-side pacing, so replays and simulators publish at a realistic rate
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::Clock;
+
+/// Sustained publication rate enforced by `write()` / `write_chunk()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimit {
+    FramesPerSec(f64),
+    /// Counted on the encoded frame, after compression
+    BytesPerSec(f64),
+}
+
+impl RateLimit {
+    fn per_sec(self) -> f64 {
+        match self {
+            RateLimit::FramesPerSec(rate) | RateLimit::BytesPerSec(rate) => rate,
+        }
+    }
+}
+
+/// Token bucket holding at most a tenth of a second of budget (and at least one
+/// frame), so publication stays smooth rather than bursting after idle periods
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    limit: RateLimit,
+    capacity: f64,
+    tokens: f64,
+    refilled_at: Instant,
+    time: Arc<dyn Clock>,
+}
+
+impl TokenBucket {
+    /// Refills and sleeps off debt on `time`
+    pub(crate) fn new(limit: RateLimit, time: Arc<dyn Clock>) -> Self {
+        let capacity = match limit {
+            RateLimit::FramesPerSec(rate) => (rate / 10.0).max(1.0),
+            RateLimit::BytesPerSec(rate) => rate / 10.0,
+        };
+        Self {
+            limit,
+            capacity,
+            tokens: capacity,
+            refilled_at: time.instant(),
+            time,
+        }
+    }
+
+    /// Charge one frame of `bytes`, sleeping off any debt. A frame larger than
+    /// the bucket still goes out, and the following ones wait for it.
+    pub(crate) fn acquire(&mut self, bytes: usize) {
+        let rate = self.limit.per_sec();
+        if !rate.is_finite() || rate <= 0.0 {
+            return;
+        }
+
+        let now = self.time.instant();
+        self.tokens = (self.tokens + now.duration_since(self.refilled_at).as_secs_f64() * rate).min(self.capacity);
+        self.refilled_at = now;
+
+        self.tokens -= match self.limit {
+            RateLimit::FramesPerSec(_) => 1.0,
+            RateLimit::BytesPerSec(_) => bytes as f64,
+        };
+        if self.tokens < 0.0 {
+            self.time.sleep(Duration::from_secs_f64(-self.tokens / rate));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{Result, SharedDataStream};
+    use polars::df;
+
+    #[test]
+    fn test_rate_limit_paces_writes() -> Result<()> {
+        let config = test_config("rate_paced", 32).with_rate_limit(RateLimit::FramesPerSec(100.0));
+        let writer = SharedDataStream::create_writer(config.clone())?;
+        let _reader = SharedDataStream::create_reader(config)?;
+
+        let df = df! { "x" => [1i64] }?;
+        let started = Instant::now();
+        for _ in 0..30 {
+            writer.write_chunk(&df)?;
+        }
+        // A bucket of 10 frames, then 10ms per frame
+        assert!(started.elapsed() >= Duration::from_millis(190));
+        Ok(())
+    }
+}
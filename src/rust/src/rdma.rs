@@ -0,0 +1,260 @@
+//! Experimental RDMA backend: FFI bindings to the C++ core's RdmaArena
+//!
+//! The reader registers the ring with its RDMA device and listens on the
+//! transport's address; the writer connects from another host and RDMA-writes
+//! every frame straight into the reader's memory, so frames cross hosts
+//! without a socket copy on either side. One writer and one reader per
+//! channel, and only the ring itself crosses the wire: replay, consumer
+//! groups, batches, acks, capability tokens and session metadata stay local
+//! to each side. Needs the C++ core built with `-DQADS_WITH_RDMA=ON`.
+
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::net::SocketAddr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::auth::Challenge;
+use crate::backend::{
+    GroupDump, HeaderDump, RawArena, ReaderDump, SlotDump, WakeWord, STATUS_ERROR, STATUS_OK,
+};
+use crate::{Advice, MemoryProtection, MemoryReport, OpenMode, QADataSwapError, Result, SeekFrom};
+
+extern "C" {
+    fn qads_rdma_create_arena(host: *const c_char, port: u16, size: usize, buffer_count: usize) -> *mut c_void;
+    fn qads_rdma_destroy_arena(arena: *mut c_void);
+    fn qads_rdma_create_writer(arena: *mut c_void, timeout_ms: c_int) -> c_int;
+    fn qads_rdma_attach_reader(arena: *mut c_void) -> c_int;
+    fn qads_rdma_write_data(arena: *mut c_void, data: *const u8, size: usize) -> c_int;
+    fn qads_rdma_read_data(arena: *mut c_void, data: *mut u8, max_size: usize,
+                           actual_size: *mut usize, timeout_ms: c_int) -> c_int;
+    fn qads_rdma_peek_size(arena: *mut c_void, size: *mut usize, timeout_ms: c_int) -> c_int;
+    fn qads_rdma_acquire_write_buffer(arena: *mut c_void, capacity: *mut usize) -> *mut u8;
+    fn qads_rdma_publish_acquired(arena: *mut c_void, size: usize) -> c_int;
+    fn qads_rdma_release_acquired(arena: *mut c_void) -> c_int;
+    fn qads_rdma_last_read_sequence(arena: *mut c_void, sequence: *mut u64) -> c_int;
+    fn qads_rdma_has_data(arena: *mut c_void) -> c_int;
+    fn qads_rdma_wait_for_data(arena: *mut c_void, timeout_ms: c_int) -> c_int;
+    fn qads_rdma_close(arena: *mut c_void);
+}
+
+/// How long a writer keeps retrying for its reader to listen, without a config timeout
+const CONNECT_TIMEOUT_MS: i32 = 30_000;
+
+/// Arena whose ring lives in RDMA-registered memory on the reader's host
+pub(crate) struct RdmaArena {
+    inner: *mut c_void,
+    connect_timeout_ms: i32,
+    meta: Mutex<BTreeMap<String, String>>,
+}
+
+unsafe impl Send for RdmaArena {}
+unsafe impl Sync for RdmaArena {}
+
+impl RdmaArena {
+    pub(crate) fn new(addr: SocketAddr, size: usize, buffer_count: usize, timeout_ms: Option<i32>) -> Result<Self> {
+        if !addr.is_ipv4() {
+            return Err(QADataSwapError::SharedMemory(format!("RDMA transport needs an IPv4 address, got {}", addr)));
+        }
+        let host = CString::new(addr.ip().to_string())
+            .map_err(|_| QADataSwapError::SharedMemory("Invalid address".to_string()))?;
+
+        let inner = unsafe { qads_rdma_create_arena(host.as_ptr(), addr.port(), size, buffer_count) };
+        if inner.is_null() {
+            return Err(QADataSwapError::SharedMemory("Failed to create RDMA arena".to_string()));
+        }
+        Ok(Self {
+            inner,
+            connect_timeout_ms: timeout_ms.unwrap_or(CONNECT_TIMEOUT_MS),
+            meta: Mutex::default(),
+        })
+    }
+}
+
+impl RawArena for RdmaArena {
+    fn create_writer(&self, _mode: OpenMode) -> i32 {
+        unsafe { qads_rdma_create_writer(self.inner, self.connect_timeout_ms) }
+    }
+
+    fn attach_reader(&self) -> i32 {
+        unsafe { qads_rdma_attach_reader(self.inner) }
+    }
+
+    fn write(&self, data: &[u8]) -> i32 {
+        unsafe { qads_rdma_write_data(self.inner, data.as_ptr(), data.len()) }
+    }
+
+    fn read(&self, buffer: &mut [u8], actual_size: &mut usize, timeout_ms: i32) -> i32 {
+        unsafe { qads_rdma_read_data(self.inner, buffer.as_mut_ptr(), buffer.len(), actual_size, timeout_ms) }
+    }
+
+    fn peek_size(&self, size: &mut usize, timeout_ms: i32) -> i32 {
+        unsafe { qads_rdma_peek_size(self.inner, size, timeout_ms) }
+    }
+
+    fn last_read_sequence(&self) -> Option<u64> {
+        let mut sequence = 0u64;
+        match unsafe { qads_rdma_last_read_sequence(self.inner, &mut sequence) } {
+            0 => Some(sequence),
+            _ => None,
+        }
+    }
+
+    fn has_data(&self) -> bool {
+        unsafe { qads_rdma_has_data(self.inner) == 1 }
+    }
+
+    fn acquire_write_buffer(&self) -> Option<(*mut u8, usize)> {
+        let mut capacity = 0usize;
+        let ptr = unsafe { qads_rdma_acquire_write_buffer(self.inner, &mut capacity) };
+        (!ptr.is_null()).then_some((ptr, capacity))
+    }
+
+    fn publish_acquired(&self, size: usize) -> i32 {
+        unsafe { qads_rdma_publish_acquired(self.inner, size) }
+    }
+
+    fn release_acquired(&self) {
+        unsafe { qads_rdma_release_acquired(self.inner) };
+    }
+
+    fn reserve_slots(&self, _max_buffer_count: usize) {}
+
+    fn reslot(&self, _buffer_count: usize) -> i32 {
+        STATUS_ERROR
+    }
+
+    fn begin_batch(&self) -> i32 {
+        STATUS_ERROR
+    }
+
+    fn commit_batch(&self) -> i32 {
+        STATUS_ERROR
+    }
+
+    fn abort_batch(&self) {}
+
+    fn memory_report(&self, _report: &mut MemoryReport) -> i32 {
+        STATUS_ERROR
+    }
+
+    fn dump_header(&self, _dump: &mut HeaderDump) -> i32 {
+        STATUS_ERROR
+    }
+
+    fn dump_slot(&self, _index: usize, _dump: &mut SlotDump) -> i32 {
+        0
+    }
+
+    fn dump_group(&self, _index: usize, _dump: &mut GroupDump) -> i32 {
+        0
+    }
+
+    fn seek(&self, _pos: SeekFrom) -> i32 {
+        STATUS_ERROR
+    }
+
+    fn retained_range(&self) -> (u64, u64) {
+        (0, 0)
+    }
+
+    fn seek_time(&self, _timestamp_us: u64) -> i32 {
+        STATUS_ERROR
+    }
+
+    fn set_retention(&self, _retention_us: u64) {}
+
+    fn enable_consumer_groups(&self) {}
+
+    fn join_group(&self, _group: &str) -> i32 {
+        STATUS_ERROR
+    }
+
+    fn set_max_readers(&self, _max_readers: usize) {}
+
+    fn set_protection(&self, _protection: MemoryProtection) {}
+
+    fn set_slot_guards(&self, _guard_pages: bool, _canaries: bool) {}
+
+    fn set_advice(&self, _advice: &[Advice]) {}
+
+    fn set_backing_path(&self, _path: &Path) -> i32 {
+        STATUS_ERROR
+    }
+
+    fn dump_reader(&self, _index: usize, _dump: &mut ReaderDump) -> i32 {
+        0
+    }
+
+    fn set_auth(&self, _challenge: Option<&Challenge>) {}
+
+    fn auth(&self) -> Option<Challenge> {
+        None
+    }
+
+    fn put_meta(&self, key: &str, value: Option<&str>) -> i32 {
+        let mut meta = self.meta.lock().unwrap();
+        match value {
+            Some(value) => meta.insert(key.to_string(), value.to_string()),
+            None => meta.remove(key),
+        };
+        STATUS_OK
+    }
+
+    fn get_meta(&self, key: &str) -> Option<String> {
+        self.meta.lock().unwrap().get(key).cloned()
+    }
+
+    fn meta_keys(&self) -> Vec<String> {
+        self.meta.lock().unwrap().keys().cloned().collect()
+    }
+
+    fn set_ack_mode(&self, _enabled: bool) {}
+
+    fn ack(&self, _sequence: u64) -> i32 {
+        STATUS_ERROR
+    }
+
+    fn unacked(&self) -> u64 {
+        0
+    }
+
+    fn wait_for_data(&self, timeout_ms: i32) -> i32 {
+        unsafe { qads_rdma_wait_for_data(self.inner, timeout_ms) }
+    }
+
+    fn wake_word(&self) -> Option<WakeWord> {
+        None
+    }
+
+    fn notify_data_ready(&self) {}
+
+    fn close(&self) {
+        unsafe { qads_rdma_close(self.inner) };
+    }
+
+    fn abandon(&self) {
+        unsafe { qads_rdma_close(self.inner) };
+    }
+}
+
+impl Drop for RdmaArena {
+    fn drop(&mut self) {
+        unsafe { qads_rdma_destroy_arena(self.inner) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ipv6_addresses_are_rejected_before_the_core() {
+        let addr: SocketAddr = "[::1]:7471".parse().unwrap();
+        match RdmaArena::new(addr, 1024 * 1024, 3, None) {
+            Err(QADataSwapError::SharedMemory(message)) => assert!(message.contains("IPv4")),
+            _ => panic!("an IPv6 address reached the RDMA core"),
+        }
+    }
+}
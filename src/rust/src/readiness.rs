@@ -0,0 +1,202 @@
+//! Level-triggered readiness handles for epoll/mio/tokio event loops
+//!
+//! A reader's handle is an eventfd. The reader passes a copy to the writer over
+//! a unix socket bound under an abstract name derived from the segment, and the
+//! writer signals every registered eventfd as it publishes. The reader clears
+//! its eventfd again after each read that leaves nothing unread, so no thread on
+//! either side polls the ring.
+
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::linux::net::SocketAddrExt;
+use std::os::raw::c_void;
+use std::os::unix::net::{SocketAddr, UnixListener, UnixStream};
+use std::sync::Mutex;
+
+/// Writer side: accepts reader registrations and signals their eventfds
+pub(crate) struct ReadinessHost {
+    listener: UnixListener,
+    readers: Mutex<Vec<Registration>>,
+}
+
+struct Registration {
+    conn: UnixStream,
+    /// `None` until the reader's eventfd has arrived on `conn`
+    eventfd: Option<OwnedFd>,
+}
+
+impl ReadinessHost {
+    pub(crate) fn bind(address: &str) -> io::Result<Self> {
+        let listener = UnixListener::bind_addr(&SocketAddr::from_abstract_name(address)?)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            readers: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Wake every registered reader, first taking on new registrations and
+    /// dropping readers that have gone away
+    pub(crate) fn notify(&self) {
+        let mut readers = self.readers.lock().unwrap();
+        while let Ok((conn, _)) = self.listener.accept() {
+            readers.push(Registration { conn, eventfd: None });
+        }
+
+        readers.retain_mut(|reader| match &reader.eventfd {
+            // A registered reader never sends again: anything readable is its hangup
+            Some(eventfd) => !hung_up(&reader.conn) && signal(eventfd),
+            None => match recv_fd(&reader.conn) {
+                Ok(Some(eventfd)) if is_eventfd(&eventfd) => {
+                    let alive = signal(&eventfd);
+                    reader.eventfd = Some(eventfd);
+                    alive
+                },
+                Ok(Some(_)) => false,
+                Ok(None) => true,
+                Err(_) => false,
+            },
+        });
+    }
+}
+
+/// Reader side: an eventfd that is readable while the reader has a frame to read
+pub(crate) struct ReadinessFd {
+    eventfd: OwnedFd,
+    /// Writers to register with, and the connection once registered
+    writers: Mutex<Vec<(SocketAddr, Option<UnixStream>)>>,
+}
+
+impl ReadinessFd {
+    pub(crate) fn new(addresses: impl IntoIterator<Item = String>) -> io::Result<Self> {
+        let writers = addresses
+            .into_iter()
+            .map(|address| Ok((SocketAddr::from_abstract_name(address)?, None)))
+            .collect::<io::Result<_>>()?;
+        let raw = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+        if raw < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            eventfd: unsafe { OwnedFd::from_raw_fd(raw) },
+            writers: Mutex::new(writers),
+        })
+    }
+
+    /// Register with writers not yet (or no longer) holding the eventfd; a
+    /// writer that is not running is retried on the next call
+    pub(crate) fn register(&self) {
+        for (address, conn) in self.writers.lock().unwrap().iter_mut() {
+            if conn.as_ref().is_some_and(|conn| !hung_up(conn)) {
+                continue;
+            }
+            *conn = UnixStream::connect_addr(address)
+                .and_then(|stream| send_fd(&stream, &self.eventfd).map(|_| stream))
+                .ok();
+        }
+    }
+
+    /// After a read: clear the eventfd, then set it again if `ready` says a
+    /// frame is still waiting. A frame published in between signals it anyway.
+    pub(crate) fn sync(&self, ready: impl FnOnce() -> bool) {
+        self.register();
+        let mut value: u64 = 0;
+        unsafe { libc::read(self.eventfd.as_raw_fd(), &mut value as *mut u64 as *mut c_void, 8) };
+        if ready() {
+            signal(&self.eventfd);
+        }
+    }
+}
+
+impl AsRawFd for ReadinessFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.eventfd.as_raw_fd()
+    }
+}
+
+/// Add one to an eventfd; `false` once the counter can take no more
+fn signal(eventfd: &OwnedFd) -> bool {
+    let value: u64 = 1;
+    let written = unsafe { libc::write(eventfd.as_raw_fd(), &value as *const u64 as *const c_void, 8) };
+    written == 8 || io::Error::last_os_error().kind() == io::ErrorKind::WouldBlock
+}
+
+/// Only eventfds are written to: a pipe or socket sent instead could block the writer
+fn is_eventfd(fd: &OwnedFd) -> bool {
+    std::fs::read_link(format!("/proc/self/fd/{}", fd.as_raw_fd()))
+        .is_ok_and(|target| target.as_os_str() == "anon_inode:[eventfd]")
+}
+
+/// Whether the peer closed its end (or sent something we never expect)
+fn hung_up(conn: &UnixStream) -> bool {
+    let mut poll = libc::pollfd {
+        fd: conn.as_raw_fd(),
+        events: libc::POLLIN | libc::POLLRDHUP,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut poll, 1, 0) != 0 }
+}
+
+fn send_fd(conn: &UnixStream, fd: &OwnedFd) -> io::Result<()> {
+    let mut byte = 0u8;
+    let mut iov = libc::iovec {
+        iov_base: &mut byte as *mut u8 as *mut c_void,
+        iov_len: 1,
+    };
+    let mut control = [0u8; unsafe { libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) } as usize];
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut c_void;
+    msg.msg_controllen = control.len() as _;
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as u32) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd.as_raw_fd());
+        if libc::sendmsg(conn.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) != 1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// The fd a reader sent, `None` if it has not arrived yet
+fn recv_fd(conn: &UnixStream) -> io::Result<Option<OwnedFd>> {
+    let mut byte = 0u8;
+    let mut iov = libc::iovec {
+        iov_base: &mut byte as *mut u8 as *mut c_void,
+        iov_len: 1,
+    };
+    let mut control = [0u8; unsafe { libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) } as usize];
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut c_void;
+    msg.msg_controllen = control.len() as _;
+
+    let received = unsafe { libc::recvmsg(conn.as_raw_fd(), &mut msg, libc::MSG_DONTWAIT | libc::MSG_CMSG_CLOEXEC) };
+    match received {
+        1 => {},
+        0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+        _ => {
+            let error = io::Error::last_os_error();
+            return match error.kind() {
+                io::ErrorKind::WouldBlock => Ok(None),
+                _ => Err(error),
+            };
+        },
+    }
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        if cmsg.is_null() || (*cmsg).cmsg_level != libc::SOL_SOCKET || (*cmsg).cmsg_type != libc::SCM_RIGHTS {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let fd = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const RawFd);
+        Ok(Some(OwnedFd::from_raw_fd(fd)))
+    }
+}
@@ -0,0 +1,139 @@
+//! Cross-host notifications: announce that a local stream advanced over a
+//! message bus, so remote orchestrators need not poll
+
+use std::fmt;
+use std::io;
+
+/// Publishes `(arena, sequence)` whenever a writer calls `notify_data_ready()`,
+/// where `sequence` counts the frames published so far. Delivery is best
+/// effort: a failing relay never fails the local data path.
+pub trait NotificationRelay: Send + Sync + fmt::Debug {
+    fn publish(&self, arena: &str, sequence: u64) -> io::Result<()>;
+}
+
+/// Wire format shared by the bundled relays: `<arena> <sequence>`
+pub fn notification_payload(arena: &str, sequence: u64) -> String {
+    format!("{} {}", arena, sequence)
+}
+
+#[cfg(feature = "redis-relay")]
+pub use redis_relay::RedisRelay;
+
+#[cfg(feature = "redis-relay")]
+mod redis_relay {
+    use std::io;
+    use std::sync::Mutex;
+
+    use redis::Commands;
+
+    use super::{notification_payload, NotificationRelay};
+
+    /// Redis pub/sub: one message per notification on `channel`
+    pub struct RedisRelay {
+        connection: Mutex<redis::Connection>,
+        channel: String,
+    }
+
+    impl RedisRelay {
+        pub fn connect(url: &str, channel: impl Into<String>) -> io::Result<Self> {
+            let connection = redis::Client::open(url)
+                .and_then(|client| client.get_connection())
+                .map_err(io::Error::other)?;
+            Ok(Self {
+                connection: Mutex::new(connection),
+                channel: channel.into(),
+            })
+        }
+    }
+
+    impl std::fmt::Debug for RedisRelay {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RedisRelay").field("channel", &self.channel).finish()
+        }
+    }
+
+    impl NotificationRelay for RedisRelay {
+        fn publish(&self, arena: &str, sequence: u64) -> io::Result<()> {
+            let mut connection = self.connection.lock().unwrap();
+            connection
+                .publish::<_, _, ()>(&self.channel, notification_payload(arena, sequence))
+                .map_err(io::Error::other)
+        }
+    }
+}
+
+#[cfg(feature = "zmq-relay")]
+pub use zmq_relay::ZmqRelay;
+
+#[cfg(feature = "zmq-relay")]
+mod zmq_relay {
+    use std::io;
+    use std::sync::Mutex;
+
+    use super::{notification_payload, NotificationRelay};
+
+    /// ZeroMQ PUB socket: the arena name is the topic frame, so subscribers can
+    /// filter by prefix
+    pub struct ZmqRelay {
+        socket: Mutex<zmq::Socket>,
+        endpoint: String,
+    }
+
+    impl ZmqRelay {
+        /// Bind a PUB socket at `endpoint`, e.g. `tcp://*:5556`
+        pub fn bind(endpoint: &str) -> io::Result<Self> {
+            let socket = zmq::Context::new().socket(zmq::PUB).map_err(io::Error::other)?;
+            socket.bind(endpoint).map_err(io::Error::other)?;
+            Ok(Self {
+                socket: Mutex::new(socket),
+                endpoint: endpoint.to_string(),
+            })
+        }
+    }
+
+    impl std::fmt::Debug for ZmqRelay {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("ZmqRelay").field("endpoint", &self.endpoint).finish()
+        }
+    }
+
+    impl NotificationRelay for ZmqRelay {
+        fn publish(&self, arena: &str, sequence: u64) -> io::Result<()> {
+            let socket = self.socket.lock().unwrap();
+            socket
+                .send_multipart([arena.as_bytes(), notification_payload(arena, sequence).as_bytes()], zmq::DONTWAIT)
+                .map_err(io::Error::other)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{Result, SharedDataFrame};
+
+    #[derive(Debug, Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl NotificationRelay for Recorder {
+        fn publish(&self, arena: &str, sequence: u64) -> io::Result<()> {
+            self.0.lock().unwrap().push(notification_payload(arena, sequence));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_notify_publishes_to_relay() -> Result<()> {
+        let recorder = Arc::new(Recorder::default());
+        let config = test_config("relay_stream", 3).with_relay(recorder.clone());
+        let writer = SharedDataFrame::create_writer(config)?;
+
+        writer.write(&polars::df! { "id" => [1i64] }?)?;
+        writer.notify_data_ready();
+        assert_eq!(*recorder.0.lock().unwrap(), vec!["relay_stream 1".to_string()]);
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+//! Replaying recorded frames into a channel, e.g. a `ParquetArchiver` archive,
+//! for backtesting consumers against a recorded stream
+
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::Duration;
+
+use polars::prelude::*;
+
+use crate::archive::ARCHIVE_MANIFEST;
+use crate::{Event, QADataSwapError, Result, SharedDataFrame};
+
+/// When the replayer publishes each frame
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReplayClock {
+    /// Keep the recorded gaps between frames
+    #[default]
+    Original,
+    /// Recorded gaps divided by the factor: `Speed(10.0)` is ten times faster
+    Speed(f64),
+    /// Only `step()` publishes, for deterministic backtests
+    Manual,
+}
+
+/// Publishes recorded `(timestamp_us, frame)` pairs through a writer
+pub struct Replayer {
+    writer: SharedDataFrame,
+    frames: Vec<(i64, DataFrame)>,
+    next: usize,
+    clock: ReplayClock,
+}
+
+impl Replayer {
+    /// Frames are replayed in timestamp order (microseconds since the epoch)
+    pub fn new(writer: SharedDataFrame, mut frames: Vec<(i64, DataFrame)>, clock: ReplayClock) -> Self {
+        frames.sort_by_key(|(timestamp_us, _)| *timestamp_us);
+        Self {
+            writer,
+            frames,
+            next: 0,
+            clock,
+        }
+    }
+
+    /// Replay the files listed in an archive's manifest. Rows are ordered by
+    /// `time_column` (integer microseconds or a Datetime) and rows sharing a
+    /// timestamp are published as one frame.
+    pub fn from_archive(
+        writer: SharedDataFrame,
+        root: impl AsRef<Path>,
+        time_column: &str,
+        clock: ReplayClock,
+    ) -> Result<Self> {
+        let root = root.as_ref();
+        let manifest = fs::read_to_string(root.join(ARCHIVE_MANIFEST))?;
+        let mut rows: Option<DataFrame> = None;
+        for line in manifest.lines().skip(1) {
+            let Some(path) = line.split('\t').next().filter(|path| !path.is_empty()) else {
+                continue;
+            };
+            let df = ParquetReader::new(File::open(root.join(path))?).finish()?;
+            match &mut rows {
+                Some(rows) => {
+                    rows.vstack_mut(&df)?;
+                },
+                None => rows = Some(df),
+            }
+        }
+        let Some(rows) = rows else {
+            return Ok(Self::new(writer, Vec::new(), clock));
+        };
+
+        let rows = rows.sort([time_column], SortMultipleOptions::default().with_maintain_order(true))?;
+        let times = rows.column(time_column)?.cast(&DataType::Int64)?;
+        let times = times.i64()?;
+        if times.null_count() > 0 {
+            return Err(QADataSwapError::SharedMemory(format!("Column '{}' has null timestamps", time_column)));
+        }
+
+        let mut frames = Vec::new();
+        let mut start = 0;
+        for row in 1..=rows.height() {
+            if row == rows.height() || times.get(row) != times.get(start) {
+                frames.push((times.get(start).unwrap_or_default(), rows.slice(start as i64, row - start)));
+                start = row;
+            }
+        }
+        Ok(Self::new(writer, frames, clock))
+    }
+
+    /// Publish the next frame now, whatever the clock; `false` once exhausted
+    pub fn step(&mut self) -> Result<bool> {
+        let Some((_, df)) = self.frames.get(self.next) else {
+            return Ok(false);
+        };
+        self.writer.write(df)?;
+        self.next += 1;
+        Ok(true)
+    }
+
+    /// Publish the remaining frames on the clock's schedule, counted from now
+    pub fn run(&mut self) -> Result<usize> {
+        let speed = match self.clock {
+            ReplayClock::Original => 1.0,
+            ReplayClock::Speed(speed) if speed.is_finite() && speed > 0.0 => speed,
+            ReplayClock::Speed(speed) => {
+                return Err(QADataSwapError::SharedMemory(format!("Invalid replay speed {}", speed)))
+            },
+            ReplayClock::Manual => {
+                return Err(QADataSwapError::SharedMemory("A manual replay clock is driven with step()".to_string()))
+            },
+        };
+
+        let Some(&(origin, _)) = self.frames.get(self.next) else {
+            return Ok(0);
+        };
+        // The writer's time source, so a `MockClock` replays in simulated time
+        let time = Arc::clone(&self.writer.arena.config.time_source);
+        let started = time.instant();
+        let first = self.next;
+        while let Some(&(timestamp_us, _)) = self.frames.get(self.next) {
+            let due = started + Duration::from_secs_f64((timestamp_us - origin) as f64 / 1e6 / speed);
+            if let Some(wait) = due.checked_duration_since(time.instant()) {
+                time.sleep(wait);
+            }
+            self.step()?;
+        }
+        Ok(self.next - first)
+    }
+
+    /// Switch clocks between steps or runs, e.g. skip ahead at speed then step
+    pub fn set_clock(&mut self, clock: ReplayClock) {
+        self.clock = clock;
+    }
+
+    /// Recorded time of the frame published last, if any
+    pub fn current_time_us(&self) -> Option<i64> {
+        self.next.checked_sub(1).map(|last| self.frames[last].0)
+    }
+
+    /// Frames not yet published
+    pub fn remaining(&self) -> usize {
+        self.frames.len() - self.next
+    }
+
+    /// Tell readers the replay is over
+    pub fn finish(self) -> Result<()> {
+        self.writer.write_event(&Event::Eos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_replay_manual_and_paced() -> Result<()> {
+        let config = test_config("replay_ticks", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        // Out of order on purpose; 100ms of recorded time
+        let frames = vec![
+            (1_100_000, df! { "px" => [3.0] }?),
+            (1_000_000, df! { "px" => [1.0] }?),
+            (1_050_000, df! { "px" => [2.0] }?),
+        ];
+        let mut replayer = Replayer::new(writer, frames, ReplayClock::Manual);
+        assert!(replayer.run().is_err());
+
+        assert!(replayer.step()?);
+        assert_eq!(replayer.current_time_us(), Some(1_000_000));
+        assert_eq!(reader.read(Some(100))?.unwrap().column("px")?.f64()?.get(0), Some(1.0));
+        assert!(!matches!(reader.read(Some(0)), Ok(Some(_))), "nothing is published between steps");
+
+        // The last 50ms at double speed
+        replayer.set_clock(ReplayClock::Speed(2.0));
+        let started = Instant::now();
+        assert_eq!(replayer.run()?, 2);
+        assert!(started.elapsed() >= Duration::from_millis(25));
+        assert_eq!(replayer.remaining(), 0);
+        assert!(!replayer.step()?);
+        assert_eq!(reader.read(Some(100))?.unwrap().column("px")?.f64()?.get(0), Some(2.0));
+        assert_eq!(reader.read(Some(100))?.unwrap().column("px")?.f64()?.get(0), Some(3.0));
+        Ok(())
+    }
+}
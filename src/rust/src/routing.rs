@@ -0,0 +1,142 @@
+//! Routing keys: an application-defined tag per frame that readers filter on
+//! before decoding, e.g. to consume only their own account's orders from a
+//! shared channel without a topic per account
+//!
+//! With `FLAG_ROUTING_KEY` the key immediately follows the payload, ahead of
+//! the other trailers, so a reader finds it from the header alone:
+//!
+//! ```text
+//! u8 0 | u64 id
+//! u8 1 | u8 len | UTF-8 name
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::frame::{FrameHeader, FLAG_ROUTING_KEY, FRAME_HEADER_SIZE};
+use crate::{QADataSwapError, Result};
+
+const TAG_ID: u8 = 0;
+const TAG_NAME: u8 = 1;
+
+/// Longest name a routing key can carry
+pub const MAX_ROUTING_KEY_LEN: usize = u8::MAX as usize;
+
+/// Tag written with `write_with_key` and matched by `set_key_filter`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RoutingKey {
+    Id(u64),
+    /// Up to `MAX_ROUTING_KEY_LEN` bytes
+    Name(String),
+}
+
+impl From<u64> for RoutingKey {
+    fn from(id: u64) -> Self {
+        RoutingKey::Id(id)
+    }
+}
+
+impl From<&str> for RoutingKey {
+    fn from(name: &str) -> Self {
+        RoutingKey::Name(name.to_string())
+    }
+}
+
+impl From<String> for RoutingKey {
+    fn from(name: String) -> Self {
+        RoutingKey::Name(name)
+    }
+}
+
+impl fmt::Display for RoutingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoutingKey::Id(id) => write!(f, "{}", id),
+            RoutingKey::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+impl RoutingKey {
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            RoutingKey::Id(id) => Ok([&[TAG_ID][..], &id.to_le_bytes()].concat()),
+            RoutingKey::Name(name) => {
+                let len = u8::try_from(name.len()).map_err(|_| {
+                    QADataSwapError::LimitExceeded(format!("Routing key too long: {:.40}", name))
+                })?;
+                Ok([&[TAG_NAME, len][..], name.as_bytes()].concat())
+            },
+        }
+    }
+
+    /// The key of a complete frame, if its header has `FLAG_ROUTING_KEY`
+    pub(crate) fn decode(header: &FrameHeader, bytes: &[u8]) -> Result<Option<Self>> {
+        if header.flags & FLAG_ROUTING_KEY == 0 {
+            return Ok(None);
+        }
+        let trailer = bytes.get(FRAME_HEADER_SIZE + header.payload_len as usize..).unwrap_or_default();
+        Ok(Some(Self::parse(trailer)?.0))
+    }
+
+    /// The key at the start of `trailer` and its encoded length
+    pub(crate) fn parse(trailer: &[u8]) -> Result<(Self, usize)> {
+        match trailer {
+            [TAG_ID, id @ ..] if id.len() >= 8 => {
+                Ok((RoutingKey::Id(u64::from_le_bytes(id[..8].try_into().unwrap())), 9))
+            },
+            [TAG_NAME, len, name @ ..] if name.len() >= *len as usize => {
+                let name = std::str::from_utf8(&name[..*len as usize]).map_err(|_| corrupt("routing key is not UTF-8"))?;
+                Ok((RoutingKey::Name(name.to_string()), 2 + *len as usize))
+            },
+            _ => Err(corrupt("truncated routing key")),
+        }
+    }
+}
+
+/// The keys a reader accepts, see `set_key_filter`
+pub(crate) type KeyFilter = HashSet<RoutingKey>;
+
+fn corrupt(reason: &str) -> QADataSwapError {
+    QADataSwapError::SharedMemory(format!("Corrupt frame: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::*;
+
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{Event, SharedDataFrame};
+
+    #[test]
+    fn test_key_filter_skips_other_keys() -> Result<()> {
+        let config = test_config("routing_keys", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        reader.set_key_filter(["acct-1"]);
+
+        writer.write_with_key(&df! { "qty" => [1i64] }?, "acct-2")?;
+        writer.write(&df! { "qty" => [2i64] }?)?;
+        writer.write_with_key(&df! { "qty" => [3i64] }?, "acct-1")?;
+        writer.write_event(&Event::Heartbeat)?;
+        writer.write_with_key(&df! { "qty" => [4i64] }?, 42)?;
+
+        let (metadata, df) = reader.read_with_metadata(Some(100))?.unwrap();
+        assert_eq!(df.column("qty")?.i64()?.get(0), Some(3));
+        assert_eq!(metadata.routing_key, Some(RoutingKey::from("acct-1")));
+        assert!(matches!(reader.read_event(Some(0))?, Some(Event::Heartbeat)));
+        assert!(matches!(reader.read(Some(0)), Err(QADataSwapError::Timeout)));
+
+        reader.set_key_filter([42u64]);
+        writer.write_with_key(&df! { "qty" => [5i64] }?, 42)?;
+        assert_eq!(reader.read_with_metadata(Some(100))?.unwrap().0.routing_key, Some(RoutingKey::Id(42)));
+
+        reader.clear_key_filter();
+        writer.write(&df! { "qty" => [6i64] }?)?;
+        assert!(reader.read(Some(100))?.is_some());
+
+        assert!(writer.write_with_key(&df! { "qty" => [7i64] }?, "x".repeat(MAX_ROUTING_KEY_LEN + 1)).is_err());
+        Ok(())
+    }
+}
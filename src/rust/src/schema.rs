@@ -0,0 +1,78 @@
+//! Schema evolution: tracking the stream's schema and coercing older frames to it
+
+use polars::prelude::*;
+
+use crate::{QADataSwapError, Result};
+
+/// How a reader treats frames whose schema differs from the stream's latest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadPolicy {
+    /// Return every frame with the schema it was written with
+    #[default]
+    AsWritten,
+    /// Return every frame with the latest schema seen so far: columns added
+    /// later are backfilled with nulls in frames written before the change
+    CoerceToLatestSchema,
+    /// Return frames as written, and when a restarted writer recreates the
+    /// segment, drain the old one and move on to the new one. `read_event()`
+    /// reports the switch as `Event::Reconnected`.
+    AutoReconnect,
+}
+
+/// Extend `latest` with the columns `incoming` appends. Existing columns must
+/// keep their dtype; only additive changes can be coerced.
+pub(crate) fn merge_additive(latest: &Schema, incoming: &Schema) -> Result<Schema> {
+    let mut merged = latest.clone();
+    for (name, dtype) in incoming.iter() {
+        match latest.get(name) {
+            Some(existing) if existing != dtype => {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Column '{}' changed type from {} to {}; only additive schema changes can be coerced",
+                    name, existing, dtype
+                )));
+            },
+            Some(_) => {},
+            None => {
+                merged.with_column(name.clone(), dtype.clone());
+            },
+        }
+    }
+    Ok(merged)
+}
+
+/// Reorder `df` to `schema`, adding all-null columns for the ones it lacks
+pub(crate) fn coerce(df: DataFrame, schema: &Schema) -> Result<DataFrame> {
+    if df.schema().as_ref() == schema {
+        return Ok(df);
+    }
+
+    let height = df.height();
+    let columns = schema
+        .iter()
+        .map(|(name, dtype)| match df.column(name) {
+            Ok(column) => column.clone(),
+            Err(_) => Column::full_null(name.clone(), height, dtype),
+        })
+        .collect();
+    DataFrame::new(columns).map_err(QADataSwapError::Polars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coerce_backfills_appended_columns() -> Result<()> {
+        let old = df! { "id" => [1i64, 2] }?;
+        let new = df! { "id" => [3i64], "price" => [1.5] }?;
+
+        let latest = merge_additive(old.schema(), new.schema())?;
+        let coerced = coerce(old, &latest)?;
+        assert_eq!(coerced.shape(), (2, 2));
+        assert_eq!(coerced.column("price")?.null_count(), 2);
+
+        let retyped = df! { "id" => ["x"] }?;
+        assert!(merge_additive(&latest, retyped.schema()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+//! Rows of any serde type as frames, without `SwapMessage`
+//!
+//! Each row must serialize to a struct or map; its top-level fields become
+//! columns in first-seen order and missing fields are null. Column types follow
+//! the values: booleans, integers (Int64, or UInt64 past `i64::MAX`), floats
+//! (also for mixed numbers) and strings. Nested values are not supported.
+
+use polars::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+use crate::{QADataSwapError, Result};
+
+fn serde_error(e: serde_json::Error) -> QADataSwapError {
+    QADataSwapError::SharedMemory(format!("Serde row conversion failed: {}", e))
+}
+
+/// Narrowest column type that holds every value seen so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Inferred {
+    Null,
+    Bool,
+    Int,
+    UInt,
+    Float,
+    Str,
+}
+
+impl Inferred {
+    fn widen(self, value: &Value, name: &str) -> Result<Self> {
+        let seen = match value {
+            Value::Null => return Ok(self),
+            Value::Bool(_) => Inferred::Bool,
+            Value::Number(n) if n.is_i64() => Inferred::Int,
+            Value::Number(n) if n.is_u64() => Inferred::UInt,
+            Value::Number(_) => Inferred::Float,
+            Value::String(_) => Inferred::Str,
+            Value::Array(_) | Value::Object(_) => {
+                return Err(QADataSwapError::SharedMemory(format!("Field '{}' is nested; only scalar fields map to columns", name)))
+            },
+        };
+        match (self, seen) {
+            (a, b) if a == b => Ok(a),
+            (Inferred::Null, b) => Ok(b),
+            // Like Polars, Int64 and UInt64 together only fit in Float64
+            (Inferred::Int | Inferred::UInt | Inferred::Float, Inferred::Int | Inferred::UInt | Inferred::Float) => {
+                Ok(Inferred::Float)
+            },
+            _ => Err(QADataSwapError::SharedMemory(format!("Field '{}' mixes {:?} and {:?} values", name, self, seen))),
+        }
+    }
+}
+
+/// Build one frame from serializable rows
+pub fn to_dataframe<T: Serialize>(rows: impl IntoIterator<Item = T>) -> Result<DataFrame> {
+    let mut names: Vec<String> = Vec::new();
+    let mut objects: Vec<Map<String, Value>> = Vec::new();
+    for row in rows {
+        let object = match serde_json::to_value(row).map_err(serde_error)? {
+            Value::Object(object) => object,
+            other => {
+                return Err(QADataSwapError::SharedMemory(format!(
+                    "Rows must serialize to a struct or map, got {}", other
+                )))
+            },
+        };
+        for name in object.keys() {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        objects.push(object);
+    }
+
+    let columns = names
+        .iter()
+        .map(|name| {
+            let values: Vec<&Value> = objects.iter().map(|object| object.get(name).unwrap_or(&Value::Null)).collect();
+            let inferred = values.iter().try_fold(Inferred::Null, |inferred, value| inferred.widen(value, name))?;
+            Ok(build_column(name, inferred, &values))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    DataFrame::new(columns).map_err(QADataSwapError::Polars)
+}
+
+fn build_column(name: &str, inferred: Inferred, values: &[&Value]) -> Column {
+    let name = name.into();
+    match inferred {
+        Inferred::Bool => Column::new(name, values.iter().map(|v| v.as_bool()).collect::<Vec<_>>()),
+        Inferred::Int => Column::new(name, values.iter().map(|v| v.as_i64()).collect::<Vec<_>>()),
+        Inferred::UInt => Column::new(name, values.iter().map(|v| v.as_u64()).collect::<Vec<_>>()),
+        Inferred::Float => Column::new(name, values.iter().map(|v| v.as_f64()).collect::<Vec<_>>()),
+        Inferred::Str | Inferred::Null => {
+            Column::new(name, values.iter().map(|v| v.as_str()).collect::<Vec<_>>())
+        },
+    }
+}
+
+/// Deserialize every row of a frame
+pub fn from_dataframe<T: DeserializeOwned>(df: &DataFrame) -> Result<Vec<T>> {
+    let columns = df
+        .get_columns()
+        .iter()
+        .map(|column| Ok((column.name().to_string(), column_values(column)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    (0..df.height())
+        .map(|row| {
+            let object: Map<String, Value> =
+                columns.iter().map(|(name, values)| (name.clone(), values[row].clone())).collect();
+            serde_json::from_value(Value::Object(object)).map_err(serde_error)
+        })
+        .collect()
+}
+
+fn column_values(column: &Column) -> Result<Vec<Value>> {
+    let values = match column.dtype() {
+        DataType::Boolean => column.bool()?.into_iter().map(|v| v.map_or(Value::Null, Value::Bool)).collect(),
+        dtype if dtype.is_unsigned_integer() => {
+            let cast = column.cast(&DataType::UInt64)?;
+            cast.u64()?.into_iter().map(|v| v.map_or(Value::Null, Value::from)).collect()
+        },
+        dtype if dtype.is_integer() => {
+            let cast = column.cast(&DataType::Int64)?;
+            cast.i64()?.into_iter().map(|v| v.map_or(Value::Null, Value::from)).collect()
+        },
+        dtype if dtype.is_float() => {
+            let cast = column.cast(&DataType::Float64)?;
+            // NaN and infinities have no serde_json representation
+            cast.f64()?
+                .into_iter()
+                .map(|v| v.and_then(Number::from_f64).map_or(Value::Null, Value::Number))
+                .collect()
+        },
+        _ => {
+            let cast = column.cast(&DataType::String)?;
+            cast.str()?.into_iter().map(|v| v.map_or(Value::Null, Value::from)).collect()
+        },
+    };
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u64,
+        symbol: String,
+        price: f64,
+        note: Option<String>,
+    }
+
+    #[test]
+    fn test_serde_rows_round_trip() -> Result<()> {
+        let config = test_config("serde_orders", 4);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let orders = vec![
+            Order { id: 1, symbol: "AAPL".to_string(), price: 1.5, note: None },
+            Order { id: 2, symbol: "MSFT".to_string(), price: 2.0, note: Some("ioc".to_string()) },
+        ];
+        writer.write_serde(orders.iter())?;
+        let df = reader.read(Some(100))?.unwrap();
+        let names: Vec<&str> = df.get_column_names().into_iter().map(|n| n.as_str()).collect();
+        assert_eq!(names, vec!["id", "symbol", "price", "note"]);
+        assert_eq!(from_dataframe::<Order>(&df)?, orders);
+
+        assert!(writer.write_serde([vec![1, 2]]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,426 @@
+//! Shared memory backend: FFI bindings to the C++ core
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use crate::auth::{Challenge, NONCE_SIZE, TAG_SIZE};
+use crate::backend::{
+    GroupDump, HeaderDump, RawArena, ReaderDump, SegmentStatus, SlotDump, WakeWord, MAX_META_ENTRIES,
+    META_KEY_SIZE, META_VALUE_SIZE, STATUS_ERROR, STATUS_OK,
+};
+use crate::{Advice, MemoryProtection, MemoryReport, OpenMode, QADataSwapError, Result, SeekFrom};
+
+extern "C" {
+    fn qads_create_arena(name: *const c_char, size: usize, buffer_count: usize) -> *mut c_void;
+    fn qads_destroy_arena(arena: *mut c_void);
+    fn qads_create_writer(arena: *mut c_void) -> c_int;
+    fn qads_create_writer_with_mode(arena: *mut c_void, mode: c_int) -> c_int;
+    fn qads_attach_reader(arena: *mut c_void) -> c_int;
+    fn qads_write_data(arena: *mut c_void, data: *const u8, size: usize) -> c_int;
+    fn qads_read_data(arena: *mut c_void, data: *mut u8, max_size: usize,
+                      actual_size: *mut usize, timeout_ms: c_int) -> c_int;
+    fn qads_peek_size(arena: *mut c_void, size: *mut usize, timeout_ms: c_int) -> c_int;
+    fn qads_last_read_sequence(arena: *mut c_void, sequence: *mut u64) -> c_int;
+    fn qads_has_data(arena: *mut c_void) -> c_int;
+    fn qads_acquire_write_buffer(arena: *mut c_void, capacity: *mut usize) -> *mut u8;
+    fn qads_publish_acquired(arena: *mut c_void, size: usize) -> c_int;
+    fn qads_release_acquired(arena: *mut c_void) -> c_int;
+    fn qads_reserve_slots(arena: *mut c_void, max_buffer_count: usize) -> c_int;
+    fn qads_reslot(arena: *mut c_void, buffer_count: usize) -> c_int;
+    fn qads_begin_batch(arena: *mut c_void) -> c_int;
+    fn qads_commit_batch(arena: *mut c_void) -> c_int;
+    fn qads_abort_batch(arena: *mut c_void) -> c_int;
+    fn qads_memory_report(arena: *mut c_void, report: *mut MemoryReport) -> c_int;
+    fn qads_dump_header(arena: *mut c_void, dump: *mut HeaderDump) -> c_int;
+    fn qads_dump_slot(arena: *mut c_void, index: usize, dump: *mut SlotDump) -> c_int;
+    fn qads_dump_group(arena: *mut c_void, index: usize, dump: *mut GroupDump) -> c_int;
+    fn qads_dump_reader(arena: *mut c_void, index: usize, dump: *mut ReaderDump) -> c_int;
+    fn qads_seek(arena: *mut c_void, origin: c_int, sequence: u64) -> c_int;
+    fn qads_retained_range(arena: *mut c_void, first: *mut u64, next: *mut u64);
+    fn qads_seek_time(arena: *mut c_void, timestamp_us: u64) -> c_int;
+    fn qads_set_retention(arena: *mut c_void, retention_us: u64);
+    fn qads_enable_consumer_groups(arena: *mut c_void);
+    fn qads_join_group(arena: *mut c_void, group: *const c_char) -> c_int;
+    fn qads_set_max_readers(arena: *mut c_void, max_readers: i32);
+    fn qads_set_protection(arena: *mut c_void, flags: c_int);
+    fn qads_set_slot_guards(arena: *mut c_void, flags: c_int);
+    fn qads_set_advice(arena: *mut c_void, flags: c_int);
+    fn qads_set_backing_path(arena: *mut c_void, path: *const c_char) -> c_int;
+    fn qads_set_auth(arena: *mut c_void, nonce: *const u8, tag: *const u8);
+    fn qads_get_auth(arena: *mut c_void, nonce: *mut u8, tag: *mut u8) -> c_int;
+    fn qads_put_meta(arena: *mut c_void, key: *const c_char, value: *const c_char) -> c_int;
+    fn qads_get_meta(arena: *mut c_void, key: *const c_char, value: *mut c_char, max_size: usize) -> c_int;
+    fn qads_meta_key_at(arena: *mut c_void, index: usize, key: *mut c_char) -> c_int;
+    fn qads_set_ack_mode(arena: *mut c_void, enabled: c_int);
+    fn qads_ack(arena: *mut c_void, sequence: u64) -> c_int;
+    fn qads_unacked(arena: *mut c_void) -> u64;
+    fn qads_wait_for_data(arena: *mut c_void, timeout_ms: c_int) -> c_int;
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    fn qads_publish_futex(arena: *mut c_void, word: *mut *mut u32, waiters: *mut *mut i32) -> c_int;
+    fn qads_notify_data_ready(arena: *mut c_void);
+    fn qads_close(arena: *mut c_void);
+    fn qads_abandon(arena: *mut c_void);
+    fn qads_inspect_segment(name: *const c_char, status: *mut SegmentStatus) -> c_int;
+    fn qads_remove_segment(name: *const c_char);
+}
+
+/// Prefix the C++ core gives segment names under /dev/shm
+const SEGMENT_PREFIX: &str = "qads_";
+
+/// String up to the first NUL of a fixed-size buffer filled by the C++ core
+fn c_string(buffer: &[u8]) -> String {
+    CStr::from_bytes_until_nul(buffer)
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+pub(crate) fn list_segments() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir("/dev/shm") else {
+        return Vec::new();
+    };
+    // Semaphores live alongside as sem.qads_w_*/sem.qads_r_*, so the prefix skips them
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|file| file.strip_prefix(SEGMENT_PREFIX).map(str::to_string))
+        .collect()
+}
+
+pub(crate) fn inspect_segment(name: &str) -> Option<SegmentStatus> {
+    let name = CString::new(name).ok()?;
+    let mut status = SegmentStatus::default();
+    match unsafe { qads_inspect_segment(name.as_ptr(), &mut status) } {
+        STATUS_OK => Some(status),
+        _ => None,
+    }
+}
+
+pub(crate) fn remove_segment(name: &str) {
+    if let Ok(name) = CString::new(name) {
+        unsafe { qads_remove_segment(name.as_ptr()) };
+    }
+}
+
+/// Arena living in a POSIX shared memory segment managed by the C++ core
+pub(crate) struct ShmArena {
+    inner: *mut c_void,
+}
+
+unsafe impl Send for ShmArena {}
+unsafe impl Sync for ShmArena {}
+
+impl ShmArena {
+    pub(crate) fn new(name: &str, size: usize, buffer_count: usize) -> Result<Self> {
+        let name_cstr = CString::new(name)
+            .map_err(|_| QADataSwapError::SharedMemory("Invalid name".to_string()))?;
+
+        let inner = unsafe { qads_create_arena(name_cstr.as_ptr(), size, buffer_count) };
+        if inner.is_null() {
+            return Err(QADataSwapError::SharedMemory("Failed to create arena".to_string()));
+        }
+        Ok(Self { inner })
+    }
+}
+
+impl RawArena for ShmArena {
+    fn create_writer(&self, mode: OpenMode) -> i32 {
+        let mode = match mode {
+            OpenMode::Create => return unsafe { qads_create_writer(self.inner) },
+            OpenMode::OpenOrAttach => 1,
+            OpenMode::FailIfExists => 2,
+            OpenMode::Truncate => 3,
+        };
+        unsafe { qads_create_writer_with_mode(self.inner, mode) }
+    }
+
+    fn attach_reader(&self) -> i32 {
+        unsafe { qads_attach_reader(self.inner) }
+    }
+
+    fn write(&self, data: &[u8]) -> i32 {
+        unsafe { qads_write_data(self.inner, data.as_ptr(), data.len()) }
+    }
+
+    fn read(&self, buffer: &mut [u8], actual_size: &mut usize, timeout_ms: i32) -> i32 {
+        unsafe { qads_read_data(self.inner, buffer.as_mut_ptr(), buffer.len(), actual_size, timeout_ms) }
+    }
+
+    fn peek_size(&self, size: &mut usize, timeout_ms: i32) -> i32 {
+        unsafe { qads_peek_size(self.inner, size, timeout_ms) }
+    }
+
+    fn last_read_sequence(&self) -> Option<u64> {
+        let mut sequence = 0u64;
+        match unsafe { qads_last_read_sequence(self.inner, &mut sequence) } {
+            0 => Some(sequence),
+            _ => None,
+        }
+    }
+
+    fn has_data(&self) -> bool {
+        unsafe { qads_has_data(self.inner) == 1 }
+    }
+
+    fn acquire_write_buffer(&self) -> Option<(*mut u8, usize)> {
+        let mut capacity = 0usize;
+        let slot = unsafe { qads_acquire_write_buffer(self.inner, &mut capacity) };
+        (!slot.is_null()).then_some((slot, capacity))
+    }
+
+    fn publish_acquired(&self, size: usize) -> i32 {
+        unsafe { qads_publish_acquired(self.inner, size) }
+    }
+
+    fn release_acquired(&self) {
+        unsafe { qads_release_acquired(self.inner) };
+    }
+
+    fn reserve_slots(&self, max_buffer_count: usize) {
+        unsafe { qads_reserve_slots(self.inner, max_buffer_count) };
+    }
+
+    fn reslot(&self, buffer_count: usize) -> i32 {
+        unsafe { qads_reslot(self.inner, buffer_count) }
+    }
+
+    fn begin_batch(&self) -> i32 {
+        unsafe { qads_begin_batch(self.inner) }
+    }
+
+    fn commit_batch(&self) -> i32 {
+        unsafe { qads_commit_batch(self.inner) }
+    }
+
+    fn abort_batch(&self) {
+        unsafe { qads_abort_batch(self.inner) };
+    }
+
+    fn memory_report(&self, report: &mut MemoryReport) -> i32 {
+        unsafe { qads_memory_report(self.inner, report) }
+    }
+
+    fn dump_header(&self, dump: &mut HeaderDump) -> i32 {
+        unsafe { qads_dump_header(self.inner, dump) }
+    }
+
+    fn dump_slot(&self, index: usize, dump: &mut SlotDump) -> i32 {
+        unsafe { qads_dump_slot(self.inner, index, dump) }
+    }
+
+    fn dump_group(&self, index: usize, dump: &mut GroupDump) -> i32 {
+        unsafe { qads_dump_group(self.inner, index, dump) }
+    }
+
+    fn seek(&self, pos: SeekFrom) -> i32 {
+        let (origin, sequence) = match pos {
+            SeekFrom::Sequence(sequence) => (0, sequence),
+            SeekFrom::Latest => (1, 0),
+            SeekFrom::Earliest => (2, 0),
+        };
+        unsafe { qads_seek(self.inner, origin, sequence) }
+    }
+
+    fn retained_range(&self) -> (u64, u64) {
+        let mut first = 0u64;
+        let mut next = 0u64;
+        unsafe { qads_retained_range(self.inner, &mut first, &mut next) };
+        (first, next)
+    }
+
+    fn seek_time(&self, timestamp_us: u64) -> i32 {
+        unsafe { qads_seek_time(self.inner, timestamp_us) }
+    }
+
+    fn set_retention(&self, retention_us: u64) {
+        unsafe { qads_set_retention(self.inner, retention_us) };
+    }
+
+    fn enable_consumer_groups(&self) {
+        unsafe { qads_enable_consumer_groups(self.inner) };
+    }
+
+    fn join_group(&self, group: &str) -> i32 {
+        match CString::new(group) {
+            Ok(group) => unsafe { qads_join_group(self.inner, group.as_ptr()) },
+            Err(_) => STATUS_ERROR,
+        }
+    }
+
+    fn set_max_readers(&self, max_readers: usize) {
+        unsafe { qads_set_max_readers(self.inner, max_readers.min(i32::MAX as usize) as i32) }
+    }
+
+    fn set_protection(&self, protection: MemoryProtection) {
+        // The C++ core's PROTECT_MEMORY and GUARD_READS flags
+        let flags = match protection {
+            MemoryProtection::Off => 0,
+            MemoryProtection::ReadOnly => 1,
+            MemoryProtection::Guarded => 3,
+        };
+        unsafe { qads_set_protection(self.inner, flags) }
+    }
+
+    fn set_slot_guards(&self, guard_pages: bool, canaries: bool) {
+        // GUARD_PAGES and CANARIES
+        let flags = guard_pages as c_int | (canaries as c_int) << 1;
+        unsafe { qads_set_slot_guards(self.inner, flags) }
+    }
+
+    fn set_advice(&self, advice: &[Advice]) {
+        // The C++ core's ADVISE_* flags
+        let flags = advice.iter().fold(0, |flags, advice| {
+            flags | match advice {
+                Advice::Willneed => 1,
+                Advice::Sequential => 2,
+                Advice::Random => 4,
+                Advice::Hugepage => 8,
+                Advice::NoHugepage => 16,
+                Advice::Dontdump => 32,
+            }
+        });
+        unsafe { qads_set_advice(self.inner, flags) }
+    }
+
+    fn set_backing_path(&self, path: &Path) -> i32 {
+        let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+            return STATUS_ERROR;
+        };
+        unsafe { qads_set_backing_path(self.inner, path.as_ptr()) }
+    }
+
+    fn dump_reader(&self, index: usize, dump: &mut ReaderDump) -> i32 {
+        unsafe { qads_dump_reader(self.inner, index, dump) }
+    }
+
+    fn set_auth(&self, challenge: Option<&Challenge>) {
+        match challenge {
+            Some(challenge) => unsafe {
+                qads_set_auth(self.inner, challenge.nonce.as_ptr(), challenge.tag.as_ptr())
+            },
+            None => unsafe { qads_set_auth(self.inner, std::ptr::null(), std::ptr::null()) },
+        }
+    }
+
+    fn auth(&self) -> Option<Challenge> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        let mut tag = [0u8; TAG_SIZE];
+        match unsafe { qads_get_auth(self.inner, nonce.as_mut_ptr(), tag.as_mut_ptr()) } {
+            1 => Some(Challenge { nonce, tag }),
+            _ => None,
+        }
+    }
+
+    fn put_meta(&self, key: &str, value: Option<&str>) -> i32 {
+        let Ok(key) = CString::new(key) else {
+            return STATUS_ERROR;
+        };
+        match value.map(CString::new) {
+            Some(Ok(value)) => unsafe { qads_put_meta(self.inner, key.as_ptr(), value.as_ptr()) },
+            Some(Err(_)) => STATUS_ERROR,
+            None => unsafe { qads_put_meta(self.inner, key.as_ptr(), std::ptr::null()) },
+        }
+    }
+
+    fn get_meta(&self, key: &str) -> Option<String> {
+        let key = CString::new(key).ok()?;
+        let mut value = [0u8; META_VALUE_SIZE];
+        match unsafe { qads_get_meta(self.inner, key.as_ptr(), value.as_mut_ptr() as *mut c_char, value.len()) } {
+            1 => Some(c_string(&value)),
+            _ => None,
+        }
+    }
+
+    fn meta_keys(&self) -> Vec<String> {
+        (0..MAX_META_ENTRIES)
+            .filter_map(|index| {
+                let mut key = [0u8; META_KEY_SIZE];
+                let rc = unsafe { qads_meta_key_at(self.inner, index, key.as_mut_ptr() as *mut c_char) };
+                (rc == 1).then(|| c_string(&key))
+            })
+            .collect()
+    }
+
+    fn set_ack_mode(&self, enabled: bool) {
+        unsafe { qads_set_ack_mode(self.inner, enabled as c_int) };
+    }
+
+    fn ack(&self, sequence: u64) -> i32 {
+        unsafe { qads_ack(self.inner, sequence) }
+    }
+
+    fn unacked(&self) -> u64 {
+        unsafe { qads_unacked(self.inner) }
+    }
+
+    fn wait_for_data(&self, timeout_ms: i32) -> i32 {
+        unsafe { qads_wait_for_data(self.inner, timeout_ms) }
+    }
+
+    fn wake_word(&self) -> Option<WakeWord> {
+        let mut word = std::ptr::null_mut();
+        let mut waiters = std::ptr::null_mut();
+        match unsafe { qads_publish_futex(self.inner, &mut word, &mut waiters) } {
+            STATUS_OK => Some(WakeWord {
+                futex: word.cast(),
+                waiters: waiters.cast(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn notify_data_ready(&self) {
+        unsafe { qads_notify_data_ready(self.inner) };
+    }
+
+    fn close(&self) {
+        unsafe { qads_close(self.inner) };
+    }
+
+    fn abandon(&self) {
+        unsafe { qads_abandon(self.inner) };
+    }
+}
+
+impl Drop for ShmArena {
+    fn drop(&mut self) {
+        if !self.inner.is_null() {
+            unsafe { qads_destroy_arena(self.inner) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_c_string_stops_at_the_first_nul() {
+        assert_eq!(c_string(b"orders\0stale\0"), "orders");
+        assert_eq!(c_string(b"\0"), "");
+        // A buffer the core left unterminated is not trusted
+        assert_eq!(c_string(b"orders"), "");
+    }
+
+    #[test]
+    fn test_segments_are_listed_without_their_semaphores() {
+        let segment = format!("/dev/shm/{}shm_listing_{}", SEGMENT_PREFIX, std::process::id());
+        let semaphore = format!("/dev/shm/sem.{}w_shm_listing_{}", SEGMENT_PREFIX, std::process::id());
+        if std::fs::write(&segment, b"").is_err() {
+            return;
+        }
+        std::fs::write(&semaphore, b"").unwrap();
+
+        let listed = list_segments();
+        let _ = std::fs::remove_file(&segment);
+        let _ = std::fs::remove_file(&semaphore);
+        assert!(listed.contains(&format!("shm_listing_{}", std::process::id())));
+        assert!(!listed.iter().any(|name| name.contains("w_shm_listing")));
+    }
+
+    #[test]
+    fn test_names_with_a_nul_are_rejected_before_the_core() {
+        assert!(matches!(ShmArena::new("bad\0name", 1024 * 1024, 3), Err(QADataSwapError::SharedMemory(_))));
+        assert!(inspect_segment("bad\0name").is_none());
+    }
+}
@@ -0,0 +1,242 @@
+//! Splitting DataFrames too large for one frame, see `with_max_frame_bytes`
+//!
+//! The writer cuts an oversize DataFrame into row ranges and publishes each as
+//! a part of one message. With `FLAG_PART` a part trailer follows the payload
+//! (after the routing key, if any):
+//!
+//! ```text
+//! u64 message | u32 index | u32 count
+//! ```
+//!
+//! Readers hold parts back until the last one arrives and deliver the message
+//! as one DataFrame. A message with a part missing (expired, or lost to a
+//! reader that attached midway) is dropped whole, its parts counted in
+//! `ReadStats::incomplete`. Readers sharing a cursor with others (consumer
+//! group members, or plain readers of one ring) each get only some of the
+//! parts, so they complete few split messages; splitting suits channels with
+//! one reader per cursor.
+
+use std::collections::HashMap;
+
+use polars::prelude::*;
+
+use crate::frame::{FrameHeader, FLAG_PART, FLAG_ROUTING_KEY, FRAME_HEADER_SIZE};
+use crate::{FrameMetadata, QADataSwapError, Result, RoutingKey};
+
+/// Encoded size of the part trailer
+pub(crate) const PART_SIZE: usize = 16;
+
+/// Where a frame sits in a split message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Part {
+    /// Writer-local message number, shared by all of its parts
+    pub(crate) message: u64,
+    pub(crate) index: u32,
+    pub(crate) count: u32,
+}
+
+impl Part {
+    pub(crate) fn to_bytes(self) -> [u8; PART_SIZE] {
+        let mut bytes = [0u8; PART_SIZE];
+        bytes[..8].copy_from_slice(&self.message.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.index.to_le_bytes());
+        bytes[12..].copy_from_slice(&self.count.to_le_bytes());
+        bytes
+    }
+
+    /// The part trailer of a complete frame, if its header has `FLAG_PART`
+    pub(crate) fn decode(header: &FrameHeader, bytes: &[u8]) -> Result<Option<Self>> {
+        if header.flags & FLAG_PART == 0 {
+            return Ok(None);
+        }
+        let mut trailer = bytes.get(FRAME_HEADER_SIZE + header.payload_len as usize..).unwrap_or_default();
+        if header.flags & FLAG_ROUTING_KEY != 0 {
+            trailer = &trailer[RoutingKey::parse(trailer)?.1..];
+        }
+        let Some(trailer) = trailer.get(..PART_SIZE) else {
+            return Err(QADataSwapError::SharedMemory("Corrupt frame: truncated part".to_string()));
+        };
+        let part = Self {
+            message: u64::from_le_bytes(trailer[..8].try_into().unwrap()),
+            index: u32::from_le_bytes(trailer[8..12].try_into().unwrap()),
+            count: u32::from_le_bytes(trailer[12..].try_into().unwrap()),
+        };
+        if part.index >= part.count {
+            return Err(QADataSwapError::SharedMemory("Corrupt frame: part index out of range".to_string()));
+        }
+        Ok(Some(part))
+    }
+}
+
+/// Row ranges of `height` rows in `count` nearly equal parts
+pub(crate) fn row_ranges(height: usize, count: usize) -> impl Iterator<Item = (i64, usize)> {
+    let rows = height.div_ceil(count.max(1)).max(1);
+    (0..height).step_by(rows).map(move |offset| (offset as i64, rows.min(height - offset)))
+}
+
+/// Messages held back at once; beyond this the oldest is dropped
+const MAX_PENDING: usize = 16;
+
+/// The ring a part arrived on (normal or high lane, or a stripe), its writer
+/// and its message number
+type MessageKey = (usize, u32, u64);
+
+/// A reader's messages being put back together
+#[derive(Default)]
+pub(crate) struct Assembly {
+    pending: HashMap<MessageKey, Pending>,
+    started: u64,
+    dropped: u64,
+}
+
+struct Pending {
+    started: u64,
+    part: Part,
+    metadata: FrameMetadata,
+    frames: Vec<DataFrame>,
+}
+
+impl Assembly {
+    /// Add a part decoded from `ring` (`None` when the reader's filter left
+    /// no rows). Returns the message, with the first part's metadata, once
+    /// complete.
+    pub(crate) fn push(&mut self, ring: usize, part: Part, metadata: FrameMetadata, df: Option<DataFrame>)
+                       -> Result<Option<(FrameMetadata, Option<DataFrame>)>> {
+        let key = (ring, metadata.writer_id, part.message);
+        let follows = self.pending.get(&key).is_some_and(|pending| {
+            pending.part.index + 1 == part.index && pending.part.count == part.count
+        });
+        if part.index == 0 {
+            self.drop_message(&key);
+            if self.pending.len() >= MAX_PENDING {
+                let oldest = self.pending.iter().min_by_key(|(_, pending)| pending.started).map(|(key, _)| *key);
+                self.drop_message(&oldest.unwrap());
+            }
+            self.started += 1;
+            self.pending.insert(key, Pending {
+                started: self.started,
+                part,
+                metadata,
+                frames: Vec::new(),
+            });
+        } else if follows {
+            let pending = self.pending.get_mut(&key).unwrap();
+            pending.part = part;
+            // Null counts of the message are those of all its parts
+            pending.metadata.null_counts = match (pending.metadata.null_counts.take(), metadata.null_counts) {
+                (Some(mut counts), Some(more)) => {
+                    for (column, nulls) in more {
+                        *counts.entry(column).or_default() += nulls;
+                    }
+                    Some(counts)
+                },
+                _ => None,
+            };
+        } else {
+            // A part before this one was missed; skip the rest of the message
+            self.drop_message(&key);
+            self.dropped += 1;
+            return Ok(None);
+        }
+
+        let pending = self.pending.get_mut(&key).unwrap();
+        pending.frames.extend(df);
+        if part.index + 1 < part.count {
+            return Ok(None);
+        }
+        let Pending { metadata, frames, .. } = self.pending.remove(&key).unwrap();
+        let mut frames = frames.into_iter();
+        let Some(mut df) = frames.next() else {
+            return Ok(Some((metadata, None)));
+        };
+        for part in frames {
+            df.vstack_mut(&part)?;
+        }
+        df.rechunk_mut();
+        Ok(Some((metadata, Some(df))))
+    }
+
+    /// Parts dropped with incomplete messages since the last call
+    pub(crate) fn take_dropped(&mut self) -> u64 {
+        std::mem::take(&mut self.dropped)
+    }
+
+    fn drop_message(&mut self, key: &MessageKey) {
+        if let Some(dropped) = self.pending.remove(key) {
+            log::warn!("Dropping incomplete message {} from writer {}", key.2, key.1);
+            self.dropped += dropped.part.index as u64 + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{test_config, FaultInjector};
+    use crate::validators::{Check, ValidationPolicy, Validators};
+    use crate::SharedDataFrame;
+
+    #[test]
+    fn test_oversize_frames_split_and_reassemble() -> Result<()> {
+        let config = test_config("split_frames", 64).with_max_frame_bytes(8 * 1024);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        reader.set_key_filter(["book"]);
+
+        let px: Vec<f64> = (0..5000).map(|i| i as f64).collect();
+        let qty: Vec<Option<i64>> = (0..5000).map(|i| (i % 10 != 0).then_some(i)).collect();
+        let book = df! { "px" => px, "qty" => qty }?;
+        writer.write_with_key(&book, "book")?;
+        assert!(writer.write_stats().frames > 5);
+
+        let (metadata, df) = reader.read_with_metadata(Some(100))?.unwrap();
+        assert_eq!(df, book);
+        assert_eq!(metadata.routing_key, Some(RoutingKey::from("book")));
+        assert_eq!(metadata.null_count("qty"), Some(500));
+
+        // Frames that fit go out whole
+        writer.write_with_key(&df! { "px" => [1.0] }?, "book")?;
+        assert_eq!(reader.read(Some(100))?.map(|df| df.height()), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_frames_validated_whole() -> Result<()> {
+        // Strictly increasing across frames: a part checked after the whole would fail
+        let validators = Validators::new(ValidationPolicy::Reject).with_check(Check::strictly_increasing("px"));
+        let config = test_config("split_validated", 64).with_max_frame_bytes(8 * 1024);
+        let writer = SharedDataFrame::create_writer(config.clone().with_validators(validators))?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let px: Vec<f64> = (0..5000).map(|i| i as f64).collect();
+        writer.write(&df! { "px" => px }?)?;
+        assert!(writer.write_stats().frames > 5);
+        assert_eq!(writer.validation_stats().map(|stats| stats.frames_checked), Some(1));
+        assert_eq!(reader.read(Some(100))?.map(|df| df.height()), Some(5000));
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_members_drop_split_messages() -> Result<()> {
+        let config = test_config("split_groups", 64)
+            .with_max_frame_bytes(8 * 1024)
+            .with_consumer_groups(true);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        // The first member stops after two parts, leaving the rest on the group's cursor
+        let first = SharedDataFrame::create_reader(
+            config.clone().with_consumer_group("workers").with_fault_injector(FaultInjector::new().memory_fault_at(2)),
+        )?;
+        let second = SharedDataFrame::create_reader(config.with_consumer_group("workers"))?;
+
+        let px: Vec<f64> = (0..5000).map(|i| i as f64).collect();
+        writer.write(&df! { "px" => px }?)?;
+        writer.write(&df! { "px" => [1.0] }?)?;
+        let parts = writer.write_stats().frames - 1;
+
+        assert!(matches!(first.read(Some(0)), Err(QADataSwapError::MemoryFault(_))));
+        assert_eq!(second.read(Some(0))?.map(|df| df.height()), Some(1));
+        assert_eq!(second.read_stats().incomplete, parts - 3);
+        assert!(matches!(first.read(Some(0)), Err(QADataSwapError::Timeout)));
+        Ok(())
+    }
+}
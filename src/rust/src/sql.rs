@@ -0,0 +1,183 @@
+//! DataFusion integration: query a live stream with SQL
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use datafusion::arrow::datatypes::SchemaRef as ArrowSchemaRef;
+use datafusion::arrow::ipc::reader::FileReader;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::catalog::Session;
+use datafusion::datasource::TableProvider;
+use datafusion::error::{DataFusionError, Result as DataFusionResult};
+use datafusion::logical_expr::{Expr, TableType};
+use datafusion::physical_plan::memory::MemoryExec;
+use datafusion::physical_plan::ExecutionPlan;
+use polars::prelude::*;
+
+use crate::{QADataSwapError, Result, SharedDataFrame};
+
+/// Which frames a `SharedArenaTable` exposes to queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableSnapshot {
+    /// Only the most recent frame
+    Latest,
+    /// The last `n` frames, concatenated
+    Window(usize),
+}
+
+/// DataFusion table over a shared stream. Every scan first drains the frames
+/// published since the previous one, so queries see the live snapshot:
+///
+/// ```ignore
+/// let ctx = SessionContext::new();
+/// ctx.register_table("quotes", Arc::new(SharedArenaTable::new(reader, TableSnapshot::Window(100))?))?;
+/// ctx.sql("SELECT symbol, avg(price) FROM quotes GROUP BY symbol").await?.show().await?;
+/// ```
+pub struct SharedArenaTable {
+    reader: Mutex<SharedDataFrame>,
+    snapshot: TableSnapshot,
+    schema: ArrowSchemaRef,
+    frames: Mutex<VecDeque<Vec<RecordBatch>>>,
+}
+
+impl SharedArenaTable {
+    /// Wait (up to the reader's configured timeout) for the first frame, which
+    /// fixes the table schema
+    pub fn new(reader: SharedDataFrame, snapshot: TableSnapshot) -> Result<Self> {
+        let first = reader.read(None)?.ok_or(QADataSwapError::Timeout)?;
+        let batches = to_record_batches(&first)?;
+        let schema = match batches.first() {
+            Some(batch) => batch.schema(),
+            None => return Err(QADataSwapError::SharedMemory("First frame carried no schema".to_string())),
+        };
+
+        Ok(Self {
+            reader: Mutex::new(reader),
+            snapshot,
+            schema,
+            frames: Mutex::new(VecDeque::from([batches])),
+        })
+    }
+
+    /// Pull in every frame published since the last call without blocking
+    pub fn refresh(&self) -> Result<()> {
+        let reader = self.reader.lock().unwrap();
+        let mut frames = self.frames.lock().unwrap();
+        let keep = match self.snapshot {
+            TableSnapshot::Latest => 1,
+            TableSnapshot::Window(n) => n.max(1),
+        };
+
+        while reader.has_data() {
+            let Some(df) = reader.read(Some(0))? else {
+                break;
+            };
+            let batches = to_record_batches(&df)?;
+            if batches.iter().any(|batch| batch.schema() != self.schema) {
+                return Err(QADataSwapError::SharedMemory(
+                    "Frame schema differs from the table schema".to_string(),
+                ));
+            }
+            frames.push_back(batches);
+            while frames.len() > keep {
+                frames.pop_front();
+            }
+        }
+        Ok(())
+    }
+
+    fn batches(&self) -> Vec<RecordBatch> {
+        self.frames.lock().unwrap().iter().flatten().cloned().collect()
+    }
+}
+
+impl std::fmt::Debug for SharedArenaTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedArenaTable")
+            .field("snapshot", &self.snapshot)
+            .field("schema", &self.schema)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl TableProvider for SharedArenaTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> ArrowSchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Temporary
+    }
+
+    async fn scan(
+        &self,
+        _state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        self.refresh().map_err(|e| DataFusionError::External(Box::new(e)))?;
+        let exec = MemoryExec::try_new(&[self.batches()], self.schema(), projection.cloned())?;
+        Ok(Arc::new(exec))
+    }
+}
+
+/// Hand a frame to arrow-rs through an IPC round trip (polars keeps its own arrow)
+pub(crate) fn to_record_batches(df: &DataFrame) -> Result<Vec<RecordBatch>> {
+    let mut bytes = Vec::new();
+    IpcWriter::new(&mut bytes)
+        .with_compat_level(CompatLevel::oldest())
+        .finish(&mut df.clone())
+        .map_err(QADataSwapError::Polars)?;
+
+    let arrow_error = |e: datafusion::arrow::error::ArrowError| {
+        QADataSwapError::SharedMemory(format!("Arrow conversion failed: {}", e))
+    };
+    FileReader::try_new(std::io::Cursor::new(bytes), None)
+        .map_err(arrow_error)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(arrow_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::SessionContext;
+
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{SharedMemoryConfig, Transport};
+
+    #[test]
+    fn test_sql_over_live_stream() -> Result<()> {
+        let config = test_config("sql_quotes", 8).with_timeout_ms(1000);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        writer.write(&df! { "symbol" => ["A", "B"], "price" => [1.0, 10.0] }?)?;
+
+        let table = Arc::new(SharedArenaTable::new(reader, TableSnapshot::Window(2))?);
+        writer.write(&df! { "symbol" => ["A", "B"], "price" => [3.0, 20.0] }?)?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        let batches = runtime
+            .block_on(async {
+                let ctx = SessionContext::new();
+                ctx.register_table("quotes", table)?;
+                ctx.sql("SELECT symbol, avg(price) AS avg_price FROM quotes GROUP BY symbol ORDER BY symbol")
+                    .await?
+                    .collect()
+                    .await
+            })
+            .map_err(|e| QADataSwapError::SharedMemory(e.to_string()))?;
+
+        let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+        assert_eq!(rows, 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+//! One logical channel striped across several segments, so multi-GB frames are
+//! copied in and out by one thread per stripe instead of a single memcpy
+//!
+//! The encoded frame is cut into `stripes` contiguous pieces; piece `i` goes to
+//! segment `<name>#stripe<i>`, sized by the config's `size_mb`. Each piece
+//! starts with the frame's sequence number and total length so the reader can
+//! check the stripes are in step before reassembling.
+
+use std::thread;
+use std::time::Duration;
+
+use polars::prelude::*;
+
+use crate::frame::{FrameHeader, FrameKind};
+use crate::masking;
+use crate::parallel;
+use crate::priority::LANE_SEPARATOR;
+use crate::zdict::PayloadCompression;
+use crate::{decode_dataframe, Event, QADataSwapError, Result, Scratch, SharedMemoryArena, SharedMemoryConfig};
+
+/// Sequence number and total frame length ahead of every piece
+const STRIPE_HEADER_SIZE: usize = 16;
+
+/// How long to wait for the other pieces once the first has arrived
+const STRIPE_SKEW_TIMEOUT_MS: i32 = 5000;
+
+const MAX_STRIPES: usize = 64;
+
+fn open_stripes(config: &SharedMemoryConfig, stripes: usize) -> Result<Vec<SharedMemoryArena>> {
+    if stripes == 0 || stripes > MAX_STRIPES {
+        return Err(QADataSwapError::SharedMemory(format!(
+            "Stripe count must be between 1 and {}, got {}", MAX_STRIPES, stripes
+        )));
+    }
+    (0..stripes)
+        .map(|i| {
+            let mut stripe = config.clone();
+            stripe.name = format!("{}{}stripe{}", config.name, LANE_SEPARATOR, i);
+            stripe.priority_lanes = None;
+            stripe.dead_letter_queue = false;
+            SharedMemoryArena::new(stripe)
+        })
+        .collect()
+}
+
+/// Writer half of a striped channel
+pub struct StripedWriter {
+    stripes: Vec<SharedMemoryArena>,
+    scratch: Scratch,
+    sequence: u64,
+}
+
+impl StripedWriter {
+    pub fn create(config: SharedMemoryConfig, stripes: usize) -> Result<Self> {
+        let mut stripes = open_stripes(&config, stripes)?;
+        for stripe in &mut stripes {
+            stripe.create_writer()?;
+        }
+        Ok(Self {
+            stripes,
+            scratch: Scratch::new().with_clock(config.clock).with_time_source(Arc::clone(&config.time_source)),
+            sequence: 0,
+        })
+    }
+
+    pub fn stripe_count(&self) -> usize {
+        self.stripes.len()
+    }
+
+    /// Encode `df` once and write its pieces to all stripes in parallel
+    pub fn write(&mut self, df: &DataFrame) -> Result<()> {
+        let arena = &self.stripes[0];
+        let masked = masking::mask(df, &arena.config.column_policies)?;
+        let df = masked.as_ref().unwrap_or(df);
+        let bytes = self.scratch.encode_with(df, arena.writer_id(), PayloadCompression::Ipc(arena.config.compression), None, None)?;
+        publish(&self.stripes, bytes, self.sequence)?;
+        self.sequence += 1;
+        Ok(())
+    }
+
+    /// Control frames (EOS, heartbeat, flush) go through the same striping
+    pub fn write_event(&mut self, event: &Event) -> Result<()> {
+        if let Event::Data(df) = event {
+            return self.write(df);
+        }
+        let bytes = self.scratch.encode_control(event, self.stripes[0].writer_id())?;
+        publish(&self.stripes, bytes, self.sequence)?;
+        self.sequence += 1;
+        Ok(())
+    }
+}
+
+fn publish(stripes: &[SharedMemoryArena], bytes: &[u8], sequence: u64) -> Result<()> {
+    let piece_len = bytes.len().div_ceil(stripes.len()).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = stripes
+            .iter()
+            .enumerate()
+            .map(|(i, stripe)| {
+                let start = (i * piece_len).min(bytes.len());
+                let piece = &bytes[start..(start + piece_len).min(bytes.len())];
+                scope.spawn(move || {
+                    let mut framed = Vec::with_capacity(STRIPE_HEADER_SIZE + piece.len());
+                    framed.extend_from_slice(&sequence.to_le_bytes());
+                    framed.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+                    framed.extend_from_slice(piece);
+                    stripe.write_dataframe_bytes(&framed)
+                })
+            })
+            .collect();
+        handles.into_iter().try_for_each(join_stripe)
+    })
+}
+
+fn join_stripe<T>(handle: thread::ScopedJoinHandle<'_, Result<T>>) -> Result<T> {
+    handle
+        .join()
+        .map_err(|_| QADataSwapError::SharedMemory("Stripe thread panicked".to_string()))?
+}
+
+/// Reader half of a striped channel
+pub struct StripedReader {
+    stripes: Vec<SharedMemoryArena>,
+}
+
+impl StripedReader {
+    /// `stripes` must match the writer's count
+    pub fn attach(config: SharedMemoryConfig, stripes: usize) -> Result<Self> {
+        let mut stripes = open_stripes(&config, stripes)?;
+        for stripe in &mut stripes {
+            stripe.attach_reader()?;
+        }
+        Ok(Self { stripes })
+    }
+
+    pub fn stripe_count(&self) -> usize {
+        self.stripes.len()
+    }
+
+    /// Next data frame; control frames are skipped
+    pub fn read(&self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
+        loop {
+            match self.read_event(timeout_ms)? {
+                Some(Event::Data(df)) => return Ok(Some(df)),
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Next frame once every stripe has delivered its piece
+    pub fn read_event(&self, timeout_ms: Option<i32>) -> Result<Option<Event>> {
+        self.wait_for_first(timeout_ms)?;
+
+        let pieces = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .stripes
+                .iter()
+                .map(|stripe| scope.spawn(move || stripe.read_dataframe_bytes(Some(STRIPE_SKEW_TIMEOUT_MS))))
+                .collect();
+            handles.into_iter().map(join_stripe).collect::<Result<Vec<_>>>()
+        })?;
+        let Some(pieces) = pieces.into_iter().collect::<Option<Vec<_>>>() else {
+            return Ok(None);
+        };
+
+        let bytes = reassemble(&pieces)?;
+        let Some((header, payload)) = FrameHeader::decode(&bytes)? else {
+            return Ok(Some(Event::Data(decode_dataframe(&bytes)?)));
+        };
+        let policies = &self.stripes[0].config.column_policies;
+        let event = match header.kind {
+            FrameKind::Eos => Event::Eos,
+            FrameKind::Heartbeat => Event::Heartbeat,
+            FrameKind::Flush => Event::Flush,
+            FrameKind::SchemaChange => Event::SchemaChange(decode_dataframe(payload)?.schema().clone()),
+            FrameKind::Data => Event::Data(masking::unmask(decode_dataframe(payload)?, policies)?),
+            FrameKind::ColumnGroups => Event::Data(masking::unmask(parallel::decode_groups(payload)?, policies)?),
+            kind => {
+                return Err(QADataSwapError::SharedMemory(format!("{:?} frames cannot be striped", kind)))
+            },
+        };
+        Ok(Some(event))
+    }
+
+    /// Block until the first stripe has a piece, without consuming it
+    fn wait_for_first(&self, timeout_ms: Option<i32>) -> Result<()> {
+        let first = &self.stripes[0];
+        let timeout = timeout_ms.unwrap_or(first.config.timeout_ms.unwrap_or(-1));
+        let time = &first.config.time_source;
+        let deadline = (timeout >= 0).then(|| time.instant() + Duration::from_millis(timeout as u64));
+        while !first.has_data() {
+            let remaining = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(time.instant()) {
+                    Some(remaining) if !remaining.is_zero() => remaining.as_millis().max(1) as i32,
+                    _ => return Err(QADataSwapError::Timeout),
+                },
+                None => -1,
+            };
+            match first.wait_for_data(Some(remaining)) {
+                Ok(()) | Err(QADataSwapError::Timeout) => {},
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check every piece belongs to the same frame and stitch the frame back together
+fn reassemble(pieces: &[Vec<u8>]) -> Result<Vec<u8>> {
+    let prefix = |piece: &[u8]| -> Result<(u64, usize)> {
+        if piece.len() < STRIPE_HEADER_SIZE {
+            return Err(QADataSwapError::SharedMemory("Truncated stripe piece".to_string()));
+        }
+        let sequence = u64::from_le_bytes(piece[..8].try_into().unwrap());
+        let total = u64::from_le_bytes(piece[8..16].try_into().unwrap()) as usize;
+        Ok((sequence, total))
+    };
+
+    let (sequence, total) = prefix(&pieces[0])?;
+    let mut bytes = Vec::with_capacity(total);
+    for (i, piece) in pieces.iter().enumerate() {
+        if prefix(piece)? != (sequence, total) {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Stripe {} is out of step with stripe 0 (frame {})", i, sequence
+            )));
+        }
+        bytes.extend_from_slice(&piece[STRIPE_HEADER_SIZE..]);
+    }
+    if bytes.len() != total {
+        return Err(QADataSwapError::SharedMemory(format!(
+            "Reassembled {} of {} bytes for frame {}", bytes.len(), total, sequence
+        )));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_striped_round_trip() -> Result<()> {
+        let config = test_config("striped", 4);
+        let mut writer = StripedWriter::create(config.clone(), 4)?;
+        let reader = StripedReader::attach(config, 4)?;
+
+        let df = df! {
+            "id" => (0..20_000i64).collect::<Vec<_>>(),
+            "px" => (0..20_000).map(|i| i as f64 * 0.5).collect::<Vec<_>>(),
+        }?;
+        writer.write(&df)?;
+        writer.write_event(&Event::Eos)?;
+
+        assert_eq!(reader.read(Some(1000))?, Some(df));
+        assert!(matches!(reader.read_event(Some(1000))?, Some(Event::Eos)));
+        assert!(matches!(reader.read(Some(10)), Err(QADataSwapError::Timeout)));
+        assert!(StripedWriter::create(SharedMemoryConfig::new("striped_none"), 0).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+//! Data-freshness supervision for service health checks
+//!
+//! A reader configured `with_supervision` runs a watcher thread that notices
+//! when neither data nor a heartbeat has been published for the configured
+//! interval and tells a `StallHook`: a callback, or `SdNotify` to feed the
+//! systemd watchdog only while the stream is fresh.
+
+use std::fmt;
+use std::io;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::backend::{HeaderDump, RawArena};
+use crate::Clock;
+
+/// Checks per interval, so a stall is noticed at most a quarter late
+const CHECKS_PER_INTERVAL: u32 = 4;
+
+/// Told about the freshness of a supervised reader's stream
+pub trait StallHook: Send + Sync {
+    /// Frames arrived within the interval; called on every check
+    fn alive(&self, _arena: &str) {}
+
+    /// Nothing arrived for `idle`; called once per stall
+    fn stalled(&self, arena: &str, idle: Duration);
+
+    /// Frames arrive again after a stall
+    fn recovered(&self, _arena: &str) {}
+}
+
+impl<F: Fn(&str, Duration) + Send + Sync> StallHook for F {
+    fn stalled(&self, arena: &str, idle: Duration) {
+        self(arena, idle)
+    }
+}
+
+/// Stall detection settings, see `SharedMemoryConfig::with_supervision`
+#[derive(Clone)]
+pub struct Supervision {
+    pub interval: Duration,
+    pub hook: Arc<dyn StallHook>,
+}
+
+impl Supervision {
+    pub fn new(interval: Duration, hook: impl StallHook + 'static) -> Self {
+        Self {
+            interval,
+            hook: Arc::new(hook),
+        }
+    }
+
+    /// Ping the systemd watchdog from `$NOTIFY_SOCKET` while frames arrive
+    pub fn sd_notify(interval: Duration) -> Self {
+        Self::new(interval, SdNotify::from_env())
+    }
+}
+
+impl fmt::Debug for Supervision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Supervision").field("interval", &self.interval).finish_non_exhaustive()
+    }
+}
+
+/// systemd notify protocol: `WATCHDOG=1` on every fresh check, none while
+/// stalled, so a unit with `WatchdogSec=` is restarted once the stream stalls
+/// for longer than that; `STATUS=` tracks the state. Send failures are ignored.
+#[derive(Debug, Clone)]
+pub struct SdNotify {
+    socket: Option<PathBuf>,
+}
+
+impl SdNotify {
+    /// No-op when the process was not started by systemd
+    pub fn from_env() -> Self {
+        Self {
+            socket: std::env::var_os("NOTIFY_SOCKET").filter(|s| !s.is_empty()).map(PathBuf::from),
+        }
+    }
+
+    pub fn to(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: Some(socket.into()),
+        }
+    }
+
+    pub fn notify(&self, state: &str) -> io::Result<()> {
+        let Some(socket) = &self.socket else {
+            return Ok(());
+        };
+        let address = match socket.to_str().and_then(|s| s.strip_prefix('@')) {
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                SocketAddr::from_abstract_name(name)?
+            },
+            _ => SocketAddr::from_pathname(socket)?,
+        };
+        UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address)?;
+        Ok(())
+    }
+}
+
+impl StallHook for SdNotify {
+    fn alive(&self, arena: &str) {
+        let _ = self.notify(&format!("WATCHDOG=1\nSTATUS={} receiving", arena));
+    }
+
+    fn stalled(&self, arena: &str, idle: Duration) {
+        let _ = self.notify(&format!("STATUS={} stalled: no data or heartbeat for {:?}", arena, idle));
+    }
+
+    fn recovered(&self, arena: &str) {
+        let _ = self.notify(&format!("WATCHDOG=1\nSTATUS={} receiving again", arena));
+    }
+}
+
+/// Watcher thread, stopped on drop
+pub(crate) struct Supervisor {
+    stop: Arc<AtomicBool>,
+    watcher: Option<JoinHandle<()>>,
+}
+
+impl Supervisor {
+    /// Watch the publish sequence of `rings` (the channel and its high lane),
+    /// timing stalls on `time`
+    pub(crate) fn spawn(rings: Vec<Arc<dyn RawArena>>, segment: String, supervision: Supervision,
+                        time: Arc<dyn Clock>) -> io::Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let watcher = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("qads-supervisor".to_string())
+                .spawn(move || watch(&rings, &segment, &supervision, &*time, &stop))?
+        };
+        Ok(Self {
+            stop,
+            watcher: Some(watcher),
+        })
+    }
+}
+
+fn published(rings: &[Arc<dyn RawArena>]) -> u64 {
+    rings
+        .iter()
+        .map(|ring| {
+            let mut header = HeaderDump::default();
+            ring.dump_header(&mut header);
+            header.publish_sequence
+        })
+        .sum()
+}
+
+fn watch(rings: &[Arc<dyn RawArena>], segment: &str, supervision: &Supervision, time: &dyn Clock, stop: &AtomicBool) {
+    let hook = &*supervision.hook;
+    let check_every = (supervision.interval / CHECKS_PER_INTERVAL).max(Duration::from_millis(1));
+    let mut sequence = published(rings);
+    let mut last_frame = time.instant();
+    let mut stalled = false;
+
+    while !stop.load(Ordering::Acquire) {
+        thread::park_timeout(check_every);
+        let now = published(rings);
+        if now != sequence {
+            sequence = now;
+            last_frame = time.instant();
+            if stalled {
+                stalled = false;
+                hook.recovered(segment);
+            }
+            hook.alive(segment);
+        } else if !stalled && time.instant() - last_frame >= supervision.interval {
+            stalled = true;
+            hook.stalled(segment, time.instant() - last_frame);
+        } else if !stalled {
+            hook.alive(segment);
+        }
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(watcher) = self.watcher.take() {
+            watcher.thread().unpark();
+            let _ = watcher.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{Event, MockClock, Result, SharedDataFrame};
+    use polars::df;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    /// What the watcher reported, shared with the test
+    #[derive(Default)]
+    struct Reports {
+        checks: AtomicUsize,
+        stalls: Mutex<Vec<(String, Duration)>>,
+        recoveries: AtomicUsize,
+    }
+
+    struct Recorder(Arc<Reports>);
+
+    impl StallHook for Recorder {
+        fn alive(&self, _arena: &str) {
+            self.0.checks.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn stalled(&self, arena: &str, idle: Duration) {
+            self.0.stalls.lock().unwrap().push((arena.to_string(), idle));
+        }
+
+        fn recovered(&self, _arena: &str) {
+            self.0.recoveries.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Poll until the watcher thread has caught up with `done`
+    fn wait_for(done: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done() {
+            assert!(Instant::now() < deadline, "watcher never caught up");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_stall_reported_once_until_frames_resume() -> Result<()> {
+        let clock = Arc::new(MockClock::default());
+        let reports = Arc::new(Reports::default());
+        let stalls = || reports.stalls.lock().unwrap().clone();
+        let config = test_config("supervised_ticks", 4).with_time_source(clock.clone());
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(
+            config.with_supervision(Supervision::new(Duration::from_millis(80), Recorder(Arc::clone(&reports)))),
+        )?;
+
+        // Heartbeats alone keep the stream fresh
+        for _ in 0..6 {
+            writer.write_event(&Event::Heartbeat)?;
+            reader.read_event(Some(100))?;
+            clock.advance(Duration::from_millis(30));
+        }
+        // The second check from now starts after the last heartbeat was published
+        let checks = reports.checks.load(Ordering::SeqCst);
+        wait_for(|| reports.checks.load(Ordering::SeqCst) >= checks + 2);
+        assert!(stalls().is_empty());
+
+        clock.advance(Duration::from_millis(250));
+        wait_for(|| stalls().len() == 1);
+        // Still the same stall: not reported again
+        clock.advance(Duration::from_millis(250));
+        writer.write(&df! { "px" => [1.0] }?)?;
+        reader.read(Some(100))?;
+        wait_for(|| reports.recoveries.load(Ordering::SeqCst) == 1);
+
+        clock.advance(Duration::from_millis(200));
+        wait_for(|| stalls().len() == 2);
+        let stalls = stalls();
+        assert_eq!(stalls.len(), 2, "{:?}", stalls);
+        assert_eq!(stalls[0].0, "supervised_ticks");
+        assert!(stalls[0].1 >= Duration::from_millis(250));
+        assert!(stalls[1].1 >= Duration::from_millis(200));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sd_notify_feeds_the_watchdog() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("qads_notify_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let systemd = UnixDatagram::bind(&path)?;
+        systemd.set_read_timeout(Some(Duration::from_secs(1)))?;
+
+        let notify = SdNotify::to(&path);
+        notify.alive("ticks");
+        notify.stalled("ticks", Duration::from_secs(3));
+
+        let mut buf = [0u8; 256];
+        let n = systemd.recv(&mut buf)?;
+        assert_eq!(&buf[..n], b"WATCHDOG=1\nSTATUS=ticks receiving");
+        let n = systemd.recv(&mut buf)?;
+        assert_eq!(&buf[..n], b"STATUS=ticks stalled: no data or heartbeat for 3s");
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+//! Live view of a stream for browser dashboards over WebSockets
+
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use polars::prelude::*;
+use tungstenite::{Message, WebSocket};
+
+use crate::{FrameMetadata, QADataSwapError, Result, SharedDataFrame};
+
+/// How often idle tap threads check whether they should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A client that stops reading is dropped rather than stalling the others
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What each WebSocket message carries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TapFormat {
+    /// Text: `{"sequence":..,"timestamp_us":..,"rows":[{column: value, ..}, ..]}`
+    #[default]
+    Json,
+    /// Binary: an Arrow IPC file, readable with `tableFromIPC` in Arrow JS
+    ArrowIpc,
+}
+
+type Clients = Arc<Mutex<Vec<WebSocket<TcpStream>>>>;
+
+/// Serves a downsampled copy of a stream to every connected WebSocket client
+pub struct WebSocketTap {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    clients: Clients,
+    threads: Vec<JoinHandle<Result<()>>>,
+}
+
+impl WebSocketTap {
+    /// Listen on `addr` and push at most `sample_rate` frames per second from
+    /// `reader`; frames arriving faster are skipped, so clients see the latest
+    pub fn serve(reader: SharedDataFrame, addr: impl ToSocketAddrs, sample_rate: f64, format: TapFormat) -> Result<Self> {
+        if !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return Err(QADataSwapError::SharedMemory(format!("Invalid sample rate {}", sample_rate)));
+        }
+        let interval = Duration::from_secs_f64(1.0 / sample_rate);
+
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let clients: Clients = Arc::default();
+        let accept = {
+            let (stop, clients) = (Arc::clone(&stop), Arc::clone(&clients));
+            thread::Builder::new()
+                .name("qads-tap-accept".to_string())
+                .spawn(move || accept_clients(&listener, &clients, &stop))?
+        };
+        let publish = {
+            let (stop, clients) = (Arc::clone(&stop), Arc::clone(&clients));
+            thread::Builder::new()
+                .name("qads-tap".to_string())
+                .spawn(move || publish_frames(&reader, &clients, &stop, interval, format))?
+        };
+
+        Ok(Self {
+            addr,
+            stop,
+            clients,
+            threads: vec![accept, publish],
+        })
+    }
+
+    /// Address the tap listens on (useful after binding port 0)
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Disconnect every client and stop, returning the read error that ended
+    /// the tap early, if any
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::Release);
+        let mut result = Ok(());
+        for thread in self.threads.drain(..) {
+            let outcome = match thread.join() {
+                Ok(outcome) => outcome,
+                Err(_) => Err(QADataSwapError::SharedMemory("WebSocket tap thread panicked".to_string())),
+            };
+            if result.is_ok() {
+                result = outcome;
+            }
+        }
+        for mut client in self.clients.lock().unwrap().drain(..) {
+            let _ = client.close(None);
+            let _ = client.flush();
+        }
+        result
+    }
+}
+
+impl Drop for WebSocketTap {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+fn accept_clients(listener: &TcpListener, clients: &Mutex<Vec<WebSocket<TcpStream>>>, stop: &AtomicBool) -> Result<()> {
+    while !stop.load(Ordering::Acquire) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(CLIENT_WRITE_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT))?;
+        // A client failing its handshake is simply not added
+        if let Ok(client) = tungstenite::accept(stream) {
+            clients.lock().unwrap().push(client);
+        }
+    }
+    Ok(())
+}
+
+fn publish_frames(
+    reader: &SharedDataFrame,
+    clients: &Mutex<Vec<WebSocket<TcpStream>>>,
+    stop: &AtomicBool,
+    interval: Duration,
+    format: TapFormat,
+) -> Result<()> {
+    let time = &reader.arena.config.time_source;
+    let mut last_sent: Option<Instant> = None;
+    while !stop.load(Ordering::Acquire) {
+        let (metadata, df) = match reader.read_with_metadata(Some(POLL_INTERVAL.as_millis() as i32)) {
+            Ok(Some(frame)) => frame,
+            Ok(None) | Err(QADataSwapError::Timeout) => continue,
+            Err(e) => return Err(e),
+        };
+        let now = time.instant();
+        if last_sent.is_some_and(|sent| now.duration_since(sent) < interval) {
+            continue;
+        }
+        last_sent = Some(now);
+
+        let message = encode(&metadata, df, format)?;
+        // Clients that went away are dropped
+        clients.lock().unwrap().retain_mut(|client| client.send(message.clone()).is_ok());
+    }
+    Ok(())
+}
+
+fn encode(metadata: &FrameMetadata, mut df: DataFrame, format: TapFormat) -> Result<Message> {
+    let mut bytes = Vec::new();
+    match format {
+        TapFormat::Json => {
+            JsonWriter::new(&mut bytes).with_json_format(JsonFormat::Json).finish(&mut df)?;
+            let rows = String::from_utf8_lossy(&bytes);
+            Ok(Message::Text(format!(
+                "{{\"sequence\":{},\"timestamp_us\":{},\"rows\":{}}}",
+                metadata.sequence.map_or("null".to_string(), |s| s.to_string()),
+                metadata.timestamp_us,
+                rows
+            )))
+        },
+        TapFormat::ArrowIpc => {
+            // Arrow JS predates the view types polars writes by default
+            IpcWriter::new(&mut bytes).with_compat_level(CompatLevel::oldest()).finish(&mut df)?;
+            Ok(Message::Binary(bytes))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_tap_pushes_json_frames() -> Result<()> {
+        let config = test_config("tap_quotes", 8);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+        let tap = WebSocketTap::serve(reader, "127.0.0.1:0", 1000.0, TapFormat::Json)?;
+
+        let url = format!("ws://{}", tap.local_addr());
+        let (mut client, _) = tungstenite::connect(url.as_str()).map_err(io::Error::other)?;
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while tap.client_count() == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        writer.write(&df! { "symbol" => ["AAPL"], "price" => [1.5] }?)?;
+        let message = client.read().map_err(io::Error::other)?;
+        let text = message.to_text().map_err(io::Error::other)?;
+        assert!(text.contains("\"rows\":[{\"symbol\":\"AAPL\",\"price\":1.5}]"), "{}", text);
+        tap.stop()
+    }
+}
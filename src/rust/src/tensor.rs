@@ -0,0 +1,355 @@
+//! Dense 2-D f32/f64 arrays (feature matrices, model outputs) over a channel
+//!
+//! A tensor frame is a `FrameKind::Tensor` header followed by this payload,
+//! little-endian, with strides counted in elements:
+//!
+//! ```text
+//! 0  dtype u8 (0 = f32, 1 = f64) | 1 ndim u8 (2) | 2..8 reserved
+//! 8  rows u64 | 16 cols u64
+//! 24 row_stride i64 | 32 col_stride i64
+//! 40..64 reserved
+//! 64 data
+//! ```
+//!
+//! numpy maps the data without copying:
+//! `np.ndarray((rows, cols), dtype, buffer, offset=64, strides=(row_stride * itemsize, col_stride * itemsize))`
+
+use std::borrow::Cow;
+use std::mem::{size_of, size_of_val};
+use std::ptr;
+
+use crate::frame::{FrameHeader, FrameKind, FrameMetadata, FRAME_HEADER_SIZE};
+use crate::{QADataSwapError, Result, SharedMemoryArena, SharedMemoryConfig};
+
+/// Bytes between the frame header and the tensor data
+pub const TENSOR_HEADER_SIZE: usize = 64;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Element type of a tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TensorDType {
+    F32,
+    F64,
+}
+
+impl TensorDType {
+    /// Size of one element in bytes
+    pub fn itemsize(self) -> usize {
+        match self {
+            TensorDType::F32 => 4,
+            TensorDType::F64 => 8,
+        }
+    }
+
+    /// numpy dtype string (`np.dtype(...)`)
+    pub fn numpy_str(self) -> &'static str {
+        match self {
+            TensorDType::F32 => "<f4",
+            TensorDType::F64 => "<f8",
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            TensorDType::F32 => 0,
+            TensorDType::F64 => 1,
+        }
+    }
+
+    fn from_u8(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(TensorDType::F32),
+            1 => Ok(TensorDType::F64),
+            other => Err(QADataSwapError::SharedMemory(format!("Unknown tensor dtype {}", other))),
+        }
+    }
+}
+
+/// Element types a `SharedTensor` carries
+pub trait TensorElement: sealed::Sealed + Copy + 'static {
+    const DTYPE: TensorDType;
+}
+
+impl sealed::Sealed for f32 {}
+impl TensorElement for f32 {
+    const DTYPE: TensorDType = TensorDType::F32;
+}
+
+impl sealed::Sealed for f64 {}
+impl TensorElement for f64 {
+    const DTYPE: TensorDType = TensorDType::F64;
+}
+
+/// A 2-D array received from a `SharedTensor`
+#[derive(Debug, Clone)]
+pub struct Tensor {
+    metadata: FrameMetadata,
+    dtype: TensorDType,
+    shape: [usize; 2],
+    strides: [isize; 2],
+    /// The whole frame as read; the data starts at `data_offset`
+    bytes: Vec<u8>,
+    data_offset: usize,
+}
+
+impl Tensor {
+    pub fn metadata(&self) -> &FrameMetadata {
+        &self.metadata
+    }
+
+    pub fn dtype(&self) -> TensorDType {
+        self.dtype
+    }
+
+    /// `[rows, cols]`
+    pub fn shape(&self) -> [usize; 2] {
+        self.shape
+    }
+
+    /// Element strides as written: `[cols, 1]` for row-major, `[1, rows]` for column-major
+    pub fn strides(&self) -> [isize; 2] {
+        self.strides
+    }
+
+    /// The elements in storage order, borrowed when the buffer is suitably aligned
+    pub fn data<T: TensorElement>(&self) -> Result<Cow<'_, [T]>> {
+        self.check_dtype::<T>()?;
+        let len = self.shape[0] * self.shape[1];
+        let bytes = &self.bytes[self.data_offset..self.data_offset + len * size_of::<T>()];
+
+        if (bytes.as_ptr() as usize).is_multiple_of(std::mem::align_of::<T>()) {
+            // Aligned, in bounds, and every bit pattern is a valid float
+            return Ok(Cow::Borrowed(unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const T, len) }));
+        }
+        let mut values: Vec<T> = Vec::with_capacity(len);
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), values.as_mut_ptr() as *mut u8, bytes.len());
+            values.set_len(len);
+        }
+        Ok(Cow::Owned(values))
+    }
+
+    /// Copy out in row-major order
+    pub fn to_vec<T: TensorElement>(&self) -> Result<Vec<T>> {
+        let data = self.data::<T>()?;
+        let [rows, cols] = self.shape;
+        let [row_stride, col_stride] = self.strides;
+        Ok((0..rows)
+            .flat_map(|r| (0..cols).map(move |c| (r as isize * row_stride + c as isize * col_stride) as usize))
+            .map(|i| data[i])
+            .collect())
+    }
+
+    /// View as an ndarray, borrowing the received buffer when it is aligned
+    #[cfg(feature = "ndarray")]
+    pub fn view<T: TensorElement>(&self) -> Result<ndarray::CowArray<'_, T, ndarray::Ix2>> {
+        use ndarray::ShapeBuilder;
+
+        let shape = self.shape.strides([self.strides[0] as usize, self.strides[1] as usize]);
+        let shape_error = |e: ndarray::ShapeError| QADataSwapError::SharedMemory(format!("Bad tensor layout: {}", e));
+        match self.data::<T>()? {
+            Cow::Borrowed(data) => Ok(ndarray::ArrayView2::from_shape(shape, data).map_err(shape_error)?.into()),
+            Cow::Owned(data) => Ok(ndarray::Array2::from_shape_vec(shape, data).map_err(shape_error)?.into()),
+        }
+    }
+
+    fn check_dtype<T: TensorElement>(&self) -> Result<()> {
+        if T::DTYPE != self.dtype {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Tensor holds {:?}, not {:?}", self.dtype, T::DTYPE
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Channel of dense 2-D arrays. Writes copy the elements straight into the
+/// next ring slot behind a shape/stride header, with no intermediate buffer.
+pub struct SharedTensor {
+    arena: SharedMemoryArena,
+}
+
+impl SharedTensor {
+    pub fn create_writer(config: SharedMemoryConfig) -> Result<Self> {
+        let mut arena = SharedMemoryArena::new(config)?;
+        arena.create_writer()?;
+        Ok(Self { arena })
+    }
+
+    pub fn create_reader(config: SharedMemoryConfig) -> Result<Self> {
+        let mut arena = SharedMemoryArena::new(config)?;
+        arena.attach_reader()?;
+        Ok(Self { arena })
+    }
+
+    /// Publish a `rows x cols` array given in row-major order
+    pub fn write<T: TensorElement>(&self, rows: usize, cols: usize, data: &[T]) -> Result<()> {
+        if rows.checked_mul(cols) != Some(data.len()) {
+            return Err(QADataSwapError::SharedMemory(format!(
+                "{} elements do not form a {}x{} tensor", data.len(), rows, cols
+            )));
+        }
+        self.publish::<T>([rows, cols], [cols as isize, 1], |dst| unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr() as *const u8, dst, size_of_val(data));
+        })
+    }
+
+    /// Publish an ndarray; row- and column-major arrays are copied as one block
+    /// with their strides, any other layout element by element in row-major order
+    #[cfg(feature = "ndarray")]
+    pub fn write_array<T: TensorElement>(&self, array: ndarray::ArrayView2<'_, T>) -> Result<()> {
+        let [rows, cols] = [array.nrows(), array.ncols()];
+        let bytes = rows * cols * size_of::<T>();
+        let strides = if array.is_standard_layout() {
+            Some([cols as isize, 1])
+        } else if array.t().is_standard_layout() {
+            Some([1, rows as isize])
+        } else {
+            None
+        };
+
+        match (strides, array.as_slice_memory_order()) {
+            (Some(strides), Some(data)) => self.publish::<T>([rows, cols], strides, |dst| unsafe {
+                ptr::copy_nonoverlapping(data.as_ptr() as *const u8, dst, bytes);
+            }),
+            _ => self.publish::<T>([rows, cols], [cols as isize, 1], |dst| {
+                for (i, value) in array.iter().enumerate() {
+                    unsafe { ptr::write_unaligned((dst as *mut T).add(i), *value) };
+                }
+            }),
+        }
+    }
+
+    /// Reserve a slot, write the headers and let `fill` copy the elements in
+    fn publish<T: TensorElement>(&self, shape: [usize; 2], strides: [isize; 2], fill: impl FnOnce(*mut u8)) -> Result<()> {
+        let data_len = shape[0] * shape[1] * size_of::<T>();
+        let payload_len = TENSOR_HEADER_SIZE + data_len;
+
+        let (slot, capacity) = self.arena.acquire_write_buffer()?;
+        if FRAME_HEADER_SIZE + payload_len > capacity {
+            self.arena.release_acquired();
+            return Err(QADataSwapError::SharedMemory(format!(
+                "Tensor of {} bytes does not fit in a {} byte slot", payload_len, capacity
+            )));
+        }
+
+        let mut tensor_header = [0u8; TENSOR_HEADER_SIZE];
+        tensor_header[0] = T::DTYPE.as_u8();
+        tensor_header[1] = 2;
+        tensor_header[8..16].copy_from_slice(&(shape[0] as u64).to_le_bytes());
+        tensor_header[16..24].copy_from_slice(&(shape[1] as u64).to_le_bytes());
+        tensor_header[24..32].copy_from_slice(&(strides[0] as i64).to_le_bytes());
+        tensor_header[32..40].copy_from_slice(&(strides[1] as i64).to_le_bytes());
+        let header = FrameHeader::new_with_clock(FrameKind::Tensor, self.arena.writer_id(), payload_len, self.arena.config.clock)
+            .stamped_by(&*self.arena.config.time_source);
+
+        unsafe {
+            ptr::copy_nonoverlapping(header.to_bytes().as_ptr(), slot, FRAME_HEADER_SIZE);
+            ptr::copy_nonoverlapping(tensor_header.as_ptr(), slot.add(FRAME_HEADER_SIZE), TENSOR_HEADER_SIZE);
+            fill(slot.add(FRAME_HEADER_SIZE + TENSOR_HEADER_SIZE));
+        }
+        self.arena.publish_acquired(FRAME_HEADER_SIZE + payload_len)
+    }
+
+    /// Next tensor, skipping control frames
+    pub fn read(&self, timeout_ms: Option<i32>) -> Result<Option<Tensor>> {
+        let timeout = timeout_ms.unwrap_or(self.arena.config.timeout_ms.unwrap_or(-1));
+        loop {
+            let Some((source, bytes)) = self.arena.next_bytes(timeout)? else {
+                return Ok(None);
+            };
+            let header = match FrameHeader::decode(&bytes)? {
+                Some((header, _)) => header,
+                None => return Err(QADataSwapError::SharedMemory("Not a tensor frame".to_string())),
+            };
+            match header.kind {
+                FrameKind::Tensor => {},
+                FrameKind::Eos | FrameKind::Heartbeat | FrameKind::Flush | FrameKind::SchemaChange => continue,
+                kind => return Err(QADataSwapError::SharedMemory(format!("Expected a tensor frame, got {:?}", kind))),
+            }
+            let metadata = FrameMetadata::from_header(&header, source.last_read_sequence());
+            return decode_tensor(metadata, bytes).map(Some);
+        }
+    }
+
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.arena.last_read_sequence()
+    }
+
+    pub fn has_data(&self) -> bool {
+        self.arena.has_data()
+    }
+
+    pub fn close(&self) {
+        self.arena.close()
+    }
+}
+
+fn decode_tensor(metadata: FrameMetadata, bytes: Vec<u8>) -> Result<Tensor> {
+    let corrupt = || QADataSwapError::SharedMemory("Corrupt tensor frame".to_string());
+
+    let header = bytes.get(FRAME_HEADER_SIZE..FRAME_HEADER_SIZE + TENSOR_HEADER_SIZE).ok_or_else(corrupt)?;
+    if header[1] != 2 {
+        return Err(QADataSwapError::SharedMemory(format!("Unsupported tensor rank {}", header[1])));
+    }
+    let dtype = TensorDType::from_u8(header[0])?;
+    let field = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+    let shape = [field(8) as usize, field(16) as usize];
+    let strides = [field(24) as i64 as isize, field(32) as i64 as isize];
+
+    // The furthest element must lie inside the frame
+    let len = shape[0].checked_mul(shape[1]).ok_or_else(corrupt)?;
+    let data_offset = FRAME_HEADER_SIZE + TENSOR_HEADER_SIZE;
+    let available = (bytes.len() - data_offset) / dtype.itemsize();
+    if len > available || strides.iter().any(|s| *s < 0) {
+        return Err(corrupt());
+    }
+    if len > 0 {
+        let last = (shape[0] - 1) as isize * strides[0] + (shape[1] - 1) as isize * strides[1];
+        if last as usize >= len {
+            return Err(corrupt());
+        }
+    }
+
+    Ok(Tensor {
+        metadata,
+        dtype,
+        shape,
+        strides,
+        bytes,
+        data_offset,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_tensor_round_trip() -> Result<()> {
+        let writer = SharedTensor::create_writer(test_config("tensor_features", 4))?;
+        let reader = SharedTensor::create_reader(test_config("tensor_features", 4))?;
+
+        writer.write(2, 3, &[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])?;
+        let tensor = reader.read(Some(100))?.unwrap();
+        assert_eq!(tensor.dtype(), TensorDType::F32);
+        assert_eq!(tensor.shape(), [2, 3]);
+        assert_eq!(tensor.to_vec::<f32>()?, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert!(tensor.to_vec::<f64>().is_err());
+        assert!(writer.write(2, 2, &[1.0f64]).is_err());
+
+        #[cfg(feature = "ndarray")]
+        {
+            let array = ndarray::array![[1.0f64, 2.0], [3.0, 4.0], [5.0, 6.0]];
+            writer.write_array(array.t())?;
+            let tensor = reader.read(Some(100))?.unwrap();
+            assert_eq!(tensor.strides(), [1, 2]);
+            assert_eq!(tensor.view::<f64>()?, array.t());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,452 @@
+//! Fault injection for testing application recovery logic against the transport
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::auth::Challenge;
+use crate::backend::{
+    GroupDump, HeaderDump, RawArena, ReaderDump, SlotDump, WakeWord, STATUS_ERROR, STATUS_FAULT, STATUS_OK,
+    STATUS_TIMEOUT,
+};
+use crate::frame::{FrameHeader, FRAME_HEADER_SIZE};
+use crate::{Advice, MemoryProtection, MemoryReport, OpenMode, SeekFrom};
+
+/// Bytes overwritten at the end of a corrupted frame's payload
+const CORRUPT_BYTES: usize = 8;
+
+/// A fault the injector has fired, as recorded in `FaultInjector::history()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// The writer stopped halfway through copying frame `n` and detached like a
+    /// crashed process: the frame is never published and the segment is left behind
+    WriterCrash(u64),
+    /// A read was delayed before reaching the transport
+    SlowRead(Duration),
+    /// Received frame `n` had the end of its payload overwritten
+    Corruption(u64),
+    /// A read timed out without consuming a frame
+    Timeout,
+    /// A guarded read of frame `n` hit a memory fault, see `MemoryProtection::Guarded`
+    MemoryFault(u64),
+}
+
+#[derive(Debug, Default)]
+struct Schedule {
+    crash_at: Option<u64>,
+    read_delay: Option<Duration>,
+    corrupt: Vec<u64>,
+    memory_faults: Vec<u64>,
+    timeouts: u64,
+    frames_written: u64,
+    frames_read: u64,
+    crashed: bool,
+    history: Vec<Fault>,
+}
+
+impl Schedule {
+    /// Use up one scheduled read timeout, if any are left
+    fn time_out(&mut self) -> bool {
+        if self.timeouts == 0 {
+            return false;
+        }
+        self.timeouts -= 1;
+        self.history.push(Fault::Timeout);
+        true
+    }
+}
+
+/// Deterministic fault schedule, attached to an arena with
+/// `SharedMemoryConfig::with_fault_injector`. Faults are keyed on frame counts,
+/// so a test triggers them at exactly the same point on every run.
+///
+/// Clones share the schedule: give the writer and each reader their own
+/// injector, or share one to inspect `history()` from the test.
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    schedule: Arc<Mutex<Schedule>>,
+}
+
+impl FaultInjector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Crash the writer midway through its `frame`-th write (0-based)
+    pub fn crash_writer_at(self, frame: u64) -> Self {
+        self.schedule.lock().unwrap().crash_at = Some(frame);
+        self
+    }
+
+    /// Delay every read by `delay`, simulating a reader that falls behind
+    pub fn slow_reader(self, delay: Duration) -> Self {
+        self.schedule.lock().unwrap().read_delay = Some(delay);
+        self
+    }
+
+    /// Corrupt the `frame`-th frame a reader receives (0-based), so decoding it fails
+    pub fn corrupt_frame(self, frame: u64) -> Self {
+        self.schedule.lock().unwrap().corrupt.push(frame);
+        self
+    }
+
+    /// Fault while copying out the `frame`-th frame a reader receives (0-based),
+    /// the way a guarded read reports a bad mapping: the frame is consumed
+    pub fn memory_fault_at(self, frame: u64) -> Self {
+        self.schedule.lock().unwrap().memory_faults.push(frame);
+        self
+    }
+
+    /// Make the next `count` reads time out without consuming anything
+    pub fn timeout_reads(self, count: u64) -> Self {
+        self.schedule.lock().unwrap().timeouts += count;
+        self
+    }
+
+    /// Whether the scheduled writer crash has happened
+    pub fn crashed(&self) -> bool {
+        self.schedule.lock().unwrap().crashed
+    }
+
+    /// Every fault fired so far, in order
+    pub fn history(&self) -> Vec<Fault> {
+        self.schedule.lock().unwrap().history.clone()
+    }
+
+    pub(crate) fn wrap(&self, inner: Arc<dyn RawArena>) -> Arc<dyn RawArena> {
+        Arc::new(FaultyArena {
+            inner,
+            injector: self.clone(),
+        })
+    }
+
+    /// Whether the write about to happen is the one that crashes
+    fn crash_now(&self) -> bool {
+        let mut schedule = self.schedule.lock().unwrap();
+        if schedule.crash_at != Some(schedule.frames_written) {
+            schedule.frames_written += 1;
+            return false;
+        }
+
+        schedule.crash_at = None;
+        schedule.crashed = true;
+        let frame = schedule.frames_written;
+        schedule.history.push(Fault::WriterCrash(frame));
+        true
+    }
+}
+
+/// Backend decorator that applies a `FaultInjector` schedule
+struct FaultyArena {
+    inner: Arc<dyn RawArena>,
+    injector: FaultInjector,
+}
+
+impl FaultyArena {
+    fn crash(&self, slot: *mut u8, data: &[u8]) -> i32 {
+        // Half the frame lands in the slot before the writer goes away
+        let torn = data.len() / 2;
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), slot, torn) };
+        self.inner.abandon();
+        STATUS_ERROR
+    }
+}
+
+impl RawArena for FaultyArena {
+    fn create_writer(&self, mode: OpenMode) -> i32 {
+        self.inner.create_writer(mode)
+    }
+
+    fn attach_reader(&self) -> i32 {
+        self.inner.attach_reader()
+    }
+
+    fn write(&self, data: &[u8]) -> i32 {
+        if !self.injector.crash_now() {
+            return self.inner.write(data);
+        }
+
+        match self.inner.acquire_write_buffer() {
+            Some((slot, capacity)) if data.len() <= capacity => self.crash(slot, data),
+            Some(_) => {
+                self.inner.abandon();
+                STATUS_ERROR
+            },
+            None => STATUS_ERROR,
+        }
+    }
+
+    fn read(&self, buffer: &mut [u8], actual_size: &mut usize, timeout_ms: i32) -> i32 {
+        let delay = {
+            let mut schedule = self.injector.schedule.lock().unwrap();
+            if schedule.time_out() {
+                return STATUS_TIMEOUT;
+            }
+            if let Some(delay) = schedule.read_delay {
+                schedule.history.push(Fault::SlowRead(delay));
+            }
+            schedule.read_delay
+        };
+        if let Some(delay) = delay {
+            std::thread::sleep(delay);
+        }
+
+        let result = self.inner.read(buffer, actual_size, timeout_ms);
+        if result != STATUS_OK {
+            return result;
+        }
+
+        let mut schedule = self.injector.schedule.lock().unwrap();
+        let frame = schedule.frames_read;
+        schedule.frames_read += 1;
+        if let Some(pos) = schedule.memory_faults.iter().position(|&f| f == frame) {
+            schedule.memory_faults.swap_remove(pos);
+            schedule.history.push(Fault::MemoryFault(frame));
+            return STATUS_FAULT;
+        }
+        if let Some(pos) = schedule.corrupt.iter().position(|&f| f == frame) {
+            schedule.corrupt.swap_remove(pos);
+            schedule.history.push(Fault::Corruption(frame));
+
+            // The tail holds the IPC footer length and magic, so decoding is bound to fail
+            let bytes = &mut buffer[..*actual_size];
+            let payload_start = match FrameHeader::decode(bytes) {
+                Ok(Some(_)) => FRAME_HEADER_SIZE,
+                _ => 0,
+            };
+            let start = bytes.len().saturating_sub(CORRUPT_BYTES).max(payload_start);
+            for byte in &mut bytes[start..] {
+                *byte ^= 0xFF;
+            }
+        }
+        STATUS_OK
+    }
+
+    fn peek_size(&self, size: &mut usize, timeout_ms: i32) -> i32 {
+        // Readers peek before every read, so scheduled timeouts fire here first
+        if self.injector.schedule.lock().unwrap().time_out() {
+            return STATUS_TIMEOUT;
+        }
+        self.inner.peek_size(size, timeout_ms)
+    }
+
+    fn last_read_sequence(&self) -> Option<u64> {
+        self.inner.last_read_sequence()
+    }
+
+    fn has_data(&self) -> bool {
+        self.inner.has_data()
+    }
+
+    fn acquire_write_buffer(&self) -> Option<(*mut u8, usize)> {
+        self.inner.acquire_write_buffer()
+    }
+
+    fn publish_acquired(&self, size: usize) -> i32 {
+        // An in-place frame is fully written by now; crashing leaves it unpublished
+        if self.injector.crash_now() {
+            self.inner.abandon();
+            return STATUS_ERROR;
+        }
+        self.inner.publish_acquired(size)
+    }
+
+    fn release_acquired(&self) {
+        self.inner.release_acquired()
+    }
+
+    fn reserve_slots(&self, max_buffer_count: usize) {
+        self.inner.reserve_slots(max_buffer_count)
+    }
+
+    fn reslot(&self, buffer_count: usize) -> i32 {
+        self.inner.reslot(buffer_count)
+    }
+
+    fn begin_batch(&self) -> i32 {
+        self.inner.begin_batch()
+    }
+
+    fn commit_batch(&self) -> i32 {
+        self.inner.commit_batch()
+    }
+
+    fn abort_batch(&self) {
+        self.inner.abort_batch()
+    }
+
+    fn memory_report(&self, report: &mut MemoryReport) -> i32 {
+        self.inner.memory_report(report)
+    }
+
+    fn dump_header(&self, dump: &mut HeaderDump) -> i32 {
+        self.inner.dump_header(dump)
+    }
+
+    fn dump_slot(&self, index: usize, dump: &mut SlotDump) -> i32 {
+        self.inner.dump_slot(index, dump)
+    }
+
+    fn dump_group(&self, index: usize, dump: &mut GroupDump) -> i32 {
+        self.inner.dump_group(index, dump)
+    }
+
+    fn seek(&self, pos: SeekFrom) -> i32 {
+        self.inner.seek(pos)
+    }
+
+    fn retained_range(&self) -> (u64, u64) {
+        self.inner.retained_range()
+    }
+
+    fn seek_time(&self, timestamp_us: u64) -> i32 {
+        self.inner.seek_time(timestamp_us)
+    }
+
+    fn set_retention(&self, retention_us: u64) {
+        self.inner.set_retention(retention_us)
+    }
+
+    fn enable_consumer_groups(&self) {
+        self.inner.enable_consumer_groups()
+    }
+
+    fn join_group(&self, group: &str) -> i32 {
+        self.inner.join_group(group)
+    }
+
+    fn set_max_readers(&self, max_readers: usize) {
+        self.inner.set_max_readers(max_readers)
+    }
+
+    fn set_protection(&self, protection: MemoryProtection) {
+        self.inner.set_protection(protection)
+    }
+
+    fn set_slot_guards(&self, guard_pages: bool, canaries: bool) {
+        self.inner.set_slot_guards(guard_pages, canaries)
+    }
+
+    fn set_advice(&self, advice: &[Advice]) {
+        self.inner.set_advice(advice)
+    }
+
+    fn set_backing_path(&self, path: &Path) -> i32 {
+        self.inner.set_backing_path(path)
+    }
+
+    fn dump_reader(&self, index: usize, dump: &mut ReaderDump) -> i32 {
+        self.inner.dump_reader(index, dump)
+    }
+
+    fn set_auth(&self, challenge: Option<&Challenge>) {
+        self.inner.set_auth(challenge)
+    }
+
+    fn auth(&self) -> Option<Challenge> {
+        self.inner.auth()
+    }
+
+    fn put_meta(&self, key: &str, value: Option<&str>) -> i32 {
+        self.inner.put_meta(key, value)
+    }
+
+    fn get_meta(&self, key: &str) -> Option<String> {
+        self.inner.get_meta(key)
+    }
+
+    fn meta_keys(&self) -> Vec<String> {
+        self.inner.meta_keys()
+    }
+
+    fn set_ack_mode(&self, enabled: bool) {
+        self.inner.set_ack_mode(enabled)
+    }
+
+    fn ack(&self, sequence: u64) -> i32 {
+        self.inner.ack(sequence)
+    }
+
+    fn unacked(&self) -> u64 {
+        self.inner.unacked()
+    }
+
+    fn wait_for_data(&self, timeout_ms: i32) -> i32 {
+        self.inner.wait_for_data(timeout_ms)
+    }
+
+    fn wake_word(&self) -> Option<WakeWord> {
+        self.inner.wake_word()
+    }
+
+    fn notify_data_ready(&self) {
+        self.inner.notify_data_ready()
+    }
+
+    fn close(&self) {
+        self.inner.close()
+    }
+
+    fn abandon(&self) {
+        self.inner.abandon()
+    }
+}
+
+/// A 1 MB in-process channel with `buffers` slots, for unit tests
+#[cfg(test)]
+pub(crate) fn test_config(name: &str, buffers: usize) -> crate::SharedMemoryConfig {
+    crate::SharedMemoryConfig::new(name)
+        .with_size_mb(1)
+        .with_buffer_count(buffers)
+        .with_transport(crate::Transport::InProcess)
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::*;
+
+    use super::*;
+    use crate::{QADataSwapError, SharedDataFrame};
+
+    #[test]
+    fn test_writer_crash_and_restart() -> crate::Result<()> {
+        let injector = FaultInjector::new().crash_writer_at(1);
+        let writer = SharedDataFrame::create_writer(test_config("fault_crash", 3).with_fault_injector(injector.clone()))?;
+        let reader = SharedDataFrame::create_reader(test_config("fault_crash", 3))?;
+
+        let df = df! { "id" => [1i64, 2, 3] }?;
+        writer.write(&df)?;
+        assert!(writer.write(&df).is_err());
+        assert_eq!(injector.history(), vec![Fault::WriterCrash(1)]);
+
+        // Only the frame published before the crash is visible
+        assert!(reader.read(Some(100))?.is_some());
+        assert!(matches!(reader.read(Some(10)), Err(QADataSwapError::Timeout)));
+
+        let reopen = test_config("fault_crash", 3).with_open_mode(OpenMode::OpenOrAttach);
+        let restarted = SharedDataFrame::create_writer(reopen)?;
+        restarted.write(&df)?;
+        assert!(reader.read(Some(100))?.unwrap().equals(&df));
+        Ok(())
+    }
+
+    #[test]
+    fn test_corruption_and_timeout() -> crate::Result<()> {
+        let injector = FaultInjector::new().timeout_reads(1).corrupt_frame(0).memory_fault_at(1);
+        let writer = SharedDataFrame::create_writer(test_config("fault_corrupt", 3))?;
+        let faulty = test_config("fault_corrupt", 3).with_fault_injector(injector.clone());
+        let reader = SharedDataFrame::create_reader(faulty)?;
+
+        let df = df! { "id" => [1i64, 2, 3] }?;
+        for _ in 0..3 {
+            writer.write(&df)?;
+        }
+
+        assert!(matches!(reader.read(Some(100)), Err(QADataSwapError::Timeout)));
+        assert!(reader.read(Some(100)).is_err());
+        assert!(matches!(reader.read(Some(100)), Err(QADataSwapError::MemoryFault(_))));
+        assert!(reader.read(Some(100))?.unwrap().equals(&df));
+        assert_eq!(injector.history(), vec![Fault::Timeout, Fault::Corruption(0), Fault::MemoryFault(1)]);
+        Ok(())
+    }
+}
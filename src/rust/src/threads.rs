@@ -0,0 +1,257 @@
+//! Background reader threads: prefetching and callbacks, with CPU pinning and
+//! realtime scheduling for consistent latencies
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use polars::prelude::*;
+
+use crate::{QADataSwapError, Result, SharedDataFrame};
+
+/// How often an idle reader thread checks whether it should stop
+pub(crate) const POLL_TIMEOUT_MS: i32 = 50;
+
+/// Scheduling for the threads started by `spawn_prefetcher()` and `on_data()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReaderThreadOptions {
+    /// Restrict the thread to this CPU
+    pub pin_to_core: Option<usize>,
+    /// Run under SCHED_FIFO at this priority (1-99; usually needs CAP_SYS_NICE)
+    pub sched_fifo_priority: Option<i32>,
+}
+
+impl ReaderThreadOptions {
+    pub fn with_pin_to_core(mut self, core: usize) -> Self {
+        self.pin_to_core = Some(core);
+        self
+    }
+
+    pub fn with_sched_fifo_priority(mut self, priority: i32) -> Self {
+        self.sched_fifo_priority = Some(priority);
+        self
+    }
+
+    /// Apply to the calling thread
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self) -> io::Result<()> {
+        if let Some(core) = self.pin_to_core {
+            if core >= libc::CPU_SETSIZE as usize {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("No CPU {}", core)));
+            }
+            unsafe {
+                let mut set: libc::cpu_set_t = std::mem::zeroed();
+                libc::CPU_SET(core, &mut set);
+                if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+
+        if let Some(priority) = self.sched_fifo_priority {
+            let param = libc::sched_param { sched_priority: priority };
+            let rc = unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) };
+            if rc != 0 {
+                return Err(io::Error::from_raw_os_error(rc));
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self) -> io::Result<()> {
+        match self == &Self::default() {
+            true => Ok(()),
+            false => Err(io::Error::new(io::ErrorKind::Unsupported, "CPU pinning and SCHED_FIFO need Linux")),
+        }
+    }
+}
+
+/// Start `body` on a named thread once `options` are applied; a failure to apply
+/// them is returned here instead of starting the thread
+pub(crate) fn spawn_reader<F>(name: &str, options: ReaderThreadOptions, body: F) -> Result<JoinHandle<Result<()>>>
+where
+    F: FnOnce() -> Result<()> + Send + 'static,
+{
+    let (started_tx, started_rx) = mpsc::sync_channel(1);
+    let handle = thread::Builder::new().name(name.to_string()).spawn(move || {
+        let applied = options.apply();
+        let ok = applied.is_ok();
+        let _ = started_tx.send(applied);
+        if !ok {
+            return Ok(());
+        }
+        body()
+    })?;
+
+    match started_rx.recv() {
+        Ok(Ok(())) => Ok(handle),
+        Ok(Err(e)) => {
+            let _ = handle.join();
+            Err(QADataSwapError::Io(e))
+        },
+        Err(_) => Err(QADataSwapError::SharedMemory(format!("Thread '{}' exited during startup", name))),
+    }
+}
+
+/// Frames read ahead on a background thread, see `SharedDataFrame::spawn_prefetcher`
+pub struct Prefetcher {
+    frames: Option<Receiver<Result<DataFrame>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl Prefetcher {
+    pub(crate) fn spawn(reader: SharedDataFrame, depth: usize, options: ReaderThreadOptions) -> Result<Self> {
+        let (tx, rx) = mpsc::sync_channel(depth.max(1));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            spawn_reader("qads-prefetch", options, move || prefetch(&reader, &tx, &stop))?
+        };
+
+        Ok(Self {
+            frames: Some(rx),
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Next prefetched frame; `None` waits forever
+    pub fn recv(&self, timeout_ms: Option<i32>) -> Result<Option<DataFrame>> {
+        let frames = self.frames.as_ref().ok_or(QADataSwapError::NotConnected)?;
+        let received = match timeout_ms {
+            Some(ms) if ms >= 0 => frames.recv_timeout(Duration::from_millis(ms as u64)),
+            _ => frames.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            Ok(frame) => frame.map(Some),
+            Err(RecvTimeoutError::Timeout) => Err(QADataSwapError::Timeout),
+            Err(RecvTimeoutError::Disconnected) => Err(QADataSwapError::NotConnected),
+        }
+    }
+}
+
+fn prefetch(reader: &SharedDataFrame, frames: &SyncSender<Result<DataFrame>>, stop: &AtomicBool) -> Result<()> {
+    while !stop.load(Ordering::Acquire) {
+        let frame = match reader.read(Some(POLL_TIMEOUT_MS)) {
+            Ok(Some(df)) => Ok(df),
+            Ok(None) | Err(QADataSwapError::Timeout) => continue,
+            Err(e) => Err(e),
+        };
+        // The receiver is gone once the Prefetcher is dropped
+        if frames.send(frame).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+impl Drop for Prefetcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        drop(self.frames.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Callback thread started by `SharedDataFrame::on_data`; dropping it stops the thread
+pub struct Subscription {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl Subscription {
+    pub(crate) fn spawn<F>(reader: SharedDataFrame, options: ReaderThreadOptions, mut callback: F) -> Result<Self>
+    where
+        F: FnMut(DataFrame) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            spawn_reader("qads-on-data", options, move || {
+                while !stop.load(Ordering::Acquire) {
+                    match reader.read(Some(POLL_TIMEOUT_MS)) {
+                        Ok(Some(df)) => callback(df),
+                        Ok(None) | Err(QADataSwapError::Timeout) => {},
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(())
+            })?
+        };
+
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Stop the thread, returning the read error that ended it early, if any
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::Release);
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(QADataSwapError::SharedMemory("on_data callback panicked".to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_prefetcher_and_on_data() -> Result<()> {
+        let df = df! { "id" => [1i64, 2, 3] }?;
+        let pinned = ReaderThreadOptions::default().with_pin_to_core(0);
+
+        let writer = SharedDataFrame::create_writer(test_config("threads_prefetch", 4))?;
+        let prefetcher = SharedDataFrame::create_reader(test_config("threads_prefetch", 4))?.spawn_prefetcher(2, pinned)?;
+        writer.write(&df)?;
+        assert!(prefetcher.recv(Some(1000))?.unwrap().equals(&df));
+        assert!(matches!(prefetcher.recv(Some(10)), Err(QADataSwapError::Timeout)));
+
+        let writer = SharedDataFrame::create_writer(test_config("threads_on_data", 4))?;
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let subscription = {
+            let seen = Arc::clone(&seen);
+            SharedDataFrame::create_reader(test_config("threads_on_data", 4))?
+                .on_data(pinned, move |df| seen.lock().unwrap().push(df.height()))?
+        };
+        writer.write(&df)?;
+        writer.write(&df)?;
+        let deadline = std::time::Instant::now() + Duration::from_secs(1);
+        while seen.lock().unwrap().len() < 2 && std::time::Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        subscription.stop()?;
+        assert_eq!(*seen.lock().unwrap(), vec![3, 3]);
+
+        let bad_core = ReaderThreadOptions::default().with_pin_to_core(usize::MAX);
+        let reader = SharedDataFrame::create_reader(test_config("threads_on_data", 4))?;
+        assert!(matches!(reader.spawn_prefetcher(1, bad_core), Err(QADataSwapError::Io(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,329 @@
+//! Per-frame data quality checks attached to a channel
+//!
+//! ```ignore
+//! let validators = Validators::new(ValidationPolicy::Reject)
+//!     .with_check(Check::monotonic("timestamp_us"))
+//!     .with_check(Check::range("price", Some(0.0), None))
+//!     .with_check(Check::one_of("side", ["B", "S"]));
+//! let config = SharedMemoryConfig::new("ticks").with_validators(validators);
+//! ```
+//!
+//! On a writer, a rejected frame fails `write()` and is not published; on a
+//! reader it is skipped. Either way it goes to the dead-letter writer, if set.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Mutex;
+
+use polars::prelude::*;
+
+use crate::{QADataSwapError, Result, SharedDataFrame};
+
+/// Boolean column added under `ValidationPolicy::Tag`, true for failing rows
+pub const VALIDATION_TAG_COLUMN: &str = "__invalid";
+
+/// One rule evaluated against every row of a frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum Check {
+    /// Values never decrease, across frames too; nulls are ignored
+    Monotonic { column: String, strict: bool },
+    /// Values within `[min, max]`; nulls are ignored
+    Range { column: String, min: Option<f64>, max: Option<f64> },
+    /// Values, as strings, drawn from a fixed set; nulls are ignored
+    OneOf { column: String, values: Vec<String> },
+}
+
+impl Check {
+    pub fn monotonic(column: impl Into<String>) -> Self {
+        Check::Monotonic { column: column.into(), strict: false }
+    }
+
+    pub fn strictly_increasing(column: impl Into<String>) -> Self {
+        Check::Monotonic { column: column.into(), strict: true }
+    }
+
+    pub fn range(column: impl Into<String>, min: Option<f64>, max: Option<f64>) -> Self {
+        Check::Range { column: column.into(), min, max }
+    }
+
+    pub fn one_of<S: Into<String>>(column: impl Into<String>, values: impl IntoIterator<Item = S>) -> Self {
+        Check::OneOf { column: column.into(), values: values.into_iter().map(Into::into).collect() }
+    }
+
+    fn column(&self) -> &str {
+        match self {
+            Check::Monotonic { column, .. } | Check::Range { column, .. } | Check::OneOf { column, .. } => column,
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Monotonic { column, strict: false } => write!(f, "'{}' is non-decreasing", column),
+            Check::Monotonic { column, strict: true } => write!(f, "'{}' is strictly increasing", column),
+            Check::Range { column, min, max } => {
+                let bound = |b: &Option<f64>| b.map_or("..".to_string(), |b| b.to_string());
+                write!(f, "'{}' is within [{}, {}]", column, bound(min), bound(max))
+            },
+            Check::OneOf { column, values } => write!(f, "'{}' is one of {:?}", column, values),
+        }
+    }
+}
+
+/// What happens to a frame with failing rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationPolicy {
+    /// Drop the whole frame
+    #[default]
+    Reject,
+    /// Deliver it unchanged; only the stats record the failure
+    Warn,
+    /// Deliver it with a `VALIDATION_TAG_COLUMN` marking failing rows
+    Tag,
+}
+
+/// Counters returned by `validation_stats()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationStats {
+    pub frames_checked: u64,
+    /// Frames with at least one failing row
+    pub frames_failed: u64,
+    pub rows_failed: u64,
+    /// Frames dropped under `ValidationPolicy::Reject`
+    pub frames_rejected: u64,
+    pub last_failure: Option<String>,
+}
+
+/// Outcome of validating one frame
+pub(crate) enum Verdict {
+    Pass(DataFrame),
+    Reject(String),
+}
+
+/// Last value seen per monotonic column, carried across frames
+#[derive(Debug, Clone, Copy)]
+enum Last {
+    Int(i64),
+    Float(f64),
+}
+
+/// A set of checks with one policy, see the module docs
+pub struct Validators {
+    checks: Vec<Check>,
+    policy: Mutex<ValidationPolicy>,
+    dead_letter: Option<SharedDataFrame>,
+    last: Mutex<HashMap<String, Last>>,
+    stats: Mutex<ValidationStats>,
+}
+
+impl fmt::Debug for Validators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Validators")
+            .field("checks", &self.checks)
+            .field("policy", &self.policy())
+            .field("dead_letter", &self.dead_letter.is_some())
+            .finish()
+    }
+}
+
+impl Validators {
+    pub fn new(policy: ValidationPolicy) -> Self {
+        Self {
+            checks: Vec::new(),
+            policy: Mutex::new(policy),
+            dead_letter: None,
+            last: Mutex::new(HashMap::new()),
+            stats: Mutex::new(ValidationStats::default()),
+        }
+    }
+
+    pub fn with_check(mut self, check: Check) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    /// Write rejected frames here for later inspection
+    pub fn with_dead_letter(mut self, writer: SharedDataFrame) -> Self {
+        self.dead_letter = Some(writer);
+        self
+    }
+
+    pub fn policy(&self) -> ValidationPolicy {
+        *self.policy.lock().unwrap()
+    }
+
+    /// Takes effect from the next frame, see `update_policy`
+    pub fn set_policy(&self, policy: ValidationPolicy) {
+        *self.policy.lock().unwrap() = policy;
+    }
+
+    pub fn stats(&self) -> ValidationStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Run every check over `df` and apply the policy
+    pub(crate) fn validate(&self, df: DataFrame) -> Result<Verdict> {
+        let policy = self.policy();
+        let mut last = self.last.lock().unwrap();
+        let mut next_last = HashMap::new();
+        let mut failing = vec![false; df.height()];
+        let mut failures = Vec::new();
+
+        for check in &self.checks {
+            let column = df.column(check.column()).map_err(|_| {
+                QADataSwapError::SharedMemory(format!("Validator column '{}' is missing", check.column()))
+            })?;
+            let rows = match check {
+                Check::Monotonic { column: name, strict } => {
+                    let (rows, tail) = monotonic(column, last.get(name).copied(), *strict)?;
+                    if let Some(tail) = tail {
+                        next_last.insert(name.clone(), tail);
+                    }
+                    rows
+                },
+                Check::Range { min, max, .. } => {
+                    let values = column.cast(&DataType::Float64)?;
+                    values
+                        .f64()?
+                        .into_iter()
+                        .map(|v| v.is_some_and(|v| min.is_some_and(|min| v < min) || max.is_some_and(|max| v > max)))
+                        .collect()
+                },
+                Check::OneOf { values, .. } => {
+                    let allowed: HashSet<&str> = values.iter().map(String::as_str).collect();
+                    let strings = column.cast(&DataType::String)?;
+                    strings.str()?.into_iter().map(|v| v.is_some_and(|v| !allowed.contains(v))).collect()
+                },
+            };
+
+            let count = rows.iter().filter(|failed| **failed).count();
+            if count > 0 {
+                let first = rows.iter().position(|failed| *failed).unwrap_or_default();
+                failures.push(format!("{} failed on {} row(s), first at row {}", check, count, first));
+            }
+            for (failing, failed) in failing.iter_mut().zip(rows) {
+                *failing |= failed;
+            }
+        }
+
+        let rows_failed = failing.iter().filter(|failed| **failed).count() as u64;
+        let rejected = rows_failed > 0 && policy == ValidationPolicy::Reject;
+        if !rejected {
+            last.extend(next_last);
+        }
+        drop(last);
+
+        let summary = failures.join("; ");
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.frames_checked += 1;
+            if rows_failed > 0 {
+                stats.frames_failed += 1;
+                stats.rows_failed += rows_failed;
+                stats.frames_rejected += rejected as u64;
+                stats.last_failure = Some(summary.clone());
+            }
+        }
+
+        if rejected {
+            if let Some(dead_letter) = &self.dead_letter {
+                dead_letter.write(&df)?;
+            }
+            return Ok(Verdict::Reject(summary));
+        }
+        match policy {
+            ValidationPolicy::Tag => {
+                let mut df = df;
+                df.with_column(Column::new(VALIDATION_TAG_COLUMN.into(), failing))?;
+                Ok(Verdict::Pass(df))
+            },
+            _ => Ok(Verdict::Pass(df)),
+        }
+    }
+}
+
+/// Rows that go backwards relative to the previous non-null value, and the
+/// last non-null value of the frame
+fn monotonic(column: &Column, previous: Option<Last>, strict: bool) -> Result<(Vec<bool>, Option<Last>)> {
+    fn scan<T: PartialOrd + Copy>(
+        values: impl Iterator<Item = Option<T>>,
+        mut previous: Option<T>,
+        strict: bool,
+    ) -> (Vec<bool>, Option<T>) {
+        let rows = values
+            .map(|value| {
+                let Some(value) = value else {
+                    return false;
+                };
+                let failed = previous.is_some_and(|p| if strict { value <= p } else { value < p });
+                if !failed {
+                    previous = Some(value);
+                }
+                failed
+            })
+            .collect();
+        (rows, previous)
+    }
+
+    if column.dtype().is_float() {
+        let values = column.cast(&DataType::Float64)?;
+        let previous = match previous {
+            Some(Last::Float(v)) => Some(v),
+            Some(Last::Int(v)) => Some(v as f64),
+            None => None,
+        };
+        let (rows, tail) = scan(values.f64()?.into_iter(), previous, strict);
+        Ok((rows, tail.map(Last::Float)))
+    } else {
+        let values = column.to_physical_repr().cast(&DataType::Int64)?;
+        let previous = match previous {
+            Some(Last::Int(v)) => Some(v),
+            Some(Last::Float(v)) => Some(v as i64),
+            None => None,
+        };
+        let (rows, tail) = scan(values.i64()?.into_iter(), previous, strict);
+        Ok((rows, tail.map(Last::Int)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    #[test]
+    fn test_validators_reject_tag_and_dead_letter() -> Result<()> {
+        let dead_writer = SharedDataFrame::create_writer(test_config("validators_dlq", 8))?;
+        let dead_reader = SharedDataFrame::create_reader(test_config("validators_dlq", 8))?;
+        let rejecting = Validators::new(ValidationPolicy::Reject)
+            .with_check(Check::monotonic("ts"))
+            .with_check(Check::range("px", Some(0.0), None))
+            .with_check(Check::one_of("side", ["B", "S"]))
+            .with_dead_letter(dead_writer);
+        let writer = SharedDataFrame::create_writer(test_config("validators_ticks", 8).with_validators(rejecting))?;
+        let reader = SharedDataFrame::create_reader(test_config("validators_ticks", 8))?;
+
+        let good = df! { "ts" => [1i64, 2], "px" => [1.0, 2.0], "side" => ["B", "S"] }?;
+        let stale = df! { "ts" => [1i64], "px" => [1.0], "side" => ["B"] }?;
+        writer.write(&good)?;
+        let error = writer.write(&stale).unwrap_err().to_string();
+        assert!(error.contains("'ts' is non-decreasing failed on 1 row(s)"), "{}", error);
+        assert!(writer.write(&df! { "ts" => [3i64], "px" => [-1.0], "side" => ["X"] }?).is_err());
+
+        assert!(reader.read(Some(100))?.unwrap().equals(&good));
+        assert!(!reader.has_data());
+        assert!(dead_reader.read(Some(100))?.unwrap().equals(&stale));
+        let stats = writer.validation_stats().unwrap();
+        assert_eq!((stats.frames_checked, stats.frames_rejected, stats.rows_failed), (3, 2, 2));
+
+        // Tagging on the reader side
+        let tagging = Validators::new(ValidationPolicy::Tag).with_check(Check::range("px", None, Some(10.0)));
+        let writer = SharedDataFrame::create_writer(test_config("validators_tagged", 8))?;
+        let reader = SharedDataFrame::create_reader(test_config("validators_tagged", 8).with_validators(tagging))?;
+        writer.write(&df! { "px" => [5.0, 50.0] }?)?;
+        let tagged = reader.read(Some(100))?.unwrap();
+        assert_eq!(tagged.column(VALIDATION_TAG_COLUMN)?.bool()?.into_iter().collect::<Vec<_>>(), vec![Some(false), Some(true)]);
+        Ok(())
+    }
+}
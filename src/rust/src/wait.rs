@@ -0,0 +1,386 @@
+//! How a reader sleeps until the next frame is published
+
+use std::sync::atomic::AtomicU32;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::backend::{RawArena, STATUS_TIMEOUT};
+use crate::Clock;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Mechanism a reader blocks on while waiting for a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WaitStrategy {
+    /// The backend's own wait: POSIX semaphores, or a condvar in-process
+    #[default]
+    Blocking,
+    /// Experimental: sleep in an io_uring futex wait on the segment's publish word,
+    /// saving the semaphore syscalls under extreme message rates. Needs Linux 6.7+.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    IoUring,
+    /// Spin briefly, then yield, then sleep in the backend's own wait, and stop
+    /// spinning altogether outside market hours
+    Adaptive(AdaptiveWait),
+}
+
+/// Spin-then-sleep waiting for latency-sensitive readers that must not burn a
+/// core while the market is closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveWait {
+    spin: Duration,
+    yields: u32,
+    hours: Option<MarketHours>,
+    pause_when_closed: bool,
+}
+
+impl AdaptiveWait {
+    /// Busy-poll for up to `spin` before sleeping
+    pub fn new(spin: Duration) -> Self {
+        Self {
+            spin,
+            yields: 0,
+            hours: None,
+            pause_when_closed: false,
+        }
+    }
+
+    /// Yield the CPU this many times once spinning is over, before sleeping
+    pub fn with_yields(mut self, yields: u32) -> Self {
+        self.yields = yields;
+        self
+    }
+
+    /// Only spin while `hours` are open; outside them the reader goes straight to sleep
+    pub fn with_market_hours(mut self, hours: MarketHours) -> Self {
+        self.hours = Some(hours);
+        self
+    }
+
+    /// Outside market hours, sleep until the next open (or the read timeout)
+    /// without waking for frames; they stay in the ring until then
+    pub fn with_pause_when_closed(mut self, pause: bool) -> Self {
+        self.pause_when_closed = pause;
+        self
+    }
+
+    /// Run the backend wait `op` for up to `timeout_ms`, spinning on the cheap
+    /// `has_data` check first while the market is open. The deadline and market
+    /// hours follow `time`; the spin budget is real CPU time. Returns a `STATUS_*` code.
+    pub(crate) fn wait_until(&self, raw: &dyn RawArena, time: &dyn Clock, timeout_ms: i32,
+                             mut op: impl FnMut(i32) -> i32) -> i32 {
+        let start = Instant::now();
+        let deadline = (timeout_ms >= 0).then(|| time.instant() + Duration::from_millis(timeout_ms as u64));
+        let remaining_ms = |deadline: Option<Instant>| {
+            deadline.map_or(-1, |deadline| deadline.saturating_duration_since(time.instant()).as_millis() as i32)
+        };
+
+        if let Some(hours) = self.hours.filter(|hours| !hours.is_open(time.now())) {
+            if self.pause_when_closed {
+                let pause = hours.until_open(time.now());
+                match deadline {
+                    Some(deadline) if time.instant() + pause >= deadline => {
+                        time.sleep(deadline.saturating_duration_since(time.instant()));
+                        return op(0);
+                    },
+                    _ => time.sleep(pause),
+                }
+            }
+            return op(remaining_ms(deadline));
+        }
+
+        let mut yields = 0;
+        loop {
+            if raw.has_data() {
+                let status = op(0);
+                if status != STATUS_TIMEOUT {
+                    return status;
+                }
+            }
+            if deadline.is_some_and(|deadline| time.instant() >= deadline) {
+                return STATUS_TIMEOUT;
+            }
+            if start.elapsed() < self.spin {
+                std::hint::spin_loop();
+            } else if yields < self.yields {
+                yields += 1;
+                thread::yield_now();
+            } else {
+                return op(remaining_ms(deadline));
+            }
+        }
+    }
+}
+
+/// A daily trading session in exchange-local time. A session whose close is
+/// before its open runs past midnight (night sessions) and counts for the day
+/// it opened on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketHours {
+    open: Duration,
+    close: Duration,
+    utc_offset_secs: i32,
+    weekdays: u8,  // bit 0 = Monday
+}
+
+impl MarketHours {
+    /// Open every day between these offsets from local midnight, UTC by default
+    pub fn new(open: Duration, close: Duration) -> Self {
+        Self {
+            open,
+            close,
+            utc_offset_secs: 0,
+            weekdays: 0x7f,
+        }
+    }
+
+    /// Exchange-local time as seconds east of UTC, e.g. `8 * 3600` for Shanghai
+    pub fn with_utc_offset(mut self, secs: i32) -> Self {
+        self.utc_offset_secs = secs;
+        self
+    }
+
+    /// Only sessions opening Monday through Friday
+    pub fn weekdays_only(mut self) -> Self {
+        self.weekdays = 0x1f;
+        self
+    }
+
+    pub fn is_open(&self, now: SystemTime) -> bool {
+        let (day, time) = self.local(now);
+        let (open, close) = (self.open.as_secs(), self.close.as_secs());
+        if open <= close {
+            return self.trades_on(day) && (open..close).contains(&time);
+        }
+        (time >= open && self.trades_on(day)) || (time < close && day > 0 && self.trades_on(day - 1))
+    }
+
+    /// How long until the next session opens; zero while one is open
+    pub fn until_open(&self, now: SystemTime) -> Duration {
+        if self.is_open(now) || self.weekdays == 0 {
+            return Duration::ZERO;
+        }
+        let (day, time) = self.local(now);
+        let open = self.open.as_secs();
+        let next = (day..day + 8)
+            .find(|&candidate| self.trades_on(candidate) && (candidate > day || time < open))
+            .unwrap_or(day);
+        Duration::from_secs((next - day) * DAY_SECS + open) - Duration::from_secs(time)
+    }
+
+    /// Local day number since the epoch and seconds into that day
+    fn local(&self, now: SystemTime) -> (u64, u64) {
+        let secs = now.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        let local = secs.saturating_add_signed(self.utc_offset_secs as i64);
+        (local / DAY_SECS, local % DAY_SECS)
+    }
+
+    fn trades_on(&self, day: u64) -> bool {
+        // 1970-01-01 was a Thursday
+        self.weekdays & (1 << ((day + 3) % 7)) != 0
+    }
+}
+
+/// Wake every thread (in any process) sleeping on `word`
+#[cfg(target_os = "linux")]
+pub(crate) fn futex_wake(word: &AtomicU32) {
+    // Shared futex, matching the C++ core and the io_uring waits below
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            libc::FUTEX_WAKE,
+            i32::MAX,
+            std::ptr::null::<libc::timespec>(),
+            std::ptr::null::<u32>(),
+            0,
+        )
+    };
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn futex_wake(_word: &AtomicU32) {}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub(crate) use uring::UringWaiter;
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring {
+    use std::sync::atomic::Ordering;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    use io_uring::{opcode, squeue, types, IoUring, Probe};
+
+    use crate::backend::{RawArena, WakeWord, STATUS_TIMEOUT};
+    use crate::{QADataSwapError, Result};
+
+    const FUTEX2_SIZE_U32: u32 = 0x02;
+    const FUTEX_BITSET_MATCH_ANY: u64 = u32::MAX as u64;
+
+    /// A small ring per reader for futex waits on the publish word
+    pub(crate) struct UringWaiter {
+        ring: Mutex<IoUring>,
+    }
+
+    impl UringWaiter {
+        pub(crate) fn new() -> Result<Self> {
+            let ring = IoUring::new(4)?;
+            let mut probe = Probe::new();
+            ring.submitter().register_probe(&mut probe)?;
+            if !probe.is_supported(opcode::FutexWait::CODE) {
+                return Err(QADataSwapError::SharedMemory(
+                    "WaitStrategy::IoUring needs io_uring futex waits (Linux 6.7+)".to_string(),
+                ));
+            }
+            Ok(Self { ring: Mutex::new(ring) })
+        }
+
+        /// Retry the non-blocking `attempt` until it stops reporting a timeout,
+        /// sleeping on the publish word in between. Returns a `STATUS_*` code.
+        pub(crate) fn wait_until(&self, raw: &dyn RawArena, timeout_ms: i32, mut attempt: impl FnMut() -> i32) -> i32 {
+            let Some(word) = raw.wake_word() else {
+                return attempt();
+            };
+            let deadline = (timeout_ms >= 0).then(|| Instant::now() + Duration::from_millis(timeout_ms as u64));
+
+            loop {
+                // Read the word first: a publish after this makes the wait return at once
+                let seen = unsafe { &*word.futex }.load(Ordering::SeqCst);
+                let status = attempt();
+                if status != STATUS_TIMEOUT {
+                    return status;
+                }
+
+                let remaining = match deadline {
+                    Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                        Some(remaining) if !remaining.is_zero() => Some(remaining),
+                        _ => return STATUS_TIMEOUT,
+                    },
+                    None => None,
+                };
+                if self.sleep(word, seen, remaining).is_err() {
+                    // Ring unusable: degrade to the backend's own wait
+                    let ms = remaining.map_or(-1, |r| r.as_millis() as i32);
+                    if raw.wait_for_data(ms) != 0 {
+                        return STATUS_TIMEOUT;
+                    }
+                }
+            }
+        }
+
+        fn sleep(&self, word: WakeWord, seen: u32, timeout: Option<Duration>) -> std::io::Result<()> {
+            let waiters = unsafe { &*word.waiters };
+            waiters.fetch_add(1, Ordering::SeqCst);
+            let result = self.submit(word, seen, timeout);
+            waiters.fetch_sub(1, Ordering::SeqCst);
+            result
+        }
+
+        fn submit(&self, word: WakeWord, seen: u32, timeout: Option<Duration>) -> std::io::Result<()> {
+            let mut ring = self.ring.lock().unwrap();
+            let futex = unsafe { &*word.futex }.as_ptr() as *const u32;
+            let wait = opcode::FutexWait::new(futex, seen as u64, FUTEX_BITSET_MATCH_ANY, FUTEX2_SIZE_U32).build();
+            let timespec = timeout.map(|t| types::Timespec::new().sec(t.as_secs()).nsec(t.subsec_nanos()));
+
+            // The wait and its linked timeout each post a completion
+            let expected = match &timespec {
+                Some(timespec) => unsafe {
+                    let mut sq = ring.submission();
+                    sq.push(&wait.flags(squeue::Flags::IO_LINK)).map_err(std::io::Error::other)?;
+                    sq.push(&opcode::LinkTimeout::new(timespec).build()).map_err(std::io::Error::other)?;
+                    2
+                },
+                None => unsafe {
+                    ring.submission().push(&wait).map_err(std::io::Error::other)?;
+                    1
+                },
+            };
+            ring.submit_and_wait(expected)?;
+            ring.completion().for_each(drop);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use polars::prelude::*;
+
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{MockClock, QADataSwapError, SharedDataFrame};
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    #[test]
+    fn test_io_uring_wakeups() -> crate::Result<()> {
+        let config = test_config("wait_io_uring", 3);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = match SharedDataFrame::create_reader(config.with_wait_strategy(WaitStrategy::IoUring)) {
+            Err(QADataSwapError::Io(_)) => return Ok(()), // io_uring disabled on this host
+            reader => reader?,
+        };
+
+        assert!(matches!(reader.read(Some(20)), Err(QADataSwapError::Timeout)));
+
+        let df = df! { "id" => [1i64, 2, 3] }?;
+        let delayed = df.clone();
+        let publisher = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            writer.write(&delayed)
+        });
+        assert!(reader.read(Some(2000))?.unwrap().equals(&df));
+        publisher.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_adaptive_wait_follows_market_hours() -> crate::Result<()> {
+        // Shanghai day session, 09:30-15:00 local on weekdays
+        let hours = MarketHours::new(Duration::from_secs(9 * 3600 + 1800), Duration::from_secs(15 * 3600))
+            .with_utc_offset(8 * 3600)
+            .weekdays_only();
+        let at = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+        // 2024-05-03 is a Friday: 10:00 local is 02:00 UTC
+        let friday_10am = 1_714_701_600;
+        assert!(hours.is_open(at(friday_10am)));
+        assert!(!hours.is_open(at(friday_10am + 6 * 3600)));
+        assert_eq!(hours.until_open(at(friday_10am)), Duration::ZERO);
+        // From Friday 16:00 the next open is Monday 09:30
+        assert_eq!(hours.until_open(at(friday_10am + 6 * 3600)), Duration::from_secs(2 * 86400 + 17 * 3600 + 1800));
+
+        let night = MarketHours::new(Duration::from_secs(21 * 3600), Duration::from_secs(2 * 3600 + 1800));
+        assert!(night.is_open(at(23 * 3600)) && night.is_open(at(86400 + 3600)));
+        assert!(!night.is_open(at(86400 + 3 * 3600)));
+
+        let config = test_config("wait_adaptive", 3);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let strategy = AdaptiveWait::new(Duration::from_micros(200)).with_yields(10);
+        let reader = SharedDataFrame::create_reader(config.clone().with_wait_strategy(WaitStrategy::Adaptive(strategy)))?;
+
+        assert!(matches!(reader.read(Some(20)), Err(QADataSwapError::Timeout)));
+
+        // Market hours and the pause follow the reader's clock: closed until
+        // Monday, it sleeps out the whole timeout on the mock instead
+        let clock = Arc::new(MockClock::new(at(friday_10am + 6 * 3600)));
+        let paused = strategy.with_market_hours(hours).with_pause_when_closed(true);
+        let closed = SharedDataFrame::create_reader(
+            config.with_wait_strategy(WaitStrategy::Adaptive(paused)).with_time_source(clock.clone()),
+        )?;
+        assert!(matches!(closed.read(Some(5000)), Err(QADataSwapError::Timeout)));
+        assert_eq!(clock.elapsed(), Duration::from_secs(5));
+        let df = df! { "id" => [1i64, 2, 3] }?;
+        let delayed = df.clone();
+        let publisher = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            writer.write(&delayed)
+        });
+        assert!(reader.read(Some(2000))?.unwrap().equals(&df));
+        publisher.join().unwrap()?;
+        Ok(())
+    }
+}
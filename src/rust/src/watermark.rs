@@ -0,0 +1,160 @@
+//! Watermarks: callbacks fired when ring occupancy or reader lag crosses a
+//! threshold, so a producer can shed load or alert before writes block
+//!
+//! A writer checks its watermarks after every frame it publishes. Each one
+//! tells its hook once when the level reaches the threshold and once when a
+//! later check finds it back below; a hook runs on the writing thread, so it
+//! should hand anything slow to another thread.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::backend::{HeaderDump, RawArena};
+use crate::MemoryReport;
+
+/// Told when a watermark's level crosses its threshold
+pub trait WatermarkHook: Send + Sync {
+    /// The level reached the threshold
+    fn crossed(&self, arena: &str, level: f64);
+
+    /// The level fell back below the threshold
+    fn cleared(&self, _arena: &str, _level: f64) {}
+}
+
+impl<F: Fn(&str, f64) + Send + Sync> WatermarkHook for F {
+    fn crossed(&self, arena: &str, level: f64) {
+        self(arena, level)
+    }
+}
+
+/// What a watermark measures, and its threshold
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatermarkLevel {
+    /// Fraction of ring slots holding unconsumed frames, 0.0 to 1.0
+    Occupancy(f64),
+    /// Frames published that the readers' shared cursor has not reached
+    ReaderLag(u64),
+}
+
+/// A threshold and the hook to tell, see `SharedMemoryConfig::on_occupancy`
+#[derive(Clone)]
+pub struct Watermark {
+    pub level: WatermarkLevel,
+    pub hook: Arc<dyn WatermarkHook>,
+}
+
+impl Watermark {
+    pub fn new(level: WatermarkLevel, hook: impl WatermarkHook + 'static) -> Self {
+        Self {
+            level,
+            hook: Arc::new(hook),
+        }
+    }
+}
+
+impl fmt::Debug for Watermark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watermark").field("level", &self.level).finish_non_exhaustive()
+    }
+}
+
+/// Writer side: which watermarks are currently crossed
+#[derive(Default)]
+pub(crate) struct WatermarkState {
+    crossed: Mutex<Vec<bool>>,
+}
+
+impl WatermarkState {
+    /// Measure the ring and tell the hooks of watermarks that changed side
+    pub(crate) fn check(&self, raw: &dyn RawArena, arena: &str, watermarks: &[Watermark]) {
+        let mut report = None;
+        let mut header = None;
+        let mut crossed = self.crossed.lock().unwrap();
+        crossed.resize(watermarks.len(), false);
+        for (watermark, crossed) in watermarks.iter().zip(crossed.iter_mut()) {
+            let (level, above) = match watermark.level {
+                WatermarkLevel::Occupancy(threshold) => {
+                    let Some(report) = report.get_or_insert_with(|| memory_report(raw)) else {
+                        continue;
+                    };
+                    let occupancy = report.used_buffers as f64 / report.buffer_count.max(1) as f64;
+                    (occupancy, occupancy >= threshold)
+                },
+                WatermarkLevel::ReaderLag(threshold) => {
+                    let Some(header) = header.get_or_insert_with(|| dump_header(raw)) else {
+                        continue;
+                    };
+                    let lag = header.publish_sequence.saturating_sub(header.read_sequence);
+                    (lag as f64, lag >= threshold)
+                },
+            };
+            match (above, *crossed) {
+                (true, false) => watermark.hook.crossed(arena, level),
+                (false, true) => watermark.hook.cleared(arena, level),
+                _ => continue,
+            }
+            *crossed = above;
+        }
+    }
+}
+
+fn memory_report(raw: &dyn RawArena) -> Option<MemoryReport> {
+    let mut report = MemoryReport::default();
+    (raw.memory_report(&mut report) == 0).then_some(report)
+}
+
+fn dump_header(raw: &dyn RawArena) -> Option<HeaderDump> {
+    let mut header = HeaderDump::default();
+    (raw.dump_header(&mut header) == 0).then_some(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::{Result, SharedDataFrame};
+    use polars::df;
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<(&'static str, f64)>>>);
+
+    impl WatermarkHook for Recorder {
+        fn crossed(&self, _arena: &str, level: f64) {
+            self.0.lock().unwrap().push(("crossed", level));
+        }
+
+        fn cleared(&self, _arena: &str, level: f64) {
+            self.0.lock().unwrap().push(("cleared", level));
+        }
+    }
+
+    #[test]
+    fn test_watermarks_fire_on_crossing_and_clearing() -> Result<()> {
+        let config = test_config("watermarked", 4);
+        let occupancy = Recorder::default();
+        let lag = Arc::new(Mutex::new(Vec::new()));
+        let lagged = lag.clone();
+        let writer = SharedDataFrame::create_writer(
+            config
+                .clone()
+                .on_occupancy(0.75, occupancy.clone())
+                .on_reader_lag(2, move |arena: &str, level| lagged.lock().unwrap().push((arena.to_string(), level))),
+        )?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        let df = df! { "px" => [1.0] }?;
+        for _ in 0..3 {
+            writer.write(&df)?;
+        }
+        assert_eq!(*occupancy.0.lock().unwrap(), [("crossed", 0.75)]);
+        assert_eq!(*lag.lock().unwrap(), [("watermarked".to_string(), 2.0)]);
+
+        for _ in 0..3 {
+            reader.read(Some(100))?;
+        }
+        writer.write(&df)?;
+        assert_eq!(occupancy.0.lock().unwrap()[1], ("cleared", 0.25));
+        assert_eq!(lag.lock().unwrap().len(), 1);
+        Ok(())
+    }
+}
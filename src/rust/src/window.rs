@@ -0,0 +1,268 @@
+//! Tumbling and sliding window aggregation over streamed chunks
+
+use std::time::Duration;
+
+use polars::prelude::*;
+
+use crate::{QADataSwapError, Result, SharedDataStream};
+
+const WINDOW_START: &str = "window_start";
+
+/// Event-time window aggregation driven by a time column in the chunks.
+///
+/// Windows are aligned to multiples of `every` since the Unix epoch and are
+/// emitted once a later row shows they can receive no more data. Output holds
+/// a `window_start` datetime column, the group keys and the aggregations.
+pub struct WindowAggregator {
+    every_us: i64,
+    period_us: i64,
+    time_column: String,
+    keys: Vec<Expr>,
+    aggs: Vec<Expr>,
+    buffer: Option<DataFrame>,
+    emitted_until: i64,
+}
+
+impl WindowAggregator {
+    /// Tumbling windows of length `every` over the `timestamp` column
+    pub fn new(every: Duration, aggs: Vec<Expr>) -> Self {
+        let every_us = (every.as_micros() as i64).max(1);
+        Self {
+            every_us,
+            period_us: every_us,
+            time_column: "timestamp".to_string(),
+            keys: Vec::new(),
+            aggs,
+            buffer: None,
+            emitted_until: i64::MIN,
+        }
+    }
+
+    /// Column holding event time: a Datetime, or an integer in microseconds
+    pub fn on(mut self, column: impl Into<String>) -> Self {
+        self.time_column = column.into();
+        self
+    }
+
+    /// Make the windows slide: each spans `period` (rounded up to a multiple of
+    /// `every`) and a new one starts every `every`
+    pub fn period(mut self, period: Duration) -> Self {
+        let period_us = period.as_micros() as i64;
+        let steps = ((period_us + self.every_us - 1) / self.every_us).max(1);
+        self.period_us = steps * self.every_us;
+        self
+    }
+
+    /// Aggregate per window and per distinct value of these key expressions
+    pub fn by(mut self, keys: Vec<Expr>) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Feed a chunk; returns the windows it completed, if any
+    pub fn push(&mut self, chunk: &DataFrame) -> Result<Option<DataFrame>> {
+        let buffer = match self.buffer.take() {
+            Some(mut buffer) => {
+                buffer.vstack_mut(chunk).map_err(QADataSwapError::Polars)?;
+                buffer
+            },
+            None => chunk.clone(),
+        };
+
+        let times = self.event_times(&buffer)?;
+        let Some(&max_time) = times.iter().max() else {
+            self.buffer = Some(buffer);
+            return Ok(None);
+        };
+
+        // Windows ending at or before the current bucket boundary are complete
+        let cutoff = max_time.div_euclid(self.every_us) * self.every_us;
+        let result = self.aggregate(&buffer, &times, cutoff)?;
+
+        let keep_from = cutoff + self.every_us - self.period_us;
+        let mask: BooleanChunked = times.iter().map(|t| *t >= keep_from).collect();
+        self.buffer = Some(buffer.filter(&mask).map_err(QADataSwapError::Polars)?);
+        self.emitted_until = self.emitted_until.max(cutoff);
+
+        Ok(result)
+    }
+
+    /// Emit every window still holding buffered rows, complete or not
+    pub fn flush(&mut self) -> Result<Option<DataFrame>> {
+        let Some(buffer) = self.buffer.take() else {
+            return Ok(None);
+        };
+        let times = self.event_times(&buffer)?;
+        let result = self.aggregate(&buffer, &times, i64::MAX)?;
+        self.emitted_until = i64::MAX;
+        Ok(result)
+    }
+
+    fn event_times(&self, df: &DataFrame) -> Result<Vec<i64>> {
+        let column = df.column(&self.time_column).map_err(QADataSwapError::Polars)?;
+        let column = match column.dtype() {
+            DataType::Datetime(_, _) => column
+                .cast(&DataType::Datetime(TimeUnit::Microseconds, None))
+                .map_err(QADataSwapError::Polars)?,
+            _ => column.clone(),
+        };
+        let micros = column.cast(&DataType::Int64).map_err(QADataSwapError::Polars)?;
+
+        micros
+            .i64()
+            .map_err(QADataSwapError::Polars)?
+            .into_iter()
+            .map(|t| t.ok_or_else(|| QADataSwapError::SharedMemory(format!(
+                "Null event time in column '{}'", self.time_column
+            ))))
+            .collect()
+    }
+
+    /// Aggregate the windows ending in `(emitted_until, cutoff]`
+    fn aggregate(&self, df: &DataFrame, times: &[i64], cutoff: i64) -> Result<Option<DataFrame>> {
+        let mut rows: Vec<IdxSize> = Vec::new();
+        let mut starts: Vec<i64> = Vec::new();
+
+        for (row, t) in times.iter().enumerate() {
+            let first_start = t.div_euclid(self.every_us) * self.every_us;
+            let mut start = first_start;
+            while start > first_start - self.period_us {
+                let end = start.saturating_add(self.period_us);
+                if end > self.emitted_until && end <= cutoff {
+                    rows.push(row as IdxSize);
+                    starts.push(start);
+                }
+                start -= self.every_us;
+            }
+        }
+
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let idx = IdxCa::from_vec("idx".into(), rows);
+        let mut expanded = df.take(&idx).map_err(QADataSwapError::Polars)?;
+        let window_start = Series::new(WINDOW_START.into(), starts)
+            .cast(&DataType::Datetime(TimeUnit::Microseconds, None))
+            .map_err(QADataSwapError::Polars)?;
+        expanded.with_column(window_start).map_err(QADataSwapError::Polars)?;
+
+        let mut keys = vec![col(WINDOW_START)];
+        keys.extend(self.keys.iter().cloned());
+
+        let out = expanded
+            .lazy()
+            .group_by_stable(keys)
+            .agg(self.aggs.clone())
+            .sort([WINDOW_START], SortMultipleOptions::default().with_maintain_order(true))
+            .collect()
+            .map_err(QADataSwapError::Polars)?;
+        Ok(Some(out))
+    }
+}
+
+/// Iterator returned by `SharedDataStream::window`, yielding one DataFrame per
+/// batch of completed windows and flushing the rest when the stream ends
+pub struct WindowedStream<'a> {
+    stream: &'a SharedDataStream,
+    aggregator: WindowAggregator,
+    timeout_ms: Option<i32>,
+    finished: bool,
+}
+
+impl<'a> WindowedStream<'a> {
+    pub(crate) fn new(stream: &'a SharedDataStream, aggregator: WindowAggregator) -> Self {
+        Self {
+            stream,
+            aggregator,
+            timeout_ms: None,
+            finished: false,
+        }
+    }
+
+    /// See `WindowAggregator::on`
+    pub fn on(mut self, column: impl Into<String>) -> Self {
+        self.aggregator = self.aggregator.on(column);
+        self
+    }
+
+    /// See `WindowAggregator::period`
+    pub fn period(mut self, period: Duration) -> Self {
+        self.aggregator = self.aggregator.period(period);
+        self
+    }
+
+    /// See `WindowAggregator::by`
+    pub fn by(mut self, keys: Vec<Expr>) -> Self {
+        self.aggregator = self.aggregator.by(keys);
+        self
+    }
+
+    /// Treat a read timeout as the end of the stream instead of an error
+    pub fn with_idle_timeout_ms(mut self, timeout_ms: i32) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
+}
+
+impl<'a> Iterator for WindowedStream<'a> {
+    type Item = Result<DataFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let chunk = match self.stream.read_chunk(self.timeout_ms) {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => {
+                    self.finished = true;
+                    break;
+                },
+                Err(QADataSwapError::Timeout) if self.timeout_ms.is_some() => {
+                    self.finished = true;
+                    break;
+                },
+                Err(e) => return Some(Err(e)),
+            };
+
+            match self.aggregator.push(&chunk) {
+                Ok(Some(df)) => return Some(Ok(df)),
+                Ok(None) => {},
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        self.aggregator.flush().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tumbling_ohlcv_bars() -> Result<()> {
+        let ticks = df! {
+            "timestamp" => [0i64, 400_000, 900_000, 1_100_000, 1_500_000, 2_200_000],
+            "price" => [10.0, 12.0, 11.0, 13.0, 9.0, 14.0],
+            "volume" => [1i64, 2, 3, 4, 5, 6],
+        }.map_err(QADataSwapError::Polars)?;
+
+        let mut bars = WindowAggregator::new(Duration::from_secs(1), vec![
+            col("price").first().alias("open"),
+            col("price").max().alias("high"),
+            col("price").min().alias("low"),
+            col("price").last().alias("close"),
+            col("volume").sum().alias("volume"),
+        ]);
+
+        // The tick at 2.2s closes the [0s, 1s) and [1s, 2s) bars
+        let closed = bars.push(&ticks)?.unwrap();
+        assert_eq!(closed.height(), 2);
+        let high: Vec<Option<f64>> = closed.column("high").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(high, vec![Some(12.0), Some(13.0)]);
+
+        let rest = bars.flush()?.unwrap();
+        assert_eq!(rest.height(), 1);
+        assert!(bars.flush()?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+//! Trained zstd dictionaries for channels of many small, similar frames
+//!
+//! `train_dictionary` builds a dictionary from sample frames and publishes it
+//! to the companion arena `<name>#zdict`, with its id in the control block's
+//! metadata entry `ZSTD_DICTIONARY_META_KEY`; a dictionary is too large for
+//! the metadata table itself. While the writer compresses with zstd, each
+//! data frame's payload is then compressed as a whole with the dictionary
+//! instead of per IPC buffer, and flagged `FLAG_ZSTD_DICTIONARY`:
+//!
+//! ```text
+//! u32 dictionary_id | u32 raw_len | zstd frame
+//! ```
+//!
+//! Readers fetch a dictionary from the companion arena the first time a frame
+//! needs it. The companion keeps the last `RETAINED_DICTIONARIES`, so frames
+//! still in the ring when a new dictionary is trained stay readable.
+
+use std::sync::Mutex;
+
+use polars::prelude::*;
+use zstd::bulk::{Compressor, Decompressor};
+
+use crate::priority::LANE_SEPARATOR;
+use crate::{OpenMode, QADataSwapError, Result, SeekFrom, SharedMemoryArena, SharedMemoryConfig};
+
+/// How a data frame's payload is compressed
+#[derive(Clone, Copy)]
+pub(crate) enum PayloadCompression<'a> {
+    /// Per IPC buffer, or not at all
+    Ipc(Option<IpcCompression>),
+    /// Whole payload, with a trained dictionary
+    Dictionary(&'a DictionaryWriter),
+}
+
+/// Metadata key holding the id of the writer's current dictionary
+pub const ZSTD_DICTIONARY_META_KEY: &str = "__zdict";
+
+/// Largest dictionary `train_dictionary` builds
+pub const MAX_DICTIONARY_SIZE: usize = 64 * 1024;
+
+const RETAINED_DICTIONARIES: usize = 4;
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Name of the arena holding channel `name`'s dictionaries
+fn companion_name(name: &str) -> String {
+    format!("{}{}zdict", name, LANE_SEPARATOR)
+}
+
+fn companion_config(config: &SharedMemoryConfig) -> SharedMemoryConfig {
+    // Consumer groups without members: the writer never waits for readers
+    SharedMemoryConfig {
+        transport: config.transport,
+        namespace: config.namespace.clone(),
+        resource_limits: config.resource_limits,
+        wait_strategy: config.wait_strategy,
+        ..SharedMemoryConfig::new(companion_name(&config.name))
+    }
+    .with_size_mb(1)
+    .with_buffer_count(RETAINED_DICTIONARIES)
+    .with_consumer_groups(true)
+    .with_open_mode(OpenMode::OpenOrAttach)
+}
+
+/// Train a dictionary on the uncompressed IPC encoding of `samples`
+pub(crate) fn train(samples: &[DataFrame]) -> Result<Vec<u8>> {
+    let encoded = samples
+        .iter()
+        .map(|df| {
+            let mut file = Vec::new();
+            IpcWriter::new(&mut file).finish(&mut df.clone())?;
+            Ok(file)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    zstd::dict::from_samples(&encoded, MAX_DICTIONARY_SIZE)
+        .map_err(|e| QADataSwapError::SharedMemory(format!("Failed to train a zstd dictionary: {}", e)))
+}
+
+/// Writer side: the current dictionary and where it was published
+pub(crate) struct DictionaryWriter {
+    id: u32,
+    compressor: Mutex<Compressor<'static>>,
+    companion: Box<SharedMemoryArena>,
+}
+
+impl DictionaryWriter {
+    /// Publish `dictionary` under the id after `last_id`, through the companion
+    /// `previous` already holds if there is one
+    pub(crate) fn publish(config: &SharedMemoryConfig, previous: Option<Self>, last_id: Option<u32>,
+                          dictionary: &[u8]) -> Result<Self> {
+        let id = last_id.map_or(1, |id| id.wrapping_add(1).max(1));
+        let companion = match previous {
+            Some(previous) => previous.companion,
+            None => {
+                let mut companion = SharedMemoryArena::new(companion_config(config))?;
+                companion.create_writer()?;
+                Box::new(companion)
+            },
+        };
+        let mut record = id.to_le_bytes().to_vec();
+        record.extend_from_slice(dictionary);
+        companion.write_dataframe_bytes(&record)?;
+        Ok(Self {
+            id,
+            compressor: Mutex::new(Compressor::with_dictionary(COMPRESSION_LEVEL, dictionary)?),
+            companion,
+        })
+    }
+
+    pub(crate) fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Replace `buffer[start..]` with its compressed form
+    pub(crate) fn compress_tail(&self, buffer: &mut Vec<u8>, start: usize) -> Result<()> {
+        let compressed = self.compressor.lock().unwrap().compress(&buffer[start..])?;
+        let raw_len = (buffer.len() - start) as u32;
+        buffer.truncate(start);
+        buffer.extend_from_slice(&self.id.to_le_bytes());
+        buffer.extend_from_slice(&raw_len.to_le_bytes());
+        buffer.extend_from_slice(&compressed);
+        Ok(())
+    }
+}
+
+/// Reader side: the dictionary last fetched and the companion to fetch from
+#[derive(Default)]
+pub(crate) struct DictionaryReader {
+    current: Option<(u32, Decompressor<'static>)>,
+    companion: Option<Box<SharedMemoryArena>>,
+}
+
+impl DictionaryReader {
+    pub(crate) fn decompress(&mut self, config: &SharedMemoryConfig, payload: &[u8]) -> Result<Vec<u8>> {
+        if payload.len() < 8 {
+            return Err(QADataSwapError::SharedMemory("Truncated dictionary-compressed payload".to_string()));
+        }
+        let id = u32::from_le_bytes(payload[0..4].try_into().unwrap());
+        let raw_len = u32::from_le_bytes(payload[4..8].try_into().unwrap()) as usize;
+        if self.current.as_ref().is_none_or(|(current, _)| *current != id) {
+            let dictionary = self.fetch(config, id)?;
+            self.current = Some((id, Decompressor::with_dictionary(&dictionary)?));
+        }
+        let (_, decompressor) = self.current.as_mut().unwrap();
+        Ok(decompressor.decompress(&payload[8..], raw_len)?)
+    }
+
+    /// Look through the dictionaries the companion still retains for `id`
+    fn fetch(&mut self, config: &SharedMemoryConfig, id: u32) -> Result<Vec<u8>> {
+        if self.companion.is_none() {
+            let mut companion = SharedMemoryArena::new(companion_config(config))?;
+            companion.attach_view()?;
+            self.companion = Some(Box::new(companion));
+        }
+        let companion = self.companion.as_ref().unwrap();
+        companion.seek(SeekFrom::Earliest)?;
+        loop {
+            match companion.read_dataframe_bytes(Some(0)) {
+                Ok(Some(record)) if record.len() > 4 && record[0..4] == id.to_le_bytes() => {
+                    return Ok(record[4..].to_vec());
+                },
+                Ok(Some(_)) => continue,
+                Ok(None) | Err(QADataSwapError::Timeout) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(QADataSwapError::SharedMemory(format!("zstd dictionary {} is no longer retained", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+    use crate::SharedDataFrame;
+    use polars::df;
+
+    fn tick(i: usize) -> Result<DataFrame> {
+        Ok(df! {
+            "symbol" => [format!("rb24{:02}", i % 12), format!("cu24{:02}", i % 12)],
+            "exchange" => ["SHFE", "SHFE"],
+            "px" => [3500.0 + i as f64, 78000.0 - i as f64],
+            "volume" => [i as i64 * 10, i as i64 * 20],
+        }?)
+    }
+
+    #[test]
+    fn test_trained_dictionary_shrinks_small_frames() -> Result<()> {
+        let config = test_config("zdict_ticks", 4).with_compression(IpcCompression::ZSTD);
+        let writer = SharedDataFrame::create_writer(config.clone())?;
+        let reader = SharedDataFrame::create_reader(config)?;
+
+        writer.write(&tick(0)?)?;
+        assert_eq!(reader.read(Some(100))?, Some(tick(0)?));
+        let samples = (0..400).map(tick).collect::<Result<Vec<_>>>()?;
+        assert_eq!(writer.train_dictionary(&samples)?, 1);
+        assert_eq!(reader.get_meta(ZSTD_DICTIONARY_META_KEY).as_deref(), Some("1"));
+
+        writer.write(&tick(1)?)?;
+        let slots = writer.debug_dump()?.slots;
+        assert!(slots[1].size * 2 < slots[0].size, "{} vs {} bytes", slots[1].size, slots[0].size);
+        assert_eq!(reader.read(Some(100))?, Some(tick(1)?));
+
+        // A second dictionary; the reader fetches it on the next frame
+        assert_eq!(writer.train_dictionary(&samples[..200])?, 2);
+        writer.write(&tick(2)?)?;
+        assert_eq!(reader.read(Some(100))?, Some(tick(2)?));
+        Ok(())
+    }
+}
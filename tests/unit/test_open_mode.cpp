@@ -0,0 +1,160 @@
+// Writer open modes: refusing, reattaching and replacing an existing segment
+#include "qadataswap_core.h"
+#include <cstdlib>
+#include <iostream>
+#include <string>
+#include <sys/mman.h>
+#include <unistd.h>
+
+using namespace qadataswap;
+
+namespace {
+
+int failures = 0;
+
+#define CHECK(cond)                                                              \
+    do {                                                                         \
+        if (!(cond)) {                                                           \
+            std::cerr << __FILE__ << ":" << __LINE__ << ": " #cond << std::endl; \
+            ++failures;                                                          \
+        }                                                                        \
+    } while (0)
+
+constexpr size_t SIZE = 1024 * 1024;
+
+std::string SegmentName(const char* test) {
+    return std::string("open_mode_") + test + "_" + std::to_string(getpid());
+}
+
+uint64_t BufferCount(const SharedMemoryArena& arena) {
+    return arena.GetMemoryReport().buffer_count;
+}
+
+void TestFailIfExists() {
+    std::string name = SegmentName("fail_if_exists");
+    SharedMemoryArena first(name, SIZE);
+    CHECK(first.CreateWriter(OpenMode::FailIfExists) == QADS_OK);
+
+    SharedMemoryArena second(name, SIZE);
+    CHECK(second.CreateWriter(OpenMode::FailIfExists) == QADS_ALREADY_EXISTS);
+
+    // Even a segment whose writer is gone is not replaced
+    first.Abandon();
+    CHECK(second.CreateWriter(OpenMode::FailIfExists) == QADS_ALREADY_EXISTS);
+    SharedMemoryArena::RemoveSegment(name);
+}
+
+void TestOpenOrAttachWithLiveWriter() {
+    std::string name = SegmentName("live_writer");
+    SharedMemoryArena writer(name, SIZE, 3);
+    CHECK(writer.CreateWriter(OpenMode::Create) == QADS_OK);
+    const uint8_t frame[] = {1, 2, 3};
+    CHECK(writer.WriteBytes(frame, sizeof(frame)) == QADS_OK);
+
+    SharedMemoryArena standby(name, SIZE, 4);
+    CHECK(standby.CreateWriter(OpenMode::OpenOrAttach) == QADS_ALREADY_EXISTS);
+    CHECK(standby.CreateWriter(OpenMode::Create) == QADS_ALREADY_EXISTS);
+
+    // Refused, the standby still holds its own geometry rather than the segment's
+    writer.Close();
+    CHECK(standby.CreateWriter(OpenMode::Create) == QADS_OK);
+    CHECK(BufferCount(standby) == 4);
+}
+
+void TestReattachKeepsTheRing() {
+    std::string name = SegmentName("reattach");
+    SharedMemoryArena crashed(name, SIZE, 3);
+    CHECK(crashed.CreateWriter(OpenMode::Create) == QADS_OK);
+    const uint8_t frame[] = {1, 2, 3};
+    CHECK(crashed.WriteBytes(frame, sizeof(frame)) == QADS_OK);
+    crashed.Abandon();
+
+    SharedMemoryArena restarted(name, SIZE, 3);
+    CHECK(restarted.CreateWriter(OpenMode::OpenOrAttach) == QADS_OK);
+    CHECK(restarted.GetMemoryReport().used_buffers == 1);
+    restarted.Close();
+}
+
+void TestReattachWithMismatchedGeometry() {
+    std::string name = SegmentName("mismatch");
+    SharedMemoryArena crashed(name, SIZE, 3);
+    CHECK(crashed.CreateWriter(OpenMode::Create) == QADS_OK);
+    crashed.Abandon();
+
+    SegmentStatus before{};
+    CHECK(SharedMemoryArena::InspectSegment(name, &before));
+
+    SharedMemoryArena restarted(name, SIZE, 4);
+    CHECK(restarted.CreateWriter(OpenMode::OpenOrAttach) == QADS_ERROR);
+
+    // The segment is left as it was, and the writer as it was configured
+    SegmentStatus after{};
+    CHECK(SharedMemoryArena::InspectSegment(name, &after));
+    CHECK(after.generation == before.generation);
+    CHECK(restarted.CreateWriter(OpenMode::Truncate) == QADS_OK);
+    CHECK(BufferCount(restarted) == 4);
+    restarted.Close();
+}
+
+void TestUnreadableSegmentIsNotReplaced() {
+    std::string name = SegmentName("unreadable");
+    int fd = shm_open(("/qads_" + name).c_str(), O_CREAT | O_EXCL | O_RDWR, 0644);
+    CHECK(fd != -1);
+    CHECK(ftruncate(fd, SIZE) == 0);
+    close(fd);
+
+    // Not a segment this build can attach to, so it is not this writer's to remove
+    SharedMemoryArena writer(name, SIZE, 3);
+    CHECK(writer.CreateWriter(OpenMode::OpenOrAttach) == QADS_ERROR);
+    fd = shm_open(("/qads_" + name).c_str(), O_RDONLY, 0);
+    CHECK(fd != -1);
+    close(fd);
+
+    // Create still replaces it, with the writer's own geometry
+    CHECK(writer.CreateWriter(OpenMode::Create) == QADS_OK);
+    CHECK(BufferCount(writer) == 3);
+    writer.Close();
+}
+
+void TestReusedWriterPidIsNotLive() {
+    std::string name = SegmentName("pid_reuse");
+    SharedMemoryArena crashed(name, SIZE, 3);
+    CHECK(crashed.CreateWriter(OpenMode::Create) == QADS_OK);
+
+    // Pretend the writer died and its pid went to this (younger) process
+    int fd = shm_open(("/qads_" + name).c_str(), O_RDWR, 0);
+    CHECK(fd != -1);
+    void* mapped = mmap(nullptr, sizeof(SharedMemoryHeader), PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    close(fd);
+    CHECK(mapped != MAP_FAILED);
+    auto header = static_cast<SharedMemoryHeader*>(mapped);
+    header->writer_start_time.fetch_sub(1);
+
+    SegmentStatus status{};
+    CHECK(SharedMemoryArena::InspectSegment(name, &status));
+    CHECK(status.writer_alive == 0);
+
+    SharedMemoryArena restarted(name, SIZE, 3);
+    CHECK(restarted.CreateWriter(OpenMode::OpenOrAttach) == QADS_OK);
+    munmap(mapped, sizeof(SharedMemoryHeader));
+    crashed.Abandon();
+    restarted.Close();
+}
+
+}  // namespace
+
+int main() {
+    TestFailIfExists();
+    TestOpenOrAttachWithLiveWriter();
+    TestReattachKeepsTheRing();
+    TestReattachWithMismatchedGeometry();
+    TestUnreadableSegmentIsNotReplaced();
+    TestReusedWriterPidIsNotLive();
+
+    if (failures) {
+        std::cerr << failures << " check(s) failed" << std::endl;
+        return EXIT_FAILURE;
+    }
+    std::cout << "open mode tests passed" << std::endl;
+    return EXIT_SUCCESS;
+}